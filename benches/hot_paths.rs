@@ -0,0 +1,144 @@
+// ⏱️ Hot Paths - Criterion benchmarks for the code imports and scans spend time in
+//
+// Problem solved:
+// - Redesigns motivated by speed (indexes, parallel scans, a new hash) had
+//   nothing to be measured against, so "faster" was a guess
+//
+// Every group runs on demo data (`generate_transactions`, fixed seed), sized
+// in months of activity (about 30 rows a month), so numbers compare across
+// machines and commits. Save a baseline before a change and compare after:
+//
+//   cargo bench --bench hot_paths -- --save-baseline before
+//   cargo bench --bench hot_paths -- --baseline before
+//
+// A single group runs with its name as the filter (`cargo bench --bench
+// hot_paths -- dedup`).
+
+use chrono::NaiveDate;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use rusqlite::Connection;
+use trust_construction::aliases::key_similarity;
+use trust_construction::{
+    cluster_spellings, generate_transactions, insert_transactions_as, lookup_mcc, setup_database,
+    BankRegistry, DataQualityEngine, DeduplicationEngine, MerchantRegistry, MerchantSpelling, Transaction,
+};
+
+/// Months of demo activity per benchmark size
+const SIZES: [u32; 3] = [3, 12, 36];
+
+fn demo(months: u32) -> Vec<Transaction> {
+    generate_transactions(42, months, NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())
+}
+
+// ============================================================================
+// STORAGE
+// ============================================================================
+
+/// Rows per second written by a batch insert into a fresh database
+fn insert_throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+    for months in SIZES {
+        let rows = demo(months);
+        group.throughput(Throughput::Elements(rows.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(rows.len()), &rows, |b, rows| {
+            b.iter_batched(
+                || {
+                    let conn = Connection::open_in_memory().unwrap();
+                    setup_database(&conn).unwrap();
+                    conn
+                },
+                |conn| insert_transactions_as(&conn, rows, "bench").unwrap(),
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+// ============================================================================
+// MATCHING
+// ============================================================================
+
+/// Terminal-numbered spellings of every demo description ("SHELL OIL 5744
+/// 100", "SHELL OIL 5744 101", ...), as alias clustering sees them
+fn spellings(per_merchant: usize) -> Vec<MerchantSpelling> {
+    let mut merchants: Vec<String> = demo(12).into_iter().map(|tx| tx.description).collect();
+    merchants.sort();
+    merchants.dedup();
+    merchants
+        .iter()
+        .flat_map(|name| {
+            (0..per_merchant).map(move |i| MerchantSpelling { name: format!("{} {}", name, 100 + i), rows: i + 1 })
+        })
+        .collect()
+}
+
+/// Edit-distance similarity and the alias clustering built on it
+fn merchant_matching(c: &mut Criterion) {
+    let mut group = c.benchmark_group("merchant_matching");
+    group.bench_function("key_similarity", |b| {
+        b.iter(|| key_similarity(black_box("amazon mktpl marketplace"), black_box("amazon.com marketplace")))
+    });
+    for per_merchant in [1, 4, 16] {
+        let spellings = spellings(per_merchant);
+        group.throughput(Throughput::Elements(spellings.len() as u64));
+        group.bench_with_input(BenchmarkId::new("cluster_spellings", spellings.len()), &spellings, |b, spellings| {
+            b.iter(|| cluster_spellings(spellings))
+        });
+    }
+    group.finish();
+}
+
+/// Duplicate scan over a whole ledger
+fn dedup_scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dedup");
+    let engine = DeduplicationEngine::new();
+    for months in SIZES {
+        let rows = demo(months);
+        group.throughput(Throughput::Elements(rows.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(rows.len()), &rows, |b, rows| {
+            b.iter(|| engine.find_duplicates(rows))
+        });
+    }
+    group.finish();
+}
+
+// ============================================================================
+// VALIDATION
+// ============================================================================
+
+/// Quality rules over an import batch, and its summary
+fn quality_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("quality_batch");
+    let engine = DataQualityEngine::new();
+    for months in SIZES {
+        let rows = demo(months);
+        group.throughput(Throughput::Elements(rows.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(rows.len()), &rows, |b, rows| {
+            b.iter(|| engine.batch_summary(&engine.validate_batch(rows)))
+        });
+    }
+    group.finish();
+}
+
+// ============================================================================
+// REGISTRIES
+// ============================================================================
+
+/// Lookups import normalization does per row: merchant and bank registries
+/// (a hit and a miss, which scans every entry) and the MCC table
+fn registry_lookups(c: &mut Criterion) {
+    let mut group = c.benchmark_group("registry");
+    let merchants = MerchantRegistry::with_defaults();
+    let banks = BankRegistry::new();
+    group.bench_function("merchant_hit", |b| {
+        b.iter(|| merchants.find_by_string(black_box("STARBUCKS STORE #1234")))
+    });
+    group.bench_function("merchant_miss", |b| b.iter(|| merchants.find_by_string(black_box("LOCAL BAKERY 77"))));
+    group.bench_function("bank_hit", |b| b.iter(|| banks.find_by_string(black_box("BofA"))));
+    group.bench_function("mcc", |b| b.iter(|| lookup_mcc(black_box(5812))));
+    group.finish();
+}
+
+criterion_group!(benches, insert_throughput, merchant_matching, dedup_scan, quality_batch, registry_lookups);
+criterion_main!(benches);
@@ -2,23 +2,69 @@
 // Badge 13: REST API with Axum
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::{Html, IntoResponse, Json},
-    routing::get,
+    async_trait,
+    body::Bytes,
+    extract::{DefaultBodyLimit, FromRequest, FromRequestParts, Multipart, Path, Query, Request, State},
+    http::{header::AUTHORIZATION, header::CONTENT_TYPE, header::RETRY_AFTER, request::Parts, HeaderMap, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Json, Response},
+    routing::{get, post},
     Router,
 };
-use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Instant;
 use tower_http::cors::CorsLayer;
 use tower_http::services::ServeDir;
-use trust_construction::{get_all_transactions, get_source_file_stats, get_transactions_by_source, Transaction, SourceFileStat};
+use trust_construction::{get_active_transactions, get_transactions_by_source, source_stats, SourceStats, Transaction};
+use trust_construction::{totals_by_bank, totals_by_type};
+use trust_construction::{
+    approve_change, authenticate, create_user, get_current_transaction, list_pending_changes,
+    list_users, reject_change, set_user_role, submit_correction, submit_redo, submit_undo,
+    submit_void, user_count, AppConfig, Role, User, WriteOutcome,
+};
+use trust_construction::{add_note, get_notes};
+use trust_construction::{import_statement_with, DeduplicationEngine, ImportContext, RuleEngine, DEFAULT_LEDGER_ID};
+use trust_construction::{setup_database, shared_registry, Correction, TransactionQuery, TrustSystem};
+use trust_construction::{ledger_transactions, merchant_profile};
+use trust_construction::{
+    merchant_history, merchants_as_of, parse_as_of, rule_history, rules_as_of, transaction_history,
+    transactions_as_of,
+};
+use trust_construction::provenance;
+use trust_construction::{is_mutating, record_api_call, RateLimiter, DEFAULT_WRITES_PER_MINUTE};
+use trust_construction::{
+    enqueue_job, get_queued_job, list_queued_jobs, open_worker_connection, requeue_interrupted_jobs,
+    run_next_job, JobSpec,
+};
+use trust_construction::{ConnectionPool, PooledConnection, DEFAULT_POOL_SIZE};
 
 /// Shared application state
 #[derive(Clone)]
 struct AppState {
-    db: Arc<Mutex<Connection>>,
+    db: ConnectionPool,
+    /// Read-only connections: GET handlers can never write
+    reader: ConnectionPool,
+    limiter: Arc<RateLimiter>,
+}
+
+impl AppState {
+    /// A pooled connection, or the 503 to answer when none frees up in time
+    #[allow(clippy::result_large_err)]
+    fn conn(&self) -> Result<PooledConnection, Response> {
+        self.db
+            .get()
+            .map_err(|e| error_response(StatusCode::SERVICE_UNAVAILABLE, format!("{:#}", e)))
+    }
+
+    /// A read-only pooled connection (same 503 when none frees up)
+    #[allow(clippy::result_large_err)]
+    fn read_conn(&self) -> Result<PooledConnection, Response> {
+        self.reader
+            .get()
+            .map_err(|e| error_response(StatusCode::SERVICE_UNAVAILABLE, format!("{:#}", e)))
+    }
 }
 
 /// API Response wrapper
@@ -40,6 +86,140 @@ impl<T> ApiResponse<T> {
     }
 }
 
+impl ApiResponse<()> {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            data: (),
+            error: Some(message.into()),
+        }
+    }
+}
+
+/// Error response with a status code
+fn error_response(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, Json(ApiResponse::error(message))).into_response()
+}
+
+// ============================================================================
+// Authentication
+// ============================================================================
+
+/// The user making the request (from `Authorization: Bearer <token>`)
+///
+/// Until the first user is created the server runs in open mode and every
+/// request acts as `anonymous` with admin rights, so single-user installs
+/// keep working unchanged.
+struct AuthUser(User);
+
+impl AuthUser {
+    /// 403 response unless the user has at least `role`
+    fn forbidden_unless(&self, role: Role) -> Option<Response> {
+        if self.0.can(role) {
+            None
+        } else {
+            Some(error_response(
+                StatusCode::FORBIDDEN,
+                format!("'{}' requires the {} role", self.0.username, role.as_str()),
+            ))
+        }
+    }
+}
+
+/// Resolve the bearer token in `headers` (anonymous admin in open mode)
+fn resolve_user(state: &AppState, headers: &HeaderMap) -> Result<User, (StatusCode, String)> {
+    let conn = state.db.get().map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, format!("{:#}", e)))?;
+
+    let users = user_count(&conn).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if users == 0 {
+        return Ok(User::anonymous());
+    }
+
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Missing bearer token".to_string()))?;
+
+    match authenticate(&conn, token) {
+        Ok(Some(user)) => Ok(user),
+        Ok(None) => Err((StatusCode::UNAUTHORIZED, "Invalid token".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        resolve_user(state, &parts.headers)
+            .map(AuthUser)
+            .map_err(|(status, message)| error_response(status, message))
+    }
+}
+
+/// A database connection from the pool, held for the rest of the handler
+struct Db(PooledConnection);
+
+#[async_trait]
+impl FromRequestParts<AppState> for Db {
+    type Rejection = Response;
+
+    async fn from_request_parts(_parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        state.conn().map(Db)
+    }
+}
+
+/// A read-only connection from the pool, for handlers that only read
+struct ReadDb(PooledConnection);
+
+#[async_trait]
+impl FromRequestParts<AppState> for ReadDb {
+    type Rejection = Response;
+
+    async fn from_request_parts(_parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        state.read_conn().map(ReadDb)
+    }
+}
+
+/// Rate-limit and audit every mutating request
+///
+/// Writes count against the caller's token; over the limit the request is
+/// answered with 429 and never reaches the handler. Every mutating call,
+/// including rejected ones, is recorded as an `api_request` event.
+async fn audit_writes(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    if !is_mutating(&method) {
+        return next.run(request).await;
+    }
+    let path = request.uri().path().to_string();
+
+    let (actor, response) = match resolve_user(&state, request.headers()) {
+        Err((status, message)) => ("unauthenticated".to_string(), error_response(status, message)),
+        Ok(user) => match state.limiter.check(&user.username, Instant::now()) {
+            Err(wait) => {
+                let mut response = error_response(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    format!("Rate limit reached for '{}', retry in {}s", user.username, wait.as_secs() + 1),
+                );
+                response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(wait.as_secs() + 1));
+                (user.username, response)
+            }
+            Ok(()) => (user.username, next.run(request).await),
+        },
+    };
+
+    let recorded = state
+        .db
+        .get()
+        .and_then(|conn| record_api_call(&conn, &actor, &method, &path, response.status().as_u16()));
+    if let Err(e) = recorded {
+        eprintln!("⚠️  Could not record API call {} {}: {:#}", method, path, e);
+    }
+    response
+}
+
 /// Stats response
 #[derive(Serialize)]
 struct StatsResponse {
@@ -61,35 +241,56 @@ struct BankStat {
 /// Transaction response (simplified for API)
 #[derive(Serialize, Deserialize)]
 struct TransactionResponse {
+    id: String,
+    version: i64,
     date: String,
     description: String,
     amount_numeric: f64,
     transaction_type: String,
     category: String,
     merchant: String,
+    /// Id in the server's merchant registry (filter with `merchant_id=`)
+    merchant_id: Option<String>,
     bank: String,
     source_file: String,
 }
 
+/// One page of GET /api/transactions
+#[derive(Serialize)]
+struct TransactionPageResponse {
+    transactions: Vec<TransactionResponse>,
+    /// Matching transactions across all pages
+    total: usize,
+    /// Pass back as `cursor` for the next page; null on the last page
+    next_cursor: Option<String>,
+}
+
 /// Source file response
 #[derive(Serialize)]
 struct SourceFileResponse {
     source_file: String,
     bank: String,
-    transaction_count: i64,
+    transaction_count: usize,
     total_expenses: f64,
     total_income: f64,
     date_range: String,
+    by_type: BTreeMap<String, usize>,
+    average_quality: f64,
+    duplicates: usize,
+    unresolved_merchants: usize,
 }
 
 impl From<Transaction> for TransactionResponse {
     fn from(tx: Transaction) -> Self {
         Self {
+            id: tx.id,
+            version: tx.version,
             date: tx.date,
             description: tx.description,
             amount_numeric: tx.amount_numeric,
             transaction_type: tx.transaction_type,
             category: tx.category,
+            merchant_id: shared_registry().get_id(&tx.merchant),
             merchant: tx.merchant,
             bank: tx.bank,
             source_file: tx.source_file,
@@ -97,15 +298,19 @@ impl From<Transaction> for TransactionResponse {
     }
 }
 
-impl From<SourceFileStat> for SourceFileResponse {
-    fn from(stat: SourceFileStat) -> Self {
+impl From<SourceStats> for SourceFileResponse {
+    fn from(stat: SourceStats) -> Self {
         Self {
+            date_range: stat.date_range(),
             source_file: stat.source_file,
             bank: stat.bank,
             transaction_count: stat.transaction_count,
             total_expenses: stat.total_expenses,
             total_income: stat.total_income,
-            date_range: stat.date_range,
+            by_type: stat.by_type,
+            average_quality: stat.average_quality,
+            duplicates: stat.duplicates,
+            unresolved_merchants: stat.unresolved_merchants,
         }
     }
 }
@@ -119,74 +324,71 @@ async fn health_check() -> impl IntoResponse {
     Json(ApiResponse::ok("OK"))
 }
 
-/// GET /api/transactions - Get all transactions
-async fn get_transactions(State(state): State<AppState>) -> impl IntoResponse {
-    let conn = state.db.lock().unwrap();
-
-    match get_all_transactions(&conn) {
-        Ok(transactions) => {
-            let response: Vec<TransactionResponse> = transactions
-                .into_iter()
-                .map(|tx| tx.into())
-                .collect();
+/// GET /api/transactions - Active (current, non-voided) transactions, one page
+///
+/// Query parameters: `sort` (date, amount, merchant, category, bank, type;
+/// prefix `-` for descending, default `-date`), `limit` (default 100, max
+/// 1000), `cursor` (from the previous page), and the filters ledger, bank,
+/// category, merchant, merchant_id, type, source, tag, q (description text),
+/// from/to (YYYY-MM-DD) and min_amount/max_amount. `as_of` (RFC 3339 or
+/// YYYY-MM-DD) lists the versions that were current then.
+async fn get_transactions(
+    State(state): State<AppState>,
+    _user: AuthUser,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let query = match TransactionQuery::from_params(&params) {
+        Ok(query) => query,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e.to_string()),
+    };
 
+    let as_of = query.as_of;
+    let transactions = state
+        .reader
+        .run(move |conn| match as_of {
+            Some(as_of) => transactions_as_of(conn, as_of),
+            None => get_active_transactions(conn),
+        })
+        .await;
+    let transactions = match transactions {
+        Ok(transactions) => transactions,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    match query.run(&transactions) {
+        Ok(page) => {
+            let response = TransactionPageResponse {
+                transactions: page.transactions.into_iter().map(|tx| tx.into()).collect(),
+                total: page.total,
+                next_cursor: page.next_cursor,
+            };
             (StatusCode::OK, Json(ApiResponse::ok(response))).into_response()
         }
-        Err(e) => {
-            eprintln!("Error getting transactions: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::ok(Vec::<TransactionResponse>::new())),
-            )
-                .into_response()
-        }
+        Err(e) => error_response(StatusCode::BAD_REQUEST, e.to_string()),
     }
 }
 
 /// GET /api/stats - Get statistics
-async fn get_stats(State(state): State<AppState>) -> impl IntoResponse {
-    let conn = state.db.lock().unwrap();
-
-    match get_all_transactions(&conn) {
-        Ok(transactions) => {
-            let total = transactions.len();
-
-            let mut total_expenses = 0.0;
-            let mut total_income = 0.0;
-            let mut total_transfers = 0.0;
-            let mut total_credit_payments = 0.0;
-
-            let mut bank_stats: std::collections::HashMap<String, (usize, f64)> =
-                std::collections::HashMap::new();
-
-            for tx in &transactions {
-                // Update totals by type
-                match tx.transaction_type.as_str() {
-                    "GASTO" => total_expenses += tx.amount_numeric.abs(),
-                    "INGRESO" => total_income += tx.amount_numeric.abs(),
-                    "TRASPASO" => total_transfers += tx.amount_numeric.abs(),
-                    "PAGO_TARJETA" => total_credit_payments += tx.amount_numeric.abs(),
-                    _ => {}
-                }
-
-                // Update bank stats
-                let entry = bank_stats.entry(tx.bank.clone()).or_insert((0, 0.0));
-                entry.0 += 1;
-                entry.1 += tx.amount_numeric.abs();
-            }
-
-            let by_bank: Vec<BankStat> = bank_stats
-                .into_iter()
-                .map(|(bank, (count, total))| BankStat { bank, count, total })
-                .collect();
+async fn get_stats(State(state): State<AppState>, _user: AuthUser) -> impl IntoResponse {
+    let totals = state.reader.run(|conn| Ok((totals_by_type(conn, None)?, totals_by_bank(conn, None)?)));
+    match totals.await {
+        Ok((by_type, by_bank)) => {
+            let type_total = |transaction_type: &str| {
+                by_type
+                    .iter()
+                    .find(|group| group.key == transaction_type)
+                    .map_or(0.0, |group| group.absolute_total)
+            };
 
             let stats = StatsResponse {
-                total_transactions: total,
-                total_expenses,
-                total_income,
-                total_transfers,
-                total_credit_payments,
-                by_bank,
+                total_transactions: by_type.iter().map(|group| group.count as usize).sum(),
+                total_expenses: type_total("GASTO"),
+                total_income: type_total("INGRESO"),
+                total_transfers: type_total("TRASPASO"),
+                total_credit_payments: type_total("PAGO_TARJETA"),
+                by_bank: by_bank
+                    .into_iter()
+                    .map(|group| BankStat { bank: group.key, count: group.count as usize, total: group.absolute_total })
+                    .collect(),
             };
 
             (StatusCode::OK, Json(ApiResponse::ok(stats))).into_response()
@@ -212,11 +414,10 @@ async fn get_stats(State(state): State<AppState>) -> impl IntoResponse {
 /// GET /api/filters/:type - Filter transactions by type
 async fn filter_transactions(
     State(state): State<AppState>,
+    _user: AuthUser,
     Path(filter_type): Path<String>,
 ) -> impl IntoResponse {
-    let conn = state.db.lock().unwrap();
-
-    match get_all_transactions(&conn) {
+    match state.reader.run(get_active_transactions).await {
         Ok(transactions) => {
             let filtered: Vec<TransactionResponse> = transactions
                 .into_iter()
@@ -239,11 +440,10 @@ async fn filter_transactions(
     }
 }
 
-/// GET /api/sources - Get all source files with statistics
-async fn get_sources(State(state): State<AppState>) -> impl IntoResponse {
-    let conn = state.db.lock().unwrap();
-
-    match get_source_file_stats(&conn) {
+/// GET /api/sources - Get all source files with statistics (quality,
+/// duplicates, unresolved merchants)
+async fn get_sources(State(state): State<AppState>, _user: AuthUser) -> impl IntoResponse {
+    match state.reader.run(|conn| source_stats(conn, None)).await {
         Ok(stats) => {
             let response: Vec<SourceFileResponse> = stats
                 .into_iter()
@@ -266,19 +466,20 @@ async fn get_sources(State(state): State<AppState>) -> impl IntoResponse {
 /// GET /api/sources/:filename - Get transactions from a specific source file
 async fn get_source_transactions(
     State(state): State<AppState>,
+    _user: AuthUser,
     Path(filename): Path<String>,
 ) -> impl IntoResponse {
-    let conn = state.db.lock().unwrap();
-
     // Decode URL-encoded filename
     let decoded_filename = urlencoding::decode(&filename)
         .unwrap_or_else(|_| filename.clone().into())
         .into_owned();
 
-    match get_transactions_by_source(&conn, &decoded_filename) {
+    let source_file = decoded_filename.clone();
+    match state.reader.run(move |conn| get_transactions_by_source(conn, &source_file)).await {
         Ok(transactions) => {
             let response: Vec<TransactionResponse> = transactions
                 .into_iter()
+                .filter(|tx| tx.is_active())
                 .map(|tx| tx.into())
                 .collect();
 
@@ -295,6 +496,606 @@ async fn get_source_transactions(
     }
 }
 
+// ============================================================================
+// Write Handlers (editor) - every write records the authenticated username
+// ============================================================================
+
+#[derive(Deserialize)]
+struct CorrectionRequest {
+    category: Option<String>,
+    merchant: Option<String>,
+    transaction_type: Option<String>,
+    reason: String,
+}
+
+#[derive(Deserialize)]
+struct VoidRequest {
+    reason: String,
+}
+
+#[derive(Deserialize)]
+struct RejectRequest {
+    note: Option<String>,
+}
+
+/// Respond with the written transaction version or the error
+fn write_result(result: anyhow::Result<Transaction>) -> Response {
+    match result {
+        Ok(tx) => {
+            let response: TransactionResponse = tx.into();
+            (StatusCode::OK, Json(ApiResponse::ok(response))).into_response()
+        }
+        Err(e) => error_response(StatusCode::BAD_REQUEST, e.to_string()),
+    }
+}
+
+/// 200 with the new version, or 202 with the pending change awaiting approval
+fn submit_result(result: anyhow::Result<WriteOutcome>) -> Response {
+    match result {
+        Ok(WriteOutcome::Applied(tx)) => write_result(Ok(tx)),
+        Ok(WriteOutcome::Pending(change)) => {
+            (StatusCode::ACCEPTED, Json(ApiResponse::ok(change))).into_response()
+        }
+        Err(e) => error_response(StatusCode::BAD_REQUEST, e.to_string()),
+    }
+}
+
+/// POST /api/transactions/:id/correct - Append a corrected version
+async fn correct_transaction(
+    Db(conn): Db,
+    user: AuthUser,
+    Path(tx_id): Path<String>,
+    Json(request): Json<CorrectionRequest>,
+) -> Response {
+    if let Some(rejection) = user.forbidden_unless(Role::Editor) {
+        return rejection;
+    }
+
+    let correction = Correction {
+        category: request.category,
+        merchant: request.merchant,
+        transaction_type: request.transaction_type,
+        reason: request.reason,
+    };
+    let outcome = get_current_transaction(&conn, &tx_id).and_then(|current| {
+        let current = current.ok_or_else(|| anyhow::anyhow!("Transaction {} not found", tx_id))?;
+        submit_correction(&conn, &correction.apply(&current), &user.0.username)
+    });
+
+    submit_result(outcome)
+}
+
+/// POST /api/transactions/:id/void - Void a transaction
+async fn void_transaction_handler(
+    Db(conn): Db,
+    user: AuthUser,
+    Path(tx_id): Path<String>,
+    Json(request): Json<VoidRequest>,
+) -> Response {
+    if let Some(rejection) = user.forbidden_unless(Role::Editor) {
+        return rejection;
+    }
+    submit_result(submit_void(&conn, &tx_id, &request.reason, &user.0.username))
+}
+
+/// POST /api/transactions/:id/undo - Undo the last change
+async fn undo_transaction(
+    Db(conn): Db,
+    user: AuthUser,
+    Path(tx_id): Path<String>,
+) -> Response {
+    if let Some(rejection) = user.forbidden_unless(Role::Editor) {
+        return rejection;
+    }
+    submit_result(submit_undo(&conn, &tx_id, &user.0.username))
+}
+
+/// POST /api/transactions/:id/redo - Redo the last undone change
+async fn redo_transaction(
+    Db(conn): Db,
+    user: AuthUser,
+    Path(tx_id): Path<String>,
+) -> Response {
+    if let Some(rejection) = user.forbidden_unless(Role::Editor) {
+        return rejection;
+    }
+    submit_result(submit_redo(&conn, &tx_id, &user.0.username))
+}
+
+// ============================================================================
+// Note Handlers (any user may comment; notes never change the transaction)
+// ============================================================================
+
+#[derive(Deserialize)]
+struct NoteRequest {
+    text: String,
+    parent_id: Option<String>,
+}
+
+/// GET /api/transactions/:id/notes - Notes on a transaction, oldest first
+async fn get_transaction_notes(
+    ReadDb(conn): ReadDb,
+    _user: AuthUser,
+    Path(tx_id): Path<String>,
+) -> Response {
+    match get_notes(&conn, &tx_id) {
+        Ok(notes) => (StatusCode::OK, Json(ApiResponse::ok(notes))).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// POST /api/transactions/:id/notes - Add a note (or a reply via parent_id)
+async fn post_transaction_note(
+    Db(conn): Db,
+    user: AuthUser,
+    Path(tx_id): Path<String>,
+    Json(request): Json<NoteRequest>,
+) -> Response {
+    match add_note(&conn, &tx_id, &user.0.username, &request.text, request.parent_id.as_deref()) {
+        Ok(note) => (StatusCode::CREATED, Json(ApiResponse::ok(note))).into_response(),
+        Err(e) => error_response(StatusCode::BAD_REQUEST, e.to_string()),
+    }
+}
+
+// ============================================================================
+// Approval Handlers (editor; approver must differ from proposer)
+// ============================================================================
+
+/// GET /api/changes/pending - Changes awaiting approval
+async fn get_pending_changes(ReadDb(conn): ReadDb, _user: AuthUser) -> Response {
+    match list_pending_changes(&conn, None) {
+        Ok(changes) => (StatusCode::OK, Json(ApiResponse::ok(changes))).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// POST /api/changes/:id/approve - Approve a pending change
+async fn approve_pending_change(
+    Db(conn): Db,
+    user: AuthUser,
+    Path(change_id): Path<String>,
+) -> Response {
+    if let Some(rejection) = user.forbidden_unless(Role::Editor) {
+        return rejection;
+    }
+    write_result(approve_change(&conn, &change_id, &user.0.username))
+}
+
+/// POST /api/changes/:id/reject - Reject a pending change
+async fn reject_pending_change(
+    Db(conn): Db,
+    user: AuthUser,
+    Path(change_id): Path<String>,
+    Json(request): Json<RejectRequest>,
+) -> Response {
+    if let Some(rejection) = user.forbidden_unless(Role::Editor) {
+        return rejection;
+    }
+
+    match reject_change(&conn, &change_id, &user.0.username, request.note.as_deref()) {
+        Ok(change) => (StatusCode::OK, Json(ApiResponse::ok(change))).into_response(),
+        Err(e) => error_response(StatusCode::BAD_REQUEST, e.to_string()),
+    }
+}
+
+// ============================================================================
+// History Handlers (every version with valid ranges, plus events)
+// ============================================================================
+
+#[derive(Deserialize)]
+struct AsOfParams {
+    as_of: Option<String>,
+}
+
+impl AsOfParams {
+    fn parse(&self) -> anyhow::Result<Option<chrono::DateTime<chrono::Utc>>> {
+        self.as_of.as_deref().map(parse_as_of).transpose()
+    }
+}
+
+/// 200 with the history, 404 when the id is unknown
+fn history_result<T: Serialize>(entity: &str, id: &str, result: anyhow::Result<Option<T>>) -> Response {
+    match result {
+        Ok(Some(history)) => (StatusCode::OK, Json(ApiResponse::ok(history))).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, format!("{} {} not found", entity, id)),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// GET /api/transactions/:id/history - All versions of a transaction and its events
+async fn get_transaction_history_handler(
+    ReadDb(conn): ReadDb,
+    _user: AuthUser,
+    Path(tx_id): Path<String>,
+) -> Response {
+    history_result("Transaction", &tx_id, transaction_history(&conn, &tx_id))
+}
+
+/// GET /api/transactions/:id/provenance - Lineage from report lines back to the raw line
+async fn get_transaction_provenance(
+    ReadDb(conn): ReadDb,
+    _user: AuthUser,
+    Path(tx_id): Path<String>,
+) -> Response {
+    history_result("Transaction", &tx_id, provenance::trace(&conn, &tx_id))
+}
+
+/// GET /api/merchants?as_of= - Known merchants (as they were at `as_of`)
+async fn get_merchants(_user: AuthUser, Query(params): Query<AsOfParams>) -> Response {
+    match params.parse() {
+        Ok(as_of) => (StatusCode::OK, Json(ApiResponse::ok(merchants_as_of(shared_registry(), as_of)))).into_response(),
+        Err(e) => error_response(StatusCode::BAD_REQUEST, e.to_string()),
+    }
+}
+
+/// GET /api/merchants/:id/history - All versions of a merchant
+async fn get_merchant_history(_user: AuthUser, Path(merchant_id): Path<String>) -> Response {
+    history_result("Merchant", &merchant_id, Ok(merchant_history(shared_registry(), &merchant_id)))
+}
+
+/// Query parameters of GET /api/merchants/:id/profile
+#[derive(Debug, Deserialize)]
+struct ProfileParams {
+    /// Only this ledger's transactions (all ledgers otherwise)
+    ledger: Option<String>,
+}
+
+/// GET /api/merchants/:id/profile?ledger= - Lifetime spend, monthly trend, average
+/// ticket, first/last seen, categories and accounts of a merchant
+async fn get_merchant_profile(
+    ReadDb(conn): ReadDb,
+    _user: AuthUser,
+    Path(merchant_id): Path<String>,
+    Query(params): Query<ProfileParams>,
+) -> Response {
+    let transactions = match &params.ledger {
+        Some(ledger_id) => ledger_transactions(&conn, ledger_id),
+        None => get_active_transactions(&conn),
+    };
+    let profile = transactions.map(|transactions| merchant_profile(shared_registry(), &merchant_id, &transactions));
+    history_result("Merchant", &merchant_id, profile)
+}
+
+/// GET /api/rules?as_of= - Active classification rules (as they were at `as_of`)
+async fn get_rules(ReadDb(conn): ReadDb, _user: AuthUser, Query(params): Query<AsOfParams>) -> Response {
+    let as_of = match params.parse() {
+        Ok(as_of) => as_of.unwrap_or_else(chrono::Utc::now),
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+
+    match rules_as_of(&conn, as_of) {
+        Ok(rules) => (StatusCode::OK, Json(ApiResponse::ok(rules))).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// GET /api/rules/:id/history - All versions of a rule and its events
+async fn get_rule_history_handler(
+    ReadDb(conn): ReadDb,
+    _user: AuthUser,
+    Path(rule_id): Path<String>,
+) -> Response {
+    history_result("Rule", &rule_id, rule_history(&conn, &rule_id))
+}
+
+// ============================================================================
+// Import Handlers
+// ============================================================================
+
+/// Largest upload accepted by POST /api/imports
+const MAX_UPLOAD_BYTES: usize = 20 * 1024 * 1024;
+
+/// One field of a multipart/form-data upload
+struct FormPart {
+    name: String,
+    /// Set for file fields
+    filename: Option<String>,
+    content: Bytes,
+}
+
+/// The fields of a multipart/form-data request, or the 400 to answer
+#[allow(clippy::result_large_err)]
+async fn upload_parts(request: Request, state: &AppState) -> Result<Vec<FormPart>, Response> {
+    let mut multipart = Multipart::from_request(request, state)
+        .await
+        .map_err(|rejection| error_response(StatusCode::BAD_REQUEST, rejection.body_text()))?;
+    let mut parts = Vec::new();
+    while let Some(field) =
+        multipart.next_field().await.map_err(|e| error_response(e.status(), e.body_text()))?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+        let filename = field.file_name().map(str::to_string);
+        let content = field.bytes().await.map_err(|e| error_response(e.status(), e.body_text()))?;
+        parts.push(FormPart { name, filename, content });
+    }
+    Ok(parts)
+}
+
+/// Whether the upload asks to improve rows already imported (`upsert` field)
+fn upload_upsert(parts: &[FormPart]) -> bool {
+    parts
+        .iter()
+        .find(|part| part.name == "upsert" && part.filename.is_none())
+        .map(|part| matches!(String::from_utf8_lossy(&part.content).trim(), "true" | "1" | "on"))
+        .unwrap_or(false)
+}
+
+/// The upload's `ledger` field, or the default ledger
+fn upload_ledger(parts: &[FormPart]) -> String {
+    parts
+        .iter()
+        .find(|part| part.name == "ledger" && part.filename.is_none())
+        .map(|part| String::from_utf8_lossy(&part.content).trim().to_string())
+        .filter(|ledger| !ledger.is_empty())
+        .unwrap_or_else(|| DEFAULT_LEDGER_ID.to_string())
+}
+
+/// POST /api/imports - Import uploaded statements (CSV/JSON/OFX)
+///
+/// multipart/form-data with one or more file fields, an optional `ledger`
+/// field (default ledger otherwise) and an optional `upsert=true` to fill
+/// blank fields of rows already imported. Responds with one import session per
+/// file. Each file is its own transaction: files before a failing one stay
+/// imported.
+async fn post_import(State(state): State<AppState>, user: AuthUser, request: Request) -> Response {
+    if let Some(rejection) = user.forbidden_unless(Role::Editor) {
+        return rejection;
+    }
+
+    let parts = match upload_parts(request, &state).await {
+        Ok(parts) => parts,
+        Err(rejection) => return rejection,
+    };
+    let (ledger_id, upsert) = (upload_ledger(&parts), upload_upsert(&parts));
+    let files: Vec<_> = parts.into_iter().filter(|part| part.filename.is_some()).collect();
+    if files.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "No file in upload");
+    }
+
+    let mut sessions = Vec::new();
+    for file in files {
+        // Imports are the heaviest writes: run them off the async workers, one
+        // pooled connection per file so other requests get a turn in between
+        let (ledger_id, actor) = (ledger_id.clone(), user.0.username.clone());
+        let imported = state.db.run(move |conn| {
+            let rules = RuleEngine::from_database(conn)?;
+            let deduplication = DeduplicationEngine::new();
+            let context = ImportContext::new(&rules, &deduplication).with_upsert(upsert);
+            let filename = file.filename.as_deref().unwrap_or_default();
+            import_statement_with(conn, filename, &file.content, &ledger_id, &actor, &context)
+                .map_err(|e| anyhow::anyhow!("{}: {:#}", filename, e))
+        });
+        match imported.await {
+            Ok(session) => sessions.push(session),
+            Err(e) => return error_response(StatusCode::BAD_REQUEST, format!("{:#}", e)),
+        }
+    }
+
+    (StatusCode::OK, Json(ApiResponse::ok(sessions))).into_response()
+}
+
+// ============================================================================
+// Job Handlers
+// ============================================================================
+
+/// JSON body of POST /api/jobs, e.g. `{"kind": "duplicate_scan", "ledger": "business"}`
+#[derive(Deserialize)]
+struct JobRequest {
+    #[serde(flatten)]
+    spec: JobSpec,
+    ledger: Option<String>,
+}
+
+/// POST /api/jobs - Queue a long-running job (202 Accepted)
+///
+/// A multipart upload (same fields as /api/imports) queues one import job per
+/// file and responds with the list. A JSON body queues one `duplicate_scan` or
+/// `reconcile` job. Poll GET /api/jobs/:id for progress and the result.
+async fn post_job(State(state): State<AppState>, user: AuthUser, request: Request) -> Response {
+    if let Some(rejection) = user.forbidden_unless(Role::Editor) {
+        return rejection;
+    }
+    let actor = &user.0.username;
+    let content_type = request.headers().get(CONTENT_TYPE).and_then(|value| value.to_str().ok()).unwrap_or("");
+
+    if content_type.starts_with("multipart/form-data") {
+        let parts = match upload_parts(request, &state).await {
+            Ok(parts) => parts,
+            Err(rejection) => return rejection,
+        };
+        let ledger_id = upload_ledger(&parts);
+        let files: Vec<_> = parts.iter().filter(|part| part.filename.is_some()).collect();
+        if files.is_empty() {
+            return error_response(StatusCode::BAD_REQUEST, "No file in upload");
+        }
+
+        let conn = match state.conn() {
+            Ok(conn) => conn,
+            Err(rejection) => return rejection,
+        };
+        let mut jobs = Vec::new();
+        for file in files {
+            let spec = JobSpec::Import { filename: file.filename.clone().unwrap_or_default() };
+            match enqueue_job(&conn, &spec, Some(&file.content), &ledger_id, actor) {
+                Ok(job) => jobs.push(job),
+                Err(e) => return error_response(StatusCode::BAD_REQUEST, e.to_string()),
+            }
+        }
+        return (StatusCode::ACCEPTED, Json(ApiResponse::ok(jobs))).into_response();
+    }
+
+    let body = match Bytes::from_request(request, &state).await {
+        Ok(body) => body,
+        Err(rejection) => return error_response(StatusCode::BAD_REQUEST, rejection.body_text()),
+    };
+    let request: JobRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, format!("Invalid job: {}", e)),
+    };
+    if matches!(request.spec, JobSpec::Import { .. }) {
+        return error_response(StatusCode::BAD_REQUEST, "Import jobs are submitted as a multipart upload");
+    }
+    let ledger_id = request.ledger.unwrap_or_else(|| DEFAULT_LEDGER_ID.to_string());
+
+    let conn = match state.conn() {
+        Ok(conn) => conn,
+        Err(rejection) => return rejection,
+    };
+    match enqueue_job(&conn, &request.spec, None, &ledger_id, actor) {
+        Ok(job) => (StatusCode::ACCEPTED, Json(ApiResponse::ok(job))).into_response(),
+        Err(e) => error_response(StatusCode::BAD_REQUEST, e.to_string()),
+    }
+}
+
+/// GET /api/jobs - The 50 most recent jobs
+async fn get_jobs(ReadDb(conn): ReadDb, _user: AuthUser) -> Response {
+    match list_queued_jobs(&conn, 50) {
+        Ok(jobs) => (StatusCode::OK, Json(ApiResponse::ok(jobs))).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// GET /api/jobs/:id - Status, progress and (once finished) result of a job
+async fn get_job(ReadDb(conn): ReadDb, _user: AuthUser, Path(job_id): Path<String>) -> Response {
+    match get_queued_job(&conn, &job_id) {
+        Ok(Some(job)) => (StatusCode::OK, Json(ApiResponse::ok(job))).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, format!("Job {} not found", job_id)),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// How long the worker waits before looking for new jobs when idle
+const JOB_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Run queued jobs one at a time, on a thread with its own connection
+fn spawn_job_worker(db_path: std::path::PathBuf) {
+    std::thread::spawn(move || {
+        let conn = match open_worker_connection(&db_path) {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("❌ Job worker could not open the database: {}", e);
+                return;
+            }
+        };
+        loop {
+            match run_next_job(&conn, &db_path) {
+                Ok(Some(job)) => println!("⚙️  Job {} {}", job.id, job.status.as_str()),
+                Ok(None) => std::thread::sleep(JOB_POLL_INTERVAL),
+                Err(e) => {
+                    eprintln!("⚠️  Job worker: {}", e);
+                    std::thread::sleep(JOB_POLL_INTERVAL);
+                }
+            }
+        }
+    });
+}
+
+/// How often the outbox worker looks for due deliveries
+#[cfg(feature = "webhooks")]
+const OUTBOX_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// POST due outbox rows to their webhooks, on a thread with its own connection
+#[cfg(feature = "webhooks")]
+fn spawn_outbox_worker(db_path: std::path::PathBuf) {
+    std::thread::spawn(move || {
+        let conn = match open_worker_connection(&db_path) {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("❌ Outbox worker could not open the database: {}", e);
+                return;
+            }
+        };
+        let transport = trust_construction::HttpTransport::default();
+        loop {
+            match trust_construction::deliver_due(&conn, &transport, chrono::Utc::now()) {
+                Ok(report) if report.retrying + report.failed > 0 => println!(
+                    "📤 Webhooks: {} delivered, {} to retry, {} failed",
+                    report.delivered, report.retrying, report.failed
+                ),
+                Ok(_) => {}
+                Err(e) => eprintln!("⚠️  Outbox worker: {}", e),
+            }
+            std::thread::sleep(OUTBOX_POLL_INTERVAL);
+        }
+    });
+}
+
+// ============================================================================
+// User Handlers (admin)
+// ============================================================================
+
+#[derive(Deserialize)]
+struct CreateUserRequest {
+    username: String,
+    role: Role,
+}
+
+#[derive(Deserialize)]
+struct RoleRequest {
+    role: Role,
+}
+
+#[derive(Serialize)]
+struct CreatedUserResponse {
+    user: User,
+    /// Shown only once - store it now
+    token: String,
+}
+
+/// GET /api/me - The authenticated user
+async fn get_me(user: AuthUser) -> impl IntoResponse {
+    Json(ApiResponse::ok(user.0))
+}
+
+/// GET /api/users - List users
+async fn get_users(ReadDb(conn): ReadDb, user: AuthUser) -> Response {
+    if let Some(rejection) = user.forbidden_unless(Role::Admin) {
+        return rejection;
+    }
+
+    match list_users(&conn) {
+        Ok(users) => (StatusCode::OK, Json(ApiResponse::ok(users))).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// POST /api/users - Create a user (returns its token once)
+async fn post_user(
+    Db(conn): Db,
+    user: AuthUser,
+    Json(request): Json<CreateUserRequest>,
+) -> Response {
+    if let Some(rejection) = user.forbidden_unless(Role::Admin) {
+        return rejection;
+    }
+
+    match create_user(&conn, &request.username, request.role, &user.0.username) {
+        Ok((user, token)) => (
+            StatusCode::CREATED,
+            Json(ApiResponse::ok(CreatedUserResponse { user, token })),
+        )
+            .into_response(),
+        Err(e) => error_response(StatusCode::BAD_REQUEST, e.to_string()),
+    }
+}
+
+/// POST /api/users/:username/role - Change a user's role
+async fn post_user_role(
+    Db(conn): Db,
+    user: AuthUser,
+    Path(username): Path<String>,
+    Json(request): Json<RoleRequest>,
+) -> Response {
+    if let Some(rejection) = user.forbidden_unless(Role::Admin) {
+        return rejection;
+    }
+
+    match set_user_role(&conn, &username, request.role, &user.0.username) {
+        Ok(updated) => (StatusCode::OK, Json(ApiResponse::ok(updated))).into_response(),
+        Err(e) => error_response(StatusCode::BAD_REQUEST, e.to_string()),
+    }
+}
+
 /// GET / - Serve index.html
 async fn serve_index() -> impl IntoResponse {
     Html(include_str!("../web/index.html"))
@@ -310,6 +1111,11 @@ async fn serve_statement_detail() -> impl IntoResponse {
     Html(include_str!("../web/statement-detail.html"))
 }
 
+/// GET /merchant-detail - Serve merchant detail page
+async fn serve_merchant_detail() -> impl IntoResponse {
+    Html(include_str!("../web/merchant-detail.html"))
+}
+
 // ============================================================================
 // Main Server
 // ============================================================================
@@ -320,21 +1126,57 @@ async fn main() {
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 
     // Open database
-    let db_path = std::path::Path::new("/Users/darwinborges/finance/trust-construction/transactions.db");
+    let config = AppConfig::load().expect("Failed to read config");
+    let db_path = std::path::Path::new(&config.db_path);
 
-    if !db_path.exists() {
+    if !config.in_memory() && !db_path.exists() {
         eprintln!("❌ Database not found at {:?}", db_path);
-        eprintln!("   Run: cargo run --release import");
-        eprintln!("   to import transactions first.");
+        eprintln!("   Run: cargo run --release init");
+        eprintln!("   to set up the database first.");
         std::process::exit(1);
     }
 
-    let conn = Connection::open(db_path).expect("Failed to open database");
-    println!("✓ Database opened: {:?}", db_path);
+    // Migrations run once here; handlers then share a pool of connections.
+    // In memory the pool holds the database: readers share it, and workers
+    // open their connections at its URI
+    let (pool, reader) = if config.in_memory() {
+        let pool = ConnectionPool::in_memory(DEFAULT_POOL_SIZE).expect("Failed to open database");
+        setup_database(&pool.get().expect("Failed to open database")).expect("Failed to open database");
+        println!("✓ Database in memory ({} connections, nothing kept on disk)", DEFAULT_POOL_SIZE);
+        (pool.clone(), pool)
+    } else {
+        TrustSystem::open(db_path).expect("Failed to open database");
+        let pool = ConnectionPool::open(db_path, DEFAULT_POOL_SIZE).expect("Failed to open database");
+        let reader = ConnectionPool::open_read_only(db_path, DEFAULT_POOL_SIZE).expect("Failed to open database");
+        println!("✓ Database opened: {:?} ({} connections)", db_path, DEFAULT_POOL_SIZE);
+        (pool, reader)
+    };
+    let conn = pool.get().expect("Failed to open database");
+
+    match requeue_interrupted_jobs(&conn) {
+        Ok(0) => {}
+        Ok(count) => println!("✓ {} interrupted jobs queued again", count),
+        Err(e) => eprintln!("❌ Could not requeue jobs: {}", e),
+    }
+    spawn_job_worker(pool.location().to_path_buf());
+    #[cfg(feature = "webhooks")]
+    spawn_outbox_worker(pool.location().to_path_buf());
+
+    match user_count(&conn) {
+        Ok(0) => println!("⚠️  No users configured - API is open (create one with: user add <name> admin)"),
+        Ok(count) => println!("✓ {} users - API requires bearer tokens", count),
+        Err(e) => eprintln!("❌ Could not read users: {}", e),
+    }
+
+    let writes_per_minute = config.api_writes_per_minute.unwrap_or(DEFAULT_WRITES_PER_MINUTE);
+    println!("✓ API writes limited to {} per minute per token", writes_per_minute);
 
     // Create shared state
+    drop(conn);
     let state = AppState {
-        db: Arc::new(Mutex::new(conn)),
+        db: pool,
+        reader,
+        limiter: Arc::new(RateLimiter::per_minute(writes_per_minute)),
     };
 
     // Build API routes
@@ -345,6 +1187,28 @@ async fn main() {
         .route("/filters/:type", get(filter_transactions))
         .route("/sources", get(get_sources))
         .route("/sources/:filename", get(get_source_transactions))
+        .route("/transactions/:id/correct", post(correct_transaction))
+        .route("/transactions/:id/void", post(void_transaction_handler))
+        .route("/transactions/:id/undo", post(undo_transaction))
+        .route("/transactions/:id/redo", post(redo_transaction))
+        .route("/transactions/:id/notes", get(get_transaction_notes).post(post_transaction_note))
+        .route("/transactions/:id/history", get(get_transaction_history_handler))
+        .route("/transactions/:id/provenance", get(get_transaction_provenance))
+        .route("/merchants", get(get_merchants))
+        .route("/merchants/:id/history", get(get_merchant_history))
+        .route("/merchants/:id/profile", get(get_merchant_profile))
+        .route("/rules", get(get_rules))
+        .route("/rules/:id/history", get(get_rule_history_handler))
+        .route("/changes/pending", get(get_pending_changes))
+        .route("/changes/:id/approve", post(approve_pending_change))
+        .route("/changes/:id/reject", post(reject_pending_change))
+        .route("/imports", post(post_import).layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES)))
+        .route("/jobs", get(get_jobs).post(post_job).layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES)))
+        .route("/jobs/:id", get(get_job))
+        .route("/me", get(get_me))
+        .route("/users", get(get_users).post(post_user))
+        .route("/users/:username/role", post(post_user_role))
+        .layer(middleware::from_fn_with_state(state.clone(), audit_writes))
         .with_state(state.clone());
 
     // Build main router
@@ -352,10 +1216,24 @@ async fn main() {
         .route("/", get(serve_index))
         .route("/statements", get(serve_statements))
         .route("/statement-detail", get(serve_statement_detail))
+        .route("/merchant-detail", get(serve_merchant_detail))
         .nest("/api", api_routes)
         .nest_service("/static", ServeDir::new("web"))
         .layer(CorsLayer::permissive());
 
+    // gRPC API on its own port, sharing the database connection
+    #[cfg(feature = "grpc")]
+    {
+        let service = trust_construction::LedgerService::new(state.db.clone()).into_server();
+        let grpc_addr = "0.0.0.0:50051".parse().expect("Invalid gRPC address");
+        tokio::spawn(async move {
+            if let Err(e) = tonic::transport::Server::builder().add_service(service).serve(grpc_addr).await {
+                eprintln!("❌ gRPC server stopped: {}", e);
+            }
+        });
+        println!("✓ gRPC API on {} (proto/trust.proto)", grpc_addr);
+    }
+
     // Start server
     let addr = "0.0.0.0:3000";
     let listener = tokio::net::TcpListener::bind(addr)
@@ -0,0 +1,16 @@
+// Build script: generates the gRPC service from proto/trust.proto
+// (only with the `grpc` feature; uses a vendored protoc unless $PROTOC is set)
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/trust.proto");
+        if std::env::var_os("PROTOC").is_none() {
+            let protoc = protoc_bin_vendored::protoc_bin_path().expect("No vendored protoc for this platform");
+            std::env::set_var("PROTOC", protoc);
+        }
+        tonic_build::compile_protos("proto/trust.proto").expect("Failed to compile proto/trust.proto");
+    }
+}
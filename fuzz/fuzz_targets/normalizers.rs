@@ -0,0 +1,11 @@
+// Arbitrary text through the date and amount normalizers (see src/fuzzing.rs)
+//
+//   cargo +nightly fuzz run normalizers fuzz/corpus/normalizers
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    trust_construction::fuzzing::normalize_fields(data);
+});
@@ -0,0 +1,11 @@
+// First byte picks the parser, the rest is the file (see src/fuzzing.rs)
+//
+//   cargo +nightly fuzz run parsers fuzz/corpus/parsers
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    trust_construction::fuzzing::parse_and_normalize(data);
+});
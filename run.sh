@@ -9,6 +9,10 @@ SCRIPT_DIR="$( cd "$( dirname "${BASH_SOURCE[0]}" )" && pwd )"
 cd "$SCRIPT_DIR"
 
 case "$1" in
+    init)
+        echo "🧭 Running first-run setup..."
+        cargo run --release init
+        ;;
     import)
         echo "🗄️  Running import..."
         cargo run --release import
@@ -19,6 +23,7 @@ case "$1" in
         ;;
     *)
         echo "Usage:"
+        echo "  ./run.sh init    - First-run setup (config, database, rules)"
         echo "  ./run.sh import  - Import transactions from CSV"
         echo "  ./run.sh ui      - Launch terminal UI (default)"
         echo "  ./run.sh         - Launch terminal UI"
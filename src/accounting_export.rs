@@ -0,0 +1,223 @@
+// 🧾 Accounting Export - Files QuickBooks and Xero import directly
+//
+// Problem solved:
+// - Bookkeepers re-key the ledger into QuickBooks or Xero by hand, because
+//   the journal (journal.rs) is written for plain-text accounting tools
+//
+// `export_accounting` takes the double-entry view of a ledger query and
+// writes it in the import formats those tools take: a QuickBooks Online
+// journal entry CSV, a QuickBooks Desktop IIF file, and Xero bank statement
+// CSVs (one per bank account, as Xero imports statements per account).
+// Accounts mapped in the chart of accounts go out under the chart's name and
+// code, so imports land on the accountant's accounts instead of new ones.
+
+use crate::chart_of_accounts::ChartOfAccounts;
+use crate::journal::{iif_account, render_journal, JournalEntry, JournalFormat, Posting};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountingExport {
+    /// QuickBooks Online journal entry import
+    QuickBooksCsv,
+    /// QuickBooks Desktop general journal (same file as `journal --format quickbooks`)
+    QuickBooksIif,
+    /// Xero precoded bank statement import
+    XeroCsv,
+}
+
+impl AccountingExport {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "quickbooks" | "quickbooks-csv" | "qbo" => Ok(AccountingExport::QuickBooksCsv),
+            "quickbooks-iif" | "iif" => Ok(AccountingExport::QuickBooksIif),
+            "xero" | "xero-csv" => Ok(AccountingExport::XeroCsv),
+            other => Err(anyhow!("Unknown export format: {} (quickbooks, quickbooks-iif, xero)", other)),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AccountingExport::QuickBooksIif => "iif",
+            _ => "csv",
+        }
+    }
+}
+
+/// One file to import; `account` names the bank account of a Xero statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportFile {
+    pub account: Option<String>,
+    pub contents: String,
+}
+
+// ============================================================================
+// EXPORT
+// ============================================================================
+
+/// The account name the bookkeeping tool knows: the chart's name when the
+/// posting is coded, else the journal name without its root
+fn account_name<'a>(posting: &'a Posting, chart: &'a ChartOfAccounts) -> &'a str {
+    posting
+        .code
+        .as_deref()
+        .and_then(|code| chart.mappings.iter().find(|mapping| mapping.code == code))
+        .map_or_else(|| iif_account(&posting.account).0, |mapping| mapping.name.as_str())
+}
+
+fn csv_string(writer: csv::Writer<Vec<u8>>) -> Result<String> {
+    let bytes = writer.into_inner().map_err(|e| anyhow!("CSV export failed: {}", e))?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+fn quickbooks_csv(entries: &[JournalEntry], chart: &ChartOfAccounts) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "JournalNo", "JournalDate", "AccountName", "Debits", "Credits", "Description", "Name", "Currency",
+    ])?;
+    for (number, entry) in entries.iter().enumerate() {
+        for posting in &entry.postings {
+            let (debit, credit) = if posting.amount >= 0.0 {
+                (format!("{:.2}", posting.amount), String::new())
+            } else {
+                (String::new(), format!("{:.2}", -posting.amount))
+            };
+            writer.write_record([
+                (number + 1).to_string(),
+                entry.date.format("%m/%d/%Y").to_string(),
+                account_name(posting, chart).to_string(),
+                debit,
+                credit,
+                entry.narration.clone(),
+                entry.payee.clone(),
+                posting.currency.clone(),
+            ])?;
+        }
+    }
+    csv_string(writer)
+}
+
+/// One statement per bank account, the first posting of each entry: amounts
+/// as seen from that account, coded with the other side's chart code
+fn xero_statements(entries: &[JournalEntry], chart: &ChartOfAccounts) -> Result<Vec<ExportFile>> {
+    let mut by_account: BTreeMap<&str, (&Posting, Vec<&JournalEntry>)> = BTreeMap::new();
+    for entry in entries {
+        let bank = &entry.postings[0];
+        by_account.entry(bank.account.as_str()).or_insert_with(|| (bank, Vec::new())).1.push(entry);
+    }
+
+    let mut files = Vec::new();
+    for (bank, entries) in by_account.into_values() {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(["*Date", "*Amount", "Payee", "Description", "Reference", "Account Code"])?;
+        for entry in entries {
+            writer.write_record([
+                entry.date.format("%d/%m/%Y").to_string(),
+                format!("{:.2}", entry.postings[0].amount),
+                entry.payee.clone(),
+                entry.narration.clone(),
+                entry.tx_id.clone(),
+                entry.postings.get(1).and_then(|p| p.code.clone()).unwrap_or_default(),
+            ])?;
+        }
+        files.push(ExportFile {
+            account: Some(account_name(bank, chart).to_string()),
+            contents: csv_string(writer)?,
+        });
+    }
+    Ok(files)
+}
+
+/// The entries (see `journal_entries`) as files to import
+///
+/// QuickBooks gets a single file, one numbered journal entry per ledger row
+/// (dates MM/DD/YYYY). Xero gets one bank statement per bank account (dates
+/// DD/MM/YYYY, positive amounts are money in); rows whose category is mapped
+/// carry its chart code so they arrive already coded.
+pub fn export_accounting(
+    entries: &[JournalEntry],
+    chart: &ChartOfAccounts,
+    format: AccountingExport,
+) -> Result<Vec<ExportFile>> {
+    match format {
+        AccountingExport::QuickBooksCsv => {
+            Ok(vec![ExportFile { account: None, contents: quickbooks_csv(entries, chart)? }])
+        }
+        AccountingExport::QuickBooksIif => {
+            Ok(vec![ExportFile { account: None, contents: render_journal(entries, JournalFormat::QuickBooks) }])
+        }
+        AccountingExport::XeroCsv => xero_statements(entries, chart),
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::chart_of_accounts::ChartAccountType::{Asset, Expense};
+    use crate::chart_of_accounts::{ChartMapping, MappedFrom};
+    use crate::journal::journal_entries;
+    use crate::db::Transaction;
+
+    fn row(id: &str, date: &str, amount: f64, category: &str, account: &str) -> Transaction {
+        Transaction {
+            date: date.to_string(),
+            description: format!("{}, {}", category, id),
+            amount_original: format!("{:.2}", amount),
+            amount_numeric: amount,
+            transaction_type: if amount < 0.0 { "GASTO" } else { "INGRESO" }.to_string(),
+            category: category.to_string(),
+            account_name: account.to_string(),
+            account_number: String::new(),
+            bank: "Chase".to_string(),
+            source_file: "chase.csv".to_string(),
+            id: id.to_string(),
+            version: 1,
+            ..test_support::sample_transaction()
+        }
+    }
+
+    fn chart() -> ChartOfAccounts {
+        ChartOfAccounts::new(vec![
+            ChartMapping::new(MappedFrom::Category, "Coffee", "6100", "Meals and Entertainment", Expense),
+            ChartMapping::new(MappedFrom::Account, "Checking", "1010", "Operating Account", Asset),
+        ])
+    }
+
+    #[test]
+    fn test_quickbooks_csv_uses_chart_names() {
+        let entries = journal_entries(&[row("c-1", "01/02/2025", -5.25, "Coffee", "Checking")], &[], &chart()).unwrap();
+        let files = export_accounting(&entries, &chart(), AccountingExport::QuickBooksCsv).unwrap();
+        let lines: Vec<&str> = files[0].contents.lines().collect();
+        assert_eq!(lines[0], "JournalNo,JournalDate,AccountName,Debits,Credits,Description,Name,Currency");
+        assert_eq!(lines[1], "1,01/02/2025,Operating Account,,5.25,\"Coffee, c-1\",\"Coffee, c-1\",USD");
+        assert_eq!(lines[2], "1,01/02/2025,Meals and Entertainment,5.25,,\"Coffee, c-1\",\"Coffee, c-1\",USD");
+
+        let iif = export_accounting(&entries, &chart(), AccountingExport::QuickBooksIif).unwrap();
+        assert!(iif[0].contents.starts_with("!ACCNT"));
+        assert!(AccountingExport::parse("qbo").is_ok() && AccountingExport::parse("sage").is_err());
+    }
+
+    #[test]
+    fn test_xero_statement_per_bank_account() {
+        let transactions = vec![
+            row("c-1", "01/02/2025", -5.25, "Coffee", "Checking"),
+            row("s-1", "01/15/2025", 900.0, "Salary", "Savings"),
+        ];
+        let entries = journal_entries(&transactions, &[], &chart()).unwrap();
+        let files = export_accounting(&entries, &chart(), AccountingExport::XeroCsv).unwrap();
+
+        let accounts: Vec<_> = files.iter().map(|file| file.account.as_deref().unwrap()).collect();
+        assert_eq!(accounts, vec!["Chase:Savings", "Operating Account"]);
+        assert_eq!(
+            files[1].contents,
+            "*Date,*Amount,Payee,Description,Reference,Account Code\n\
+             02/01/2025,-5.25,\"Coffee, c-1\",\"Coffee, c-1\",c-1,6100\n"
+        );
+        assert!(files[0].contents.ends_with("15/01/2025,900.00,\"Salary, s-1\",\"Salary, s-1\",s-1,\n"));
+    }
+}
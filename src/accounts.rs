@@ -0,0 +1,422 @@
+// 🏦 Accounts - Persisted account versions and opening balances
+//
+// Problem solved:
+// - Balances were only ever sums of imported transactions, so an account that
+//   had $3,000 before its first imported statement never reconciled
+// - Account entities lived only in memory (AccountRegistry)
+//
+// `set_opening_balance` creates the account (or a new version of it) with an
+// opening balance as of a date, plus one synthetic transaction flagged
+// `opening_balance` for that amount. Running balances then start from the real
+// balance on day one. Setting it again versions both instead of adding more.
+//
+// `set_account_status` freezes, closes or reopens an account (a new version,
+// same rules as the AccountRegistry). `account_of` finds the account a
+// transaction was booked on, so imports and manual entries can refuse rows
+// dated after a closed account's close date.
+//
+// `account_balances` adds up a ledger's active rows per account (opening
+// balance rows included), optionally as of a date. Rows that no stored
+// account claims are grouped by the account they name.
+
+use crate::db::{
+    get_active_transactions, insert_event, insert_transaction_row, insert_transaction_version,
+    Event, Transaction,
+};
+use crate::event_schema::{AccountStatusChanged, OpeningBalanceSet};
+use crate::entities::{Account, AccountStatus};
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+
+/// Metadata flag on the synthetic balancing transaction
+pub const OPENING_BALANCE_FLAG: &str = "opening_balance";
+
+#[derive(Debug, Clone)]
+pub struct OpeningBalance {
+    pub account: Account,
+    pub transaction: Transaction,
+}
+
+// ============================================================================
+// ACCOUNT STORAGE
+// ============================================================================
+
+/// Store a new account version, closing the previous one
+pub fn save_account_version(conn: &Connection, account: &Account) -> Result<()> {
+    conn.execute(
+        "UPDATE accounts SET valid_until = ?1 WHERE id = ?2 AND valid_until IS NULL",
+        params![account.valid_from.to_rfc3339(), account.id],
+    )?;
+    conn.execute(
+        "INSERT INTO accounts (id, version, ledger_id, name, data, valid_from, valid_until)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, NULL)",
+        params![
+            account.id,
+            account.version,
+            account.ledger_id,
+            account.name,
+            serde_json::to_string(account)?,
+            account.valid_from.to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Current version of the account with this name in a ledger
+pub fn find_account(conn: &Connection, ledger_id: &str, name: &str) -> Result<Option<Account>> {
+    let data: Option<String> = conn
+        .query_row(
+            "SELECT data FROM accounts WHERE ledger_id = ?1 AND name = ?2 AND valid_until IS NULL",
+            params![ledger_id, name],
+            |row| row.get(0),
+        )
+        .optional()?;
+    data.map(|data| Ok(serde_json::from_str(&data)?)).transpose()
+}
+
+/// Current versions of every account in a ledger, by name
+pub fn list_accounts(conn: &Connection, ledger_id: &str) -> Result<Vec<Account>> {
+    let mut stmt = conn.prepare(
+        "SELECT data FROM accounts WHERE ledger_id = ?1 AND valid_until IS NULL ORDER BY name",
+    )?;
+    let rows = stmt.query_map([ledger_id], |row| row.get::<_, String>(0))?;
+
+    let mut accounts = Vec::new();
+    for data in rows {
+        accounts.push(serde_json::from_str(&data?)?);
+    }
+    Ok(accounts)
+}
+
+/// The account `tx` was booked on: same name (case-insensitive) and, when
+/// the account has one, the same bank
+pub fn account_of<'a>(accounts: &'a [Account], tx: &Transaction) -> Option<&'a Account> {
+    accounts.iter().find(|account| {
+        account.name.eq_ignore_ascii_case(tx.account_name.trim())
+            && (account.bank_id.is_empty() || account.bank_id == tx.bank)
+    })
+}
+
+// ============================================================================
+// LIFECYCLE
+// ============================================================================
+
+/// Freeze, close or reopen the account `name` (a new version; no-op when
+/// the state doesn't change)
+pub fn set_account_status(
+    conn: &Connection,
+    ledger_id: &str,
+    name: &str,
+    status: AccountStatus,
+    actor: &str,
+) -> Result<Account> {
+    let current = find_account(conn, ledger_id, name)?
+        .ok_or_else(|| anyhow!("Account '{}' not found in ledger '{}'", name, ledger_id))?;
+    let Some(status) = current.status.transition(&status).map_err(|e| anyhow!("{}: {}", name, e))? else {
+        return Ok(current);
+    };
+
+    let mut next = current.next_version();
+    next.system_time = Utc::now();
+    next.status = status;
+    save_account_version(conn, &next)?;
+
+    let payload = AccountStatusChanged { version: next.version, status: next.status.clone() };
+    let event = Event::typed("account_status_changed", "account", &next.id, &payload, actor)?.with_ledger(ledger_id);
+    insert_event(conn, &event)?;
+    Ok(next)
+}
+
+// ============================================================================
+// OPENING BALANCE
+// ============================================================================
+
+/// Create (or version) an account with an opening balance as of a date
+///
+/// `template` supplies the account's values when it doesn't exist yet; an
+/// existing account (same ledger and name) keeps its identity and gets a new
+/// version. The balancing transaction is created once and versioned afterwards.
+pub fn set_opening_balance(
+    conn: &Connection,
+    template: Account,
+    balance: f64,
+    as_of: NaiveDate,
+    actor: &str,
+) -> Result<OpeningBalance> {
+    let mut account = match find_account(conn, &template.ledger_id, &template.name)? {
+        Some(current) => {
+            let mut next = current.next_version();
+            next.system_time = Utc::now();
+            next.current_balance += balance - current.opening_balance;
+            next
+        }
+        None => template,
+    };
+    account.opening_balance = balance;
+    if account.version == 1 {
+        account.current_balance = balance;
+    }
+    account.metadata[OPENING_BALANCE_FLAG] = serde_json::json!(as_of.to_string());
+    save_account_version(conn, &account)?;
+
+    let existing = get_active_transactions(conn)?.into_iter().find(|tx| {
+        tx.ledger_id == account.ledger_id
+            && tx.has_metadata(OPENING_BALANCE_FLAG)
+            && tx.get_metadata("account_id").and_then(|v| v.as_str()) == Some(account.id.as_str())
+    });
+
+    let transaction = match existing {
+        Some(current) => {
+            let mut next = current.next_version(Some("Opening balance changed".to_string()));
+            fill_opening_values(&mut next, &account, balance, as_of);
+            insert_transaction_version(conn, &next, actor)?;
+            next
+        }
+        None => {
+            let tx = opening_transaction(&account, balance, as_of);
+            insert_transaction_row(conn, &tx, &tx.compute_idempotency_hash())
+                .map_err(|e| anyhow!("Could not insert opening balance for {}: {}", account.name, e))?;
+            tx
+        }
+    };
+
+    let payload = OpeningBalanceSet {
+        version: account.version,
+        opening_balance: balance,
+        as_of,
+        tx_uuid: transaction.id.clone(),
+    };
+    let event = Event::typed("opening_balance_set", "account", &account.id, &payload, actor)?.with_ledger(&account.ledger_id);
+    insert_event(conn, &event)?;
+
+    Ok(OpeningBalance { account, transaction })
+}
+
+/// Synthetic transaction that brings the account to `balance` on `as_of`
+///
+/// Typed TRASPASO so statement reconciliation (credits/debits) ignores it.
+fn opening_transaction(account: &Account, balance: f64, as_of: NaiveDate) -> Transaction {
+    let mut tx = Transaction {
+        date: String::new(),
+        description: String::new(),
+        amount_original: String::new(),
+        amount_numeric: 0.0,
+        transaction_type: "TRASPASO".to_string(),
+        category: "Opening Balance".to_string(),
+        merchant: "Opening Balance".to_string(),
+        currency: account.currency.clone(),
+        account_name: account.name.clone(),
+        account_number: account.account_number.clone(),
+        bank: account.bank_id.clone(),
+        source_file: OPENING_BALANCE_FLAG.to_string(),
+        line_number: "0".to_string(),
+        classification_notes: "Synthetic balancing transaction".to_string(),
+        id: String::new(),
+        version: 0,
+        system_time: None,
+        valid_from: None,
+        valid_until: None,
+        previous_version_id: None,
+        ledger_id: account.ledger_id.clone(),
+        metadata: HashMap::new(),
+    };
+    tx.init_temporal_fields();
+    fill_opening_values(&mut tx, account, balance, as_of);
+    tx
+}
+
+fn fill_opening_values(tx: &mut Transaction, account: &Account, balance: f64, as_of: NaiveDate) {
+    tx.date = as_of.format("%m/%d/%Y").to_string();
+    tx.description = format!("Opening balance for {}", account.name);
+    tx.amount_original = format!("{:.2}", balance);
+    tx.amount_numeric = balance;
+    tx.metadata.insert(OPENING_BALANCE_FLAG.to_string(), serde_json::json!(true));
+    tx.metadata.insert("account_id".to_string(), serde_json::json!(account.id));
+}
+
+// ============================================================================
+// BALANCES
+// ============================================================================
+
+/// What the active rows of one account add up to
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountBalance {
+    /// Stored account name, or the account (or bank and number) rows name
+    pub account: String,
+    pub currency: String,
+    /// Whether the account is stored (accounts table)
+    pub stored: bool,
+    pub rows: usize,
+    pub balance: f64,
+}
+
+/// Balance of every account in a ledger from its active rows dated on or
+/// before `as_of` (every row when None): stored accounts first, by name,
+/// then the accounts rows name, as first seen
+pub fn account_balances(conn: &Connection, ledger_id: &str, as_of: Option<NaiveDate>) -> Result<Vec<AccountBalance>> {
+    let accounts = list_accounts(conn, ledger_id)?;
+    let mut balances: Vec<AccountBalance> = accounts
+        .iter()
+        .map(|account| AccountBalance {
+            account: account.name.clone(),
+            currency: account.currency.clone(),
+            stored: true,
+            rows: 0,
+            balance: 0.0,
+        })
+        .collect();
+    let mut index: HashMap<(String, String), usize> = balances
+        .iter()
+        .enumerate()
+        .map(|(i, balance)| ((balance.account.clone(), balance.currency.clone()), i))
+        .collect();
+
+    for tx in get_active_transactions(conn)? {
+        if tx.ledger_id != ledger_id || as_of.is_some_and(|as_of| tx.parsed_date().is_none_or(|date| date > as_of)) {
+            continue;
+        }
+        let (account, stored) = match account_of(&accounts, &tx) {
+            Some(account) => (account.name.clone(), true),
+            None => (named_account(&tx), false),
+        };
+        let key = (account, tx.currency.clone());
+        let i = *index.entry(key.clone()).or_insert_with(|| {
+            balances.push(AccountBalance { account: key.0, currency: key.1, stored, rows: 0, balance: 0.0 });
+            balances.len() - 1
+        });
+        balances[i].rows += 1;
+        balances[i].balance += tx.amount_numeric;
+    }
+    Ok(balances)
+}
+
+/// The account a row names: its account name, or its bank and account number
+fn named_account(tx: &Transaction) -> String {
+    match tx.account_name.trim() {
+        "" => match format!("{} {}", tx.bank.trim(), tx.account_number.trim()).trim() {
+            "" => "(no account)".to_string(),
+            name => name.to_string(),
+        },
+        name => name.to_string(),
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{get_transaction_history, setup_database};
+    use crate::entities::AccountType;
+
+    fn checking() -> Account {
+        Account::new(
+            "BofA Checking".to_string(),
+            "*1234".to_string(),
+            "BofA".to_string(),
+            AccountType::Checking,
+            "USD".to_string(),
+            0.0,
+        )
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_opening_balance_creates_account_and_transaction() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let opening = set_opening_balance(&conn, checking(), 3000.0, date("2024-12-31"), "ana").unwrap();
+        assert_eq!(opening.account.opening_balance, 3000.0);
+        assert_eq!(opening.account.current_balance, 3000.0);
+        assert_eq!(opening.transaction.date, "12/31/2024");
+        assert!(opening.transaction.has_metadata(OPENING_BALANCE_FLAG));
+
+        let active = get_active_transactions(&conn).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].amount_numeric, 3000.0);
+        assert_eq!(list_accounts(&conn, "default").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_setting_again_versions_account_and_transaction() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let first = set_opening_balance(&conn, checking(), 3000.0, date("2024-12-31"), "ana").unwrap();
+        let second = set_opening_balance(&conn, checking(), 3250.0, date("2024-11-30"), "ana").unwrap();
+
+        assert_eq!(second.account.id, first.account.id);
+        assert_eq!(second.account.version, 2);
+        assert_eq!(second.transaction.id, first.transaction.id);
+
+        let active = get_active_transactions(&conn).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].amount_numeric, 3250.0);
+        assert_eq!(active[0].date, "11/30/2024");
+        assert_eq!(get_transaction_history(&conn, &first.transaction.id).unwrap().len(), 2);
+
+        let accounts = list_accounts(&conn, "default").unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].opening_balance, 3250.0);
+    }
+
+    #[test]
+    fn test_closing_versions_account_and_logs_event() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        set_opening_balance(&conn, checking(), 3000.0, date("2024-12-31"), "ana").unwrap();
+
+        let closed = AccountStatus::Closed { closed_on: date("2025-03-31") };
+        let account = set_account_status(&conn, "default", "BofA Checking", closed.clone(), "ana").unwrap();
+        assert_eq!((account.version, account.status.clone()), (2, closed.clone()));
+        assert_eq!(list_accounts(&conn, "default").unwrap()[0].status, closed);
+        let events = crate::db::get_events_for_entity(&conn, "account", &account.id).unwrap();
+        assert_eq!(events[0].event_type, "account_status_changed");
+
+        // Same state again: nothing new
+        let again = set_account_status(&conn, "default", "BofA Checking", closed, "ana").unwrap();
+        assert_eq!(again.version, 2);
+        assert!(set_account_status(&conn, "default", "BofA Checking", AccountStatus::Frozen, "ana").is_err());
+
+        let mut tx = get_active_transactions(&conn).unwrap().remove(0);
+        let accounts = list_accounts(&conn, "default").unwrap();
+        assert_eq!(account_of(&accounts, &tx).map(|a| a.id.as_str()), Some(account.id.as_str()));
+        tx.account_name = "Savings".to_string();
+        assert!(account_of(&accounts, &tx).is_none());
+    }
+
+    #[test]
+    fn test_balances_add_up_rows_per_account() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let opening = set_opening_balance(&conn, checking(), 3000.0, date("2024-12-31"), "ana").unwrap();
+        for (day, amount, account) in [("01/15/2025", -45.5, "BofA Checking"), ("02/01/2025", -20.0, "Savings")] {
+            let mut tx = opening.transaction.clone();
+            tx.date = day.to_string();
+            tx.amount_numeric = amount;
+            tx.account_name = account.to_string();
+            tx.line_number = day.to_string();
+            tx.metadata.clear();
+            tx.id = uuid::Uuid::new_v4().to_string();
+            insert_transaction_row(&conn, &tx, &tx.compute_idempotency_hash()).unwrap();
+        }
+
+        let balances = account_balances(&conn, "default", None).unwrap();
+        assert_eq!(balances.len(), 2);
+        assert_eq!((balances[0].account.as_str(), balances[0].stored, balances[0].rows), ("BofA Checking", true, 2));
+        assert!((balances[0].balance - 2954.5).abs() < 1e-9);
+        assert_eq!((balances[1].account.as_str(), balances[1].stored, balances[1].balance), ("Savings", false, -20.0));
+
+        let january = account_balances(&conn, "default", Some(date("2025-01-31"))).unwrap();
+        assert_eq!(january.len(), 1);
+        assert!(account_balances(&conn, "other", None).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,380 @@
+// 🔗 Aliases - Merchant spellings grouped for review
+//
+// Problem solved:
+// - One merchant shows up as "SHELL OIL 5521", "Shell Oil 12" and "SHEL OIL":
+//   reports split its spending and rules had to list every spelling
+// - Building the merchant registry by hand meant reading thousands of strings
+//
+// `propose_alias_groups` runs offline over every distinct merchant string of
+// a ledger: strings with the same key (registry normalization, store numbers
+// dropped) form a group, and groups are then linked when their keys are
+// nearly equal (edit-distance similarity) or one key starts the other word by
+// word. Each proposal names the most frequent spelling as canonical. A
+// reviewer confirms (optionally choosing another canonical name) or rejects
+// it; decisions are stored by group key, so a decided group stays hidden until
+// its spellings change, and confirmed groups seed the merchant registry
+// (bootstrap.rs).
+
+use crate::db::{insert_event, Event};
+use crate::entities::merchant::{levenshtein_distance, normalize_merchant_string};
+use crate::event_schema::AliasesDecided;
+use crate::jobs::ledger_transactions;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Lowest edit-distance similarity (0.0-1.0) that links two keys
+pub const LINK_SIMILARITY: f64 = 0.85;
+
+/// Shortest key that may link by prefix ("shell" → "shell oil")
+const MIN_PREFIX_LEN: usize = 4;
+
+// ============================================================================
+// CLUSTERING
+// ============================================================================
+
+/// One distinct merchant string and how many current rows carry it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MerchantSpelling {
+    pub name: String,
+    pub rows: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AliasGroup {
+    /// Stable id: hash of the sorted spellings
+    pub key: String,
+
+    /// Proposed canonical name (the most frequent spelling)
+    pub canonical: String,
+
+    /// Members, most frequent first
+    pub spellings: Vec<MerchantSpelling>,
+
+    /// Lowest similarity between the canonical key and a member's key
+    pub similarity: f64,
+}
+
+impl AliasGroup {
+    pub fn names(&self) -> Vec<String> {
+        self.spellings.iter().map(|spelling| spelling.name.clone()).collect()
+    }
+
+    pub fn rows(&self) -> usize {
+        self.spellings.iter().map(|spelling| spelling.rows).sum()
+    }
+}
+
+/// Group key: order-independent hash of the spellings
+pub fn alias_group_key(names: &[String]) -> String {
+    let mut sorted = names.to_vec();
+    sorted.sort();
+    format!("{:x}", Sha256::digest(sorted.join("\n").as_bytes()))
+}
+
+/// Normalized name without store/terminal numbers ("shell oil 5521" → "shell oil")
+pub fn spelling_key(name: &str) -> String {
+    normalize_merchant_string(name)
+        .split_whitespace()
+        .filter(|word| !word.chars().all(|c| c.is_ascii_digit() || c == '#' || c == '*'))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Edit-distance similarity of two keys (1.0 = equal)
+pub fn key_similarity(a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein_distance(a, b) as f64 / longest as f64
+}
+
+/// Whether the shorter key's words start the longer key's words
+fn word_prefix(a: &str, b: &str) -> bool {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    short.len() >= MIN_PREFIX_LEN && long.starts_with(short) && long[short.len()..].starts_with(' ')
+}
+
+fn linked(a: &str, b: &str) -> bool {
+    key_similarity(a, b) >= LINK_SIMILARITY || word_prefix(a, b)
+}
+
+/// Union-find root of `i`
+fn root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// Alias groups (two or more spellings) among `spellings`, largest first
+pub fn cluster_spellings(spellings: &[MerchantSpelling]) -> Vec<AliasGroup> {
+    // 1. Same key: one node
+    let mut by_key: BTreeMap<String, Vec<&MerchantSpelling>> = BTreeMap::new();
+    for spelling in spellings {
+        let key = spelling_key(&spelling.name);
+        if !key.is_empty() {
+            by_key.entry(key).or_default().push(spelling);
+        }
+    }
+    let keys: Vec<&String> = by_key.keys().collect();
+
+    // 2. Fuzzy linkage between keys (single linkage)
+    let mut parents: Vec<usize> = (0..keys.len()).collect();
+    for i in 0..keys.len() {
+        for j in i + 1..keys.len() {
+            if linked(keys[i], keys[j]) {
+                let (a, b) = (root(&mut parents, i), root(&mut parents, j));
+                parents[b] = a;
+            }
+        }
+    }
+    let mut components: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..keys.len() {
+        let component = root(&mut parents, i);
+        components.entry(component).or_default().push(i);
+    }
+
+    let mut groups: Vec<AliasGroup> = components
+        .into_values()
+        .filter_map(|members| {
+            let mut spellings: Vec<(MerchantSpelling, &str)> = members
+                .iter()
+                .flat_map(|&i| {
+                    let key = keys[i].as_str();
+                    by_key[key].iter().map(move |spelling| ((*spelling).clone(), key))
+                })
+                .collect();
+            if spellings.len() < 2 {
+                return None;
+            }
+            spellings.sort_by(|(a, _), (b, _)| {
+                b.rows.cmp(&a.rows).then_with(|| a.name.len().cmp(&b.name.len())).then_with(|| a.name.cmp(&b.name))
+            });
+
+            let canonical_key = spellings[0].1;
+            let similarity = spellings
+                .iter()
+                .map(|(_, key)| key_similarity(canonical_key, key))
+                .fold(1.0, f64::min);
+            let spellings: Vec<MerchantSpelling> = spellings.into_iter().map(|(spelling, _)| spelling).collect();
+            let names: Vec<String> = spellings.iter().map(|spelling| spelling.name.clone()).collect();
+            Some(AliasGroup {
+                key: alias_group_key(&names),
+                canonical: names[0].clone(),
+                spellings,
+                similarity,
+            })
+        })
+        .collect();
+
+    groups.sort_by(|a, b| b.rows().cmp(&a.rows()).then_with(|| a.canonical.cmp(&b.canonical)));
+    groups
+}
+
+/// Distinct merchant strings of a ledger's current rows
+pub fn merchant_spellings(conn: &Connection, ledger_id: &str) -> Result<Vec<MerchantSpelling>> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for tx in ledger_transactions(conn, ledger_id)? {
+        let name = tx.merchant.trim();
+        if !name.is_empty() && !name.eq_ignore_ascii_case("unknown") {
+            *counts.entry(name.to_string()).or_default() += 1;
+        }
+    }
+    Ok(counts.into_iter().map(|(name, rows)| MerchantSpelling { name, rows }).collect())
+}
+
+/// Alias groups of a ledger that still need a decision
+pub fn propose_alias_groups(conn: &Connection, ledger_id: &str) -> Result<Vec<AliasGroup>> {
+    let mut pending = Vec::new();
+    for group in cluster_spellings(&merchant_spellings(conn, ledger_id)?) {
+        if get_alias_decision(conn, ledger_id, &group.key)?.is_none() {
+            pending.push(group);
+        }
+    }
+    Ok(pending)
+}
+
+// ============================================================================
+// DECISIONS
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AliasDecision {
+    /// Same merchant: the spellings are aliases of the canonical name
+    Confirmed,
+    /// Different merchants
+    Rejected,
+}
+
+impl AliasDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AliasDecision::Confirmed => "confirmed",
+            AliasDecision::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "confirmed" => Ok(AliasDecision::Confirmed),
+            "rejected" => Ok(AliasDecision::Rejected),
+            other => Err(anyhow!("Unknown alias decision: {}", other)),
+        }
+    }
+}
+
+/// A confirmed group: `canonical` plus the other spellings
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfirmedAliases {
+    pub ledger_id: String,
+    pub canonical: String,
+    pub aliases: Vec<String>,
+}
+
+/// Record a decision for a group; `canonical` overrides the proposed name
+pub fn record_alias_decision(
+    conn: &Connection,
+    ledger_id: &str,
+    group: &AliasGroup,
+    decision: AliasDecision,
+    canonical: Option<&str>,
+    actor: &str,
+) -> Result<()> {
+    let canonical = canonical.map(str::trim).unwrap_or(&group.canonical);
+    if canonical.is_empty() {
+        return Err(anyhow!("Canonical merchant name must not be empty"));
+    }
+
+    conn.execute(
+        "INSERT OR REPLACE INTO alias_decisions
+            (group_key, ledger_id, decision, canonical, names, decided_by, decided_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            group.key,
+            ledger_id,
+            decision.as_str(),
+            canonical,
+            serde_json::to_string(&group.names())?,
+            actor,
+            Utc::now().to_rfc3339(),
+        ],
+    )?;
+
+    let payload = AliasesDecided { canonical: canonical.to_string(), names: group.names() };
+    let event = Event::typed(&format!("aliases_{}", decision.as_str()), "alias_group", &group.key, &payload, actor)?
+        .with_ledger(ledger_id);
+    insert_event(conn, &event)
+}
+
+pub fn get_alias_decision(conn: &Connection, ledger_id: &str, group_key: &str) -> Result<Option<AliasDecision>> {
+    let decision: Option<String> = conn
+        .query_row(
+            "SELECT decision FROM alias_decisions WHERE ledger_id = ?1 AND group_key = ?2",
+            params![ledger_id, group_key],
+            |row| row.get(0),
+        )
+        .optional()?;
+    decision.map(|decision| AliasDecision::parse(&decision)).transpose()
+}
+
+/// Confirmed groups (of one ledger, or all when None), by canonical name
+pub fn confirmed_alias_groups(conn: &Connection, ledger_id: Option<&str>) -> Result<Vec<ConfirmedAliases>> {
+    let mut stmt = conn.prepare(
+        "SELECT ledger_id, canonical, names FROM alias_decisions
+         WHERE decision = 'confirmed' AND (?1 IS NULL OR ledger_id = ?1)
+         ORDER BY canonical, group_key",
+    )?;
+    let rows = stmt.query_map([ledger_id], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+    })?;
+
+    let mut groups = Vec::new();
+    for row in rows {
+        let (ledger_id, canonical, names) = row?;
+        let names: Vec<String> = serde_json::from_str(&names)?;
+        let aliases = names.into_iter().filter(|name| *name != canonical).collect();
+        groups.push(ConfirmedAliases { ledger_id, canonical, aliases });
+    }
+    Ok(groups)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bootstrap::bootstrap_entities;
+    use crate::db::setup_database;
+    use crate::imports::import_statement;
+
+    fn spelling(name: &str, rows: usize) -> MerchantSpelling {
+        MerchantSpelling { name: name.to_string(), rows }
+    }
+
+    #[test]
+    fn test_clusters_by_key_typos_and_prefix() {
+        let groups = cluster_spellings(&[
+            spelling("SHELL OIL 5521", 4),
+            spelling("Shell Oil #12", 2),
+            spelling("SHEL OIL", 1),
+            spelling("Shell", 1),
+            spelling("Starbucks", 3),
+            spelling("Netflix.com", 2),
+            spelling("Amazon", 5),
+        ]);
+        assert_eq!(groups.len(), 1);
+        let shell = &groups[0];
+        assert_eq!(shell.canonical, "SHELL OIL 5521");
+        assert_eq!(shell.spellings.len(), 4);
+        assert!(shell.similarity < 1.0);
+
+        // Same spellings in another order: same key
+        let mut names = shell.names();
+        names.reverse();
+        assert_eq!(alias_group_key(&names), shell.key);
+        assert_eq!(spelling_key("SHELL OIL 5521"), "shell oil");
+    }
+
+    #[test]
+    fn test_decided_groups_are_hidden_and_confirmed_ones_listed() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let csv = "Date,Description,Amount\n\
+            01/02/2025,SHELL OIL 5521,-40.00\n\
+            01/09/2025,SHELL OIL 12,-35.00\n\
+            01/10/2025,STARBUCKS STORE 123,-5.25\n";
+        import_statement(&conn, "bofa.csv", csv.as_bytes(), "default", "ana").unwrap();
+        conn.execute(
+            "UPDATE transactions SET merchant = description WHERE description LIKE 'SHELL%'",
+            [],
+        )
+        .unwrap();
+
+        let groups = propose_alias_groups(&conn, "default").unwrap();
+        assert_eq!(groups.len(), 1);
+        record_alias_decision(&conn, "default", &groups[0], AliasDecision::Confirmed, Some("Shell"), "ana").unwrap();
+
+        assert!(propose_alias_groups(&conn, "default").unwrap().is_empty());
+        let confirmed = confirmed_alias_groups(&conn, Some("default")).unwrap();
+        assert_eq!(confirmed.len(), 1);
+        assert_eq!(confirmed[0].canonical, "Shell");
+        assert_eq!(confirmed[0].aliases.len(), 2);
+        assert!(confirmed_alias_groups(&conn, Some("business")).unwrap().is_empty());
+        assert_eq!(get_alias_decision(&conn, "default", &groups[0].key).unwrap(), Some(AliasDecision::Confirmed));
+
+        // Confirmed spellings seed the merchant registry
+        let entities = bootstrap_entities(&conn).unwrap();
+        let shell = entities.merchants.find_by_string("SHELL OIL 12").unwrap();
+        assert_eq!(shell.canonical_name, "Shell");
+        assert_eq!(shell.aliases.len(), 2);
+    }
+}
@@ -0,0 +1,234 @@
+// 📊 Analytics - What the ledger says about one merchant
+//
+// Problem solved:
+// - "How much have I spent at Amazon, and is it going up?" meant filtering the
+//   ledger by hand and adding the rows up
+// - Nothing showed which cards a merchant is charged on or which categories
+//   its transactions ended up in
+//
+// `merchant_profile` gathers the transactions a registry merchant matches (its
+// canonical name and aliases) and sums them: lifetime spend, a month-by-month
+// trend, the average ticket, first/last seen, and the categories and accounts
+// involved. Spend is what left the accounts: charges minus refunds, in the
+// statements' own currencies.
+
+use crate::db::Transaction;
+use crate::entities::{Merchant, MerchantRegistry};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+// ============================================================================
+// PROFILE
+// ============================================================================
+
+/// Spend in one calendar month ("2025-01")
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonthlySpend {
+    pub month: String,
+    pub spend: f64,
+    pub transactions: usize,
+}
+
+/// Transactions of the merchant in one category
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CategoryUsage {
+    pub category: String,
+    pub spend: f64,
+    pub transactions: usize,
+}
+
+/// Transactions of the merchant on one account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountUsage {
+    pub bank: String,
+    pub account_name: String,
+    pub account_number: String,
+    pub spend: f64,
+    pub transactions: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MerchantProfile {
+    pub merchant_id: String,
+    pub merchant: String,
+    pub transactions: usize,
+    /// Charges minus refunds over every matching transaction
+    pub lifetime_spend: f64,
+    /// Mean of the charges (refunds and credits left out)
+    pub average_ticket: f64,
+    pub first_seen: Option<NaiveDate>,
+    pub last_seen: Option<NaiveDate>,
+    /// Every month from first to last seen, months without spend included
+    pub monthly: Vec<MonthlySpend>,
+    /// Most used first
+    pub categories: Vec<CategoryUsage>,
+    /// Most used first
+    pub accounts: Vec<AccountUsage>,
+}
+
+/// Profile of the registry merchant `merchant_id` over `transactions`
+/// (None when the registry doesn't know the id)
+pub fn merchant_profile(
+    registry: &MerchantRegistry,
+    merchant_id: &str,
+    transactions: &[Transaction],
+) -> Option<MerchantProfile> {
+    registry.find_by_id(merchant_id).map(|merchant| profile_merchant(&merchant, transactions))
+}
+
+/// Profile of `merchant` over the active `transactions` it matches
+pub fn profile_merchant(merchant: &Merchant, transactions: &[Transaction]) -> MerchantProfile {
+    let matching: Vec<&Transaction> = transactions
+        .iter()
+        .filter(|tx| tx.is_active() && !tx.merchant.trim().is_empty() && merchant.matches(&tx.merchant))
+        .collect();
+
+    let spend = |txs: &[&Transaction]| txs.iter().fold(0.0, |total, tx| total - tx.amount_numeric);
+    let charges: Vec<&Transaction> = matching.iter().copied().filter(|tx| tx.amount_numeric < 0.0).collect();
+    let average_ticket = match charges.len() {
+        0 => 0.0,
+        n => spend(&charges) / n as f64,
+    };
+
+    let dates: Vec<NaiveDate> = matching.iter().filter_map(|tx| tx.parsed_date()).collect();
+    let (first_seen, last_seen) = (dates.iter().min().copied(), dates.iter().max().copied());
+
+    let mut months: BTreeMap<String, Vec<&Transaction>> = BTreeMap::new();
+    if let (Some(first), Some(last)) = (first_seen, last_seen) {
+        let mut month = first.with_day(1).unwrap_or(first);
+        while month <= last {
+            months.insert(month_key(month), Vec::new());
+            month = month.checked_add_months(chrono::Months::new(1)).unwrap_or(NaiveDate::MAX);
+        }
+    }
+    for tx in &matching {
+        if let Some(date) = tx.parsed_date() {
+            months.entry(month_key(date)).or_default().push(tx);
+        }
+    }
+    let monthly = months
+        .into_iter()
+        .map(|(month, txs)| MonthlySpend { month, spend: spend(&txs), transactions: txs.len() })
+        .collect();
+
+    let mut categories: BTreeMap<&str, Vec<&Transaction>> = BTreeMap::new();
+    let mut accounts: BTreeMap<(&str, &str, &str), Vec<&Transaction>> = BTreeMap::new();
+    for tx in &matching {
+        categories.entry(tx.category.trim()).or_default().push(tx);
+        accounts
+            .entry((tx.bank.as_str(), tx.account_name.as_str(), tx.account_number.as_str()))
+            .or_default()
+            .push(tx);
+    }
+    let mut categories: Vec<CategoryUsage> = categories
+        .into_iter()
+        .map(|(category, txs)| CategoryUsage {
+            category: category.to_string(),
+            spend: spend(&txs),
+            transactions: txs.len(),
+        })
+        .collect();
+    categories.sort_by_key(|usage| Reverse(usage.transactions));
+    let mut accounts: Vec<AccountUsage> = accounts
+        .into_iter()
+        .map(|((bank, account_name, account_number), txs)| AccountUsage {
+            bank: bank.to_string(),
+            account_name: account_name.to_string(),
+            account_number: account_number.to_string(),
+            spend: spend(&txs),
+            transactions: txs.len(),
+        })
+        .collect();
+    accounts.sort_by_key(|usage| Reverse(usage.transactions));
+
+    MerchantProfile {
+        merchant_id: merchant.id.clone(),
+        merchant: merchant.canonical_name.clone(),
+        transactions: matching.len(),
+        lifetime_spend: spend(&matching),
+        average_ticket,
+        first_seen,
+        last_seen,
+        monthly,
+        categories,
+        accounts,
+    }
+}
+
+fn month_key(date: NaiveDate) -> String {
+    format!("{:04}-{:02}", date.year(), date.month())
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::entities::MerchantType;
+
+    fn tx(date: &str, amount: f64, category: &str, account_number: &str) -> Transaction {
+        Transaction {
+            date: date.to_string(),
+            description: "AMAZON MKTPLACE".to_string(),
+            amount_original: format!("{:.2}", amount),
+            amount_numeric: amount,
+            transaction_type: if amount < 0.0 { "GASTO" } else { "INGRESO" }.to_string(),
+            category: category.to_string(),
+            merchant: "AMAZON.COM".to_string(),
+            account_number: account_number.to_string(),
+            ..test_support::sample_transaction()
+        }
+    }
+
+    #[test]
+    fn test_profile_sums_spend_trend_and_usage() {
+        let mut amazon = Merchant::new("Amazon".to_string(), MerchantType::Retail, Some("Shopping".to_string()));
+        amazon.add_alias("AMAZON.COM".to_string());
+        let mut transactions = vec![
+            tx("01/15/2025", -30.0, "Shopping", "1234"),
+            tx("01/20/2025", -10.0, "Shopping", "1234"),
+            tx("03/02/2025", -20.0, "Books", "9999"),
+            tx("03/05/2025", 10.0, "Shopping", "1234"),
+        ];
+        let mut other = tx("02/01/2025", -99.0, "Coffee", "1234");
+        other.merchant = "Blue Bottle".to_string();
+        transactions.push(other);
+
+        let profile = profile_merchant(&amazon, &transactions);
+        assert_eq!(profile.transactions, 4);
+        assert_eq!(profile.lifetime_spend, 50.0);
+        assert_eq!(profile.average_ticket, 20.0);
+        assert_eq!(profile.first_seen, NaiveDate::from_ymd_opt(2025, 1, 15));
+        assert_eq!(profile.last_seen, NaiveDate::from_ymd_opt(2025, 3, 5));
+
+        let trend: Vec<(&str, f64)> = profile.monthly.iter().map(|m| (m.month.as_str(), m.spend)).collect();
+        assert_eq!(trend, vec![("2025-01", 40.0), ("2025-02", 0.0), ("2025-03", 10.0)]);
+
+        assert_eq!(profile.categories[0].category, "Shopping");
+        assert_eq!((profile.categories[0].transactions, profile.categories[0].spend), (3, 30.0));
+        assert_eq!(profile.accounts.len(), 2);
+        assert_eq!(profile.accounts[1].account_number, "9999");
+    }
+
+    #[test]
+    fn test_profile_by_registry_id() {
+        let registry = MerchantRegistry::with_defaults();
+        let id = registry.get_id("AMAZON.COM").unwrap();
+        let mut superseded = tx("01/15/2025", -500.0, "Shopping", "1234");
+        superseded.valid_until = Some(chrono::Utc::now());
+        let transactions = vec![tx("01/15/2025", -30.0, "Shopping", "1234"), superseded];
+
+        let profile = merchant_profile(&registry, &id, &transactions).unwrap();
+        assert_eq!((profile.transactions, profile.lifetime_spend), (1, 30.0));
+        assert!(merchant_profile(&registry, "no-such-merchant", &transactions).is_none());
+
+        let empty = merchant_profile(&registry, &id, &[]).unwrap();
+        assert_eq!((empty.first_seen, empty.average_ticket), (None, 0.0));
+        assert!(empty.monthly.is_empty());
+    }
+}
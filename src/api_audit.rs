@@ -0,0 +1,112 @@
+// 🚦 API Audit - Rate limits and an audit trail for API writes
+//
+// Problem solved:
+// - A script holding a token could hammer the write endpoints with nothing
+//   slowing it down
+// - Only changes that happened to write their own event (corrections, voids)
+//   showed up in the audit trail; a rejected or failed API call left no trace
+//
+// `RateLimiter` counts each token's writes in a sliding one-minute window.
+// `record_api_call` stores one `api_request` event per mutating call, with the
+// token's user as actor, whatever the outcome.
+
+use crate::db::{insert_event, Event};
+use crate::event_schema::ApiRequestLogged;
+use anyhow::Result;
+use rusqlite::Connection;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Writes each token may make per window when the config doesn't say
+pub const DEFAULT_WRITES_PER_MINUTE: u32 = 60;
+
+/// POST, PUT, PATCH and DELETE change state; everything else is a read
+pub fn is_mutating(method: &str) -> bool {
+    matches!(method.to_ascii_uppercase().as_str(), "POST" | "PUT" | "PATCH" | "DELETE")
+}
+
+/// Sliding-window limiter keyed by token (user)
+pub struct RateLimiter {
+    max_requests: u32,
+    window: Duration,
+    /// key → times of the requests still inside the window, oldest first
+    requests: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    pub fn new(max_requests: u32, window: Duration) -> Self {
+        RateLimiter { max_requests, window, requests: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn per_minute(max_requests: u32) -> Self {
+        Self::new(max_requests, Duration::from_secs(60))
+    }
+
+    /// Count a request for `key` at `now`; Err(wait) if the limit is reached
+    /// (rejected requests are not counted)
+    pub fn check(&self, key: &str, now: Instant) -> std::result::Result<(), Duration> {
+        let mut requests = self.requests.lock().unwrap();
+        let recent = requests.entry(key.to_string()).or_default();
+        while recent.front().is_some_and(|&time| now.duration_since(time) >= self.window) {
+            recent.pop_front();
+        }
+
+        if recent.len() >= self.max_requests as usize {
+            let oldest = recent.front().copied().unwrap_or(now);
+            return Err(self.window.saturating_sub(now.duration_since(oldest)));
+        }
+        recent.push_back(now);
+        Ok(())
+    }
+}
+
+/// Log one mutating API call as an `api_request` event
+pub fn record_api_call(conn: &Connection, actor: &str, method: &str, path: &str, status: u16) -> Result<()> {
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let payload = ApiRequestLogged { method: method.to_string(), path: path.to_string(), status };
+    let event = Event::typed("api_request", "api", &request_id, &payload, actor)?;
+    insert_event(conn, &event)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::setup_database;
+
+    #[test]
+    fn test_rate_limiter_is_per_key_and_slides() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(limiter.check("ana", start).is_ok());
+        assert!(limiter.check("ana", start + Duration::from_secs(10)).is_ok());
+        let wait = limiter.check("ana", start + Duration::from_secs(20)).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(40));
+        assert!(limiter.check("bob", start + Duration::from_secs(20)).is_ok());
+
+        // The first request has left the window
+        assert!(limiter.check("ana", start + Duration::from_secs(61)).is_ok());
+        assert!(limiter.check("ana", start + Duration::from_secs(62)).is_err());
+    }
+
+    #[test]
+    fn test_record_api_call() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        assert!(is_mutating("post") && !is_mutating("GET"));
+
+        record_api_call(&conn, "bookkeeper", "POST", "/api/transactions/abc/void", 403).unwrap();
+        let (actor, data): (String, String) = conn
+            .query_row("SELECT actor, data FROM events WHERE event_type = 'api_request'", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(actor, "bookkeeper");
+        assert!(data.contains("\"status\":403"));
+    }
+}
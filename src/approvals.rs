@@ -0,0 +1,505 @@
+// ✅ Approvals - Four-eyes check for large corrections and voids
+//
+// Problem solved:
+// - On shared/business ledgers one person could silently rewrite a large transaction
+//
+// When a ledger has `approval_threshold` set, corrections and voids of
+// transactions at or above that amount are stored as a pending change. The new
+// version only becomes current once a SECOND actor approves it. Undo and redo
+// append a restored version and go through the same check as a correction.
+
+use crate::db::{
+    build_redo_version, build_undo_version, build_void_version, get_current_transaction, insert_event,
+    write_transaction_version, Event, Transaction,
+};
+use crate::event_schema::PendingChangeLogged;
+use crate::ledger::get_ledger;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// TYPES
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Correction,
+    Void,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Correction => "correction",
+            ChangeKind::Void => "void",
+        }
+    }
+
+    /// Event type written when the change is applied
+    fn event_type(&self) -> &'static str {
+        match self {
+            ChangeKind::Correction => "transaction_corrected",
+            ChangeKind::Void => "transaction_voided",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl ChangeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeStatus::Pending => "pending",
+            ChangeStatus::Approved => "approved",
+            ChangeStatus::Rejected => "rejected",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "approved" => ChangeStatus::Approved,
+            "rejected" => ChangeStatus::Rejected,
+            _ => ChangeStatus::Pending,
+        }
+    }
+}
+
+/// A proposed transaction version waiting for a second actor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingChange {
+    pub id: String,
+    pub kind: ChangeKind,
+    pub status: ChangeStatus,
+
+    /// Transaction identity and the version the proposal was based on
+    pub tx_uuid: String,
+    pub ledger_id: String,
+    pub base_version: i64,
+
+    /// The version that becomes current on approval
+    pub proposed: Transaction,
+
+    pub proposed_by: String,
+    pub proposed_at: DateTime<Utc>,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub decision_note: Option<String>,
+}
+
+/// What happened to a submitted correction or void
+#[derive(Debug, Clone)]
+pub enum WriteOutcome {
+    /// Written immediately (below threshold or no threshold configured)
+    Applied(Transaction),
+
+    /// Stored for approval by another actor
+    Pending(PendingChange),
+}
+
+impl WriteOutcome {
+    pub fn is_pending(&self) -> bool {
+        matches!(self, WriteOutcome::Pending(_))
+    }
+}
+
+// ============================================================================
+// SUBMISSION
+// ============================================================================
+
+/// Check if changing `tx` needs a second actor under its ledger's config
+pub fn requires_approval(conn: &Connection, tx: &Transaction) -> Result<bool> {
+    let threshold = get_ledger(conn, &tx.ledger_id)?.and_then(|l| l.config.approval_threshold);
+    Ok(threshold.is_some_and(|limit| tx.amount_numeric.abs() >= limit))
+}
+
+/// Apply a corrected version, or propose it if it needs approval
+///
+/// `next` must come from `current.next_version(..)`.
+pub fn submit_correction(conn: &Connection, next: &Transaction, actor: &str) -> Result<WriteOutcome> {
+    submit(conn, ChangeKind::Correction, next, actor)
+}
+
+/// Void a transaction, or propose the void if it needs approval
+pub fn submit_void(conn: &Connection, tx_uuid: &str, reason: &str, actor: &str) -> Result<WriteOutcome> {
+    let next = build_void_version(conn, tx_uuid, reason, actor)?;
+    submit(conn, ChangeKind::Void, &next, actor)
+}
+
+/// Undo the last change, or propose the restored version if it needs approval
+pub fn submit_undo(conn: &Connection, tx_uuid: &str, actor: &str) -> Result<WriteOutcome> {
+    let next = build_undo_version(conn, tx_uuid)?;
+    submit(conn, ChangeKind::Correction, &next, actor)
+}
+
+/// Redo the last undone change, or propose it if it needs approval
+pub fn submit_redo(conn: &Connection, tx_uuid: &str, actor: &str) -> Result<WriteOutcome> {
+    let next = build_redo_version(conn, tx_uuid)?;
+    submit(conn, ChangeKind::Correction, &next, actor)
+}
+
+fn submit(conn: &Connection, kind: ChangeKind, next: &Transaction, actor: &str) -> Result<WriteOutcome> {
+    if requires_approval(conn, next)? {
+        return Ok(WriteOutcome::Pending(propose_change(conn, kind, next, actor)?));
+    }
+
+    write_transaction_version(conn, next, actor, kind.event_type())?;
+    Ok(WriteOutcome::Applied(next.clone()))
+}
+
+/// Store a proposed version for approval (one open proposal per transaction)
+pub fn propose_change(
+    conn: &Connection,
+    kind: ChangeKind,
+    next: &Transaction,
+    actor: &str,
+) -> Result<PendingChange> {
+    let open: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pending_changes WHERE tx_uuid = ?1 AND status = 'pending'",
+        [&next.id],
+        |row| row.get(0),
+    )?;
+    if open > 0 {
+        return Err(anyhow!("Transaction {} already has a pending change", next.id));
+    }
+
+    let change = PendingChange {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind,
+        status: ChangeStatus::Pending,
+        tx_uuid: next.id.clone(),
+        ledger_id: next.ledger_id.clone(),
+        base_version: next.version - 1,
+        proposed: next.clone(),
+        proposed_by: actor.to_string(),
+        proposed_at: Utc::now(),
+        decided_by: None,
+        decided_at: None,
+        decision_note: None,
+    };
+
+    conn.execute(
+        "INSERT INTO pending_changes (
+            id, kind, status, tx_uuid, ledger_id, base_version, proposed,
+            proposed_by, proposed_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            change.id,
+            change.kind.as_str(),
+            change.status.as_str(),
+            change.tx_uuid,
+            change.ledger_id,
+            change.base_version,
+            serde_json::to_string(&change.proposed)?,
+            change.proposed_by,
+            change.proposed_at.to_rfc3339(),
+        ],
+    )?;
+
+    log_change_event(conn, "change_proposed", &change, actor)?;
+    Ok(change)
+}
+
+// ============================================================================
+// DECISIONS
+// ============================================================================
+
+/// Approve a pending change: its version becomes current
+///
+/// The approver must differ from the proposer, and the transaction must not
+/// have changed since the proposal (otherwise the proposal is stale).
+pub fn approve_change(conn: &Connection, change_id: &str, actor: &str) -> Result<Transaction> {
+    let change = require_open_change(conn, change_id, actor)?;
+
+    let current = get_current_transaction(conn, &change.tx_uuid)?
+        .ok_or_else(|| anyhow!("Transaction {} no longer exists", change.tx_uuid))?;
+    if current.version != change.base_version {
+        return Err(anyhow!(
+            "Change {} is stale: transaction is at v{}, proposal was based on v{}",
+            change.id,
+            current.version,
+            change.base_version
+        ));
+    }
+
+    write_transaction_version(conn, &change.proposed, actor, change.kind.event_type())?;
+    record_decision(conn, &change, ChangeStatus::Approved, actor, None)?;
+
+    Ok(change.proposed)
+}
+
+/// Reject a pending change: nothing is written to the transaction
+pub fn reject_change(
+    conn: &Connection,
+    change_id: &str,
+    actor: &str,
+    note: Option<&str>,
+) -> Result<PendingChange> {
+    let change = require_open_change(conn, change_id, actor)?;
+    record_decision(conn, &change, ChangeStatus::Rejected, actor, note)?;
+    get_pending_change(conn, change_id)?.ok_or_else(|| anyhow!("Change {} not found", change_id))
+}
+
+fn require_open_change(conn: &Connection, change_id: &str, actor: &str) -> Result<PendingChange> {
+    let change = get_pending_change(conn, change_id)?
+        .ok_or_else(|| anyhow!("Change {} not found", change_id))?;
+
+    if change.status != ChangeStatus::Pending {
+        return Err(anyhow!("Change {} is already {}", change_id, change.status.as_str()));
+    }
+    if change.proposed_by == actor {
+        return Err(anyhow!("Change {} must be decided by someone other than its proposer", change_id));
+    }
+
+    Ok(change)
+}
+
+fn record_decision(
+    conn: &Connection,
+    change: &PendingChange,
+    status: ChangeStatus,
+    actor: &str,
+    note: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE pending_changes
+         SET status = ?1, decided_by = ?2, decided_at = ?3, decision_note = ?4
+         WHERE id = ?5",
+        params![status.as_str(), actor, Utc::now().to_rfc3339(), note, change.id],
+    )?;
+
+    let event_type = match status {
+        ChangeStatus::Approved => "change_approved",
+        _ => "change_rejected",
+    };
+    log_change_event(conn, event_type, change, actor)
+}
+
+fn log_change_event(conn: &Connection, event_type: &str, change: &PendingChange, actor: &str) -> Result<()> {
+    let payload = PendingChangeLogged {
+        kind: change.kind,
+        tx_uuid: change.tx_uuid.clone(),
+        base_version: change.base_version,
+        amount: change.proposed.amount_numeric,
+        proposed_by: change.proposed_by.clone(),
+    };
+    let event = Event::typed(event_type, "pending_change", &change.id, &payload, actor)?.with_ledger(&change.ledger_id);
+    insert_event(conn, &event)
+}
+
+// ============================================================================
+// QUERIES
+// ============================================================================
+
+const PENDING_CHANGE_COLUMNS: &str = "id, kind, status, tx_uuid, ledger_id, base_version, proposed,
+    proposed_by, proposed_at, decided_by, decided_at, decision_note";
+
+pub fn get_pending_change(conn: &Connection, change_id: &str) -> Result<Option<PendingChange>> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT {} FROM pending_changes WHERE id = ?1", PENDING_CHANGE_COLUMNS),
+            [change_id],
+            row_to_pending_change,
+        )
+        .optional()?)
+}
+
+/// Open proposals, oldest first (optionally for one ledger)
+pub fn list_pending_changes(conn: &Connection, ledger_id: Option<&str>) -> Result<Vec<PendingChange>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM pending_changes
+         WHERE status = 'pending' AND (?1 IS NULL OR ledger_id = ?1)
+         ORDER BY proposed_at ASC",
+        PENDING_CHANGE_COLUMNS
+    ))?;
+
+    let changes = stmt
+        .query_map([ledger_id], row_to_pending_change)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(changes)
+}
+
+fn row_to_pending_change(row: &rusqlite::Row) -> rusqlite::Result<PendingChange> {
+    fn parse_time(s: &str) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(s).ok().map(|dt| dt.with_timezone(&Utc))
+    }
+
+    let kind: String = row.get(1)?;
+    let status: String = row.get(2)?;
+    let proposed_json: String = row.get(6)?;
+    let proposed_at: String = row.get(8)?;
+    let decided_at: Option<String> = row.get(10)?;
+
+    let proposed = serde_json::from_str(&proposed_json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+
+    Ok(PendingChange {
+        id: row.get(0)?,
+        kind: if kind == "void" { ChangeKind::Void } else { ChangeKind::Correction },
+        status: ChangeStatus::parse(&status),
+        tx_uuid: row.get(3)?,
+        ledger_id: row.get(4)?,
+        base_version: row.get(5)?,
+        proposed,
+        proposed_by: row.get(7)?,
+        proposed_at: parse_time(&proposed_at).unwrap_or_else(Utc::now),
+        decided_by: row.get(9)?,
+        decided_at: decided_at.as_deref().and_then(parse_time),
+        decision_note: row.get(11)?,
+    })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::db::{get_transaction_history, insert_transactions_as, setup_database};
+    use crate::ledger::{create_ledger, update_ledger_config, Ledger};
+
+    fn business_transaction(amount: f64) -> Transaction {
+        let mut tx = Transaction {
+            date: "04/01/2025".to_string(),
+            description: "WIRE TRANSFER".to_string(),
+            amount_original: format!("${:.2}", amount),
+            amount_numeric: amount,
+            category: "Contractors".to_string(),
+            merchant: "ACME".to_string(),
+            account_name: "Business Checking".to_string(),
+            account_number: "9999".to_string(),
+            source_file: "business.csv".to_string(),
+            ledger_id: "business".to_string(),
+            ..test_support::sample_transaction()
+        };
+        tx.init_temporal_fields();
+        tx
+    }
+
+    fn setup_business_ledger(conn: &Connection, threshold: f64) {
+        setup_database(conn).unwrap();
+        let mut ledger = Ledger::new("business", "Business");
+        create_ledger(conn, &ledger, "owner").unwrap();
+        ledger.config.approval_threshold = Some(threshold);
+        update_ledger_config(conn, "business", &ledger.config, "owner").unwrap();
+    }
+
+    #[test]
+    fn test_small_correction_applies_immediately() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_business_ledger(&conn, 1000.0);
+
+        let tx = business_transaction(-50.0);
+        insert_transactions_as(&conn, std::slice::from_ref(&tx), "test").unwrap();
+
+        let mut next = tx.next_version(Some("fix category".to_string()));
+        next.category = "Software".to_string();
+        let outcome = submit_correction(&conn, &next, "bookkeeper").unwrap();
+
+        assert!(!outcome.is_pending());
+        assert_eq!(get_transaction_history(&conn, &tx.id).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_large_correction_needs_second_actor() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_business_ledger(&conn, 1000.0);
+
+        let tx = business_transaction(-5000.0);
+        insert_transactions_as(&conn, std::slice::from_ref(&tx), "test").unwrap();
+
+        let mut next = tx.next_version(Some("recategorize".to_string()));
+        next.category = "Equipment".to_string();
+        let change = match submit_correction(&conn, &next, "bookkeeper").unwrap() {
+            WriteOutcome::Pending(change) => change,
+            WriteOutcome::Applied(_) => panic!("expected pending change"),
+        };
+
+        // Nothing written yet; one proposal per transaction
+        assert_eq!(get_current_transaction(&conn, &tx.id).unwrap().unwrap().category, "Contractors");
+        assert!(submit_correction(&conn, &next, "bookkeeper").is_err());
+        assert_eq!(list_pending_changes(&conn, Some("business")).unwrap().len(), 1);
+
+        // Proposer cannot approve their own change
+        assert!(approve_change(&conn, &change.id, "bookkeeper").is_err());
+
+        let applied = approve_change(&conn, &change.id, "owner").unwrap();
+        assert_eq!(applied.category, "Equipment");
+        assert_eq!(get_current_transaction(&conn, &tx.id).unwrap().unwrap().version, 2);
+        assert!(list_pending_changes(&conn, None).unwrap().is_empty());
+        assert!(approve_change(&conn, &change.id, "owner").is_err());
+    }
+
+    #[test]
+    fn test_rejected_and_stale_changes() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_business_ledger(&conn, 1000.0);
+
+        let tx = business_transaction(-2500.0);
+        insert_transactions_as(&conn, std::slice::from_ref(&tx), "test").unwrap();
+
+        let void = match submit_void(&conn, &tx.id, "duplicate wire", "bookkeeper").unwrap() {
+            WriteOutcome::Pending(change) => change,
+            WriteOutcome::Applied(_) => panic!("expected pending void"),
+        };
+        let rejected = reject_change(&conn, &void.id, "owner", Some("not a duplicate")).unwrap();
+        assert_eq!(rejected.status, ChangeStatus::Rejected);
+        assert_eq!(rejected.decision_note.as_deref(), Some("not a duplicate"));
+        assert!(!get_current_transaction(&conn, &tx.id).unwrap().unwrap().is_voided());
+
+        // A proposal goes stale if the transaction moves on before approval
+        let mut next = tx.next_version(Some("recategorize".to_string()));
+        next.category = "Travel".to_string();
+        let change = propose_change(&conn, ChangeKind::Correction, &next, "bookkeeper").unwrap();
+        let mut other = tx.next_version(Some("direct fix".to_string()));
+        other.category = "Meals".to_string();
+        write_transaction_version(&conn, &other, "owner", "transaction_corrected").unwrap();
+        assert!(approve_change(&conn, &change.id, "owner").is_err());
+
+        let events: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM events WHERE entity_type = 'pending_change'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(events, 3); // void proposed, void rejected, correction proposed
+    }
+
+    #[test]
+    fn test_undo_of_approved_void_needs_second_actor() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_business_ledger(&conn, 1000.0);
+
+        let tx = business_transaction(-5000.0);
+        insert_transactions_as(&conn, std::slice::from_ref(&tx), "test").unwrap();
+
+        let void = match submit_void(&conn, &tx.id, "duplicate wire", "bookkeeper").unwrap() {
+            WriteOutcome::Pending(change) => change,
+            WriteOutcome::Applied(_) => panic!("expected pending void"),
+        };
+        approve_change(&conn, &void.id, "owner").unwrap();
+
+        // One editor cannot quietly restore the voided row
+        let undo = match submit_undo(&conn, &tx.id, "bookkeeper").unwrap() {
+            WriteOutcome::Pending(change) => change,
+            WriteOutcome::Applied(_) => panic!("expected pending undo"),
+        };
+        assert!(get_current_transaction(&conn, &tx.id).unwrap().unwrap().is_voided());
+
+        let restored = approve_change(&conn, &undo.id, "owner").unwrap();
+        assert!(!restored.is_voided());
+        assert!(submit_redo(&conn, &tx.id, "bookkeeper").unwrap().is_pending());
+    }
+}
@@ -0,0 +1,266 @@
+// 🗃️ Source Archive - The original statement files, addressed by SHA-256
+//
+// Problem solved:
+// - A transaction said which file and line it came from, but the file itself
+//   was gone (uploads were parsed from a temp dir, then deleted), so "show me
+//   the bank's own line for this charge" had no answer
+// - Two uploads of the same bytes under different names looked unrelated
+//
+// Every import stores a gzip copy of the file it read, keyed by the SHA-256 of
+// the original bytes (the same bytes are stored once). Each imported row gets
+// `source_sha256` and `source_line` in its metadata, next to the parser
+// provenance, so `transaction_provenance` can re-open the archived file and
+// return the exact line (CLI: `provenance show <tx_id>`). Reads verify the
+// hash, so a tampered archive is reported instead of trusted.
+
+use crate::db::{get_current_transaction, Transaction};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+
+/// Metadata key holding the SHA-256 of the archived source file
+pub const SOURCE_HASH_KEY: &str = "source_sha256";
+
+/// Metadata key holding the row's line (JSON: record number) in that file
+pub const SOURCE_LINE_KEY: &str = "source_line";
+
+/// SHA-256 of `content`, hex
+pub fn content_sha256(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Point a transaction at its line of an archived file
+pub fn tag_source(tx: &mut Transaction, sha256: &str, line: usize) {
+    tx.metadata.insert(SOURCE_HASH_KEY.to_string(), serde_json::json!(sha256));
+    tx.metadata.insert(SOURCE_LINE_KEY.to_string(), serde_json::json!(line));
+}
+
+// ============================================================================
+// ARCHIVE
+// ============================================================================
+
+/// An archived file (content decompressed and verified)
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchivedSource {
+    pub sha256: String,
+    /// Name of the first upload of these bytes
+    pub filename: String,
+    /// Original size in bytes
+    pub size: usize,
+    pub archived_at: DateTime<Utc>,
+    #[serde(skip)]
+    pub content: Vec<u8>,
+}
+
+/// Archive a file's bytes; returns their SHA-256 (no-op if already archived)
+pub fn archive_source(conn: &Connection, filename: &str, content: &[u8]) -> Result<String> {
+    let sha256 = content_sha256(content);
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content)?;
+
+    conn.execute(
+        "INSERT OR IGNORE INTO source_archive (sha256, filename, size, content, archived_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![sha256, filename, content.len() as i64, encoder.finish()?, Utc::now().to_rfc3339()],
+    )?;
+    Ok(sha256)
+}
+
+/// The archived file with this hash; Err if its content no longer matches
+pub fn get_archived_source(conn: &Connection, sha256: &str) -> Result<Option<ArchivedSource>> {
+    let row = conn
+        .query_row(
+            "SELECT filename, size, content, archived_at FROM source_archive WHERE sha256 = ?1",
+            [sha256],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, Vec<u8>>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            },
+        )
+        .optional()?;
+    let Some((filename, size, compressed, archived_at)) = row else {
+        return Ok(None);
+    };
+
+    let mut content = Vec::new();
+    GzDecoder::new(compressed.as_slice()).read_to_end(&mut content)?;
+    if content_sha256(&content) != sha256 {
+        return Err(anyhow!("Archived copy of {} ({}) does not match its hash", filename, sha256));
+    }
+
+    Ok(Some(ArchivedSource {
+        sha256: sha256.to_string(),
+        filename,
+        size: size as usize,
+        archived_at: DateTime::parse_from_rfc3339(&archived_at)?.with_timezone(&Utc),
+        content,
+    }))
+}
+
+/// Archived files, newest first (without content)
+pub fn list_archived_sources(conn: &Connection) -> Result<Vec<ArchivedSource>> {
+    let mut stmt = conn.prepare("SELECT sha256, filename, size, archived_at FROM source_archive ORDER BY archived_at DESC")?;
+    let sources = stmt
+        .query_map([], |row| {
+            let archived_at: String = row.get(3)?;
+            Ok(ArchivedSource {
+                sha256: row.get(0)?,
+                filename: row.get(1)?,
+                size: row.get::<_, i64>(2)? as usize,
+                archived_at: DateTime::parse_from_rfc3339(&archived_at)
+                    .map(|dt| dt.with_timezone(&Utc))
+                    .unwrap_or_else(|_| Utc::now()),
+                content: Vec::new(),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(sources)
+}
+
+/// The text of one source row: line `line` (1-based) of a text file, or
+/// record `line` of the `data` array of a JSON export (Stripe)
+pub fn source_excerpt(content: &[u8], line: usize) -> Option<String> {
+    let index = line.checked_sub(1)?;
+    if let Ok(json) = serde_json::from_slice::<Value>(content) {
+        let records = json.get("data").unwrap_or(&json).as_array()?;
+        return records.get(index).map(Value::to_string);
+    }
+    String::from_utf8_lossy(content)
+        .lines()
+        .nth(index)
+        .map(|text| text.trim_end_matches('\r').to_string())
+}
+
+// ============================================================================
+// PROVENANCE
+// ============================================================================
+
+/// Where a transaction came from, down to the source line
+#[derive(Debug, Clone, Serialize)]
+pub struct Provenance {
+    /// Current version
+    pub transaction: Transaction,
+    pub source_file: String,
+    pub parser_version: Option<String>,
+    pub extracted_at: Option<String>,
+    pub source_sha256: Option<String>,
+    pub source_line: Option<usize>,
+    /// The archived file, when the row points at one that is archived
+    pub archived: Option<ArchivedSource>,
+    /// First line of the archived file (CSV header), for context
+    pub header: Option<String>,
+    /// The row as the bank wrote it
+    pub excerpt: Option<String>,
+}
+
+/// Provenance of a transaction (None if the id is unknown)
+pub fn transaction_provenance(conn: &Connection, tx_id: &str) -> Result<Option<Provenance>> {
+    let Some(tx) = get_current_transaction(conn, tx_id)? else {
+        return Ok(None);
+    };
+    let text = |key: &str| tx.metadata.get(key).and_then(Value::as_str).map(str::to_string);
+    let source_sha256 = text(SOURCE_HASH_KEY);
+    let source_line = tx.metadata.get(SOURCE_LINE_KEY).and_then(Value::as_u64).map(|line| line as usize);
+
+    let archived = match &source_sha256 {
+        Some(sha256) => get_archived_source(conn, sha256)?,
+        None => None,
+    };
+    let (header, excerpt) = match (&archived, source_line) {
+        (Some(source), Some(line)) => {
+            let is_json = serde_json::from_slice::<Value>(&source.content).is_ok();
+            let header = if is_json { None } else { source_excerpt(&source.content, 1) };
+            (header, source_excerpt(&source.content, line))
+        }
+        _ => (None, None),
+    };
+
+    Ok(Some(Provenance {
+        source_file: tx.source_file.clone(),
+        parser_version: text("parser_version"),
+        extracted_at: text("extracted_at"),
+        source_sha256,
+        source_line,
+        archived,
+        header,
+        excerpt,
+        transaction: tx,
+    }))
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::setup_database;
+    use crate::imports::import_statement;
+    use crate::jobs::ledger_transactions;
+
+    const BOFA: &str = "Date,Description,Amount\n\
+        01/02/2025,STARBUCKS STORE 123,-5.25\n\
+        01/03/2025,SHELL OIL 5521,-40.00\n";
+
+    #[test]
+    fn test_archive_is_content_addressed_and_verified() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let sha256 = archive_source(&conn, "bofa_jan.csv", BOFA.as_bytes()).unwrap();
+        assert_eq!(archive_source(&conn, "copy.csv", BOFA.as_bytes()).unwrap(), sha256);
+        let sources = list_archived_sources(&conn).unwrap();
+        assert_eq!(sources.len(), 1);
+        assert_eq!((sources[0].filename.as_str(), sources[0].size), ("bofa_jan.csv", BOFA.len()));
+
+        let source = get_archived_source(&conn, &sha256).unwrap().unwrap();
+        assert_eq!(source.content, BOFA.as_bytes());
+        assert!(get_archived_source(&conn, "nope").unwrap().is_none());
+
+        // Swap in other (valid gzip) bytes under the same hash
+        let other = archive_source(&conn, "other.csv", b"something else").unwrap();
+        conn.execute(
+            "UPDATE source_archive SET content = (SELECT content FROM source_archive WHERE sha256 = ?1) WHERE sha256 = ?2",
+            params![other, sha256],
+        )
+        .unwrap();
+        assert!(get_archived_source(&conn, &sha256).is_err());
+
+        let stripe = br#"{"object":"list","data":[{"id":"txn_1"},{"id":"txn_2"}]}"#;
+        assert_eq!(source_excerpt(stripe, 2).as_deref(), Some(r#"{"id":"txn_2"}"#));
+        assert_eq!(source_excerpt(BOFA.as_bytes(), 3).as_deref(), Some("01/03/2025,SHELL OIL 5521,-40.00"));
+        assert!(source_excerpt(BOFA.as_bytes(), 0).is_none());
+    }
+
+    #[test]
+    fn test_imported_rows_point_at_their_source_line() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        import_statement(&conn, "bofa_jan.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+
+        let shell = ledger_transactions(&conn, "default")
+            .unwrap()
+            .into_iter()
+            .find(|tx| tx.description.contains("SHELL"))
+            .unwrap();
+        let provenance = transaction_provenance(&conn, &shell.id).unwrap().unwrap();
+        assert_eq!(provenance.source_sha256.as_deref(), Some(content_sha256(BOFA.as_bytes()).as_str()));
+        assert_eq!(provenance.source_line, Some(3));
+        assert_eq!(provenance.header.as_deref(), Some("Date,Description,Amount"));
+        assert_eq!(provenance.excerpt.as_deref(), Some("01/03/2025,SHELL OIL 5521,-40.00"));
+        assert!(provenance.parser_version.is_some());
+        assert!(transaction_provenance(&conn, "nope").unwrap().is_none());
+    }
+}
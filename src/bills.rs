@@ -0,0 +1,585 @@
+// 📅 Bills - Expected transactions matched against imports
+//
+// Problem solved:
+// - Rent is due on the 1st and Netflix on the 15th, but nothing noticed when
+//   one of them didn't show up in the statement
+// - "What's still coming this month?" meant remembering every recurring bill
+//
+// An expected transaction says WHAT should appear (merchant text, amount within
+// a tolerance) and WHEN (day of month, with a grace window). Each month's
+// occurrence is matched against the imported transactions: matched, upcoming
+// (grace window still open) or missed.
+//
+// A bill is also how a merchant is flagged as recurring. When its charge moves
+// between two cycles by more than the tolerance (a Netflix price hike),
+// `record_price_changes` stores the change in the bill's amount history, moves
+// the bill to the new amount and logs a "bill_price_changed" event, which
+// reaches any webhook subscribed to it (outbox.rs).
+
+use crate::db::{insert_event, Event, Transaction};
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// Days before/after the due date a payment still counts as this bill
+pub const DEFAULT_GRACE_DAYS: i64 = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExpectedTransaction {
+    pub id: String,
+    pub ledger_id: String,
+
+    /// e.g. "Rent", "Netflix"
+    pub name: String,
+
+    /// Substring of merchant or description (case-insensitive)
+    pub merchant_pattern: String,
+
+    /// Expected signed amount (negative = money out, like amount_numeric)
+    pub amount: f64,
+
+    /// Accepted absolute difference from `amount`
+    pub tolerance: f64,
+
+    pub currency: String,
+
+    /// 1-31; clamped to the last day in shorter months
+    pub day_of_month: u32,
+
+    pub grace_days: i64,
+}
+
+impl ExpectedTransaction {
+    pub fn new(name: &str, merchant_pattern: &str, amount: f64, currency: &str, day_of_month: u32) -> Self {
+        ExpectedTransaction {
+            id: uuid::Uuid::new_v4().to_string(),
+            ledger_id: crate::ledger::default_ledger_id(),
+            name: name.to_string(),
+            merchant_pattern: merchant_pattern.to_string(),
+            amount,
+            tolerance: 0.0,
+            currency: currency.to_string(),
+            day_of_month,
+            grace_days: DEFAULT_GRACE_DAYS,
+        }
+    }
+
+    /// Place this bill in a ledger (builder)
+    pub fn in_ledger(mut self, ledger_id: &str) -> Self {
+        self.ledger_id = ledger_id.to_string();
+        self
+    }
+
+    /// Accept amounts within `tolerance` of the expected amount (builder)
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance.abs();
+        self
+    }
+
+    /// Change the grace window around the due date (builder)
+    pub fn with_grace_days(mut self, grace_days: i64) -> Self {
+        self.grace_days = grace_days.max(0);
+        self
+    }
+
+    /// Due date in a given month (day 31 → 30th/28th in shorter months)
+    pub fn due_date(&self, year: i32, month: u32) -> Option<NaiveDate> {
+        let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+        let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+        first.with_day(self.day_of_month.clamp(1, last.day()))
+    }
+
+    /// Same ledger and currency, merchant text and amount within tolerance
+    pub fn matches(&self, tx: &Transaction) -> bool {
+        self.matches_merchant(tx) && (tx.amount_numeric - self.amount).abs() <= self.tolerance + 0.005
+    }
+
+    /// Same ledger and currency and merchant text, whatever the amount
+    pub fn matches_merchant(&self, tx: &Transaction) -> bool {
+        let pattern = self.merchant_pattern.to_lowercase();
+        tx.ledger_id == self.ledger_id
+            && tx.currency.eq_ignore_ascii_case(&self.currency)
+            && (tx.merchant.to_lowercase().contains(&pattern)
+                || tx.description.to_lowercase().contains(&pattern))
+    }
+}
+
+// ============================================================================
+// STORAGE
+// ============================================================================
+
+pub fn create_expected_transaction(conn: &Connection, bill: &ExpectedTransaction, actor: &str) -> Result<()> {
+    if bill.name.trim().is_empty() || bill.merchant_pattern.trim().is_empty() {
+        return Err(anyhow!("A bill needs a name and a merchant pattern"));
+    }
+    if !(1..=31).contains(&bill.day_of_month) {
+        return Err(anyhow!("Day of month must be 1-31, got {}", bill.day_of_month));
+    }
+
+    conn.execute(
+        "INSERT INTO expected_transactions
+            (id, ledger_id, name, merchant_pattern, amount, tolerance, currency, day_of_month, grace_days, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            bill.id,
+            bill.ledger_id,
+            bill.name,
+            bill.merchant_pattern,
+            bill.amount,
+            bill.tolerance,
+            bill.currency,
+            bill.day_of_month,
+            bill.grace_days,
+            Utc::now().to_rfc3339(),
+        ],
+    )?;
+
+    let event = Event::typed("bill_created", "bill", &bill.id, bill, actor)?
+        .with_ledger(&bill.ledger_id);
+    insert_event(conn, &event)
+}
+
+/// Stop expecting a bill (past months are no longer reported either)
+pub fn remove_expected_transaction(conn: &Connection, bill_id: &str, actor: &str) -> Result<()> {
+    let bill = get_expected_transaction(conn, bill_id)?
+        .ok_or_else(|| anyhow!("Bill {} not found", bill_id))?;
+    conn.execute("DELETE FROM expected_transactions WHERE id = ?1", [bill_id])?;
+    conn.execute("DELETE FROM bill_price_changes WHERE bill_id = ?1", [bill_id])?;
+
+    let event = Event::typed("bill_removed", "bill", &bill.id, &bill, actor)?
+        .with_ledger(&bill.ledger_id);
+    insert_event(conn, &event)
+}
+
+const BILL_COLUMNS: &str =
+    "id, ledger_id, name, merchant_pattern, amount, tolerance, currency, day_of_month, grace_days";
+
+pub fn get_expected_transaction(conn: &Connection, bill_id: &str) -> Result<Option<ExpectedTransaction>> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT {} FROM expected_transactions WHERE id = ?1", BILL_COLUMNS),
+            [bill_id],
+            row_to_bill,
+        )
+        .optional()?)
+}
+
+/// Bills in a ledger, by due day
+pub fn list_expected_transactions(conn: &Connection, ledger_id: &str) -> Result<Vec<ExpectedTransaction>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM expected_transactions WHERE ledger_id = ?1 ORDER BY day_of_month, name",
+        BILL_COLUMNS
+    ))?;
+    let bills = stmt.query_map([ledger_id], row_to_bill)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(bills)
+}
+
+fn row_to_bill(row: &rusqlite::Row) -> rusqlite::Result<ExpectedTransaction> {
+    Ok(ExpectedTransaction {
+        id: row.get(0)?,
+        ledger_id: row.get(1)?,
+        name: row.get(2)?,
+        merchant_pattern: row.get(3)?,
+        amount: row.get(4)?,
+        tolerance: row.get(5)?,
+        currency: row.get(6)?,
+        day_of_month: row.get(7)?,
+        grace_days: row.get(8)?,
+    })
+}
+
+// ============================================================================
+// MATCHING
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BillStatus {
+    /// A matching transaction was imported
+    Matched,
+    /// Not seen yet, but the grace window is still open
+    Upcoming,
+    /// Grace window passed without a matching transaction
+    Missed,
+}
+
+impl BillStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BillStatus::Matched => "matched",
+            BillStatus::Upcoming => "upcoming",
+            BillStatus::Missed => "missed",
+        }
+    }
+}
+
+/// One month's occurrence of a bill
+#[derive(Debug, Clone, Serialize)]
+pub struct BillOccurrence {
+    pub bill: ExpectedTransaction,
+    pub due_date: NaiveDate,
+    pub status: BillStatus,
+
+    /// Matching transaction (when status is Matched)
+    pub tx_uuid: Option<String>,
+    pub actual_amount: Option<f64>,
+}
+
+/// Occurrences of every bill due between `from` and `to` (inclusive), as of `today`
+///
+/// Each transaction pays at most one occurrence; when several match, the one
+/// closest to the due date wins. Voided transactions never match.
+pub fn bill_occurrences(
+    bills: &[ExpectedTransaction],
+    transactions: &[Transaction],
+    from: NaiveDate,
+    to: NaiveDate,
+    today: NaiveDate,
+) -> Vec<BillOccurrence> {
+    let dated: Vec<(NaiveDate, &Transaction)> = transactions
+        .iter()
+        .filter(|tx| tx.is_active() && !tx.is_voided())
+        .filter_map(|tx| tx.parsed_date().map(|date| (date, tx)))
+        .collect();
+
+    let mut used: HashSet<&str> = HashSet::new();
+    let mut occurrences = Vec::new();
+
+    for bill in bills {
+        let mut month = NaiveDate::from_ymd_opt(from.year(), from.month(), 1);
+        while let Some(month_start) = month.filter(|m| *m <= to) {
+            month = month_start.checked_add_months(Months::new(1));
+            let Some(due_date) = bill.due_date(month_start.year(), month_start.month()) else {
+                continue;
+            };
+            if due_date < from || due_date > to {
+                continue;
+            }
+
+            let matched = dated
+                .iter()
+                .filter(|(date, tx)| {
+                    (*date - due_date).num_days().abs() <= bill.grace_days
+                        && !used.contains(tx.id.as_str())
+                        && bill.matches(tx)
+                })
+                .min_by_key(|(date, _)| (*date - due_date).num_days().abs());
+
+            let occurrence = match matched {
+                Some((_, tx)) => {
+                    used.insert(tx.id.as_str());
+                    BillOccurrence {
+                        bill: bill.clone(),
+                        due_date,
+                        status: BillStatus::Matched,
+                        tx_uuid: Some(tx.id.clone()),
+                        actual_amount: Some(tx.amount_numeric),
+                    }
+                }
+                None => {
+                    let window_closed = today > due_date + chrono::Duration::days(bill.grace_days);
+                    BillOccurrence {
+                        bill: bill.clone(),
+                        due_date,
+                        status: if window_closed { BillStatus::Missed } else { BillStatus::Upcoming },
+                        tx_uuid: None,
+                        actual_amount: None,
+                    }
+                }
+            };
+            occurrences.push(occurrence);
+        }
+    }
+
+    occurrences.sort_by(|a, b| a.due_date.cmp(&b.due_date).then_with(|| a.bill.name.cmp(&b.bill.name)));
+    occurrences
+}
+
+// ============================================================================
+// PRICE CHANGES
+// ============================================================================
+
+/// A bill's charge moving from one cycle to the next
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BillPriceChange {
+    pub bill_id: String,
+    pub bill_name: String,
+    /// First charge at the new amount
+    pub tx_uuid: String,
+    pub effective_date: NaiveDate,
+    pub previous_amount: f64,
+    pub new_amount: f64,
+}
+
+impl BillPriceChange {
+    /// Change in size relative to the previous amount (0.10 = charged 10% more)
+    pub fn percent_change(&self) -> f64 {
+        if self.previous_amount == 0.0 {
+            return 0.0;
+        }
+        (self.new_amount.abs() - self.previous_amount.abs()) / self.previous_amount.abs()
+    }
+}
+
+/// One charge per month (the one closest to the due date), oldest first:
+/// the bill's merchant and currency, same direction as the bill, any amount
+fn cycle_charges<'a>(
+    bill: &ExpectedTransaction,
+    transactions: &'a [Transaction],
+) -> Vec<(NaiveDate, &'a Transaction)> {
+    let mut cycles: BTreeMap<(i32, u32), (NaiveDate, &Transaction)> = BTreeMap::new();
+    for tx in transactions.iter().filter(|tx| tx.is_active() && bill.matches_merchant(tx)) {
+        let Some(date) = tx.parsed_date() else {
+            continue;
+        };
+        if tx.amount_numeric == 0.0 || tx.amount_numeric.signum() != bill.amount.signum() {
+            continue;
+        }
+        let distance = |date: NaiveDate| {
+            bill.due_date(date.year(), date.month()).map_or(i64::MAX, |due| (date - due).num_days().abs())
+        };
+        cycles
+            .entry((date.year(), date.month()))
+            .and_modify(|kept| {
+                if distance(date) < distance(kept.0) {
+                    *kept = (date, tx);
+                }
+            })
+            .or_insert((date, tx));
+    }
+    cycles.into_values().collect()
+}
+
+/// Every cycle whose charge differs from the previous cycle's by more than
+/// the bill's tolerance, oldest first
+pub fn detect_price_changes(bill: &ExpectedTransaction, transactions: &[Transaction]) -> Vec<BillPriceChange> {
+    let cycles = cycle_charges(bill, transactions);
+    cycles
+        .windows(2)
+        .filter(|pair| (pair[1].1.amount_numeric - pair[0].1.amount_numeric).abs() > bill.tolerance + 0.005)
+        .map(|pair| BillPriceChange {
+            bill_id: bill.id.clone(),
+            bill_name: bill.name.clone(),
+            tx_uuid: pair[1].1.id.clone(),
+            effective_date: pair[1].0,
+            previous_amount: pair[0].1.amount_numeric,
+            new_amount: pair[1].1.amount_numeric,
+        })
+        .collect()
+}
+
+/// Store the price changes not seen before in the bill's amount history,
+/// expect the latest amount from now on and log a "bill_price_changed" event
+/// per change; returns the new changes
+pub fn record_price_changes(
+    conn: &Connection,
+    bill: &ExpectedTransaction,
+    transactions: &[Transaction],
+    actor: &str,
+) -> Result<Vec<BillPriceChange>> {
+    let known: HashSet<String> = bill_amount_history(conn, &bill.id)?
+        .into_iter()
+        .map(|change| change.tx_uuid)
+        .collect();
+    let detected = detect_price_changes(bill, transactions);
+    let latest = detected.last().map(|change| change.new_amount);
+    let new_changes: Vec<BillPriceChange> =
+        detected.into_iter().filter(|change| !known.contains(&change.tx_uuid)).collect();
+
+    for change in &new_changes {
+        conn.execute(
+            "INSERT INTO bill_price_changes
+                (bill_id, tx_uuid, bill_name, effective_date, previous_amount, new_amount, detected_by, detected_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                change.bill_id,
+                change.tx_uuid,
+                change.bill_name,
+                change.effective_date.to_string(),
+                change.previous_amount,
+                change.new_amount,
+                actor,
+                Utc::now().to_rfc3339(),
+            ],
+        )?;
+        let event = Event::typed("bill_price_changed", "bill", &bill.id, change, actor)?
+            .with_ledger(&bill.ledger_id);
+        insert_event(conn, &event)?;
+    }
+    if let Some(amount) = latest.filter(|_| !new_changes.is_empty()) {
+        conn.execute("UPDATE expected_transactions SET amount = ?1 WHERE id = ?2", params![amount, bill.id])?;
+    }
+    Ok(new_changes)
+}
+
+/// Price changes recorded for a bill, oldest first
+pub fn bill_amount_history(conn: &Connection, bill_id: &str) -> Result<Vec<BillPriceChange>> {
+    let mut stmt = conn.prepare(
+        "SELECT bill_id, bill_name, tx_uuid, effective_date, previous_amount, new_amount
+         FROM bill_price_changes WHERE bill_id = ?1 ORDER BY effective_date, detected_at",
+    )?;
+    let rows = stmt
+        .query_map([bill_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, f64>(4)?,
+                row.get::<_, f64>(5)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    rows.into_iter()
+        .map(|(bill_id, bill_name, tx_uuid, effective_date, previous_amount, new_amount)| {
+            Ok(BillPriceChange {
+                bill_id,
+                bill_name,
+                tx_uuid,
+                effective_date: NaiveDate::parse_from_str(&effective_date, "%Y-%m-%d")?,
+                previous_amount,
+                new_amount,
+            })
+        })
+        .collect()
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::db::setup_database;
+
+    fn charge(date: &str, description: &str, amount: f64) -> Transaction {
+        let mut tx = Transaction {
+            date: date.to_string(),
+            description: description.to_string(),
+            amount_original: format!("{:.2}", amount),
+            amount_numeric: amount,
+            category: String::new(),
+            ..test_support::sample_transaction()
+        };
+        tx.init_temporal_fields();
+        tx
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_matched_upcoming_and_missed() {
+        let rent = ExpectedTransaction::new("Rent", "LANDLORD", -1500.0, "USD", 1);
+        let netflix = ExpectedTransaction::new("Netflix", "netflix", -15.49, "USD", 15).with_tolerance(1.0);
+        let transactions = vec![
+            charge("01/02/2025", "LANDLORD LLC ACH", -1500.0),
+            charge("01/15/2025", "NETFLIX.COM", -15.99),
+            charge("02/01/2025", "LANDLORD LLC ACH", -1500.0),
+        ];
+
+        let occurrences = bill_occurrences(
+            &[rent, netflix],
+            &transactions,
+            date("2025-01-01"),
+            date("2025-02-28"),
+            date("2025-02-20"),
+        );
+        let statuses: Vec<(&str, &str)> = occurrences
+            .iter()
+            .map(|o| (o.bill.name.as_str(), o.status.as_str()))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("Rent", "matched"),
+                ("Netflix", "matched"),
+                ("Rent", "matched"),
+                ("Netflix", "missed"),
+            ]
+        );
+
+        // Same window, earlier "today": February's Netflix is still upcoming
+        let occurrences = bill_occurrences(
+            &[ExpectedTransaction::new("Netflix", "netflix", -15.49, "USD", 15)],
+            &transactions,
+            date("2025-02-01"),
+            date("2025-02-28"),
+            date("2025-02-10"),
+        );
+        assert_eq!(occurrences[0].status, BillStatus::Upcoming);
+    }
+
+    #[test]
+    fn test_amount_tolerance_and_short_months() {
+        let bill = ExpectedTransaction::new("Gym", "gym", -40.0, "USD", 31).with_tolerance(2.0);
+        assert_eq!(bill.due_date(2025, 2), Some(date("2025-02-28")));
+        assert_eq!(bill.due_date(2024, 4), Some(date("2024-04-30")));
+
+        assert!(bill.matches(&charge("01/31/2025", "CITY GYM", -41.5)));
+        assert!(!bill.matches(&charge("01/31/2025", "CITY GYM", -45.0)));
+        assert!(!bill.matches(&charge("01/31/2025", "GROCERY", -40.0)));
+    }
+
+    #[test]
+    fn test_store_and_list_bills() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let rent = ExpectedTransaction::new("Rent", "LANDLORD", -1500.0, "USD", 1);
+        create_expected_transaction(&conn, &rent, "ana").unwrap();
+        create_expected_transaction(&conn, &ExpectedTransaction::new("Netflix", "NETFLIX", -15.49, "USD", 15), "ana")
+            .unwrap();
+        assert!(create_expected_transaction(&conn, &ExpectedTransaction::new("Bad", "X", -1.0, "USD", 40), "ana").is_err());
+
+        let bills = list_expected_transactions(&conn, "default").unwrap();
+        assert_eq!(bills.iter().map(|b| b.name.as_str()).collect::<Vec<_>>(), vec!["Rent", "Netflix"]);
+        assert_eq!(bills[0], rent);
+
+        remove_expected_transaction(&conn, &rent.id, "ana").unwrap();
+        assert_eq!(list_expected_transactions(&conn, "default").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_price_change_is_recorded_once() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let netflix = ExpectedTransaction::new("Netflix", "netflix", -15.49, "USD", 15);
+        create_expected_transaction(&conn, &netflix, "ana").unwrap();
+
+        let mut transactions = vec![
+            charge("01/15/2025", "NETFLIX.COM", -15.49),
+            charge("02/15/2025", "NETFLIX.COM", -15.49),
+            charge("02/20/2025", "NETFLIX.COM REFUND", 15.49),
+            charge("03/16/2025", "NETFLIX.COM", -17.99),
+            charge("04/15/2025", "NETFLIX.COM", -17.99),
+        ];
+        for (i, tx) in transactions.iter_mut().enumerate() {
+            tx.id = format!("tx-{}", i);
+        }
+
+        let changes = record_price_changes(&conn, &netflix, &transactions, "ana").unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].previous_amount, changes[0].new_amount), (-15.49, -17.99));
+        assert_eq!(changes[0].tx_uuid, "tx-3");
+        assert_eq!(changes[0].effective_date, date("2025-03-16"));
+        assert!((changes[0].percent_change() - 0.1614).abs() < 0.001);
+
+        // The series now expects the new price and carries its history
+        let bill = get_expected_transaction(&conn, &netflix.id).unwrap().unwrap();
+        assert_eq!(bill.amount, -17.99);
+        assert_eq!(bill_amount_history(&conn, &netflix.id).unwrap(), changes);
+        let events = crate::db::get_events_for_entity(&conn, "bill", &netflix.id).unwrap();
+        let logged = events.iter().find(|e| e.event_type == "bill_price_changed").unwrap();
+        assert_eq!(logged.payload::<BillPriceChange>().unwrap(), changes[0]);
+
+        // Scanning again finds nothing new
+        assert!(record_price_changes(&conn, &bill, &transactions, "ana").unwrap().is_empty());
+    }
+}
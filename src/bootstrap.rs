@@ -0,0 +1,333 @@
+// 🌱 Bootstrap - Entities for a database that predates the registries
+//
+// Problem solved:
+// - The merchant, bank and account registries start with a handful of
+//   built-in entries, so a ledger imported before they existed resolved
+//   almost nothing until every merchant and account was entered by hand
+// - The data to build them was already there, in the imported transactions
+//
+// `bootstrap_entities` scans the current transactions and fills the
+// registries: one merchant per cluster of merchant strings (strings that the
+// registry's own fuzzy matching considers the same become aliases, the most
+// frequent spelling is canonical; its suggested category comes from
+// merchant_categories.rs), one bank per bank name nobody knows yet,
+// and one account per (bank, account) seen, next to the accounts already
+// stored with opening balances (accounts.rs). Alias groups a reviewer
+// confirmed (aliases.rs) are registered first, so their spellings land on the
+// chosen merchant. Entities that already match are only given the new
+// aliases. The scan starts from the saved registries (entities/store.rs), so
+// ids stay stable across runs; the scan itself writes nothing, saving the
+// result is up to the caller (`TrustSystem::save_entities`).
+
+use crate::accounts::list_accounts;
+use crate::aliases::{confirmed_alias_groups, ConfirmedAliases};
+use crate::db::{get_active_transactions, Transaction};
+use crate::entities::{
+    load_accounts, load_banks, load_merchants, Account, AccountRegistry, AccountType, Bank, BankRegistry, BankType,
+    Merchant, MerchantRegistry, MerchantType,
+};
+use crate::ledger::list_ledgers;
+use crate::merchant_categories::suggest_missing_categories;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// What a bootstrap added to the registries
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BootstrapReport {
+    pub merchants_added: usize,
+    /// Spellings added to new or existing merchants
+    pub aliases_added: usize,
+    /// Merchants given a suggested category (merchant_categories.rs)
+    pub categories_suggested: usize,
+    pub banks_added: usize,
+    pub accounts_added: usize,
+}
+
+/// Registries built from a database
+pub struct EntityBootstrap {
+    pub merchants: MerchantRegistry,
+    pub banks: BankRegistry,
+    pub accounts: AccountRegistry,
+    pub report: BootstrapReport,
+}
+
+/// Saved registries (or the defaults, see entities/store.rs) plus the stored
+/// accounts, filled from every current transaction of every ledger
+pub fn bootstrap_entities(conn: &rusqlite::Connection) -> Result<EntityBootstrap> {
+    let mut merchants = load_merchants(conn)?;
+    let mut banks = load_banks(conn)?;
+    let mut accounts = load_accounts(conn)?;
+    for ledger in list_ledgers(conn)? {
+        for account in list_accounts(conn, &ledger.id)? {
+            if accounts.find_by_id(&account.id).is_none() {
+                accounts.register(account);
+            }
+        }
+    }
+
+    let mut confirmed = BootstrapReport::default();
+    register_confirmed_aliases(&mut merchants, &confirmed_alias_groups(conn, None)?, &mut confirmed);
+
+    let transactions = get_active_transactions(conn)?;
+    let scanned = bootstrap_registries(&transactions, &mut merchants, &mut banks, &mut accounts);
+    let report = BootstrapReport {
+        merchants_added: confirmed.merchants_added + scanned.merchants_added,
+        aliases_added: confirmed.aliases_added + scanned.aliases_added,
+        ..scanned
+    };
+    Ok(EntityBootstrap { merchants, banks, accounts, report })
+}
+
+/// Fill the registries from `transactions` (voided rows are ignored)
+pub fn bootstrap_registries(
+    transactions: &[Transaction],
+    merchants: &mut MerchantRegistry,
+    banks: &mut BankRegistry,
+    accounts: &mut AccountRegistry,
+) -> BootstrapReport {
+    let active: Vec<&Transaction> = transactions.iter().filter(|tx| tx.is_active()).collect();
+    let mut report = BootstrapReport::default();
+    bootstrap_merchants(&active, merchants, &mut report);
+    report.categories_suggested = suggest_missing_categories(merchants, transactions).len();
+    bootstrap_banks(&active, banks, &mut report);
+    bootstrap_accounts(&active, banks, accounts, &mut report);
+    report
+}
+
+// ============================================================================
+// MERCHANTS
+// ============================================================================
+
+/// Rows of one merchant spelling
+struct Spelling<'a> {
+    name: &'a str,
+    rows: Vec<&'a Transaction>,
+}
+
+fn known_merchant(name: &str) -> bool {
+    !name.is_empty() && !name.eq_ignore_ascii_case("unknown")
+}
+
+/// Merchants (or aliases of known ones) from reviewed alias groups
+pub(crate) fn register_confirmed_aliases(
+    registry: &mut MerchantRegistry,
+    groups: &[ConfirmedAliases],
+    report: &mut BootstrapReport,
+) {
+    for group in groups {
+        match registry.find_by_string(&group.canonical) {
+            Some(existing) => {
+                let known = existing.all_names();
+                let missing: Vec<&String> = group.aliases.iter().filter(|alias| !known.contains(alias)).collect();
+                if missing.is_empty() {
+                    continue;
+                }
+                report.aliases_added += missing.len();
+                let _ = registry.update_merchant(&existing.id, |merchant| {
+                    for alias in &missing {
+                        merchant.add_alias((*alias).clone());
+                    }
+                });
+            }
+            None => {
+                let mut merchant = Merchant::new(group.canonical.clone(), MerchantType::Other, None)
+                    .in_ledger(&group.ledger_id);
+                for alias in &group.aliases {
+                    merchant.add_alias(alias.clone());
+                }
+                report.merchants_added += 1;
+                report.aliases_added += merchant.aliases.len();
+                registry.register(merchant);
+            }
+        }
+    }
+}
+
+fn bootstrap_merchants(transactions: &[&Transaction], registry: &mut MerchantRegistry, report: &mut BootstrapReport) {
+    let mut by_name: BTreeMap<&str, Vec<&Transaction>> = BTreeMap::new();
+    for tx in transactions {
+        let name = tx.merchant.trim();
+        if known_merchant(name) {
+            by_name.entry(name).or_default().push(tx);
+        }
+    }
+    // Most frequent spelling first: it becomes the canonical name of its cluster
+    let mut spellings: Vec<Spelling> = by_name.into_iter().map(|(name, rows)| Spelling { name, rows }).collect();
+    spellings.sort_by(|a, b| b.rows.len().cmp(&a.rows.len()).then_with(|| a.name.cmp(b.name)));
+
+    let mut new_aliases: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut clusters: Vec<Merchant> = Vec::new();
+    for spelling in spellings {
+        if let Some(existing) = registry.find_by_string(spelling.name) {
+            if !existing.all_names().iter().any(|known| known == spelling.name) {
+                new_aliases.entry(existing.id).or_default().push(spelling.name.to_string());
+            }
+        } else if let Some(merchant) = clusters.iter_mut().find(|merchant| merchant.matches(spelling.name)) {
+            merchant.add_alias(spelling.name.to_string());
+        } else {
+            let merchant = Merchant::new(spelling.name.to_string(), MerchantType::Other, None)
+                .in_ledger(&spelling.rows[0].ledger_id);
+            clusters.push(merchant);
+        }
+    }
+
+    for (id, aliases) in new_aliases {
+        report.aliases_added += aliases.len();
+        let _ = registry.update_merchant(&id, |merchant| {
+            for alias in &aliases {
+                merchant.add_alias(alias.clone());
+            }
+        });
+    }
+    for merchant in clusters {
+        report.merchants_added += 1;
+        report.aliases_added += merchant.aliases.len();
+        registry.register(merchant);
+    }
+}
+
+// ============================================================================
+// BANKS AND ACCOUNTS
+// ============================================================================
+
+fn bootstrap_banks(transactions: &[&Transaction], registry: &mut BankRegistry, report: &mut BootstrapReport) {
+    for tx in transactions {
+        let name = tx.bank.trim();
+        if name.is_empty() || registry.find_by_string(name).is_some() {
+            continue;
+        }
+        // Country unknown: statements don't say where the bank is
+        registry.register(Bank::new(name.to_string(), String::new(), BankType::Unknown).in_ledger(&tx.ledger_id));
+        report.banks_added += 1;
+    }
+}
+
+fn account_type_for(bank: Option<&Bank>) -> AccountType {
+    match bank.map(|bank| &bank.bank_type) {
+        Some(BankType::Checking) => AccountType::Checking,
+        Some(BankType::Savings) => AccountType::Savings,
+        Some(BankType::CreditCard) => AccountType::Credit,
+        Some(BankType::Investment) => AccountType::Investment,
+        _ => AccountType::Other,
+    }
+}
+
+fn bootstrap_accounts(
+    transactions: &[&Transaction],
+    banks: &BankRegistry,
+    registry: &mut AccountRegistry,
+    report: &mut BootstrapReport,
+) {
+    // (ledger, bank, account name, account number) → rows, in first-seen order
+    let mut order = Vec::new();
+    let mut groups: HashMap<(&str, &str, &str, &str), Vec<&Transaction>> = HashMap::new();
+    for tx in transactions {
+        let key = (tx.ledger_id.as_str(), tx.bank.trim(), tx.account_name.trim(), tx.account_number.trim());
+        if key.1.is_empty() && key.2.is_empty() {
+            continue;
+        }
+        if !groups.contains_key(&key) {
+            order.push(key);
+        }
+        groups.entry(key).or_default().push(tx);
+    }
+
+    for key in order {
+        let (ledger_id, bank_name, account_name, account_number) = key;
+        let number = Account::mask_account_number(account_number);
+        let name = match account_name {
+            "" => format!("{} {}", bank_name, number).trim().to_string(),
+            name => name.to_string(),
+        };
+        let known = registry.find_by_name(&name).is_some_and(|account| account.ledger_id == ledger_id);
+        if known {
+            continue;
+        }
+
+        let rows = &groups[&key];
+        let bank = banks.find_by_string(bank_name);
+        let mut account = Account::new(
+            name,
+            number,
+            bank.as_ref().map(|bank| bank.id.clone()).unwrap_or_default(),
+            account_type_for(bank.as_ref()),
+            rows[0].currency.clone(),
+            0.0,
+        )
+        .in_ledger(ledger_id);
+        // No known opening balance: the balance is what the imported rows add up to
+        account.update_balance(rows.iter().fold(0.0, |total, tx| total + tx.amount_numeric));
+        registry.register(account);
+        report.accounts_added += 1;
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::setup_database;
+    use crate::imports::import_statement;
+    use rusqlite::Connection;
+
+    const BOFA: &str = "Date,Description,Amount\n\
+        01/02/2025,STARBUCKS STORE 123,-5.25\n\
+        01/03/2025,BLUE BOTTLE COFFEE,-4.00\n\
+        01/04/2025,BLUE BOTTLE COFFEE #12,-6.00\n\
+        01/15/2025,PAYROLL ACME,2000.00\n";
+
+    #[test]
+    fn test_bootstrap_from_imported_statement() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        import_statement(&conn, "bofa.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+
+        let before = MerchantRegistry::with_defaults().count();
+        let entities = bootstrap_entities(&conn).unwrap();
+        assert!(entities.report.merchants_added > 0);
+        assert_eq!(entities.merchants.count(), before + entities.report.merchants_added);
+
+        // Starbucks was already known; Bank of America too
+        assert!(entities.merchants.find_by_string("Starbucks").is_some());
+        assert_eq!(entities.report.banks_added, 0);
+        assert_eq!(entities.report.accounts_added, 1);
+        let account = &entities.accounts.all_accounts()[0];
+        assert_eq!(account.bank_id, entities.banks.get_id("Bank of America").unwrap());
+        assert!((account.current_balance - 1984.75).abs() < 1e-9);
+
+        // Running it again on the filled registries adds nothing
+        let (mut merchants, mut banks, mut accounts) = (entities.merchants, entities.banks, entities.accounts);
+        let transactions = get_active_transactions(&conn).unwrap();
+        let again = bootstrap_registries(&transactions, &mut merchants, &mut banks, &mut accounts);
+        assert_eq!(again, BootstrapReport::default());
+    }
+
+    #[test]
+    fn test_spellings_cluster_into_one_merchant() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        import_statement(&conn, "bofa.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+        let mut transactions = get_active_transactions(&conn).unwrap();
+        let names = ["Tacos El Gordo", "Tacos El Gordo", "TACOS EL GORDO #4", "Acme"];
+        for (tx, merchant) in transactions.iter_mut().zip(names) {
+            tx.merchant = merchant.to_string();
+            tx.category = "Restaurants".to_string();
+            tx.bank = "Banco Azteca".to_string();
+        }
+
+        let (mut merchants, mut banks, mut accounts) =
+            (MerchantRegistry::new(), BankRegistry::new(), AccountRegistry::new());
+        let report = bootstrap_registries(&transactions, &mut merchants, &mut banks, &mut accounts);
+        assert_eq!((report.merchants_added, report.aliases_added, report.banks_added), (2, 1, 1));
+
+        let tacos = merchants.find_by_string("TACOS EL GORDO #4").unwrap();
+        assert_eq!(tacos.canonical_name, "Tacos El Gordo");
+        assert_eq!(tacos.suggested_category.as_deref(), Some("Restaurants"));
+        assert_eq!(report.categories_suggested, 2);
+        assert_eq!(accounts.all_accounts()[0].account_type, AccountType::Other);
+    }
+}
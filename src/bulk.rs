@@ -0,0 +1,225 @@
+// 📦 Bulk - One action applied to many transactions, audited as a batch
+//
+// Problem solved:
+// - Recategorizing twenty coffee purchases meant twenty separate corrections,
+//   with nothing tying them together in the audit trail
+// - There was no way to record "I looked at this" (reviewed) or tags
+//
+// Every transaction still gets its own versioned correction (or pending
+// approval, see approvals.rs), so history and undo work per row. One extra
+// `bulk_action` event lists the whole batch: who did what to which rows.
+
+use crate::approvals::{submit_correction, submit_void, WriteOutcome};
+use crate::db::{insert_event, Event, Transaction};
+use crate::event_schema::BulkActionApplied;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use rusqlite::Connection;
+
+/// Metadata keys written by bulk actions
+pub const TAGS_KEY: &str = "tags";
+pub const REVIEWED_KEY: &str = "reviewed";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BulkAction {
+    SetCategory(String),
+    AddTag(String),
+    MarkReviewed,
+    Void(String),
+}
+
+impl BulkAction {
+    pub fn describe(&self) -> String {
+        match self {
+            BulkAction::SetCategory(category) => format!("set category {}", category),
+            BulkAction::AddTag(tag) => format!("add tag {}", tag),
+            BulkAction::MarkReviewed => "mark reviewed".to_string(),
+            BulkAction::Void(reason) => format!("void ({})", reason),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        let value = match self {
+            BulkAction::SetCategory(value) | BulkAction::AddTag(value) | BulkAction::Void(value) => value,
+            BulkAction::MarkReviewed => return Ok(()),
+        };
+        if value.trim().is_empty() {
+            return Err(anyhow!("'{}' needs a value", self.describe().trim()));
+        }
+        Ok(())
+    }
+
+    /// True when the transaction already looks like the action's result
+    fn is_noop(&self, tx: &Transaction) -> bool {
+        match self {
+            BulkAction::SetCategory(category) => &tx.category == category,
+            BulkAction::AddTag(tag) => tags(tx).iter().any(|t| t == tag),
+            BulkAction::MarkReviewed => is_reviewed(tx),
+            BulkAction::Void(_) => tx.is_voided(),
+        }
+    }
+}
+
+/// Tags on a transaction (metadata "tags")
+pub fn tags(tx: &Transaction) -> Vec<String> {
+    tx.get_metadata(TAGS_KEY)
+        .and_then(|value| value.as_array())
+        .map(|values| values.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
+}
+
+pub fn is_reviewed(tx: &Transaction) -> bool {
+    tx.get_metadata(REVIEWED_KEY).and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BulkResult {
+    /// Id of the `bulk_action` event's batch
+    pub batch_id: String,
+    /// New versions written
+    pub applied: Vec<Transaction>,
+    /// Changes waiting for approval
+    pub pending: usize,
+    /// Already in the requested state
+    pub skipped: usize,
+    /// (tx uuid, error) for rows that could not be changed
+    pub failed: Vec<(String, String)>,
+}
+
+/// Apply `action` to each transaction as its own correction, then log the batch
+pub fn apply_bulk_action(
+    conn: &Connection,
+    transactions: &[Transaction],
+    action: &BulkAction,
+    actor: &str,
+) -> Result<BulkResult> {
+    action.validate()?;
+    if transactions.is_empty() {
+        return Err(anyhow!("No transactions selected"));
+    }
+
+    let batch_id = uuid::Uuid::new_v4().to_string();
+    let reason = format!("bulk {} (batch {})", action.describe(), &batch_id[..8]);
+    let mut result = BulkResult { batch_id: batch_id.clone(), ..BulkResult::default() };
+
+    for tx in transactions {
+        if action.is_noop(tx) {
+            result.skipped += 1;
+            continue;
+        }
+
+        let outcome = match action {
+            BulkAction::Void(void_reason) => submit_void(conn, &tx.id, void_reason, actor),
+            _ => {
+                let mut next = tx.next_version(Some(reason.clone()));
+                match action {
+                    BulkAction::SetCategory(category) => next.category = category.clone(),
+                    BulkAction::AddTag(tag) => {
+                        let mut all = tags(tx);
+                        all.push(tag.clone());
+                        next.metadata.insert(TAGS_KEY.to_string(), serde_json::json!(all));
+                    }
+                    BulkAction::MarkReviewed => {
+                        next.metadata.insert(REVIEWED_KEY.to_string(), serde_json::json!(true));
+                        next.metadata.insert("reviewed_by".to_string(), serde_json::json!(actor));
+                        next.metadata.insert("reviewed_at".to_string(), serde_json::json!(Utc::now().to_rfc3339()));
+                    }
+                    BulkAction::Void(_) => unreachable!(),
+                }
+                submit_correction(conn, &next, actor)
+            }
+        };
+
+        match outcome {
+            Ok(WriteOutcome::Applied(next)) => result.applied.push(next),
+            Ok(WriteOutcome::Pending(_)) => result.pending += 1,
+            Err(e) => result.failed.push((tx.id.clone(), e.to_string())),
+        }
+    }
+
+    let payload = BulkActionApplied {
+        action: action.describe(),
+        tx_uuids: transactions.iter().map(|tx| tx.id.clone()).collect(),
+        applied: result.applied.iter().map(|tx| tx.id.clone()).collect(),
+        pending: result.pending,
+        skipped: result.skipped,
+        failed: result.failed.clone(),
+    };
+    let event = Event::typed("bulk_action", "batch", &batch_id, &payload, actor)?.with_ledger(&transactions[0].ledger_id);
+    insert_event(conn, &event)?;
+
+    Ok(result)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::db::{
+        get_active_transactions, get_events_for_entity, get_transaction_history, insert_transactions_as, setup_database,
+    };
+
+    fn purchase(merchant: &str, line: &str) -> Transaction {
+        let mut tx = Transaction {
+            date: "01/05/2025".to_string(),
+            description: format!("{} PURCHASE", merchant),
+            category: "Unknown".to_string(),
+            merchant: merchant.to_string(),
+            account_name: "Apple Card".to_string(),
+            bank: "AppleCard".to_string(),
+            source_file: "applecard.csv".to_string(),
+            line_number: line.to_string(),
+            ..test_support::sample_transaction()
+        };
+        tx.init_temporal_fields();
+        tx
+    }
+
+    fn setup() -> (Connection, Vec<Transaction>) {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        insert_transactions_as(&conn, &[purchase("Starbucks", "2"), purchase("Blue Bottle", "3")], "test").unwrap();
+        let txs = get_active_transactions(&conn).unwrap();
+        (conn, txs)
+    }
+
+    #[test]
+    fn test_bulk_category_versions_each_row_and_logs_batch() {
+        let (conn, txs) = setup();
+        let result = apply_bulk_action(&conn, &txs, &BulkAction::SetCategory("Coffee".to_string()), "ana").unwrap();
+
+        assert_eq!(result.applied.len(), 2);
+        for tx in &txs {
+            let history = get_transaction_history(&conn, &tx.id).unwrap();
+            assert_eq!(history.len(), 2);
+            assert_eq!(history[1].category, "Coffee");
+        }
+
+        let events = get_events_for_entity(&conn, "batch", &result.batch_id).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, "bulk_action");
+        assert_eq!(events[0].actor, "ana");
+    }
+
+    #[test]
+    fn test_tags_and_reviewed_skip_rows_already_done() {
+        let (conn, txs) = setup();
+        let tag = BulkAction::AddTag("trip".to_string());
+        apply_bulk_action(&conn, &txs[..1], &tag, "ana").unwrap();
+
+        let current = get_active_transactions(&conn).unwrap();
+        let result = apply_bulk_action(&conn, &current, &tag, "ana").unwrap();
+        assert_eq!((result.applied.len(), result.skipped), (1, 1));
+        assert!(get_active_transactions(&conn).unwrap().iter().all(|tx| tags(tx) == vec!["trip"]));
+
+        let current = get_active_transactions(&conn).unwrap();
+        apply_bulk_action(&conn, &current, &BulkAction::MarkReviewed, "ana").unwrap();
+        assert!(get_active_transactions(&conn).unwrap().iter().all(is_reviewed));
+
+        assert!(apply_bulk_action(&conn, &current, &BulkAction::Void("  ".to_string()), "ana").is_err());
+    }
+}
@@ -0,0 +1,370 @@
+// 🗂️ Chart of Accounts - Categories and accounts mapped to formal account codes
+//
+// Problem solved:
+// - The journal export (journal.rs) named accounts after whatever categories
+//   the ledger used ("Expenses:Coffee"), while an accountant books to a fixed
+//   chart ("6100 Meals and Entertainment"), so every export was re-mapped by
+//   hand
+//
+// A mapping ties one internal category, or one bank account, to a code, a
+// name and an account type in the chart. Several categories may share a code.
+// The journal projection posts mapped rows to the chart's account (and
+// carries its code into the Beancount, Ledger and QuickBooks exports);
+// unmapped ones keep their derived names. Mappings are versioned like
+// envelopes: a change appends a version, so the chart in use at any time can
+// be recovered.
+
+use crate::db::{insert_event, Event};
+use crate::event_schema::ChartMappingChanged;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// MAPPINGS
+// ============================================================================
+
+/// What a mapping is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MappedFrom {
+    /// A transaction category
+    Category,
+    /// A bank account (transaction `account_name`)
+    Account,
+}
+
+impl MappedFrom {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "category" => Ok(MappedFrom::Category),
+            "account" => Ok(MappedFrom::Account),
+            other => Err(anyhow!("Unknown mapping source '{}' (category, account)", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MappedFrom::Category => "category",
+            MappedFrom::Account => "account",
+        }
+    }
+}
+
+/// Type of an account in the chart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChartAccountType {
+    Asset,
+    Liability,
+    Equity,
+    Income,
+    Expense,
+}
+
+impl ChartAccountType {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "asset" => Ok(ChartAccountType::Asset),
+            "liability" => Ok(ChartAccountType::Liability),
+            "equity" => Ok(ChartAccountType::Equity),
+            "income" => Ok(ChartAccountType::Income),
+            "expense" => Ok(ChartAccountType::Expense),
+            other => Err(anyhow!("Unknown account type '{}' (asset, liability, equity, income, expense)", other)),
+        }
+    }
+
+    /// Top-level journal account ("Expenses", "Assets", ...)
+    pub fn root(&self) -> &'static str {
+        match self {
+            ChartAccountType::Asset => "Assets",
+            ChartAccountType::Liability => "Liabilities",
+            ChartAccountType::Equity => "Equity",
+            ChartAccountType::Income => "Income",
+            ChartAccountType::Expense => "Expenses",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChartMapping {
+    pub id: String,
+    pub ledger_id: String,
+    pub mapped_from: MappedFrom,
+    /// The category or account name mapped (case-insensitive)
+    pub internal: String,
+    /// Account code in the chart, e.g. "6100"
+    pub code: String,
+    /// Account name in the chart, e.g. "Meals and Entertainment"
+    pub name: String,
+    pub account_type: ChartAccountType,
+}
+
+impl ChartMapping {
+    pub fn new(
+        mapped_from: MappedFrom,
+        internal: &str,
+        code: &str,
+        name: &str,
+        account_type: ChartAccountType,
+    ) -> Self {
+        ChartMapping {
+            id: uuid::Uuid::new_v4().to_string(),
+            ledger_id: crate::ledger::default_ledger_id(),
+            mapped_from,
+            internal: internal.trim().to_string(),
+            code: code.trim().to_string(),
+            name: name.trim().to_string(),
+            account_type,
+        }
+    }
+
+    /// Place this mapping in a ledger (builder)
+    pub fn in_ledger(mut self, ledger_id: &str) -> Self {
+        self.ledger_id = ledger_id.to_string();
+        self
+    }
+
+    pub fn maps(&self, mapped_from: MappedFrom, internal: &str) -> bool {
+        self.mapped_from == mapped_from && self.internal.eq_ignore_ascii_case(internal.trim())
+    }
+}
+
+/// One immutable version of a mapping
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionedMapping {
+    pub mapping: ChartMapping,
+    pub version: i64,
+    pub system_time: DateTime<Utc>,
+    pub valid_from: DateTime<Utc>,
+    pub valid_until: Option<DateTime<Utc>>,
+    pub changed_by: String,
+    pub change_reason: Option<String>,
+}
+
+impl VersionedMapping {
+    pub fn is_current(&self) -> bool {
+        self.valid_until.is_none()
+    }
+}
+
+/// The current mappings of a ledger, for lookups
+#[derive(Debug, Clone, Default)]
+pub struct ChartOfAccounts {
+    pub mappings: Vec<ChartMapping>,
+}
+
+impl ChartOfAccounts {
+    pub fn new(mappings: Vec<ChartMapping>) -> Self {
+        ChartOfAccounts { mappings }
+    }
+
+    pub fn lookup(&self, mapped_from: MappedFrom, internal: &str) -> Option<&ChartMapping> {
+        self.mappings.iter().find(|mapping| mapping.maps(mapped_from, internal))
+    }
+}
+
+// ============================================================================
+// STORAGE (append-only versions, like envelopes)
+// ============================================================================
+
+/// Save a mapping as a new version; identical to the current version →
+/// no-op. Returns the version that is current after the call.
+///
+/// Fails when another mapping already maps the same category or account, or
+/// when its code is in use under another name or type.
+pub fn save_chart_mapping(conn: &Connection, mapping: &ChartMapping, actor: &str, reason: Option<&str>) -> Result<i64> {
+    if mapping.internal.is_empty() || mapping.code.is_empty() || mapping.name.is_empty() {
+        return Err(anyhow!("A mapping needs a {}, a code and a name", mapping.mapped_from.as_str()));
+    }
+    for other in current_chart(conn, &mapping.ledger_id)?.mappings {
+        if other.id == mapping.id {
+            continue;
+        }
+        if other.maps(mapping.mapped_from, &mapping.internal) {
+            return Err(anyhow!(
+                "{} '{}' is already mapped to {} {}",
+                mapping.mapped_from.as_str(),
+                mapping.internal,
+                other.code,
+                other.name
+            ));
+        }
+        if other.code == mapping.code && (other.name != mapping.name || other.account_type != mapping.account_type) {
+            return Err(anyhow!("Code {} is already '{}' ({:?})", other.code, other.name, other.account_type));
+        }
+    }
+
+    let current = get_current_mapping(conn, &mapping.id)?;
+    if let Some(current) = &current {
+        if current.mapping == *mapping {
+            return Ok(current.version);
+        }
+    }
+    let next_version = mapping_history(conn, &mapping.id)?.last().map_or(1, |last| last.version + 1);
+
+    let now = Utc::now().to_rfc3339();
+    if current.is_some() {
+        conn.execute(
+            "UPDATE chart_mappings SET valid_until = ?1 WHERE mapping_id = ?2 AND valid_until IS NULL",
+            params![now, mapping.id],
+        )?;
+    }
+    conn.execute(
+        "INSERT INTO chart_mappings (
+            mapping_id, ledger_id, version, definition, changed_by, change_reason,
+            system_time, valid_from, valid_until
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, NULL)",
+        params![
+            mapping.id,
+            mapping.ledger_id,
+            next_version,
+            serde_json::to_string(mapping)?,
+            actor,
+            reason,
+            now,
+            now,
+        ],
+    )?;
+
+    let event_type = if next_version == 1 { "chart_mapping_created" } else { "chart_mapping_updated" };
+    record_change(conn, event_type, mapping, next_version, reason, actor)?;
+    Ok(next_version)
+}
+
+/// Stop mapping a category or account (exports fall back to derived names)
+pub fn retire_chart_mapping(conn: &Connection, mapping_id: &str, actor: &str, reason: Option<&str>) -> Result<()> {
+    let current = get_current_mapping(conn, mapping_id)?
+        .ok_or_else(|| anyhow!("Mapping not found or already retired: {}", mapping_id))?;
+    conn.execute(
+        "UPDATE chart_mappings SET valid_until = ?1 WHERE mapping_id = ?2 AND valid_until IS NULL",
+        params![Utc::now().to_rfc3339(), mapping_id],
+    )?;
+    record_change(conn, "chart_mapping_retired", &current.mapping, current.version, reason, actor)
+}
+
+fn record_change(
+    conn: &Connection,
+    event_type: &str,
+    mapping: &ChartMapping,
+    version: i64,
+    reason: Option<&str>,
+    actor: &str,
+) -> Result<()> {
+    let payload = ChartMappingChanged {
+        version,
+        mapped_from: mapping.mapped_from.as_str().to_string(),
+        internal: mapping.internal.clone(),
+        code: mapping.code.clone(),
+        name: mapping.name.clone(),
+        reason: reason.map(str::to_string),
+    };
+    let event = Event::typed(event_type, "chart_mapping", &mapping.id, &payload, actor)?;
+    insert_event(conn, &event.with_ledger(&mapping.ledger_id))
+}
+
+const MAPPING_COLUMNS: &str = "definition, version, changed_by, change_reason, system_time, valid_from, valid_until";
+
+pub fn get_current_mapping(conn: &Connection, mapping_id: &str) -> Result<Option<VersionedMapping>> {
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM chart_mappings WHERE mapping_id = ?1 AND valid_until IS NULL",
+                MAPPING_COLUMNS
+            ),
+            [mapping_id],
+            row_to_mapping,
+        )
+        .optional()?)
+}
+
+/// Every version of a mapping, oldest first
+pub fn mapping_history(conn: &Connection, mapping_id: &str) -> Result<Vec<VersionedMapping>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM chart_mappings WHERE mapping_id = ?1 ORDER BY version",
+        MAPPING_COLUMNS
+    ))?;
+    let versions = stmt.query_map([mapping_id], row_to_mapping)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(versions)
+}
+
+/// The ledger's chart as it is now, by code
+pub fn current_chart(conn: &Connection, ledger_id: &str) -> Result<ChartOfAccounts> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM chart_mappings WHERE ledger_id = ?1 AND valid_until IS NULL",
+        MAPPING_COLUMNS
+    ))?;
+    let mut mappings: Vec<ChartMapping> = stmt
+        .query_map([ledger_id], row_to_mapping)?
+        .map(|version| version.map(|version| version.mapping))
+        .collect::<Result<Vec<_>, _>>()?;
+    mappings.sort_by(|a, b| (&a.code, &a.internal).cmp(&(&b.code, &b.internal)));
+    Ok(ChartOfAccounts::new(mappings))
+}
+
+fn row_to_mapping(row: &rusqlite::Row) -> rusqlite::Result<VersionedMapping> {
+    let definition: String = row.get(0)?;
+    let system_time: String = row.get(4)?;
+    let valid_from: String = row.get(5)?;
+    let valid_until: Option<String> = row.get(6)?;
+
+    let parse = |s: &str| {
+        DateTime::parse_from_rfc3339(s)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|_| rusqlite::Error::InvalidQuery)
+    };
+
+    Ok(VersionedMapping {
+        mapping: serde_json::from_str(&definition).map_err(|_| rusqlite::Error::InvalidQuery)?,
+        version: row.get(1)?,
+        changed_by: row.get(2)?,
+        change_reason: row.get(3)?,
+        system_time: parse(&system_time)?,
+        valid_from: parse(&valid_from)?,
+        valid_until: valid_until.as_deref().map(parse).transpose()?,
+    })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::setup_database;
+
+    #[test]
+    fn test_mappings_are_versioned_and_checked() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let dining = ChartMapping::new(MappedFrom::Category, "Dining", "6100", "Meals", ChartAccountType::Expense);
+        assert_eq!(save_chart_mapping(&conn, &dining, "ana", None).unwrap(), 1);
+        assert_eq!(save_chart_mapping(&conn, &dining, "ana", None).unwrap(), 1);
+
+        // Several categories may share an account, but not remap one or reuse a code differently
+        let coffee = ChartMapping::new(MappedFrom::Category, "Coffee", "6100", "Meals", ChartAccountType::Expense);
+        save_chart_mapping(&conn, &coffee, "ana", None).unwrap();
+        let again = ChartMapping::new(MappedFrom::Category, "dining", "6200", "Travel", ChartAccountType::Expense);
+        assert!(save_chart_mapping(&conn, &again, "ana", None).is_err());
+        let clash = ChartMapping::new(MappedFrom::Category, "Travel", "6100", "Travel", ChartAccountType::Expense);
+        assert!(save_chart_mapping(&conn, &clash, "ana", None).is_err());
+
+        let mut renamed = dining.clone();
+        renamed.code = "6150".to_string();
+        renamed.name = "Meals and Entertainment".to_string();
+        assert_eq!(save_chart_mapping(&conn, &renamed, "ana", Some("new chart")).unwrap(), 2);
+        let history = mapping_history(&conn, &dining.id).unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(!history[0].is_current() && history[1].is_current());
+
+        let chart = current_chart(&conn, "default").unwrap();
+        assert_eq!(chart.lookup(MappedFrom::Category, "DINING").unwrap().code, "6150");
+        assert!(chart.lookup(MappedFrom::Account, "Dining").is_none());
+
+        retire_chart_mapping(&conn, &coffee.id, "ana", None).unwrap();
+        assert_eq!(current_chart(&conn, "default").unwrap().mappings.len(), 1);
+    }
+}
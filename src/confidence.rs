@@ -0,0 +1,163 @@
+// 🎯 Confidence - One score for how far to trust a row
+//
+// Problem solved:
+// - The parser, the matched rule and the MCC table each had a confidence of
+//   their own, but nothing combined them: `confidence_score` was never written
+//   at import, so triage and verification had no number to go on
+//
+// The composition model, as `combine` computes it:
+//
+//   overall        = parser × classification
+//   classification = 1 − Π(1 − cᵢ)   over the classification evidence cᵢ
+//
+// The parser's confidence (DEFAULT_PARSER_CONFIDENCE when it gives none)
+// bounds everything after it: a rule matched on a misread description is no
+// better than the read. Classification evidence is the matched rule and the
+// registry match (MCC), treated as independent signals for the category, so
+// two agreeing sources raise it above either alone (noisy-OR). A rule whose
+// category the registry overrules is not evidence. A category the file itself
+// carries counts as certain (the parse already priced it in); no evidence at
+// all leaves the row at UNCLASSIFIED_CONFIDENCE. Inputs are clamped to
+// 0.0-1.0, and an unreadable one (NaN) counts as 0.
+
+use crate::db::Transaction;
+use crate::mcc::{classify_mcc, MCC_CONFIDENCE};
+use crate::rules::{ClassificationResult, RuleEngine};
+
+/// Parser confidence assumed when the parser reports none
+pub const DEFAULT_PARSER_CONFIDENCE: f64 = 0.9;
+
+/// Classification factor of a row no rule, registry or file categorized
+pub const UNCLASSIFIED_CONFIDENCE: f64 = 0.5;
+
+/// What each component contributed to a row
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfidenceInputs {
+    /// The parser's confidence in the row it read
+    pub parser: Option<f64>,
+    /// The file carried its own category
+    pub source_category: bool,
+    /// The matched rule (id, confidence)
+    pub rule: Option<(String, f64)>,
+    /// The registry match ("mcc 5812", confidence)
+    pub registry: Option<(String, f64)>,
+}
+
+fn clamp(value: f64) -> f64 {
+    if value.is_nan() { 0.0 } else { value.clamp(0.0, 1.0) }
+}
+
+impl ConfidenceInputs {
+    /// The inputs of a freshly parsed row `tx` that the rule engine
+    /// classified as `classified`
+    pub fn for_row(
+        parser: Option<f64>,
+        source_category: bool,
+        tx: &Transaction,
+        rules: &RuleEngine,
+        classified: &ClassificationResult,
+    ) -> Self {
+        let registry = tx.mcc().and_then(|code| classify_mcc(code).map(|by_mcc| (code, by_mcc)));
+        let rule = classified
+            .rule_id
+            .as_deref()
+            .and_then(|id| rules.rules().iter().find(|rule| rule.id == id))
+            // Overruled: the registry decided the category against the rule
+            .filter(|rule| match (&rule.category, &registry) {
+                (Some(category), Some((_, by_mcc))) => by_mcc.category.as_ref() == Some(category),
+                _ => true,
+            })
+            .map(|rule| (rule.id.clone(), rule.confidence));
+
+        ConfidenceInputs {
+            parser,
+            source_category,
+            rule,
+            registry: registry.map(|(code, _)| (format!("mcc {}", code), MCC_CONFIDENCE)),
+        }
+    }
+}
+
+/// The overall confidence, with one reason per factor (see the module
+/// comment for the model)
+pub fn combine(inputs: &ConfidenceInputs) -> (f64, Vec<String>) {
+    let parser = clamp(inputs.parser.unwrap_or(DEFAULT_PARSER_CONFIDENCE));
+    let mut reasons = vec![format!("parser {:.2}", parser)];
+
+    let mut evidence = Vec::new();
+    if inputs.source_category {
+        evidence.push(1.0);
+        reasons.push("category from the file".to_string());
+    }
+    for (name, confidence) in [&inputs.rule, &inputs.registry].into_iter().flatten() {
+        let confidence = clamp(*confidence);
+        evidence.push(confidence);
+        reasons.push(format!("{} {:.2}", name, confidence));
+    }
+
+    let classification = if evidence.is_empty() {
+        reasons.push(format!("unclassified {:.2}", UNCLASSIFIED_CONFIDENCE));
+        UNCLASSIFIED_CONFIDENCE
+    } else {
+        1.0 - evidence.iter().map(|c| 1.0 - c).product::<f64>()
+    };
+    (parser * classification, reasons)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_combination_model() {
+        // The parser bounds everything: a certain rule can't beat the read
+        let rule_only =
+            ConfidenceInputs { parser: Some(0.8), rule: Some(("coffee".into(), 1.0)), ..Default::default() };
+        assert!(close(combine(&rule_only).0, 0.8));
+
+        // Agreeing rule and registry are stronger than either alone
+        let both = ConfidenceInputs {
+            parser: Some(1.0),
+            rule: Some(("coffee".into(), 0.8)),
+            registry: Some(("mcc 5814".into(), 0.5)),
+            ..Default::default()
+        };
+        let (score, reasons) = combine(&both);
+        assert!(close(score, 0.9));
+        assert_eq!(reasons, vec!["parser 1.00", "coffee 0.80", "mcc 5814 0.50"]);
+
+        // No evidence: the default parser confidence times the unclassified factor
+        let unclassified = combine(&ConfidenceInputs::default()).0;
+        assert!(close(unclassified, DEFAULT_PARSER_CONFIDENCE * UNCLASSIFIED_CONFIDENCE));
+        let from_file = ConfidenceInputs { parser: Some(0.7), source_category: true, ..Default::default() };
+        assert!(close(combine(&from_file).0, 0.7));
+    }
+
+    #[test]
+    fn test_edge_cases_are_clamped() {
+        let zero_parser =
+            ConfidenceInputs { parser: Some(0.0), rule: Some(("r".into(), 1.0)), ..Default::default() };
+        assert_eq!(combine(&zero_parser).0, 0.0);
+
+        let out_of_range = ConfidenceInputs {
+            parser: Some(1.7),
+            rule: Some(("r".into(), f64::NAN)),
+            registry: Some(("mcc 5812".into(), -3.0)),
+            ..Default::default()
+        };
+        // Unreadable and negative signals count as 0: worse than no evidence
+        assert_eq!(combine(&out_of_range).0, 0.0);
+
+        let certain =
+            ConfidenceInputs { parser: Some(1.0), registry: Some(("mcc 5812".into(), 1.0)), ..Default::default() };
+        assert_eq!(combine(&certain).0, 1.0);
+    }
+}
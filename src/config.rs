@@ -0,0 +1,200 @@
+// ⚙️ Config - Where this installation keeps its data
+//
+// Problem solved:
+// - The CLI and server assumed a database under one developer's home directory
+// - New installs had no way to say where the database lives or which currency is "home"
+//
+// `init` writes the config file; every command reads it. Without a config file
+// the defaults below are used, so nothing ever points at a machine-specific path.
+// A `db_path` of ":memory:" keeps everything in memory: nothing is read from or
+// written to disk, and the data is gone when the process exits (tests,
+// previews, a quick look at one file without touching the real ledger).
+
+use crate::layout::LedgerLayout;
+use crate::locale::Locale;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Overrides the config file location
+pub const CONFIG_ENV: &str = "TRUST_CONFIG";
+
+/// `db_path` that selects in-memory storage (SQLite's own name for it)
+pub const IN_MEMORY_DB_PATH: &str = ":memory:";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppConfig {
+    /// SQLite database file, or IN_MEMORY_DB_PATH
+    pub db_path: String,
+
+    /// ISO 4217 code amounts are reported in (e.g. "USD")
+    pub base_currency: String,
+
+    /// Classification rules file (relative to the working directory)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rules_path: Option<String>,
+
+    /// Ledger table columns chosen in the TUI (defaults when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ledger_layout: Option<LedgerLayout>,
+
+    /// Writes each API token may make per minute (default 60)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_writes_per_minute: Option<u32>,
+
+    /// Ed25519 key file used to sign exports (see `signing init`);
+    /// $TRUST_SIGNING_KEY takes precedence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_key_path: Option<String>,
+
+    /// How the TUI and reports show amounts and dates, e.g. "en-US" or
+    /// "de-DE" (see locale.rs; plain when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            db_path: data_dir().join("transactions.db").to_string_lossy().to_string(),
+            base_currency: "USD".to_string(),
+            rules_path: None,
+            ledger_layout: None,
+            api_writes_per_minute: None,
+            signing_key_path: None,
+            locale: None,
+        }
+    }
+}
+
+impl AppConfig {
+    /// Config file location: $TRUST_CONFIG, else ~/.config/trust-construction/config.json
+    pub fn path() -> PathBuf {
+        match env::var(CONFIG_ENV) {
+            Ok(path) if !path.is_empty() => PathBuf::from(path),
+            _ => home_dir().join(".config").join("trust-construction").join("config.json"),
+        }
+    }
+
+    /// Load the config file, or defaults if there is none yet
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        if path.exists() {
+            Self::load_from(&path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {:?}", path))?;
+        let config: AppConfig = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid config file: {:?}", path))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Write the config (creating parent directories)
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        self.validate()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.db_path.trim().is_empty() {
+            return Err(anyhow!("db_path must not be empty"));
+        }
+        if self.api_writes_per_minute == Some(0) {
+            return Err(anyhow!("api_writes_per_minute must be at least 1"));
+        }
+        if self.base_currency.len() != 3 || !self.base_currency.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(anyhow!(
+                "base_currency must be a 3-letter ISO code like USD, got '{}'",
+                self.base_currency
+            ));
+        }
+        if let Some(tag) = &self.locale {
+            Locale::parse(tag)?;
+        }
+        Ok(())
+    }
+
+    /// Storage is in memory (`db_path` is ":memory:"): there is no file to
+    /// look for, create or hand to another process
+    pub fn in_memory(&self) -> bool {
+        self.db_path.trim() == IN_MEMORY_DB_PATH
+    }
+
+    /// The configured locale (plain when unset)
+    pub fn locale(&self) -> Locale {
+        self.locale.as_deref().and_then(|tag| Locale::parse(tag).ok()).unwrap_or_default()
+    }
+}
+
+fn home_dir() -> PathBuf {
+    env::var("HOME").map(PathBuf::from).unwrap_or_else(|_| PathBuf::from("."))
+}
+
+/// Directory holding the config file
+pub fn config_dir() -> PathBuf {
+    AppConfig::path().parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Default home for the database: ~/.local/share/trust-construction
+pub fn data_dir() -> PathBuf {
+    home_dir().join(".local").join("share").join("trust-construction")
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let dir = env::temp_dir().join(format!("trust-config-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("nested").join("config.json");
+
+        let config = AppConfig {
+            db_path: dir.join("books.db").to_string_lossy().to_string(),
+            base_currency: "MXN".to_string(),
+            rules_path: Some("rules/merchants.json".to_string()),
+            ledger_layout: Some(LedgerLayout::default()),
+            api_writes_per_minute: Some(30),
+            signing_key_path: Some("keys/signing.key".to_string()),
+            locale: Some("es-MX".to_string()),
+        };
+        config.save_to(&path).unwrap();
+        assert_eq!(AppConfig::load_from(&path).unwrap(), config);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(AppConfig::default().validate().is_ok());
+
+        let lowercase = AppConfig { base_currency: "usd".to_string(), ..AppConfig::default() };
+        assert!(lowercase.validate().is_err());
+
+        let blank = AppConfig { db_path: "  ".to_string(), ..AppConfig::default() };
+        assert!(blank.validate().is_err());
+
+        let locale = AppConfig { locale: Some("klingon".to_string()), ..AppConfig::default() };
+        assert!(locale.validate().is_err());
+
+        let memory = AppConfig { db_path: IN_MEMORY_DB_PATH.to_string(), ..AppConfig::default() };
+        assert!(memory.validate().is_ok() && memory.in_memory());
+        assert!(!AppConfig::default().in_memory());
+    }
+}
@@ -0,0 +1,380 @@
+// ⚔️ Conflicts - Two writers, one transaction
+//
+// Problem solved:
+// - Laptop and server both corrected the same transaction before syncing
+// - The same version number now means two different things
+//
+// A conflict is never silently dropped: it is recorded as an event and then
+// resolved by the ledger's policy:
+// - last-writer-wins: the version written later (system_time) becomes current
+// - manual: the competing version is queued as a pending change (approvals.rs)
+//
+// Resolution is deterministic: both instances pick the same winner, so they
+// converge after exchanging changesets.
+
+use crate::approvals::{propose_change, ChangeKind, PendingChange};
+use crate::db::{
+    get_current_transaction, insert_event, row_to_transaction, write_transaction_version, Event,
+    Transaction, TRANSACTION_SELECT_COLUMNS,
+};
+use crate::event_schema::ConflictLogged;
+use crate::ledger::get_ledger;
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+
+// ============================================================================
+// POLICY
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictPolicy {
+    /// Later system_time wins (ties broken by content, so every instance agrees)
+    #[default]
+    LastWriterWins,
+
+    /// Keep the local version; queue the other one for a human decision
+    Manual,
+}
+
+impl ConflictPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConflictPolicy::LastWriterWins => "last-writer-wins",
+            ConflictPolicy::Manual => "manual",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "last-writer-wins" | "lww" => Ok(ConflictPolicy::LastWriterWins),
+            "manual" => Ok(ConflictPolicy::Manual),
+            other => Err(anyhow!("Unknown conflict policy '{}' (last-writer-wins, manual)", other)),
+        }
+    }
+
+    /// Policy configured for a ledger (default: last-writer-wins)
+    pub fn for_ledger(conn: &Connection, ledger_id: &str) -> Result<Self> {
+        Ok(get_ledger(conn, ledger_id)?
+            .and_then(|ledger| ledger.config.conflict_policy)
+            .unwrap_or_default())
+    }
+}
+
+// ============================================================================
+// DETECTION
+// ============================================================================
+
+/// Two versions of one identity that disagree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conflict {
+    /// Version stored here
+    pub local: Transaction,
+
+    /// Competing version (from a changeset, or a second current row)
+    pub incoming: Transaction,
+}
+
+#[derive(Debug, Clone)]
+pub enum Resolution {
+    /// Local version stays current
+    KeptLocal,
+
+    /// Incoming values were appended as a new current version
+    AppliedIncoming(Transaction),
+
+    /// Incoming values wait for approval (manual policy)
+    Queued(PendingChange),
+}
+
+/// Fingerprint of a version's values, ignoring when/where it was stored
+pub fn content_fingerprint(tx: &Transaction) -> String {
+    let mut values = tx.clone();
+    values.system_time = None;
+    values.valid_from = None;
+    values.valid_until = None;
+    values.previous_version_id = None;
+
+    // serde_json::Value sorts map keys, so metadata order doesn't matter
+    let canonical = serde_json::to_value(&values).unwrap_or_default().to_string();
+    format!("{:x}", Sha256::digest(canonical.as_bytes()))
+}
+
+/// Check if two versions of the same identity carry different values
+pub fn is_conflict(local: &Transaction, incoming: &Transaction) -> bool {
+    local.id == incoming.id && content_fingerprint(local) != content_fingerprint(incoming)
+}
+
+/// Last-writer-wins order: later system_time, then larger fingerprint
+pub fn last_writer_order(a: &Transaction, b: &Transaction) -> Ordering {
+    a.system_time
+        .cmp(&b.system_time)
+        .then_with(|| content_fingerprint(a).cmp(&content_fingerprint(b)))
+}
+
+// ============================================================================
+// RESOLUTION
+// ============================================================================
+
+/// Record a conflict and resolve it with `policy`
+pub fn resolve_conflict(
+    conn: &Connection,
+    conflict: &Conflict,
+    policy: ConflictPolicy,
+    actor: &str,
+) -> Result<Resolution> {
+    let incoming_wins = last_writer_order(&conflict.incoming, &conflict.local) == Ordering::Greater;
+    log_conflict_event(conn, "conflict_detected", conflict, policy, None, actor)?;
+
+    let resolution = match policy {
+        ConflictPolicy::LastWriterWins if !incoming_wins => Resolution::KeptLocal,
+        ConflictPolicy::LastWriterWins => {
+            let next = adopt_values(conn, &conflict.incoming, "Conflict resolved: last writer wins")?;
+            write_transaction_version(conn, &next, actor, "transaction_corrected")?;
+            Resolution::AppliedIncoming(next)
+        }
+        ConflictPolicy::Manual => {
+            let next = adopt_values(conn, &conflict.incoming, "Conflicting version from sync")?;
+            Resolution::Queued(propose_change(conn, ChangeKind::Correction, &next, actor)?)
+        }
+    };
+
+    let outcome = match &resolution {
+        Resolution::KeptLocal => "kept_local",
+        Resolution::AppliedIncoming(_) => "applied_incoming",
+        Resolution::Queued(_) => "queued",
+    };
+    log_conflict_event(conn, "conflict_resolved", conflict, policy, Some(outcome), actor)?;
+
+    Ok(resolution)
+}
+
+/// Next version on top of the current one, carrying `incoming`'s values
+fn adopt_values(conn: &Connection, incoming: &Transaction, reason: &str) -> Result<Transaction> {
+    let current = get_current_transaction(conn, &incoming.id)?
+        .ok_or_else(|| anyhow!("No current version for transaction {}", incoming.id))?;
+    let base = current.next_version(Some(reason.to_string()));
+
+    let mut next = incoming.clone();
+    next.version = base.version;
+    next.system_time = base.system_time;
+    next.valid_from = base.valid_from;
+    next.valid_until = None;
+    next.previous_version_id = base.previous_version_id;
+    next.ledger_id = current.ledger_id;
+    next.metadata.remove("restores_version");
+    next.metadata.remove("redo_stack");
+    next.metadata.insert("change_reason".to_string(), serde_json::json!(reason));
+
+    Ok(next)
+}
+
+/// Find identities with more than one current row and resolve them
+///
+/// The last writer stays current, the others are expired; under the manual
+/// policy each expired value is also queued for review.
+pub fn repair_current_conflicts(conn: &Connection, actor: &str) -> Result<Vec<Conflict>> {
+    let mut stmt = conn.prepare(
+        "SELECT tx_uuid FROM transactions
+         WHERE valid_until IS NULL AND tx_uuid IS NOT NULL
+         GROUP BY tx_uuid HAVING COUNT(*) > 1",
+    )?;
+    let tx_uuids = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut conflicts = Vec::new();
+    for tx_uuid in tx_uuids {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM transactions WHERE tx_uuid = ?1 AND valid_until IS NULL",
+            TRANSACTION_SELECT_COLUMNS
+        ))?;
+        let mut current = stmt
+            .query_map([&tx_uuid], row_to_transaction)?
+            .collect::<Result<Vec<_>, _>>()?;
+        current.sort_by(last_writer_order);
+
+        let winner = current.pop().ok_or_else(|| anyhow!("No current rows for {}", tx_uuid))?;
+        let expired_at = winner.valid_from.unwrap_or_else(chrono::Utc::now);
+
+        for loser in current {
+            conn.execute(
+                "UPDATE transactions SET valid_until = ?1 WHERE tx_uuid = ?2 AND version = ?3",
+                params![expired_at.to_rfc3339(), tx_uuid, loser.version],
+            )?;
+
+            let conflict = Conflict { local: winner.clone(), incoming: loser };
+            let policy = ConflictPolicy::for_ledger(conn, &winner.ledger_id)?;
+            resolve_conflict(conn, &conflict, policy, actor)?;
+            conflicts.push(conflict);
+        }
+    }
+
+    Ok(conflicts)
+}
+
+fn log_conflict_event(
+    conn: &Connection,
+    event_type: &str,
+    conflict: &Conflict,
+    policy: ConflictPolicy,
+    outcome: Option<&str>,
+    actor: &str,
+) -> Result<()> {
+    let payload = ConflictLogged {
+        policy,
+        outcome: outcome.map(str::to_string),
+        local: conflict.local.clone(),
+        incoming: conflict.incoming.clone(),
+    };
+    let event = Event::typed(event_type, "transaction", &conflict.local.id, &payload, actor)?
+        .with_ledger(&conflict.local.ledger_id);
+    insert_event(conn, &event)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::db::{get_events_for_entity, insert_transaction_version, insert_transactions_as, setup_database};
+    use crate::ledger::{update_ledger_config, LedgerConfig, DEFAULT_LEDGER_ID};
+    use chrono::{Duration, Utc};
+
+    fn sample_transaction(merchant: &str) -> Transaction {
+        let mut tx = Transaction {
+            date: "06/01/2025".to_string(),
+            description: "HARDWARE STORE".to_string(),
+            amount_original: "-$80.00".to_string(),
+            amount_numeric: -80.0,
+            category: "Home".to_string(),
+            merchant: merchant.to_string(),
+            account_number: "1234".to_string(),
+            line_number: "7".to_string(),
+            ..test_support::sample_transaction()
+        };
+        tx.init_temporal_fields();
+        tx
+    }
+
+    /// Local v2 (stored) and a competing v2 written `offset` later elsewhere
+    fn diverged(conn: &Connection, merchant: &str, offset: Duration) -> Conflict {
+        let tx = sample_transaction(merchant);
+        insert_transactions_as(conn, std::slice::from_ref(&tx), "test").unwrap();
+
+        let mut local = tx.next_version(Some("Garden".to_string()));
+        local.category = "Garden".to_string();
+        insert_transaction_version(conn, &local, "laptop").unwrap();
+        let local = get_current_transaction(conn, &tx.id).unwrap().unwrap();
+
+        let mut incoming = tx.next_version(Some("Repairs".to_string()));
+        incoming.category = "Repairs".to_string();
+        incoming.system_time = local.system_time.map(|t| t + offset);
+
+        Conflict { local, incoming }
+    }
+
+    #[test]
+    fn test_policy_parse() {
+        assert_eq!(ConflictPolicy::parse("lww").unwrap(), ConflictPolicy::LastWriterWins);
+        assert_eq!(ConflictPolicy::parse("Manual").unwrap(), ConflictPolicy::Manual);
+        assert!(ConflictPolicy::parse("first-wins").is_err());
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_storage_times() {
+        let tx = sample_transaction("Hardware");
+        let mut copy = tx.clone();
+        copy.system_time = Some(Utc::now() + Duration::hours(1));
+        copy.valid_until = Some(Utc::now());
+        assert!(!is_conflict(&tx, &copy));
+
+        copy.category = "Other".to_string();
+        assert!(is_conflict(&tx, &copy));
+    }
+
+    #[test]
+    fn test_last_writer_wins() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        // Incoming was written later: it becomes v3
+        let conflict = diverged(&conn, "Hardware", Duration::minutes(5));
+        let resolution =
+            resolve_conflict(&conn, &conflict, ConflictPolicy::LastWriterWins, "sync").unwrap();
+        assert!(matches!(resolution, Resolution::AppliedIncoming(_)));
+        let current = get_current_transaction(&conn, &conflict.local.id).unwrap().unwrap();
+        assert_eq!((current.version, current.category.as_str()), (3, "Repairs"));
+
+        let events = get_events_for_entity(&conn, "transaction", &conflict.local.id).unwrap();
+        assert!(events.iter().any(|e| e.event_type == "conflict_detected"));
+        assert!(events.iter().any(|e| e.event_type == "conflict_resolved"));
+
+        // Incoming was written earlier: local stays
+        let conflict = diverged(&conn, "Lumber Yard", Duration::minutes(-5));
+        let resolution =
+            resolve_conflict(&conn, &conflict, ConflictPolicy::LastWriterWins, "sync").unwrap();
+        assert!(matches!(resolution, Resolution::KeptLocal));
+        let current = get_current_transaction(&conn, &conflict.local.id).unwrap().unwrap();
+        assert_eq!(current.category, "Garden");
+    }
+
+    #[test]
+    fn test_manual_policy_queues_pending_change() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let config = LedgerConfig {
+            conflict_policy: Some(ConflictPolicy::Manual),
+            ..LedgerConfig::default()
+        };
+        update_ledger_config(&conn, DEFAULT_LEDGER_ID, &config, "owner").unwrap();
+        let policy = ConflictPolicy::for_ledger(&conn, DEFAULT_LEDGER_ID).unwrap();
+
+        let conflict = diverged(&conn, "Hardware", Duration::minutes(5));
+        match resolve_conflict(&conn, &conflict, policy, "sync").unwrap() {
+            Resolution::Queued(change) => {
+                assert_eq!(change.proposed.category, "Repairs");
+                assert_eq!(change.base_version, 2);
+            }
+            other => panic!("expected queued change, got {:?}", other),
+        }
+        let current = get_current_transaction(&conn, &conflict.local.id).unwrap().unwrap();
+        assert_eq!(current.category, "Garden");
+    }
+
+    #[test]
+    fn test_repair_two_current_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let conflict = diverged(&conn, "Hardware", Duration::minutes(5));
+
+        // Simulate a second current row for the same identity
+        let mut stray = conflict.incoming.clone();
+        stray.version = 3;
+        stray.valid_from = Some(Utc::now());
+        crate::db::insert_transaction_row(&conn, &stray, "other-hash").unwrap();
+        conn.execute(
+            "UPDATE transactions SET valid_until = NULL WHERE tx_uuid = ?1 AND version = 2",
+            [&conflict.local.id],
+        )
+        .unwrap();
+
+        let repaired = repair_current_conflicts(&conn, "sync").unwrap();
+        assert_eq!(repaired.len(), 1);
+        let current_rows: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM transactions WHERE tx_uuid = ?1 AND valid_until IS NULL",
+                [&conflict.local.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(current_rows, 1);
+        assert!(repair_current_conflicts(&conn, "sync").unwrap().is_empty());
+    }
+}
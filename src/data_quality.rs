@@ -3,11 +3,25 @@
 //
 // Inspired by Great Expectations (https://greatexpectations.io/)
 // Provides comprehensive data quality checks with confidence scoring
+//
+// With an FxRateProvider (`validate_with_rates`), converted rows are also
+// checked against the rate table: an embedded rate far from the day's rate is
+// usually a misparsed rate column (EUR/USD read as USD/EUR).
+//
+// With the ledger's accounts (`with_accounts`), rows are also checked against
+// their account's lifecycle: a row dated after a closed account's close date
+// is critical (imports reject it), a row on a frozen account a warning.
 
+use crate::accounts::account_of;
 use crate::db::Transaction;
+use crate::entities::{Account, AccountStatus};
+use crate::fx::FxRateProvider;
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
+/// Embedded FX rates further than this (fraction) from the rate table are flagged
+pub const DEFAULT_MAX_FX_DEVIATION: f64 = 0.05;
+
 // ============================================================================
 // VALIDATION RESULT
 // ============================================================================
@@ -122,6 +136,12 @@ pub struct DataQualityEngine {
 
     /// Minimum confidence threshold for "needs_review"
     review_threshold: f64,
+
+    /// Largest accepted gap between an embedded FX rate and the rate table
+    max_fx_deviation: f64,
+
+    /// Accounts whose lifecycle rows are checked against (empty: not checked)
+    accounts: Vec<Account>,
 }
 
 impl DataQualityEngine {
@@ -157,6 +177,7 @@ impl DataQualityEngine {
                 "Stripe".to_string(),
                 "Wise".to_string(),
                 "Scotiabank".to_string(),
+                "OFX".to_string(),
             ],
             known_types: vec![
                 "GASTO".to_string(),
@@ -165,11 +186,34 @@ impl DataQualityEngine {
                 "TRASPASO".to_string(),
             ],
             review_threshold: 0.7,
+            max_fx_deviation: DEFAULT_MAX_FX_DEVIATION,
+            accounts: Vec::new(),
         }
     }
 
+    /// Flag embedded FX rates more than `deviation` (0.05 = 5%) off (builder)
+    pub fn with_max_fx_deviation(mut self, deviation: f64) -> Self {
+        self.max_fx_deviation = deviation;
+        self
+    }
+
+    /// Check rows against these accounts' lifecycle (builder)
+    pub fn with_accounts(mut self, accounts: Vec<Account>) -> Self {
+        self.accounts = accounts;
+        self
+    }
+
     /// Validate a transaction and generate quality report
     pub fn validate(&self, tx: &Transaction) -> QualityReport {
+        self.report(tx, None)
+    }
+
+    /// `validate` plus the FX rate check against `rates`
+    pub fn validate_with_rates(&self, tx: &Transaction, rates: &dyn FxRateProvider) -> QualityReport {
+        self.report(tx, Some(rates))
+    }
+
+    fn report(&self, tx: &Transaction, rates: Option<&dyn FxRateProvider>) -> QualityReport {
         let mut validations = Vec::new();
         let mut issues = Vec::new();
 
@@ -312,6 +356,33 @@ impl DataQualityEngine {
             validations.push(temporal_result);
         }
 
+        // Rule 12: Embedded FX rate close to the rate table's
+        if let Some(fx_result) = rates.and_then(|rates| self.validate_fx_rate(tx, rates)) {
+            if !fx_result.passed {
+                issues.push(QualityIssue {
+                    severity: fx_result.severity.clone(),
+                    field: "fx_rate".to_string(),
+                    issue: fx_result.message.clone(),
+                    recommendation: "Check the source's rate column (direction of the pair) and re-import"
+                        .to_string(),
+                });
+            }
+            validations.push(fx_result);
+        }
+
+        // Rule 13: Account still open on the transaction's date
+        if let Some(status_result) = self.validate_account_status(tx) {
+            if !status_result.passed {
+                issues.push(QualityIssue {
+                    severity: status_result.severity.clone(),
+                    field: "account_status".to_string(),
+                    issue: status_result.message.clone(),
+                    recommendation: "Book it on another account, or reopen the account".to_string(),
+                });
+            }
+            validations.push(status_result);
+        }
+
         // Calculate overall metrics
         let passed_count = validations.iter().filter(|v| v.passed).count();
         let failed_count = validations.len() - passed_count;
@@ -340,6 +411,10 @@ impl DataQualityEngine {
         transactions.iter().map(|tx| self.validate(tx)).collect()
     }
 
+    pub fn validate_batch_with_rates(&self, transactions: &[Transaction], rates: &dyn FxRateProvider) -> Vec<QualityReport> {
+        transactions.iter().map(|tx| self.validate_with_rates(tx, rates)).collect()
+    }
+
     /// Generate summary statistics for batch validation
     pub fn batch_summary(&self, reports: &[QualityReport]) -> BatchSummary {
         let total = reports.len();
@@ -699,6 +774,68 @@ impl DataQualityEngine {
             "Temporal fields complete (Badge 19)",
         )
     }
+
+    /// Lifecycle of the row's account (None: no known account, or no date)
+    pub fn validate_account_status(&self, tx: &Transaction) -> Option<ValidationResult> {
+        let account = account_of(&self.accounts, tx)?;
+        let date = tx.parsed_date()?;
+        match account.status {
+            AccountStatus::Closed { closed_on } if !account.accepts_transaction_on(date) => {
+                Some(ValidationResult::fail(
+                    "account_closed",
+                    "account_status",
+                    &format!("{} was closed on {}; row is dated {}", account.name, closed_on, date),
+                    Severity::Critical,
+                ))
+            }
+            AccountStatus::Frozen => Some(ValidationResult::fail(
+                "account_frozen",
+                "account_status",
+                &format!("{} is frozen", account.name),
+                Severity::Warning,
+            )),
+            _ => Some(ValidationResult::pass(
+                "account_open",
+                "account_status",
+                &format!("{} is open on {}", account.name, date),
+            )),
+        }
+    }
+
+    /// Embedded rate vs the table (None: no embedded rate, or no table rate that day)
+    pub fn validate_fx_rate(&self, tx: &Transaction, rates: &dyn FxRateProvider) -> Option<ValidationResult> {
+        let fx = embedded_fx(tx)?;
+        let reference = rates.rate(&tx.currency, &fx.original_currency, tx.parsed_date()?)?;
+        if reference <= 0.0 || fx.rate <= 0.0 {
+            return None;
+        }
+
+        let deviation = (fx.rate - reference).abs() / reference;
+        if deviation <= self.max_fx_deviation {
+            return Some(ValidationResult::pass(
+                "fx_rate_consistent",
+                "fx_rate",
+                &format!("{}/{} rate {:.4} matches the table ({:.4})", fx.original_currency, tx.currency, fx.rate, reference),
+            ));
+        }
+
+        let inverted = ((1.0 / fx.rate) - reference).abs() / reference <= self.max_fx_deviation;
+        Some(ValidationResult::fail(
+            "fx_rate_deviation",
+            "fx_rate",
+            &format!(
+                "{}/{} rate {:.4} is {:.0}% off the table's {:.4} for {}{}",
+                fx.original_currency,
+                tx.currency,
+                fx.rate,
+                deviation * 100.0,
+                reference,
+                tx.date,
+                if inverted { " (looks inverted)" } else { "" }
+            ),
+            Severity::Warning,
+        ))
+    }
 }
 
 impl Default for DataQualityEngine {
@@ -707,6 +844,44 @@ impl Default for DataQualityEngine {
     }
 }
 
+// ============================================================================
+// EMBEDDED FX
+// ============================================================================
+
+/// Conversion a source applied to a row: `original_amount` of
+/// `original_currency`, at `rate` units of it per unit of the row's currency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddedFx {
+    pub original_amount: f64,
+    pub original_currency: String,
+    pub rate: f64,
+}
+
+/// The rate a row was converted at: metadata `fx_rate` + `original_currency`
+/// (+ `original_amount`), else the "(41000 MXN → $2050.00 USD @ rate 20.0000)"
+/// note the Wise parser appends to descriptions
+pub fn embedded_fx(tx: &Transaction) -> Option<EmbeddedFx> {
+    let number = |key: &str| tx.metadata.get(key).and_then(|value| value.as_f64());
+    if let (Some(rate), Some(currency)) = (number("fx_rate"), tx.metadata.get("original_currency").and_then(|v| v.as_str())) {
+        return Some(EmbeddedFx {
+            original_amount: number("original_amount").unwrap_or_default(),
+            original_currency: currency.to_string(),
+            rate,
+        });
+    }
+
+    let start = tx.description.rfind('(')?;
+    let inner = tx.description[start + 1..].strip_suffix(')')?;
+    let (conversion, rate) = inner.split_once(" @ rate ")?;
+    let (original, _) = conversion.split_once(" → ")?;
+    let (amount, currency) = original.split_once(' ')?;
+    Some(EmbeddedFx {
+        original_amount: amount.parse().ok()?,
+        original_currency: currency.to_string(),
+        rate: rate.parse().ok()?,
+    })
+}
+
 // ============================================================================
 // BATCH SUMMARY
 // ============================================================================
@@ -742,31 +917,24 @@ impl BatchSummary {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
+    use crate::db::test_support;
 
     fn create_valid_transaction() -> Transaction {
         let mut tx = Transaction {
-            date: "01/15/2025".to_string(),
             description: "Test purchase at Starbucks".to_string(),
             amount_original: "$45.99".to_string(),
             amount_numeric: -45.99,
-            transaction_type: "GASTO".to_string(),
-            category: "Restaurants".to_string(),
             merchant: "Starbucks".to_string(),
-            currency: "USD".to_string(),
             account_name: "BofA Checking".to_string(),
             account_number: "*1234".to_string(),
             bank: "Bank of America".to_string(),
             source_file: "bofa_jan_2025.csv".to_string(),
             line_number: "23".to_string(),
-            classification_notes: "".to_string(),
             id: "uuid-123".to_string(),
             version: 1,
             system_time: Some(chrono::Utc::now()),
             valid_from: Some(chrono::Utc::now()),
-            valid_until: None,
-            previous_version_id: None,
-            metadata: HashMap::new(),
+            ..test_support::sample_transaction()
         };
 
         tx.init_temporal_fields();
@@ -883,6 +1051,36 @@ mod tests {
         assert_eq!(summary.critical_issues_count, 0);
     }
 
+    #[test]
+    fn test_fx_rate_far_from_table_is_flagged() {
+        use crate::fx::HistoricalRates;
+
+        let engine = DataQualityEngine::new();
+        let date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let rates = HistoricalRates::new().with_rate("USD", "EUR", date, 0.93).with_rate("USD", "MXN", date, 20.0);
+
+        // Wise note, rate as the table has it
+        let mut tx = create_valid_transaction();
+        tx.description = "Invoice payment (500 EUR → $537.63 USD @ rate 0.9300)".to_string();
+        assert_eq!(embedded_fx(&tx).unwrap().original_currency, "EUR");
+        let report = engine.validate_with_rates(&tx, &rates);
+        assert!(report.validations.iter().any(|v| v.rule_name == "fx_rate_consistent"));
+        assert_eq!(report.issues.len(), 0);
+
+        // EUR/USD read as USD/EUR
+        tx.description = "Invoice payment (500 EUR → $465.00 USD @ rate 1.0753)".to_string();
+        let report = engine.validate_with_rates(&tx, &rates);
+        let issue = report.issues.iter().find(|issue| issue.field == "fx_rate").unwrap();
+        assert!(issue.issue.contains("looks inverted"), "{}", issue.issue);
+        assert!(engine.validate(&tx).issues.is_empty(), "no rate table, no FX check");
+
+        // Metadata wins over the description; 3% is within a 5% limit
+        tx.metadata.insert("fx_rate".to_string(), serde_json::json!(20.6));
+        tx.metadata.insert("original_currency".to_string(), serde_json::json!("MXN"));
+        assert!(engine.validate_fx_rate(&tx, &rates).unwrap().passed);
+        assert!(!engine.with_max_fx_deviation(0.01).validate_fx_rate(&tx, &rates).unwrap().passed);
+    }
+
     #[test]
     fn test_quality_report_methods() {
         let engine = DataQualityEngine::new();
@@ -895,4 +1093,33 @@ mod tests {
         assert!(!report.needs_review);
         assert!(!report.summary().is_empty());
     }
+
+    #[test]
+    fn test_rows_after_account_close_are_critical() {
+        let mut account = crate::entities::Account::new(
+            "BofA Checking".to_string(),
+            "*1234".to_string(),
+            "Bank of America".to_string(),
+            crate::entities::AccountType::Checking,
+            "USD".to_string(),
+            0.0,
+        );
+        account.status = AccountStatus::Closed { closed_on: NaiveDate::from_ymd_opt(2025, 1, 10).unwrap() };
+        let engine = DataQualityEngine::new().with_accounts(vec![account.clone()]);
+
+        let tx = create_valid_transaction();
+        let report = engine.validate(&tx);
+        assert!(report.has_critical_issues());
+        assert!(report.issues.iter().any(|issue| issue.field == "account_status"));
+
+        let mut before_close = tx.clone();
+        before_close.date = "01/10/2025".to_string();
+        assert!(engine.validate_account_status(&before_close).unwrap().passed);
+
+        account.status = AccountStatus::Frozen;
+        let frozen = DataQualityEngine::new().with_accounts(vec![account]).validate(&tx);
+        assert!(!frozen.has_critical_issues());
+        assert!(frozen.issues.iter().any(|issue| issue.field == "account_status"));
+        assert!(DataQualityEngine::new().validate_account_status(&tx).is_none());
+    }
 }
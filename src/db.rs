@@ -1,5 +1,7 @@
+use crate::event_schema::{validate_event_data, EventPayload, TransactionAdded, TransactionCorrected};
+use crate::outbox::enqueue_event;
 use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
@@ -92,6 +94,10 @@ pub struct Transaction {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub previous_version_id: Option<String>,
 
+    /// Ledger this transaction belongs to (see ledger.rs)
+    #[serde(default = "crate::ledger::default_ledger_id")]
+    pub ledger_id: String,
+
     // ========================================================================
     // EXTENSIBLE METADATA (can grow without schema changes)
     // Following Rich Hickey's philosophy: "Aggregates as maps, not structs"
@@ -110,6 +116,43 @@ fn is_zero_i64(val: &i64) -> bool {
     *val == 0
 }
 
+/// Metadata key of the date a card feed posted the charge on
+pub const POSTED_DATE_KEY: &str = "posted_date";
+
+/// Metadata key marking a charge the source reported before it settled
+pub const PENDING_KEY: &str = "pending";
+
+/// Metadata key holding the import session a quarantined row came in with
+/// (see quarantine.rs)
+pub const QUARANTINE_KEY: &str = "quarantined";
+
+/// Which date places a transaction in a period: the date it was made, or the
+/// date the card posted it (statements cut on the latter)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateBasis {
+    #[default]
+    Transaction,
+    Posted,
+}
+
+impl DateBasis {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DateBasis::Transaction => "transaction",
+            DateBasis::Posted => "posted",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "transaction" | "statement" => Ok(DateBasis::Transaction),
+            "posted" | "posting" => Ok(DateBasis::Posted),
+            other => Err(anyhow::anyhow!("Unknown date basis '{}' (transaction or posted)", other)),
+        }
+    }
+}
+
 impl Transaction {
     /// Compute idempotency hash for duplicate detection
     /// NOTE: This is for DEDUPLICATION, not IDENTITY!
@@ -150,6 +193,29 @@ impl Transaction {
         }
     }
 
+    /// Statement date as a calendar date (MM/DD/YYYY, or YYYY-MM-DD)
+    pub fn parsed_date(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%m/%d/%Y")
+            .or_else(|_| NaiveDate::parse_from_str(&self.date, "%Y-%m-%d"))
+            .ok()
+    }
+
+    /// Date the card posted the charge (metadata "posted_date"), when the feed gave one
+    pub fn posted_date(&self) -> Option<NaiveDate> {
+        let posted = self.get_metadata(POSTED_DATE_KEY)?.as_str()?;
+        NaiveDate::parse_from_str(posted, "%m/%d/%Y")
+            .or_else(|_| NaiveDate::parse_from_str(posted, "%Y-%m-%d"))
+            .ok()
+    }
+
+    /// Date on `basis`; rows without a posted date fall back to their own date
+    pub fn date_on(&self, basis: DateBasis) -> Option<NaiveDate> {
+        match basis {
+            DateBasis::Transaction => self.parsed_date(),
+            DateBasis::Posted => self.posted_date().or_else(|| self.parsed_date()),
+        }
+    }
+
     /// Check if this transaction is current (no valid_until)
     pub fn is_current(&self) -> bool {
         self.valid_until.is_none()
@@ -188,6 +254,10 @@ impl Transaction {
         next.valid_until = None;  // New version is current
         next.previous_version_id = Some(self.id.clone());
 
+        // A regular change starts a new branch: nothing left to redo
+        next.metadata.remove("restores_version");
+        next.metadata.remove("redo_stack");
+
         // Store change reason in metadata
         if let Some(reason) = change_reason {
             next.metadata.insert(
@@ -257,6 +327,51 @@ impl Transaction {
         );
     }
 
+    /// Mark this transaction as voided (excluded from reports and balances)
+    pub fn set_voided(&mut self, reason: &str, voided_by: &str, voided_at: DateTime<Utc>) {
+        self.metadata
+            .insert("voided".to_string(), serde_json::json!(true));
+        self.metadata
+            .insert("void_reason".to_string(), serde_json::json!(reason));
+        self.metadata
+            .insert("voided_by".to_string(), serde_json::json!(voided_by));
+        self.metadata.insert(
+            "voided_at".to_string(),
+            serde_json::json!(voided_at.to_rfc3339()),
+        );
+    }
+
+    /// Check if this version is voided
+    pub fn is_voided(&self) -> bool {
+        self.get_metadata("voided")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// Get the void reason, if voided
+    pub fn void_reason(&self) -> Option<&str> {
+        if !self.is_voided() {
+            return None;
+        }
+        self.get_metadata("void_reason").and_then(|v| v.as_str())
+    }
+
+    /// Reported by the source before it settled (see settlement.rs)
+    pub fn is_pending(&self) -> bool {
+        self.get_metadata(PENDING_KEY).and_then(|v| v.as_bool()).unwrap_or(false)
+    }
+
+    /// Held back by the import quality gate until reviewed
+    pub fn is_quarantined(&self) -> bool {
+        self.metadata.contains_key(QUARANTINE_KEY)
+    }
+
+    /// Current, not voided and not quarantined: counts toward reports and
+    /// balances
+    pub fn is_active(&self) -> bool {
+        self.is_current() && !self.is_voided() && !self.is_quarantined()
+    }
+
     /// Get metadata value by key
     pub fn get_metadata(&self, key: &str) -> Option<&serde_json::Value> {
         self.metadata.get(key)
@@ -266,6 +381,15 @@ impl Transaction {
     pub fn has_metadata(&self, key: &str) -> bool {
         self.metadata.contains_key(key)
     }
+
+    /// Merchant category code from the source feed (metadata "mcc")
+    pub fn mcc(&self) -> Option<u16> {
+        match self.metadata.get("mcc")? {
+            serde_json::Value::Number(n) => n.as_u64().and_then(|code| u16::try_from(code).ok()),
+            serde_json::Value::String(s) => crate::mcc::parse_mcc(s),
+            _ => None,
+        }
+    }
 }
 
 /// Event for audit trail (Rich Hickey: "Every change is an event")
@@ -278,6 +402,8 @@ pub struct Event {
     pub entity_id: String,
     pub data: serde_json::Value,
     pub actor: String,
+    #[serde(default = "crate::ledger::default_ledger_id")]
+    pub ledger_id: String,
 }
 
 impl Event {
@@ -296,10 +422,48 @@ impl Event {
             entity_id: entity_id.to_string(),
             data,
             actor: actor.to_string(),
+            ledger_id: crate::ledger::default_ledger_id(),
+        }
+    }
+
+    /// Event whose data is a registered payload (see event_schema.rs)
+    pub fn typed<P: EventPayload>(
+        event_type: &str,
+        entity_type: &str,
+        entity_id: &str,
+        payload: &P,
+        actor: &str,
+    ) -> Result<Self> {
+        if !P::EVENT_TYPES.contains(&event_type) {
+            return Err(anyhow::anyhow!(
+                "{} is not the payload of '{}' events",
+                std::any::type_name::<P>().rsplit("::").next().unwrap_or_default(),
+                event_type
+            ));
         }
+        Ok(Self::new(event_type, entity_type, entity_id, serde_json::to_value(payload)?, actor))
+    }
+
+    /// This event's data as its payload type
+    pub fn payload<P: EventPayload>(&self) -> Result<P> {
+        if !P::EVENT_TYPES.contains(&self.event_type.as_str()) {
+            return Err(anyhow::anyhow!("'{}' events don't carry this payload", self.event_type));
+        }
+        P::deserialize(&self.data).with_context(|| format!("Invalid '{}' event payload", self.event_type))
+    }
+
+    /// Attribute this event to a ledger (builder)
+    pub fn with_ledger(mut self, ledger_id: &str) -> Self {
+        self.ledger_id = ledger_id.to_string();
+        self
     }
 }
 
+/// Schema version written by setup_database (PRAGMA user_version)
+///
+/// Bump when setup_database gains a table, column or index.
+pub const SCHEMA_VERSION: i64 = 23;
+
 pub fn setup_database(conn: &Connection) -> Result<()> {
     // Enable WAL mode for crash recovery
     conn.pragma_update(None, "journal_mode", "WAL")?;
@@ -307,52 +471,468 @@ pub fn setup_database(conn: &Connection) -> Result<()> {
     // ==========================================================================
     // Transactions Table (with extensible metadata column)
     // Badge 19: Added temporal fields (tx_uuid, version, time model)
+    // One row per (tx_uuid, version) - corrections append rows, never overwrite
+    // ==========================================================================
+    conn.execute(&transactions_table_sql("transactions"), [])?;
+    migrate_transactions_table(conn)?;
+
+    // ==========================================================================
+    // Events Table (audit trail / event sourcing)
     // ==========================================================================
     conn.execute(
-        "CREATE TABLE IF NOT EXISTS transactions (
+        "CREATE TABLE IF NOT EXISTS events (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
-            idempotency_hash TEXT UNIQUE NOT NULL,
-            date TEXT NOT NULL,
-            description TEXT NOT NULL,
-            amount_original TEXT NOT NULL,
-            amount_numeric REAL NOT NULL,
-            transaction_type TEXT NOT NULL,
-            category TEXT NOT NULL,
-            merchant TEXT NOT NULL,
-            currency TEXT NOT NULL,
-            account_name TEXT NOT NULL,
-            account_number TEXT NOT NULL,
-            bank TEXT NOT NULL,
-            source_file TEXT NOT NULL,
-            line_number TEXT NOT NULL,
-            classification_notes TEXT,
-            metadata TEXT,
+            event_id TEXT UNIQUE NOT NULL,
+            timestamp TEXT NOT NULL,
+            event_type TEXT NOT NULL,
+            entity_type TEXT NOT NULL,
+            entity_id TEXT NOT NULL,
+            data TEXT NOT NULL,
+            actor TEXT NOT NULL,
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            -- Badge 19: Time & Identity Model (Rich Hickey's philosophy)
-            tx_uuid TEXT UNIQUE,
-            version INTEGER DEFAULT 1,
-            system_time TEXT,
-            valid_from TEXT,
+            ledger_id TEXT NOT NULL DEFAULT 'default'
+        )",
+        [],
+    )?;
+    add_missing_columns(conn, "events", &[("ledger_id", "TEXT NOT NULL DEFAULT 'default'")])?;
+
+    // ==========================================================================
+    // Ledgers Table (independent books: personal, business, ...)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ledgers (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            config TEXT NOT NULL DEFAULT '{}',
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "INSERT OR IGNORE INTO ledgers (id, name, config, created_at) VALUES (?1, 'Default', '{}', ?2)",
+        params![crate::ledger::DEFAULT_LEDGER_ID, Utc::now().to_rfc3339()],
+    )?;
+
+    // ==========================================================================
+    // Users Table (who may read/correct; username is the event actor)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS users (
+            id TEXT PRIMARY KEY,
+            username TEXT UNIQUE NOT NULL,
+            role TEXT NOT NULL,
+            token_hash TEXT UNIQUE NOT NULL,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Pending Changes (corrections/voids awaiting a second actor's approval)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pending_changes (
+            id TEXT PRIMARY KEY,
+            kind TEXT NOT NULL,
+            status TEXT NOT NULL,
+            tx_uuid TEXT NOT NULL,
+            ledger_id TEXT NOT NULL,
+            base_version INTEGER NOT NULL,
+            proposed TEXT NOT NULL,
+            proposed_by TEXT NOT NULL,
+            proposed_at TEXT NOT NULL,
+            decided_by TEXT,
+            decided_at TEXT,
+            decision_note TEXT
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Transaction Notes (free-text comments, threaded via parent_id)
+    // Keyed by tx_uuid so every version of a transaction shares its notes
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS transaction_notes (
+            id TEXT PRIMARY KEY,
+            tx_uuid TEXT NOT NULL,
+            ledger_id TEXT NOT NULL,
+            parent_id TEXT,
+            author TEXT NOT NULL,
+            text TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Disputes (charges contested with the bank; transitions live in events)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS disputes (
+            id TEXT PRIMARY KEY,
+            tx_uuid TEXT NOT NULL,
+            ledger_id TEXT NOT NULL,
+            status TEXT NOT NULL,
+            opened_by TEXT NOT NULL,
+            opened_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            resolved_at TEXT,
+            note TEXT
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Merchant Enrichment Cache (one answer per provider and merchant;
+    // result NULL = provider doesn't know the merchant)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS merchant_enrichment_cache (
+            provider TEXT NOT NULL,
+            merchant_key TEXT NOT NULL,
+            result TEXT,
+            fetched_at TEXT NOT NULL,
+            PRIMARY KEY (provider, merchant_key)
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Projects (trips/projects with a date range) and their transactions
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS projects (
+            id TEXT PRIMARY KEY,
+            ledger_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            start_date TEXT NOT NULL,
+            end_date TEXT NOT NULL,
+            rules TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS project_transactions (
+            project_id TEXT NOT NULL,
+            tx_uuid TEXT NOT NULL,
+            method TEXT NOT NULL,
+            assigned_by TEXT NOT NULL,
+            assigned_at TEXT NOT NULL,
+            PRIMARY KEY (project_id, tx_uuid)
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Expected transactions (recurring bills matched against imports)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS expected_transactions (
+            id TEXT PRIMARY KEY,
+            ledger_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            merchant_pattern TEXT NOT NULL,
+            amount REAL NOT NULL,
+            tolerance REAL NOT NULL,
+            currency TEXT NOT NULL,
+            day_of_month INTEGER NOT NULL,
+            grace_days INTEGER NOT NULL,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Balance snapshots at each statement close (one per ledger, account and close date)
+    // ==========================================================================
+    conn.execute(&balance_snapshots_table_sql("balance_snapshots"), [])?;
+    migrate_balance_snapshots_table(conn)?;
+
+    // ==========================================================================
+    // Accounts (one row per version; the current version has valid_until NULL)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS accounts (
+            id TEXT NOT NULL,
+            version INTEGER NOT NULL,
+            ledger_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            data TEXT NOT NULL,
+            valid_from TEXT NOT NULL,
             valid_until TEXT,
-            previous_version_id TEXT
+            PRIMARY KEY (id, version)
         )",
         [],
     )?;
 
     // ==========================================================================
-    // Events Table (audit trail / event sourcing)
+    // Duplicate review decisions (by cluster key, so decided clusters stay hidden)
     // ==========================================================================
     conn.execute(
-        "CREATE TABLE IF NOT EXISTS events (
+        "CREATE TABLE IF NOT EXISTS duplicate_decisions (
+            cluster_key TEXT PRIMARY KEY,
+            ledger_id TEXT NOT NULL,
+            decision TEXT NOT NULL,
+            tx_uuids TEXT NOT NULL,
+            decided_by TEXT NOT NULL,
+            decided_at TEXT NOT NULL,
+            defer_until TEXT
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Rules Table (Badge 30 - Rules as Data, versioned like entities)
+    // One row per (rule_id, version); the current version has valid_until NULL
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS rules (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
-            event_id TEXT UNIQUE NOT NULL,
-            timestamp TEXT NOT NULL,
+            rule_id TEXT NOT NULL,
+            version INTEGER NOT NULL,
+            definition TEXT NOT NULL,
+            changed_by TEXT NOT NULL,
+            change_reason TEXT,
+            system_time TEXT NOT NULL,
+            valid_from TEXT NOT NULL,
+            valid_until TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(rule_id, version)
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Job Queue (long operations submitted over the API, run by a worker;
+    // payload holds uploaded bytes, params/result are JSON)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS job_queue (
+            id TEXT PRIMARY KEY,
+            ledger_id TEXT NOT NULL,
+            spec TEXT NOT NULL,
+            payload BLOB,
+            status TEXT NOT NULL,
+            progress_done INTEGER NOT NULL DEFAULT 0,
+            progress_total INTEGER NOT NULL DEFAULT 0,
+            message TEXT,
+            result TEXT,
+            error TEXT,
+            created_by TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            started_at TEXT,
+            finished_at TEXT
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Source Archive (original statement files, gzip, keyed by SHA-256)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS source_archive (
+            sha256 TEXT PRIMARY KEY,
+            filename TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            content BLOB NOT NULL,
+            archived_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Import Recordings (inputs and outcome of an import, for replay)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS import_recordings (
+            session_id TEXT PRIMARY KEY,
+            ledger_id TEXT NOT NULL,
+            filename TEXT NOT NULL,
+            content BLOB NOT NULL,
+            content_hash TEXT NOT NULL,
+            config TEXT NOT NULL,
+            rules TEXT NOT NULL,
+            rule_versions TEXT NOT NULL,
+            recorded_at TEXT NOT NULL,
+            actor TEXT NOT NULL,
+            outcome TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Sync State (this instance's id for changeset export/import)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sync_state (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Webhooks and their outbox (one row per event and webhook; see outbox.rs)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS webhooks (
+            id TEXT PRIMARY KEY,
+            url TEXT NOT NULL,
+            event_types TEXT NOT NULL,
+            ledger_id TEXT,
+            secret TEXT NOT NULL,
+            active INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS outbox (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            webhook_id TEXT NOT NULL,
+            event_id TEXT NOT NULL,
             event_type TEXT NOT NULL,
+            status TEXT NOT NULL,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            next_attempt_at TEXT NOT NULL,
+            last_error TEXT,
+            delivered_at TEXT,
+            created_at TEXT NOT NULL,
+            UNIQUE(webhook_id, event_id)
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Merchant alias review decisions (by group key, per ledger)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS alias_decisions (
+            group_key TEXT NOT NULL,
+            ledger_id TEXT NOT NULL,
+            decision TEXT NOT NULL,
+            canonical TEXT NOT NULL,
+            names TEXT NOT NULL,
+            decided_by TEXT NOT NULL,
+            decided_at TEXT NOT NULL,
+            PRIMARY KEY (ledger_id, group_key)
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Amount history of recurring bills (one row per detected price change)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS bill_price_changes (
+            bill_id TEXT NOT NULL,
+            tx_uuid TEXT NOT NULL,
+            bill_name TEXT NOT NULL,
+            effective_date TEXT NOT NULL,
+            previous_amount REAL NOT NULL,
+            new_amount REAL NOT NULL,
+            detected_by TEXT NOT NULL,
+            detected_at TEXT NOT NULL,
+            PRIMARY KEY (bill_id, tx_uuid)
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Budget envelopes (append-only versions, like rules; see envelopes.rs)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS envelopes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            envelope_id TEXT NOT NULL,
+            ledger_id TEXT NOT NULL,
+            version INTEGER NOT NULL,
+            definition TEXT NOT NULL,
+            changed_by TEXT NOT NULL,
+            change_reason TEXT,
+            system_time TEXT NOT NULL,
+            valid_from TEXT NOT NULL,
+            valid_until TEXT,
+            UNIQUE(envelope_id, version)
+        )",
+        [],
+    )?;
+
+    // ==========================================================================
+    // Brokerage positions (one snapshot per account and as-of date; see positions.rs)
+    // ==========================================================================
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS positions (
+            ledger_id TEXT NOT NULL,
+            account TEXT NOT NULL,
+            symbol TEXT NOT NULL,
+            as_of TEXT NOT NULL,
+            quantity REAL NOT NULL,
+            cost_basis REAL,
+            currency TEXT NOT NULL,
+            statement_price REAL,
+            source_file TEXT NOT NULL,
+            recorded_by TEXT NOT NULL,
+            recorded_at TEXT NOT NULL,
+            PRIMARY KEY (ledger_id, account, as_of, symbol)
+        )",
+        [],
+    )?;
+
+    // Statement periods locked against corrections (see period_close.rs);
+    // a reopened period keeps its row with who reopened it and why
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS closed_periods (
+            ledger_id TEXT NOT NULL,
+            account_id TEXT NOT NULL,
+            statement_period TEXT NOT NULL,
+            period_start TEXT NOT NULL,
+            period_end TEXT NOT NULL,
+            closed_by TEXT NOT NULL,
+            closed_at TEXT NOT NULL,
+            reopened_by TEXT,
+            reopened_at TEXT,
+            reopen_reason TEXT,
+            PRIMARY KEY (ledger_id, account_id, period_end)
+        )",
+        [],
+    )?;
+
+    // Chart of accounts mappings (append-only versions, like envelopes)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS chart_mappings (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            mapping_id TEXT NOT NULL,
+            ledger_id TEXT NOT NULL,
+            version INTEGER NOT NULL,
+            definition TEXT NOT NULL,
+            changed_by TEXT NOT NULL,
+            change_reason TEXT,
+            system_time TEXT NOT NULL,
+            valid_from TEXT NOT NULL,
+            valid_until TEXT,
+            UNIQUE(mapping_id, version)
+        )",
+        [],
+    )?;
+
+    // Entity registry versions (merchants, banks, categories, accounts; see
+    // entities/store.rs). One row per (entity_type, id, version); the entity
+    // itself is stored as JSON, expiring a version updates its valid_until
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS entity_versions (
             entity_type TEXT NOT NULL,
-            entity_id TEXT NOT NULL,
+            id TEXT NOT NULL,
+            version INTEGER NOT NULL,
+            ledger_id TEXT NOT NULL,
             data TEXT NOT NULL,
-            actor TEXT NOT NULL,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            valid_from TEXT NOT NULL,
+            valid_until TEXT,
+            PRIMARY KEY (entity_type, id, version)
         )",
         [],
     )?;
@@ -365,6 +945,20 @@ pub fn setup_database(conn: &Connection) -> Result<()> {
         [],
     )?;
 
+    // Deduplication applies to CURRENT versions only (history keeps the same hash),
+    // and per ledger: the same line may legitimately exist in two ledgers
+    conn.execute("DROP INDEX IF EXISTS idx_current_idempotency_hash", [])?;
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_current_ledger_idempotency_hash
+         ON transactions(ledger_id, idempotency_hash) WHERE valid_until IS NULL",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_tx_uuid_version ON transactions(tx_uuid, version)",
+        [],
+    )?;
+
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_date ON transactions(date)",
         [],
@@ -375,6 +969,18 @@ pub fn setup_database(conn: &Connection) -> Result<()> {
         [],
     )?;
 
+    // Common filters (doctor's "indexes" check explains these queries)
+    for (name, columns) in [
+        ("idx_transaction_type", "transaction_type"),
+        ("idx_category", "category"),
+        ("idx_merchant", "merchant"),
+        ("idx_amount_numeric", "amount_numeric"),
+        ("idx_valid_until", "valid_until"),
+        ("idx_date_bank", "date, bank"),
+    ] {
+        conn.execute(&format!("CREATE INDEX IF NOT EXISTS {} ON transactions({})", name, columns), [])?;
+    }
+
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_events_entity ON events(entity_type, entity_id)",
         [],
@@ -385,243 +991,964 @@ pub fn setup_database(conn: &Connection) -> Result<()> {
         [],
     )?;
 
-    Ok(())
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_events_type ON events(event_type, timestamp)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_ledger ON transactions(ledger_id)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_pending_changes_status ON pending_changes(status, tx_uuid)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_transaction_notes_tx ON transaction_notes(tx_uuid, created_at)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_disputes_tx ON disputes(tx_uuid, status)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_job_queue_status ON job_queue(status, created_at)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_rules_rule_id ON rules(rule_id, valid_until)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_outbox_due ON outbox(status, next_attempt_at)",
+        [],
+    )?;
+
+    // Everything above is applied: record it so `doctor` can spot drift
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+
+    Ok(())
+}
+
+/// DDL for the transactions table
+///
+/// Uniqueness is enforced by partial indexes (see setup_database) instead of
+/// column constraints, so older versions of a transaction can share its
+/// idempotency hash and UUID.
+fn transactions_table_sql(table: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            idempotency_hash TEXT NOT NULL,
+            date TEXT NOT NULL,
+            description TEXT NOT NULL,
+            amount_original TEXT NOT NULL,
+            amount_numeric REAL NOT NULL,
+            transaction_type TEXT NOT NULL,
+            category TEXT NOT NULL,
+            merchant TEXT NOT NULL,
+            currency TEXT NOT NULL,
+            account_name TEXT NOT NULL,
+            account_number TEXT NOT NULL,
+            bank TEXT NOT NULL,
+            source_file TEXT NOT NULL,
+            line_number TEXT NOT NULL,
+            classification_notes TEXT,
+            metadata TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            -- Badge 19: Time & Identity Model (Rich Hickey's philosophy)
+            tx_uuid TEXT,
+            version INTEGER DEFAULT 1,
+            system_time TEXT,
+            valid_from TEXT,
+            valid_until TEXT,
+            previous_version_id TEXT,
+            ledger_id TEXT NOT NULL DEFAULT 'default'
+        )",
+        table
+    )
+}
+
+/// Bring a transactions table created by an older build up to date
+///
+/// 1. Adds columns that did not exist yet (metadata, Badge 19 temporal fields, ledger_id)
+/// 2. Rebuilds the table if it still has the UNIQUE column constraints that
+///    prevent storing more than one version per transaction
+fn migrate_transactions_table(conn: &Connection) -> Result<()> {
+    add_missing_columns(
+        conn,
+        "transactions",
+        &[
+            ("metadata", "TEXT"),
+            ("tx_uuid", "TEXT"),
+            ("version", "INTEGER DEFAULT 1"),
+            ("system_time", "TEXT"),
+            ("valid_from", "TEXT"),
+            ("valid_until", "TEXT"),
+            ("previous_version_id", "TEXT"),
+            ("ledger_id", "TEXT NOT NULL DEFAULT 'default'"),
+        ],
+    )?;
+
+    let table_sql: String = conn.query_row(
+        "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'transactions'",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if table_sql.contains("idempotency_hash TEXT UNIQUE") || table_sql.contains("tx_uuid TEXT UNIQUE") {
+        let column_list = "id, idempotency_hash, date, description, amount_original, amount_numeric,
+            transaction_type, category, merchant, currency, account_name, account_number,
+            bank, source_file, line_number, classification_notes, metadata, created_at,
+            tx_uuid, version, system_time, valid_from, valid_until, previous_version_id, ledger_id";
+
+        conn.execute_batch(&format!(
+            "BEGIN;
+             {};
+             INSERT INTO transactions_rebuild ({cols}) SELECT {cols} FROM transactions;
+             DROP TABLE transactions;
+             ALTER TABLE transactions_rebuild RENAME TO transactions;
+             COMMIT;",
+            transactions_table_sql("transactions_rebuild"),
+            cols = column_list
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// DDL for the balance_snapshots table
+fn balance_snapshots_table_sql(table: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} (
+            account_id TEXT NOT NULL,
+            ledger_id TEXT NOT NULL,
+            statement_period TEXT NOT NULL,
+            statement_date TEXT NOT NULL,
+            opening_balance REAL NOT NULL,
+            closing_balance REAL NOT NULL,
+            recorded_at TEXT NOT NULL,
+            declared_count INTEGER,
+            declared_total REAL,
+            PRIMARY KEY (ledger_id, account_id, statement_date)
+        )",
+        table
+    )
+}
+
+/// Bring a balance_snapshots table created by an older build up to date
+///
+/// 1. Adds the declared count and total columns
+/// 2. Rebuilds the table if its key is still (account_id, statement_date),
+///    which let two ledgers overwrite each other's snapshot of an account
+fn migrate_balance_snapshots_table(conn: &Connection) -> Result<()> {
+    add_missing_columns(conn, "balance_snapshots", &[("declared_count", "INTEGER"), ("declared_total", "REAL")])?;
+
+    let table_sql: String = conn.query_row(
+        "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'balance_snapshots'",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if table_sql.contains("PRIMARY KEY (account_id, statement_date)") {
+        let column_list = "account_id, ledger_id, statement_period, statement_date, opening_balance,
+            closing_balance, recorded_at, declared_count, declared_total";
+
+        conn.execute_batch(&format!(
+            "BEGIN;
+             {};
+             INSERT INTO balance_snapshots_rebuild ({cols}) SELECT {cols} FROM balance_snapshots;
+             DROP TABLE balance_snapshots;
+             ALTER TABLE balance_snapshots_rebuild RENAME TO balance_snapshots;
+             COMMIT;",
+            balance_snapshots_table_sql("balance_snapshots_rebuild"),
+            cols = column_list
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// ALTER TABLE ... ADD COLUMN for every column the table doesn't have yet
+fn add_missing_columns(conn: &Connection, table: &str, columns: &[(&str, &str)]) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let existing: Vec<String> = stmt
+        .query_map([], |row| row.get(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (name, sql_type) in columns {
+        if !existing.iter().any(|c| c == name) {
+            conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, name, sql_type),
+                [],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn load_csv(csv_path: &Path) -> Result<Vec<Transaction>> {
+    let mut rdr = csv::Reader::from_path(csv_path).context("Failed to open CSV file")?;
+
+    let mut transactions = Vec::new();
+
+    // Optional MCC column (card feeds) goes to metadata, see Transaction::mcc
+    let headers = rdr.headers()?.clone();
+    let mcc_column = headers.iter().position(|h| h.eq_ignore_ascii_case("mcc"));
+
+    for record in rdr.records() {
+        let record = record.context("Failed to read CSV record")?;
+        let mut transaction: Transaction = record
+            .deserialize(Some(&headers))
+            .context("Failed to deserialize transaction")?;
+        if let Some(mcc) = mcc_column.and_then(|i| record.get(i)).and_then(crate::mcc::parse_mcc) {
+            transaction.metadata.insert("mcc".to_string(), serde_json::json!(mcc));
+        }
+
+        // Initialize temporal fields (UUID, version, timestamps) - Badge 19
+        transaction.init_temporal_fields();
+
+        // Add provenance metadata
+        transaction.set_provenance(
+            Utc::now(),
+            "csv_loader_v1.0",
+            vec!["loaded_from_csv".to_string()],
+        );
+
+        // City/country hints at the end of card descriptions (see location.rs)
+        crate::location::annotate_location(&mut transaction);
+
+        transactions.push(transaction);
+    }
+
+    Ok(transactions)
+}
+
+/// A row `insert_transactions_as` could not store
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RowError {
+    /// Position in the slice passed in
+    pub index: usize,
+    pub line_number: String,
+    pub message: String,
+}
+
+/// What a batch insert did; inserting the same batch again gives
+/// `inserted: 0` and every row under `duplicates`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct InsertSummary {
+    pub inserted: usize,
+    /// Already stored (same idempotency hash in the ledger), skipped
+    pub duplicates: usize,
+    pub failed: Vec<RowError>,
+}
+
+/// Insert transactions, recording `actor` (the importing user) on each event
+///
+/// A row that fails for any reason other than being a duplicate is reported
+/// in `failed` and the rest of the batch still goes in.
+pub fn insert_transactions_as(
+    conn: &Connection,
+    transactions: &[Transaction],
+    actor: &str,
+) -> Result<InsertSummary> {
+    require_actor(actor)?;
+
+    let mut summary = InsertSummary::default();
+    for (index, tx) in transactions.iter().enumerate() {
+        match insert_transaction_as(conn, tx, actor) {
+            Ok(true) => summary.inserted += 1,
+            Ok(false) => summary.duplicates += 1,
+            Err(e) => summary.failed.push(RowError {
+                index,
+                line_number: tx.line_number.clone(),
+                message: format!("{:#}", e),
+            }),
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Insert one transaction without printing; `false` when it is a duplicate
+pub fn insert_transaction_as(conn: &Connection, tx: &Transaction, actor: &str) -> Result<bool> {
+    require_actor(actor)?;
+    let hash = tx.compute_idempotency_hash();
+
+    match insert_transaction_row(conn, tx, &hash) {
+        Ok(_) => {
+            // Log event to audit trail
+            let payload = TransactionAdded {
+                bank: tx.bank.clone(),
+                amount: tx.amount_numeric,
+                source_file: tx.source_file.clone(),
+            };
+            if let Ok(event) = Event::typed("transaction_added", "transaction", &hash, &payload, actor) {
+                let _ = insert_event(conn, &event.with_ledger(&tx.ledger_id));
+            }
+            Ok(true)
+        }
+        Err(e) if is_duplicate_insert(conn, &e, &tx.ledger_id, &hash)? => Ok(false),
+        Err(e) => Err(anyhow::Error::from(e)
+            .context(format!("Could not store transaction {} (line {})", tx.id, tx.line_number))),
+    }
+}
+
+/// Whether an insert failed only because the row is already stored: the
+/// ledger has a current row with the same idempotency hash
+///
+/// Any other constraint (NOT NULL, UNIQUE(tx_uuid, version) for different
+/// content, ...) means the row itself or the caller is wrong, and must not be
+/// counted as a duplicate.
+pub(crate) fn is_duplicate_insert(conn: &Connection, error: &rusqlite::Error, ledger_id: &str, hash: &str) -> Result<bool> {
+    let rusqlite::Error::SqliteFailure(err, message) = error else {
+        return Ok(false);
+    };
+    if err.extended_code != rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE {
+        return Ok(false);
+    }
+    if message.as_deref().is_some_and(|message| message.contains("idempotency_hash")) {
+        return Ok(true);
+    }
+    // Another unique index fired first; still a duplicate if the same row is there
+    let stored: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM transactions WHERE ledger_id = ?1 AND idempotency_hash = ?2 AND valid_until IS NULL",
+        params![ledger_id, hash],
+        |row| row.get(0),
+    )?;
+    Ok(stored > 0)
+}
+
+/// Insert one transaction row exactly as given (temporal fields included)
+///
+/// Constraint violations (duplicate hash or version) are returned as-is so
+/// callers can count them as duplicates.
+pub(crate) fn insert_transaction_row(
+    conn: &Connection,
+    tx: &Transaction,
+    hash: &str,
+) -> rusqlite::Result<usize> {
+    let metadata_json = serde_json::to_string(&tx.metadata)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+    // Serialize temporal fields (Badge 19)
+    let system_time_str = tx.system_time.map(|dt| dt.to_rfc3339());
+    let valid_from_str = tx.valid_from.map(|dt| dt.to_rfc3339());
+    let valid_until_str = tx.valid_until.map(|dt| dt.to_rfc3339());
+
+    conn.execute(
+        "INSERT INTO transactions (
+            idempotency_hash, date, description, amount_original, amount_numeric,
+            transaction_type, category, merchant, currency, account_name,
+            account_number, bank, source_file, line_number, classification_notes,
+            metadata,
+            tx_uuid, version, system_time, valid_from, valid_until, previous_version_id,
+            ledger_id
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)",
+        params![
+            hash,
+            tx.date,
+            tx.description,
+            tx.amount_original,
+            tx.amount_numeric,
+            tx.transaction_type,
+            tx.category,
+            tx.merchant,
+            tx.currency,
+            tx.account_name,
+            tx.account_number,
+            tx.bank,
+            tx.source_file,
+            tx.line_number,
+            tx.classification_notes,
+            metadata_json,
+            // Badge 19 temporal fields
+            if tx.id.is_empty() { None } else { Some(&tx.id) },
+            tx.version,
+            system_time_str,
+            valid_from_str,
+            valid_until_str,
+            tx.previous_version_id,
+            tx.ledger_id,
+        ],
+    )
+}
+
+/// Insert event into audit trail (its data must match the event type's payload)
+pub fn insert_event(conn: &Connection, event: &Event) -> Result<()> {
+    validate_event_data(&event.event_type, &event.data)?;
+    let data_json = serde_json::to_string(&event.data)?;
+
+    conn.execute(
+        "INSERT INTO events (
+            event_id, timestamp, event_type, entity_type, entity_id, data, actor, ledger_id
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            event.event_id,
+            event.timestamp.to_rfc3339(),
+            event.event_type,
+            event.entity_type,
+            event.entity_id,
+            data_json,
+            event.actor,
+            event.ledger_id,
+        ],
+    )?;
+    enqueue_event(conn, event)?;
+
+    Ok(())
+}
+
+/// Get events for a specific entity
+pub fn get_events_for_entity(
+    conn: &Connection,
+    entity_type: &str,
+    entity_id: &str,
+) -> Result<Vec<Event>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM events
+         WHERE entity_type = ?1 AND entity_id = ?2
+         ORDER BY timestamp DESC",
+        EVENT_SELECT_COLUMNS
+    ))?;
+
+    let events = stmt
+        .query_map(params![entity_type, entity_id], row_to_event)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(events)
+}
+
+/// One event by its id
+pub fn get_event(conn: &Connection, event_id: &str) -> Result<Option<Event>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM events WHERE event_id = ?1", EVENT_SELECT_COLUMNS))?;
+    let mut events = stmt.query_map([event_id], row_to_event)?;
+    Ok(events.next().transpose()?)
+}
+
+/// Events with a sequence number (the events table's rowid) above `after`,
+/// oldest first
+pub fn get_events_after(conn: &Connection, after: i64, limit: usize) -> Result<Vec<(i64, Event)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, id FROM events WHERE id > ?1 ORDER BY id ASC LIMIT ?2",
+        EVENT_SELECT_COLUMNS
+    ))?;
+
+    let events = stmt
+        .query_map(params![after, limit as i64], |row| Ok((row.get(8)?, row_to_event(row)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(events)
+}
+
+/// EventFilter - Which events `query_events` returns
+///
+/// Empty filter matches every event. `since` and `until` are inclusive;
+/// `limit` and `offset` page through the matches, newest first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventFilter {
+    pub ledger_id: Option<String>,
+    pub event_type: Option<String>,
+    pub entity_type: Option<String>,
+    pub actor: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// None = no limit
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+/// WHERE clause shared by query_events and count_events (parameters ?1-?6)
+const EVENT_FILTER_WHERE: &str = "(?1 IS NULL OR ledger_id = ?1)
+         AND (?2 IS NULL OR event_type = ?2)
+         AND (?3 IS NULL OR entity_type = ?3)
+         AND (?4 IS NULL OR actor = ?4)
+         AND (?5 IS NULL OR timestamp >= ?5)
+         AND (?6 IS NULL OR timestamp <= ?6)";
+
+impl EventFilter {
+    // Timestamps are stored as UTC RFC 3339, so they compare as strings
+    fn params(&self) -> [Option<String>; 6] {
+        [
+            self.ledger_id.clone(),
+            self.event_type.clone(),
+            self.entity_type.clone(),
+            self.actor.clone(),
+            self.since.map(|time| time.to_rfc3339()),
+            self.until.map(|time| time.to_rfc3339()),
+        ]
+    }
+}
+
+/// Events matching `filter`, newest first
+pub fn query_events(conn: &Connection, filter: &EventFilter) -> Result<Vec<Event>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM events WHERE {}
+         ORDER BY timestamp DESC, id DESC
+         LIMIT ?7 OFFSET ?8",
+        EVENT_SELECT_COLUMNS, EVENT_FILTER_WHERE
+    ))?;
+
+    let [ledger_id, event_type, entity_type, actor, since, until] = filter.params();
+    // SQLite reads a negative LIMIT as "no limit"
+    let limit = filter.limit.map_or(-1, |limit| limit as i64);
+    let events = stmt
+        .query_map(
+            params![ledger_id, event_type, entity_type, actor, since, until, limit, filter.offset as i64],
+            row_to_event,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(events)
+}
+
+/// Number of events matching `filter`, ignoring its limit and offset
+pub fn count_events(conn: &Connection, filter: &EventFilter) -> Result<usize> {
+    let count: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM events WHERE {}", EVENT_FILTER_WHERE),
+        rusqlite::params_from_iter(filter.params()),
+        |row| row.get(0),
+    )?;
+    Ok(count as usize)
+}
+
+/// Columns read by row_to_event, in order
+pub(crate) const EVENT_SELECT_COLUMNS: &str =
+    "event_id, timestamp, event_type, entity_type, entity_id, data, actor, ledger_id";
+
+pub(crate) fn row_to_event(row: &rusqlite::Row) -> rusqlite::Result<Event> {
+    let timestamp_str: String = row.get(1)?;
+    let data_json: String = row.get(5)?;
+
+    Ok(Event {
+        event_id: row.get(0)?,
+        timestamp: DateTime::parse_from_rfc3339(&timestamp_str)
+            .map_err(|e| rusqlite::Error::InvalidQuery)?
+            .with_timezone(&Utc),
+        event_type: row.get(2)?,
+        entity_type: row.get(3)?,
+        entity_id: row.get(4)?,
+        data: serde_json::from_str(&data_json)
+            .map_err(|_| rusqlite::Error::InvalidQuery)?,
+        actor: row.get(6)?,
+        ledger_id: row.get(7)?,
+    })
 }
 
-pub fn load_csv(csv_path: &Path) -> Result<Vec<Transaction>> {
-    let mut rdr = csv::Reader::from_path(csv_path).context("Failed to open CSV file")?;
+/// Current version of every transaction (one row per identity, voided included)
+///
+/// Corrections append rows, so reading the table as-is counts a corrected
+/// amount once per version; use `get_transactions(conn, true)` for history.
+pub fn get_all_transactions(conn: &Connection) -> Result<Vec<Transaction>> {
+    get_transactions(conn, false)
+}
 
-    let mut transactions = Vec::new();
+/// Every transaction; with `include_history`, expired versions too
+pub fn get_transactions(conn: &Connection, include_history: bool) -> Result<Vec<Transaction>> {
+    let filter = if include_history { "" } else { "WHERE valid_until IS NULL " };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM transactions {}ORDER BY date DESC",
+        TRANSACTION_SELECT_COLUMNS, filter
+    ))?;
 
-    for result in rdr.deserialize() {
-        let mut transaction: Transaction = result.context("Failed to deserialize transaction")?;
+    let transactions = stmt
+        .query_map([], row_to_transaction)?
+        .collect::<Result<Vec<_>, _>>()?;
 
-        // Initialize temporal fields (UUID, version, timestamps) - Badge 19
-        transaction.init_temporal_fields();
+    Ok(transactions)
+}
 
-        // Add provenance metadata
-        transaction.set_provenance(
-            Utc::now(),
-            "csv_loader_v1.0",
-            vec!["loaded_from_csv".to_string()],
-        );
+pub fn verify_count(conn: &Connection) -> Result<i64> {
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM transactions", [], |row| row.get(0))?;
 
-        transactions.push(transaction);
-    }
+    Ok(count)
+}
 
-    Ok(transactions)
+// ============================================================================
+// VERSIONED WRITES (Badge 19 - corrections append, never overwrite)
+// ============================================================================
+
+/// Store a new version of an existing transaction
+///
+/// `next` must come from `Transaction::next_version()` on the current version:
+/// same identity (id), version = current + 1. The current row is expired
+/// (valid_until = next.valid_from) and `next` is appended with the SAME
+/// idempotency hash, so re-importing the original statement line is still
+/// detected as a duplicate even if merchant or amount were corrected.
+pub fn insert_transaction_version(conn: &Connection, next: &Transaction, actor: &str) -> Result<()> {
+    write_transaction_version(conn, next, actor, "transaction_corrected")
 }
 
-pub fn insert_transactions(conn: &Connection, transactions: &[Transaction]) -> Result<usize> {
-    let mut inserted = 0;
-    let mut duplicates = 0;
+pub(crate) fn write_transaction_version(
+    conn: &Connection,
+    next: &Transaction,
+    actor: &str,
+    event_type: &str,
+) -> Result<()> {
+    require_actor(actor)?;
+
+    if next.id.is_empty() {
+        return Err(anyhow::anyhow!("Transaction has no identity (run migrate_add_uuids first)"));
+    }
 
-    for tx in transactions {
-        let hash = tx.compute_idempotency_hash();
+    crate::period_close::require_open_period(conn, next)?;
+
+    // The current version's tracked fields, to attribute what this one changes
+    let mut previous = next.clone();
+    let (current_version, hash): (i64, String) = conn
+        .query_row(
+            "SELECT COALESCE(version, 1), idempotency_hash, merchant, category, transaction_type, amount_numeric
+             FROM transactions WHERE tx_uuid = ?1 AND valid_until IS NULL",
+            params![next.id],
+            |row| {
+                previous.merchant = row.get(2)?;
+                previous.category = row.get(3)?;
+                previous.transaction_type = row.get(4)?;
+                previous.amount_numeric = row.get(5)?;
+                Ok((row.get(0)?, row.get(1)?))
+            },
+        )
+        .with_context(|| format!("No current version for transaction {}", next.id))?;
+
+    if next.version != current_version + 1 {
+        return Err(anyhow::anyhow!(
+            "Version conflict for transaction {}: current is v{}, got v{}",
+            next.id,
+            current_version,
+            next.version
+        ));
+    }
 
-        // Serialize metadata to JSON
-        let metadata_json = serde_json::to_string(&tx.metadata)?;
+    let valid_from = next.valid_from.unwrap_or_else(Utc::now);
+    let mut attributed = next.clone();
+    crate::field_provenance::attribute_changes(&previous, &mut attributed, actor, valid_from);
+    let metadata_json = serde_json::to_string(&attributed.metadata)?;
 
-        // Serialize temporal fields (Badge 19)
-        let system_time_str = tx.system_time.map(|dt| dt.to_rfc3339());
-        let valid_from_str = tx.valid_from.map(|dt| dt.to_rfc3339());
-        let valid_until_str = tx.valid_until.map(|dt| dt.to_rfc3339());
+    // Expire, append and log together: a failed insert must not leave the
+    // identity without a current version
+    in_savepoint(conn, || {
+        conn.execute(
+            "UPDATE transactions SET valid_until = ?1 WHERE tx_uuid = ?2 AND valid_until IS NULL",
+            params![valid_from.to_rfc3339(), next.id],
+        )?;
 
-        let result = conn.execute(
+        conn.execute(
             "INSERT INTO transactions (
                 idempotency_hash, date, description, amount_original, amount_numeric,
                 transaction_type, category, merchant, currency, account_name,
                 account_number, bank, source_file, line_number, classification_notes,
                 metadata,
-                tx_uuid, version, system_time, valid_from, valid_until, previous_version_id
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)",
+                tx_uuid, version, system_time, valid_from, valid_until, previous_version_id,
+                ledger_id
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                ?17, ?18, ?19, ?20, NULL, ?21, ?22)",
             params![
                 hash,
-                tx.date,
-                tx.description,
-                tx.amount_original,
-                tx.amount_numeric,
-                tx.transaction_type,
-                tx.category,
-                tx.merchant,
-                tx.currency,
-                tx.account_name,
-                tx.account_number,
-                tx.bank,
-                tx.source_file,
-                tx.line_number,
-                tx.classification_notes,
+                next.date,
+                next.description,
+                next.amount_original,
+                next.amount_numeric,
+                next.transaction_type,
+                next.category,
+                next.merchant,
+                next.currency,
+                next.account_name,
+                next.account_number,
+                next.bank,
+                next.source_file,
+                next.line_number,
+                next.classification_notes,
                 metadata_json,
-                // Badge 19 temporal fields
-                if tx.id.is_empty() { None } else { Some(&tx.id) },
-                tx.version,
-                system_time_str,
-                valid_from_str,
-                valid_until_str,
-                tx.previous_version_id,
+                next.id,
+                next.version,
+                Utc::now().to_rfc3339(),
+                valid_from.to_rfc3339(),
+                next.previous_version_id,
+                next.ledger_id,
             ],
-        );
+        )?;
 
-        match result {
-            Ok(_) => {
-                inserted += 1;
-
-                // Log event to audit trail
-                let event = Event::new(
-                    "transaction_added",
-                    "transaction",
-                    &hash,
-                    serde_json::json!({
-                        "bank": tx.bank,
-                        "amount": tx.amount_numeric,
-                        "source_file": tx.source_file,
-                    }),
-                    "csv_importer",
-                );
-                let _ = insert_event(conn, &event);
-            }
-            Err(rusqlite::Error::SqliteFailure(err, _))
-                if err.code == rusqlite::ErrorCode::ConstraintViolation =>
-            {
-                duplicates += 1;
-            }
-            Err(e) => return Err(e.into()),
+        let payload = TransactionCorrected {
+            version: next.version,
+            change_reason: next.get_metadata("change_reason").and_then(|reason| reason.as_str()).map(str::to_string),
+            voided: next.is_voided(),
+            category: next.category.clone(),
+            merchant: next.merchant.clone(),
+            transaction_type: next.transaction_type.clone(),
+        };
+        let event = Event::typed(event_type, "transaction", &next.id, &payload, actor)?.with_ledger(&next.ledger_id);
+        insert_event(conn, &event)?;
+
+        Ok(())
+    })
+}
+
+/// Run `write` inside a savepoint: everything it wrote is rolled back when it
+/// fails. Savepoints nest, so callers may already be in a transaction.
+fn in_savepoint<T>(conn: &Connection, write: impl FnOnce() -> Result<T>) -> Result<T> {
+    conn.execute_batch("SAVEPOINT versioned_write")?;
+    match write() {
+        Ok(value) => {
+            conn.execute_batch("RELEASE versioned_write")?;
+            Ok(value)
+        }
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO versioned_write; RELEASE versioned_write")?;
+            Err(e)
         }
     }
+}
+
+/// Every write must say who made it
+fn require_actor(actor: &str) -> Result<()> {
+    if actor.trim().is_empty() {
+        return Err(anyhow::anyhow!("Refusing to write without an actor"));
+    }
+    Ok(())
+}
+
+/// Get ALL versions of a transaction by identity (oldest first)
+pub fn get_transaction_history(conn: &Connection, tx_uuid: &str) -> Result<Vec<Transaction>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM transactions WHERE tx_uuid = ?1 ORDER BY version ASC",
+        TRANSACTION_SELECT_COLUMNS
+    ))?;
 
-    println!("✓ Inserted: {} transactions", inserted);
-    println!("✓ Skipped duplicates: {}", duplicates);
+    let transactions = stmt
+        .query_map([tx_uuid], row_to_transaction)?
+        .collect::<Result<Vec<_>, _>>()?;
 
-    Ok(inserted)
+    Ok(transactions)
 }
 
-/// Insert event into audit trail
-pub fn insert_event(conn: &Connection, event: &Event) -> Result<()> {
-    let data_json = serde_json::to_string(&event.data)?;
+/// Get the current version of a transaction by identity
+pub fn get_current_transaction(conn: &Connection, tx_uuid: &str) -> Result<Option<Transaction>> {
+    Ok(get_transaction_history(conn, tx_uuid)?
+        .into_iter()
+        .find(|tx| tx.is_current()))
+}
 
-    conn.execute(
-        "INSERT INTO events (
-            event_id, timestamp, event_type, entity_type, entity_id, data, actor
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        params![
-            event.event_id,
-            event.timestamp.to_rfc3339(),
-            event.event_type,
-            event.entity_type,
-            event.entity_id,
-            data_json,
-            event.actor,
-        ],
-    )?;
+/// Get current, non-voided, non-quarantined transactions (what reports and
+/// balances see)
+pub fn get_active_transactions(conn: &Connection) -> Result<Vec<Transaction>> {
+    Ok(get_all_transactions(conn)?
+        .into_iter()
+        .filter(|tx| tx.is_active())
+        .collect())
+}
 
-    Ok(())
+/// Void a transaction: append a version marked voided
+///
+/// The transaction stays in history and the event log; undo_last_change
+/// restores the pre-void version.
+pub fn void_transaction(
+    conn: &Connection,
+    tx_uuid: &str,
+    reason: &str,
+    actor: &str,
+) -> Result<Transaction> {
+    let next = build_void_version(conn, tx_uuid, reason, actor)?;
+    write_transaction_version(conn, &next, actor, "transaction_voided")?;
+    Ok(next)
 }
 
-/// Get events for a specific entity
-pub fn get_events_for_entity(
+/// Validate a void and build (but don't write) the voided version
+pub(crate) fn build_void_version(
     conn: &Connection,
-    entity_type: &str,
-    entity_id: &str,
-) -> Result<Vec<Event>> {
-    let mut stmt = conn.prepare(
-        "SELECT event_id, timestamp, event_type, entity_type, entity_id, data, actor
-         FROM events
-         WHERE entity_type = ?1 AND entity_id = ?2
-         ORDER BY timestamp DESC",
-    )?;
+    tx_uuid: &str,
+    reason: &str,
+    actor: &str,
+) -> Result<Transaction> {
+    if reason.trim().is_empty() {
+        return Err(anyhow::anyhow!("A void reason is required"));
+    }
 
-    let events = stmt
-        .query_map(params![entity_type, entity_id], |row| {
-            let timestamp_str: String = row.get(1)?;
-            let data_json: String = row.get(5)?;
-
-            Ok(Event {
-                event_id: row.get(0)?,
-                timestamp: DateTime::parse_from_rfc3339(&timestamp_str)
-                    .map_err(|e| rusqlite::Error::InvalidQuery)?
-                    .with_timezone(&Utc),
-                event_type: row.get(2)?,
-                entity_type: row.get(3)?,
-                entity_id: row.get(4)?,
-                data: serde_json::from_str(&data_json)
-                    .map_err(|_| rusqlite::Error::InvalidQuery)?,
-                actor: row.get(6)?,
-            })
-        })?
-        .collect::<Result<Vec<_>, _>>()?;
+    let current = get_current_transaction(conn, tx_uuid)?
+        .ok_or_else(|| anyhow::anyhow!("No current version for transaction {}", tx_uuid))?;
+    if current.is_voided() {
+        return Err(anyhow::anyhow!("Transaction {} is already voided", tx_uuid));
+    }
 
-    Ok(events)
+    let mut next = current.next_version(Some(format!("voided: {}", reason)));
+    next.set_voided(reason, actor, Utc::now());
+    Ok(next)
 }
 
-pub fn get_all_transactions(conn: &Connection) -> Result<Vec<Transaction>> {
-    let mut stmt = conn.prepare(
-        "SELECT date, description, amount_original, amount_numeric,
-                transaction_type, category, merchant, currency,
-                account_name, account_number, bank, source_file,
-                line_number, classification_notes, metadata,
-                tx_uuid, version, system_time, valid_from, valid_until, previous_version_id
-         FROM transactions
-         ORDER BY date DESC",
-    )?;
+// ============================================================================
+// UNDO / REDO (append a reverting version, never delete)
+// ============================================================================
 
-    let transactions = stmt
-        .query_map([], |row| {
-            let metadata_json: Option<String> = row.get(14)?;
-            let metadata = if let Some(json_str) = metadata_json {
-                serde_json::from_str(&json_str).unwrap_or_default()
-            } else {
-                HashMap::new()
-            };
+/// Metadata key: version whose content an undo/redo version restored
+const RESTORES_VERSION_KEY: &str = "restores_version";
 
-            // Parse temporal fields (Badge 19)
-            let tx_uuid: Option<String> = row.get(15)?;
-            let version: Option<i64> = row.get(16)?;
-            let system_time_str: Option<String> = row.get(17)?;
-            let valid_from_str: Option<String> = row.get(18)?;
-            let valid_until_str: Option<String> = row.get(19)?;
-            let previous_version_id: Option<String> = row.get(20)?;
-
-            let system_time = system_time_str.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
-                .map(|dt| dt.with_timezone(&Utc));
-            let valid_from = valid_from_str.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
-                .map(|dt| dt.with_timezone(&Utc));
-            let valid_until = valid_until_str.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
-                .map(|dt| dt.with_timezone(&Utc));
-
-            Ok(Transaction {
-                date: row.get(0)?,
-                description: row.get(1)?,
-                amount_original: row.get(2)?,
-                amount_numeric: row.get(3)?,
-                transaction_type: row.get(4)?,
-                category: row.get(5)?,
-                merchant: row.get(6)?,
-                currency: row.get(7)?,
-                account_name: row.get(8)?,
-                account_number: row.get(9)?,
-                bank: row.get(10)?,
-                source_file: row.get(11)?,
-                line_number: row.get(12)?,
-                classification_notes: row.get(13)?,
-                // Badge 19 fields
-                id: tx_uuid.unwrap_or_default(),
-                version: version.unwrap_or(0),
-                system_time,
-                valid_from,
-                valid_until,
-                previous_version_id,
-                metadata,
-            })
-        })?
-        .collect::<Result<Vec<_>, _>>()?;
+/// Metadata key: versions that can be re-applied by redo (last = next)
+const REDO_STACK_KEY: &str = "redo_stack";
 
-    Ok(transactions)
+/// Version whose content this row carries (itself unless it is an undo/redo)
+fn logical_version(tx: &Transaction) -> i64 {
+    tx.get_metadata(RESTORES_VERSION_KEY)
+        .and_then(|v| v.as_i64())
+        .unwrap_or(tx.version)
 }
 
-pub fn verify_count(conn: &Connection) -> Result<i64> {
-    let count: i64 = conn.query_row("SELECT COUNT(*) FROM transactions", [], |row| row.get(0))?;
+fn redo_stack(tx: &Transaction) -> Vec<i64> {
+    tx.get_metadata(REDO_STACK_KEY)
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default()
+}
 
-    Ok(count)
+/// Build (but don't write) the next version of `current` carrying the content of `target`
+fn restored_version(current: &Transaction, target: &Transaction, reason: String, redo: Vec<i64>) -> Transaction {
+    let mut next = current.next_version(None);
+    next.date = target.date.clone();
+    next.description = target.description.clone();
+    next.amount_original = target.amount_original.clone();
+    next.amount_numeric = target.amount_numeric;
+    next.transaction_type = target.transaction_type.clone();
+    next.category = target.category.clone();
+    next.merchant = target.merchant.clone();
+    next.currency = target.currency.clone();
+    next.account_name = target.account_name.clone();
+    next.account_number = target.account_number.clone();
+    next.bank = target.bank.clone();
+    next.source_file = target.source_file.clone();
+    next.line_number = target.line_number.clone();
+    next.classification_notes = target.classification_notes.clone();
+
+    next.metadata = target.metadata.clone();
+    next.metadata.insert("change_reason".to_string(), serde_json::json!(reason));
+    next.metadata.insert(RESTORES_VERSION_KEY.to_string(), serde_json::json!(logical_version(target)));
+    if redo.is_empty() {
+        next.metadata.remove(REDO_STACK_KEY);
+    } else {
+        next.metadata.insert(REDO_STACK_KEY.to_string(), serde_json::json!(redo));
+    }
+    next
+}
+
+/// Roll a transaction back to the content it had before its last change
+///
+/// Appends a new version (history is never rewritten). Repeated undos walk
+/// further back; any regular correction clears the redo stack.
+pub fn undo_last_change(conn: &Connection, tx_uuid: &str, actor: &str) -> Result<Transaction> {
+    let next = build_undo_version(conn, tx_uuid)?;
+    insert_transaction_version(conn, &next, actor)?;
+    Ok(next)
+}
+
+/// Re-apply the change most recently undone by `undo_last_change`
+pub fn redo_last_change(conn: &Connection, tx_uuid: &str, actor: &str) -> Result<Transaction> {
+    let next = build_redo_version(conn, tx_uuid)?;
+    insert_transaction_version(conn, &next, actor)?;
+    Ok(next)
+}
+
+/// Build (but don't write) the version an undo appends
+pub(crate) fn build_undo_version(conn: &Connection, tx_uuid: &str) -> Result<Transaction> {
+    let history = get_transaction_history(conn, tx_uuid)?;
+    let current = history
+        .iter()
+        .find(|tx| tx.is_current())
+        .ok_or_else(|| anyhow::anyhow!("No current version for transaction {}", tx_uuid))?;
+
+    let logical = logical_version(current);
+    let target = history
+        .iter()
+        .find(|tx| tx.version == logical - 1)
+        .ok_or_else(|| anyhow::anyhow!("Nothing to undo for transaction {}", tx_uuid))?;
+
+    let mut redo = redo_stack(current);
+    redo.push(logical);
+
+    Ok(restored_version(current, target, format!("undo of v{}", logical), redo))
+}
+
+/// Build (but don't write) the version a redo appends
+pub(crate) fn build_redo_version(conn: &Connection, tx_uuid: &str) -> Result<Transaction> {
+    let history = get_transaction_history(conn, tx_uuid)?;
+    let current = history
+        .iter()
+        .find(|tx| tx.is_current())
+        .ok_or_else(|| anyhow::anyhow!("No current version for transaction {}", tx_uuid))?;
+
+    let mut redo = redo_stack(current);
+    let redo_version = redo
+        .pop()
+        .ok_or_else(|| anyhow::anyhow!("Nothing to redo for transaction {}", tx_uuid))?;
+    let target = history
+        .iter()
+        .find(|tx| tx.version == redo_version)
+        .ok_or_else(|| anyhow::anyhow!("Version v{} missing for transaction {}", redo_version, tx_uuid))?;
+
+    Ok(restored_version(current, target, format!("redo of v{}", redo_version), redo))
+}
+
+/// Columns read by row_to_transaction, in order
+pub(crate) const TRANSACTION_SELECT_COLUMNS: &str = "date, description, amount_original, amount_numeric,
+                transaction_type, category, merchant, currency,
+                account_name, account_number, bank, source_file,
+                line_number, classification_notes, metadata,
+                tx_uuid, version, system_time, valid_from, valid_until, previous_version_id,
+                ledger_id";
+
+pub(crate) fn row_to_transaction(row: &rusqlite::Row) -> rusqlite::Result<Transaction> {
+    let metadata_json: Option<String> = row.get(14)?;
+    let metadata = if let Some(json_str) = metadata_json {
+        serde_json::from_str(&json_str).unwrap_or_default()
+    } else {
+        HashMap::new()
+    };
+
+    // Parse temporal fields (Badge 19)
+    let tx_uuid: Option<String> = row.get(15)?;
+    let version: Option<i64> = row.get(16)?;
+    let system_time_str: Option<String> = row.get(17)?;
+    let valid_from_str: Option<String> = row.get(18)?;
+    let valid_until_str: Option<String> = row.get(19)?;
+    let previous_version_id: Option<String> = row.get(20)?;
+
+    let system_time = system_time_str.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+    let valid_from = valid_from_str.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+    let valid_until = valid_until_str.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    Ok(Transaction {
+        date: row.get(0)?,
+        description: row.get(1)?,
+        amount_original: row.get(2)?,
+        amount_numeric: row.get(3)?,
+        transaction_type: row.get(4)?,
+        category: row.get(5)?,
+        merchant: row.get(6)?,
+        currency: row.get(7)?,
+        account_name: row.get(8)?,
+        account_number: row.get(9)?,
+        bank: row.get(10)?,
+        source_file: row.get(11)?,
+        line_number: row.get(12)?,
+        classification_notes: row.get::<_, Option<String>>(13)?.unwrap_or_default(),
+        // Badge 19 fields
+        id: tx_uuid.unwrap_or_default(),
+        version: version.unwrap_or(0),
+        system_time,
+        valid_from,
+        valid_until,
+        previous_version_id,
+        ledger_id: row
+            .get::<_, Option<String>>(21)?
+            .unwrap_or_else(crate::ledger::default_ledger_id),
+        metadata,
+    })
 }
 
 /// Migrate existing transactions to have UUIDs (Badge 19)
@@ -684,6 +2011,9 @@ pub fn get_source_file_stats(conn: &Connection) -> Result<Vec<SourceFileStat>> {
             SUM(CASE WHEN transaction_type = 'INGRESO' THEN ABS(amount_numeric) ELSE 0 END) as income,
             MIN(date) || ' - ' || MAX(date) as date_range
          FROM transactions
+         WHERE valid_until IS NULL
+           AND NOT COALESCE(CASE WHEN json_valid(metadata) THEN json_extract(metadata, '$.voided') END, 0)
+           AND (NOT json_valid(metadata) OR json_extract(metadata, '$.quarantined') IS NULL)
          GROUP BY source_file, bank
          ORDER BY bank, source_file",
     )?;
@@ -709,69 +2039,125 @@ pub fn get_transactions_by_source(
     conn: &Connection,
     source_file: &str,
 ) -> Result<Vec<Transaction>> {
-    let mut stmt = conn.prepare(
-        "SELECT date, description, amount_original, amount_numeric,
-                transaction_type, category, merchant, currency,
-                account_name, account_number, bank, source_file,
-                line_number, classification_notes, metadata,
-                tx_uuid, version, system_time, valid_from, valid_until, previous_version_id
-         FROM transactions
-         WHERE source_file = ?1
-         ORDER BY date DESC",
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM transactions WHERE source_file = ?1 ORDER BY date DESC",
+        TRANSACTION_SELECT_COLUMNS
+    ))?;
 
     let transactions = stmt
-        .query_map([source_file], |row| {
-            let metadata_json: Option<String> = row.get(14)?;
-            let metadata = if let Some(json_str) = metadata_json {
-                serde_json::from_str(&json_str).unwrap_or_default()
-            } else {
-                HashMap::new()
-            };
+        .query_map([source_file], row_to_transaction)?
+        .collect::<Result<Vec<_>, _>>()?;
 
-            // Parse temporal fields (Badge 19)
-            let tx_uuid: Option<String> = row.get(15)?;
-            let version: Option<i64> = row.get(16)?;
-            let system_time_str: Option<String> = row.get(17)?;
-            let valid_from_str: Option<String> = row.get(18)?;
-            let valid_until_str: Option<String> = row.get(19)?;
-            let previous_version_id: Option<String> = row.get(20)?;
-
-            let system_time = system_time_str.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
-                .map(|dt| dt.with_timezone(&Utc));
-            let valid_from = valid_from_str.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
-                .map(|dt| dt.with_timezone(&Utc));
-            let valid_until = valid_until_str.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
-                .map(|dt| dt.with_timezone(&Utc));
-
-            Ok(Transaction {
-                date: row.get(0)?,
-                description: row.get(1)?,
-                amount_original: row.get(2)?,
-                amount_numeric: row.get(3)?,
-                transaction_type: row.get(4)?,
-                category: row.get(5)?,
-                merchant: row.get(6)?,
-                currency: row.get(7)?,
-                account_name: row.get(8)?,
-                account_number: row.get(9)?,
-                bank: row.get(10)?,
-                source_file: row.get(11)?,
-                line_number: row.get(12)?,
-                classification_notes: row.get(13)?,
-                // Badge 19 fields
-                id: tx_uuid.unwrap_or_default(),
-                version: version.unwrap_or(0),
-                system_time,
-                valid_from,
-                valid_until,
-                previous_version_id,
-                metadata,
+    Ok(transactions)
+}
+
+// ============================================================================
+// AGGREGATES (computed in SQL over current, non-voided, non-quarantined rows)
+// ============================================================================
+
+/// Count and totals of one group of transactions
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupTotal {
+    /// Transaction type, bank, category, or month ("YYYY-MM")
+    pub key: String,
+    pub count: i64,
+    /// Signed sum of amount_numeric
+    pub total: f64,
+    /// Sum of absolute amounts (expenses and income both positive)
+    pub absolute_total: f64,
+}
+
+/// Month of the MM/DD/YYYY or YYYY-MM-DD `date` column, as "YYYY-MM"
+const MONTH_EXPR: &str =
+    "CASE WHEN substr(date, 3, 1) = '/' THEN substr(date, 7, 4) || '-' || substr(date, 1, 2) ELSE substr(date, 1, 7) END";
+
+/// Totals of the active transactions (of `ledger_id`, or of every ledger)
+/// grouped by the SQL expression `group`
+fn group_totals(conn: &Connection, ledger_id: Option<&str>, group: &str, order: &str) -> Result<Vec<GroupTotal>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} AS key, COUNT(*), COALESCE(SUM(amount_numeric), 0), COALESCE(SUM(ABS(amount_numeric)), 0)
+         FROM transactions
+         WHERE valid_until IS NULL
+           AND NOT COALESCE(CASE WHEN json_valid(metadata) THEN json_extract(metadata, '$.voided') END, 0)
+           AND (NOT json_valid(metadata) OR json_extract(metadata, '$.quarantined') IS NULL)
+           AND (?1 IS NULL OR ledger_id = ?1)
+         GROUP BY key
+         ORDER BY {}",
+        group, order
+    ))?;
+
+    let totals = stmt
+        .query_map([ledger_id], |row| {
+            Ok(GroupTotal {
+                key: row.get(0)?,
+                count: row.get(1)?,
+                total: row.get(2)?,
+                absolute_total: row.get(3)?,
             })
         })?
         .collect::<Result<Vec<_>, _>>()?;
 
-    Ok(transactions)
+    Ok(totals)
+}
+
+/// Totals per transaction type (GASTO, INGRESO, ...), most frequent first
+pub fn totals_by_type(conn: &Connection, ledger_id: Option<&str>) -> Result<Vec<GroupTotal>> {
+    group_totals(conn, ledger_id, "transaction_type", "COUNT(*) DESC, key")
+}
+
+/// Totals per bank, most transactions first
+pub fn totals_by_bank(conn: &Connection, ledger_id: Option<&str>) -> Result<Vec<GroupTotal>> {
+    group_totals(conn, ledger_id, "bank", "COUNT(*) DESC, key")
+}
+
+/// Totals per category, largest absolute total first
+pub fn totals_by_category(conn: &Connection, ledger_id: Option<&str>) -> Result<Vec<GroupTotal>> {
+    group_totals(conn, ledger_id, "category", "SUM(ABS(amount_numeric)) DESC, key")
+}
+
+/// Totals per calendar month ("YYYY-MM"), oldest first
+pub fn totals_by_month(conn: &Connection, ledger_id: Option<&str>) -> Result<Vec<GroupTotal>> {
+    group_totals(conn, ledger_id, MONTH_EXPR, "key")
+}
+
+// ============================================================================
+// TEST SUPPORT
+// ============================================================================
+
+/// Fixtures shared by the test modules of the crate
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::*;
+
+    /// A $4.50 coffee on the default ledger's BofA checking account, no identity
+    /// yet; tests set the fields they care about and spread the rest
+    /// (`Transaction { amount_numeric: -9.0, ..sample_transaction() }`)
+    pub fn sample_transaction() -> Transaction {
+        Transaction {
+            date: "01/15/2025".to_string(),
+            description: "COFFEE SHOP".to_string(),
+            amount_original: "-4.50".to_string(),
+            amount_numeric: -4.50,
+            transaction_type: "GASTO".to_string(),
+            category: "Restaurants".to_string(),
+            merchant: String::new(),
+            currency: "USD".to_string(),
+            account_name: "Checking".to_string(),
+            account_number: "0001".to_string(),
+            bank: "BofA".to_string(),
+            source_file: "bofa.csv".to_string(),
+            line_number: "1".to_string(),
+            classification_notes: String::new(),
+            id: String::new(),
+            version: 0,
+            system_time: None,
+            valid_from: None,
+            valid_until: None,
+            previous_version_id: None,
+            ledger_id: crate::ledger::default_ledger_id(),
+            metadata: HashMap::new(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -795,21 +2181,11 @@ mod tests {
             transaction_type: tx_type.to_string(),
             category: category.to_string(),
             merchant: merchant.to_string(),
-            currency: "USD".to_string(),
             account_name: "Test Account".to_string(),
             account_number: "1234".to_string(),
             bank: "Test Bank".to_string(),
             source_file: "test.csv".to_string(),
-            line_number: "1".to_string(),
-            classification_notes: "".to_string(),
-            // Badge 19 fields
-            id: String::new(),  // Will be set by init_temporal_fields()
-            version: 0,
-            system_time: None,
-            valid_from: None,
-            valid_until: None,
-            previous_version_id: None,
-            metadata: HashMap::new(),
+            ..test_support::sample_transaction()
         }
     }
 
@@ -850,7 +2226,7 @@ mod tests {
         println!("Created {} test transactions", transactions.len());
 
         // First import
-        let inserted1 = insert_transactions(&conn, &transactions).unwrap();
+        let inserted1 = insert_transactions_as(&conn, &transactions, "test").unwrap().inserted;
         let count1 = verify_count(&conn).unwrap();
 
         println!(
@@ -859,7 +2235,8 @@ mod tests {
         );
 
         // Second import (same transactions)
-        let inserted2 = insert_transactions(&conn, &transactions).unwrap();
+        let second = insert_transactions_as(&conn, &transactions, "test").unwrap();
+        let inserted2 = second.inserted;
         let count2 = verify_count(&conn).unwrap();
 
         println!(
@@ -877,6 +2254,7 @@ mod tests {
             inserted2, 0,
             "Second import should insert 0 transactions (all duplicates)"
         );
+        assert_eq!((second.duplicates, second.failed.len()), (3, 0));
         assert_eq!(
             count2, 3,
             "Database should still have 3 transactions after second import"
@@ -885,6 +2263,33 @@ mod tests {
         println!("✅ Idempotency test PASSED: 0 duplicates inserted on second import");
     }
 
+    #[test]
+    fn test_insert_summary_reports_failed_rows_and_keeps_going() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let mut good = create_test_transaction("01/02/2025", "STARBUCKS", -5.25, "GASTO", "Dining", "STARBUCKS");
+        good.init_temporal_fields();
+        let mut bad = create_test_transaction("01/03/2025", "BROKEN", f64::NAN, "GASTO", "Dining", "BROKEN");
+        bad.line_number = "3".to_string();
+        let other = create_test_transaction("01/04/2025", "AMAZON", -12.0, "GASTO", "Shopping", "AMAZON");
+
+        let summary = insert_transactions_as(&conn, &[good.clone(), bad, other], "test").unwrap();
+        assert_eq!((summary.inserted, summary.duplicates), (2, 0));
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!((summary.failed[0].index, summary.failed[0].line_number.as_str()), (1, "3"));
+
+        let again = insert_transactions_as(&conn, &[good.clone()], "test").unwrap();
+        assert_eq!(again, InsertSummary { inserted: 0, duplicates: 1, failed: Vec::new() });
+
+        // Same uuid and version, different content: not a duplicate, a broken row
+        let mut clash = good;
+        clash.amount_numeric = -6.0;
+        let summary = insert_transactions_as(&conn, &[clash], "test").unwrap();
+        assert_eq!((summary.inserted, summary.duplicates), (0, 0));
+        assert!(summary.failed[0].message.contains("UNIQUE constraint failed: transactions.tx_uuid"), "{:?}", summary.failed);
+    }
+
     #[test]
     fn test_compute_idempotency_hash() {
         let tx = create_test_transaction(
@@ -946,22 +2351,414 @@ mod tests {
         let conn = Connection::open_in_memory().unwrap();
         setup_database(&conn).unwrap();
 
-        let event = Event::new(
-            "test_event",
-            "transaction",
-            "test_id_123",
-            serde_json::json!({"test": "data"}),
-            "test_actor",
-        );
+        let payload = crate::event_schema::NoteAdded { note_id: "note-1".to_string(), parent_id: None };
+        let event = Event::typed("note_added", "transaction", "test_id_123", &payload, "test_actor").unwrap();
 
         insert_event(&conn, &event).unwrap();
 
         let events = get_events_for_entity(&conn, "transaction", "test_id_123").unwrap();
 
         assert_eq!(events.len(), 1);
-        assert_eq!(events[0].event_type, "test_event");
+        assert_eq!(events[0].event_type, "note_added");
         assert_eq!(events[0].actor, "test_actor");
 
         println!("✅ Event log test PASSED");
     }
+
+    #[test]
+    fn test_versioned_write_keeps_history_and_dedup() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let mut tx = create_test_transaction(
+            "12/31/2024",
+            "STARBUCKS #12345",
+            -45.99,
+            "GASTO",
+            "Dining",
+            "STARBUCKS",
+        );
+        tx.init_temporal_fields();
+        insert_transactions_as(&conn, &[tx.clone()], "test").unwrap();
+
+        let mut next = tx.next_version(Some("user correction".to_string()));
+        next.merchant = "Starbucks".to_string();
+        next.category = "Café".to_string();
+        insert_transaction_version(&conn, &next, "test").unwrap();
+
+        // Stale writers are rejected
+        assert!(insert_transaction_version(&conn, &next, "test").is_err());
+
+        let history = get_transaction_history(&conn, &tx.id).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].merchant, "STARBUCKS");
+        assert!(history[0].valid_until.is_some());
+
+        let current = get_current_transaction(&conn, &tx.id).unwrap().unwrap();
+        assert_eq!(current.version, 2);
+        assert_eq!(current.category, "Café");
+
+        // Re-importing the original line is still a duplicate
+        assert_eq!(insert_transactions_as(&conn, &[tx], "test").unwrap().inserted, 0);
+    }
+
+    #[test]
+    fn test_failed_versioned_write_keeps_the_current_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let mut tx = test_support::sample_transaction();
+        tx.init_temporal_fields();
+        insert_transactions_as(&conn, std::slice::from_ref(&tx), "test").unwrap();
+        let events_before = get_events_for_entity(&conn, "transaction", &tx.id).unwrap().len();
+
+        conn.execute_batch(
+            "CREATE TEMP TRIGGER reject_versions BEFORE INSERT ON main.transactions
+             BEGIN SELECT RAISE(ABORT, 'insert rejected'); END",
+        )
+        .unwrap();
+        let mut next = tx.next_version(Some("fix category".to_string()));
+        next.category = "Coffee".to_string();
+        assert!(insert_transaction_version(&conn, &next, "test").is_err());
+
+        let current = get_current_transaction(&conn, &tx.id).unwrap().unwrap();
+        assert_eq!((current.version, current.category.as_str()), (1, "Restaurants"));
+        assert!(current.valid_until.is_none());
+        assert_eq!(get_events_for_entity(&conn, "transaction", &tx.id).unwrap().len(), events_before);
+
+        // The savepoint nests inside a caller's transaction
+        conn.execute_batch("DROP TRIGGER reject_versions").unwrap();
+        let db_tx = conn.unchecked_transaction().unwrap();
+        insert_transaction_version(&db_tx, &next, "test").unwrap();
+        db_tx.commit().unwrap();
+        assert_eq!(get_current_transaction(&conn, &tx.id).unwrap().unwrap().category, "Coffee");
+    }
+
+    #[test]
+    fn test_setup_migrates_legacy_transactions_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE transactions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                idempotency_hash TEXT UNIQUE NOT NULL,
+                date TEXT NOT NULL,
+                description TEXT NOT NULL,
+                amount_original TEXT NOT NULL,
+                amount_numeric REAL NOT NULL,
+                transaction_type TEXT NOT NULL,
+                category TEXT NOT NULL,
+                merchant TEXT NOT NULL,
+                currency TEXT NOT NULL,
+                account_name TEXT NOT NULL,
+                account_number TEXT NOT NULL,
+                bank TEXT NOT NULL,
+                source_file TEXT NOT NULL,
+                line_number TEXT NOT NULL,
+                classification_notes TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO transactions (idempotency_hash, date, description, amount_original,
+                amount_numeric, transaction_type, category, merchant, currency, account_name,
+                account_number, bank, source_file, line_number)
+             VALUES ('h1', '01/01/2025', 'Legacy', '$1.00', -1.0, 'GASTO', 'Test', 'M',
+                'USD', 'A', '1', 'BofA', 'legacy.csv', '2')",
+            [],
+        )
+        .unwrap();
+
+        setup_database(&conn).unwrap();
+        // Idempotent
+        setup_database(&conn).unwrap();
+
+        let table_sql: String = conn
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'transactions'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(!table_sql.contains("idempotency_hash TEXT UNIQUE"));
+
+        let transactions = get_all_transactions(&conn).unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].description, "Legacy");
+    }
+
+    #[test]
+    fn test_setup_keys_legacy_balance_snapshots_by_ledger() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE balance_snapshots (
+                account_id TEXT NOT NULL,
+                ledger_id TEXT NOT NULL,
+                statement_period TEXT NOT NULL,
+                statement_date TEXT NOT NULL,
+                opening_balance REAL NOT NULL,
+                closing_balance REAL NOT NULL,
+                recorded_at TEXT NOT NULL,
+                PRIMARY KEY (account_id, statement_date)
+            )",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO balance_snapshots VALUES
+                ('Bank of America', 'default', 'January 2025', '2025-01-31', 100.0, 200.0, '2025-02-01T00:00:00Z')",
+            [],
+        )
+        .unwrap();
+
+        setup_database(&conn).unwrap();
+        setup_database(&conn).unwrap();
+
+        let history = crate::statements::balance_history(&conn, "Bank of America", "default").unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].closing_balance, 200.0);
+        conn.execute(
+            "INSERT INTO balance_snapshots
+                (account_id, ledger_id, statement_period, statement_date, opening_balance, closing_balance, recorded_at)
+             VALUES ('Bank of America', 'business', 'January 2025', '2025-01-31', 0.0, 50.0, '2025-02-01T00:00:00Z')",
+            [],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_undo_redo_appends_versions() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let mut tx = create_test_transaction("01/05/2025", "UBER TRIP", -12.0, "GASTO", "A", "UBER");
+        tx.init_temporal_fields();
+        insert_transactions_as(&conn, &[tx.clone()], "test").unwrap();
+
+        let correct = |category: &str| {
+            let current = get_current_transaction(&conn, &tx.id).unwrap().unwrap();
+            let mut next = current.next_version(Some("user correction".to_string()));
+            next.category = category.to_string();
+            insert_transaction_version(&conn, &next, "test").unwrap();
+        };
+        let category = || get_current_transaction(&conn, &tx.id).unwrap().unwrap().category;
+
+        correct("B");
+        correct("C");
+
+        assert_eq!(undo_last_change(&conn, &tx.id, "test").unwrap().category, "B");
+        assert_eq!(undo_last_change(&conn, &tx.id, "test").unwrap().category, "A");
+        assert!(undo_last_change(&conn, &tx.id, "test").is_err());
+
+        assert_eq!(redo_last_change(&conn, &tx.id, "test").unwrap().category, "B");
+        assert_eq!(redo_last_change(&conn, &tx.id, "test").unwrap().category, "C");
+        assert!(redo_last_change(&conn, &tx.id, "test").is_err());
+
+        // A new correction after an undo discards the redo stack
+        undo_last_change(&conn, &tx.id, "test").unwrap();
+        correct("D");
+        assert!(redo_last_change(&conn, &tx.id, "test").is_err());
+        assert_eq!(category(), "D");
+
+        // Nothing was deleted: 1 import + 2 corrections + 4 undo/redo + 1 undo + 1 correction
+        assert_eq!(get_transaction_history(&conn, &tx.id).unwrap().len(), 9);
+    }
+
+    #[test]
+    fn test_void_transaction_keeps_history() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let mut tx = create_test_transaction("02/01/2025", "DOUBLE CHARGE", -30.0, "GASTO", "Shopping", "AMAZON");
+        tx.init_temporal_fields();
+        let mut other = create_test_transaction("02/02/2025", "GROCERIES", -20.0, "GASTO", "Food", "HEB");
+        other.init_temporal_fields();
+        insert_transactions_as(&conn, &[tx.clone(), other], "test").unwrap();
+
+        assert!(void_transaction(&conn, &tx.id, " ", "test").is_err());
+
+        let voided = void_transaction(&conn, &tx.id, "bank reversed charge", "test").unwrap();
+        assert!(voided.is_voided());
+        assert_eq!(voided.void_reason(), Some("bank reversed charge"));
+        assert!(void_transaction(&conn, &tx.id, "again", "test").is_err());
+
+        // Excluded from active set, retained in history and events
+        let active = get_active_transactions(&conn).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].merchant, "HEB");
+        assert_eq!(get_transaction_history(&conn, &tx.id).unwrap().len(), 2);
+
+        let events: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM events WHERE event_type = 'transaction_voided' AND entity_id = ?1",
+                [&tx.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(events, 1);
+
+        // Undo brings it back
+        let restored = undo_last_change(&conn, &tx.id, "test").unwrap();
+        assert!(!restored.is_voided());
+        assert_eq!(get_active_transactions(&conn).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_deduplication_is_per_ledger() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let mut personal = create_test_transaction("03/01/2025", "STRIPE PAYOUT", 500.0, "INGRESO", "Income", "STRIPE");
+        personal.init_temporal_fields();
+        let mut business = personal.clone();
+        business.id = String::new();
+        business.ledger_id = "business".to_string();
+        business.init_temporal_fields();
+
+        assert_eq!(insert_transactions_as(&conn, &[personal.clone(), business], "test").unwrap().inserted, 2);
+        assert_eq!(insert_transactions_as(&conn, &[personal], "test").unwrap().inserted, 0);
+
+        let ledgers: Vec<String> = get_all_transactions(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|tx| tx.ledger_id)
+            .collect();
+        assert!(ledgers.contains(&"default".to_string()));
+        assert!(ledgers.contains(&"business".to_string()));
+
+        let events: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM events WHERE ledger_id = 'business' AND event_type = 'transaction_added'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(events, 1);
+    }
+
+    #[test]
+    fn test_get_all_transactions_totals_are_stable_after_corrections() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let mut coffee = create_test_transaction("01/02/2025", "STARBUCKS", -5.25, "GASTO", "Dining", "STARBUCKS");
+        coffee.init_temporal_fields();
+        let mut fuel = create_test_transaction("01/03/2025", "SHELL OIL", -40.0, "GASTO", "Transport", "SHELL");
+        fuel.init_temporal_fields();
+        insert_transactions_as(&conn, &[coffee.clone(), fuel], "test").unwrap();
+
+        let total = |transactions: Vec<Transaction>| transactions.iter().map(|tx| tx.amount_numeric).sum::<f64>();
+        assert_eq!(total(get_all_transactions(&conn).unwrap()), -45.25);
+
+        let mut corrected = coffee.next_version(Some("amount typo".to_string()));
+        corrected.amount_numeric = -5.52;
+        insert_transaction_version(&conn, &corrected, "test").unwrap();
+        let mut recategorized = corrected.next_version(Some("recategorized".to_string()));
+        recategorized.category = "Café".to_string();
+        insert_transaction_version(&conn, &recategorized, "test").unwrap();
+
+        let current = get_all_transactions(&conn).unwrap();
+        assert_eq!(current.len(), 2);
+        assert!((total(current) - -45.52).abs() < 1e-9);
+
+        let history = get_transactions(&conn, true).unwrap();
+        assert_eq!(history.len(), 4);
+        assert_eq!(history.iter().filter(|tx| tx.id == coffee.id).count(), 3);
+        assert_eq!(get_transactions(&conn, false).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_group_totals_match_in_memory_sums() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let mut rows = vec![
+            create_test_transaction("01/02/2025", "STARBUCKS", -5.25, "GASTO", "Dining", "STARBUCKS"),
+            create_test_transaction("01/20/2025", "SHELL OIL", -40.0, "GASTO", "Transport", "SHELL"),
+            create_test_transaction("2025-02-01", "PAYROLL", 2000.0, "INGRESO", "Income", "ACME"),
+            create_test_transaction("02/03/2025", "DINER", -12.0, "GASTO", "Dining", "DINER"),
+        ];
+        rows[2].bank = "Wise".to_string();
+        rows[3].ledger_id = "business".to_string();
+        for tx in &mut rows {
+            tx.init_temporal_fields();
+        }
+        insert_transactions_as(&conn, &rows, "test").unwrap();
+
+        // A correction and a void must not be counted twice (or at all)
+        let mut corrected = rows[0].next_version(Some("amount typo".to_string()));
+        corrected.amount_numeric = -5.52;
+        insert_transaction_version(&conn, &corrected, "test").unwrap();
+        void_transaction(&conn, &rows[1].id, "duplicate", "test").unwrap();
+
+        let by_type = totals_by_type(&conn, Some("default")).unwrap();
+        assert_eq!(by_type.len(), 2);
+        let expenses = by_type.iter().find(|group| group.key == "GASTO").unwrap();
+        assert_eq!(expenses.count, 1);
+        assert!((expenses.total - -5.52).abs() < 1e-9);
+
+        let by_bank = totals_by_bank(&conn, None).unwrap();
+        assert_eq!(by_bank[0], GroupTotal { key: "Test Bank".to_string(), count: 2, total: -17.52, absolute_total: 17.52 });
+
+        let by_category = totals_by_category(&conn, None).unwrap();
+        let keys: Vec<&str> = by_category.iter().map(|group| group.key.as_str()).collect();
+        assert_eq!(keys, vec!["Income", "Dining"]);
+
+        let by_month = totals_by_month(&conn, None).unwrap();
+        let months: Vec<(&str, i64)> = by_month.iter().map(|group| (group.key.as_str(), group.count)).collect();
+        assert_eq!(months, vec![("2025-01", 1), ("2025-02", 2)]);
+
+        let active = get_active_transactions(&conn).unwrap();
+        let sum: f64 = active.iter().map(|tx| tx.amount_numeric).sum();
+        assert!((by_month.iter().map(|group| group.total).sum::<f64>() - sum).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_query_events_filters_and_pages() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let start = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        for (day, (event_type, actor)) in [
+            ("transaction_corrected", "ana"),
+            ("transaction_voided", "ana"),
+            ("transaction_corrected", "bob"),
+            ("transaction_corrected", "ana"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let payload = TransactionCorrected {
+                version: 2,
+                change_reason: None,
+                voided: event_type == "transaction_voided",
+                category: "Dining".to_string(),
+                merchant: "STARBUCKS".to_string(),
+                transaction_type: "GASTO".to_string(),
+            };
+            let mut event = Event::typed(event_type, "transaction", &format!("tx-{}", day), &payload, actor).unwrap();
+            event.timestamp = start + chrono::Duration::days(day as i64 * 10);
+            insert_event(&conn, &event).unwrap();
+        }
+
+        let corrections = EventFilter { event_type: Some("transaction_corrected".to_string()), ..Default::default() };
+        let ids: Vec<String> = query_events(&conn, &corrections).unwrap().into_iter().map(|e| e.entity_id).collect();
+        assert_eq!(ids, vec!["tx-3", "tx-2", "tx-0"], "newest first");
+
+        let by_ana_in_january = EventFilter {
+            actor: Some("ana".to_string()),
+            since: Some(start),
+            until: Some(start + chrono::Duration::days(29)),
+            ..corrections.clone()
+        };
+        let events = query_events(&conn, &by_ana_in_january).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].entity_id, "tx-0");
+
+        let page = EventFilter { limit: Some(2), offset: 2, ..Default::default() };
+        let ids: Vec<String> = query_events(&conn, &page).unwrap().into_iter().map(|e| e.entity_id).collect();
+        assert_eq!(ids, vec!["tx-1", "tx-0"]);
+        assert_eq!(count_events(&conn, &page).unwrap(), 4);
+        assert_eq!(count_events(&conn, &corrections).unwrap(), 3);
+        assert!(query_events(&conn, &EventFilter { ledger_id: Some("business".to_string()), ..Default::default() })
+            .unwrap()
+            .is_empty());
+    }
 }
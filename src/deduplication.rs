@@ -1,7 +1,9 @@
 // 🔍 Deduplication Engine - Detect duplicate transactions
 // Three strategies: Exact Match, Fuzzy Match, Transfer Pair
+// (plus any custom `DuplicateMatcher`s, tried after them)
 
 use crate::db::Transaction;
+use crate::manual::is_manual;
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
@@ -19,6 +21,18 @@ pub enum MatchStrategy {
 
     /// Transfer pair: same date, opposite amounts, both TRASPASO
     TransferPair,
+
+    /// A custom `DuplicateMatcher`, by name
+    Custom(String),
+}
+
+/// A caller-supplied way to spot duplicates
+pub trait DuplicateMatcher: Send + Sync {
+    /// Reported as `MatchStrategy::Custom(name)`
+    fn name(&self) -> &str;
+
+    /// Some((confidence, reason)) when the two transactions look like duplicates
+    fn check(&self, tx1: &Transaction, tx2: &Transaction) -> Option<(f64, String)>;
 }
 
 // ============================================================================
@@ -62,6 +76,9 @@ pub struct DeduplicationEngine {
 
     /// Date tolerance for fuzzy matching in days (default: 1)
     pub fuzzy_date_tolerance_days: i64,
+
+    /// Custom strategies, tried in order when no built-in one matches
+    matchers: Vec<Box<dyn DuplicateMatcher>>,
 }
 
 impl DeduplicationEngine {
@@ -73,19 +90,46 @@ impl DeduplicationEngine {
             transfer_match_threshold: 0.90,
             fuzzy_amount_tolerance: 0.50,
             fuzzy_date_tolerance_days: 1,
+            matchers: Vec::new(),
         }
     }
 
+    /// Add a custom strategy
+    pub fn with_matcher(mut self, matcher: impl DuplicateMatcher + 'static) -> Self {
+        self.matchers.push(Box::new(matcher));
+        self
+    }
+
     /// Find all duplicate matches in a list of transactions
     pub fn find_duplicates(&self, transactions: &[Transaction]) -> Vec<DuplicateMatch> {
+        self.find_duplicates_with_progress(transactions, |_| true)
+            .unwrap_or_default()
+    }
+
+    /// Same as `find_duplicates`, calling `keep_going(rows_done)` after each row
+    ///
+    /// Returns None as soon as `keep_going` returns false (scan cancelled).
+    pub fn find_duplicates_with_progress(
+        &self,
+        transactions: &[Transaction],
+        mut keep_going: impl FnMut(usize) -> bool,
+    ) -> Option<Vec<DuplicateMatch>> {
         let mut matches = Vec::new();
 
         // Compare each transaction with every other transaction
         for i in 0..transactions.len() {
+            if !keep_going(i) {
+                return None;
+            }
             for j in (i + 1)..transactions.len() {
                 let tx1 = &transactions[i];
                 let tx2 = &transactions[j];
 
+                // Rows entered by hand are never duplicates (two cash coffees are two coffees)
+                if is_manual(tx1) || is_manual(tx2) {
+                    continue;
+                }
+
                 // Try exact match first (highest confidence)
                 if let Some(m) = self.check_exact_match(i, j, tx1, tx2) {
                     matches.push(m);
@@ -101,11 +145,17 @@ impl DeduplicationEngine {
                 // Try fuzzy match (lowest confidence)
                 if let Some(m) = self.check_fuzzy_match(i, j, tx1, tx2) {
                     matches.push(m);
+                    continue;
+                }
+
+                // Custom strategies last
+                if let Some(m) = self.check_custom(i, j, tx1, tx2) {
+                    matches.push(m);
                 }
             }
         }
 
-        matches
+        Some(matches)
     }
 
     /// Strategy 1: Exact Match
@@ -266,6 +316,26 @@ impl DeduplicationEngine {
         })
     }
 
+    /// First custom strategy that matches
+    fn check_custom(
+        &self,
+        i: usize,
+        j: usize,
+        tx1: &Transaction,
+        tx2: &Transaction,
+    ) -> Option<DuplicateMatch> {
+        self.matchers.iter().find_map(|matcher| {
+            let (confidence, reason) = matcher.check(tx1, tx2)?;
+            Some(DuplicateMatch {
+                tx1_index: i,
+                tx2_index: j,
+                confidence,
+                strategy: MatchStrategy::Custom(matcher.name().to_string()),
+                reason,
+            })
+        })
+    }
+
     /// Parse date from string (supports MM/DD/YYYY and YYYY-MM-DD)
     fn parse_date(&self, date_str: &str) -> Option<NaiveDate> {
         // Try MM/DD/YYYY
@@ -295,7 +365,7 @@ impl Default for DeduplicationEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
+    use crate::db::test_support;
 
     fn create_test_transaction(
         date: &str,
@@ -311,21 +381,11 @@ mod tests {
             transaction_type: tx_type.to_string(),
             category: "Test".to_string(),
             merchant: merchant.to_string(),
-            currency: "USD".to_string(),
             account_name: "Test Account".to_string(),
             account_number: "1234".to_string(),
             bank: "Test Bank".to_string(),
             source_file: "test.csv".to_string(),
-            line_number: "1".to_string(),
-            classification_notes: "".to_string(),
-            // Badge 19 fields - defaults
-            id: String::new(),
-            version: 0,
-            system_time: None,
-            valid_from: None,
-            valid_until: None,
-            previous_version_id: None,
-            metadata: HashMap::new(),
+            ..test_support::sample_transaction()
         }
     }
 
@@ -461,4 +521,36 @@ mod tests {
 
         assert_eq!(matches.len(), 0);
     }
+
+    #[test]
+    fn test_custom_matcher_runs_after_builtin_strategies() {
+        /// Same amount within a week counts as a duplicate
+        struct SameAmountSameWeek;
+
+        impl DuplicateMatcher for SameAmountSameWeek {
+            fn name(&self) -> &str {
+                "same_amount_same_week"
+            }
+
+            fn check(&self, tx1: &Transaction, tx2: &Transaction) -> Option<(f64, String)> {
+                let days = (tx1.parsed_date()? - tx2.parsed_date()?).num_days().abs();
+                ((tx1.amount_numeric - tx2.amount_numeric).abs() < 0.001 && days <= 7)
+                    .then(|| (0.6, format!("same amount {} days apart", days)))
+            }
+        }
+
+        let engine = DeduplicationEngine::new().with_matcher(SameAmountSameWeek);
+        let transactions = vec![
+            create_test_transaction("12/25/2024", 45.99, "Starbucks", "GASTO"),
+            create_test_transaction("12/25/2024", 45.99, "Starbucks", "GASTO"),
+            create_test_transaction("12/29/2024", 45.99, "Amazon", "GASTO"),
+        ];
+        let matches = engine.find_duplicates(&transactions);
+
+        assert_eq!(matches.len(), 3);
+        assert_eq!(matches[0].strategy, MatchStrategy::ExactMatch);
+        assert_eq!(matches[1].strategy, MatchStrategy::Custom("same_amount_same_week".to_string()));
+        assert_eq!(matches[1].reason, "same amount 4 days apart");
+        assert!(DeduplicationEngine::new().find_duplicates(&transactions[1..]).is_empty());
+    }
 }
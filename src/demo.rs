@@ -0,0 +1,273 @@
+// 🎭 Demo Data - Synthetic statements for exploring the system
+//
+// Problem solved:
+// - New users had to point the tool at real bank statements to see anything
+// - Tests and screenshots need realistic data that contains nothing private
+//
+// The generator is deterministic: the same seed always produces the same
+// transactions, so demos and tests are reproducible.
+
+use crate::db::{insert_transactions_as, setup_database, Transaction};
+use anyhow::Result;
+use chrono::{Datelike, Duration, NaiveDate};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+/// Actor recorded on demo imports
+pub const DEMO_ACTOR: &str = "demo";
+
+// ============================================================================
+// RANDOMNESS (xorshift64* - small, deterministic, no extra dependency)
+// ============================================================================
+
+struct DemoRng(u64);
+
+impl DemoRng {
+    fn new(seed: u64) -> Self {
+        // xorshift must never start at 0
+        DemoRng((seed ^ 0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in [low, high)
+    fn range(&mut self, low: f64, high: f64) -> f64 {
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        low + unit * (high - low)
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[(self.next_u64() % items.len() as u64) as usize]
+    }
+}
+
+// ============================================================================
+// CATALOG
+// ============================================================================
+
+/// (raw description, merchant, category, min amount, max amount)
+const PURCHASES: &[(&str, &str, &str, f64, f64)] = &[
+    ("STARBUCKS STORE #1234", "Starbucks", "Restaurants", 4.0, 12.0),
+    ("UBER *EATS PENDING", "Uber Eats", "Restaurants", 12.0, 45.0),
+    ("AMAZON.COM MARKETPLACE", "Amazon Marketplace", "Online Shopping", 15.0, 180.0),
+    ("AMAZON MKTPL*2K4", "Amazon", "Shopping", 8.0, 90.0),
+    ("WHOLE FOODS MARKET", "Whole Foods", "Groceries", 35.0, 160.0),
+    ("TRADER JOE'S #552", "Trader Joe's", "Groceries", 20.0, 95.0),
+    ("SHELL OIL 5744", "Shell", "Transportation", 30.0, 70.0),
+    ("UBER *TRIP", "Uber", "Transportation", 9.0, 38.0),
+    ("NETFLIX.COM", "Netflix", "Subscriptions", 15.49, 15.5),
+    ("SPOTIFY USA", "Spotify", "Subscriptions", 10.99, 11.0),
+    ("APPLE.COM/BILL", "Apple", "Subscriptions", 2.99, 9.99),
+    ("CVS/PHARMACY #0321", "CVS", "Health", 6.0, 60.0),
+    ("HOME DEPOT #4410", "Home Depot", "Home", 20.0, 240.0),
+];
+
+/// (bank, account name, account number, currency)
+const CARDS: &[(&str, &str, &str, &str)] = &[
+    ("AppleCard", "Apple Card", "0001", "USD"),
+    ("BofA", "BofA Checking", "4321", "USD"),
+    ("Scotiabank", "Scotia Debit", "7788", "MXN"),
+];
+
+// ============================================================================
+// GENERATOR
+// ============================================================================
+
+/// Generate `months` months of synthetic activity ending before `until`
+///
+/// Each month has a salary deposit, rent, a credit card payment, a transfer
+/// to savings, Stripe payouts and 15-25 card purchases.
+pub fn generate_transactions(seed: u64, months: u32, until: NaiveDate) -> Vec<Transaction> {
+    let mut rng = DemoRng::new(seed);
+    let mut transactions = Vec::new();
+    let mut line = 0usize;
+
+    let first = first_of_month(until, months);
+    for month in 0..months {
+        let start = add_months(first, month);
+        let days_in_month = (add_months(start, 1) - start).num_days();
+        let on_day = |day: i64| start + Duration::days(day.min(days_in_month) - 1);
+
+        let mut push = |date: NaiveDate,
+                        description: &str,
+                        merchant: &str,
+                        category: &str,
+                        tx_type: &str,
+                        amount: f64,
+                        card: (&str, &str, &str, &str)| {
+            line += 1;
+            transactions.push(demo_transaction(
+                date,
+                description,
+                merchant,
+                category,
+                tx_type,
+                amount,
+                card,
+                line,
+            ));
+        };
+
+        let checking = CARDS[1];
+        push(on_day(1), "ACME CORP PAYROLL", "ACME Corp", "Salary", "INGRESO", 5200.0, checking);
+        push(on_day(3), "ZELLE TO LANDLORD", "Landlord", "Rent", "GASTO", -1850.0, checking);
+        push(
+            on_day(15),
+            "Stripe, Des:transfer",
+            "Stripe",
+            "Business Income",
+            "INGRESO",
+            (rng.range(400.0, 1600.0) * 100.0).round() / 100.0,
+            checking,
+        );
+        push(
+            on_day(20),
+            "Apple Card Payment",
+            "Apple Card",
+            "Credit Card Payment",
+            "PAGO_TARJETA",
+            -(rng.range(600.0, 1400.0) * 100.0).round() / 100.0,
+            checking,
+        );
+        push(on_day(25), "Wise Us Inc, Des:transfer", "Wise", "Savings", "TRASPASO", -500.0, checking);
+
+        let purchases = 15 + (rng.next_u64() % 11) as i64;
+        for _ in 0..purchases {
+            let (description, merchant, category, low, high) = *rng.pick(PURCHASES);
+            let card = *rng.pick(CARDS);
+            let mut amount = (rng.range(low, high) * 100.0).round() / 100.0;
+            if card.3 == "MXN" {
+                amount = (amount * 17.0).round();
+            }
+            let day = 1 + (rng.next_u64() % days_in_month as u64) as i64;
+            push(on_day(day), description, merchant, category, "GASTO", -amount, card);
+        }
+    }
+
+    transactions.sort_by_key(|tx| {
+        NaiveDate::parse_from_str(&tx.date, "%m/%d/%Y").unwrap_or(until)
+    });
+    transactions
+}
+
+#[allow(clippy::too_many_arguments)]
+fn demo_transaction(
+    date: NaiveDate,
+    description: &str,
+    merchant: &str,
+    category: &str,
+    tx_type: &str,
+    amount: f64,
+    (bank, account_name, account_number, currency): (&str, &str, &str, &str),
+    line: usize,
+) -> Transaction {
+    let sign = if amount < 0.0 { "-" } else { "" };
+    let symbol = if currency == "MXN" { "MX$" } else { "$" };
+
+    let mut tx = Transaction {
+        date: date.format("%m/%d/%Y").to_string(),
+        description: description.to_string(),
+        amount_original: format!("{}{}{:.2}", sign, symbol, amount.abs()),
+        amount_numeric: amount,
+        transaction_type: tx_type.to_string(),
+        category: category.to_string(),
+        merchant: merchant.to_string(),
+        currency: currency.to_string(),
+        account_name: account_name.to_string(),
+        account_number: account_number.to_string(),
+        bank: bank.to_string(),
+        source_file: format!("demo/{}.csv", bank.to_lowercase()),
+        line_number: line.to_string(),
+        classification_notes: "Synthetic demo data".to_string(),
+        id: String::new(),
+        version: 0,
+        system_time: None,
+        valid_from: None,
+        valid_until: None,
+        previous_version_id: None,
+        ledger_id: crate::ledger::default_ledger_id(),
+        metadata: HashMap::new(),
+    };
+    tx.metadata.insert("demo".to_string(), serde_json::json!(true));
+    tx.init_temporal_fields();
+    tx
+}
+
+/// First day of the month `months` months before `date`'s month
+fn first_of_month(date: NaiveDate, months: u32) -> NaiveDate {
+    let index = date.year() * 12 + date.month0() as i32 - months as i32;
+    NaiveDate::from_ymd_opt(index.div_euclid(12), index.rem_euclid(12) as u32 + 1, 1)
+        .unwrap_or(date)
+}
+
+fn add_months(first: NaiveDate, months: u32) -> NaiveDate {
+    let index = first.year() * 12 + first.month0() as i32 + months as i32;
+    NaiveDate::from_ymd_opt(index.div_euclid(12), index.rem_euclid(12) as u32 + 1, 1)
+        .unwrap_or(first)
+}
+
+// ============================================================================
+// DATABASE
+// ============================================================================
+
+/// Create the schema and load six months of demo data into `conn`
+///
+/// Meant for `Connection::open_in_memory()`: nothing touches disk.
+pub fn seed_demo_database(conn: &Connection, seed: u64) -> Result<usize> {
+    setup_database(conn)?;
+    let transactions = generate_transactions(seed, 6, chrono::Utc::now().date_naive());
+    Ok(insert_transactions_as(conn, &transactions, DEMO_ACTOR)?.inserted)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::get_active_transactions;
+
+    fn anchor() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, 15).unwrap()
+    }
+
+    #[test]
+    fn test_generator_is_deterministic() {
+        let a = generate_transactions(42, 3, anchor());
+        let b = generate_transactions(42, 3, anchor());
+        let c = generate_transactions(7, 3, anchor());
+
+        let amounts = |txs: &[Transaction]| txs.iter().map(|tx| tx.amount_numeric).collect::<Vec<_>>();
+        assert_eq!(amounts(&a), amounts(&b));
+        assert_ne!(amounts(&a), amounts(&c));
+    }
+
+    #[test]
+    fn test_generator_covers_months_and_types() {
+        let txs = generate_transactions(42, 3, anchor());
+
+        // Dec 2024 .. Feb 2025, never the anchor month itself
+        assert!(txs.iter().all(|tx| tx.date.ends_with("/2024") || tx.date.ends_with("/2025")));
+        assert!(txs.iter().all(|tx| !tx.date.starts_with("03/")));
+        assert_eq!(txs.iter().filter(|tx| tx.category == "Salary").count(), 3);
+
+        for tx_type in ["GASTO", "INGRESO", "PAGO_TARJETA", "TRASPASO"] {
+            assert!(txs.iter().any(|tx| tx.transaction_type == tx_type), "missing {}", tx_type);
+        }
+    }
+
+    #[test]
+    fn test_seed_in_memory_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        let inserted = seed_demo_database(&conn, 1).unwrap();
+
+        assert!(inserted > 100);
+        assert_eq!(get_active_transactions(&conn).unwrap().len(), inserted);
+    }
+}
@@ -0,0 +1,393 @@
+// ⚖️ Disputes - Tracking charges contested with the bank
+//
+// Problem solved:
+// - A disputed charge looked like any other expense until the refund showed up
+// - Nobody noticed when a dispute had been sitting with the bank for two months
+//
+// A dispute is a sub-state of a transaction, not a new version of it: the
+// charge itself is unchanged until the bank decides. Each transition is
+// logged as an event (with its note), so the dispute's history is the event log.
+//
+//   opened ──► bank_contacted ──► resolved_refunded
+//      │                     └──► resolved_upheld
+//      └──────────────────────────► (either resolution)
+
+use crate::db::{get_current_transaction, get_events_for_entity, insert_event, Event};
+use crate::event_schema::DisputeChanged;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// TYPES
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisputeStatus {
+    Opened,
+    BankContacted,
+    ResolvedRefunded,
+    ResolvedUpheld,
+}
+
+impl DisputeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DisputeStatus::Opened => "opened",
+            DisputeStatus::BankContacted => "bank_contacted",
+            DisputeStatus::ResolvedRefunded => "resolved_refunded",
+            DisputeStatus::ResolvedUpheld => "resolved_upheld",
+        }
+    }
+
+    /// Parse a status name (also accepts "contacted", "refunded", "upheld")
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "opened" | "open" => Ok(DisputeStatus::Opened),
+            "bank_contacted" | "contacted" => Ok(DisputeStatus::BankContacted),
+            "resolved_refunded" | "refunded" => Ok(DisputeStatus::ResolvedRefunded),
+            "resolved_upheld" | "upheld" => Ok(DisputeStatus::ResolvedUpheld),
+            other => Err(anyhow!(
+                "Unknown dispute status '{}' (expected opened, bank_contacted, resolved_refunded, resolved_upheld)",
+                other
+            )),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        matches!(self, DisputeStatus::Opened | DisputeStatus::BankContacted)
+    }
+
+    /// Disputes only move forward; resolutions are final
+    pub fn can_move_to(&self, next: DisputeStatus) -> bool {
+        match self {
+            DisputeStatus::Opened => next != DisputeStatus::Opened,
+            DisputeStatus::BankContacted => !next.is_open(),
+            DisputeStatus::ResolvedRefunded | DisputeStatus::ResolvedUpheld => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dispute {
+    pub id: String,
+    pub tx_uuid: String,
+    pub ledger_id: String,
+    pub status: DisputeStatus,
+
+    pub opened_by: String,
+    pub opened_at: DateTime<Utc>,
+
+    /// Time of the latest transition
+    pub updated_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+
+    /// Note given with the latest transition
+    pub note: Option<String>,
+}
+
+impl Dispute {
+    /// Whole days since the dispute was opened
+    pub fn age_days(&self, now: DateTime<Utc>) -> i64 {
+        (now - self.opened_at).num_days()
+    }
+}
+
+// ============================================================================
+// TRANSITIONS
+// ============================================================================
+
+/// Open a dispute on a transaction (one open dispute per transaction)
+pub fn open_dispute(conn: &Connection, tx_uuid: &str, note: Option<&str>, actor: &str) -> Result<Dispute> {
+    let tx = get_current_transaction(conn, tx_uuid)?
+        .ok_or_else(|| anyhow!("Transaction {} not found", tx_uuid))?;
+
+    if let Some(existing) = get_open_dispute(conn, tx_uuid)? {
+        return Err(anyhow!(
+            "Transaction {} already has an open dispute ({}, {})",
+            tx_uuid,
+            existing.id,
+            existing.status.as_str()
+        ));
+    }
+
+    let now = Utc::now();
+    let dispute = Dispute {
+        id: uuid::Uuid::new_v4().to_string(),
+        tx_uuid: tx_uuid.to_string(),
+        ledger_id: tx.ledger_id,
+        status: DisputeStatus::Opened,
+        opened_by: actor.to_string(),
+        opened_at: now,
+        updated_at: now,
+        resolved_at: None,
+        note: note.map(str::to_string),
+    };
+
+    conn.execute(
+        "INSERT INTO disputes (
+            id, tx_uuid, ledger_id, status, opened_by, opened_at, updated_at, resolved_at, note
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, NULL, ?8)",
+        params![
+            dispute.id,
+            dispute.tx_uuid,
+            dispute.ledger_id,
+            dispute.status.as_str(),
+            dispute.opened_by,
+            dispute.opened_at.to_rfc3339(),
+            dispute.updated_at.to_rfc3339(),
+            dispute.note,
+        ],
+    )?;
+
+    log_dispute_event(conn, "dispute_opened", &dispute, None, actor)?;
+    Ok(dispute)
+}
+
+/// Move a dispute to its next status (e.g. bank_contacted, resolved_refunded)
+pub fn advance_dispute(
+    conn: &Connection,
+    dispute_id: &str,
+    status: DisputeStatus,
+    note: Option<&str>,
+    actor: &str,
+) -> Result<Dispute> {
+    let dispute = get_dispute(conn, dispute_id)?
+        .ok_or_else(|| anyhow!("Dispute {} not found", dispute_id))?;
+
+    if !dispute.status.can_move_to(status) {
+        return Err(anyhow!(
+            "Dispute {} cannot move from {} to {}",
+            dispute_id,
+            dispute.status.as_str(),
+            status.as_str()
+        ));
+    }
+
+    let now = Utc::now();
+    let resolved_at = if status.is_open() { None } else { Some(now) };
+    conn.execute(
+        "UPDATE disputes SET status = ?1, updated_at = ?2, resolved_at = ?3, note = ?4 WHERE id = ?5",
+        params![
+            status.as_str(),
+            now.to_rfc3339(),
+            resolved_at.map(|t| t.to_rfc3339()),
+            note,
+            dispute_id
+        ],
+    )?;
+
+    let updated = Dispute {
+        status,
+        updated_at: now,
+        resolved_at,
+        note: note.map(str::to_string),
+        ..dispute.clone()
+    };
+    let event_type = if status.is_open() { "dispute_updated" } else { "dispute_resolved" };
+    log_dispute_event(conn, event_type, &updated, Some(dispute.status), actor)?;
+
+    Ok(updated)
+}
+
+fn log_dispute_event(
+    conn: &Connection,
+    event_type: &str,
+    dispute: &Dispute,
+    from: Option<DisputeStatus>,
+    actor: &str,
+) -> Result<()> {
+    let payload = DisputeChanged {
+        tx_uuid: dispute.tx_uuid.clone(),
+        from,
+        to: dispute.status,
+        note: dispute.note.clone(),
+    };
+    let event = Event::typed(event_type, "dispute", &dispute.id, &payload, actor)?.with_ledger(&dispute.ledger_id);
+    insert_event(conn, &event)
+}
+
+// ============================================================================
+// QUERIES
+// ============================================================================
+
+const DISPUTE_COLUMNS: &str =
+    "id, tx_uuid, ledger_id, status, opened_by, opened_at, updated_at, resolved_at, note";
+
+pub fn get_dispute(conn: &Connection, dispute_id: &str) -> Result<Option<Dispute>> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT {} FROM disputes WHERE id = ?1", DISPUTE_COLUMNS),
+            [dispute_id],
+            row_to_dispute,
+        )
+        .optional()?)
+}
+
+/// The open dispute on a transaction, if any
+pub fn get_open_dispute(conn: &Connection, tx_uuid: &str) -> Result<Option<Dispute>> {
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM disputes
+                 WHERE tx_uuid = ?1 AND status IN ('opened', 'bank_contacted')",
+                DISPUTE_COLUMNS
+            ),
+            [tx_uuid],
+            row_to_dispute,
+        )
+        .optional()?)
+}
+
+/// Disputes in a ledger, oldest first
+pub fn list_disputes(conn: &Connection, ledger_id: &str, open_only: bool) -> Result<Vec<Dispute>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM disputes
+         WHERE ledger_id = ?1 AND (?2 = 0 OR status IN ('opened', 'bank_contacted'))
+         ORDER BY opened_at ASC",
+        DISPUTE_COLUMNS
+    ))?;
+    let disputes = stmt
+        .query_map(params![ledger_id, open_only], row_to_dispute)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(disputes)
+}
+
+/// Open disputes opened more than `days` days before `now`, oldest first
+pub fn stale_disputes(
+    conn: &Connection,
+    ledger_id: &str,
+    days: i64,
+    now: DateTime<Utc>,
+) -> Result<Vec<Dispute>> {
+    Ok(list_disputes(conn, ledger_id, true)?
+        .into_iter()
+        .filter(|dispute| dispute.age_days(now) > days)
+        .collect())
+}
+
+/// Every transition of a dispute, oldest first
+pub fn get_dispute_history(conn: &Connection, dispute_id: &str) -> Result<Vec<Event>> {
+    let mut events = get_events_for_entity(conn, "dispute", dispute_id)?;
+    events.reverse();
+    Ok(events)
+}
+
+fn row_to_dispute(row: &rusqlite::Row) -> rusqlite::Result<Dispute> {
+    fn parse_time(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now())
+    }
+
+    let status: String = row.get(3)?;
+    let opened_at: String = row.get(5)?;
+    let updated_at: String = row.get(6)?;
+    let resolved_at: Option<String> = row.get(7)?;
+
+    Ok(Dispute {
+        id: row.get(0)?,
+        tx_uuid: row.get(1)?,
+        ledger_id: row.get(2)?,
+        status: DisputeStatus::parse(&status).unwrap_or(DisputeStatus::Opened),
+        opened_by: row.get(4)?,
+        opened_at: parse_time(&opened_at),
+        updated_at: parse_time(&updated_at),
+        resolved_at: resolved_at.as_deref().map(parse_time),
+        note: row.get(8)?,
+    })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::db::{insert_transactions_as, setup_database, Transaction};
+    use crate::ledger::DEFAULT_LEDGER_ID;
+    use chrono::Duration;
+
+    fn setup() -> (Connection, String) {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let mut tx = Transaction {
+            date: "03/04/2025".to_string(),
+            description: "HOME DEPOT #4410".to_string(),
+            amount_original: "-$240.00".to_string(),
+            amount_numeric: -240.0,
+            category: "Home".to_string(),
+            merchant: "Home Depot".to_string(),
+            account_name: "Apple Card".to_string(),
+            bank: "AppleCard".to_string(),
+            source_file: "applecard.csv".to_string(),
+            line_number: "7".to_string(),
+            ..test_support::sample_transaction()
+        };
+        tx.init_temporal_fields();
+        insert_transactions_as(&conn, std::slice::from_ref(&tx), "test").unwrap();
+        (conn, tx.id)
+    }
+
+    #[test]
+    fn test_dispute_lifecycle() {
+        let (conn, tx_uuid) = setup();
+
+        let dispute = open_dispute(&conn, &tx_uuid, Some("Charged twice"), "ana").unwrap();
+        assert!(open_dispute(&conn, &tx_uuid, None, "ana").is_err());
+
+        advance_dispute(&conn, &dispute.id, DisputeStatus::BankContacted, Some("Case #881"), "ana").unwrap();
+        let resolved =
+            advance_dispute(&conn, &dispute.id, DisputeStatus::ResolvedRefunded, None, "ana").unwrap();
+        assert!(resolved.resolved_at.is_some());
+        assert_eq!(get_dispute(&conn, &dispute.id).unwrap().unwrap(), resolved);
+
+        let history: Vec<String> = get_dispute_history(&conn, &dispute.id)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.event_type)
+            .collect();
+        assert_eq!(history, vec!["dispute_opened", "dispute_updated", "dispute_resolved"]);
+
+        // Resolved: a new dispute may be opened on the same charge
+        assert!(get_open_dispute(&conn, &tx_uuid).unwrap().is_none());
+        assert!(open_dispute(&conn, &tx_uuid, None, "ana").is_ok());
+    }
+
+    #[test]
+    fn test_transitions_only_move_forward() {
+        use DisputeStatus::*;
+
+        assert!(Opened.can_move_to(BankContacted));
+        assert!(Opened.can_move_to(ResolvedUpheld));
+        assert!(!BankContacted.can_move_to(Opened));
+        assert!(!ResolvedRefunded.can_move_to(ResolvedUpheld));
+
+        let (conn, tx_uuid) = setup();
+        let dispute = open_dispute(&conn, &tx_uuid, None, "ana").unwrap();
+        advance_dispute(&conn, &dispute.id, ResolvedUpheld, None, "ana").unwrap();
+        assert!(advance_dispute(&conn, &dispute.id, BankContacted, None, "ana").is_err());
+    }
+
+    #[test]
+    fn test_stale_report() {
+        let (conn, tx_uuid) = setup();
+        let dispute = open_dispute(&conn, &tx_uuid, None, "ana").unwrap();
+
+        let now = Utc::now();
+        assert!(stale_disputes(&conn, DEFAULT_LEDGER_ID, 30, now).unwrap().is_empty());
+
+        let later = now + Duration::days(45);
+        let stale = stale_disputes(&conn, DEFAULT_LEDGER_ID, 30, later).unwrap();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].age_days(later), 45);
+
+        advance_dispute(&conn, &dispute.id, DisputeStatus::ResolvedUpheld, None, "ana").unwrap();
+        assert!(stale_disputes(&conn, DEFAULT_LEDGER_ID, 30, later).unwrap().is_empty());
+    }
+}
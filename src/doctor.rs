@@ -0,0 +1,488 @@
+// 🩺 Doctor - Environment and data diagnostics
+//
+// Problem solved:
+// - "It shows nothing" could mean a missing config, an unreachable DB, rows
+//   from before UUIDs existed, or a database written by another build
+// - Each check says what is wrong AND the command that fixes it
+//
+// Checks only read; `apply_fixes` performs the safe, repeatable fixes.
+
+use crate::config::AppConfig;
+use crate::db::{get_active_transactions, migrate_add_uuids, setup_database, SCHEMA_VERSION};
+use crate::entities::{BankRegistry, CategoryRegistry};
+use anyhow::Result;
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// ============================================================================
+// TYPES
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+
+    /// Not applicable to this installation
+    Skipped,
+}
+
+impl CheckStatus {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            CheckStatus::Ok => "✅",
+            CheckStatus::Warn => "⚠️ ",
+            CheckStatus::Fail => "❌",
+            CheckStatus::Skipped => "➖",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+
+    /// Actionable fix (a command to run or a setting to change)
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Check { name, status: CheckStatus::Ok, detail: detail.into(), fix: None }
+    }
+
+    fn problem(name: &'static str, status: CheckStatus, detail: impl Into<String>, fix: &str) -> Self {
+        Check { name, status, detail: detail.into(), fix: Some(fix.to_string()) }
+    }
+
+    fn skipped(name: &'static str, detail: impl Into<String>) -> Self {
+        Check { name, status: CheckStatus::Skipped, detail: detail.into(), fix: None }
+    }
+}
+
+// ============================================================================
+// ENVIRONMENT
+// ============================================================================
+
+/// Run every check: config file, database reachability, then the data checks
+pub fn diagnose(config_path: &Path) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let config = if !config_path.exists() {
+        checks.push(Check::problem(
+            "config",
+            CheckStatus::Warn,
+            format!("No config file at {:?}; using defaults", config_path),
+            "Run: init",
+        ));
+        AppConfig::default()
+    } else {
+        match AppConfig::load_from(config_path) {
+            Ok(config) => {
+                checks.push(Check::ok("config", format!("{:?} is valid", config_path)));
+                config
+            }
+            Err(e) => {
+                checks.push(Check::problem(
+                    "config",
+                    CheckStatus::Fail,
+                    format!("{:#}", e),
+                    "Fix the file by hand or re-run: init",
+                ));
+                return checks;
+            }
+        }
+    };
+
+    if config.in_memory() {
+        checks.push(Check::ok("database", "In memory: nothing is read from or kept on disk"));
+        return checks;
+    }
+
+    // Open without CREATE so a wrong path is reported instead of hidden
+    let conn = match Connection::open_with_flags(&config.db_path, OpenFlags::SQLITE_OPEN_READ_WRITE) {
+        Ok(conn) => conn,
+        Err(e) => {
+            checks.push(Check::problem(
+                "database",
+                CheckStatus::Fail,
+                format!("Cannot open {}: {}", config.db_path, e),
+                "Check db_path in the config, or run: init",
+            ));
+            return checks;
+        }
+    };
+    checks.push(Check::ok("database", format!("Opened {}", config.db_path)));
+
+    match check_database(&conn) {
+        Ok(data_checks) => checks.extend(data_checks),
+        Err(e) => checks.push(Check::problem(
+            "database",
+            CheckStatus::Fail,
+            format!("Diagnostics failed: {:#}", e),
+            "Restore from backup if the file is damaged",
+        )),
+    }
+
+    checks
+}
+
+// ============================================================================
+// DATA CHECKS
+// ============================================================================
+
+/// Checks on an open database (no writes)
+pub fn check_database(conn: &Connection) -> Result<Vec<Check>> {
+    Ok(vec![
+        check_wal(conn)?,
+        check_integrity(conn)?,
+        check_schema_version(conn)?,
+        check_missing_uuids(conn)?,
+        check_entity_links(conn)?,
+        check_orphaned_events(conn)?,
+        check_fts(conn)?,
+        check_indexes(conn)?,
+    ])
+}
+
+fn check_wal(conn: &Connection) -> Result<Check> {
+    let mode: String = conn.query_row("PRAGMA journal_mode", [], |row| row.get(0))?;
+    if !mode.eq_ignore_ascii_case("wal") {
+        return Ok(Check::problem(
+            "wal",
+            CheckStatus::Warn,
+            format!("journal_mode is {} (expected wal)", mode),
+            "Run: doctor --fix (re-applies WAL mode)",
+        ));
+    }
+
+    // PASSIVE never blocks writers; busy=1 means another connection holds it
+    let (busy, log_frames, checkpointed): (i64, i64, i64) =
+        conn.query_row("PRAGMA wal_checkpoint(PASSIVE)", [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+    if busy != 0 || log_frames > 10_000 {
+        return Ok(Check::problem(
+            "wal",
+            CheckStatus::Warn,
+            format!("WAL has {} frames, {} checkpointed (busy={})", log_frames, checkpointed, busy),
+            "Close other connections, then run: doctor --fix",
+        ));
+    }
+
+    Ok(Check::ok("wal", format!("WAL mode, {} frames pending", log_frames - checkpointed)))
+}
+
+fn check_integrity(conn: &Connection) -> Result<Check> {
+    let result: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+    Ok(if result == "ok" {
+        Check::ok("integrity", "quick_check passed")
+    } else {
+        Check::problem(
+            "integrity",
+            CheckStatus::Fail,
+            format!("quick_check: {}", result),
+            "Restore from backup, or export with sync export and re-import",
+        )
+    })
+}
+
+fn check_schema_version(conn: &Connection) -> Result<Check> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    Ok(match version.cmp(&SCHEMA_VERSION) {
+        std::cmp::Ordering::Equal => Check::ok("schema", format!("Schema v{}", version)),
+        std::cmp::Ordering::Less => Check::problem(
+            "schema",
+            CheckStatus::Warn,
+            format!("Database schema v{} is older than this build (v{})", version, SCHEMA_VERSION),
+            "Run: doctor --fix (applies migrations)",
+        ),
+        std::cmp::Ordering::Greater => Check::problem(
+            "schema",
+            CheckStatus::Fail,
+            format!("Database schema v{} is newer than this build (v{})", version, SCHEMA_VERSION),
+            "Upgrade this installation before writing to the database",
+        ),
+    })
+}
+
+fn check_missing_uuids(conn: &Connection) -> Result<Check> {
+    let missing: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM transactions WHERE tx_uuid IS NULL OR tx_uuid = ''",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(if missing == 0 {
+        Check::ok("uuids", "Every transaction has an identity")
+    } else {
+        Check::problem(
+            "uuids",
+            CheckStatus::Warn,
+            format!("{} transactions have no UUID (cannot be corrected or synced)", missing),
+            "Run: doctor --fix (assigns UUIDs)",
+        )
+    })
+}
+
+/// Active transactions whose bank or category no registry recognizes
+fn check_entity_links(conn: &Connection) -> Result<Check> {
+    let banks = BankRegistry::new();
+    let categories = CategoryRegistry::with_defaults();
+
+    let mut unlinked: BTreeMap<String, usize> = BTreeMap::new();
+    let mut rows = 0;
+    for tx in get_active_transactions(conn)? {
+        let mut missing = false;
+        if banks.find_by_string(&tx.bank).is_none() {
+            *unlinked.entry(format!("bank '{}'", tx.bank)).or_default() += 1;
+            missing = true;
+        }
+        if !tx.category.is_empty() && categories.find_by_name(&tx.category).is_none() {
+            *unlinked.entry(format!("category '{}'", tx.category)).or_default() += 1;
+            missing = true;
+        }
+        if missing {
+            rows += 1;
+        }
+    }
+
+    if rows == 0 {
+        return Ok(Check::ok("entities", "Every bank and category resolves to an entity"));
+    }
+
+    let mut worst: Vec<(String, usize)> = unlinked.into_iter().collect();
+    worst.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    let examples: Vec<String> = worst
+        .iter()
+        .take(3)
+        .map(|(name, count)| format!("{} ×{}", name, count))
+        .collect();
+
+    Ok(Check::problem(
+        "entities",
+        CheckStatus::Warn,
+        format!("{} transactions lack entity links ({})", rows, examples.join(", ")),
+        "Add aliases to the bank/category registries, or fix values with reclassify",
+    ))
+}
+
+/// Transaction events that point at no stored transaction (by UUID or hash)
+fn check_orphaned_events(conn: &Connection) -> Result<Check> {
+    let orphaned: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM events e
+         WHERE e.entity_type = 'transaction'
+           AND NOT EXISTS (SELECT 1 FROM transactions t WHERE t.tx_uuid = e.entity_id)
+           AND NOT EXISTS (SELECT 1 FROM transactions t WHERE t.idempotency_hash = e.entity_id)",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(if orphaned == 0 {
+        Check::ok("events", "Every transaction event has its transaction")
+    } else {
+        Check::problem(
+            "events",
+            CheckStatus::Warn,
+            format!("{} events refer to transactions that are not stored here", orphaned),
+            "Import the missing changeset from the other instance: sync import <file>",
+        )
+    })
+}
+
+fn check_fts(conn: &Connection) -> Result<Check> {
+    let fts_tables: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND sql LIKE '%USING fts%'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(if fts_tables == 0 {
+        Check::skipped("fts", "No full-text index configured")
+    } else {
+        Check::ok("fts", format!("{} full-text indexes present", fts_tables))
+    })
+}
+
+// ============================================================================
+// QUERY PLANS
+// ============================================================================
+
+/// Filters the ledger, search and API run all the time, with sample values
+pub const COMMON_QUERIES: &[(&str, &str)] = &[
+    ("by uuid", "SELECT * FROM transactions WHERE tx_uuid = 'x'"),
+    ("current rows", "SELECT * FROM transactions WHERE valid_until IS NULL"),
+    ("by ledger", "SELECT * FROM transactions WHERE ledger_id = 'default'"),
+    ("by type", "SELECT * FROM transactions WHERE transaction_type = 'GASTO'"),
+    ("by category", "SELECT * FROM transactions WHERE category = 'Groceries'"),
+    ("by merchant", "SELECT * FROM transactions WHERE merchant = 'STARBUCKS'"),
+    ("by amount", "SELECT * FROM transactions WHERE amount_numeric BETWEEN -100 AND -10"),
+    ("by date and bank", "SELECT * FROM transactions WHERE date = '01/02/2025' AND bank = 'BofA'"),
+];
+
+/// How SQLite runs one of COMMON_QUERIES
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryPlan {
+    pub name: &'static str,
+    pub sql: &'static str,
+    /// EXPLAIN QUERY PLAN details, in order
+    pub steps: Vec<String>,
+    /// A step reads the whole transactions table
+    pub full_scan: bool,
+}
+
+/// EXPLAIN QUERY PLAN of every common query
+pub fn explain_common_queries(conn: &Connection) -> Result<Vec<QueryPlan>> {
+    let mut plans = Vec::new();
+    for &(name, sql) in COMMON_QUERIES {
+        let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql))?;
+        let steps = stmt
+            .query_map([], |row| row.get::<_, String>(3))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let full_scan = steps
+            .iter()
+            .any(|step| step.starts_with("SCAN transactions") && !step.contains("USING"));
+        plans.push(QueryPlan { name, sql, steps, full_scan });
+    }
+    Ok(plans)
+}
+
+fn check_indexes(conn: &Connection) -> Result<Check> {
+    let scans: Vec<&str> = explain_common_queries(conn)?
+        .into_iter()
+        .filter(|plan| plan.full_scan)
+        .map(|plan| plan.name)
+        .collect();
+    Ok(if scans.is_empty() {
+        Check::ok("indexes", format!("{} common filters use an index", COMMON_QUERIES.len()))
+    } else {
+        Check::problem(
+            "indexes",
+            CheckStatus::Warn,
+            format!("Full table scan for: {}", scans.join(", ")),
+            "Run: doctor --fix (creates missing indexes); doctor explain shows the plans",
+        )
+    })
+}
+
+// ============================================================================
+// FIXES
+// ============================================================================
+
+/// Apply the safe fixes: migrations + WAL mode, UUIDs, WAL checkpoint
+///
+/// Returns a line per action taken.
+pub fn apply_fixes(conn: &Connection) -> Result<Vec<String>> {
+    let mut actions = Vec::new();
+
+    setup_database(conn)?;
+    actions.push(format!("Applied migrations (schema v{}, WAL mode)", SCHEMA_VERSION));
+
+    let assigned = migrate_add_uuids(conn)?;
+    if assigned > 0 {
+        actions.push(format!("Assigned UUIDs to {} transactions", assigned));
+    }
+
+    let (busy, _, checkpointed): (i64, i64, i64) =
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+    if busy == 0 {
+        actions.push(format!("Checkpointed {} WAL frames", checkpointed.max(0)));
+    }
+
+    Ok(actions)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_event, insert_transactions_as, Event};
+    use crate::event_schema::TransactionAdded;
+    use crate::demo::generate_transactions;
+
+    fn status(checks: &[Check], name: &str) -> CheckStatus {
+        checks.iter().find(|c| c.name == name).unwrap().status
+    }
+
+    #[test]
+    fn test_healthy_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        insert_transactions_as(&conn, &generate_transactions(3, 1, today), "test").unwrap();
+
+        let checks = check_database(&conn).unwrap();
+        assert_eq!(status(&checks, "integrity"), CheckStatus::Ok);
+        assert_eq!(status(&checks, "schema"), CheckStatus::Ok);
+        assert_eq!(status(&checks, "uuids"), CheckStatus::Ok);
+        assert_eq!(status(&checks, "events"), CheckStatus::Ok);
+        assert_eq!(status(&checks, "fts"), CheckStatus::Skipped);
+    }
+
+    #[test]
+    fn test_problems_have_fixes() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        conn.pragma_update(None, "user_version", 0).unwrap();
+        let payload = TransactionAdded { bank: "BofA".to_string(), amount: -5.25, source_file: "gone.csv".to_string() };
+        let event = Event::typed("transaction_added", "transaction", "gone", &payload, "test").unwrap();
+        insert_event(&conn, &event).unwrap();
+
+        let checks = check_database(&conn).unwrap();
+        assert_eq!(status(&checks, "schema"), CheckStatus::Warn);
+        assert_eq!(status(&checks, "events"), CheckStatus::Warn);
+        assert!(checks
+            .iter()
+            .filter(|c| matches!(c.status, CheckStatus::Warn | CheckStatus::Fail))
+            .all(|c| c.fix.is_some()));
+
+        apply_fixes(&conn).unwrap();
+        let checks = check_database(&conn).unwrap();
+        assert_eq!(status(&checks, "schema"), CheckStatus::Ok);
+    }
+
+    #[test]
+    fn test_missing_config_and_database() {
+        let dir = std::env::temp_dir().join(format!("trust-doctor-{}", uuid::Uuid::new_v4()));
+        let checks = diagnose(&dir.join("config.json"));
+        assert_eq!(status(&checks, "config"), CheckStatus::Warn);
+
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = AppConfig {
+            db_path: dir.join("missing.db").to_string_lossy().to_string(),
+            ..AppConfig::default()
+        };
+        config.save_to(&dir.join("config.json")).unwrap();
+        let checks = diagnose(&dir.join("config.json"));
+        assert_eq!(status(&checks, "config"), CheckStatus::Ok);
+        assert_eq!(status(&checks, "database"), CheckStatus::Fail);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_index_is_reported_and_fixed() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let plans = explain_common_queries(&conn).unwrap();
+        assert!(plans.iter().all(|plan| !plan.full_scan), "{:?}", plans);
+        assert_eq!(status(&check_database(&conn).unwrap(), "indexes"), CheckStatus::Ok);
+
+        conn.execute("DROP INDEX idx_merchant", []).unwrap();
+        let checks = check_database(&conn).unwrap();
+        let indexes = checks.iter().find(|c| c.name == "indexes").unwrap();
+        assert_eq!(indexes.status, CheckStatus::Warn);
+        assert_eq!(indexes.detail, "Full table scan for: by merchant");
+
+        apply_fixes(&conn).unwrap();
+        assert_eq!(status(&check_database(&conn).unwrap(), "indexes"), CheckStatus::Ok);
+    }
+}
@@ -0,0 +1,343 @@
+// 👯 Duplicates - Review clusters found by the DeduplicationEngine
+//
+// Problem solved:
+// - find_duplicates reports pairs (A~B, B~C), but a reviewer thinks in groups
+//   ("these three rows are the same coffee")
+// - Every scan reported the same false positives again
+//
+// Pairs are grouped into clusters. A reviewer merges a cluster (keeps one
+// transaction, voids the rest), dismisses it (not duplicates) or defers it.
+// Decisions are stored by cluster key (a hash of its transaction UUIDs), so a
+// decided cluster stays hidden until its membership changes.
+
+use crate::db::{insert_event, void_transaction, Event, Transaction};
+use crate::event_schema::DuplicatesDecided;
+use crate::deduplication::{DeduplicationEngine, MatchStrategy};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// How long a deferred cluster stays out of the review list
+pub const DEFER_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateCluster {
+    /// Stable id: hash of the sorted transaction UUIDs
+    pub key: String,
+
+    /// Candidates, oldest first
+    pub transactions: Vec<Transaction>,
+
+    /// Highest pair confidence inside the cluster
+    pub confidence: f64,
+
+    /// Why the engine matched them (one per pair)
+    pub reasons: Vec<String>,
+}
+
+impl DuplicateCluster {
+    pub fn tx_uuids(&self) -> Vec<String> {
+        self.transactions.iter().map(|tx| tx.id.clone()).collect()
+    }
+
+    pub fn ledger_id(&self) -> &str {
+        self.transactions.first().map(|tx| tx.ledger_id.as_str()).unwrap_or("default")
+    }
+}
+
+/// Cluster key: order-independent hash of the member UUIDs
+pub fn cluster_key(tx_uuids: &[String]) -> String {
+    let mut sorted = tx_uuids.to_vec();
+    sorted.sort();
+    let mut hasher = Sha256::new();
+    hasher.update(sorted.join(","));
+    format!("{:x}", hasher.finalize())
+}
+
+/// Group duplicate pairs (exact and fuzzy; transfer pairs are not duplicates)
+pub fn find_duplicate_clusters(engine: &DeduplicationEngine, transactions: &[Transaction]) -> Vec<DuplicateCluster> {
+    find_duplicate_clusters_with_progress(engine, transactions, |_| true).unwrap_or_default()
+}
+
+/// `find_duplicate_clusters` with a per-row callback; None when it returns false
+pub fn find_duplicate_clusters_with_progress(
+    engine: &DeduplicationEngine,
+    transactions: &[Transaction],
+    keep_going: impl FnMut(usize) -> bool,
+) -> Option<Vec<DuplicateCluster>> {
+    let owned: Vec<Transaction> = transactions
+        .iter()
+        .filter(|tx| tx.is_active() && !tx.is_voided())
+        .cloned()
+        .collect();
+
+    // Union-find over candidate indices
+    let mut parent: Vec<usize> = (0..owned.len()).collect();
+    fn root(parent: &mut [usize], i: usize) -> usize {
+        let mut i = i;
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let matches: Vec<_> = engine
+        .find_duplicates_with_progress(&owned, keep_going)?
+        .into_iter()
+        .filter(|m| m.strategy != MatchStrategy::TransferPair)
+        .collect();
+    for m in &matches {
+        let (a, b) = (root(&mut parent, m.tx1_index), root(&mut parent, m.tx2_index));
+        if a != b {
+            parent[b] = a;
+        }
+    }
+
+    let mut groups: BTreeMap<usize, (Vec<usize>, f64, Vec<String>)> = BTreeMap::new();
+    for m in &matches {
+        let group = groups.entry(root(&mut parent, m.tx1_index)).or_default();
+        for index in [m.tx1_index, m.tx2_index] {
+            if !group.0.contains(&index) {
+                group.0.push(index);
+            }
+        }
+        group.1 = group.1.max(m.confidence);
+        group.2.push(m.reason.clone());
+    }
+
+    let clusters = groups
+        .into_values()
+        .map(|(mut indices, confidence, reasons)| {
+            indices.sort_unstable();
+            let mut members: Vec<Transaction> = indices.into_iter().map(|i| owned[i].clone()).collect();
+            members.sort_by_key(|tx| (tx.parsed_date(), tx.system_time));
+            let key = cluster_key(&members.iter().map(|tx| tx.id.clone()).collect::<Vec<_>>());
+            DuplicateCluster { key, transactions: members, confidence, reasons }
+        })
+        .collect();
+    Some(clusters)
+}
+
+// ============================================================================
+// DECISIONS
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateDecision {
+    /// One transaction kept, the others voided
+    Merged,
+    /// Not duplicates
+    Dismissed,
+    /// Look again later (hidden for DEFER_DAYS)
+    Deferred,
+}
+
+impl DuplicateDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DuplicateDecision::Merged => "merged",
+            DuplicateDecision::Dismissed => "dismissed",
+            DuplicateDecision::Deferred => "deferred",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "merged" => Ok(DuplicateDecision::Merged),
+            "dismissed" => Ok(DuplicateDecision::Dismissed),
+            "deferred" => Ok(DuplicateDecision::Deferred),
+            other => Err(anyhow!("Unknown duplicate decision: {}", other)),
+        }
+    }
+}
+
+/// Record a decision for a cluster (replacing an earlier deferral)
+pub fn record_decision(
+    conn: &Connection,
+    cluster: &DuplicateCluster,
+    decision: DuplicateDecision,
+    actor: &str,
+) -> Result<()> {
+    let now = Utc::now();
+    let defer_until = (decision == DuplicateDecision::Deferred).then(|| (now + Duration::days(DEFER_DAYS)).to_rfc3339());
+
+    conn.execute(
+        "INSERT OR REPLACE INTO duplicate_decisions
+            (cluster_key, ledger_id, decision, tx_uuids, decided_by, decided_at, defer_until)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            cluster.key,
+            cluster.ledger_id(),
+            decision.as_str(),
+            serde_json::to_string(&cluster.tx_uuids())?,
+            actor,
+            now.to_rfc3339(),
+            defer_until,
+        ],
+    )?;
+
+    let event = Event::typed(
+        &format!("duplicates_{}", decision.as_str()),
+        "duplicate_cluster",
+        &cluster.key,
+        &DuplicatesDecided { tx_uuids: cluster.tx_uuids() },
+        actor,
+    )?
+    .with_ledger(cluster.ledger_id());
+    insert_event(conn, &event)
+}
+
+/// Keep one transaction of a cluster and void the others as its duplicates
+pub fn merge_cluster(conn: &Connection, cluster: &DuplicateCluster, keep_uuid: &str, actor: &str) -> Result<Vec<Transaction>> {
+    if !cluster.transactions.iter().any(|tx| tx.id == keep_uuid) {
+        return Err(anyhow!("Transaction {} is not in this cluster", keep_uuid));
+    }
+
+    let mut voided = Vec::new();
+    for tx in cluster.transactions.iter().filter(|tx| tx.id != keep_uuid) {
+        voided.push(void_transaction(conn, &tx.id, &format!("duplicate of {}", keep_uuid), actor)?);
+    }
+    record_decision(conn, cluster, DuplicateDecision::Merged, actor)?;
+    Ok(voided)
+}
+
+/// Decision currently in force for a cluster (expired deferrals don't count)
+pub fn get_decision(conn: &Connection, cluster_key: &str, now: DateTime<Utc>) -> Result<Option<DuplicateDecision>> {
+    let row: Option<(String, Option<String>)> = conn
+        .query_row(
+            "SELECT decision, defer_until FROM duplicate_decisions WHERE cluster_key = ?1",
+            [cluster_key],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    let Some((decision, defer_until)) = row else {
+        return Ok(None);
+    };
+    let expired = defer_until
+        .and_then(|until| DateTime::parse_from_rfc3339(&until).ok())
+        .is_some_and(|until| until.with_timezone(&Utc) <= now);
+    if expired {
+        return Ok(None);
+    }
+    Ok(Some(DuplicateDecision::parse(&decision)?))
+}
+
+/// Clusters still waiting for a review decision
+pub fn pending_clusters(
+    conn: &Connection,
+    engine: &DeduplicationEngine,
+    transactions: &[Transaction],
+    now: DateTime<Utc>,
+) -> Result<Vec<DuplicateCluster>> {
+    retain_pending(conn, find_duplicate_clusters(engine, transactions), now)
+}
+
+/// Drop clusters that already have a decision in force
+pub fn retain_pending(
+    conn: &Connection,
+    clusters: Vec<DuplicateCluster>,
+    now: DateTime<Utc>,
+) -> Result<Vec<DuplicateCluster>> {
+    let mut pending = Vec::new();
+    for cluster in clusters {
+        if get_decision(conn, &cluster.key, now)?.is_none() {
+            pending.push(cluster);
+        }
+    }
+    Ok(pending)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::db::{get_active_transactions, insert_transactions_as, setup_database};
+
+    fn charge(date: &str, merchant: &str, amount: f64, line: &str) -> Transaction {
+        let mut tx = Transaction {
+            date: date.to_string(),
+            description: format!("{} PURCHASE", merchant),
+            amount_original: format!("{:.2}", amount),
+            amount_numeric: amount,
+            merchant: merchant.to_string(),
+            account_name: "Apple Card".to_string(),
+            bank: "AppleCard".to_string(),
+            source_file: "applecard.csv".to_string(),
+            line_number: line.to_string(),
+            ..test_support::sample_transaction()
+        };
+        tx.init_temporal_fields();
+        tx
+    }
+
+    fn sample() -> Vec<Transaction> {
+        vec![
+            charge("01/10/2025", "Starbucks", -5.75, "1"),
+            charge("01/11/2025", "Starbucks", -5.75, "2"),
+            charge("01/11/2025", "Starbucks", -5.80, "3"),
+            charge("01/20/2025", "Chipotle", -12.40, "4"),
+        ]
+    }
+
+    #[test]
+    fn test_pairs_group_into_clusters() {
+        let clusters = find_duplicate_clusters(&DeduplicationEngine::new(), &sample());
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].transactions.len(), 3);
+        assert_eq!(clusters[0].transactions[0].line_number, "1");
+
+        // Key doesn't depend on member order
+        let mut uuids = clusters[0].tx_uuids();
+        uuids.reverse();
+        assert_eq!(cluster_key(&uuids), clusters[0].key);
+    }
+
+    #[test]
+    fn test_decisions_hide_clusters() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let engine = DeduplicationEngine::new();
+        let transactions = sample();
+        let now = Utc::now();
+
+        let cluster = pending_clusters(&conn, &engine, &transactions, now).unwrap().remove(0);
+        record_decision(&conn, &cluster, DuplicateDecision::Dismissed, "ana").unwrap();
+        assert!(pending_clusters(&conn, &engine, &transactions, now).unwrap().is_empty());
+
+        // Deferred clusters come back once the deferral expires
+        record_decision(&conn, &cluster, DuplicateDecision::Deferred, "ana").unwrap();
+        assert!(pending_clusters(&conn, &engine, &transactions, now).unwrap().is_empty());
+        let later = now + Duration::days(DEFER_DAYS + 1);
+        assert_eq!(pending_clusters(&conn, &engine, &transactions, later).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_merge_keeps_one_and_voids_the_rest() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        insert_transactions_as(&conn, &sample(), "test").unwrap();
+        let engine = DeduplicationEngine::new();
+
+        let stored = get_active_transactions(&conn).unwrap();
+        let cluster = pending_clusters(&conn, &engine, &stored, Utc::now()).unwrap().remove(0);
+        let keep = cluster.transactions[0].id.clone();
+
+        assert!(merge_cluster(&conn, &cluster, "not-a-member", "ana").is_err());
+        let voided = merge_cluster(&conn, &cluster, &keep, "ana").unwrap();
+        assert_eq!(voided.len(), 2);
+        assert!(voided.iter().all(|tx| tx.void_reason() == Some(&*format!("duplicate of {}", keep))));
+
+        let remaining = get_active_transactions(&conn).unwrap();
+        assert!(pending_clusters(&conn, &engine, &remaining, Utc::now()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,492 @@
+// 🔎 Merchant Enrichment - Website, type and logo for known merchants
+//
+// Problem solved:
+// - A Merchant was just a name and aliases; the UI had nothing to show next to it
+// - Looking merchants up on a web API for every import would be slow and get us throttled
+//
+// Enrichers are pluggable: the local table ships with the binary, and a web API
+// lookup is available behind the `enrichment-web` feature. Results (including
+// "not found") are cached in SQLite, and each provider has its own rate limit,
+// so a re-run only touches the network for merchants it has never seen.
+
+use crate::entities::{Merchant, MerchantType};
+use crate::mcc::lookup_mcc;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::time::Instant;
+
+// ============================================================================
+// TYPES
+// ============================================================================
+
+/// What a provider knows about a merchant (every field optional)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Enrichment {
+    pub website: Option<String>,
+
+    /// MCC-style business type
+    pub merchant_type: Option<MerchantType>,
+
+    pub logo_url: Option<String>,
+}
+
+impl Enrichment {
+    pub fn is_empty(&self) -> bool {
+        self.website.is_none() && self.merchant_type.is_none() && self.logo_url.is_none()
+    }
+
+    /// Fill fields still missing here from `other`
+    fn fill_from(&mut self, other: &Enrichment) {
+        if self.website.is_none() {
+            self.website = other.website.clone();
+        }
+        if self.merchant_type.is_none() {
+            self.merchant_type = other.merchant_type.clone();
+        }
+        if self.logo_url.is_none() {
+            self.logo_url = other.logo_url.clone();
+        }
+    }
+}
+
+/// A source of merchant details
+pub trait MerchantEnricher {
+    /// Short provider name, used as the cache key and in metadata
+    fn name(&self) -> &str;
+
+    /// Minimum time between two lookups (None = unlimited)
+    fn min_interval(&self) -> Option<std::time::Duration> {
+        None
+    }
+
+    /// Look a merchant up; Ok(None) when the provider doesn't know it
+    fn lookup(&self, merchant: &Merchant) -> Result<Option<Enrichment>>;
+}
+
+// ============================================================================
+// LOCAL RULES
+// ============================================================================
+
+/// (merchant name, website, type)
+const KNOWN_MERCHANTS: &[(&str, &str, MerchantType)] = &[
+    ("Starbucks", "starbucks.com", MerchantType::Restaurant),
+    ("Amazon", "amazon.com", MerchantType::Retail),
+    ("Uber", "uber.com", MerchantType::Transportation),
+    ("Uber Eats", "ubereats.com", MerchantType::Restaurant),
+    ("Netflix", "netflix.com", MerchantType::Entertainment),
+    ("Spotify", "spotify.com", MerchantType::Entertainment),
+    ("Apple", "apple.com", MerchantType::OnlineService),
+    ("Stripe Fees", "stripe.com", MerchantType::Financial),
+    ("Stripe", "stripe.com", MerchantType::Financial),
+    ("Wise", "wise.com", MerchantType::Financial),
+    ("Whole Foods", "wholefoodsmarket.com", MerchantType::Retail),
+    ("Trader Joe's", "traderjoes.com", MerchantType::Retail),
+    ("Shell", "shell.com", MerchantType::Transportation),
+    ("CVS", "cvs.com", MerchantType::Healthcare),
+    ("Home Depot", "homedepot.com", MerchantType::Retail),
+];
+
+/// Offline lookups from a bundled table (plus any entries added at runtime)
+pub struct LocalRulesEnricher {
+    entries: Vec<(String, String, MerchantType)>,
+}
+
+impl LocalRulesEnricher {
+    pub fn new() -> Self {
+        LocalRulesEnricher {
+            entries: KNOWN_MERCHANTS
+                .iter()
+                .map(|(name, site, kind)| (name.to_string(), site.to_string(), kind.clone()))
+                .collect(),
+        }
+    }
+
+    /// Add or replace a merchant → website/type entry
+    pub fn with_entry(mut self, name: &str, website: &str, merchant_type: MerchantType) -> Self {
+        self.entries.retain(|(existing, _, _)| !existing.eq_ignore_ascii_case(name));
+        self.entries.push((name.to_string(), website.to_string(), merchant_type));
+        self
+    }
+}
+
+impl Default for LocalRulesEnricher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MerchantEnricher for LocalRulesEnricher {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn lookup(&self, merchant: &Merchant) -> Result<Option<Enrichment>> {
+        let found = merchant.all_names().iter().find_map(|name| {
+            self.entries
+                .iter()
+                .find(|(known, _, _)| known.eq_ignore_ascii_case(name.trim()))
+        });
+
+        if let Some((_, site, kind)) = found {
+            return Ok(Some(Enrichment {
+                website: Some(format!("https://{}", site)),
+                merchant_type: Some(kind.clone()),
+                logo_url: Some(format!("https://{}/favicon.ico", site)),
+            }));
+        }
+
+        // Unknown name, but a known MCC still tells us the kind of business
+        Ok(merchant.mcc.and_then(lookup_mcc).map(|entry| Enrichment {
+            merchant_type: Some(entry.merchant_type.clone()),
+            ..Enrichment::default()
+        }))
+    }
+}
+
+// ============================================================================
+// WEB API (feature = "enrichment-web")
+// ============================================================================
+
+/// Looks merchants up on an HTTP API: GET {endpoint}?name=<merchant>
+///
+/// The API answers 200 with `{"website", "merchant_type", "logo_url"}` (any
+/// subset) or 404 when it doesn't know the merchant.
+#[cfg(feature = "enrichment-web")]
+pub struct WebApiEnricher {
+    endpoint: String,
+    api_key: Option<String>,
+    min_interval: std::time::Duration,
+}
+
+#[cfg(feature = "enrichment-web")]
+impl WebApiEnricher {
+    pub fn new(endpoint: &str, api_key: Option<String>) -> Self {
+        WebApiEnricher {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            api_key,
+            min_interval: std::time::Duration::from_millis(500),
+        }
+    }
+
+    pub fn with_min_interval(mut self, min_interval: std::time::Duration) -> Self {
+        self.min_interval = min_interval;
+        self
+    }
+}
+
+#[cfg(feature = "enrichment-web")]
+impl MerchantEnricher for WebApiEnricher {
+    fn name(&self) -> &str {
+        "web"
+    }
+
+    fn min_interval(&self) -> Option<std::time::Duration> {
+        Some(self.min_interval)
+    }
+
+    fn lookup(&self, merchant: &Merchant) -> Result<Option<Enrichment>> {
+        let url = format!(
+            "{}?name={}",
+            self.endpoint,
+            urlencoding::encode(&merchant.canonical_name)
+        );
+        let mut request = ureq::get(&url).timeout(std::time::Duration::from_secs(10));
+        if let Some(key) = &self.api_key {
+            request = request.set("Authorization", &format!("Bearer {}", key));
+        }
+
+        match request.call() {
+            Ok(response) => {
+                let enrichment: Enrichment = serde_json::from_str(&response.into_string()?)?;
+                Ok(Some(enrichment).filter(|e| !e.is_empty()))
+            }
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => Err(anyhow::anyhow!("Merchant lookup failed for {}: {}", merchant.canonical_name, e)),
+        }
+    }
+}
+
+// ============================================================================
+// RATE LIMITING
+// ============================================================================
+
+/// Enforces a minimum interval between calls by sleeping
+struct RateLimiter {
+    min_interval: Option<std::time::Duration>,
+    last_call: Cell<Option<Instant>>,
+}
+
+impl RateLimiter {
+    fn new(min_interval: Option<std::time::Duration>) -> Self {
+        RateLimiter { min_interval, last_call: Cell::new(None) }
+    }
+
+    fn wait(&self) {
+        if let (Some(interval), Some(last)) = (self.min_interval, self.last_call.get()) {
+            let elapsed = last.elapsed();
+            if elapsed < interval {
+                std::thread::sleep(interval - elapsed);
+            }
+        }
+        self.last_call.set(Some(Instant::now()));
+    }
+}
+
+// ============================================================================
+// PIPELINE (providers in priority order + cache)
+// ============================================================================
+
+/// Runs providers in order, caching every answer per (provider, merchant)
+pub struct EnrichmentPipeline {
+    providers: Vec<(Box<dyn MerchantEnricher>, RateLimiter)>,
+    cache_ttl: Duration,
+}
+
+impl EnrichmentPipeline {
+    /// Cached answers are reused for 30 days
+    pub fn new() -> Self {
+        EnrichmentPipeline { providers: Vec::new(), cache_ttl: Duration::days(30) }
+    }
+
+    /// Add a provider; earlier providers win when two know the same field
+    pub fn with_provider(mut self, provider: Box<dyn MerchantEnricher>) -> Self {
+        let limiter = RateLimiter::new(provider.min_interval());
+        self.providers.push((provider, limiter));
+        self
+    }
+
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// Collect what all providers know about a merchant
+    ///
+    /// A failing provider is skipped (and not cached) so one flaky API
+    /// doesn't stop a batch.
+    pub fn lookup(&self, conn: &Connection, merchant: &Merchant) -> Result<Option<(Enrichment, Vec<String>)>> {
+        let key = cache_key(merchant);
+        let mut combined = Enrichment::default();
+        let mut sources = Vec::new();
+
+        for (provider, limiter) in &self.providers {
+            let answer = match cached_lookup(conn, provider.name(), &key, self.cache_ttl)? {
+                Some(cached) => cached,
+                None => {
+                    limiter.wait();
+                    match provider.lookup(merchant) {
+                        Ok(answer) => {
+                            store_lookup(conn, provider.name(), &key, answer.as_ref())?;
+                            answer
+                        }
+                        Err(_) => continue,
+                    }
+                }
+            };
+
+            if let Some(answer) = answer {
+                combined.fill_from(&answer);
+                sources.push(provider.name().to_string());
+            }
+        }
+
+        Ok(Some((combined, sources)).filter(|(e, _)| !e.is_empty()))
+    }
+
+    /// Enrich a merchant in place: details go to `metadata.enrichment`
+    ///
+    /// Returns false when no provider knows the merchant.
+    pub fn enrich(&self, conn: &Connection, merchant: &mut Merchant) -> Result<bool> {
+        let Some((enrichment, sources)) = self.lookup(conn, merchant)? else {
+            return Ok(false);
+        };
+        apply_enrichment(merchant, &enrichment, &sources);
+        Ok(true)
+    }
+}
+
+impl Default for EnrichmentPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Write an enrichment into `merchant.metadata["enrichment"]`
+pub fn apply_enrichment(merchant: &mut Merchant, enrichment: &Enrichment, sources: &[String]) {
+    if !merchant.metadata.is_object() {
+        merchant.metadata = serde_json::json!({});
+    }
+    merchant.metadata["enrichment"] = serde_json::json!({
+        "website": enrichment.website,
+        "merchant_type": enrichment.merchant_type.as_ref().map(|t| t.as_str()),
+        "logo_url": enrichment.logo_url,
+        "sources": sources,
+        "enriched_at": Utc::now().to_rfc3339(),
+    });
+}
+
+// ============================================================================
+// CACHE
+// ============================================================================
+
+fn cache_key(merchant: &Merchant) -> String {
+    merchant.canonical_name.trim().to_lowercase()
+}
+
+/// Some(answer) on a fresh cache hit (answer itself may be "not found")
+fn cached_lookup(
+    conn: &Connection,
+    provider: &str,
+    key: &str,
+    ttl: Duration,
+) -> Result<Option<Option<Enrichment>>> {
+    let row: Option<(Option<String>, String)> = conn
+        .query_row(
+            "SELECT result, fetched_at FROM merchant_enrichment_cache WHERE provider = ?1 AND merchant_key = ?2",
+            params![provider, key],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    let Some((result, fetched_at)) = row else {
+        return Ok(None);
+    };
+    let fresh = DateTime::parse_from_rfc3339(&fetched_at)
+        .map(|t| Utc::now() - t.with_timezone(&Utc) < ttl)
+        .unwrap_or(false);
+    if !fresh {
+        return Ok(None);
+    }
+
+    Ok(Some(match result {
+        Some(json) => Some(serde_json::from_str(&json)?),
+        None => None,
+    }))
+}
+
+fn store_lookup(conn: &Connection, provider: &str, key: &str, answer: Option<&Enrichment>) -> Result<()> {
+    let result = answer.map(serde_json::to_string).transpose()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO merchant_enrichment_cache (provider, merchant_key, result, fetched_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![provider, key, result, Utc::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::setup_database;
+    use std::rc::Rc;
+
+    /// Counts lookups so tests can see the cache working
+    struct CountingEnricher {
+        calls: Rc<Cell<usize>>,
+        answer: Option<Enrichment>,
+        interval: Option<std::time::Duration>,
+    }
+
+    impl MerchantEnricher for CountingEnricher {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn min_interval(&self) -> Option<std::time::Duration> {
+            self.interval
+        }
+
+        fn lookup(&self, _merchant: &Merchant) -> Result<Option<Enrichment>> {
+            self.calls.set(self.calls.get() + 1);
+            Ok(self.answer.clone())
+        }
+    }
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_local_rules_fill_metadata() {
+        let conn = setup();
+        let pipeline = EnrichmentPipeline::new().with_provider(Box::new(LocalRulesEnricher::new()));
+
+        let mut netflix = Merchant::new("NETFLIX".to_string(), MerchantType::Other, None);
+        assert!(pipeline.enrich(&conn, &mut netflix).unwrap());
+        assert_eq!(netflix.metadata["enrichment"]["website"], "https://netflix.com");
+        assert_eq!(netflix.metadata["enrichment"]["merchant_type"], "Entertainment");
+        assert_eq!(netflix.metadata["enrichment"]["sources"][0], "local");
+
+        let mut unknown = Merchant::new("Corner Bodega".to_string(), MerchantType::Other, None);
+        assert!(!pipeline.enrich(&conn, &mut unknown).unwrap());
+        assert!(unknown.metadata.get("enrichment").is_none());
+
+        let mut by_mcc = Merchant::new("Blue Bottle".to_string(), MerchantType::Other, None).with_mcc(5812);
+        assert!(pipeline.enrich(&conn, &mut by_mcc).unwrap());
+        assert_eq!(by_mcc.metadata["enrichment"]["merchant_type"], "Restaurant");
+    }
+
+    #[test]
+    fn test_answers_are_cached_including_misses() {
+        let conn = setup();
+        let calls = Rc::new(Cell::new(0));
+        let pipeline = EnrichmentPipeline::new().with_provider(Box::new(CountingEnricher {
+            calls: calls.clone(),
+            answer: None,
+            interval: None,
+        }));
+
+        let mut merchant = Merchant::new("Corner Bodega".to_string(), MerchantType::Other, None);
+        pipeline.enrich(&conn, &mut merchant).unwrap();
+        pipeline.enrich(&conn, &mut merchant).unwrap();
+        assert_eq!(calls.get(), 1);
+
+        // Expired entries are looked up again
+        let expired = EnrichmentPipeline::new()
+            .with_cache_ttl(Duration::zero())
+            .with_provider(Box::new(CountingEnricher { calls: calls.clone(), answer: None, interval: None }));
+        expired.enrich(&conn, &mut merchant).unwrap();
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_providers_fill_gaps_in_order_and_rate_limit() {
+        let conn = setup();
+        let calls = Rc::new(Cell::new(0));
+        let logo_only = Enrichment {
+            website: Some("https://example.com".to_string()),
+            merchant_type: None,
+            logo_url: Some("https://cdn.example.com/starbucks.png".to_string()),
+        };
+        let pipeline = EnrichmentPipeline::new()
+            .with_provider(Box::new(LocalRulesEnricher::new()))
+            .with_provider(Box::new(CountingEnricher {
+                calls: calls.clone(),
+                answer: Some(logo_only),
+                interval: Some(std::time::Duration::from_millis(30)),
+            }));
+
+        let (enrichment, sources) = pipeline
+            .lookup(&conn, &Merchant::new("Starbucks".to_string(), MerchantType::Other, None))
+            .unwrap()
+            .unwrap();
+        assert_eq!(enrichment.website.as_deref(), Some("https://starbucks.com"));
+        assert_eq!(sources, vec!["local", "counting"]);
+
+        let started = Instant::now();
+        for name in ["Alpha Cafe", "Beta Cafe"] {
+            pipeline.lookup(&conn, &Merchant::new(name.to_string(), MerchantType::Other, None)).unwrap();
+        }
+        assert_eq!(calls.get(), 3);
+        assert!(started.elapsed() >= std::time::Duration::from_millis(30));
+    }
+}
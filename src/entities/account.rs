@@ -9,8 +9,11 @@
 // - Renaming doesn't break historical transactions
 // - Balance tracking with temporal history
 // - UUID provides stable foreign key for transactions
+// - Lifecycle (active, frozen, closed on a date): a closed account takes no
+//   new transactions after its close date and leaves the balance totals, but
+//   every version stays queryable
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, RwLock};
 
@@ -46,6 +49,63 @@ impl AccountType {
             AccountType::Other => "Other",
         }
     }
+    /// Parse a type name as typed on the command line (case-insensitive)
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "checking" => Some(AccountType::Checking),
+            "savings" => Some(AccountType::Savings),
+            "credit" => Some(AccountType::Credit),
+            "investment" => Some(AccountType::Investment),
+            "other" => Some(AccountType::Other),
+            _ => None,
+        }
+    }
+}
+
+// ============================================================================
+// ACCOUNT STATUS
+// ============================================================================
+
+/// Lifecycle of an account
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum AccountStatus {
+    /// Open and in use
+    #[default]
+    Active,
+
+    /// Temporarily blocked (lost card, dispute): still in the totals, but new
+    /// transactions are flagged
+    Frozen,
+
+    /// Closed: nothing may be booked after `closed_on`, and the account is
+    /// left out of balance totals
+    Closed { closed_on: NaiveDate },
+}
+
+impl AccountStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountStatus::Active => "active",
+            AccountStatus::Frozen => "frozen",
+            AccountStatus::Closed { .. } => "closed",
+        }
+    }
+
+    /// The state after asking for `next`: None when nothing changes
+    ///
+    /// Closing twice keeps the first close date; a closed account must be
+    /// reopened before it can be frozen.
+    pub fn transition(&self, next: &AccountStatus) -> Result<Option<AccountStatus>, String> {
+        match (self, next) {
+            (AccountStatus::Closed { .. }, AccountStatus::Closed { .. }) => Ok(None),
+            (AccountStatus::Closed { .. }, AccountStatus::Frozen) => {
+                Err("Account is closed; reopen it first".to_string())
+            }
+            (current, next) if current == next => Ok(None),
+            (_, next) => Ok(Some(next.clone())),
+        }
+    }
 }
 
 // ============================================================================
@@ -65,6 +125,10 @@ pub struct Account {
     /// Stable identity (UUID) - NEVER changes
     pub id: String,
 
+    /// Ledger this entity belongs to (see ledger.rs)
+    #[serde(default = "crate::ledger::default_ledger_id")]
+    pub ledger_id: String,
+
     // ========================================================================
     // VALUES (can change over time)
     // ========================================================================
@@ -90,6 +154,10 @@ pub struct Account {
     /// Current balance (updated with each transaction)
     pub current_balance: f64,
 
+    /// Active, frozen or closed (accounts stored before lifecycles are active)
+    #[serde(default)]
+    pub status: AccountStatus,
+
     // ========================================================================
     // VERSIONING (Badge 19 - temporal tracking)
     // ========================================================================
@@ -118,6 +186,7 @@ impl Account {
 
         Account {
             id: uuid::Uuid::new_v4().to_string(),
+            ledger_id: crate::ledger::default_ledger_id(),
             name,
             account_number,
             bank_id,
@@ -125,6 +194,7 @@ impl Account {
             currency,
             opening_balance,
             current_balance: opening_balance,
+            status: AccountStatus::Active,
             version: 1,
             system_time: now,
             valid_from: now,
@@ -153,6 +223,26 @@ impl Account {
         self.current_balance < 0.0
     }
 
+    /// Whether the account was closed (on any date)
+    pub fn is_closed(&self) -> bool {
+        matches!(self.status, AccountStatus::Closed { .. })
+    }
+
+    /// Whether a transaction dated `date` may be booked on this account
+    /// (frozen accounts still accept them; they are only flagged)
+    pub fn accepts_transaction_on(&self, date: NaiveDate) -> bool {
+        match self.status {
+            AccountStatus::Closed { closed_on } => date <= closed_on,
+            AccountStatus::Active | AccountStatus::Frozen => true,
+        }
+    }
+
+    /// Place this account in a ledger (builder)
+    pub fn in_ledger(mut self, ledger_id: &str) -> Self {
+        self.ledger_id = ledger_id.to_string();
+        self
+    }
+
     /// Check if this version is current
     pub fn is_current(&self) -> bool {
         self.valid_until.is_none()
@@ -204,6 +294,13 @@ impl AccountRegistry {
         }
     }
 
+    /// Rebuild a registry from stored versions (see entities/store.rs)
+    pub fn from_versions(versions: Vec<Account>) -> Self {
+        AccountRegistry {
+            versions: Arc::new(RwLock::new(versions)),
+        }
+    }
+
     /// Register a new account version (append-only, never overwrites)
     pub fn register(&mut self, account: Account) {
         let mut versions = self.versions.write().unwrap();
@@ -270,6 +367,31 @@ impl AccountRegistry {
         Ok(())
     }
 
+    /// Change the lifecycle state of an account (creates new version, unless
+    /// nothing changes; see `AccountStatus::transition`)
+    pub fn set_status(&mut self, id: &str, status: AccountStatus) -> Result<(), String> {
+        let current = self
+            .get_current_version(id)
+            .ok_or_else(|| format!("Account not found: {}", id))?;
+        match current.status.transition(&status)? {
+            Some(status) => self.update_account(id, |next| next.status = status.clone()),
+            None => Ok(()),
+        }
+    }
+
+    /// Close an account as of `closed_on` (creates new version)
+    pub fn close_account(&mut self, id: &str, closed_on: NaiveDate) -> Result<(), String> {
+        self.set_status(id, AccountStatus::Closed { closed_on })
+    }
+
+    /// Accounts that are not closed (current versions only)
+    pub fn open_accounts(&self) -> Vec<Account> {
+        self.all_accounts()
+            .into_iter()
+            .filter(|acc| !acc.is_closed())
+            .collect()
+    }
+
     /// Find account by name (exact match, case-insensitive) - returns current version
     pub fn find_by_name(&self, name: &str) -> Option<Account> {
         let versions = self.versions.read().unwrap();
@@ -296,6 +418,11 @@ impl AccountRegistry {
             .cloned()
     }
 
+    /// Every version of every account, expired ones included
+    pub fn all_versions(&self) -> Vec<Account> {
+        self.versions.read().unwrap().clone()
+    }
+
     /// Get all accounts (current versions only)
     pub fn all_accounts(&self) -> Vec<Account> {
         let versions = self.versions.read().unwrap();
@@ -341,14 +468,14 @@ impl AccountRegistry {
         self.find_by_name(name).map(|acc| acc.id)
     }
 
-    /// Calculate total balance across all accounts (current versions only)
+    /// Calculate total balance across open accounts (current versions only)
     pub fn total_balance(&self) -> f64 {
-        self.all_accounts().iter().map(|acc| acc.current_balance).sum()
+        self.open_accounts().iter().map(|acc| acc.current_balance).sum()
     }
 
-    /// Calculate total balance by currency (current versions only)
+    /// Calculate total balance by currency (open accounts, current versions only)
     pub fn total_balance_by_currency(&self, currency: &str) -> f64 {
-        self.all_accounts()
+        self.open_accounts()
             .iter()
             .filter(|acc| acc.currency == currency)
             .map(|acc| acc.current_balance)
@@ -1167,4 +1294,49 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("Account not found"));
     }
+
+    #[test]
+    fn test_closed_account_leaves_totals_but_stays_queryable() {
+        let mut registry = AccountRegistry::new();
+        let bank_id = create_test_bank_id();
+        let checking = Account::new(
+            "Old Checking".to_string(),
+            "*1234".to_string(),
+            bank_id.clone(),
+            AccountType::Checking,
+            "USD".to_string(),
+            100.0,
+        );
+        let savings = Account::new(
+            "Savings".to_string(),
+            "*5678".to_string(),
+            bank_id,
+            AccountType::Savings,
+            "USD".to_string(),
+            400.0,
+        );
+        let checking_id = checking.id.clone();
+        registry.register(checking);
+        registry.register(savings);
+        assert_eq!(registry.total_balance(), 500.0);
+
+        let closed_on = NaiveDate::from_ymd_opt(2025, 3, 31).unwrap();
+        registry.close_account(&checking_id, closed_on).unwrap();
+        assert_eq!(registry.total_balance(), 400.0);
+        assert_eq!(registry.total_balance_by_currency("USD"), 400.0);
+        assert_eq!(registry.open_accounts().len(), 1);
+
+        let closed = registry.find_by_name("Old Checking").unwrap();
+        assert!(closed.is_closed());
+        assert!(closed.accepts_transaction_on(closed_on));
+        assert!(!closed.accepts_transaction_on(closed_on.succ_opt().unwrap()));
+        assert_eq!(registry.get_all_versions(&checking_id).len(), 2);
+
+        // Closing again keeps the first date; freezing needs a reopen
+        registry.close_account(&checking_id, NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()).unwrap();
+        assert_eq!(registry.find_by_id(&checking_id).unwrap().status, AccountStatus::Closed { closed_on });
+        assert!(registry.set_status(&checking_id, AccountStatus::Frozen).is_err());
+        registry.set_status(&checking_id, AccountStatus::Active).unwrap();
+        assert_eq!(registry.total_balance(), 500.0);
+    }
 }
@@ -66,6 +66,10 @@ pub struct Bank {
     /// Stable identity (UUID) - NEVER changes
     pub id: String,
 
+    /// Ledger this entity belongs to (see ledger.rs)
+    #[serde(default = "crate::ledger::default_ledger_id")]
+    pub ledger_id: String,
+
     // ========================================================================
     // VALUES (can change over time)
     // ========================================================================
@@ -107,6 +111,7 @@ impl Bank {
 
         Bank {
             id: uuid::Uuid::new_v4().to_string(),
+            ledger_id: crate::ledger::default_ledger_id(),
             canonical_name,
             aliases: Vec::new(),
             country,
@@ -151,6 +156,12 @@ impl Bank {
         names
     }
 
+    /// Place this bank in a ledger (builder)
+    pub fn in_ledger(mut self, ledger_id: &str) -> Self {
+        self.ledger_id = ledger_id.to_string();
+        self
+    }
+
     /// Check if this version is current
     pub fn is_current(&self) -> bool {
         self.valid_until.is_none()
@@ -249,6 +260,13 @@ impl BankRegistry {
         self.register(scotiabank);
     }
 
+    /// Rebuild a registry from stored versions (see entities/store.rs)
+    pub fn from_versions(versions: Vec<Bank>) -> Self {
+        BankRegistry {
+            versions: Arc::new(RwLock::new(versions)),
+        }
+    }
+
     /// Register a new bank version (append-only, never overwrites)
     pub fn register(&mut self, bank: Bank) {
         let mut versions = self.versions.write().unwrap();
@@ -343,6 +361,11 @@ impl BankRegistry {
         self.get_current_version(id)
     }
 
+    /// Every version of every bank, expired ones included
+    pub fn all_versions(&self) -> Vec<Bank> {
+        self.versions.read().unwrap().clone()
+    }
+
     /// Get all banks (current versions only)
     pub fn all_banks(&self) -> Vec<Bank> {
         let versions = self.versions.read().unwrap();
@@ -56,6 +56,10 @@ pub struct Category {
     /// Stable identity (UUID) - NEVER changes
     pub id: String,
 
+    /// Ledger this entity belongs to (see ledger.rs)
+    #[serde(default = "crate::ledger::default_ledger_id")]
+    pub ledger_id: String,
+
     // ========================================================================
     // VALUES (can change over time)
     // ========================================================================
@@ -101,6 +105,7 @@ impl Category {
 
         Category {
             id: uuid::Uuid::new_v4().to_string(),
+            ledger_id: crate::ledger::default_ledger_id(),
             name,
             parent_id,
             category_type,
@@ -138,6 +143,12 @@ impl Category {
         self.parent_id.is_some()
     }
 
+    /// Place this category in a ledger (builder)
+    pub fn in_ledger(mut self, ledger_id: &str) -> Self {
+        self.ledger_id = ledger_id.to_string();
+        self
+    }
+
     /// Check if this version is current
     pub fn is_current(&self) -> bool {
         self.valid_until.is_none()
@@ -384,6 +395,13 @@ impl CategoryRegistry {
         self.register(account_transfer);
     }
 
+    /// Rebuild a registry from stored versions (see entities/store.rs)
+    pub fn from_versions(versions: Vec<Category>) -> Self {
+        CategoryRegistry {
+            versions: Arc::new(RwLock::new(versions)),
+        }
+    }
+
     /// Register a new category version (append-only, never overwrites)
     pub fn register(&mut self, category: Category) {
         let mut versions = self.versions.write().unwrap();
@@ -466,6 +484,11 @@ impl CategoryRegistry {
         self.get_current_version(id)
     }
 
+    /// Every version of every category, expired ones included
+    pub fn all_versions(&self) -> Vec<Category> {
+        self.versions.read().unwrap().clone()
+    }
+
     /// Get all categories (current versions only)
     pub fn all_categories(&self) -> Vec<Category> {
         let versions = self.versions.read().unwrap();
@@ -585,6 +608,19 @@ impl CategoryRegistry {
 
         depth
     }
+
+    /// Every category under its parent, depth first, with its depth
+    ///
+    /// Example: [(0, "Food & Dining"), (1, "Restaurants"), (2, "Fast Food"), ...]
+    pub fn tree(&self) -> Vec<(usize, Category)> {
+        let mut tree = Vec::new();
+        for root in self.root_categories() {
+            let descendants = self.get_descendants(&root.id);
+            tree.push((0, root));
+            tree.extend(descendants.into_iter().map(|category| (self.get_depth(&category), category)));
+        }
+        tree
+    }
 }
 
 impl Default for CategoryRegistry {
@@ -848,6 +884,22 @@ mod tests {
         assert_eq!(registry.get_depth(&fast_food), 2); // Level 3
     }
 
+    #[test]
+    fn test_category_registry_tree() {
+        let registry = CategoryRegistry::with_defaults();
+        let tree = registry.tree();
+        assert_eq!(tree.len(), registry.count());
+
+        // Children follow their parent, one level deeper
+        let food = tree.iter().position(|(_, c)| c.name == "Food & Dining").unwrap();
+        assert_eq!(tree[food].0, 0);
+        assert_eq!(tree[food + 1].0, 1);
+        let fast_food = tree.iter().position(|(_, c)| c.name == "Fast Food").unwrap();
+        let restaurants = tree.iter().position(|(_, c)| c.name == "Restaurants").unwrap();
+        assert!(food < restaurants && restaurants < fast_food);
+        assert_eq!(tree[fast_food].0, 2);
+    }
+
     #[test]
     fn test_category_versioning() {
         let category = Category::new(
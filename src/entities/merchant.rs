@@ -11,7 +11,7 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, OnceLock, RwLock};
 
 // ============================================================================
 // MERCHANT TYPE
@@ -83,6 +83,10 @@ pub struct Merchant {
     /// Stable identity (UUID) - NEVER changes
     pub id: String,
 
+    /// Ledger this entity belongs to (see ledger.rs)
+    #[serde(default = "crate::ledger::default_ledger_id")]
+    pub ledger_id: String,
+
     // ========================================================================
     // VALUES (can change over time)
     // ========================================================================
@@ -99,6 +103,10 @@ pub struct Merchant {
     /// Suggested category (can be None if unknown)
     pub suggested_category: Option<String>,
 
+    /// Card-network merchant category code, when a feed provides one (see mcc.rs)
+    #[serde(default)]
+    pub mcc: Option<u16>,
+
     // ========================================================================
     // VERSIONING (Badge 19 - temporal tracking)
     // ========================================================================
@@ -124,10 +132,12 @@ impl Merchant {
 
         Merchant {
             id: uuid::Uuid::new_v4().to_string(),
+            ledger_id: crate::ledger::default_ledger_id(),
             canonical_name,
             aliases: Vec::new(),
             merchant_type,
             suggested_category,
+            mcc: None,
             version: 1,
             system_time: now,
             valid_from: now,
@@ -186,6 +196,23 @@ impl Merchant {
         names
     }
 
+    /// Set the merchant category code (builder)
+    pub fn with_mcc(mut self, mcc: u16) -> Self {
+        self.mcc = Some(mcc);
+        self
+    }
+
+    /// Category implied by the MCC, if the code is in the bundled table
+    pub fn mcc_category(&self) -> Option<&'static str> {
+        self.mcc.and_then(crate::mcc::lookup_mcc).map(|entry| entry.category)
+    }
+
+    /// Place this merchant in a ledger (builder)
+    pub fn in_ledger(mut self, ledger_id: &str) -> Self {
+        self.ledger_id = ledger_id.to_string();
+        self
+    }
+
     /// Check if this version is current
     pub fn is_current(&self) -> bool {
         self.valid_until.is_none()
@@ -288,6 +315,13 @@ impl MerchantRegistry {
         self.register(stripe_fees);
     }
 
+    /// Rebuild a registry from stored versions (see entities/store.rs)
+    pub fn from_versions(versions: Vec<Merchant>) -> Self {
+        MerchantRegistry {
+            versions: Arc::new(RwLock::new(versions)),
+        }
+    }
+
     /// Register a new merchant version (append-only, never overwrites)
     pub fn register(&mut self, merchant: Merchant) {
         let mut versions = self.versions.write().unwrap();
@@ -369,6 +403,11 @@ impl MerchantRegistry {
         self.get_current_version(id)
     }
 
+    /// Every version of every merchant, expired ones included
+    pub fn all_versions(&self) -> Vec<Merchant> {
+        self.versions.read().unwrap().clone()
+    }
+
     /// Get all merchants (current versions only)
     pub fn all_merchants(&self) -> Vec<Merchant> {
         let versions = self.versions.read().unwrap();
@@ -421,6 +460,15 @@ impl Default for MerchantRegistry {
     }
 }
 
+/// Process-wide registry with the default merchants
+///
+/// Merchant UUIDs are minted at registration, so an id handed out (e.g. in a
+/// server response) only resolves against the same registry instance.
+pub fn shared_registry() -> &'static MerchantRegistry {
+    static REGISTRY: OnceLock<MerchantRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(MerchantRegistry::with_defaults)
+}
+
 // ============================================================================
 // HELPER FUNCTIONS
 // ============================================================================
@@ -431,7 +479,7 @@ impl Default for MerchantRegistry {
 /// - Remove location codes (*123, #456) but keep words like *TRIP
 /// - Remove common suffixes (Inc, Corp, LLC)
 /// - Trim whitespace
-fn normalize_merchant_string(s: &str) -> String {
+pub(crate) fn normalize_merchant_string(s: &str) -> String {
     let mut normalized = s.to_lowercase();
 
     // Process words: remove pure location codes, clean words with prefixes
@@ -482,7 +530,7 @@ fn levenshtein_match(s1: &str, s2: &str, threshold: usize) -> bool {
 ///
 /// Levenshtein distance = minimum number of single-character edits
 /// (insertions, deletions, substitutions) to change one string into another
-fn levenshtein_distance(s1: &str, s2: &str) -> usize {
+pub(crate) fn levenshtein_distance(s1: &str, s2: &str) -> usize {
     let len1 = s1.len();
     let len2 = s2.len();
 
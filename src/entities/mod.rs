@@ -5,13 +5,19 @@
 // - Stable identity (UUID) that NEVER changes
 // - Timeline of immutable values (with temporal tracking from Badge 19)
 // - Registry for normalization and lookups
+// - Persistence of every version (store.rs)
 
 pub mod bank;
 pub mod merchant;
 pub mod category;
 pub mod account;
+pub mod store;
 
 pub use bank::{Bank, BankType, BankRegistry};
-pub use merchant::{Merchant, MerchantType, MerchantRegistry};
+pub use merchant::{Merchant, MerchantType, MerchantRegistry, shared_registry};
 pub use category::{Category, CategoryType, CategoryRegistry};
-pub use account::{Account, AccountType, AccountRegistry};
+pub use account::{Account, AccountStatus, AccountType, AccountRegistry};
+pub use store::{
+    load_accounts, load_banks, load_categories, load_merchants, load_versions, save_registries, save_versions,
+    VersionedEntity,
+};
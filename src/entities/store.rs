@@ -0,0 +1,262 @@
+// 💾 Entity Store - Registry versions kept in the database
+//
+// Problem solved:
+// - Merchant, bank, category and account registries lived only in memory:
+//   every start rebuilt them from defaults with freshly minted UUIDs, so ids
+//   handed out before a restart no longer resolved, and every version history
+//   was lost
+//
+// Each entity version is one row of `entity_versions` (see db.rs), keyed by
+// (entity_type, id, version) and holding the entity as JSON. Saving upserts
+// every version a registry holds: new versions are appended, and a version
+// expired since the last save gets its `valid_until`. Rows are never deleted.
+// Loading returns versions in the order they were first saved, so registry
+// lookups (first match wins) behave as before the restart. A registry that
+// was never saved starts from its defaults.
+
+use crate::entities::{
+    Account, AccountRegistry, Bank, BankRegistry, Category, CategoryRegistry, Merchant, MerchantRegistry,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// ============================================================================
+// VERSIONED ENTITIES
+// ============================================================================
+
+/// An entity stored as versions under a compound key (id, version)
+pub trait VersionedEntity: Serialize + DeserializeOwned {
+    /// Value of the `entity_type` column
+    const ENTITY_TYPE: &'static str;
+
+    fn id(&self) -> &str;
+    fn version(&self) -> i64;
+    fn ledger_id(&self) -> &str;
+    fn valid_from(&self) -> DateTime<Utc>;
+    fn valid_until(&self) -> Option<DateTime<Utc>>;
+}
+
+impl VersionedEntity for Merchant {
+    const ENTITY_TYPE: &'static str = "merchant";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn version(&self) -> i64 {
+        self.version
+    }
+    fn ledger_id(&self) -> &str {
+        &self.ledger_id
+    }
+    fn valid_from(&self) -> DateTime<Utc> {
+        self.valid_from
+    }
+    fn valid_until(&self) -> Option<DateTime<Utc>> {
+        self.valid_until
+    }
+}
+
+impl VersionedEntity for Bank {
+    const ENTITY_TYPE: &'static str = "bank";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn version(&self) -> i64 {
+        self.version
+    }
+    fn ledger_id(&self) -> &str {
+        &self.ledger_id
+    }
+    fn valid_from(&self) -> DateTime<Utc> {
+        self.valid_from
+    }
+    fn valid_until(&self) -> Option<DateTime<Utc>> {
+        self.valid_until
+    }
+}
+
+impl VersionedEntity for Category {
+    const ENTITY_TYPE: &'static str = "category";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn version(&self) -> i64 {
+        self.version
+    }
+    fn ledger_id(&self) -> &str {
+        &self.ledger_id
+    }
+    fn valid_from(&self) -> DateTime<Utc> {
+        self.valid_from
+    }
+    fn valid_until(&self) -> Option<DateTime<Utc>> {
+        self.valid_until
+    }
+}
+
+impl VersionedEntity for Account {
+    const ENTITY_TYPE: &'static str = "account";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn version(&self) -> i64 {
+        self.version
+    }
+    fn ledger_id(&self) -> &str {
+        &self.ledger_id
+    }
+    fn valid_from(&self) -> DateTime<Utc> {
+        self.valid_from
+    }
+    fn valid_until(&self) -> Option<DateTime<Utc>> {
+        self.valid_until
+    }
+}
+
+// ============================================================================
+// SAVE / LOAD
+// ============================================================================
+
+/// Upsert versions of one entity type; returns how many were written
+pub fn save_versions<T: VersionedEntity>(conn: &Connection, versions: &[T]) -> Result<usize> {
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO entity_versions (entity_type, id, version, ledger_id, data, valid_from, valid_until)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT (entity_type, id, version) DO UPDATE SET
+            ledger_id = excluded.ledger_id,
+            data = excluded.data,
+            valid_from = excluded.valid_from,
+            valid_until = excluded.valid_until",
+    )?;
+    for entity in versions {
+        stmt.execute(params![
+            T::ENTITY_TYPE,
+            entity.id(),
+            entity.version(),
+            entity.ledger_id(),
+            serde_json::to_string(entity)?,
+            entity.valid_from().to_rfc3339(),
+            entity.valid_until().map(|until| until.to_rfc3339()),
+        ])?;
+    }
+    Ok(versions.len())
+}
+
+/// Every stored version of one entity type, in the order first saved
+pub fn load_versions<T: VersionedEntity>(conn: &Connection) -> Result<Vec<T>> {
+    let mut stmt = conn.prepare("SELECT data FROM entity_versions WHERE entity_type = ?1 ORDER BY rowid")?;
+    let rows = stmt.query_map([T::ENTITY_TYPE], |row| row.get::<_, String>(0))?;
+    let mut versions = Vec::new();
+    for data in rows {
+        versions.push(serde_json::from_str(&data?)?);
+    }
+    Ok(versions)
+}
+
+/// Save every version of all four registries in one database transaction
+pub fn save_registries(
+    conn: &Connection,
+    merchants: &MerchantRegistry,
+    categories: &CategoryRegistry,
+    banks: &BankRegistry,
+    accounts: &AccountRegistry,
+) -> Result<usize> {
+    let tx = conn.unchecked_transaction()?;
+    let saved = save_versions(&tx, &merchants.all_versions())?
+        + save_versions(&tx, &categories.all_versions())?
+        + save_versions(&tx, &banks.all_versions())?
+        + save_versions(&tx, &accounts.all_versions())?;
+    tx.commit()?;
+    Ok(saved)
+}
+
+/// Stored merchants, or the default merchants when none were saved yet
+pub fn load_merchants(conn: &Connection) -> Result<MerchantRegistry> {
+    hydrate(conn, MerchantRegistry::from_versions, MerchantRegistry::with_defaults)
+}
+
+/// Stored categories, or the default categories when none were saved yet
+pub fn load_categories(conn: &Connection) -> Result<CategoryRegistry> {
+    hydrate(conn, CategoryRegistry::from_versions, CategoryRegistry::with_defaults)
+}
+
+/// Stored banks, or the default banks when none were saved yet
+pub fn load_banks(conn: &Connection) -> Result<BankRegistry> {
+    hydrate(conn, BankRegistry::from_versions, BankRegistry::new)
+}
+
+/// Stored accounts (an empty registry when none were saved yet)
+pub fn load_accounts(conn: &Connection) -> Result<AccountRegistry> {
+    hydrate(conn, AccountRegistry::from_versions, AccountRegistry::new)
+}
+
+fn hydrate<T: VersionedEntity, R>(
+    conn: &Connection,
+    from_versions: impl FnOnce(Vec<T>) -> R,
+    defaults: impl FnOnce() -> R,
+) -> Result<R> {
+    let versions = load_versions::<T>(conn)?;
+    Ok(if versions.is_empty() { defaults() } else { from_versions(versions) })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::setup_database;
+    use crate::entities::MerchantType;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_registries_survive_a_round_trip_with_history() {
+        let conn = test_db();
+        let mut merchants = MerchantRegistry::with_defaults();
+        let starbucks = merchants.find_by_string("STARBUCKS STORE #1234").unwrap();
+        let banks = BankRegistry::new();
+        save_registries(&conn, &merchants, &CategoryRegistry::with_defaults(), &banks, &AccountRegistry::new())
+            .unwrap();
+
+        // Expiring v1 after the first save updates its row and appends v2
+        merchants
+            .update_merchant(&starbucks.id, |m| m.merchant_type = MerchantType::Restaurant)
+            .unwrap();
+        save_versions(&conn, &merchants.all_versions()).unwrap();
+
+        let loaded = load_merchants(&conn).unwrap();
+        assert_eq!(loaded.count(), merchants.count());
+        let history = loaded.get_all_versions(&starbucks.id);
+        assert_eq!(history.len(), 2);
+        assert!(history.iter().any(|m| m.version == 1 && m.valid_until.is_some()));
+        let current = loaded.get_current_version(&starbucks.id).unwrap();
+        assert_eq!(current.version, 2);
+        assert_eq!(current.merchant_type, MerchantType::Restaurant);
+        assert_eq!(loaded.get_id("STARBUCKS STORE #99"), Some(starbucks.id));
+
+        // Stable ids across restarts
+        let bofa = banks.get_id("BofA").unwrap();
+        assert_eq!(load_banks(&conn).unwrap().get_id("BofA"), Some(bofa));
+    }
+
+    #[test]
+    fn test_nothing_saved_starts_from_defaults() {
+        let conn = test_db();
+        assert_eq!(load_merchants(&conn).unwrap().count(), MerchantRegistry::with_defaults().count());
+        assert_eq!(load_categories(&conn).unwrap().count(), CategoryRegistry::with_defaults().count());
+        assert!(load_accounts(&conn).unwrap().all_accounts().is_empty());
+        assert!(load_versions::<Bank>(&conn).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,547 @@
+// ✉️ Envelopes - Envelope budgeting on top of categories
+//
+// Problem solved:
+// - Totals per category said what was spent, not what was still left to
+//   spend this month, or what last month's leftovers were worth now
+// - Going over in one place (a big grocery run) had nowhere to come from
+//
+// An envelope is funded with a fixed amount every month and covers one or
+// more categories; spending in those categories draws its balance down. An
+// envelope that goes negative borrows from the ledger's buffer
+// (`envelope_buffer` in the ledger config) until the buffer is empty. At the
+// end of a month the carryover rule decides what happens to a leftover: kept
+// (Full), given back to the buffer (Reset), or kept up to a cap with the rest
+// given back (Capped). A deficit is always carried into the next month.
+//
+// Envelopes are versioned like rules: changing the funding appends a version,
+// and a month is budgeted with the versions valid at its end (an envelope
+// created later also budgets the months before it).
+
+use crate::db::{insert_event, Event, Transaction};
+use crate::event_schema::EnvelopeChanged;
+use crate::jobs::ledger_transactions;
+use crate::ledger::require_ledger;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// ============================================================================
+// ENVELOPES
+// ============================================================================
+
+/// What happens to money left in an envelope at the end of a month
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum CarryoverRule {
+    /// The whole leftover stays in the envelope
+    #[default]
+    Full,
+    /// The envelope starts every month empty; the leftover goes to the buffer
+    Reset,
+    /// Up to `max` stays; the rest goes to the buffer
+    Capped { max: f64 },
+}
+
+impl CarryoverRule {
+    /// "full", "reset" or "cap:<amount>"
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            None if s == "full" => Ok(CarryoverRule::Full),
+            None if s == "reset" => Ok(CarryoverRule::Reset),
+            Some(("cap", max)) => match max.parse::<f64>() {
+                Ok(max) if max >= 0.0 => Ok(CarryoverRule::Capped { max }),
+                _ => Err(anyhow!("Carryover cap must be an amount of zero or more, got '{}'", max)),
+            },
+            _ => Err(anyhow!("Unknown carryover rule '{}' (full, reset or cap:<amount>)", s)),
+        }
+    }
+
+    /// Part of a month's closing balance that stays in the envelope
+    fn kept(&self, closing: f64) -> f64 {
+        match self {
+            _ if closing <= 0.0 => closing,
+            CarryoverRule::Full => closing,
+            CarryoverRule::Reset => 0.0,
+            CarryoverRule::Capped { max } => closing.min(*max),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    pub id: String,
+    pub ledger_id: String,
+    pub name: String,
+
+    /// Added at the start of every month
+    pub monthly_funding: f64,
+
+    /// Transaction categories drawing on this envelope (case-insensitive)
+    pub categories: Vec<String>,
+
+    #[serde(default)]
+    pub carryover: CarryoverRule,
+}
+
+impl Envelope {
+    pub fn new(name: &str, monthly_funding: f64) -> Self {
+        Envelope {
+            id: uuid::Uuid::new_v4().to_string(),
+            ledger_id: crate::ledger::default_ledger_id(),
+            name: name.to_string(),
+            monthly_funding,
+            categories: Vec::new(),
+            carryover: CarryoverRule::Full,
+        }
+    }
+
+    /// Place this envelope in a ledger (builder)
+    pub fn in_ledger(mut self, ledger_id: &str) -> Self {
+        self.ledger_id = ledger_id.to_string();
+        self
+    }
+
+    /// Cover a category (builder)
+    pub fn with_category(mut self, category: &str) -> Self {
+        self.categories.push(category.to_string());
+        self
+    }
+
+    /// Change the end-of-month carryover (builder)
+    pub fn with_carryover(mut self, carryover: CarryoverRule) -> Self {
+        self.carryover = carryover;
+        self
+    }
+
+    pub fn covers(&self, category: &str) -> bool {
+        self.categories.iter().any(|c| c.trim().eq_ignore_ascii_case(category.trim()))
+    }
+}
+
+/// One immutable version of an envelope
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionedEnvelope {
+    pub envelope: Envelope,
+    pub version: i64,
+    pub system_time: DateTime<Utc>,
+    pub valid_from: DateTime<Utc>,
+    pub valid_until: Option<DateTime<Utc>>,
+    pub changed_by: String,
+    pub change_reason: Option<String>,
+}
+
+impl VersionedEnvelope {
+    pub fn is_current(&self) -> bool {
+        self.valid_until.is_none()
+    }
+
+    pub fn was_valid_at(&self, time: DateTime<Utc>) -> bool {
+        self.valid_from <= time && self.valid_until.is_none_or(|until| until > time)
+    }
+}
+
+// ============================================================================
+// STORAGE (append-only versions, like rules)
+// ============================================================================
+
+/// Save an envelope as a new version; identical to the current version →
+/// no-op. Returns the version that is current after the call.
+pub fn save_envelope(conn: &Connection, envelope: &Envelope, actor: &str, reason: Option<&str>) -> Result<i64> {
+    if envelope.name.trim().is_empty() || envelope.categories.is_empty() {
+        return Err(anyhow!("An envelope needs a name and at least one category"));
+    }
+    if envelope.monthly_funding < 0.0 {
+        return Err(anyhow!("Monthly funding can't be negative, got {:.2}", envelope.monthly_funding));
+    }
+    for other in current_envelopes(conn, &envelope.ledger_id)? {
+        if let Some(category) = envelope
+            .categories
+            .iter()
+            .find(|category| other.envelope.id != envelope.id && other.envelope.covers(category))
+        {
+            return Err(anyhow!("Category '{}' already draws on envelope '{}'", category, other.envelope.name));
+        }
+    }
+
+    let current = get_current_envelope(conn, &envelope.id)?;
+    if let Some(current) = &current {
+        if current.envelope == *envelope {
+            return Ok(current.version);
+        }
+    }
+    // A retired envelope saved again continues its version numbers
+    let next_version = envelope_history(conn, &envelope.id)?.last().map_or(1, |last| last.version + 1);
+
+    let now = Utc::now().to_rfc3339();
+    if current.is_some() {
+        conn.execute(
+            "UPDATE envelopes SET valid_until = ?1 WHERE envelope_id = ?2 AND valid_until IS NULL",
+            params![now, envelope.id],
+        )?;
+    }
+    conn.execute(
+        "INSERT INTO envelopes (
+            envelope_id, ledger_id, version, definition, changed_by, change_reason,
+            system_time, valid_from, valid_until
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, NULL)",
+        params![
+            envelope.id,
+            envelope.ledger_id,
+            next_version,
+            serde_json::to_string(envelope)?,
+            actor,
+            reason,
+            now,
+            now,
+        ],
+    )?;
+
+    let payload = EnvelopeChanged {
+        version: next_version,
+        name: envelope.name.clone(),
+        monthly_funding: envelope.monthly_funding,
+        reason: reason.map(str::to_string),
+    };
+    let event_type = if next_version == 1 { "envelope_created" } else { "envelope_updated" };
+    let event = Event::typed(event_type, "envelope", &envelope.id, &payload, actor)?
+        .with_ledger(&envelope.ledger_id);
+    insert_event(conn, &event)?;
+
+    Ok(next_version)
+}
+
+/// Stop budgeting with an envelope from now on (earlier months keep it)
+pub fn retire_envelope(conn: &Connection, envelope_id: &str, actor: &str, reason: Option<&str>) -> Result<()> {
+    let current = get_current_envelope(conn, envelope_id)?
+        .ok_or_else(|| anyhow!("Envelope not found or already retired: {}", envelope_id))?;
+    conn.execute(
+        "UPDATE envelopes SET valid_until = ?1 WHERE envelope_id = ?2 AND valid_until IS NULL",
+        params![Utc::now().to_rfc3339(), envelope_id],
+    )?;
+
+    let payload = EnvelopeChanged {
+        version: current.version,
+        name: current.envelope.name.clone(),
+        monthly_funding: current.envelope.monthly_funding,
+        reason: reason.map(str::to_string),
+    };
+    let event = Event::typed("envelope_retired", "envelope", envelope_id, &payload, actor)?
+        .with_ledger(&current.envelope.ledger_id);
+    insert_event(conn, &event)
+}
+
+const ENVELOPE_COLUMNS: &str = "definition, version, changed_by, change_reason, system_time, valid_from, valid_until";
+
+pub fn get_current_envelope(conn: &Connection, envelope_id: &str) -> Result<Option<VersionedEnvelope>> {
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM envelopes WHERE envelope_id = ?1 AND valid_until IS NULL",
+                ENVELOPE_COLUMNS
+            ),
+            [envelope_id],
+            row_to_envelope,
+        )
+        .optional()?)
+}
+
+/// Envelopes a ledger budgets with now, by name
+pub fn current_envelopes(conn: &Connection, ledger_id: &str) -> Result<Vec<VersionedEnvelope>> {
+    let mut envelopes: Vec<VersionedEnvelope> = ledger_envelope_versions(conn, ledger_id)?
+        .into_iter()
+        .filter(VersionedEnvelope::is_current)
+        .collect();
+    envelopes.sort_by(|a, b| a.envelope.name.cmp(&b.envelope.name));
+    Ok(envelopes)
+}
+
+/// Every version of an envelope, oldest first
+pub fn envelope_history(conn: &Connection, envelope_id: &str) -> Result<Vec<VersionedEnvelope>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM envelopes WHERE envelope_id = ?1 ORDER BY version",
+        ENVELOPE_COLUMNS
+    ))?;
+    let versions = stmt.query_map([envelope_id], row_to_envelope)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(versions)
+}
+
+/// Every version of every envelope in a ledger (retired ones included)
+pub fn ledger_envelope_versions(conn: &Connection, ledger_id: &str) -> Result<Vec<VersionedEnvelope>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM envelopes WHERE ledger_id = ?1 ORDER BY envelope_id, version",
+        ENVELOPE_COLUMNS
+    ))?;
+    let versions = stmt.query_map([ledger_id], row_to_envelope)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(versions)
+}
+
+fn row_to_envelope(row: &rusqlite::Row) -> rusqlite::Result<VersionedEnvelope> {
+    let definition: String = row.get(0)?;
+    let system_time: String = row.get(4)?;
+    let valid_from: String = row.get(5)?;
+    let valid_until: Option<String> = row.get(6)?;
+
+    let parse = |s: &str| {
+        DateTime::parse_from_rfc3339(s)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|_| rusqlite::Error::InvalidQuery)
+    };
+
+    Ok(VersionedEnvelope {
+        envelope: serde_json::from_str(&definition).map_err(|_| rusqlite::Error::InvalidQuery)?,
+        version: row.get(1)?,
+        changed_by: row.get(2)?,
+        change_reason: row.get(3)?,
+        system_time: parse(&system_time)?,
+        valid_from: parse(&valid_from)?,
+        valid_until: valid_until.as_deref().map(parse).transpose()?,
+    })
+}
+
+// ============================================================================
+// MONTHLY BALANCES
+// ============================================================================
+
+/// One envelope in one month
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EnvelopeBalance {
+    pub envelope_id: String,
+    pub name: String,
+    /// Carried over from the previous month
+    pub opening: f64,
+    pub funded: f64,
+    /// Spending in its categories (refunds count back)
+    pub spent: f64,
+    /// Taken from the buffer to cover overspending
+    pub borrowed: f64,
+    /// opening + funded - spent + borrowed (negative: the buffer ran out)
+    pub closing: f64,
+}
+
+impl EnvelopeBalance {
+    pub fn is_overspent(&self) -> bool {
+        self.borrowed > 0.0 || self.closing < 0.0
+    }
+}
+
+/// Every envelope in one month, with the buffer before and after
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EnvelopeMonth {
+    /// "2025-01"
+    pub month: String,
+    pub envelopes: Vec<EnvelopeBalance>,
+    pub buffer_opening: f64,
+    pub buffer_closing: f64,
+}
+
+/// Versions valid at the end of a month: one per envelope, the first version
+/// for months before the envelope existed, none after it was retired
+fn envelopes_for_month(versions: &[VersionedEnvelope], month_end: DateTime<Utc>) -> Vec<&Envelope> {
+    let mut by_id: BTreeMap<&str, Vec<&VersionedEnvelope>> = BTreeMap::new();
+    for version in versions {
+        by_id.entry(version.envelope.id.as_str()).or_default().push(version);
+    }
+    let mut envelopes: Vec<&Envelope> = by_id
+        .into_values()
+        .filter_map(|mut versions| {
+            versions.sort_by_key(|version| version.version);
+            let first = versions[0];
+            match versions.iter().find(|version| version.was_valid_at(month_end)) {
+                Some(version) => Some(&version.envelope),
+                None if first.valid_from >= month_end => Some(&first.envelope),
+                None => None,
+            }
+        })
+        .collect();
+    envelopes.sort_by(|a, b| a.name.cmp(&b.name));
+    envelopes
+}
+
+/// Month-by-month envelope balances from `from`'s month through `to`'s month,
+/// starting with empty envelopes and a full buffer of `buffer`
+pub fn envelope_months(
+    versions: &[VersionedEnvelope],
+    buffer: f64,
+    transactions: &[Transaction],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<EnvelopeMonth> {
+    let mut carried: BTreeMap<String, f64> = BTreeMap::new();
+    let mut buffer_balance = buffer;
+    let mut months = Vec::new();
+
+    let mut month = from.with_day(1).unwrap_or(from);
+    while month <= to {
+        let Some(next) = month.checked_add_months(Months::new(1)) else {
+            break;
+        };
+        let month_end = next.and_hms_opt(0, 0, 0).map_or_else(Utc::now, |end| end.and_utc());
+        let buffer_opening = buffer_balance;
+        let in_month: Vec<&Transaction> = transactions
+            .iter()
+            .filter(|tx| tx.is_active() && tx.parsed_date().is_some_and(|date| date >= month && date < next))
+            .collect();
+
+        let mut envelopes = Vec::new();
+        for envelope in envelopes_for_month(versions, month_end) {
+            let opening = carried.get(&envelope.id).copied().unwrap_or(0.0);
+            let spent = in_month
+                .iter()
+                .filter(|tx| envelope.covers(&tx.category))
+                .fold(0.0, |total, tx| total - tx.amount_numeric);
+            let available = opening + envelope.monthly_funding - spent;
+            let borrowed = if available < 0.0 { (-available).min(buffer_balance.max(0.0)) } else { 0.0 };
+            buffer_balance -= borrowed;
+            let closing = available + borrowed;
+
+            let kept = envelope.carryover.kept(closing);
+            buffer_balance += closing - kept;
+            carried.insert(envelope.id.clone(), kept);
+
+            envelopes.push(EnvelopeBalance {
+                envelope_id: envelope.id.clone(),
+                name: envelope.name.clone(),
+                opening,
+                funded: envelope.monthly_funding,
+                spent,
+                borrowed,
+                closing,
+            });
+        }
+
+        months.push(EnvelopeMonth {
+            month: format!("{:04}-{:02}", month.year(), month.month()),
+            envelopes,
+            buffer_opening,
+            buffer_closing: buffer_balance,
+        });
+        month = next;
+    }
+    months
+}
+
+/// Envelope balances of a ledger from `from`'s month through `to`'s month,
+/// with the buffer from the ledger config
+pub fn ledger_envelope_months(
+    conn: &Connection,
+    ledger_id: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<EnvelopeMonth>> {
+    let buffer = require_ledger(conn, ledger_id)?.config.envelope_buffer.unwrap_or(0.0);
+    let versions = ledger_envelope_versions(conn, ledger_id)?;
+    let transactions = ledger_transactions(conn, ledger_id)?;
+    Ok(envelope_months(&versions, buffer, &transactions, from, to))
+}
+
+/// First day of the month the ledger's oldest envelope was created in
+pub fn budgeting_start(conn: &Connection, ledger_id: &str) -> Result<Option<NaiveDate>> {
+    Ok(ledger_envelope_versions(conn, ledger_id)?
+        .iter()
+        .map(|version| version.valid_from.date_naive())
+        .min()
+        .and_then(|date| date.with_day(1)))
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::db::setup_database;
+
+    fn spend(date: &str, category: &str, amount: f64) -> Transaction {
+        let mut tx = Transaction {
+            date: date.to_string(),
+            description: category.to_uppercase(),
+            amount_original: format!("{:.2}", amount),
+            amount_numeric: amount,
+            category: category.to_string(),
+            ..test_support::sample_transaction()
+        };
+        tx.init_temporal_fields();
+        tx
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_overspend_borrows_from_buffer_and_carryover_rules() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let groceries = Envelope::new("Groceries", 300.0).with_category("Groceries");
+        let fun = Envelope::new("Fun", 100.0)
+            .with_category("Entertainment")
+            .with_carryover(CarryoverRule::Capped { max: 20.0 });
+        save_envelope(&conn, &groceries, "ana", None).unwrap();
+        save_envelope(&conn, &fun, "ana", None).unwrap();
+        let versions = ledger_envelope_versions(&conn, "default").unwrap();
+
+        let transactions = vec![
+            spend("01/05/2025", "Groceries", -250.0),
+            spend("01/09/2025", "Entertainment", -40.0),
+            spend("02/03/2025", "Groceries", -420.0),
+            spend("02/10/2025", "groceries", 20.0),
+        ];
+        let months = envelope_months(&versions, 50.0, &transactions, date("2025-01-01"), date("2025-02-28"));
+        assert_eq!(months.len(), 2);
+
+        // January: 50 left in groceries (kept), 60 left in fun (20 kept, 40 to the buffer)
+        let january = &months[0];
+        assert_eq!(january.envelopes.iter().map(|e| e.closing).collect::<Vec<_>>(), vec![60.0, 50.0]);
+        assert_eq!(january.buffer_closing, 90.0);
+
+        // February: groceries needs 50 + 300 - 400 = -50, borrowed from the buffer
+        let february = &months[1];
+        let groceries = february.envelopes.iter().find(|e| e.name == "Groceries").unwrap();
+        assert_eq!((groceries.opening, groceries.spent, groceries.borrowed), (50.0, 400.0, 50.0));
+        assert_eq!(groceries.closing, 0.0);
+        assert!(groceries.is_overspent());
+        let fun = february.envelopes.iter().find(|e| e.name == "Fun").unwrap();
+        assert_eq!((fun.opening, fun.closing), (20.0, 120.0));
+        // 90 - 50 borrowed + 100 of fun's leftover over the cap
+        assert_eq!(february.buffer_closing, 140.0);
+
+        // No buffer to start with: only fun's 80 over its cap can be borrowed,
+        // the rest of the deficit stays in the envelope
+        let months = envelope_months(&versions, 0.0, &transactions, date("2025-02-01"), date("2025-02-28"));
+        let groceries = months[0].envelopes.iter().find(|e| e.name == "Groceries").unwrap();
+        assert_eq!((groceries.borrowed, groceries.closing), (80.0, -20.0));
+        assert_eq!(months[0].buffer_closing, 0.0);
+    }
+
+    #[test]
+    fn test_envelopes_are_versioned() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let mut dining = Envelope::new("Dining", 200.0).with_category("Restaurants");
+        assert_eq!(save_envelope(&conn, &dining, "ana", None).unwrap(), 1);
+        assert_eq!(save_envelope(&conn, &dining, "ana", None).unwrap(), 1);
+
+        dining.monthly_funding = 250.0;
+        assert_eq!(save_envelope(&conn, &dining, "ana", Some("raise")).unwrap(), 2);
+        let history = envelope_history(&conn, &dining.id).unwrap();
+        assert_eq!(history.iter().map(|v| v.envelope.monthly_funding).collect::<Vec<_>>(), vec![200.0, 250.0]);
+        assert!(!history[0].is_current() && history[1].is_current());
+
+        // A category draws on one envelope only
+        let clash = Envelope::new("Eating out", 50.0).with_category("restaurants");
+        assert!(save_envelope(&conn, &clash, "ana", None).is_err());
+
+        retire_envelope(&conn, &dining.id, "ana", None).unwrap();
+        assert!(current_envelopes(&conn, "default").unwrap().is_empty());
+        assert_eq!(envelope_history(&conn, &dining.id).unwrap().len(), 2);
+        assert!(retire_envelope(&conn, &dining.id, "ana", None).is_err());
+
+        let events = crate::db::get_events_for_entity(&conn, "envelope", &dining.id).unwrap();
+        let types: Vec<&str> = events.iter().map(|e| e.event_type.as_str()).collect();
+        assert_eq!(types, vec!["envelope_retired", "envelope_updated", "envelope_created"]);
+    }
+}
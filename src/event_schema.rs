@@ -0,0 +1,608 @@
+// 🗂️ Event Schema - One payload type per event_type
+//
+// Problem solved:
+// - Event `data` was whatever `json!` the writer built, so a consumer of the
+//   audit trail had to guess its shape, and a renamed key silently broke
+//   every query written against the old one
+// - Nothing stopped a changeset from another instance (or a typo) writing an
+//   event type nobody knew how to read
+//
+// Each event type has a payload struct (or reuses the record it describes:
+// a closed statement is its `BalanceSnapshot`). Writers build events with
+// `Event::typed`, readers get the struct back with `Event::payload`, and
+// `insert_event` rejects an unknown type or a payload that doesn't match its
+// type. New fields must be optional (or defaulted) so old events still read.
+
+use crate::approvals::ChangeKind;
+use crate::bills::{BillPriceChange, ExpectedTransaction};
+use crate::conflicts::ConflictPolicy;
+use crate::db::Transaction;
+use crate::disputes::DisputeStatus;
+use crate::entities::AccountStatus;
+use crate::ledger::LedgerConfig;
+use crate::merge::MergeSummary;
+use crate::projects::{AssignmentMethod, Project};
+use crate::purge::PurgeReport;
+use crate::statements::BalanceSnapshot;
+use crate::sync::ImportSummary;
+use crate::users::Role;
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Payload of one or more event types
+pub trait EventPayload: Serialize + DeserializeOwned {
+    /// Event types whose `data` has this shape
+    const EVENT_TYPES: &'static [&'static str];
+}
+
+// ============================================================================
+// TRANSACTIONS
+// ============================================================================
+
+/// A statement row stored for the first time (entity: its idempotency hash)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionAdded {
+    pub bank: String,
+    pub amount: f64,
+    pub source_file: String,
+}
+
+impl EventPayload for TransactionAdded {
+    const EVENT_TYPES: &'static [&'static str] = &["transaction_added"];
+}
+
+/// A new version of a transaction (corrections and voids)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionCorrected {
+    pub version: i64,
+    pub change_reason: Option<String>,
+    pub voided: bool,
+    pub category: String,
+    pub merchant: String,
+    pub transaction_type: String,
+}
+
+impl EventPayload for TransactionCorrected {
+    const EVENT_TYPES: &'static [&'static str] = &["transaction_corrected", "transaction_voided"];
+}
+
+/// Two versions of a transaction that disagree, and how that was settled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictLogged {
+    pub policy: ConflictPolicy,
+    /// None while only detected
+    pub outcome: Option<String>,
+    pub local: Transaction,
+    pub incoming: Transaction,
+}
+
+impl EventPayload for ConflictLogged {
+    const EVENT_TYPES: &'static [&'static str] = &["conflict_detected", "conflict_resolved"];
+}
+
+/// A decision on a cluster of likely duplicates (merging voids all but one)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicatesDecided {
+    pub tx_uuids: Vec<String>,
+}
+
+impl EventPayload for DuplicatesDecided {
+    const EVENT_TYPES: &'static [&'static str] = &["duplicates_merged", "duplicates_dismissed", "duplicates_deferred"];
+}
+
+/// Review decision on a group of merchant spellings (see aliases.rs)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AliasesDecided {
+    pub canonical: String,
+    pub names: Vec<String>,
+}
+
+impl EventPayload for AliasesDecided {
+    const EVENT_TYPES: &'static [&'static str] = &["aliases_confirmed", "aliases_rejected"];
+}
+
+/// One bulk action over a selection of transactions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BulkActionApplied {
+    pub action: String,
+    pub tx_uuids: Vec<String>,
+    pub applied: Vec<String>,
+    pub pending: usize,
+    pub skipped: usize,
+    /// (tx uuid, error)
+    pub failed: Vec<(String, String)>,
+}
+
+impl EventPayload for BulkActionApplied {
+    const EVENT_TYPES: &'static [&'static str] = &["bulk_action"];
+}
+
+/// A change proposed for approval, and its approval or rejection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingChangeLogged {
+    pub kind: ChangeKind,
+    pub tx_uuid: String,
+    pub base_version: i64,
+    pub amount: f64,
+    pub proposed_by: String,
+}
+
+impl EventPayload for PendingChangeLogged {
+    const EVENT_TYPES: &'static [&'static str] = &["change_proposed", "change_approved", "change_rejected"];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoteAdded {
+    pub note_id: String,
+    pub parent_id: Option<String>,
+}
+
+impl EventPayload for NoteAdded {
+    const EVENT_TYPES: &'static [&'static str] = &["note_added"];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisputeChanged {
+    pub tx_uuid: String,
+    /// None when opened
+    pub from: Option<DisputeStatus>,
+    pub to: DisputeStatus,
+    pub note: Option<String>,
+}
+
+impl EventPayload for DisputeChanged {
+    const EVENT_TYPES: &'static [&'static str] = &["dispute_opened", "dispute_updated", "dispute_resolved"];
+}
+
+// ============================================================================
+// IMPORTS AND SYNC
+// ============================================================================
+
+/// One statement import (see imports::ImportSession)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportRecorded {
+    pub filename: String,
+    pub source: String,
+    pub rows: usize,
+    pub inserted: usize,
+    pub duplicates: usize,
+    pub repeated: usize,
+    pub upsert: bool,
+    pub updated: usize,
+    pub failed: usize,
+    /// Number of row issues
+    pub issues: usize,
+    /// Settled rows that upgraded a pending one (older events predate it)
+    #[serde(default)]
+    pub settled: usize,
+    /// Rows held in quarantine by the ledger's import policy (see quarantine.rs)
+    #[serde(default)]
+    pub quarantined: bool,
+}
+
+impl EventPayload for ImportRecorded {
+    const EVENT_TYPES: &'static [&'static str] = &["import_session"];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangesetImported {
+    pub since: String,
+    pub until: String,
+    pub summary: ImportSummary,
+}
+
+impl EventPayload for ChangesetImported {
+    const EVENT_TYPES: &'static [&'static str] = &["changeset_imported"];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerMerged {
+    /// Source ledger id → local ledger id
+    pub ledger_map: BTreeMap<String, String>,
+    /// None: each target ledger's own policy
+    pub policy: Option<ConflictPolicy>,
+    pub summary: MergeSummary,
+}
+
+impl EventPayload for LedgerMerged {
+    const EVENT_TYPES: &'static [&'static str] = &["ledger_merged"];
+}
+
+// ============================================================================
+// ACCOUNTS, LEDGERS, RULES, PROJECTS, BILLS
+// ============================================================================
+
+impl EventPayload for BalanceSnapshot {
+    const EVENT_TYPES: &'static [&'static str] = &["statement_closed"];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpeningBalanceSet {
+    pub version: i64,
+    pub opening_balance: f64,
+    pub as_of: NaiveDate,
+    /// The synthetic opening transaction
+    pub tx_uuid: String,
+}
+
+impl EventPayload for OpeningBalanceSet {
+    const EVENT_TYPES: &'static [&'static str] = &["opening_balance_set"];
+}
+
+/// An account frozen, closed or reopened
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountStatusChanged {
+    pub version: i64,
+    pub status: AccountStatus,
+}
+
+impl EventPayload for AccountStatusChanged {
+    const EVENT_TYPES: &'static [&'static str] = &["account_status_changed"];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerCreated {
+    pub name: String,
+    pub config: LedgerConfig,
+}
+
+impl EventPayload for LedgerCreated {
+    const EVENT_TYPES: &'static [&'static str] = &["ledger_created"];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerConfigUpdated {
+    pub config: LedgerConfig,
+}
+
+impl EventPayload for LedgerConfigUpdated {
+    const EVENT_TYPES: &'static [&'static str] = &["ledger_config_updated"];
+}
+
+/// A rule saved (version 1 = created)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleChanged {
+    pub version: i64,
+    pub pattern: String,
+    pub reason: Option<String>,
+}
+
+impl EventPayload for RuleChanged {
+    const EVENT_TYPES: &'static [&'static str] = &["rule_created", "rule_updated"];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleRetired {
+    pub reason: Option<String>,
+}
+
+impl EventPayload for RuleRetired {
+    const EVENT_TYPES: &'static [&'static str] = &["rule_retired"];
+}
+
+impl EventPayload for Project {
+    const EVENT_TYPES: &'static [&'static str] = &["project_created"];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectAssignmentChanged {
+    pub tx_uuid: String,
+    /// None when removed
+    pub method: Option<AssignmentMethod>,
+}
+
+impl EventPayload for ProjectAssignmentChanged {
+    const EVENT_TYPES: &'static [&'static str] = &["project_transaction_added", "project_transaction_removed"];
+}
+
+impl EventPayload for ExpectedTransaction {
+    const EVENT_TYPES: &'static [&'static str] = &["bill_created", "bill_removed"];
+}
+
+impl EventPayload for BillPriceChange {
+    const EVENT_TYPES: &'static [&'static str] = &["bill_price_changed"];
+}
+
+/// An envelope saved (version 1 = created) or retired (its last version)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvelopeChanged {
+    pub version: i64,
+    pub name: String,
+    pub monthly_funding: f64,
+    pub reason: Option<String>,
+}
+
+impl EventPayload for EnvelopeChanged {
+    const EVENT_TYPES: &'static [&'static str] = &["envelope_created", "envelope_updated", "envelope_retired"];
+}
+
+/// A transaction entered by hand (cash spending or an adjustment)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManualTransactionCreated {
+    /// "cash" or "adjustment"
+    pub kind: String,
+    pub amount: f64,
+    pub currency: String,
+    pub reason: String,
+}
+
+impl EventPayload for ManualTransactionCreated {
+    const EVENT_TYPES: &'static [&'static str] = &["manual_transaction_created"];
+}
+
+/// Both legs of a transfer written and linked (entity: the transfer_id)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferCreated {
+    pub amount: f64,
+    pub date: NaiveDate,
+    /// "BofA Checking"
+    pub from: String,
+    pub to: String,
+    /// Outgoing then incoming tx_uuid
+    pub legs: Vec<String>,
+    /// The leg that came from a statement, when only the other was written
+    pub imported_leg: Option<String>,
+    /// Both legs were imported and differed by FX slippage: its amount and the
+    /// adjustment row that carries it
+    #[serde(default)]
+    pub fx_slippage: Option<f64>,
+    #[serde(default)]
+    pub fx_adjustment: Option<String>,
+}
+
+impl EventPayload for TransferCreated {
+    const EVENT_TYPES: &'static [&'static str] = &["transfer_created"];
+}
+
+/// A pending transaction upgraded to its settled values
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionSettled {
+    pub pending_amount: f64,
+    pub settled_amount: f64,
+    pub pending_date: String,
+    pub settled_date: String,
+    /// The separately stored settled row (voided), when there was one
+    pub settled_row: Option<String>,
+}
+
+impl EventPayload for TransactionSettled {
+    const EVENT_TYPES: &'static [&'static str] = &["transaction_settled"];
+}
+
+/// A brokerage snapshot of an account's positions (see positions.rs)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionsRecorded {
+    pub account: String,
+    pub as_of: NaiveDate,
+    pub positions: usize,
+    /// Positions of an earlier snapshot for the same date that were replaced
+    pub replaced: usize,
+    pub source_file: String,
+}
+
+impl EventPayload for PositionsRecorded {
+    const EVENT_TYPES: &'static [&'static str] = &["positions_recorded"];
+}
+
+/// A statement period of an account closed to corrections, or reopened (see
+/// period_close.rs)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeriodLockChanged {
+    pub statement_period: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Why it was reopened
+    pub reason: Option<String>,
+}
+
+impl EventPayload for PeriodLockChanged {
+    const EVENT_TYPES: &'static [&'static str] = &["period_closed", "period_reopened"];
+}
+
+/// A quarantined import released into the ledger or discarded (see
+/// quarantine.rs)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuarantineReviewed {
+    pub rows: usize,
+    /// Why it was discarded
+    pub reason: Option<String>,
+}
+
+impl EventPayload for QuarantineReviewed {
+    const EVENT_TYPES: &'static [&'static str] = &["quarantine_released", "quarantine_discarded"];
+}
+
+/// A counterparty's data removed or pseudonymized (see purge.rs; entity: the
+/// purge id, the name itself is never recorded)
+impl EventPayload for PurgeReport {
+    const EVENT_TYPES: &'static [&'static str] = &["counterparty_purged"];
+}
+
+/// A chart of accounts mapping saved (version 1 = created) or retired (see
+/// chart_of_accounts.rs)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChartMappingChanged {
+    pub version: i64,
+    /// "category" or "account"
+    pub mapped_from: String,
+    pub internal: String,
+    pub code: String,
+    pub name: String,
+    pub reason: Option<String>,
+}
+
+impl EventPayload for ChartMappingChanged {
+    const EVENT_TYPES: &'static [&'static str] =
+        &["chart_mapping_created", "chart_mapping_updated", "chart_mapping_retired"];
+}
+
+// ============================================================================
+// USERS, API AND WEBHOOKS
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserCreated {
+    pub username: String,
+    pub role: Role,
+}
+
+impl EventPayload for UserCreated {
+    const EVENT_TYPES: &'static [&'static str] = &["user_created"];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserRoleChanged {
+    pub username: String,
+    pub from: Role,
+    pub to: Role,
+}
+
+impl EventPayload for UserRoleChanged {
+    const EVENT_TYPES: &'static [&'static str] = &["user_role_changed"];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserTokenRotated {
+    pub username: String,
+}
+
+impl EventPayload for UserTokenRotated {
+    const EVENT_TYPES: &'static [&'static str] = &["user_token_rotated"];
+}
+
+/// One mutating API call, whatever its outcome
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiRequestLogged {
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+}
+
+impl EventPayload for ApiRequestLogged {
+    const EVENT_TYPES: &'static [&'static str] = &["api_request"];
+}
+
+/// A webhook added or removed (see outbox.rs)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookChanged {
+    pub url: String,
+    /// Empty = every event type
+    pub event_types: Vec<String>,
+}
+
+impl EventPayload for WebhookChanged {
+    const EVENT_TYPES: &'static [&'static str] = &["webhook_added", "webhook_removed"];
+}
+
+// ============================================================================
+// REGISTRY
+// ============================================================================
+
+type Check = fn(&serde_json::Value) -> Result<()>;
+
+fn check<P: EventPayload>(data: &serde_json::Value) -> Result<()> {
+    P::deserialize(data).map(drop).map_err(Into::into)
+}
+
+/// Every event type with its payload check
+const REGISTRY: &[(&[&str], Check)] = &[
+    (TransactionAdded::EVENT_TYPES, check::<TransactionAdded>),
+    (TransactionCorrected::EVENT_TYPES, check::<TransactionCorrected>),
+    (ConflictLogged::EVENT_TYPES, check::<ConflictLogged>),
+    (DuplicatesDecided::EVENT_TYPES, check::<DuplicatesDecided>),
+    (AliasesDecided::EVENT_TYPES, check::<AliasesDecided>),
+    (BulkActionApplied::EVENT_TYPES, check::<BulkActionApplied>),
+    (PendingChangeLogged::EVENT_TYPES, check::<PendingChangeLogged>),
+    (NoteAdded::EVENT_TYPES, check::<NoteAdded>),
+    (DisputeChanged::EVENT_TYPES, check::<DisputeChanged>),
+    (ImportRecorded::EVENT_TYPES, check::<ImportRecorded>),
+    (ChangesetImported::EVENT_TYPES, check::<ChangesetImported>),
+    (LedgerMerged::EVENT_TYPES, check::<LedgerMerged>),
+    (BalanceSnapshot::EVENT_TYPES, check::<BalanceSnapshot>),
+    (OpeningBalanceSet::EVENT_TYPES, check::<OpeningBalanceSet>),
+    (AccountStatusChanged::EVENT_TYPES, check::<AccountStatusChanged>),
+    (LedgerCreated::EVENT_TYPES, check::<LedgerCreated>),
+    (LedgerConfigUpdated::EVENT_TYPES, check::<LedgerConfigUpdated>),
+    (RuleChanged::EVENT_TYPES, check::<RuleChanged>),
+    (RuleRetired::EVENT_TYPES, check::<RuleRetired>),
+    (Project::EVENT_TYPES, check::<Project>),
+    (ProjectAssignmentChanged::EVENT_TYPES, check::<ProjectAssignmentChanged>),
+    (ExpectedTransaction::EVENT_TYPES, check::<ExpectedTransaction>),
+    (BillPriceChange::EVENT_TYPES, check::<BillPriceChange>),
+    (EnvelopeChanged::EVENT_TYPES, check::<EnvelopeChanged>),
+    (ManualTransactionCreated::EVENT_TYPES, check::<ManualTransactionCreated>),
+    (TransferCreated::EVENT_TYPES, check::<TransferCreated>),
+    (TransactionSettled::EVENT_TYPES, check::<TransactionSettled>),
+    (PositionsRecorded::EVENT_TYPES, check::<PositionsRecorded>),
+    (PeriodLockChanged::EVENT_TYPES, check::<PeriodLockChanged>),
+    (ChartMappingChanged::EVENT_TYPES, check::<ChartMappingChanged>),
+    (QuarantineReviewed::EVENT_TYPES, check::<QuarantineReviewed>),
+    (PurgeReport::EVENT_TYPES, check::<PurgeReport>),
+    (UserCreated::EVENT_TYPES, check::<UserCreated>),
+    (UserRoleChanged::EVENT_TYPES, check::<UserRoleChanged>),
+    (UserTokenRotated::EVENT_TYPES, check::<UserTokenRotated>),
+    (ApiRequestLogged::EVENT_TYPES, check::<ApiRequestLogged>),
+    (WebhookChanged::EVENT_TYPES, check::<WebhookChanged>),
+];
+
+/// All event types with a registered payload, sorted
+pub fn registered_event_types() -> Vec<&'static str> {
+    let mut types: Vec<&'static str> = REGISTRY.iter().flat_map(|(types, _)| types.iter().copied()).collect();
+    types.sort_unstable();
+    types
+}
+
+/// Fail unless `event_type` is registered and `data` has its payload's shape
+pub fn validate_event_data(event_type: &str, data: &serde_json::Value) -> Result<()> {
+    let (_, check) = REGISTRY
+        .iter()
+        .find(|(types, _)| types.contains(&event_type))
+        .ok_or_else(|| anyhow!("Unknown event type '{}': register its payload in event_schema.rs", event_type))?;
+    check(data).with_context(|| format!("Invalid '{}' event payload", event_type))
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{get_events_for_entity, insert_event, setup_database, Event};
+    use rusqlite::Connection;
+
+    #[test]
+    fn test_every_event_type_has_one_payload() {
+        let types = registered_event_types();
+        let mut unique = types.clone();
+        unique.dedup();
+        assert_eq!(types, unique, "an event type is registered twice");
+        assert!(types.contains(&"transaction_corrected"));
+        assert!(types.contains(&"statement_closed"));
+    }
+
+    #[test]
+    fn test_payloads_round_trip_and_bad_ones_are_rejected() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let payload = NoteAdded { note_id: "n-1".to_string(), parent_id: None };
+        let event = Event::typed("note_added", "transaction", "tx-1", &payload, "ana").unwrap();
+        insert_event(&conn, &event).unwrap();
+        let stored = get_events_for_entity(&conn, "transaction", "tx-1").unwrap();
+        assert_eq!(stored[0].payload::<NoteAdded>().unwrap(), payload);
+        assert!(stored[0].payload::<DisputeChanged>().is_err(), "payload type must match the event type");
+
+        // Wrong payload for the type, unknown type, wrong shape
+        assert!(Event::typed("note_removed", "transaction", "tx-1", &payload, "ana").is_err());
+        let unknown = Event::new("note_removed", "transaction", "tx-1", serde_json::json!({}), "ana");
+        assert!(insert_event(&conn, &unknown).is_err());
+        let missing_field = Event::new("note_added", "transaction", "tx-1", serde_json::json!({ "parent_id": null }), "ana");
+        let error = insert_event(&conn, &missing_field).unwrap_err();
+        assert!(format!("{:#}", error).contains("note_id"));
+        assert_eq!(get_events_for_entity(&conn, "transaction", "tx-1").unwrap().len(), 1);
+    }
+}
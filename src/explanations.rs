@@ -0,0 +1,319 @@
+// 🔎 Discrepancy Explanations - Why a statement is off, ranked
+//
+// Problem solved:
+// - A reconciliation said "balance mismatch: $12.50" and stopped there; the
+//   usual causes are few and checkable, but finding the row behind one meant
+//   scrolling the account by hand
+//
+// `explain_gap` tries each cause against the account's rows around the
+// statement period: a row dated on one side of the boundary and booked on the
+// other, a fee the feed never carried, a row still pending here that the
+// statement lists settled, the same row imported twice. Each hypothesis is
+// scored by how well it accounts for the difference and lists the rows it
+// rests on; `explain_report` attaches the best one to the report's balance
+// mismatch.
+
+use crate::db::{DateBasis, Transaction};
+use crate::deduplication::DeduplicationEngine;
+use crate::reconciliation::{DiscrepancyCategory, ReconciliationReport, StatementMetadata};
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Days either side of the statement period searched for rows booked across
+/// the boundary
+pub const TIMING_WINDOW_DAYS: i64 = 5;
+
+/// Largest difference read as a missing bank fee
+pub const MAX_FEE: f64 = 50.0;
+
+/// Amounts closer than this are the same
+const AMOUNT_TOLERANCE: f64 = 0.01;
+
+// ============================================================================
+// HYPOTHESES
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HypothesisKind {
+    /// A row dated on one side of the period boundary, booked on the other
+    TimingDifference,
+    /// A fee on the statement that no imported feed carried
+    FeeNotImported,
+    /// A row still pending here that the statement lists settled
+    PendingTransaction,
+    /// The same row imported twice
+    Duplicate,
+}
+
+impl HypothesisKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            HypothesisKind::TimingDifference => "timing difference",
+            HypothesisKind::FeeNotImported => "fee not imported",
+            HypothesisKind::PendingTransaction => "pending transaction",
+            HypothesisKind::Duplicate => "duplicate",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hypothesis {
+    pub kind: HypothesisKind,
+    /// How well it accounts for the difference (0.0 - 1.0)
+    pub likelihood: f64,
+    pub summary: String,
+    /// The rows it rests on, one line each
+    pub evidence: Vec<String>,
+    pub transaction_ids: Vec<String>,
+}
+
+impl Hypothesis {
+    fn new(kind: HypothesisKind, likelihood: f64, summary: String, rows: &[&Transaction]) -> Self {
+        Hypothesis {
+            kind,
+            likelihood,
+            summary,
+            evidence: rows
+                .iter()
+                .map(|tx| format!("{}  {:>10.2}  {}  ({})", tx.date, tx.amount_numeric, tx.description, tx.id))
+                .collect(),
+            transaction_ids: rows.iter().map(|tx| tx.id.clone()).collect(),
+        }
+    }
+}
+
+/// What a row does to the balance, counted the way `ReconciliationEngine` does
+fn balance_effect(tx: &Transaction) -> f64 {
+    match tx.transaction_type.as_str() {
+        "INGRESO" => tx.amount_numeric.abs(),
+        "GASTO" | "PAGO_TARJETA" => -tx.amount_numeric.abs(),
+        _ => 0.0,
+    }
+}
+
+fn is_fee(tx: &Transaction) -> bool {
+    let text = format!("{} {}", tx.category, tx.description).to_lowercase();
+    text.contains("fee") || text.contains("comisi")
+}
+
+// ============================================================================
+// EXPLAINING
+// ============================================================================
+
+/// Possible causes of a statement being off by `gap` (its closing balance
+/// minus the calculated one: what the ledger is missing), most likely first
+///
+/// `transactions` may be the whole ledger: only the statement's account is
+/// looked at, dated (on `basis`) from `TIMING_WINDOW_DAYS` before `from` to
+/// as many after the close, plus its earlier fees.
+pub fn explain_gap(
+    gap: f64,
+    statement: &StatementMetadata,
+    transactions: &[Transaction],
+    from: NaiveDate,
+    basis: DateBasis,
+) -> Vec<Hypothesis> {
+    if gap.abs() < AMOUNT_TOLERANCE {
+        return Vec::new();
+    }
+
+    let close = statement.statement_date;
+    let window = Duration::days(TIMING_WINDOW_DAYS);
+    let in_period = |date: NaiveDate| date >= from && date <= close;
+    let explains = |effect: f64| effect.abs() >= AMOUNT_TOLERANCE && (effect - gap).abs() < AMOUNT_TOLERANCE;
+    let other_basis = match basis {
+        DateBasis::Transaction => DateBasis::Posted,
+        DateBasis::Posted => DateBasis::Transaction,
+    };
+
+    let account: Vec<(&Transaction, NaiveDate)> = transactions
+        .iter()
+        .filter(|tx| tx.is_active())
+        .filter(|tx| tx.account_name == statement.account_name || tx.account_number == statement.account_name)
+        .filter_map(|tx| tx.date_on(basis).map(|date| (tx, date)))
+        .collect();
+    let nearby = account.iter().filter(|(_, date)| *date >= from - window && *date <= close + window);
+
+    let mut hypotheses = Vec::new();
+    for &(tx, date) in nearby {
+        let effect = balance_effect(tx);
+        // Its other date (transaction vs posted) falls on the other side of the boundary
+        let crosses = tx.date_on(other_basis).is_some_and(|other| in_period(other) != in_period(date));
+        let bonus = if crosses { 0.2 } else { 0.0 };
+
+        if tx.is_pending() {
+            if in_period(date) && explains(effect) {
+                let summary = format!("{} is still pending here; the statement lists it settled", tx.description);
+                hypotheses.push(Hypothesis::new(HypothesisKind::PendingTransaction, 0.9, summary, &[tx]));
+            }
+        } else if !in_period(date) && explains(effect) {
+            let summary =
+                format!("{} is dated {}, outside the period; the bank booked it inside", tx.description, date);
+            hypotheses.push(Hypothesis::new(HypothesisKind::TimingDifference, 0.75 + bonus, summary, &[tx]));
+        } else if in_period(date) && explains(-effect) && (date - from < window || close - date < window) {
+            let summary = format!(
+                "{} is dated {}, inside the period; the bank booked it on the {} statement",
+                tx.description,
+                date,
+                if close - date < window { "next" } else { "previous" }
+            );
+            hypotheses.push(Hypothesis::new(HypothesisKind::TimingDifference, 0.6 + bonus, summary, &[tx]));
+        }
+    }
+
+    // The same row twice among the period's settled rows
+    let period: Vec<Transaction> = account
+        .iter()
+        .filter(|(tx, date)| in_period(*date) && !tx.is_pending())
+        .map(|(tx, _)| (*tx).clone())
+        .collect();
+    for duplicate in DeduplicationEngine::new().find_duplicates(&period) {
+        let (first, second) = (&period[duplicate.tx1_index], &period[duplicate.tx2_index]);
+        if explains(-balance_effect(second)) {
+            let summary = format!("{} was imported twice ({})", second.description, duplicate.reason);
+            let likelihood = 0.9 * duplicate.confidence;
+            hypotheses.push(Hypothesis::new(HypothesisKind::Duplicate, likelihood, summary, &[first, second]));
+        }
+    }
+
+    // A small debit the ledger lacks: likeliest a fee, more so when the
+    // account was charged the same fee before
+    if gap < 0.0 && -gap <= MAX_FEE {
+        let earlier = account
+            .iter()
+            .filter(|(tx, date)| *date < from && is_fee(tx) && explains(balance_effect(tx)))
+            .max_by_key(|(_, date)| *date);
+        hypotheses.push(match earlier {
+            Some((fee, date)) => Hypothesis::new(
+                HypothesisKind::FeeNotImported,
+                0.8,
+                format!("A {:.2} fee no feed carried; the same fee was charged on {}", -gap, date),
+                &[fee],
+            ),
+            None => Hypothesis::new(
+                HypothesisKind::FeeNotImported,
+                0.3,
+                format!("A {:.2} fee no feed carried", -gap),
+                &[],
+            ),
+        });
+    }
+
+    hypotheses.sort_by(|a, b| b.likelihood.total_cmp(&a.likelihood));
+    hypotheses
+}
+
+/// Attach the most likely explanation to the report's balance mismatch
+/// (`transactions` as for `explain_gap`)
+pub fn explain_report(
+    report: &mut ReconciliationReport,
+    transactions: &[Transaction],
+    from: NaiveDate,
+    basis: DateBasis,
+) {
+    let gap = report.statement.closing_balance - report.calculated_balance;
+    let best = explain_gap(gap, &report.statement, transactions, from, basis).into_iter().next();
+    for discrepancy in report
+        .discrepancies
+        .iter_mut()
+        .filter(|d| d.category == DiscrepancyCategory::AmountMismatch)
+    {
+        discrepancy.explanation = best.clone();
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::db::{PENDING_KEY, POSTED_DATE_KEY};
+    use crate::reconciliation::ReconciliationEngine;
+
+    fn row(id: &str, date: &str, amount: f64, description: &str) -> Transaction {
+        Transaction {
+            date: date.to_string(),
+            description: description.to_string(),
+            amount_original: format!("{:.2}", amount),
+            amount_numeric: amount,
+            transaction_type: if amount < 0.0 { "GASTO" } else { "INGRESO" }.to_string(),
+            category: "Shopping".to_string(),
+            merchant: description.to_string(),
+            account_number: "1234".to_string(),
+            id: id.to_string(),
+            version: 1,
+            ..test_support::sample_transaction()
+        }
+    }
+
+    fn january(opening: f64, closing: f64) -> StatementMetadata {
+        StatementMetadata {
+            account_name: "Checking".to_string(),
+            statement_period: "January 2025".to_string(),
+            opening_balance: opening,
+            closing_balance: closing,
+            statement_date: NaiveDate::from_ymd_opt(2025, 1, 31).unwrap(),
+            declared_count: None,
+            declared_total: None,
+        }
+    }
+
+    fn reconcile(statement: &StatementMetadata, transactions: &[Transaction]) -> ReconciliationReport {
+        let from = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let basis = DateBasis::Transaction;
+        let period = ReconciliationEngine::statement_transactions(transactions, statement, from, basis);
+        let mut report = ReconciliationEngine::new().reconcile(&period, statement);
+        explain_report(&mut report, transactions, from, basis);
+        report
+    }
+
+    #[test]
+    fn test_timing_and_pending_explanations() {
+        // Dated Feb 1 but posted Jan 31: the statement has it, the ledger's period doesn't
+        let mut late = row("late", "02/01/2025", -40.0, "Gas station");
+        late.metadata.insert(POSTED_DATE_KEY.to_string(), serde_json::json!("2025-01-31"));
+        let transactions = vec![row("pay", "01/05/2025", 500.0, "Payroll"), late];
+        let report = reconcile(&january(100.0, 560.0), &transactions);
+        let best = report.discrepancies[0].explanation.as_ref().unwrap();
+        assert_eq!(best.kind, HypothesisKind::TimingDifference);
+        assert!((best.likelihood - 0.95).abs() < 1e-9);
+        assert_eq!(best.transaction_ids, vec!["late".to_string()]);
+
+        let mut pending = row("pending", "01/20/2025", -60.0, "Hotel");
+        pending.metadata.insert(PENDING_KEY.to_string(), serde_json::json!(true));
+        let transactions = vec![row("pay", "01/05/2025", 500.0, "Payroll"), pending];
+        let report = reconcile(&january(100.0, 540.0), &transactions);
+        let best = report.discrepancies[0].explanation.as_ref().unwrap();
+        assert_eq!(best.kind, HypothesisKind::PendingTransaction);
+        assert_eq!(best.evidence.len(), 1);
+    }
+
+    #[test]
+    fn test_duplicate_ranks_above_fee_and_fee_uses_history() {
+        let transactions = vec![
+            row("a", "01/10/2025", -12.0, "Coffee Shop"),
+            row("b", "01/10/2025", -12.0, "Coffee Shop"),
+        ];
+        let ranked = explain_gap(
+            12.0,
+            &january(100.0, 88.0),
+            &transactions,
+            NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            DateBasis::Transaction,
+        );
+        assert_eq!(ranked[0].kind, HypothesisKind::Duplicate);
+        assert_eq!(ranked[0].transaction_ids, vec!["a".to_string(), "b".to_string()]);
+
+        let mut fee = row("fee", "12/15/2024", -15.0, "Monthly maintenance fee");
+        fee.category = "Fees".to_string();
+        let transactions = vec![fee, row("pay", "01/05/2025", 500.0, "Payroll")];
+        let report = reconcile(&january(100.0, 585.0), &transactions);
+        let best = report.discrepancies[0].explanation.as_ref().unwrap();
+        assert_eq!(best.kind, HypothesisKind::FeeNotImported);
+        assert_eq!(best.transaction_ids, vec!["fee".to_string()]);
+        assert!(best.likelihood > 0.5);
+    }
+}
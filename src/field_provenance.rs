@@ -0,0 +1,213 @@
+// 🧬 Field Provenance - Which component set each field, and when
+//
+// Problem solved:
+// - The `transformation_log` written at import is free text, so "who made
+//   this Groceries?" meant reading sentences, and it only covered the import:
+//   a later reclassification or a human fix left no trace on the field
+//
+// Every row carries, under `metadata.field_provenance`, one `FieldOrigin` per
+// tracked field (merchant, category, type, amount): the value, the component
+// that set it (the parser, a rule, a registry lookup such as the MCC table, a
+// human correction) and when. The import records the first origins; rule
+// reclassification, settlement and re-import upserts record theirs; and any
+// other change written as a new version is stamped as a correction by the
+// writing actor (see `attribute_changes`). Rows stored before this existed
+// simply have no origins.
+
+use crate::db::Transaction;
+use crate::mcc::classify_mcc;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// Metadata key holding the per-field origins
+pub const FIELD_PROVENANCE_KEY: &str = "field_provenance";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackedField {
+    Merchant,
+    Category,
+    Type,
+    Amount,
+}
+
+impl TrackedField {
+    pub const ALL: [TrackedField; 4] =
+        [TrackedField::Merchant, TrackedField::Category, TrackedField::Type, TrackedField::Amount];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrackedField::Merchant => "merchant",
+            TrackedField::Category => "category",
+            TrackedField::Type => "type",
+            TrackedField::Amount => "amount",
+        }
+    }
+
+    /// The field's value on `tx` (amounts with two decimals)
+    pub fn value(&self, tx: &Transaction) -> String {
+        match self {
+            TrackedField::Merchant => tx.merchant.clone(),
+            TrackedField::Category => tx.category.clone(),
+            TrackedField::Type => tx.transaction_type.clone(),
+            TrackedField::Amount => format!("{:.2}", tx.amount_numeric),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum FieldSource {
+    /// Read from the bank file (or derived by the parser's type classifier)
+    Parser { parser: String },
+    /// Set by a classification rule
+    Rule { rule_id: String },
+    /// Looked up in a registry ("mcc" with the code)
+    Registry { registry: String, key: String },
+    /// Changed by a person (or a command they ran)
+    Correction { actor: String, reason: Option<String> },
+}
+
+impl FieldSource {
+    /// The component behind a rule engine result: "mcc:5812" ids come from
+    /// the MCC table, anything else is a rule
+    pub fn classified(rule_id: &str) -> Self {
+        match rule_id.strip_prefix("mcc:") {
+            Some(code) => FieldSource::Registry { registry: "mcc".to_string(), key: code.to_string() },
+            None => FieldSource::Rule { rule_id: rule_id.to_string() },
+        }
+    }
+
+    /// Who set `field` of `tx` when the rule engine classified it with
+    /// `rule_id`: a known MCC decides the category over any rule
+    pub fn for_classification(tx: &Transaction, rule_id: &str, field: TrackedField) -> Self {
+        match tx.mcc().filter(|code| field == TrackedField::Category && classify_mcc(*code).is_some()) {
+            Some(code) => FieldSource::Registry { registry: "mcc".to_string(), key: code.to_string() },
+            None => FieldSource::classified(rule_id),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            FieldSource::Parser { parser } => format!("parser {}", parser),
+            FieldSource::Rule { rule_id } => format!("rule {}", rule_id),
+            FieldSource::Registry { registry, key } => format!("{} registry ({})", registry, key),
+            FieldSource::Correction { actor, .. } => format!("corrected by {}", actor),
+        }
+    }
+}
+
+/// Where one field's current value came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldOrigin {
+    /// The value it set (a later unattributed change makes it stale)
+    pub value: String,
+    #[serde(flatten)]
+    pub source: FieldSource,
+    pub at: DateTime<Utc>,
+}
+
+// ============================================================================
+// READ / RECORD
+// ============================================================================
+
+/// The recorded origins of `tx`, by field name (empty for older rows)
+pub fn field_origins(tx: &Transaction) -> BTreeMap<String, FieldOrigin> {
+    tx.get_metadata(FIELD_PROVENANCE_KEY)
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+fn store(metadata: &mut HashMap<String, Value>, origins: &BTreeMap<String, FieldOrigin>) {
+    metadata.insert(FIELD_PROVENANCE_KEY.to_string(), serde_json::json!(origins));
+}
+
+/// Record that `source` set `field` of `tx` to its current value at `at`
+pub fn record_origin(tx: &mut Transaction, field: TrackedField, source: FieldSource, at: DateTime<Utc>) {
+    let mut origins = field_origins(tx);
+    origins.insert(field.as_str().to_string(), FieldOrigin { value: field.value(tx), source, at });
+    store(&mut tx.metadata, &origins);
+}
+
+/// Carry the origins of `fields` over from `from`, whose values `tx` took
+pub fn inherit_origins(tx: &mut Transaction, from: &Transaction, fields: &[TrackedField]) {
+    let source = field_origins(from);
+    let mut origins = field_origins(tx);
+    for field in fields {
+        if let Some(origin) = source.get(field.as_str()) {
+            origins.insert(field.as_str().to_string(), origin.clone());
+        }
+    }
+    store(&mut tx.metadata, &origins);
+}
+
+/// Stamp the fields `next` changes from `previous` that no component claimed
+/// as corrections by `actor` (called on every new version, so a change can't
+/// keep the origin of the value it replaced)
+pub fn attribute_changes(previous: &Transaction, next: &mut Transaction, actor: &str, at: DateTime<Utc>) {
+    let origins = field_origins(next);
+    let reason = next.get_metadata("change_reason").and_then(Value::as_str).map(str::to_string);
+    for field in TrackedField::ALL {
+        let value = field.value(next);
+        let claimed = origins.get(field.as_str()).is_some_and(|origin| origin.value == value);
+        if value != field.value(previous) && !claimed {
+            let source = FieldSource::Correction { actor: actor.to_string(), reason: reason.clone() };
+            record_origin(next, field, source, at);
+        }
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{get_current_transaction, insert_transaction_version, setup_database};
+    use crate::imports::import_statement;
+    use crate::jobs::ledger_transactions;
+    use rusqlite::Connection;
+
+    const BOFA: &str = "Date,Description,Amount\n01/02/2025,STARBUCKS STORE 123,-5.25\n";
+
+    #[test]
+    fn test_import_records_origins_and_corrections_stamp_the_actor() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        import_statement(&conn, "bofa_jan.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+        let tx = ledger_transactions(&conn, "default").unwrap().remove(0);
+
+        let origins = field_origins(&tx);
+        assert!(matches!(origins["amount"].source, FieldSource::Parser { .. }));
+        assert_eq!(origins["amount"].value, "-5.25");
+        assert!(matches!(origins["type"].source, FieldSource::Parser { .. } | FieldSource::Rule { .. }));
+
+        let mut next = tx.next_version(Some("wrong category".to_string()));
+        next.category = "Travel".to_string();
+        insert_transaction_version(&conn, &next, "bob").unwrap();
+
+        let stored = get_current_transaction(&conn, &tx.id).unwrap().unwrap();
+        let category = &field_origins(&stored)["category"];
+        assert_eq!(category.value, "Travel");
+        assert_eq!(
+            category.source,
+            FieldSource::Correction { actor: "bob".to_string(), reason: Some("wrong category".to_string()) }
+        );
+        // Untouched fields keep their import origin
+        assert_eq!(field_origins(&stored)["amount"], origins["amount"]);
+    }
+
+    #[test]
+    fn test_rule_ids_from_the_mcc_table_are_registry_matches() {
+        assert_eq!(FieldSource::classified("coffee"), FieldSource::Rule { rule_id: "coffee".to_string() });
+        let mcc = FieldSource::classified("mcc:5812");
+        assert_eq!(mcc.label(), "mcc registry (5812)");
+
+        let origin = FieldOrigin { value: "Dining".to_string(), source: mcc, at: Utc::now() };
+        let json = serde_json::to_value(&origin).unwrap();
+        assert_eq!((json["source"].as_str(), json["key"].as_str()), (Some("registry"), Some("5812")));
+        assert_eq!(serde_json::from_value::<FieldOrigin>(json).unwrap(), origin);
+    }
+}
@@ -0,0 +1,117 @@
+// 🐛 Fuzzing - Entry points for the cargo-fuzz targets (feature `fuzzing`)
+//
+// Problem solved:
+// - Parsers and normalizers are fed whatever a user downloads or a browser
+//   uploads; a truncated, corrupt or hostile file must end in an error, never
+//   in a panic that takes the server worker or the TUI down with it
+//
+// The targets in fuzz/fuzz_targets call these functions with arbitrary bytes:
+// `parse_and_normalize` picks a parser with the first byte and runs the rest
+// through it and through import normalization, `normalize_fields` feeds
+// arbitrary text to the date and amount normalizers. Errors are expected and
+// ignored; only a panic is a finding. The seed corpus lives in fuzz/corpus
+// (one file per built-in parser, from fixtures/parsers and the parser tests).
+
+use crate::imports::normalize_raw;
+use crate::parser::{get_parser, iso_timestamp_date, normalize_day_first_date, RawTransaction, SourceType};
+use crate::rules::RuleEngine;
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// The source a selector byte picks (cycles through every parser)
+pub fn source_for(selector: u8) -> SourceType {
+    let all = SourceType::all();
+    all[selector as usize % all.len()].clone()
+}
+
+/// The file name a parser of `source` is given (the extension it expects)
+fn fuzz_file_name(source: &SourceType) -> String {
+    let extension = match source {
+        SourceType::Stripe => "json",
+        SourceType::Ofx => "ofx",
+        SourceType::Cfdi => "xml",
+        _ => "csv",
+    };
+    format!("{}_fuzz.{}", source.code().to_lowercase(), extension)
+}
+
+/// Parse `bytes` as a file of `source` (parsers read files, so the bytes go
+/// through a scratch file of this process and thread)
+pub fn parse_bytes(source: SourceType, bytes: &[u8]) -> Result<Vec<RawTransaction>> {
+    let dir: PathBuf = std::env::temp_dir()
+        .join(format!("trust-fuzz-{}", std::process::id()))
+        .join(format!("{:?}", std::thread::current().id()).replace(|c: char| !c.is_ascii_alphanumeric(), ""));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(fuzz_file_name(&source));
+    std::fs::write(&path, bytes)?;
+    get_parser(source).parse(&path)
+}
+
+/// Target `parsers`: the first byte picks the parser, the rest is the file;
+/// every row read is normalized as an import would
+pub fn parse_and_normalize(data: &[u8]) {
+    let Some((&selector, file)) = data.split_first() else {
+        return;
+    };
+    let rules = RuleEngine::new();
+    if let Ok(rows) = parse_bytes(source_for(selector), file) {
+        for row in &rows {
+            let _ = normalize_raw(row, &rules, "default");
+        }
+    }
+}
+
+/// Target `normalizers`: arbitrary text through the date and amount
+/// normalizers, and as the fields of a parsed row
+pub fn normalize_fields(data: &[u8]) {
+    let text = String::from_utf8_lossy(data);
+    let _ = normalize_day_first_date(&text);
+    let _ = iso_timestamp_date(&text);
+
+    let mut fields = text.splitn(3, '\u{1f}');
+    let (date, description, amount) =
+        (fields.next().unwrap_or_default(), fields.next().unwrap_or_default(), fields.next().unwrap_or_default());
+    for source in SourceType::all() {
+        let row = RawTransaction::new(
+            date.to_string(),
+            description.to_string(),
+            amount.to_string(),
+            source,
+            "fuzz.csv".to_string(),
+            1,
+            text.to_string(),
+        );
+        let _ = normalize_raw(&row, &RuleEngine::new(), "default");
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_seed_corpus_runs_through_both_targets() {
+        let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus");
+        let mut selectors = Vec::new();
+        for (target, run) in [("parsers", parse_and_normalize as fn(&[u8])), ("normalizers", normalize_fields)] {
+            for seed in std::fs::read_dir(corpus.join(target)).unwrap() {
+                let data = std::fs::read(seed.unwrap().path()).unwrap();
+                if target == "parsers" {
+                    selectors.push(data[0]);
+                }
+                run(&data);
+            }
+        }
+        // One parser seed per built-in parser
+        selectors.sort();
+        assert_eq!(selectors, (0..SourceType::all().len() as u8).collect::<Vec<_>>());
+
+        let rows = parse_bytes(source_for(0), b"Date,Description,Amount\n01/02/2025,STARBUCKS,-5.25\n").unwrap();
+        assert_eq!(rows.len(), 1);
+    }
+}
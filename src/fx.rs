@@ -0,0 +1,176 @@
+// 💱 FX Rates - Pluggable exchange rates for multi-currency ledgers
+//
+// Problem solved:
+// - Amounts in other currencies were converted with whatever rate happened to
+//   be in the source row (Wise) or not at all, so a MXN charge and a USD
+//   charge were summed as if they were the same money
+// - There was no place to plug a real rate source (a bank feed, a CSV of
+//   month-end rates) without touching every report
+//
+// `FxRateProvider` answers "how many `to` for one `from` on this date".
+// `FixedRates` is the built-in provider: a table of rates set by hand, used
+// for every date, with the inverse of each pair for free. `HistoricalRates`
+// keeps one rate per day instead (e.g. loaded from a CSV of daily rates) and
+// answers with the latest rate on or before the date asked.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap};
+
+/// A source of exchange rates
+pub trait FxRateProvider: Send + Sync {
+    /// Units of `to` for one unit of `from` on `date` (None when unknown)
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Option<f64>;
+}
+
+/// Convert `amount` from one currency to another (same currency: unchanged)
+pub fn convert(rates: &dyn FxRateProvider, amount: f64, from: &str, to: &str, date: NaiveDate) -> Result<f64> {
+    if from.eq_ignore_ascii_case(to) {
+        return Ok(amount);
+    }
+    rates
+        .rate(from, to, date)
+        .map(|rate| amount * rate)
+        .ok_or_else(|| anyhow!("No {}→{} rate for {}", from, to, date))
+}
+
+/// Hand-set rates, the same for every date
+#[derive(Debug, Clone, Default)]
+pub struct FixedRates {
+    /// (FROM, TO) → rate, codes upper-cased
+    rates: HashMap<(String, String), f64>,
+}
+
+impl FixedRates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// One `from` is worth `rate` of `to`
+    pub fn with_rate(mut self, from: &str, to: &str, rate: f64) -> Self {
+        self.rates.insert((from.to_ascii_uppercase(), to.to_ascii_uppercase()), rate);
+        self
+    }
+}
+
+impl FxRateProvider for FixedRates {
+    fn rate(&self, from: &str, to: &str, _date: NaiveDate) -> Option<f64> {
+        let (from, to) = (from.to_ascii_uppercase(), to.to_ascii_uppercase());
+        if from == to {
+            return Some(1.0);
+        }
+        if let Some(rate) = self.rates.get(&(from.clone(), to.clone())) {
+            return Some(*rate);
+        }
+        self.rates
+            .get(&(to, from))
+            .filter(|rate| **rate != 0.0)
+            .map(|rate| 1.0 / rate)
+    }
+}
+
+/// Dated rates: the rate of a day holds until the next one
+#[derive(Debug, Clone, Default)]
+pub struct HistoricalRates {
+    /// (FROM, TO) → date → rate, codes upper-cased
+    rates: HashMap<(String, String), BTreeMap<NaiveDate, f64>>,
+}
+
+impl HistoricalRates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// From `date` on, one `from` is worth `rate` of `to`
+    pub fn with_rate(mut self, from: &str, to: &str, date: NaiveDate, rate: f64) -> Self {
+        self.insert(from, to, date, rate);
+        self
+    }
+
+    pub fn insert(&mut self, from: &str, to: &str, date: NaiveDate, rate: f64) {
+        self.rates
+            .entry((from.to_ascii_uppercase(), to.to_ascii_uppercase()))
+            .or_default()
+            .insert(date, rate);
+    }
+
+    /// Rates from CSV text with rows `date,from,to,rate` (header optional)
+    pub fn from_csv(text: &str) -> Result<Self> {
+        let mut rates = Self::new();
+        for (index, line) in text.lines().enumerate() {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if line.trim().is_empty() || (index == 0 && fields[0].eq_ignore_ascii_case("date")) {
+                continue;
+            }
+            let [date, from, to, rate] = fields[..] else {
+                return Err(anyhow!("Line {}: expected date,from,to,rate", index + 1));
+            };
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").with_context(|| format!("Line {}: bad date", index + 1))?;
+            let rate: f64 = rate.parse().with_context(|| format!("Line {}: bad rate", index + 1))?;
+            rates.insert(from, to, date, rate);
+        }
+        Ok(rates)
+    }
+
+    fn latest(&self, from: &str, to: &str, date: NaiveDate) -> Option<f64> {
+        self.rates
+            .get(&(from.to_string(), to.to_string()))
+            .and_then(|by_date| by_date.range(..=date).next_back())
+            .map(|(_, rate)| *rate)
+    }
+}
+
+impl FxRateProvider for HistoricalRates {
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Option<f64> {
+        let (from, to) = (from.to_ascii_uppercase(), to.to_ascii_uppercase());
+        if from == to {
+            return Some(1.0);
+        }
+        self.latest(&from, &to, date).or_else(|| {
+            self.latest(&to, &from, date)
+                .filter(|rate| *rate != 0.0)
+                .map(|rate| 1.0 / rate)
+        })
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, 15).unwrap()
+    }
+
+    #[test]
+    fn test_fixed_rates_and_inverse() {
+        let rates = FixedRates::new().with_rate("usd", "MXN", 20.0);
+        assert_eq!(rates.rate("USD", "MXN", day()), Some(20.0));
+        assert_eq!(rates.rate("MXN", "USD", day()), Some(0.05));
+        assert_eq!(rates.rate("EUR", "EUR", day()), Some(1.0));
+        assert_eq!(rates.rate("EUR", "USD", day()), None);
+    }
+
+    #[test]
+    fn test_historical_rates_use_latest_rate_on_or_before() {
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        let rates = HistoricalRates::from_csv("date,from,to,rate\n2025-01-01,USD,MXN,20.0\n2025-01-10,usd,mxn,20.5\n").unwrap();
+        assert_eq!(rates.rate("USD", "MXN", day()), Some(20.5));
+        assert_eq!(rates.rate("USD", "MXN", date("2025-01-09")), Some(20.0));
+        assert_eq!(rates.rate("MXN", "USD", date("2025-01-05")), Some(0.05));
+        assert_eq!(rates.rate("USD", "MXN", date("2024-12-31")), None);
+        assert!(HistoricalRates::from_csv("2025-01-01,USD,MXN").is_err());
+    }
+
+    #[test]
+    fn test_convert() {
+        let rates = FixedRates::new().with_rate("EUR", "USD", 1.1);
+        assert!((convert(&rates, -100.0, "EUR", "USD", day()).unwrap() + 110.0).abs() < 1e-9);
+        assert_eq!(convert(&rates, 42.0, "GBP", "gbp", day()).unwrap(), 42.0);
+        assert!(convert(&rates, 1.0, "GBP", "USD", day()).is_err());
+    }
+}
@@ -0,0 +1,360 @@
+// 📡 gRPC - The core operations for programmatic clients
+//
+// Problem solved:
+// - Integrations (a mobile app backend, other services) had to scrape the
+//   REST API's JSON and poll it for changes
+// - There was no way to be told about new events as they happened
+//
+// `LedgerService` implements `trust.v1.Ledger` (proto/trust.proto): query,
+// get, correct and void transactions, and stream events. It shares the REST
+// server's connection pool and rules: same bearer tokens and roles, same
+// TransactionQuery parameters, same approval thresholds.
+
+use crate::approvals::{submit_correction, submit_void, WriteOutcome};
+use crate::db::{get_active_transactions, get_current_transaction, get_events_after, Event, Transaction};
+use crate::history::transactions_as_of;
+use crate::query::TransactionQuery;
+use crate::system::Correction;
+use crate::users::{authenticate, user_count, Role, User};
+use crate::pool::ConnectionPool;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+/// Generated from proto/trust.proto
+pub mod proto {
+    tonic::include_proto!("trust.v1");
+}
+
+use proto::ledger_server::{Ledger, LedgerServer};
+
+/// Events read per query while streaming
+const EVENT_BATCH: usize = 500;
+
+/// How often a following stream checks for new events
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+// ============================================================================
+// CONVERSIONS
+// ============================================================================
+
+impl From<Transaction> for proto::Transaction {
+    fn from(tx: Transaction) -> Self {
+        proto::Transaction {
+            metadata_json: serde_json::to_string(&tx.metadata).unwrap_or_else(|_| "{}".to_string()),
+            valid_from: tx.valid_from.map(|time| time.to_rfc3339()),
+            valid_until: tx.valid_until.map(|time| time.to_rfc3339()),
+            id: tx.id,
+            version: tx.version,
+            ledger_id: tx.ledger_id,
+            date: tx.date,
+            description: tx.description,
+            amount_original: tx.amount_original,
+            amount_numeric: tx.amount_numeric,
+            transaction_type: tx.transaction_type,
+            category: tx.category,
+            merchant: tx.merchant,
+            currency: tx.currency,
+            account_name: tx.account_name,
+            account_number: tx.account_number,
+            bank: tx.bank,
+            source_file: tx.source_file,
+            line_number: tx.line_number,
+            classification_notes: tx.classification_notes,
+        }
+    }
+}
+
+fn event_to_proto(sequence: i64, event: Event) -> proto::Event {
+    proto::Event {
+        sequence,
+        event_id: event.event_id,
+        timestamp: event.timestamp.to_rfc3339(),
+        event_type: event.event_type,
+        entity_type: event.entity_type,
+        entity_id: event.entity_id,
+        data_json: event.data.to_string(),
+        actor: event.actor,
+        ledger_id: event.ledger_id,
+    }
+}
+
+// Status is large, but it is what every tonic handler returns
+#[allow(clippy::result_large_err)]
+fn write_result(outcome: anyhow::Result<WriteOutcome>) -> Result<Response<proto::WriteResult>, Status> {
+    let outcome = match outcome.map_err(|e| Status::failed_precondition(e.to_string()))? {
+        WriteOutcome::Applied(tx) => proto::write_result::Outcome::Applied(tx.into()),
+        WriteOutcome::Pending(change) => proto::write_result::Outcome::PendingChangeId(change.id),
+    };
+    Ok(Response::new(proto::WriteResult { outcome: Some(outcome) }))
+}
+
+fn internal(e: anyhow::Error) -> Status {
+    Status::internal(e.to_string())
+}
+
+/// No pooled connection freed up in time
+fn unavailable(e: anyhow::Error) -> Status {
+    Status::unavailable(format!("{:#}", e))
+}
+
+// ============================================================================
+// SERVICE
+// ============================================================================
+
+pub struct LedgerService {
+    db: ConnectionPool,
+}
+
+impl LedgerService {
+    pub fn new(db: ConnectionPool) -> Self {
+        LedgerService { db }
+    }
+
+    /// The tonic service to add to a `tonic::transport::Server`
+    pub fn into_server(self) -> LedgerServer<Self> {
+        LedgerServer::new(self)
+    }
+
+    /// The caller (`authorization: Bearer <token>`), if it has at least `role`;
+    /// open mode (no users yet) acts as `anonymous` admin like the REST API
+    #[allow(clippy::result_large_err)]
+    fn authorize<T>(&self, request: &Request<T>, role: Role) -> Result<User, Status> {
+        let conn = self.db.get().map_err(unavailable)?;
+        let user = if user_count(&conn).map_err(internal)? == 0 {
+            User::anonymous()
+        } else {
+            let token = request
+                .metadata()
+                .get("authorization")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .ok_or_else(|| Status::unauthenticated("Missing bearer token"))?;
+            authenticate(&conn, token)
+                .map_err(internal)?
+                .ok_or_else(|| Status::unauthenticated("Invalid token"))?
+        };
+
+        if !user.can(role) {
+            return Err(Status::permission_denied(format!(
+                "'{}' requires the {} role",
+                user.username,
+                role.as_str()
+            )));
+        }
+        Ok(user)
+    }
+}
+
+#[tonic::async_trait]
+impl Ledger for LedgerService {
+    async fn query_transactions(
+        &self,
+        request: Request<proto::QueryTransactionsRequest>,
+    ) -> Result<Response<proto::TransactionPage>, Status> {
+        self.authorize(&request, Role::Viewer)?;
+        let query = TransactionQuery::from_params(&request.into_inner().params)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        let conn = self.db.get().map_err(unavailable)?;
+        let transactions = match query.as_of {
+            Some(as_of) => transactions_as_of(&conn, as_of),
+            None => get_active_transactions(&conn),
+        }
+        .map_err(internal)?;
+        let page = query.run(&transactions).map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        Ok(Response::new(proto::TransactionPage {
+            transactions: page.transactions.into_iter().map(Into::into).collect(),
+            total: page.total as u64,
+            next_cursor: page.next_cursor,
+        }))
+    }
+
+    async fn get_transaction(
+        &self,
+        request: Request<proto::GetTransactionRequest>,
+    ) -> Result<Response<proto::Transaction>, Status> {
+        self.authorize(&request, Role::Viewer)?;
+        let id = request.into_inner().id;
+
+        let conn = self.db.get().map_err(unavailable)?;
+        match get_current_transaction(&conn, &id).map_err(internal)? {
+            Some(tx) => Ok(Response::new(tx.into())),
+            None => Err(Status::not_found(format!("Transaction {} not found", id))),
+        }
+    }
+
+    async fn submit_correction(
+        &self,
+        request: Request<proto::CorrectionRequest>,
+    ) -> Result<Response<proto::WriteResult>, Status> {
+        let user = self.authorize(&request, Role::Editor)?;
+        let correction = request.into_inner();
+
+        let conn = self.db.get().map_err(unavailable)?;
+        let current = get_current_transaction(&conn, &correction.id)
+            .map_err(internal)?
+            .ok_or_else(|| Status::not_found(format!("Transaction {} not found", correction.id)))?;
+        let next = Correction {
+            category: correction.category,
+            merchant: correction.merchant,
+            transaction_type: correction.transaction_type,
+            reason: correction.reason,
+        }
+        .apply(&current);
+        write_result(submit_correction(&conn, &next, &user.username))
+    }
+
+    async fn void_transaction(
+        &self,
+        request: Request<proto::VoidRequest>,
+    ) -> Result<Response<proto::WriteResult>, Status> {
+        let user = self.authorize(&request, Role::Editor)?;
+        let void = request.into_inner();
+
+        let conn = self.db.get().map_err(unavailable)?;
+        write_result(submit_void(&conn, &void.id, &void.reason, &user.username))
+    }
+
+    type StreamEventsStream = ReceiverStream<Result<proto::Event, Status>>;
+
+    async fn stream_events(
+        &self,
+        request: Request<proto::StreamEventsRequest>,
+    ) -> Result<Response<Self::StreamEventsStream>, Status> {
+        self.authorize(&request, Role::Viewer)?;
+        let request = request.into_inner();
+        let db = self.db.clone();
+        let (sender, receiver) = mpsc::channel(EVENT_BATCH);
+
+        tokio::spawn(async move {
+            let mut after = request.after_sequence;
+            loop {
+                let batch = db.get().and_then(|conn| get_events_after(&conn, after, EVENT_BATCH));
+                let batch = match batch {
+                    Ok(batch) => batch,
+                    Err(e) => {
+                        let _ = sender.send(Err(internal(e))).await;
+                        return;
+                    }
+                };
+
+                let caught_up = batch.len() < EVENT_BATCH;
+                for (sequence, event) in batch {
+                    after = sequence;
+                    if !request.ledger_id.is_empty() && event.ledger_id != request.ledger_id {
+                        continue;
+                    }
+                    if sender.send(Ok(event_to_proto(sequence, event))).await.is_err() {
+                        return; // client went away
+                    }
+                }
+
+                if caught_up {
+                    if !request.follow {
+                        return;
+                    }
+                    tokio::time::sleep(FOLLOW_INTERVAL).await;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::db::{insert_transactions_as, setup_database};
+    use crate::users::create_user;
+    use std::collections::HashMap;
+    use tokio_stream::StreamExt;
+
+    fn service() -> LedgerService {
+        let pool = ConnectionPool::in_memory(2).unwrap();
+        let conn = pool.get().unwrap();
+        setup_database(&conn).unwrap();
+        let mut tx = Transaction {
+            date: "01/05/2025".to_string(),
+            description: "BLUE BOTTLE".to_string(),
+            category: "Unknown".to_string(),
+            merchant: "Blue Bottle".to_string(),
+            account_name: "Apple Card".to_string(),
+            bank: "AppleCard".to_string(),
+            source_file: "applecard.csv".to_string(),
+            line_number: "2".to_string(),
+            ..test_support::sample_transaction()
+        };
+        tx.init_temporal_fields();
+        insert_transactions_as(&conn, &[tx], "test").unwrap();
+        LedgerService::new(pool)
+    }
+
+    #[tokio::test]
+    async fn test_query_correct_and_stream_events() {
+        let service = service();
+        let page = service
+            .query_transactions(Request::new(proto::QueryTransactionsRequest {
+                params: HashMap::from([("q".to_string(), "blue".to_string())]),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(page.total, 1);
+        let id = page.transactions[0].id.clone();
+
+        let result = service
+            .submit_correction(Request::new(proto::CorrectionRequest {
+                id: id.clone(),
+                category: Some("Coffee".to_string()),
+                reason: "it was coffee".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        match result.outcome {
+            Some(proto::write_result::Outcome::Applied(tx)) => assert_eq!((tx.category, tx.version), ("Coffee".to_string(), 2)),
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+
+        let stream = service
+            .stream_events(Request::new(proto::StreamEventsRequest::default()))
+            .await
+            .unwrap()
+            .into_inner();
+        let events: Vec<_> = stream.collect::<Result<Vec<_>, _>>().await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events[0].sequence < events[1].sequence);
+        assert_eq!(events[1].entity_id, id);
+    }
+
+    #[tokio::test]
+    async fn test_tokens_and_roles_are_enforced() {
+        let service = service();
+        let viewer_token = {
+            let conn = service.db.get().unwrap();
+            create_user(&conn, "owner", Role::Admin, "setup").unwrap();
+            create_user(&conn, "auditor", Role::Viewer, "setup").unwrap().1
+        };
+
+        let missing = service.get_transaction(Request::new(proto::GetTransactionRequest::default())).await;
+        assert_eq!(missing.unwrap_err().code(), tonic::Code::Unauthenticated);
+
+        let mut void = Request::new(proto::VoidRequest { id: "x".to_string(), reason: "dup".to_string() });
+        void.metadata_mut().insert("authorization", format!("Bearer {}", viewer_token).parse().unwrap());
+        assert_eq!(service.void_transaction(void).await.unwrap_err().code(), tonic::Code::PermissionDenied);
+
+        let mut get = Request::new(proto::GetTransactionRequest { id: "nope".to_string() });
+        get.metadata_mut().insert("authorization", format!("Bearer {}", viewer_token).parse().unwrap());
+        assert_eq!(service.get_transaction(get).await.unwrap_err().code(), tonic::Code::NotFound);
+    }
+}
@@ -0,0 +1,271 @@
+// 🕰️ History - Every version of an entity with its events, and as-of views
+//
+// Problem solved:
+// - The temporal model (versions with valid_from / valid_until) was only
+//   reachable from Rust; nothing over HTTP answered "what did this look like
+//   before, and who changed it?"
+// - Lists always showed the present
+//
+// `EntityHistory` has the same shape for every entity type: the versions
+// (oldest first) with their valid ranges, and the audit events (newest first).
+// The `*_as_of` functions return a list as it was at one instant.
+
+use crate::db::{get_events_for_entity, get_transaction_history, get_transactions, Event, Transaction};
+use crate::entities::{Merchant, MerchantRegistry};
+use crate::rules::{get_rule_history, get_rules_at_time, VersionedRule};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
+
+/// One version and the time range it was (or is) true
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionRecord<T> {
+    pub version: i64,
+    pub valid_from: Option<DateTime<Utc>>,
+    /// None for the current version
+    pub valid_until: Option<DateTime<Utc>>,
+    /// When the system recorded this version
+    pub system_time: Option<DateTime<Utc>>,
+    pub value: T,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EntityHistory<T> {
+    pub entity_type: String,
+    pub id: String,
+    /// Oldest first
+    pub versions: Vec<VersionRecord<T>>,
+    /// Newest first
+    pub events: Vec<Event>,
+}
+
+/// "2025-01-31T12:00:00Z", or a date meaning the end of that day (UTC)
+pub fn parse_as_of(text: &str) -> Result<DateTime<Utc>> {
+    let text = text.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(23, 59, 59))
+        .map(|time| time.and_utc())
+        .ok_or_else(|| anyhow!("Invalid as_of '{}': expected RFC 3339 or YYYY-MM-DD", text))
+}
+
+// ============================================================================
+// TRANSACTIONS
+// ============================================================================
+
+/// All versions of a transaction plus its events (None if the id is unknown)
+pub fn transaction_history(conn: &Connection, tx_uuid: &str) -> Result<Option<EntityHistory<Transaction>>> {
+    let versions = get_transaction_history(conn, tx_uuid)?;
+    let Some(first) = versions.first() else {
+        return Ok(None);
+    };
+
+    // The import event is keyed by the idempotency hash, later ones by uuid
+    let mut events = get_events_for_entity(conn, "transaction", tx_uuid)?;
+    events.extend(get_events_for_entity(conn, "transaction", &first.compute_idempotency_hash())?);
+    events.sort_by_key(|event| std::cmp::Reverse(event.timestamp));
+
+    Ok(Some(EntityHistory {
+        entity_type: "transaction".to_string(),
+        id: tx_uuid.to_string(),
+        versions: versions
+            .into_iter()
+            .map(|tx| VersionRecord {
+                version: tx.version,
+                valid_from: tx.valid_from,
+                valid_until: tx.valid_until,
+                system_time: tx.system_time,
+                value: tx,
+            })
+            .collect(),
+        events,
+    }))
+}
+
+/// Transactions as they were at `as_of`: the version valid then, unless voided
+pub fn transactions_as_of(conn: &Connection, as_of: DateTime<Utc>) -> Result<Vec<Transaction>> {
+    Ok(get_transactions(conn, true)?
+        .into_iter()
+        .filter(|tx| tx.was_valid_at(as_of) && !tx.is_voided())
+        .collect())
+}
+
+// ============================================================================
+// RULES
+// ============================================================================
+
+/// All versions of a classification rule plus its events
+pub fn rule_history(conn: &Connection, rule_id: &str) -> Result<Option<EntityHistory<VersionedRule>>> {
+    let versions = get_rule_history(conn, rule_id)?;
+    if versions.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(EntityHistory {
+        entity_type: "rule".to_string(),
+        id: rule_id.to_string(),
+        versions: versions
+            .into_iter()
+            .map(|rule| VersionRecord {
+                version: rule.version,
+                valid_from: Some(rule.valid_from),
+                valid_until: rule.valid_until,
+                system_time: Some(rule.system_time),
+                value: rule,
+            })
+            .collect(),
+        events: get_events_for_entity(conn, "rule", rule_id)?,
+    }))
+}
+
+/// Rules that were active at `as_of`
+pub fn rules_as_of(conn: &Connection, as_of: DateTime<Utc>) -> Result<Vec<VersionedRule>> {
+    get_rules_at_time(conn, as_of)
+}
+
+// ============================================================================
+// MERCHANTS (in-memory registry: versions only, no stored events)
+// ============================================================================
+
+pub fn merchant_history(registry: &MerchantRegistry, merchant_id: &str) -> Option<EntityHistory<Merchant>> {
+    let mut versions = registry.get_all_versions(merchant_id);
+    if versions.is_empty() {
+        return None;
+    }
+    versions.sort_by_key(|merchant| merchant.version);
+
+    Some(EntityHistory {
+        entity_type: "merchant".to_string(),
+        id: merchant_id.to_string(),
+        versions: versions
+            .into_iter()
+            .map(|merchant| VersionRecord {
+                version: merchant.version,
+                valid_from: Some(merchant.valid_from),
+                valid_until: merchant.valid_until,
+                system_time: Some(merchant.system_time),
+                value: merchant,
+            })
+            .collect(),
+        events: Vec::new(),
+    })
+}
+
+/// Merchants as they were at `as_of` (None as_of: current versions)
+pub fn merchants_as_of(registry: &MerchantRegistry, as_of: Option<DateTime<Utc>>) -> Vec<Merchant> {
+    let current = registry.all_merchants();
+    match as_of {
+        None => current,
+        Some(time) => current
+            .iter()
+            .filter_map(|merchant| registry.get_merchant_at_time(&merchant.id, time))
+            .collect(),
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::db::{insert_transaction_version, insert_transactions_as, setup_database, void_transaction};
+    use crate::entities::MerchantType;
+    use crate::rules::{retire_rule, save_rule, ClassificationRule};
+
+    fn coffee() -> Transaction {
+        let mut tx = Transaction {
+            date: "01/05/2025".to_string(),
+            description: "BLUE BOTTLE".to_string(),
+            category: "Unknown".to_string(),
+            merchant: "Blue Bottle".to_string(),
+            account_name: "Apple Card".to_string(),
+            bank: "AppleCard".to_string(),
+            source_file: "applecard.csv".to_string(),
+            line_number: "2".to_string(),
+            ..test_support::sample_transaction()
+        };
+        tx.init_temporal_fields();
+        tx
+    }
+
+    #[test]
+    fn test_transaction_history_and_as_of() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        insert_transactions_as(&conn, &[coffee()], "test").unwrap();
+        let original = crate::db::get_active_transactions(&conn).unwrap().remove(0);
+        let before_correction = Utc::now();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+
+        let mut corrected = original.next_version(Some("it was coffee".to_string()));
+        corrected.category = "Coffee".to_string();
+        insert_transaction_version(&conn, &corrected, "ana").unwrap();
+
+        let history = transaction_history(&conn, &original.id).unwrap().unwrap();
+        assert_eq!(history.versions.len(), 2);
+        assert!(history.versions[0].valid_until.is_some());
+        assert!(history.versions[1].valid_until.is_none());
+        // Import event (keyed by hash) and the correction event
+        assert_eq!(history.events.len(), 2);
+        assert!(transaction_history(&conn, "nope").unwrap().is_none());
+
+        assert_eq!(transactions_as_of(&conn, before_correction).unwrap()[0].category, "Unknown");
+        assert_eq!(transactions_as_of(&conn, Utc::now()).unwrap()[0].category, "Coffee");
+
+        void_transaction(&conn, &original.id, "duplicate", "ana").unwrap();
+        assert!(transactions_as_of(&conn, Utc::now()).unwrap().is_empty());
+        assert_eq!(transactions_as_of(&conn, before_correction).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_rule_and_merchant_history() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let mut rule = ClassificationRule {
+            id: "coffee".to_string(),
+            pattern: "BLUE BOTTLE".to_string(),
+            merchant: None,
+            category: Some("Coffee".to_string()),
+            transaction_type: None,
+            confidence: 0.9,
+            description: None,
+            priority: 0,
+        };
+        save_rule(&conn, &rule, "ana", None).unwrap();
+        rule.category = Some("Cafe".to_string());
+        save_rule(&conn, &rule, "ana", Some("rename")).unwrap();
+        let before_retire = Utc::now();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        retire_rule(&conn, "coffee", "ana", None).unwrap();
+
+        let history = rule_history(&conn, "coffee").unwrap().unwrap();
+        assert_eq!(history.versions.len(), 2);
+        assert_eq!(history.events.len(), 3);
+        assert_eq!(rules_as_of(&conn, before_retire).unwrap().len(), 1);
+        assert!(rules_as_of(&conn, Utc::now()).unwrap().is_empty());
+
+        let mut registry = MerchantRegistry::new();
+        let merchant = Merchant::new("Blue Bottle".to_string(), MerchantType::Restaurant, None);
+        let id = merchant.id.clone();
+        registry.register(merchant);
+        let before_update = Utc::now();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        registry.update_merchant(&id, |m| m.canonical_name = "Blue Bottle Coffee".to_string()).unwrap();
+
+        let history = merchant_history(&registry, &id).unwrap();
+        assert_eq!(history.versions.len(), 2);
+        assert_eq!(merchants_as_of(&registry, Some(before_update))[0].canonical_name, "Blue Bottle");
+        assert_eq!(merchants_as_of(&registry, None)[0].canonical_name, "Blue Bottle Coffee");
+
+        assert!(parse_as_of("2025-01-31").is_ok());
+        assert!(parse_as_of("2025-01-31T08:00:00-05:00").is_ok());
+        assert!(parse_as_of("last week").is_err());
+    }
+}
@@ -0,0 +1,1137 @@
+// 📤 Imports - Uploaded statements (CSV/JSON/OFX) turned into ledger rows
+//
+// Problem solved:
+// - Importing needed shell access: the CLI only reads the CSV path stored in
+//   the ledger config
+// - Bank exports (BofA, Apple Card, Stripe JSON, Wise, OFX) had parsers, but
+//   nothing turned their RawTransactions into stored Transactions
+//
+// `import_statement` detects the format, parses, normalizes (sign, type, rules),
+// masks card, IBAN and account numbers (pii.rs), checks data quality and
+// duplicates, and inserts in one SQLite transaction. The returned
+// `ImportSession` says what happened to every row and is logged as an
+// `import_session` event. The server's `POST /api/imports` receives uploads
+// as multipart/form-data and imports each file with `import_statement_with`.
+//
+// `import_directory_with` imports every statement under a directory, one
+// session per file, skipping files whose format isn't detected, and totals
+// the sessions.
+
+use crate::accounts::list_accounts;
+use crate::archive::{archive_source, tag_source, SOURCE_HASH_KEY, SOURCE_LINE_KEY};
+use crate::confidence::{combine as combine_confidence, ConfidenceInputs};
+use crate::data_quality::{BatchSummary, DataQualityEngine, Severity};
+use crate::db::{
+    get_current_transaction, insert_event, insert_transaction_as, insert_transaction_version, load_csv, Event, Transaction,
+    PENDING_KEY, POSTED_DATE_KEY, QUARANTINE_KEY,
+};
+use crate::event_schema::ImportRecorded;
+use crate::field_provenance::{inherit_origins, record_origin, FieldSource, TrackedField, FIELD_PROVENANCE_KEY};
+use crate::deduplication::DeduplicationEngine;
+use crate::jobs::ledger_transactions;
+use crate::ledger::require_ledger;
+use crate::quarantine::{ImportGate, ImportPolicy};
+use crate::pii::{describe_kinds, mask_transaction, PiiKind, PII_MASKED_KEY};
+use crate::parser::{
+    detect_source, get_parser, get_type_classifier, parser_version_tag, RawTransaction, SourceType,
+};
+use crate::fx::FxRateProvider;
+use crate::replay::record_import;
+use crate::rules::RuleEngine;
+use crate::settlement::{already_settled, settle_pending, SettlementMatcher};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Column that marks the canonical CSV this tool reads and writes (see load_csv)
+const CANONICAL_HEADER: &str = "Amount_Numeric";
+
+/// Metadata key holding the id of the import session that inserted a row
+pub const IMPORT_SESSION_KEY: &str = "import_session";
+
+/// Metadata key holding the source's own id for a row (OFX FITID, Stripe id)
+pub const EXTERNAL_ID_KEY: &str = "external_id";
+
+/// Metadata keys of crypto rows: the asset moved, its signed quantity, and
+/// the fiat price per unit the amount was valued at
+pub const ASSET_KEY: &str = "asset";
+pub const QUANTITY_KEY: &str = "quantity";
+pub const ASSET_PRICE_KEY: &str = "asset_price";
+
+// ============================================================================
+// IMPORT SESSION
+// ============================================================================
+
+/// Something worth a look on one row of an upload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RowIssue {
+    /// Line in the uploaded file (JSON: 1-based record index)
+    pub line: usize,
+    pub severity: Severity,
+    pub field: String,
+    pub message: String,
+}
+
+/// What one uploaded file did to the ledger
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportSession {
+    pub id: String,
+    pub ledger_id: String,
+    pub filename: String,
+    /// Detected format, e.g. "Bank of America" or "canonical CSV"
+    pub source: String,
+    pub rows: usize,
+    pub inserted: usize,
+    /// Already in the database (same idempotency hash), not inserted again
+    pub duplicates: usize,
+    /// Rows repeating an earlier row of the same file (same external id, else
+    /// same idempotency hash), collapsed before insert
+    pub repeated: usize,
+    /// Whether rows already stored were improved instead of skipped
+    pub upsert: bool,
+    /// Already stored and given the fields they lacked (upsert only)
+    pub updated: usize,
+    /// Settled rows that upgraded a stored pending row instead of being
+    /// inserted (see settlement.rs)
+    pub settled: usize,
+    /// Rows that could not be turned into a transaction
+    pub failed: usize,
+    pub issues: Vec<RowIssue>,
+    pub imported_at: DateTime<Utc>,
+    /// Data quality of the parsed rows, as the import gate judged it
+    pub quality: Option<BatchSummary>,
+    /// Why the rows were quarantined instead of joining the ledger (see
+    /// quarantine.rs)
+    pub quarantine: Option<String>,
+}
+
+pub(crate) enum StatementFormat {
+    /// Our own export format, read by load_csv
+    Canonical,
+    Bank(SourceType),
+}
+
+fn detect_format(filename: &str, content: &[u8]) -> Result<StatementFormat> {
+    let first_line = content.split(|b| *b == b'\n').next().unwrap_or_default();
+    if String::from_utf8_lossy(first_line).contains(CANONICAL_HEADER) {
+        return Ok(StatementFormat::Canonical);
+    }
+    if String::from_utf8_lossy(content).contains("<OFX>") {
+        return Ok(StatementFormat::Bank(SourceType::Ofx));
+    }
+    if String::from_utf8_lossy(content).contains("<cfdi:Comprobante") {
+        return Ok(StatementFormat::Bank(SourceType::Cfdi));
+    }
+    // Exchanges name their exports generically (ledgers.csv), so look inside
+    if String::from_utf8_lossy(first_line).replace('"', "").starts_with("txid,refid,time") {
+        return Ok(StatementFormat::Bank(SourceType::Kraken));
+    }
+    if String::from_utf8_lossy(content).contains("Quantity Transacted") {
+        return Ok(StatementFormat::Bank(SourceType::Coinbase));
+    }
+    // So do payment apps (Download.CSV, transaction_history.csv)
+    let header = String::from_utf8_lossy(first_line).replace('"', "");
+    if header.contains(",Gross,Fee") {
+        return Ok(StatementFormat::Bank(SourceType::PayPal));
+    }
+    if String::from_utf8_lossy(content).contains("Amount (total)") {
+        return Ok(StatementFormat::Bank(SourceType::Venmo));
+    }
+
+    match detect_source(Path::new(filename)) {
+        Ok(source) => Ok(StatementFormat::Bank(source)),
+        // Stripe is the only JSON export we read
+        Err(_) if filename.to_lowercase().ends_with(".json") => Ok(StatementFormat::Bank(SourceType::Stripe)),
+        Err(e) => Err(e.context(
+            "name the file after its bank (bofa, apple, stripe, wise, scotia, bbva, banorte, coinbase, kraken, \
+             paypal, venmo) or upload OFX or CFDI XML",
+        )),
+    }
+}
+
+/// Amount text as banks print it: "-$855.94", "$2,000.00", "(12.00)"
+fn parse_amount(text: &str) -> Option<f64> {
+    let cleaned: String = text.chars().filter(|c| !matches!(c, '$' | ',' | ' ')).collect();
+    let amount = match cleaned.strip_prefix('(').and_then(|inner| inner.strip_suffix(')')) {
+        Some(inner) => inner.parse::<f64>().ok().map(|value| -value),
+        None => cleaned.parse::<f64>().ok(),
+    };
+    // "NaN", "inf" and "1e999" parse as floats but are no amount
+    amount.filter(|amount| amount.is_finite())
+}
+
+/// Turn a parser row into a transaction of `ledger_id`
+///
+/// Amounts are signed money-in positive (Apple Card exports charges as
+/// positive, so they are flipped). The source's TypeClassifier sets the type;
+/// a matching rule (or MCC) then overrides merchant, category and type.
+pub fn normalize_raw(raw: &RawTransaction, rules: &RuleEngine, ledger_id: &str) -> Result<Transaction> {
+    normalize_raw_at(raw, rules, ledger_id, Utc::now())
+}
+
+/// `normalize_raw` with the extraction time given (replays pass the recorded one)
+pub fn normalize_raw_at(
+    raw: &RawTransaction,
+    rules: &RuleEngine,
+    ledger_id: &str,
+    now: DateTime<Utc>,
+) -> Result<Transaction> {
+    let parser_version = parser_version_tag(raw.source_type.clone());
+    let mut log = vec![format!("parsed line {} of {} with {}", raw.line_number, raw.source_file, parser_version)];
+
+    let mut amount = parse_amount(&raw.amount).ok_or_else(|| anyhow!("Unreadable amount '{}'", raw.amount))?;
+    log.push(format!("amount '{}' read as {:.2}", raw.amount, amount));
+    if raw.source_type == SourceType::AppleCard {
+        amount = -amount;
+        log.push(format!("sign flipped to {:.2} (Apple Card exports charges as positive)", amount));
+    }
+    let transaction_type = get_type_classifier(raw.source_type.clone()).classify_type(&raw.description, amount);
+    log.push(format!("type {} from the {} classifier", transaction_type, raw.source_type.name()));
+
+    let mut tx = Transaction {
+        date: raw.date.clone(),
+        description: raw.description.clone(),
+        amount_original: raw.amount.clone(),
+        amount_numeric: amount,
+        transaction_type,
+        category: raw.category.clone().unwrap_or_else(|| "Unknown".to_string()),
+        merchant: raw.merchant.clone().unwrap_or_default(),
+        currency: raw.currency.clone().unwrap_or_else(|| "USD".to_string()),
+        account_name: raw.source_type.name().to_string(),
+        account_number: raw.account.clone().unwrap_or_default(),
+        bank: raw.source_type.name().to_string(),
+        source_file: raw.source_file.clone(),
+        line_number: raw.line_number.to_string(),
+        classification_notes: String::new(),
+        id: String::new(),
+        version: 0,
+        system_time: None,
+        valid_from: None,
+        valid_until: None,
+        previous_version_id: None,
+        ledger_id: ledger_id.to_string(),
+        metadata: HashMap::new(),
+    };
+    if let Some(mcc) = raw.mcc {
+        tx.metadata.insert("mcc".to_string(), serde_json::json!(mcc));
+    }
+    if let Some(external_id) = &raw.external_id {
+        tx.metadata.insert(EXTERNAL_ID_KEY.to_string(), serde_json::json!(external_id));
+    }
+    if let Some(posted_date) = &raw.posted_date {
+        tx.metadata.insert(POSTED_DATE_KEY.to_string(), serde_json::json!(posted_date));
+    }
+    if raw.pending {
+        tx.metadata.insert(PENDING_KEY.to_string(), serde_json::json!(true));
+    }
+    let quantity = raw.quantity.as_deref().and_then(|q| q.parse::<f64>().ok()).filter(|q| q.is_finite());
+    if let (Some(asset), Some(quantity)) = (&raw.asset, quantity) {
+        tx.metadata.insert(ASSET_KEY.to_string(), serde_json::json!(asset));
+        tx.metadata.insert(QUANTITY_KEY.to_string(), serde_json::json!(quantity));
+        if quantity != 0.0 {
+            tx.metadata.insert(ASSET_PRICE_KEY.to_string(), serde_json::json!((amount / quantity).abs()));
+        }
+    }
+    let masked = mask_transaction(&mut tx);
+    if !masked.is_empty() {
+        log.push(format!("masked {} before storage", describe_kinds(&masked)));
+    }
+
+    let parsed_by = FieldSource::Parser { parser: parser_version.clone() };
+    record_origin(&mut tx, TrackedField::Amount, parsed_by.clone(), now);
+    record_origin(&mut tx, TrackedField::Type, parsed_by.clone(), now);
+    if raw.merchant.is_some() {
+        record_origin(&mut tx, TrackedField::Merchant, parsed_by.clone(), now);
+    }
+    if raw.category.is_some() {
+        record_origin(&mut tx, TrackedField::Category, parsed_by, now);
+    }
+
+    let classified = rules.classify_transaction(&tx);
+    let inputs = ConfidenceInputs::for_row(raw.confidence, raw.category.is_some(), &tx, rules, &classified);
+    let (score, reasons) = combine_confidence(&inputs);
+    let by = match &classified.rule_id {
+        Some(rule_id) => format!("rule {}", rule_id),
+        None => "MCC".to_string(),
+    };
+    let rule_id = classified.rule_id.clone().unwrap_or_default();
+    if let Some(merchant) = classified.merchant {
+        log.push(format!("merchant '{}' by {}", merchant, by));
+        tx.merchant = merchant;
+        let source = FieldSource::for_classification(&tx, &rule_id, TrackedField::Merchant);
+        record_origin(&mut tx, TrackedField::Merchant, source, now);
+    }
+    if let Some(category) = classified.category {
+        log.push(format!("category '{}' by {}", category, by));
+        tx.category = category;
+        tx.classification_notes = by.clone();
+        let source = FieldSource::for_classification(&tx, &rule_id, TrackedField::Category);
+        record_origin(&mut tx, TrackedField::Category, source, now);
+    }
+    if let Some(transaction_type) = classified.transaction_type {
+        log.push(format!("type {} by {}", transaction_type, by));
+        tx.transaction_type = transaction_type;
+        let source = FieldSource::for_classification(&tx, &rule_id, TrackedField::Type);
+        record_origin(&mut tx, TrackedField::Type, source, now);
+    }
+    log.push(format!("confidence {:.2} ({})", score, reasons.join(", ")));
+    tx.set_confidence(score, reasons);
+
+    tx.init_temporal_fields();
+    if crate::location::annotate_location(&mut tx) {
+        let country = tx.get_metadata("country_code").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        log.push(format!("location {} from the description", country));
+    }
+    tx.set_provenance(now, &parser_version, log);
+
+    Ok(tx)
+}
+
+/// Everything an import runs with besides the file and the database
+pub struct ImportContext<'a> {
+    pub rules: &'a RuleEngine,
+    pub deduplication: &'a DeduplicationEngine,
+    /// Prices crypto rows without a fiat amount are valued at (asset → fiat)
+    pub prices: Option<&'a dyn FxRateProvider>,
+    /// Import time, used for provenance (replays pass the recorded one)
+    pub now: DateTime<Utc>,
+    /// Rows already stored are improved (blank fields filled, as a new
+    /// version) instead of skipped
+    pub upsert: bool,
+}
+
+impl<'a> ImportContext<'a> {
+    pub fn new(rules: &'a RuleEngine, deduplication: &'a DeduplicationEngine) -> Self {
+        ImportContext { rules, deduplication, prices: None, now: Utc::now(), upsert: false }
+    }
+
+    pub fn with_prices(mut self, prices: &'a dyn FxRateProvider) -> Self {
+        self.prices = Some(prices);
+        self
+    }
+
+    pub fn with_upsert(mut self, upsert: bool) -> Self {
+        self.upsert = upsert;
+        self
+    }
+}
+
+/// Parsed rows as (line, transaction or why not)
+pub(crate) type ParsedRows = Vec<(usize, Result<Transaction>)>;
+
+fn parse_rows(format: &StatementFormat, path: &Path, context: &ImportContext, ledger_id: &str) -> Result<ParsedRows> {
+    match format {
+        StatementFormat::Canonical => Ok(load_csv(path)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut tx)| {
+                tx.ledger_id = ledger_id.to_string();
+                mask_transaction(&mut tx);
+                (i + 2, Ok(tx))
+            })
+            .collect()),
+        StatementFormat::Bank(source) => Ok(get_parser(source.clone())
+            .parse(path)?
+            .iter()
+            .map(|raw| {
+                let row = value_in_fiat(raw, context.prices)
+                    .and_then(|raw| normalize_raw_at(&raw, context.rules, ledger_id, context.now));
+                (raw.line_number, row)
+            })
+            .collect()),
+    }
+}
+
+/// A crypto row the export gave no fiat amount for, valued at quantity ×
+/// the provider's price of the asset on the row's date
+fn value_in_fiat(raw: &RawTransaction, prices: Option<&dyn FxRateProvider>) -> Result<RawTransaction> {
+    let mut raw = raw.clone();
+    let (Some(asset), Some(quantity)) = (&raw.asset, &raw.quantity) else {
+        return Ok(raw);
+    };
+    if !raw.amount.trim().is_empty() {
+        return Ok(raw);
+    }
+
+    let quantity: f64 = quantity.parse().map_err(|_| anyhow!("Unreadable quantity '{}'", quantity))?;
+    let date = chrono::NaiveDate::parse_from_str(&raw.date, "%m/%d/%Y")
+        .map_err(|_| anyhow!("Unreadable date '{}'", raw.date))?;
+    let fiat = raw.currency.clone().unwrap_or_else(|| "USD".to_string());
+    let price = prices
+        .and_then(|prices| prices.rate(asset, &fiat, date))
+        .ok_or_else(|| anyhow!("No {}→{} price for {} to value {} {}", asset, fiat, date, quantity, asset))?;
+    raw.amount = format!("{:.2}", quantity * price);
+    Ok(raw)
+}
+
+/// A statement file parsed and normalized, not stored yet
+pub(crate) struct ParsedStatement {
+    /// The file name without any client path
+    pub filename: String,
+    pub format: StatementFormat,
+    pub rows: ParsedRows,
+}
+
+/// Detect the format of a file's content, then parse and normalize its rows
+pub(crate) fn parse_statement(
+    filename: &str,
+    content: &[u8],
+    context: &ImportContext,
+    ledger_id: &str,
+) -> Result<ParsedStatement> {
+    // Browsers may send a client path; only the name is kept
+    let filename = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .filter(|name| !name.trim().is_empty())
+        .ok_or_else(|| anyhow!("Upload has no file name"))?
+        .to_string();
+    let format = detect_format(&filename, content)?;
+
+    // Parsers read files; the original name keeps source_file meaningful
+    let dir = std::env::temp_dir().join(format!("trust-upload-{}", uuid::Uuid::new_v4()));
+    let path = dir.join(&filename);
+    let parsed = std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::write(&path, content))
+        .map_err(anyhow::Error::from)
+        .and_then(|_| parse_rows(&format, &path, context, ledger_id));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    Ok(ParsedStatement { filename, format, rows: parsed? })
+}
+
+/// Warn about inserted rows that look like (without being identical to) rows
+/// already in the ledger or elsewhere in the same upload
+fn flag_possible_duplicates(
+    engine: &DeduplicationEngine,
+    existing: &[Transaction],
+    inserted: &[(usize, Transaction)],
+    issues: &mut Vec<RowIssue>,
+) {
+    // Only rows with a similar amount can match; uploaded rows go first
+    let mut candidates: Vec<Transaction> = inserted.iter().map(|(_, tx)| tx.clone()).collect();
+    candidates.extend(
+        existing
+            .iter()
+            .filter(|old| {
+                inserted.iter().any(|(_, new)| {
+                    (old.amount_numeric.abs() - new.amount_numeric.abs()).abs() <= engine.fuzzy_amount_tolerance
+                })
+            })
+            .cloned(),
+    );
+
+    for found in engine.find_duplicates(&candidates) {
+        // tx1_index < tx2_index, so tx1 is the uploaded row when any is
+        let Some((line, _)) = inserted.get(found.tx1_index) else {
+            continue;
+        };
+        let other = match inserted.get(found.tx2_index) {
+            Some((other_line, _)) => format!("line {} of this upload", other_line),
+            None => {
+                let old = &candidates[found.tx2_index];
+                format!("{} {} ({})", old.date, old.merchant, old.source_file)
+            }
+        };
+        issues.push(RowIssue {
+            line: *line,
+            severity: Severity::Warning,
+            field: "duplicate".to_string(),
+            message: format!("Possible duplicate of {}: {}", other, found.reason),
+        });
+    }
+}
+
+/// Import one uploaded file into `ledger_id`
+///
+/// All rows are written in one SQLite transaction, together with the
+/// `import_session` event. Rows with quality issues are still imported (as the
+/// CLI does); the issues are reported so they can be fixed afterwards. Only
+/// rows dated after their account's close date are refused, unless the whole
+/// batch falls below the ledger's quality gate (see quarantine.rs).
+pub fn import_statement(
+    conn: &Connection,
+    filename: &str,
+    content: &[u8],
+    ledger_id: &str,
+    actor: &str,
+) -> Result<ImportSession> {
+    let rules = RuleEngine::from_database(conn)?;
+    let deduplication = DeduplicationEngine::new();
+    import_statement_with(conn, filename, content, ledger_id, actor, &ImportContext::new(&rules, &deduplication))
+}
+
+/// What makes two rows of one file the same row: the source's id when it
+/// has one, else the idempotency hash
+fn batch_key(tx: &Transaction) -> String {
+    match tx.metadata.get(EXTERNAL_ID_KEY).and_then(|id| id.as_str()) {
+        Some(external_id) => format!("id:{}", external_id),
+        None => tx.compute_idempotency_hash(),
+    }
+}
+
+/// Drop rows that repeat an earlier row of the same batch (re-downloads with
+/// overlapping ranges), counting them in `session.repeated`
+fn collapse_repeated_rows(rows: ParsedRows, session: &mut ImportSession) -> ParsedRows {
+    let mut first_line: HashMap<String, usize> = HashMap::new();
+    let mut kept = Vec::with_capacity(rows.len());
+    for (line, row) in rows {
+        if let Ok(tx) = &row {
+            if let Some(first) = first_line.get(&batch_key(tx)) {
+                session.repeated += 1;
+                session.issues.push(RowIssue {
+                    line,
+                    severity: Severity::Info,
+                    field: "repeated".to_string(),
+                    message: format!("Same row as line {} of this file; collapsed", first),
+                });
+                continue;
+            }
+            first_line.insert(batch_key(tx), line);
+        }
+        kept.push((line, row));
+    }
+    kept
+}
+
+// ============================================================================
+// UPSERT
+// ============================================================================
+
+/// Metadata written by the import itself; an upsert keeps the stored row's
+const IMPORT_METADATA_KEYS: &[&str] = &[
+    "extracted_at",
+    "parser_version",
+    "transformation_log",
+    FIELD_PROVENANCE_KEY,
+    IMPORT_SESSION_KEY,
+    SOURCE_HASH_KEY,
+    SOURCE_LINE_KEY,
+];
+
+/// The current stored row `tx` is a re-import of: same external id, else
+/// same idempotency hash, in the same ledger
+fn find_stored(conn: &Connection, tx: &Transaction) -> Result<Option<Transaction>> {
+    let by_external_id = match tx.metadata.get(EXTERNAL_ID_KEY).and_then(|id| id.as_str()) {
+        Some(external_id) => conn
+            .query_row(
+                "SELECT tx_uuid FROM transactions
+                 WHERE ledger_id = ?1 AND valid_until IS NULL AND json_extract(metadata, '$.external_id') = ?2",
+                params![tx.ledger_id, external_id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?,
+        None => None,
+    };
+    let tx_uuid = match by_external_id {
+        Some(tx_uuid) => Some(tx_uuid),
+        None => conn
+            .query_row(
+                "SELECT tx_uuid FROM transactions WHERE ledger_id = ?1 AND valid_until IS NULL AND idempotency_hash = ?2",
+                params![tx.ledger_id, tx.compute_idempotency_hash()],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?,
+    };
+    match tx_uuid {
+        Some(tx_uuid) => get_current_transaction(conn, &tx_uuid),
+        None => Ok(None),
+    }
+}
+
+/// Next version of `stored` with the fields it lacks taken from `incoming`,
+/// and the names of those fields (None when the re-import adds nothing)
+///
+/// Only blanks are filled: a value already stored, including any correction
+/// made since the first import, always wins.
+fn fill_missing_fields(stored: &Transaction, incoming: &Transaction, filename: &str) -> Option<(Transaction, Vec<String>)> {
+    let blank = |value: &str| value.trim().is_empty() || value == "Unknown";
+    let mut next = stored.clone();
+    let mut fields = Vec::new();
+
+    if blank(&stored.merchant) && !blank(&incoming.merchant) {
+        next.merchant = incoming.merchant.clone();
+        inherit_origins(&mut next, incoming, &[TrackedField::Merchant]);
+        fields.push("merchant".to_string());
+    }
+    if blank(&stored.category) && !blank(&incoming.category) {
+        next.category = incoming.category.clone();
+        next.classification_notes = incoming.classification_notes.clone();
+        inherit_origins(&mut next, incoming, &[TrackedField::Category]);
+        fields.push("category".to_string());
+    }
+    if blank(&stored.account_number) && !blank(&incoming.account_number) {
+        next.account_number = incoming.account_number.clone();
+        fields.push("account number".to_string());
+    }
+    for (key, value) in &incoming.metadata {
+        if !IMPORT_METADATA_KEYS.contains(&key.as_str()) && !stored.metadata.contains_key(key) {
+            next.metadata.insert(key.clone(), value.clone());
+            fields.push(key.clone());
+        }
+    }
+    if fields.is_empty() {
+        return None;
+    }
+
+    let next = next.next_version(Some(format!("Re-import of {} filled {}", filename, fields.join(", "))));
+    Some((next, fields))
+}
+
+/// `import_statement` with the caller's rules, duplicate detection and clock
+///
+/// When the ledger has `record_imports` set, the inputs and the outcome are
+/// stored so the session can be replayed later (see replay.rs).
+pub fn import_statement_with(
+    conn: &Connection,
+    filename: &str,
+    content: &[u8],
+    ledger_id: &str,
+    actor: &str,
+    context: &ImportContext,
+) -> Result<ImportSession> {
+    let ledger = require_ledger(conn, ledger_id)?;
+    let ParsedStatement { filename, format, rows } = parse_statement(filename, content, context, ledger_id)?;
+    let source = match &format {
+        StatementFormat::Canonical => "canonical CSV".to_string(),
+        StatementFormat::Bank(source) => source.name().to_string(),
+    };
+
+    let existing = ledger_transactions(conn, ledger_id)?;
+    let quality = DataQualityEngine::new().with_accounts(list_accounts(conn, ledger_id)?);
+
+    // The quality gate judges the batch before anything is written
+    let reports: Vec<_> = rows.iter().filter_map(|(_, row)| row.as_ref().ok()).map(|tx| quality.validate(tx)).collect();
+    let batch = (!reports.is_empty()).then(|| quality.batch_summary(&reports));
+    let gate = ImportGate::from_config(&ledger.config);
+    let quarantine = match (gate.policy, batch.as_ref().and_then(|batch| gate.shortfall(batch))) {
+        (ImportPolicy::Strict, Some(shortfall)) => {
+            return Err(anyhow!("{} rejected by the strict import policy: {}", filename, shortfall));
+        }
+        (ImportPolicy::Quarantine, Some(shortfall)) => Some(shortfall),
+        _ => None,
+    };
+
+    let mut session = ImportSession {
+        id: uuid::Uuid::new_v4().to_string(),
+        ledger_id: ledger_id.to_string(),
+        filename,
+        source,
+        rows: rows.len(),
+        inserted: 0,
+        duplicates: 0,
+        repeated: 0,
+        upsert: context.upsert,
+        updated: 0,
+        settled: 0,
+        failed: 0,
+        issues: Vec::new(),
+        imported_at: context.now,
+        quality: batch,
+        quarantine,
+    };
+    let settlement = SettlementMatcher::new();
+    let mut pending: Vec<Transaction> = existing.iter().filter(|tx| tx.is_pending()).cloned().collect();
+
+    let db_tx = conn.unchecked_transaction()?;
+    let source_hash = archive_source(&db_tx, &session.filename, content)?;
+    let rows = collapse_repeated_rows(rows, &mut session);
+    let mut inserted = Vec::new();
+    for (line, row) in rows {
+        let tx = match row {
+            Ok(mut tx) => {
+                tag_source(&mut tx, &source_hash, line);
+                tx.metadata.insert(IMPORT_SESSION_KEY.to_string(), serde_json::json!(session.id));
+                if session.quarantine.is_some() {
+                    tx.metadata.insert(QUARANTINE_KEY.to_string(), serde_json::json!(session.id));
+                }
+                let masked: Option<Vec<PiiKind>> =
+                    tx.get_metadata(PII_MASKED_KEY).and_then(|kinds| serde_json::from_value(kinds.clone()).ok());
+                if let Some(masked) = masked {
+                    session.issues.push(RowIssue {
+                        line,
+                        severity: Severity::Info,
+                        field: "description".to_string(),
+                        message: format!("Masked {} before storage", describe_kinds(&masked)),
+                    });
+                }
+                tx
+            }
+            Err(e) => {
+                session.failed += 1;
+                session.issues.push(RowIssue {
+                    line,
+                    severity: Severity::Critical,
+                    field: "row".to_string(),
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        let mut closed_account = false;
+        for issue in quality.validate(&tx).issues {
+            closed_account |= issue.field == "account_status" && issue.severity == Severity::Critical;
+            if issue.severity != Severity::Info {
+                session.issues.push(RowIssue { line, severity: issue.severity, field: issue.field, message: issue.issue });
+            }
+        }
+        // Closed accounts take nothing dated after their close date
+        if closed_account {
+            session.failed += 1;
+            continue;
+        }
+
+        // A settled row replaces the pending row it matches, and is a
+        // duplicate once it has (a quarantined batch touches no stored row)
+        if !tx.is_pending() && session.quarantine.is_none() {
+            if let Some(stored) = already_settled(&tx, &existing) {
+                session.duplicates += 1;
+                session.issues.push(RowIssue {
+                    line,
+                    severity: Severity::Info,
+                    field: "duplicate".to_string(),
+                    message: format!("Already settled into transaction {}", stored.id),
+                });
+                continue;
+            }
+            let found = settlement.find_pending(&tx, &pending).map(|found| found.id.clone());
+            if let Some(index) = found.and_then(|id| pending.iter().position(|candidate| candidate.id == id)) {
+                let stored = pending.remove(index);
+                settle_pending(&db_tx, &stored, &tx, actor)?;
+                session.settled += 1;
+                session.issues.push(RowIssue {
+                    line,
+                    severity: Severity::Info,
+                    field: "settled".to_string(),
+                    message: format!(
+                        "Settles pending transaction {} ({:.2} → {:.2})",
+                        stored.id, stored.amount_numeric, tx.amount_numeric
+                    ),
+                });
+                continue;
+            }
+        }
+
+        if context.upsert && session.quarantine.is_none() {
+            if let Some(stored) = find_stored(&db_tx, &tx)? {
+                match fill_missing_fields(&stored, &tx, &session.filename) {
+                    Some((next, fields)) => {
+                        insert_transaction_version(&db_tx, &next, actor)?;
+                        session.updated += 1;
+                        session.issues.push(RowIssue {
+                            line,
+                            severity: Severity::Info,
+                            field: "updated".to_string(),
+                            message: format!("Already imported; filled {}", fields.join(", ")),
+                        });
+                    }
+                    None => {
+                        session.duplicates += 1;
+                        session.issues.push(RowIssue {
+                            line,
+                            severity: Severity::Info,
+                            field: "duplicate".to_string(),
+                            message: "Already imported; nothing new".to_string(),
+                        });
+                    }
+                }
+                continue;
+            }
+        }
+
+        if insert_transaction_as(&db_tx, &tx, actor)? {
+            session.inserted += 1;
+            inserted.push((line, tx));
+        } else {
+            session.duplicates += 1;
+            session.issues.push(RowIssue {
+                line,
+                severity: Severity::Info,
+                field: "duplicate".to_string(),
+                message: "Already imported; skipped".to_string(),
+            });
+        }
+    }
+    flag_possible_duplicates(context.deduplication, &existing, &inserted, &mut session.issues);
+    session.issues.sort_by_key(|issue| issue.line);
+
+    let payload = ImportRecorded {
+        filename: session.filename.clone(),
+        source: session.source.clone(),
+        rows: session.rows,
+        inserted: session.inserted,
+        duplicates: session.duplicates,
+        repeated: session.repeated,
+        upsert: session.upsert,
+        updated: session.updated,
+        failed: session.failed,
+        issues: session.issues.len(),
+        settled: session.settled,
+        quarantined: session.quarantine.is_some(),
+    };
+    let event = Event::typed("import_session", "import", &session.id, &payload, actor)?.with_ledger(ledger_id);
+    insert_event(&db_tx, &event)?;
+    if ledger.config.record_imports {
+        let transactions: Vec<Transaction> = inserted.into_iter().map(|(_, tx)| tx).collect();
+        record_import(&db_tx, &session, content, context, actor, &ledger.config, &transactions)?;
+    }
+    db_tx.commit()?;
+
+    Ok(session)
+}
+
+// ============================================================================
+// DIRECTORY IMPORTS
+// ============================================================================
+
+/// What happened to one file of a directory import
+#[derive(Debug, Clone)]
+pub enum FileOutcome {
+    Imported(Box<ImportSession>),
+    /// Not a statement format this tool reads
+    Skipped(String),
+    /// A statement, but its import failed (nothing of it was stored)
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct FileImport {
+    pub path: PathBuf,
+    pub outcome: FileOutcome,
+}
+
+/// Every file of a directory import, in path order
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryImport {
+    pub files: Vec<FileImport>,
+}
+
+/// Counts over every imported file of a directory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DirectoryTotals {
+    pub imported: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub rows: usize,
+    pub inserted: usize,
+    pub duplicates: usize,
+    pub repeated: usize,
+    pub updated: usize,
+    pub settled: usize,
+    pub failed_rows: usize,
+    pub quarantined: usize,
+}
+
+impl DirectoryImport {
+    pub fn sessions(&self) -> impl Iterator<Item = &ImportSession> {
+        self.files.iter().filter_map(|file| match &file.outcome {
+            FileOutcome::Imported(session) => Some(session.as_ref()),
+            _ => None,
+        })
+    }
+
+    pub fn totals(&self) -> DirectoryTotals {
+        let mut totals = DirectoryTotals::default();
+        for file in &self.files {
+            match &file.outcome {
+                FileOutcome::Imported(session) => {
+                    totals.imported += 1;
+                    totals.rows += session.rows;
+                    totals.inserted += session.inserted;
+                    totals.duplicates += session.duplicates;
+                    totals.repeated += session.repeated;
+                    totals.updated += session.updated;
+                    totals.settled += session.settled;
+                    totals.failed_rows += session.failed;
+                    totals.quarantined += usize::from(session.quarantine.is_some());
+                }
+                FileOutcome::Skipped(_) => totals.skipped += 1,
+                FileOutcome::Failed(_) => totals.failed += 1,
+            }
+        }
+        totals
+    }
+}
+
+/// Files under `dir` (subdirectories included, hidden entries left out),
+/// sorted by path
+pub fn statement_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let entries = std::fs::read_dir(dir).map_err(|e| anyhow!("Failed to read {}: {}", dir.display(), e))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with('.')) {
+            continue;
+        }
+        if path.is_dir() {
+            files.extend(statement_files(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Import every statement file under `dir`, each as its own session (and
+/// database transaction), so one bad file doesn't hold back the others
+///
+/// Files whose format isn't detected are skipped; a file that fails to
+/// import is reported and the rest carry on.
+pub fn import_directory_with(
+    conn: &Connection,
+    dir: &Path,
+    ledger_id: &str,
+    actor: &str,
+    context: &ImportContext,
+) -> Result<DirectoryImport> {
+    require_ledger(conn, ledger_id)?;
+    let mut import = DirectoryImport::default();
+    for path in statement_files(dir)? {
+        let filename = path.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_string();
+        let outcome = match std::fs::read(&path) {
+            Err(e) => FileOutcome::Failed(format!("Failed to read: {}", e)),
+            Ok(content) => match detect_format(&filename, &content) {
+                Err(e) => FileOutcome::Skipped(e.root_cause().to_string()),
+                Ok(_) => match import_statement_with(conn, &filename, &content, ledger_id, actor, context) {
+                    Ok(session) => FileOutcome::Imported(Box::new(session)),
+                    Err(e) => FileOutcome::Failed(format!("{:#}", e)),
+                },
+            },
+        };
+        import.files.push(FileImport { path, outcome });
+    }
+    Ok(import)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{get_events_for_entity, setup_database};
+
+    const BOFA: &str = "Date,Description,Amount\n\
+        12/31/2024,\"Stripe, Des:transfer, Id:st-1\",\"-$855.94\"\n\
+        01/02/2025,STARBUCKS STORE 123,\"-$5.25\"\n\
+        01/03/2025,MYSTERY,\"n/a\"\n";
+
+    #[test]
+    fn test_import_statement_reports_rows_and_skips_reimport() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let session = import_statement(&conn, "C:\\fakepath\\bofa_jan.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+        assert_eq!(session.filename, "bofa_jan.csv");
+        assert_eq!(session.source, "Bank of America");
+        assert_eq!((session.rows, session.inserted, session.duplicates, session.failed), (3, 2, 0, 1));
+        assert!(session.issues.iter().any(|issue| issue.line == 4 && issue.severity == Severity::Critical));
+
+        let stored = ledger_transactions(&conn, "default").unwrap();
+        let coffee = stored.iter().find(|tx| tx.description.contains("STARBUCKS")).unwrap();
+        assert_eq!(coffee.amount_numeric, -5.25);
+        assert_eq!(coffee.bank, "Bank of America");
+        assert_eq!(coffee.line_number, "3");
+        let confidence = coffee.get_metadata("confidence_score").and_then(|score| score.as_f64()).unwrap();
+        assert!(confidence > 0.0 && confidence <= crate::confidence::DEFAULT_PARSER_CONFIDENCE);
+
+        let again = import_statement(&conn, "bofa_jan.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+        assert_eq!((again.inserted, again.duplicates), (0, 2));
+        assert_eq!(ledger_transactions(&conn, "default").unwrap().len(), 2);
+
+        let events = get_events_for_entity(&conn, "import", &again.id).unwrap();
+        assert_eq!(events[0].event_type, "import_session");
+
+        assert!(import_statement(&conn, "statement.csv", BOFA.as_bytes(), "default", "ana").is_err());
+        assert!(import_statement(&conn, "bofa_jan.csv", BOFA.as_bytes(), "nope", "ana").is_err());
+    }
+
+    #[test]
+    fn test_directory_import_reports_each_file_and_totals() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let dir = std::env::temp_dir().join(format!("statements-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("2025")).unwrap();
+        std::fs::write(dir.join("bofa_jan.csv"), BOFA).unwrap();
+        std::fs::write(dir.join("2025").join("bofa_copy.csv"), BOFA).unwrap();
+        std::fs::write(dir.join("notes.txt"), "call the bank").unwrap();
+        std::fs::write(dir.join("stripe_payouts.json"), "{ not json").unwrap();
+        std::fs::write(dir.join(".DS_Store"), "").unwrap();
+
+        let rules = RuleEngine::new();
+        let deduplication = DeduplicationEngine::new();
+        let context = ImportContext::new(&rules, &deduplication);
+        let import = import_directory_with(&conn, &dir, "default", "ana", &context).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<_> = import.files.iter().map(|f| f.path.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, ["bofa_copy.csv", "bofa_jan.csv", "notes.txt", "stripe_payouts.json"]);
+        assert!(matches!(import.files[2].outcome, FileOutcome::Skipped(_)));
+        assert!(matches!(import.files[3].outcome, FileOutcome::Failed(_)));
+
+        // The second copy of the statement only finds duplicates
+        let totals = import.totals();
+        assert_eq!((totals.imported, totals.skipped, totals.failed), (2, 1, 1));
+        assert_eq!((totals.rows, totals.inserted, totals.duplicates, totals.failed_rows), (6, 2, 2, 2));
+        assert_eq!(import.sessions().count(), 2);
+        assert_eq!(ledger_transactions(&conn, "default").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_apple_card_charges_are_negated_and_near_duplicates_flagged() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let csv = "Date,Description,Amount,Category,Merchant\n\
+            01/05/2025,UBER *EATS,12.00,Restaurants,Uber Eats\n\
+            01/06/2025,UBER *EATS,12.30,Restaurants,Uber Eats\n";
+
+        let session = import_statement(&conn, "apple_jan.csv", csv.as_bytes(), "default", "ana").unwrap();
+        assert_eq!(session.inserted, 2);
+        assert!(session.issues.iter().any(|issue| issue.line == 2 && issue.message.contains("line 3 of this upload")));
+        assert!(ledger_transactions(&conn, "default").unwrap().iter().all(|tx| tx.amount_numeric <= -12.0));
+    }
+
+    #[test]
+    fn test_card_and_account_numbers_are_masked_before_storage() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let csv = "Date,Description,Amount\n\
+            01/02/2025,PAYMENT CARD 4111 1111 1111 1111,\"-$25.00\"\n\
+            01/03/2025,TRANSFER TO ACCT# 004412345678,\"-$100.00\"\n\
+            01/04/2025,TRANSFER FROM ACCOUNT ENDING IN 5226,\"$40.00\"\n";
+
+        let session = import_statement(&conn, "bofa_jan.csv", csv.as_bytes(), "default", "ana").unwrap();
+        assert_eq!(session.inserted, 3);
+        let masked: Vec<_> = session.issues.iter().filter(|issue| issue.field == "description").collect();
+        assert_eq!(masked.len(), 2);
+        assert_eq!(masked[0].message, "Masked card number before storage");
+
+        let stored = ledger_transactions(&conn, "default").unwrap();
+        let descriptions: Vec<&str> = stored.iter().map(|tx| tx.description.as_str()).collect();
+        assert!(descriptions.contains(&"PAYMENT CARD ****1111"));
+        assert!(descriptions.contains(&"TRANSFER TO ACCT# ****5678"));
+        assert!(descriptions.contains(&"TRANSFER FROM ACCOUNT ENDING IN 5226"));
+        assert_eq!(stored.iter().filter(|tx| tx.get_metadata(PII_MASKED_KEY).is_some()).count(), 2);
+    }
+
+    #[test]
+    fn test_reimport_matches_rows_stored_before_masking() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        // A canonical export whose merchant carries the full card number
+        let csv = "Date,Description,Amount_Original,Amount_Numeric,Transaction_Type,Category,Merchant,Currency,\
+            Account_Name,Account_Number,Bank,Source_File,Line_Number,Classification_Notes\n\
+            01/02/2025,PAYMENT CARD 4111 1111 1111 1111,-25.00,-25.00,GASTO,Payments,CARD 4111 1111 1111 1111,\
+            USD,Checking,0001,BofA,bofa.csv,2,\n";
+
+        // Stored unmasked, as imports did before masking
+        let path = std::env::temp_dir().join(format!("premask-{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(&path, csv).unwrap();
+        let legacy = load_csv(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        crate::db::insert_transactions_as(&conn, &legacy, "ana").unwrap();
+
+        let again = import_statement(&conn, "export.csv", csv.as_bytes(), "default", "ana").unwrap();
+        assert_eq!((again.inserted, again.duplicates), (0, 1));
+        assert_eq!(ledger_transactions(&conn, "default").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_rows_repeated_within_a_file_are_collapsed_before_insert() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        // Two downloads with overlapping ranges pasted into one file
+        let csv = "Date,Description,Amount\n\
+            01/02/2025,STARBUCKS STORE 123,-5.25\n\
+            01/03/2025,SHELL OIL 5521,-40.00\n\
+            01/03/2025,SHELL OIL 5521,-40.00\n\
+            01/04/2025,AMAZON MKTPLACE,-12.00\n";
+        let session = import_statement(&conn, "bofa_jan.csv", csv.as_bytes(), "default", "ana").unwrap();
+        assert_eq!((session.rows, session.inserted, session.repeated, session.duplicates), (4, 3, 1, 0));
+        let issue = session.issues.iter().find(|issue| issue.field == "repeated").unwrap();
+        assert_eq!(issue.line, 4);
+        assert!(issue.message.contains("line 3"));
+
+        // The source's own ids decide when present: same id, same row
+        let stripe = r#"{"object":"list","data":[
+            {"id":"txn_1","amount":1000,"created":1735689600,"currency":"usd","description":"Payout","type":"payout"},
+            {"id":"txn_1","amount":1000,"created":1735689600,"currency":"usd","description":"Payout","type":"payout"},
+            {"id":"txn_2","amount":2500,"created":1735776000,"currency":"usd","description":"Charge","type":"charge"}]}"#;
+        let session = import_statement(&conn, "stripe.json", stripe.as_bytes(), "default", "ana").unwrap();
+        assert_eq!((session.inserted, session.repeated), (2, 1));
+        assert!(ledger_transactions(&conn, "default")
+            .unwrap()
+            .iter()
+            .any(|tx| tx.get_metadata(EXTERNAL_ID_KEY) == Some(&serde_json::json!("txn_2"))));
+    }
+
+    #[test]
+    fn test_upsert_fills_blank_fields_of_rows_already_imported() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let stripe = |description: &str| {
+            format!(
+                r#"{{"object":"list","data":[{{"id":"txn_1","amount":-4200,"created":1735689600,"currency":"usd","description":"{}","type":"charge"}}]}}"#,
+                description
+            )
+        };
+        import_statement(&conn, "stripe.json", stripe("").as_bytes(), "default", "ana").unwrap();
+        let original = ledger_transactions(&conn, "default").unwrap().remove(0);
+        assert!(original.merchant.is_empty());
+
+        // The richer export: skipped by a plain import, filled in by an upsert
+        let richer = stripe("Payment to Figma");
+        let skipped = import_statement(&conn, "stripe.json", richer.as_bytes(), "default", "ana").unwrap();
+        assert_eq!((skipped.inserted, skipped.updated), (1, 0), "hash differs, so a plain import adds a row");
+        conn.execute("DELETE FROM transactions WHERE tx_uuid != ?1", [&original.id]).unwrap();
+
+        let (rules, deduplication) = (RuleEngine::new(), DeduplicationEngine::new());
+        let context = ImportContext::new(&rules, &deduplication).with_upsert(true);
+        let session = import_statement_with(&conn, "stripe.json", richer.as_bytes(), "default", "ana", &context).unwrap();
+        assert_eq!((session.inserted, session.updated, session.duplicates), (0, 1, 0));
+        assert!(session.issues.iter().any(|issue| issue.field == "updated" && issue.message.contains("merchant")));
+
+        let current = get_current_transaction(&conn, &original.id).unwrap().unwrap();
+        assert_eq!(current.version, original.version + 1);
+        assert!(!current.merchant.is_empty());
+        assert_eq!(ledger_transactions(&conn, "default").unwrap().len(), 1);
+
+        let again = import_statement_with(&conn, "stripe.json", richer.as_bytes(), "default", "ana", &context).unwrap();
+        assert_eq!((again.updated, again.duplicates), (0, 1));
+    }
+
+    #[test]
+    fn test_kraken_crypto_rows_are_valued_from_prices() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let ledger = r#""txid","refid","time","type","subtype","aclass","asset","amount","fee","balance"
+"L1","T1","2025-01-15 10:00:00","trade","","currency","ZUSD","-100.0000","0.2600","0"
+"L2","T1","2025-01-15 10:00:00","trade","","currency","XXBT","0.0010000000","0","0.001"
+"L3","S1","2025-01-20 00:00:00","staking","","currency","DOT.S","0.5","0","0.5"
+"#;
+
+        // Without a DOT price the staking reward can't be valued
+        let prices = crate::fx::FixedRates::new().with_rate("BTC", "USD", 100_000.0);
+        let (rules, deduplication) = (RuleEngine::new(), DeduplicationEngine::new());
+        let context = ImportContext::new(&rules, &deduplication).with_prices(&prices);
+        let session =
+            import_statement_with(&conn, "ledgers.csv", ledger.as_bytes(), "default", "ana", &context).unwrap();
+        assert_eq!(session.source, "Kraken");
+        assert_eq!((session.inserted, session.failed), (2, 1));
+        assert!(session.issues.iter().any(|issue| issue.line == 4 && issue.message.contains("DOT→USD")));
+
+        let stored = ledger_transactions(&conn, "default").unwrap();
+        let btc = stored.iter().find(|tx| tx.get_metadata(ASSET_KEY) == Some(&serde_json::json!("BTC"))).unwrap();
+        assert_eq!((btc.amount_numeric, btc.transaction_type.as_str()), (100.0, "TRASPASO"));
+        assert_eq!(btc.get_metadata(QUANTITY_KEY), Some(&serde_json::json!(0.001)));
+        let usd = stored.iter().find(|tx| !tx.has_metadata(ASSET_KEY)).unwrap();
+        assert_eq!((usd.amount_numeric, usd.currency.as_str()), (-100.26, "USD"));
+    }
+}
@@ -0,0 +1,425 @@
+// 📬 Job Queue - Long operations submitted over the API, run by a worker
+//
+// Problem solved:
+// - Imports, duplicate scans and reconciliations can take minutes; run inside
+//   an HTTP request they hit client and proxy timeouts
+// - A job's outcome was lost if nobody was waiting on the connection
+//
+// Submitting stores a `job_queue` row and returns at once. The server's worker
+// claims queued rows oldest first and runs each as a `Job` (jobs.rs), copying
+// progress into the row while it runs; clients poll the row for status and
+// the JSON result. Jobs still `running` when the server stops are queued again
+// on the next start.
+
+use crate::explanations::explain_report;
+use crate::imports::import_statement;
+use crate::jobs::{ledger_transactions, scan_duplicates_job, Job, JobContext, JobOutcome, JobProgress};
+use crate::ledger::require_ledger;
+use crate::reconciliation::{ReconciliationEngine, StatementMetadata};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+/// How often the worker copies progress into the queue row
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Worker connections wait this long for the server's writes to finish
+pub const WORKER_BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+// ============================================================================
+// TYPES
+// ============================================================================
+
+/// What a queued job does
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobSpec {
+    /// Import an uploaded statement (bytes in the row's payload)
+    Import { filename: String },
+
+    /// Find duplicate clusters that still need a decision
+    DuplicateScan,
+
+    /// Reconcile one account's transactions from `from` to the statement date
+    Reconcile { statement: StatementMetadata, from: NaiveDate },
+}
+
+impl JobSpec {
+    pub fn describe(&self) -> String {
+        match self {
+            JobSpec::Import { filename } => format!("import {}", filename),
+            JobSpec::DuplicateScan => "duplicate scan".to_string(),
+            JobSpec::Reconcile { statement, .. } => {
+                format!("reconcile {} {}", statement.account_name, statement.statement_period)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuedJobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl QueuedJobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueuedJobStatus::Queued => "queued",
+            QueuedJobStatus::Running => "running",
+            QueuedJobStatus::Completed => "completed",
+            QueuedJobStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "queued" => Ok(QueuedJobStatus::Queued),
+            "running" => Ok(QueuedJobStatus::Running),
+            "completed" => Ok(QueuedJobStatus::Completed),
+            "failed" => Ok(QueuedJobStatus::Failed),
+            other => Err(anyhow!("Unknown job status '{}'", other)),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self, QueuedJobStatus::Completed | QueuedJobStatus::Failed)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedJob {
+    pub id: String,
+    pub ledger_id: String,
+    pub spec: JobSpec,
+    pub status: QueuedJobStatus,
+
+    pub progress_done: usize,
+    /// 0 while the amount of work is not known
+    pub progress_total: usize,
+    pub message: Option<String>,
+
+    /// JSON outcome of a completed job
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+// ============================================================================
+// QUEUE
+// ============================================================================
+
+/// Queue a job; `payload` carries uploaded bytes (imports)
+pub fn enqueue_job(
+    conn: &Connection,
+    spec: &JobSpec,
+    payload: Option<&[u8]>,
+    ledger_id: &str,
+    actor: &str,
+) -> Result<QueuedJob> {
+    require_ledger(conn, ledger_id)?;
+    if matches!(spec, JobSpec::Import { .. }) && payload.is_none() {
+        return Err(anyhow!("An import job needs the file content"));
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO job_queue (id, ledger_id, spec, payload, status, created_by, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            id,
+            ledger_id,
+            serde_json::to_string(spec)?,
+            payload,
+            QueuedJobStatus::Queued.as_str(),
+            actor,
+            Utc::now().to_rfc3339(),
+        ],
+    )?;
+
+    get_queued_job(conn, &id)?.ok_or_else(|| anyhow!("Job {} vanished after insert", id))
+}
+
+const JOB_COLUMNS: &str = "id, ledger_id, spec, status, progress_done, progress_total, message, result, error,
+     created_by, created_at, started_at, finished_at";
+
+pub fn get_queued_job(conn: &Connection, job_id: &str) -> Result<Option<QueuedJob>> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT {} FROM job_queue WHERE id = ?1", JOB_COLUMNS),
+            [job_id],
+            row_to_job,
+        )
+        .optional()?)
+}
+
+/// Most recent jobs first
+pub fn list_queued_jobs(conn: &Connection, limit: usize) -> Result<Vec<QueuedJob>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM job_queue ORDER BY created_at DESC LIMIT ?1",
+        JOB_COLUMNS
+    ))?;
+    let jobs = stmt
+        .query_map([limit as i64], row_to_job)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(jobs)
+}
+
+/// Mark the oldest queued job as running and return it with its payload
+pub fn claim_next_job(conn: &Connection) -> Result<Option<(QueuedJob, Option<Vec<u8>>)>> {
+    let db_tx = conn.unchecked_transaction()?;
+    let next: Option<(String, Option<Vec<u8>>)> = db_tx
+        .query_row(
+            "SELECT id, payload FROM job_queue WHERE status = 'queued' ORDER BY created_at ASC LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let Some((id, payload)) = next else {
+        return Ok(None);
+    };
+
+    db_tx.execute(
+        "UPDATE job_queue SET status = 'running', started_at = ?1 WHERE id = ?2",
+        params![Utc::now().to_rfc3339(), id],
+    )?;
+    db_tx.commit()?;
+
+    Ok(get_queued_job(conn, &id)?.map(|job| (job, payload)))
+}
+
+fn set_progress(conn: &Connection, job_id: &str, progress: &JobProgress) -> Result<()> {
+    conn.execute(
+        "UPDATE job_queue SET progress_done = ?1, progress_total = ?2, message = ?3 WHERE id = ?4",
+        params![progress.done as i64, progress.total as i64, progress.message, job_id],
+    )?;
+    Ok(())
+}
+
+/// Store a job's outcome; the payload is dropped once the job is done
+fn finish_job(conn: &Connection, job_id: &str, outcome: Result<serde_json::Value>) -> Result<()> {
+    let (status, result, error) = match outcome {
+        Ok(value) => (QueuedJobStatus::Completed, Some(serde_json::to_string(&value)?), None),
+        Err(e) => (QueuedJobStatus::Failed, None, Some(e.to_string())),
+    };
+    conn.execute(
+        "UPDATE job_queue SET status = ?1, result = ?2, error = ?3, finished_at = ?4, payload = NULL
+         WHERE id = ?5",
+        params![status.as_str(), result, error, Utc::now().to_rfc3339(), job_id],
+    )?;
+    Ok(())
+}
+
+/// Queue jobs again that were running when the server stopped; returns how many
+pub fn requeue_interrupted_jobs(conn: &Connection) -> Result<usize> {
+    Ok(conn.execute(
+        "UPDATE job_queue SET status = 'queued', started_at = NULL WHERE status = 'running'",
+        [],
+    )?)
+}
+
+// ============================================================================
+// WORKER
+// ============================================================================
+
+/// Open the database the way worker threads do
+pub fn open_worker_connection(db_path: &Path) -> Result<Connection> {
+    let conn = Connection::open(db_path)?;
+    conn.busy_timeout(WORKER_BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+/// Run one job body against `conn`
+fn execute(
+    ctx: &JobContext,
+    conn: &Connection,
+    spec: &JobSpec,
+    payload: Option<&[u8]>,
+    ledger_id: &str,
+    actor: &str,
+) -> Result<serde_json::Value> {
+    match spec {
+        JobSpec::Import { filename } => {
+            let content = payload.ok_or_else(|| anyhow!("Import job has no file content"))?;
+            ctx.progress(0, 1, format!("Importing {}", filename));
+            let session = import_statement(conn, filename, content, ledger_id, actor)?;
+            ctx.progress(1, 1, format!("Imported {}", filename));
+            Ok(serde_json::to_value(session)?)
+        }
+        JobSpec::DuplicateScan => {
+            let clusters = scan_duplicates_job(ctx, conn, ledger_id, Utc::now())?;
+            Ok(serde_json::to_value(clusters)?)
+        }
+        JobSpec::Reconcile { statement, from } => {
+            ctx.progress(0, 0, "Loading transactions");
+            let basis = require_ledger(conn, ledger_id)?.config.date_basis.unwrap_or_default();
+            let ledger = ledger_transactions(conn, ledger_id)?;
+            let transactions = ReconciliationEngine::statement_transactions(&ledger, statement, *from, basis);
+            ctx.check_cancelled()?;
+
+            let mut report = ReconciliationEngine::new().reconcile(&transactions, statement);
+            explain_report(&mut report, &ledger, *from, basis);
+            ctx.progress(transactions.len(), transactions.len(), report.summary());
+            Ok(serde_json::to_value(report)?)
+        }
+    }
+}
+
+/// Claim and run the oldest queued job, if any; returns it once finished
+///
+/// The body runs on its own thread with its own connection to `db_path`;
+/// `conn` is only used for the queue row.
+pub fn run_next_job(conn: &Connection, db_path: &Path) -> Result<Option<QueuedJob>> {
+    let Some((job, payload)) = claim_next_job(conn)? else {
+        return Ok(None);
+    };
+
+    let spec = job.spec.clone();
+    let ledger_id = job.ledger_id.clone();
+    let actor = job.created_by.clone();
+    let path = db_path.to_path_buf();
+    let mut running = Job::spawn(&spec.describe(), move |ctx| {
+        let conn = open_worker_connection(&path)?;
+        execute(ctx, &conn, &spec, payload.as_deref(), &ledger_id, &actor)
+    });
+
+    let mut reported: Option<JobProgress> = None;
+    let outcome = loop {
+        let finished = running.poll();
+        if let Some(progress) = running.progress() {
+            if reported.as_ref() != Some(progress) {
+                set_progress(conn, &job.id, progress)?;
+                reported = Some(progress.clone());
+            }
+        }
+        match finished {
+            Some(outcome) => break outcome,
+            None => std::thread::sleep(PROGRESS_INTERVAL),
+        }
+    };
+
+    let outcome = match outcome {
+        JobOutcome::Completed(value) => Ok(value),
+        JobOutcome::Cancelled => Err(anyhow!("{} was cancelled", running.name)),
+        JobOutcome::Failed(e) => Err(e),
+    };
+    finish_job(conn, &job.id, outcome)?;
+    get_queued_job(conn, &job.id)
+}
+
+fn row_to_job(row: &rusqlite::Row) -> rusqlite::Result<QueuedJob> {
+    fn parse_time(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now())
+    }
+
+    let spec: String = row.get(2)?;
+    let status: String = row.get(3)?;
+    let result: Option<String> = row.get(7)?;
+    let created_at: String = row.get(10)?;
+    let started_at: Option<String> = row.get(11)?;
+    let finished_at: Option<String> = row.get(12)?;
+
+    Ok(QueuedJob {
+        id: row.get(0)?,
+        ledger_id: row.get(1)?,
+        spec: serde_json::from_str(&spec).unwrap_or(JobSpec::DuplicateScan),
+        status: QueuedJobStatus::parse(&status).unwrap_or(QueuedJobStatus::Failed),
+        progress_done: row.get::<_, i64>(4)? as usize,
+        progress_total: row.get::<_, i64>(5)? as usize,
+        message: row.get(6)?,
+        result: result.and_then(|json| serde_json::from_str(&json).ok()),
+        error: row.get(8)?,
+        created_by: row.get(9)?,
+        created_at: parse_time(&created_at),
+        started_at: started_at.as_deref().map(parse_time),
+        finished_at: finished_at.as_deref().map(parse_time),
+    })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::setup_database;
+
+    fn temp_database() -> (std::path::PathBuf, Connection) {
+        let path = std::env::temp_dir().join(format!("job-queue-{}.db", uuid::Uuid::new_v4()));
+        let conn = open_worker_connection(&path).unwrap();
+        setup_database(&conn).unwrap();
+        (path, conn)
+    }
+
+    const CSV: &str = "Date,Description,Amount_Original,Amount_Numeric,Transaction_Type,Category,Merchant,Currency,Account_Name,Account_Number,Bank,Source_File,Line_Number,Classification_Notes
+01/05/2025,STARBUCKS,-5.00,-5.00,GASTO,Restaurants,Starbucks,USD,Apple Card,0001,AppleCard,a.csv,2,
+01/20/2025,PAYROLL,100.00,100.00,INGRESO,Income,Acme,USD,Apple Card,0001,AppleCard,a.csv,3,
+";
+
+    #[test]
+    fn test_worker_runs_jobs_oldest_first_and_stores_results() {
+        let (path, conn) = temp_database();
+        let import = JobSpec::Import { filename: "a.csv".to_string() };
+        let queued = enqueue_job(&conn, &import, Some(CSV.as_bytes()), "default", "ana").unwrap();
+        assert_eq!(queued.status, QueuedJobStatus::Queued);
+        let statement = StatementMetadata {
+            account_name: "Apple Card".to_string(),
+            statement_period: "2025-01".to_string(),
+            opening_balance: 0.0,
+            closing_balance: 95.0,
+            statement_date: NaiveDate::from_ymd_opt(2025, 1, 31).unwrap(),
+            declared_count: None,
+            declared_total: None,
+        };
+        let reconcile = JobSpec::Reconcile { statement, from: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap() };
+        enqueue_job(&conn, &reconcile, None, "default", "ana").unwrap();
+
+        let imported = run_next_job(&conn, &path).unwrap().unwrap();
+        assert_eq!(imported.id, queued.id);
+        assert_eq!(imported.status, QueuedJobStatus::Completed);
+        assert_eq!(imported.result.as_ref().unwrap()["inserted"], 2);
+        assert_eq!((imported.progress_done, imported.progress_total), (1, 1));
+
+        let reconciled = run_next_job(&conn, &path).unwrap().unwrap();
+        assert_eq!(reconciled.status, QueuedJobStatus::Completed, "{:?}", reconciled.error);
+        assert_eq!(reconciled.result.as_ref().unwrap()["transaction_count"], 2);
+        assert!(run_next_job(&conn, &path).unwrap().is_none());
+        assert_eq!(list_queued_jobs(&conn, 10).unwrap().len(), 2);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_failed_and_interrupted_jobs() {
+        let (path, conn) = temp_database();
+        assert!(enqueue_job(&conn, &JobSpec::DuplicateScan, None, "no-such-ledger", "ana").is_err());
+        assert!(enqueue_job(&conn, &JobSpec::Import { filename: "a.csv".to_string() }, None, "default", "ana").is_err());
+
+        let garbage = JobSpec::Import { filename: "notes.txt".to_string() };
+        enqueue_job(&conn, &garbage, Some(b"not a statement"), "default", "ana").unwrap();
+        let failed = run_next_job(&conn, &path).unwrap().unwrap();
+        assert_eq!(failed.status, QueuedJobStatus::Failed);
+        assert!(failed.error.is_some());
+
+        let scan = enqueue_job(&conn, &JobSpec::DuplicateScan, None, "default", "ana").unwrap();
+        claim_next_job(&conn).unwrap().unwrap();
+        assert_eq!(get_queued_job(&conn, &scan.id).unwrap().unwrap().status, QueuedJobStatus::Running);
+        assert_eq!(requeue_interrupted_jobs(&conn).unwrap(), 1);
+        assert_eq!(run_next_job(&conn, &path).unwrap().unwrap().status, QueuedJobStatus::Completed);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,328 @@
+// ⏳ Jobs - Long operations on a worker thread, with progress and cancel
+//
+// Problem solved:
+// - Imports and duplicate scans ran on the TUI's event loop, so the screen
+//   froze (no redraw, no keys) until they finished
+// - A scan started by mistake could only be stopped by killing the process
+//
+// `Job::spawn` runs the work on its own thread. The work reports progress and
+// checks for cancellation through a `JobContext`; the UI drains progress every
+// frame and collects the outcome once the thread is done. Job bodies open their
+// own Connection (rusqlite connections are not shared across threads).
+
+use crate::db::{get_active_transactions, insert_transaction_as, load_csv, Transaction};
+use crate::deduplication::DeduplicationEngine;
+use crate::duplicates::{find_duplicate_clusters_with_progress, retain_pending, DuplicateCluster};
+use crate::pii::mask_transaction;
+use crate::query::TransactionFilter;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Returned by job bodies that stopped because the job was cancelled
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobProgress {
+    pub done: usize,
+    /// 0 when the amount of work is not known yet
+    pub total: usize,
+    pub message: String,
+}
+
+impl JobProgress {
+    /// Completed fraction (0.0 while the total is unknown)
+    pub fn ratio(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            (self.done as f64 / self.total as f64).clamp(0.0, 1.0)
+        }
+    }
+}
+
+/// Handed to the job body: report progress, check for cancellation
+pub struct JobContext {
+    progress: Sender<JobProgress>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl JobContext {
+    pub fn progress(&self, done: usize, total: usize, message: impl Into<String>) {
+        // The receiver only goes away when the UI quit; nothing to report to then
+        let _ = self.progress.send(JobProgress { done, total, message: message.into() });
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// `Err(Cancelled)` once cancel was requested (use with `?` between steps)
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum JobOutcome<T> {
+    Completed(T),
+    Cancelled,
+    Failed(anyhow::Error),
+}
+
+/// A running job
+pub struct Job<T> {
+    pub name: String,
+    started: Instant,
+    latest: Option<JobProgress>,
+    progress: Receiver<JobProgress>,
+    cancelled: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Result<T>>>,
+}
+
+impl<T: Send + 'static> Job<T> {
+    pub fn spawn<F>(name: &str, work: F) -> Self
+    where
+        F: FnOnce(&JobContext) -> Result<T> + Send + 'static,
+    {
+        let (sender, receiver) = channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let context = JobContext { progress: sender, cancelled: Arc::clone(&cancelled) };
+        let handle = std::thread::spawn(move || work(&context));
+
+        Job {
+            name: name.to_string(),
+            started: Instant::now(),
+            latest: None,
+            progress: receiver,
+            cancelled,
+            handle: Some(handle),
+        }
+    }
+
+    /// Ask the job to stop at its next checkpoint
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelling(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Latest progress received by the last `poll`/`join`
+    pub fn progress(&self) -> Option<&JobProgress> {
+        self.latest.as_ref()
+    }
+
+    fn drain_progress(&mut self) {
+        while let Ok(update) = self.progress.try_recv() {
+            self.latest = Some(update);
+        }
+    }
+
+    /// Take in progress updates; the outcome once the worker has finished
+    pub fn poll(&mut self) -> Option<JobOutcome<T>> {
+        self.drain_progress();
+        if !self.handle.as_ref().is_some_and(|handle| handle.is_finished()) {
+            return None;
+        }
+        Some(self.join())
+    }
+
+    /// Block until the worker is done
+    pub fn join(&mut self) -> JobOutcome<T> {
+        let handle = self.handle.take();
+        let outcome = self.join_handle(handle);
+        self.drain_progress();
+        outcome
+    }
+
+    fn join_handle(&self, handle: Option<JoinHandle<Result<T>>>) -> JobOutcome<T> {
+        let Some(handle) = handle else {
+            return JobOutcome::Failed(anyhow!("{} already finished", self.name));
+        };
+        match handle.join() {
+            Ok(Ok(value)) => JobOutcome::Completed(value),
+            Ok(Err(e)) if e.is::<Cancelled>() => JobOutcome::Cancelled,
+            Ok(Err(e)) => JobOutcome::Failed(e),
+            Err(_) => JobOutcome::Failed(anyhow!("{} panicked", self.name)),
+        }
+    }
+}
+
+// ============================================================================
+// JOB BODIES
+// ============================================================================
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CsvImportSummary {
+    pub inserted: usize,
+    pub skipped: usize,
+}
+
+/// Import a CSV into a ledger, one row at a time
+///
+/// Card, IBAN and account numbers in descriptions are masked before storage,
+/// as on every other import path. Runs in a single SQLite transaction: a
+/// cancelled import leaves nothing behind.
+pub fn import_csv_job(
+    ctx: &JobContext,
+    conn: &Connection,
+    csv_path: &Path,
+    ledger_id: &str,
+    actor: &str,
+) -> Result<CsvImportSummary> {
+    ctx.progress(0, 0, format!("Reading {}", csv_path.display()));
+    let mut transactions = load_csv(csv_path)?;
+    let total = transactions.len();
+
+    let db_tx = conn.unchecked_transaction()?;
+    let mut summary = CsvImportSummary::default();
+    for (i, tx) in transactions.iter_mut().enumerate() {
+        ctx.check_cancelled()?;
+        tx.ledger_id = ledger_id.to_string();
+        mask_transaction(tx);
+        if insert_transaction_as(&db_tx, tx, actor)? {
+            summary.inserted += 1;
+        } else {
+            summary.skipped += 1;
+        }
+        ctx.progress(i + 1, total, format!("Importing {}", csv_path.display()));
+    }
+    ctx.check_cancelled()?;
+    db_tx.commit()?;
+
+    Ok(summary)
+}
+
+/// Active transactions of a ledger (reloaded after a job changed the database)
+pub fn ledger_transactions(conn: &Connection, ledger_id: &str) -> Result<Vec<Transaction>> {
+    Ok(TransactionFilter::new().in_ledger(ledger_id).apply(&get_active_transactions(conn)?))
+}
+
+/// Find duplicate clusters in a ledger that still need a decision
+pub fn scan_duplicates_job(
+    ctx: &JobContext,
+    conn: &Connection,
+    ledger_id: &str,
+    now: DateTime<Utc>,
+) -> Result<Vec<DuplicateCluster>> {
+    ctx.progress(0, 0, "Loading transactions");
+    let transactions = ledger_transactions(conn, ledger_id)?;
+    let total = transactions.len();
+
+    let clusters = find_duplicate_clusters_with_progress(&DeduplicationEngine::new(), &transactions, |done| {
+        ctx.progress(done, total, "Comparing transactions");
+        !ctx.is_cancelled()
+    })
+    .ok_or(Cancelled)?;
+
+    ctx.progress(total, total, "Checking earlier decisions");
+    retain_pending(conn, clusters, now)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::setup_database;
+    use std::io::Write;
+
+    #[test]
+    fn test_job_reports_progress_and_completes() {
+        let mut job = Job::spawn("count", |ctx| {
+            for i in 1..=3 {
+                ctx.progress(i, 3, "counting");
+            }
+            Ok(42)
+        });
+
+        match job.join() {
+            JobOutcome::Completed(value) => assert_eq!(value, 42),
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+        let progress = job.progress().unwrap();
+        assert_eq!((progress.done, progress.total), (3, 3));
+        assert_eq!(progress.ratio(), 1.0);
+    }
+
+    #[test]
+    fn test_cancelled_job_stops_at_checkpoint() {
+        let mut job: Job<()> = Job::spawn("forever", |ctx| loop {
+            ctx.check_cancelled()?;
+            std::thread::sleep(Duration::from_millis(1));
+        });
+        job.cancel();
+        assert!(matches!(job.join(), JobOutcome::Cancelled));
+    }
+
+    #[test]
+    fn test_import_job_inserts_and_skips_duplicates() {
+        let path = std::env::temp_dir().join(format!("jobs-import-{}.csv", uuid::Uuid::new_v4()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(file, "Date,Description,Amount_Original,Amount_Numeric,Transaction_Type,Category,Merchant,Currency,Account_Name,Account_Number,Bank,Source_File,Line_Number,Classification_Notes").unwrap();
+        writeln!(file, "01/05/2025,STARBUCKS,-5.00,-5.00,GASTO,Restaurants,Starbucks,USD,Apple Card,0001,AppleCard,a.csv,2,").unwrap();
+        writeln!(file, "01/06/2025,UBER,-12.00,-12.00,GASTO,Transport,Uber,USD,Apple Card,0001,AppleCard,a.csv,3,").unwrap();
+        drop(file);
+
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let (sender, _receiver) = channel();
+        let ctx = JobContext { progress: sender, cancelled: Arc::new(AtomicBool::new(false)) };
+        let first = import_csv_job(&ctx, &conn, &path, "default", "ana").unwrap();
+        let second = import_csv_job(&ctx, &conn, &path, "default", "ana").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(first, CsvImportSummary { inserted: 2, skipped: 0 });
+        assert_eq!(second, CsvImportSummary { inserted: 0, skipped: 2 });
+        assert_eq!(ledger_transactions(&conn, "default").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_import_job_masks_card_numbers() {
+        let path = std::env::temp_dir().join(format!("jobs-import-{}.csv", uuid::Uuid::new_v4()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(file, "Date,Description,Amount_Original,Amount_Numeric,Transaction_Type,Category,Merchant,Currency,Account_Name,Account_Number,Bank,Source_File,Line_Number,Classification_Notes").unwrap();
+        writeln!(file, "01/05/2025,PAYMENT FROM CARD 4111 1111 1111 1111,-50.00,-50.00,GASTO,Payments,Card Payment,USD,Apple Card,0001,AppleCard,a.csv,2,").unwrap();
+        drop(file);
+
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let (sender, _receiver) = channel();
+        let ctx = JobContext { progress: sender, cancelled: Arc::new(AtomicBool::new(false)) };
+        import_csv_job(&ctx, &conn, &path, "default", "ana").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let stored = ledger_transactions(&conn, "default").unwrap();
+        assert!(stored[0].description.ends_with("****1111"), "{}", stored[0].description);
+        assert!(!stored[0].description.contains("4111 1111"));
+    }
+}
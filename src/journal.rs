@@ -0,0 +1,416 @@
+// 📒 Journal - Double-entry projection for accountants
+//
+// Problem solved:
+// - The ledger stores single-sided rows (an amount on a bank account, with a
+//   type and category); accountants and their tools (Ledger, hledger,
+//   Beancount) expect balanced debit/credit postings
+//
+// `journal_entries` derives one entry per active transaction: the amount
+// posted to the bank account ("Assets:..." or, for credit cards,
+// "Liabilities:...") and the opposite amount to the account its type and
+// category stand for ("Expenses:Dining", "Income:Salary", "Equity:Transfers"
+// for both legs of a transfer, "Equity:Opening-Balances"). Every entry is
+// checked to balance per currency before `render_journal` writes it out.
+// Categories and accounts mapped in the chart of accounts (chart_of_accounts.rs)
+// post to the chart's account instead, with its code. Nothing is stored: the
+// journal is recomputed from the rows each time.
+
+use crate::accounts::{account_of, OPENING_BALANCE_FLAG};
+use crate::chart_of_accounts::{ChartMapping, ChartOfAccounts, MappedFrom};
+use crate::db::Transaction;
+use crate::entities::{Account, AccountType};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+/// Clearing account both legs of a transfer (and card payments) post to
+pub const TRANSFERS_ACCOUNT: &str = "Equity:Transfers";
+pub const OPENING_BALANCES_ACCOUNT: &str = "Equity:Opening-Balances";
+
+// ============================================================================
+// ENTRIES
+// ============================================================================
+
+/// One side of an entry: positive is a debit, negative a credit
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Posting {
+    pub account: String,
+    pub amount: f64,
+    pub currency: String,
+    /// Chart of accounts code, when the account is mapped
+    pub code: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JournalEntry {
+    pub date: NaiveDate,
+    pub tx_id: String,
+    pub payee: String,
+    pub narration: String,
+    /// Still pending at the source (flagged `!` in the exports)
+    pub pending: bool,
+    pub postings: Vec<Posting>,
+}
+
+impl JournalEntry {
+    /// Net per currency, cents that don't cancel out (empty when balanced)
+    pub fn imbalance(&self) -> BTreeMap<&str, f64> {
+        let mut cents: BTreeMap<&str, i64> = BTreeMap::new();
+        for posting in &self.postings {
+            *cents.entry(posting.currency.as_str()).or_default() += (posting.amount * 100.0).round() as i64;
+        }
+        cents
+            .into_iter()
+            .filter(|(_, net)| *net != 0)
+            .map(|(currency, net)| (currency, net as f64 / 100.0))
+            .collect()
+    }
+
+    pub fn is_balanced(&self) -> bool {
+        self.postings.len() >= 2 && self.imbalance().is_empty()
+    }
+}
+
+/// An account name component: words capitalised and joined by '-', only
+/// letters and digits kept (what Beancount accepts; Ledger takes it as is)
+fn component(name: &str) -> String {
+    let words: Vec<String> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+        })
+        .collect();
+    if words.is_empty() { "Unknown".to_string() } else { words.join("-") }
+}
+
+fn charted(mapping: &ChartMapping) -> (String, Option<String>) {
+    (format!("{}:{}", mapping.account_type.root(), component(&mapping.name)), Some(mapping.code.clone()))
+}
+
+/// The account the row's money sits in, with its code when mapped
+fn balance_account(tx: &Transaction, accounts: &[Account], chart: &ChartOfAccounts) -> (String, Option<String>) {
+    if let Some(mapping) = chart.lookup(MappedFrom::Account, &tx.account_name) {
+        return charted(mapping);
+    }
+    let root = match account_of(accounts, tx).map(|account| &account.account_type) {
+        Some(AccountType::Credit) => "Liabilities",
+        _ => "Assets",
+    };
+    let account = [&tx.account_name, &tx.account_number]
+        .into_iter()
+        .find(|name| !name.trim().is_empty())
+        .map_or("Main", |name| name.as_str());
+    (format!("{}:{}:{}", root, component(&tx.bank), component(account)), None)
+}
+
+/// The account on the other side: what the money was for or came from
+fn counter_account(tx: &Transaction, chart: &ChartOfAccounts) -> (String, Option<String>) {
+    let category = if tx.category.trim().is_empty() { "Uncategorized" } else { tx.category.as_str() };
+    if tx.has_metadata(OPENING_BALANCE_FLAG) {
+        return (OPENING_BALANCES_ACCOUNT.to_string(), None);
+    }
+    let root = match tx.transaction_type.as_str() {
+        "TRASPASO" | "PAGO_TARJETA" => return (TRANSFERS_ACCOUNT.to_string(), None),
+        "INGRESO" => "Income",
+        "GASTO" => "Expenses",
+        _ if tx.amount_numeric >= 0.0 => "Income",
+        _ => "Expenses",
+    };
+    match chart.lookup(MappedFrom::Category, category) {
+        Some(mapping) => charted(mapping),
+        None => (format!("{}:{}", root, component(category)), None),
+    }
+}
+
+fn entry(tx: &Transaction, accounts: &[Account], chart: &ChartOfAccounts) -> Option<JournalEntry> {
+    let amount = (tx.amount_numeric * 100.0).round() / 100.0;
+    let posting = |(account, code): (String, Option<String>), amount: f64| Posting {
+        account,
+        amount,
+        currency: tx.currency.clone(),
+        code,
+    };
+    Some(JournalEntry {
+        date: tx.parsed_date()?,
+        tx_id: tx.id.clone(),
+        payee: if tx.merchant.trim().is_empty() { tx.description.clone() } else { tx.merchant.clone() },
+        narration: tx.description.clone(),
+        pending: tx.is_pending(),
+        postings: vec![
+            posting(balance_account(tx, accounts, chart), amount),
+            posting(counter_account(tx, chart), -amount),
+        ],
+    })
+}
+
+/// Double-entry view of the active transactions, oldest first
+///
+/// `accounts` tell credit cards (liabilities) from the rest; `chart` maps
+/// categories and accounts to the chart of accounts. Fails, naming the rows,
+/// when a row has no readable date or an entry doesn't balance.
+pub fn journal_entries(
+    transactions: &[Transaction],
+    accounts: &[Account],
+    chart: &ChartOfAccounts,
+) -> Result<Vec<JournalEntry>> {
+    let mut entries = Vec::new();
+    let mut problems = Vec::new();
+    for tx in transactions.iter().filter(|tx| tx.is_active()) {
+        match entry(tx, accounts, chart) {
+            Some(entry) if entry.is_balanced() => entries.push(entry),
+            Some(entry) => problems.push(format!("{} doesn't balance: {:?}", tx.id, entry.imbalance())),
+            None => problems.push(format!("{} has no readable date ({})", tx.id, tx.date)),
+        }
+    }
+    if !problems.is_empty() {
+        return Err(anyhow!("Journal not exported:\n  {}", problems.join("\n  ")));
+    }
+    entries.sort_by(|a, b| (a.date, &a.tx_id).cmp(&(b.date, &b.tx_id)));
+    Ok(entries)
+}
+
+// ============================================================================
+// EXPORT
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalFormat {
+    /// Ledger / hledger plain-text journal
+    Ledger,
+    Beancount,
+    /// QuickBooks Desktop IIF general journal
+    QuickBooks,
+}
+
+impl JournalFormat {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "ledger" | "hledger" => Ok(JournalFormat::Ledger),
+            "beancount" => Ok(JournalFormat::Beancount),
+            "quickbooks" | "iif" => Ok(JournalFormat::QuickBooks),
+            other => Err(anyhow!("Unknown journal format: {} (ledger, beancount, quickbooks)", other)),
+        }
+    }
+}
+
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Every account the entries post to, with its code when mapped
+fn accounts_used(entries: &[JournalEntry]) -> BTreeMap<&str, Option<&str>> {
+    entries
+        .iter()
+        .flat_map(|entry| &entry.postings)
+        .map(|posting| (posting.account.as_str(), posting.code.as_deref()))
+        .collect()
+}
+
+/// IIF text field: no tabs or line breaks
+fn iif_field(text: &str) -> String {
+    text.replace(['\t', '\n', '\r'], " ")
+}
+
+/// QuickBooks account name and type: the journal name without its root
+/// ("Expenses:Meals" → "Meals", EXP)
+pub(crate) fn iif_account(account: &str) -> (&str, &'static str) {
+    let (root, name) = account.split_once(':').unwrap_or(("", account));
+    let kind = match root {
+        "Assets" => "BANK",
+        "Liabilities" => "CCARD",
+        "Equity" => "EQUITY",
+        "Income" => "INC",
+        _ => "EXP",
+    };
+    (name, kind)
+}
+
+fn render_quickbooks(entries: &[JournalEntry]) -> String {
+    let mut out = String::from("!ACCNT\tNAME\tACCNTTYPE\tACCNUM\n");
+    for (account, code) in accounts_used(entries) {
+        let (name, kind) = iif_account(account);
+        let _ = writeln!(out, "ACCNT\t{}\t{}\t{}", name, kind, code.unwrap_or(""));
+    }
+    out.push_str("!TRNS\tTRNSTYPE\tDATE\tACCNT\tNAME\tAMOUNT\tMEMO\n");
+    out.push_str("!SPL\tTRNSTYPE\tDATE\tACCNT\tNAME\tAMOUNT\tMEMO\n");
+    out.push_str("!ENDTRNS\n");
+    for entry in entries {
+        for (i, posting) in entry.postings.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "{}\tGENERAL JOURNAL\t{}\t{}\t{}\t{:.2}\t{}",
+                if i == 0 { "TRNS" } else { "SPL" },
+                entry.date.format("%m/%d/%Y"),
+                iif_account(&posting.account).0,
+                iif_field(&entry.payee),
+                posting.amount,
+                iif_field(&entry.narration)
+            );
+        }
+        out.push_str("ENDTRNS\n");
+    }
+    out
+}
+
+/// The entries as a journal file
+///
+/// Beancount gets an `open` directive per account (dated on the first entry,
+/// with its chart code as metadata) and the transaction id as metadata;
+/// Ledger gets `account` directives for the coded accounts and the id as a
+/// comment. QuickBooks gets an IIF file: the accounts list, then one general
+/// journal transaction per entry.
+pub fn render_journal(entries: &[JournalEntry], format: JournalFormat) -> String {
+    if format == JournalFormat::QuickBooks {
+        return render_quickbooks(entries);
+    }
+
+    let mut out = String::new();
+    match (format, entries.first()) {
+        (JournalFormat::Beancount, Some(first)) => {
+            for (account, code) in accounts_used(entries) {
+                let _ = writeln!(out, "{} open {}", first.date, account);
+                if let Some(code) = code {
+                    let _ = writeln!(out, "  code: {}", quoted(code));
+                }
+            }
+            out.push('\n');
+        }
+        (JournalFormat::Ledger, _) => {
+            let coded: Vec<_> = accounts_used(entries).into_iter().filter(|(_, code)| code.is_some()).collect();
+            for (account, code) in &coded {
+                let _ = writeln!(out, "account {}\n    ; code: {}", account, code.unwrap_or(""));
+            }
+            if !coded.is_empty() {
+                out.push('\n');
+            }
+        }
+        _ => {}
+    }
+
+    for entry in entries {
+        let flag = if entry.pending { '!' } else { '*' };
+        match format {
+            JournalFormat::Beancount => {
+                let _ = writeln!(out, "{} {} {} {}", entry.date, flag, quoted(&entry.payee), quoted(&entry.narration));
+                let _ = writeln!(out, "  tx_id: {}", quoted(&entry.tx_id));
+            }
+            _ => {
+                let _ = writeln!(out, "{} {} {}", entry.date, flag, entry.payee.replace('\n', " "));
+                let _ = writeln!(out, "    ; {}", entry.narration.replace('\n', " "));
+                let _ = writeln!(out, "    ; tx_id: {}", entry.tx_id);
+            }
+        }
+        for posting in &entry.postings {
+            let _ = writeln!(out, "    {:<48}  {:>12.2} {}", posting.account, posting.amount, posting.currency);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+
+    fn row(id: &str, date: &str, amount: f64, tx_type: &str, category: &str, account: &str) -> Transaction {
+        Transaction {
+            date: date.to_string(),
+            description: format!("{} row", category),
+            amount_original: format!("{:.2}", amount),
+            amount_numeric: amount,
+            transaction_type: tx_type.to_string(),
+            category: category.to_string(),
+            account_name: account.to_string(),
+            account_number: String::new(),
+            bank: "Bank of America".to_string(),
+            id: id.to_string(),
+            version: 1,
+            ..test_support::sample_transaction()
+        }
+    }
+
+    #[test]
+    fn test_entries_balance_and_name_accounts() {
+        let card = Account::new(
+            "Visa Signature".to_string(),
+            "*9876".to_string(),
+            "Bank of America".to_string(),
+            AccountType::Credit,
+            "USD".to_string(),
+            0.0,
+        );
+        let transactions = vec![
+            row("salary", "01/15/2025", 2000.0, "INGRESO", "Salary", "Checking"),
+            row("dinner", "01/03/2025", -45.5, "GASTO", "Restaurants & Bars", "Visa Signature"),
+            row("payment", "01/20/2025", -300.0, "PAGO_TARJETA", "", "Checking"),
+        ];
+        let entries = journal_entries(&transactions, &[card], &ChartOfAccounts::default()).unwrap();
+
+        let ids: Vec<&str> = entries.iter().map(|e| e.tx_id.as_str()).collect();
+        assert_eq!(ids, vec!["dinner", "salary", "payment"]);
+        assert!(entries.iter().all(JournalEntry::is_balanced));
+        let accounts = |i: usize| -> Vec<&str> { entries[i].postings.iter().map(|p| p.account.as_str()).collect() };
+        assert_eq!(accounts(0), vec!["Liabilities:Bank-Of-America:Visa-Signature", "Expenses:Restaurants-Bars"]);
+        assert_eq!(accounts(1), vec!["Assets:Bank-Of-America:Checking", "Income:Salary"]);
+        assert_eq!(accounts(2)[1], TRANSFERS_ACCOUNT);
+        assert_eq!((entries[0].postings[0].amount, entries[0].postings[1].amount), (-45.5, 45.5));
+
+        let mut broken = entries[0].clone();
+        broken.postings[1].amount = 45.0;
+        assert_eq!(broken.imbalance().get("USD"), Some(&-0.5));
+    }
+
+    #[test]
+    fn test_render_ledger_and_beancount() {
+        let mut pending = row("p-1", "2025-02-01", -12.0, "GASTO", "Coffee", "Checking");
+        pending.metadata.insert(crate::db::PENDING_KEY.to_string(), serde_json::json!(true));
+        pending.merchant = "Blue \"Bottle\"".to_string();
+        let entries = journal_entries(&[pending], &[], &ChartOfAccounts::default()).unwrap();
+
+        let ledger = render_journal(&entries, JournalFormat::Ledger);
+        assert!(ledger.starts_with("2025-02-01 ! Blue \"Bottle\"\n"), "{}", ledger);
+        assert!(ledger.contains("    ; tx_id: p-1\n"));
+        assert!(ledger.contains("Expenses:Coffee") && ledger.contains("12.00 USD"));
+
+        let beancount = render_journal(&entries, JournalFormat::Beancount);
+        assert!(beancount.starts_with("2025-02-01 open Assets:Bank-Of-America:Checking\n"), "{}", beancount);
+        assert!(beancount.contains("2025-02-01 ! \"Blue \\\"Bottle\\\"\" \"Coffee row\"\n  tx_id: \"p-1\"\n"));
+        assert!(JournalFormat::parse("hledger").is_ok() && JournalFormat::parse("qif").is_err());
+    }
+
+    #[test]
+    fn test_chart_mappings_carry_codes_into_exports() {
+        use crate::chart_of_accounts::ChartAccountType::{Asset, Expense};
+        use crate::chart_of_accounts::ChartMapping;
+        let chart = ChartOfAccounts::new(vec![
+            ChartMapping::new(MappedFrom::Category, "coffee", "6100", "Meals and Entertainment", Expense),
+            ChartMapping::new(MappedFrom::Account, "Checking", "1010", "Operating Account", Asset),
+        ]);
+        let coffee = row("c-1", "01/02/2025", -5.25, "GASTO", "Coffee", "Checking");
+        let entries = journal_entries(&[coffee], &[], &chart).unwrap();
+        let postings = &entries[0].postings;
+        assert_eq!(postings[0].account, "Assets:Operating-Account");
+        assert_eq!(postings[1].account, "Expenses:Meals-And-Entertainment");
+        assert_eq!(postings[1].code.as_deref(), Some("6100"));
+
+        let beancount = render_journal(&entries, JournalFormat::Beancount);
+        assert!(beancount.contains("2025-01-02 open Expenses:Meals-And-Entertainment\n  code: \"6100\"\n"));
+        let ledger = render_journal(&entries, JournalFormat::Ledger);
+        assert!(ledger.starts_with("account Assets:Operating-Account\n    ; code: 1010\n"), "{}", ledger);
+
+        let iif = render_journal(&entries, JournalFormat::QuickBooks);
+        assert!(iif.contains("ACCNT\tMeals-And-Entertainment\tEXP\t6100\n"), "{}", iif);
+        assert!(iif.contains("TRNS\tGENERAL JOURNAL\t01/02/2025\tOperating-Account\tCoffee row\t-5.25\tCoffee row\n"));
+        assert!(iif.contains("SPL\tGENERAL JOURNAL\t01/02/2025\tMeals-And-Entertainment\tCoffee row\t5.25\t"));
+        assert!(iif.trim_end().ends_with("ENDTRNS"));
+    }
+}
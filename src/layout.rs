@@ -0,0 +1,193 @@
+// 🧱 Layout - Which ledger columns the TUI shows, and how wide
+//
+// Problem solved:
+// - The transaction table had six fixed columns; account, currency, source file
+//   and confidence were only visible one row at a time in the detail panel
+// - Widths were tuned for one terminal size
+//
+// A `LedgerLayout` lists every column with its visibility and width. It is
+// stored in the config file (`ledger_layout`), so changes made in the column
+// chooser survive restarts.
+
+use serde::{Deserialize, Serialize};
+
+/// Narrowest / widest a column can be resized to
+pub const MIN_COLUMN_WIDTH: u16 = 4;
+pub const MAX_COLUMN_WIDTH: u16 = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerColumn {
+    Date,
+    Bank,
+    Merchant,
+    Amount,
+    Type,
+    Category,
+    Account,
+    Currency,
+    SourceFile,
+    Confidence,
+}
+
+impl LedgerColumn {
+    /// Every column, in default order
+    pub const ALL: [LedgerColumn; 10] = [
+        LedgerColumn::Date,
+        LedgerColumn::Bank,
+        LedgerColumn::Merchant,
+        LedgerColumn::Amount,
+        LedgerColumn::Type,
+        LedgerColumn::Category,
+        LedgerColumn::Account,
+        LedgerColumn::Currency,
+        LedgerColumn::SourceFile,
+        LedgerColumn::Confidence,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            LedgerColumn::Date => "Date",
+            LedgerColumn::Bank => "Bank",
+            LedgerColumn::Merchant => "Merchant",
+            LedgerColumn::Amount => "Amount",
+            LedgerColumn::Type => "Type",
+            LedgerColumn::Category => "Category",
+            LedgerColumn::Account => "Account",
+            LedgerColumn::Currency => "Currency",
+            LedgerColumn::SourceFile => "Source File",
+            LedgerColumn::Confidence => "Confidence",
+        }
+    }
+
+    fn default_setting(self) -> ColumnSetting {
+        let (visible, width) = match self {
+            LedgerColumn::Date => (true, 12),
+            LedgerColumn::Bank => (true, 18),
+            LedgerColumn::Merchant => (true, 32),
+            LedgerColumn::Amount => (true, 12),
+            LedgerColumn::Type => (true, 15),
+            LedgerColumn::Category => (true, 22),
+            LedgerColumn::Account => (false, 20),
+            LedgerColumn::Currency => (false, 8),
+            LedgerColumn::SourceFile => (false, 24),
+            LedgerColumn::Confidence => (false, 10),
+        };
+        ColumnSetting { column: self, visible, width }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnSetting {
+    pub column: LedgerColumn,
+    pub visible: bool,
+    pub width: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerLayout {
+    pub columns: Vec<ColumnSetting>,
+}
+
+impl Default for LedgerLayout {
+    fn default() -> Self {
+        LedgerLayout {
+            columns: LedgerColumn::ALL.iter().map(|c| c.default_setting()).collect(),
+        }
+    }
+}
+
+impl LedgerLayout {
+    /// Repair a stored layout: drop repeats, add columns it doesn't know yet
+    /// (hidden), clamp widths, and keep at least one column visible
+    pub fn normalized(mut self) -> Self {
+        let mut seen = Vec::new();
+        self.columns.retain(|setting| {
+            let first = !seen.contains(&setting.column);
+            seen.push(setting.column);
+            first
+        });
+        for column in LedgerColumn::ALL {
+            if !seen.contains(&column) {
+                self.columns.push(ColumnSetting { visible: false, ..column.default_setting() });
+            }
+        }
+        for setting in &mut self.columns {
+            setting.width = setting.width.clamp(MIN_COLUMN_WIDTH, MAX_COLUMN_WIDTH);
+        }
+        if !self.columns.iter().any(|setting| setting.visible) {
+            self.columns[0].visible = true;
+        }
+        self
+    }
+
+    pub fn visible(&self) -> impl Iterator<Item = &ColumnSetting> {
+        self.columns.iter().filter(|setting| setting.visible)
+    }
+
+    /// Show/hide the column at `index`; the last visible column stays visible
+    pub fn toggle(&mut self, index: usize) -> bool {
+        let visible_count = self.visible().count();
+        match self.columns.get_mut(index) {
+            Some(setting) if !(setting.visible && visible_count == 1) => {
+                setting.visible = !setting.visible;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Widen (positive) or narrow (negative) the column at `index`
+    pub fn resize(&mut self, index: usize, delta: i32) {
+        if let Some(setting) = self.columns.get_mut(index) {
+            let width = (setting.width as i32 + delta).clamp(MIN_COLUMN_WIDTH as i32, MAX_COLUMN_WIDTH as i32);
+            setting.width = width as u16;
+        }
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_and_resize() {
+        let mut layout = LedgerLayout::default();
+        assert_eq!(layout.visible().count(), 6);
+
+        let account = layout.columns.iter().position(|s| s.column == LedgerColumn::Account).unwrap();
+        assert!(layout.toggle(account));
+        assert_eq!(layout.visible().count(), 7);
+
+        layout.resize(account, 100);
+        assert_eq!(layout.columns[account].width, MAX_COLUMN_WIDTH);
+        layout.resize(account, -200);
+        assert_eq!(layout.columns[account].width, MIN_COLUMN_WIDTH);
+
+        // The last visible column can't be hidden
+        for index in 0..layout.columns.len() {
+            layout.toggle(index);
+        }
+        assert!(layout.visible().count() >= 1);
+    }
+
+    #[test]
+    fn test_normalized_repairs_stored_layout() {
+        let stored = LedgerLayout {
+            columns: vec![
+                ColumnSetting { column: LedgerColumn::Merchant, visible: false, width: 200 },
+                ColumnSetting { column: LedgerColumn::Merchant, visible: true, width: 10 },
+            ],
+        };
+        let layout = stored.normalized();
+
+        assert_eq!(layout.columns.len(), LedgerColumn::ALL.len());
+        assert_eq!(layout.columns[0].width, MAX_COLUMN_WIDTH);
+        assert_eq!(layout.visible().count(), 1);
+        assert!(layout.columns[0].visible);
+    }
+}
@@ -0,0 +1,358 @@
+// 📒 Ledgers - Multiple independent books in one installation
+//
+// Problem solved:
+// - Business Stripe data and personal AppleCard data mixed in one table
+// - One tool, separate books: every transaction, entity and event carries a ledger_id
+//
+// The "default" ledger always exists, so single-ledger installs never need to
+// know about ledgers at all.
+
+use crate::conflicts::ConflictPolicy;
+use crate::db::{insert_event, DateBasis, Event};
+use crate::event_schema::{LedgerConfigUpdated, LedgerCreated};
+use crate::quarantine::ImportPolicy;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+/// Ledger every pre-existing row belongs to
+pub const DEFAULT_LEDGER_ID: &str = "default";
+
+/// Serde default for `ledger_id` fields
+pub fn default_ledger_id() -> String {
+    DEFAULT_LEDGER_ID.to_string()
+}
+
+// ============================================================================
+// LEDGER
+// ============================================================================
+
+/// Per-ledger settings (stored as JSON, so new keys need no migration)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LedgerConfig {
+    /// Currency assumed when a source doesn't state one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_currency: Option<String>,
+
+    /// Classification rules file used when the rules table is empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rules_path: Option<String>,
+
+    /// CSV imported by `import` for this ledger
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub import_path: Option<String>,
+
+    /// Corrections/voids of transactions at or above this amount need a
+    /// second actor's approval (see approvals.rs). None = no approvals.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_threshold: Option<f64>,
+
+    /// How sync conflicts are resolved (None = last-writer-wins)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflict_policy: Option<ConflictPolicy>,
+    /// Month (1-12) the fiscal year starts in (None = calendar year)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fiscal_year_start_month: Option<u32>,
+
+    /// Reports group by statement cycles closing on this day instead of
+    /// calendar months (see reports.rs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statement_closing_day: Option<u32>,
+
+    /// Store each import's inputs and outcome so it can be replayed
+    /// (see replay.rs)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub record_imports: bool,
+
+    /// Money overspent envelopes may borrow before they go negative
+    /// (see envelopes.rs). None = no buffer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub envelope_buffer: Option<f64>,
+
+    /// Date that places card transactions in reports and reconciliations:
+    /// made or posted (None = transaction date)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_basis: Option<DateBasis>,
+
+    /// What happens to an import below the quality thresholds: rejected,
+    /// quarantined or imported anyway (see quarantine.rs; None = lenient)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub import_policy: Option<ImportPolicy>,
+
+    /// Lowest average quality / confidence (0-1) an import needs to pass the
+    /// policy (None = quarantine.rs defaults)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_import_quality: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_import_confidence: Option<f64>,
+}
+
+impl LedgerConfig {
+    /// Set a config key by name (CLI: `ledger set <id> <key> <value>`)
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let value = if value.is_empty() { None } else { Some(value.to_string()) };
+
+        match key {
+            "default_currency" | "currency" => self.default_currency = value,
+            "rules_path" | "rules" => self.rules_path = value,
+            "import_path" | "import" => self.import_path = value,
+            "approval_threshold" | "approval" => {
+                self.approval_threshold = match value {
+                    Some(v) => Some(
+                        v.parse()
+                            .map_err(|_| anyhow!("approval_threshold must be a number, got '{}'", v))?,
+                    ),
+                    None => None,
+                }
+            }
+            "conflict_policy" | "conflicts" => {
+                self.conflict_policy = value.as_deref().map(ConflictPolicy::parse).transpose()?
+            }
+            "fiscal_year_start_month" | "fiscal_year_start" => {
+                self.fiscal_year_start_month = parse_day_or_month(key, value.as_deref(), 12)?
+            }
+            "statement_closing_day" | "closing_day" => {
+                self.statement_closing_day = parse_day_or_month(key, value.as_deref(), 31)?
+            }
+            "record_imports" | "record" => {
+                self.record_imports = match value.as_deref() {
+                    None | Some("false") | Some("off") => false,
+                    Some("true") | Some("on") => true,
+                    Some(v) => return Err(anyhow!("record_imports must be true or false, got '{}'", v)),
+                }
+            }
+            "envelope_buffer" | "buffer" => {
+                self.envelope_buffer = match value {
+                    Some(v) => Some(
+                        v.parse::<f64>()
+                            .ok()
+                            .filter(|amount| *amount >= 0.0)
+                            .ok_or_else(|| anyhow!("envelope_buffer must be an amount of zero or more, got '{}'", v))?,
+                    ),
+                    None => None,
+                }
+            }
+            "date_basis" | "basis" => self.date_basis = value.as_deref().map(DateBasis::parse).transpose()?,
+            "import_policy" | "policy" => {
+                self.import_policy = value.as_deref().map(ImportPolicy::parse).transpose()?
+            }
+            "min_import_quality" | "min_quality" => self.min_import_quality = parse_fraction(key, value.as_deref())?,
+            "min_import_confidence" | "min_confidence" => {
+                self.min_import_confidence = parse_fraction(key, value.as_deref())?
+            }
+            other => return Err(anyhow!("Unknown ledger config key: {}", other)),
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse an optional 1..=max config value
+fn parse_day_or_month(key: &str, value: Option<&str>, max: u32) -> Result<Option<u32>> {
+    match value {
+        Some(v) => match v.parse::<u32>() {
+            Ok(n) if (1..=max).contains(&n) => Ok(Some(n)),
+            _ => Err(anyhow!("{} must be a number from 1 to {}, got '{}'", key, max, v)),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Parse an optional 0..=1 config value
+fn parse_fraction(key: &str, value: Option<&str>) -> Result<Option<f64>> {
+    match value {
+        Some(v) => match v.parse::<f64>() {
+            Ok(n) if (0.0..=1.0).contains(&n) => Ok(Some(n)),
+            _ => Err(anyhow!("{} must be a number from 0 to 1, got '{}'", key, v)),
+        },
+        None => Ok(None),
+    }
+}
+
+/// An independent set of books (e.g. "personal", "business")
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ledger {
+    /// Short stable id used on every row (e.g. "business")
+    pub id: String,
+
+    /// Display name
+    pub name: String,
+
+    pub config: LedgerConfig,
+
+    pub created_at: DateTime<Utc>,
+}
+
+impl Ledger {
+    pub fn new(id: &str, name: &str) -> Self {
+        Ledger {
+            id: id.to_string(),
+            name: name.to_string(),
+            config: LedgerConfig::default(),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Ledger ids go into every row and CLI flag: keep them simple
+    pub fn validate_id(id: &str) -> Result<()> {
+        if id.is_empty()
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        {
+            return Err(anyhow!(
+                "Invalid ledger id '{}': use lowercase letters, digits, '-' or '_'",
+                id
+            ));
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// PERSISTENCE
+// ============================================================================
+
+/// Create a ledger (errors if the id is taken)
+pub fn create_ledger(conn: &Connection, ledger: &Ledger, actor: &str) -> Result<()> {
+    Ledger::validate_id(&ledger.id)?;
+
+    if get_ledger(conn, &ledger.id)?.is_some() {
+        return Err(anyhow!("Ledger '{}' already exists", ledger.id));
+    }
+
+    conn.execute(
+        "INSERT INTO ledgers (id, name, config, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            ledger.id,
+            ledger.name,
+            serde_json::to_string(&ledger.config)?,
+            ledger.created_at.to_rfc3339(),
+        ],
+    )?;
+
+    let payload = LedgerCreated { name: ledger.name.clone(), config: ledger.config.clone() };
+    let event = Event::typed("ledger_created", "ledger", &ledger.id, &payload, actor)?.with_ledger(&ledger.id);
+    insert_event(conn, &event)?;
+
+    Ok(())
+}
+
+/// Replace a ledger's config
+pub fn update_ledger_config(
+    conn: &Connection,
+    ledger_id: &str,
+    config: &LedgerConfig,
+    actor: &str,
+) -> Result<()> {
+    let updated = conn.execute(
+        "UPDATE ledgers SET config = ?1 WHERE id = ?2",
+        params![serde_json::to_string(config)?, ledger_id],
+    )?;
+    if updated == 0 {
+        return Err(anyhow!("Ledger '{}' not found", ledger_id));
+    }
+
+    let payload = LedgerConfigUpdated { config: config.clone() };
+    let event = Event::typed("ledger_config_updated", "ledger", ledger_id, &payload, actor)?.with_ledger(ledger_id);
+    insert_event(conn, &event)?;
+
+    Ok(())
+}
+
+pub fn get_ledger(conn: &Connection, ledger_id: &str) -> Result<Option<Ledger>> {
+    Ok(conn
+        .query_row(
+            "SELECT id, name, config, created_at FROM ledgers WHERE id = ?1",
+            [ledger_id],
+            row_to_ledger,
+        )
+        .optional()?)
+}
+
+/// Get a ledger or fail with a hint (for CLI `--ledger`)
+pub fn require_ledger(conn: &Connection, ledger_id: &str) -> Result<Ledger> {
+    get_ledger(conn, ledger_id)?.ok_or_else(|| {
+        anyhow!(
+            "Ledger '{}' not found (create it with: ledger create {} <name>)",
+            ledger_id,
+            ledger_id
+        )
+    })
+}
+
+pub fn list_ledgers(conn: &Connection) -> Result<Vec<Ledger>> {
+    let mut stmt = conn.prepare("SELECT id, name, config, created_at FROM ledgers ORDER BY id")?;
+    let ledgers = stmt
+        .query_map([], row_to_ledger)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ledgers)
+}
+
+fn row_to_ledger(row: &rusqlite::Row) -> rusqlite::Result<Ledger> {
+    let config_json: Option<String> = row.get(2)?;
+    let created_at: String = row.get(3)?;
+
+    Ok(Ledger {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        config: config_json
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        created_at: DateTime::parse_from_rfc3339(&created_at)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::setup_database;
+
+    #[test]
+    fn test_default_ledger_exists() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let ledgers = list_ledgers(&conn).unwrap();
+        assert_eq!(ledgers.len(), 1);
+        assert_eq!(ledgers[0].id, DEFAULT_LEDGER_ID);
+    }
+
+    #[test]
+    fn test_create_and_configure_ledger() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let business = Ledger::new("business", "Business");
+        create_ledger(&conn, &business, "test").unwrap();
+        assert!(create_ledger(&conn, &business, "test").is_err());
+        assert!(create_ledger(&conn, &Ledger::new("My Books", "x"), "test").is_err());
+
+        let mut config = business.config.clone();
+        config.set("currency", "USD").unwrap();
+        config.set("rules", "rules/business.json").unwrap();
+        config.set("approval_threshold", "1000").unwrap();
+        assert!(config.set("approval_threshold", "lots").is_err());
+        config.set("conflicts", "manual").unwrap();
+        assert!(config.set("conflicts", "coin-flip").is_err());
+        config.set("closing_day", "15").unwrap();
+        assert!(config.set("fiscal_year_start", "13").is_err());
+        assert!(config.set("color", "blue").is_err());
+        update_ledger_config(&conn, "business", &config, "test").unwrap();
+
+        let loaded = require_ledger(&conn, "business").unwrap();
+        assert_eq!(loaded.config.default_currency, Some("USD".to_string()));
+        assert_eq!(loaded.config.rules_path, Some("rules/business.json".to_string()));
+        assert_eq!(loaded.config.approval_threshold, Some(1000.0));
+        assert_eq!(loaded.config.conflict_policy, Some(ConflictPolicy::Manual));
+        assert_eq!(loaded.config.statement_closing_day, Some(15));
+        assert!(require_ledger(&conn, "missing").is_err());
+    }
+}
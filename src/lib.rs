@@ -11,20 +11,96 @@ pub mod temporal;       // NEW: Temporal Model - Badge 19A
 pub mod reconciliation; // NEW: Reconciliation Engine - Badge 19B
 pub mod data_quality;   // NEW: Data Quality Engine - Badge 20
 pub mod entities;       // NEW: Entity Models - Badge 21
+pub mod query;          // Transaction filters shared by CLI, server and TUI
+pub mod ledger;         // Multiple independent ledgers (personal, business)
+pub mod users;          // Users, roles and API tokens for the server
+pub mod approvals;      // Two-step approval for large corrections/voids
+pub mod sync;           // Signed changesets to sync two instances
+pub mod conflicts;      // Detect and resolve concurrent corrections
+pub mod demo;           // Synthetic data generator for demo mode
+pub mod config;         // Config file written by `init` (DB location, currency)
+pub mod doctor;         // Environment and data diagnostics
+pub mod notes;          // Threaded notes/comments on transactions
+pub mod disputes;       // Dispute tracking (opened → bank contacted → resolved)
+pub mod enrichment;     // Pluggable merchant lookups (website, type, logo)
+pub mod mcc;            // Merchant category codes → categories
+pub mod location;       // City/country hints and spend-by-country report
+pub mod projects;       // Trips/projects grouping transactions
+pub mod bills;          // Expected transactions and bill tracking
+pub mod statements;     // Balance snapshots at statement close
+pub mod accounts;       // Persisted accounts and opening balances
+pub mod reports;        // Period summaries (calendar, statement cycle, fiscal year)
+pub mod report_render;  // HTML/PDF rendering of period reports
+pub mod duplicates;     // Duplicate clusters and review decisions
+pub mod jobs;           // Background jobs with progress and cancellation (TUI)
+pub mod layout;         // Ledger table columns and widths (TUI)
+pub mod locale;         // Locale-aware amounts (separators, currency symbols) and dates
+pub mod bulk;           // Bulk actions over many transactions (batch-audited)
+pub mod triage;         // Category suggestions for uncategorized transactions
+pub mod imports;        // Statement uploads: detect, parse, normalize, dedup, insert
+pub mod history;        // Version histories with events, and as-of views
+pub mod api_audit;      // API write rate limits and request audit events
+pub mod job_queue;      // DB-backed queue of long API jobs (imports, scans, reconciliations)
+pub mod fx;             // Pluggable exchange rates (FxRateProvider)
+pub mod replay;         // Recorded imports, replayed and compared for debugging
+pub mod parser_changes; // Re-parse imported files to spot parser behavior changes
+pub mod archive;        // Original statement files by SHA-256, and per-row provenance
+pub mod provenance;     // Full lineage of a transaction: report line back to raw line and import
+pub mod field_provenance; // Which component set merchant, category, type and amount, and when
+pub mod confidence;     // Parser, rule and registry confidence combined into one score per row
+pub mod signing;        // Ed25519 signatures on exported reports and changesets, and their checks
+pub mod secrets;        // API and signing keys from the OS keyring, falling back to environment variables
+pub mod pool;           // r2d2 pool of WAL connections with a busy timeout (read-write or read-only)
+pub mod snapshot;       // Read-only connections and immutable point-in-time copies for reports
+pub mod manifest;       // Per-bank counts and checksums verified against statements (trust scorecard)
+pub mod source_stats;   // Per-source-file counts, dates, quality, duplicates and unresolved merchants
+pub mod event_schema;   // Typed payload per event type, validated when events are written
+pub mod outbox;         // Webhooks: matching events queued with each write, signed POSTs with retries
+pub mod merge;          // Merge another instance's exported log: ledger/id mapping and conflict policies
+pub mod bootstrap;      // Merchant, bank and account registries filled from existing transactions
+pub mod aliases;        // Merchant spellings clustered into alias groups for review
+pub mod merchant_categories; // Suggested merchant categories from history and similar-name peers
+pub mod analytics;      // Merchant spend profiles: lifetime spend, monthly trend, categories and accounts
+pub mod envelopes;      // Envelope budgets: monthly funding, overspend buffer, carryover (versioned)
+pub mod manual;         // Cash spending and adjustments entered by hand (dedup-exempt)
+pub mod transfers;      // Transfers: both legs written atomically, missing legs completed
+pub mod settlement;     // Pending charges upgraded to their settled versions
+pub mod positions;      // Brokerage positions: snapshots, valuation and net worth
+pub mod explanations;   // Ranked explanations for reconciliation discrepancies
+pub mod period_close;   // Reconciled statement periods locked against corrections
+pub mod chart_of_accounts; // Versioned mapping of categories and accounts to a formal chart
+pub mod journal;        // Double-entry projection exported as Ledger / Beancount / QuickBooks journals
+pub mod accounting_export; // QuickBooks (CSV / IIF) and Xero bank statement import files
+pub mod quarantine;     // Import quality gate: strict / quarantine / lenient policies and review
+pub mod system;         // TrustSystem facade: storage, registries and engines in one handle
+pub mod system_archive; // Whole installation in one tar.zst: DB snapshot, registries, config, rules, event head
+pub mod purge;          // Counterparty erasure: remove or pseudonymize its rows, events and entities
+pub mod pii;            // Card, IBAN and account numbers found in descriptions and masked on import
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;        // Entry points of the cargo-fuzz targets (fuzz/): parsers and normalizers
+#[cfg(feature = "grpc")]
+pub mod grpc;           // gRPC service (proto/trust.proto) next to the REST API
 
 // Re-export commonly used types
 pub use db::{
-    Transaction, SourceFileStat, Event,
-    load_csv, setup_database, insert_transactions,
-    get_all_transactions, get_source_file_stats, get_transactions_by_source,
-    verify_count, insert_event, get_events_for_entity,
-    migrate_add_uuids  // Badge 19: Migration function
+    Transaction, SourceFileStat, Event, InsertSummary, RowError, DateBasis, PENDING_KEY, POSTED_DATE_KEY,
+    QUARANTINE_KEY,
+    load_csv, setup_database, insert_transactions_as, insert_transaction_as,
+    get_all_transactions, get_source_file_stats, get_transactions, get_transactions_by_source,
+    GroupTotal, totals_by_type, totals_by_bank, totals_by_category, totals_by_month,
+    verify_count, insert_event, get_events_for_entity, get_events_after,
+    EventFilter, query_events, count_events, get_event,
+    migrate_add_uuids,  // Badge 19: Migration function
+    SCHEMA_VERSION,
+    insert_transaction_version, get_transaction_history, get_current_transaction,
+    undo_last_change, redo_last_change, void_transaction, get_active_transactions,
 };
 pub use parser::{
-    BankParser, MerchantExtractor, TypeClassifier,
+    BankParser, DateNormalizer, MerchantExtractor, TypeClassifier,
     RawTransaction, SourceType,
-    detect_source, get_parser,
-    BofAParser, AppleCardParser, StripeParser, WiseParser, ScotiabankParser,
+    detect_source, get_parser, get_type_classifier, parser_version_tag,
+    BofAParser, AppleCardParser, StripeParser, WiseParser, ScotiabankParser, OfxParser, CoinbaseParser, KrakenParser,
+    PayPalParser, VenmoParser, BbvaParser, BanorteParser, CfdiParser, WiseDirection, normalize_day_first_date,
 };
 pub use attributes::{
     AttributeRegistry, AttributeDefinition, AttributeType, ValidationRule,
@@ -33,27 +109,241 @@ pub use schema::{
     SchemaValidator, Context, ValidationError, ValidationResult,
 };
 pub use rules::{
-    ClassificationRule, RuleEngine, ClassificationResult,
+    ClassificationRule, RuleEngine, ClassificationResult, VersionedRule,
+    RuleChange, SimulationReport, ClassificationChange, apply_reclassification,
+    save_rule, save_rules, retire_rule, get_current_rules, get_rule_history, get_rule_at_time,
+    get_rules_at_time,
 };
 pub use deduplication::{
-    DeduplicationEngine, DuplicateMatch, MatchStrategy,
+    DeduplicationEngine, DuplicateMatch, DuplicateMatcher, MatchStrategy,
 };
 pub use temporal::{
     TimeModel, VersionedValue, TemporalEntity, Snapshot,
 };
 pub use reconciliation::{
     ReconciliationEngine, ReconciliationReport, ReconciliationResult,
-    StatementMetadata, Discrepancy, DiscrepancyCategory,
+    StatementMetadata, Discrepancy, DiscrepancyCategory, FxMatch, FX_WINDOW_DAYS,
 };
 pub use data_quality::{
     DataQualityEngine, QualityReport, ValidationResult as QualityValidationResult,
-    QualityIssue, Severity, BatchSummary,
+    QualityIssue, Severity, BatchSummary, EmbeddedFx, embedded_fx, DEFAULT_MAX_FX_DEVIATION,
+};
+pub use query::{
+    TransactionFilter, TransactionQuery, TransactionPage, Sort, SortKey, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE,
+};
+pub use ledger::{
+    Ledger, LedgerConfig, DEFAULT_LEDGER_ID,
+    create_ledger, get_ledger, require_ledger, list_ledgers, update_ledger_config,
+};
+pub use approvals::{
+    ChangeKind, ChangeStatus, PendingChange, WriteOutcome,
+    requires_approval, submit_correction, submit_void, submit_undo, submit_redo, propose_change,
+    approve_change, reject_change, get_pending_change, list_pending_changes,
+};
+pub use sync::{
+    Changeset, Checkpoint, ImportSummary, SyncedVersion,
+    export_changeset, import_changeset, instance_id,
+};
+pub use conflicts::{
+    Conflict, ConflictPolicy, Resolution,
+    content_fingerprint, is_conflict, resolve_conflict, repair_current_conflicts,
+};
+pub use demo::{generate_transactions, seed_demo_database};
+pub use config::{config_dir, AppConfig, IN_MEMORY_DB_PATH};
+pub use doctor::{Check, CheckStatus, QueryPlan, diagnose, check_database, apply_fixes, explain_common_queries};
+pub use notes::{Note, add_note, get_note, get_notes, get_ledger_notes, thread_notes};
+pub use disputes::{
+    Dispute, DisputeStatus,
+    open_dispute, advance_dispute, get_dispute, get_open_dispute, list_disputes, stale_disputes,
+    get_dispute_history,
+};
+pub use enrichment::{
+    Enrichment, EnrichmentPipeline, LocalRulesEnricher, MerchantEnricher, apply_enrichment,
+};
+#[cfg(feature = "enrichment-web")]
+pub use enrichment::WebApiEnricher;
+pub use mcc::{MccEntry, MCC_TABLE, classify_mcc, lookup_mcc, parse_mcc};
+pub use location::{
+    CountrySpend, Location,
+    extract_location, annotate_location, transaction_location, spend_by_country,
+};
+pub use projects::{
+    AssignmentMethod, Project, ProjectReport, ProjectRules,
+    create_project, get_project, list_projects, assign_transaction, unassign_transaction,
+    apply_project_rules, project_transaction_ids, project_report,
+};
+pub use bills::{
+    BillOccurrence, BillStatus, ExpectedTransaction, DEFAULT_GRACE_DAYS,
+    create_expected_transaction, remove_expected_transaction, get_expected_transaction,
+    list_expected_transactions, bill_occurrences,
+    bill_amount_history, detect_price_changes, record_price_changes, BillPriceChange,
+};
+pub use statements::{
+    BalanceSnapshot, record_statement_close, balance_history, snapshot_accounts,
+};
+pub use accounts::{
+    AccountBalance, OpeningBalance, OPENING_BALANCE_FLAG,
+    save_account_version, find_account, list_accounts, set_opening_balance, set_account_status, account_of,
+    account_balances,
+};
+pub use reports::{
+    AccountReconciliation, Period, PeriodDefinition, PeriodReport, PeriodSummary, ReportCalendar,
+    build_period_report, summarize_by_period, summarize_by_fiscal_year,
+};
+pub use report_render::{render_html, report_lines};
+pub use duplicates::{
+    DuplicateCluster, DuplicateDecision, DEFER_DAYS,
+    cluster_key, find_duplicate_clusters, find_duplicate_clusters_with_progress,
+    record_decision, merge_cluster, get_decision, pending_clusters, retain_pending,
+};
+pub use jobs::{
+    Job, JobContext, JobOutcome, JobProgress, Cancelled, CsvImportSummary,
+    import_csv_job, scan_duplicates_job, ledger_transactions,
+};
+pub use layout::{LedgerColumn, LedgerLayout, ColumnSetting, MIN_COLUMN_WIDTH, MAX_COLUMN_WIDTH};
+pub use locale::{DateStyle, Locale};
+pub use bulk::{BulkAction, BulkResult, apply_bulk_action, tags, is_reviewed, TAGS_KEY, REVIEWED_KEY};
+pub use triage::{CategorySuggester, CategorySuggestion, needs_triage, categorize};
+pub use imports::{
+    ImportSession, RowIssue, import_statement, import_statement_with, normalize_raw, normalize_raw_at,
+    ImportContext, IMPORT_SESSION_KEY, EXTERNAL_ID_KEY, ASSET_KEY, ASSET_PRICE_KEY, QUANTITY_KEY,
+    DirectoryImport, DirectoryTotals, FileImport, FileOutcome, import_directory_with, statement_files,
+};
+pub use history::{
+    EntityHistory, VersionRecord, parse_as_of,
+    transaction_history, transactions_as_of, rule_history, rules_as_of, merchant_history, merchants_as_of,
+};
+pub use api_audit::{RateLimiter, DEFAULT_WRITES_PER_MINUTE, is_mutating, record_api_call};
+pub use job_queue::{
+    JobSpec, QueuedJob, QueuedJobStatus,
+    enqueue_job, get_queued_job, list_queued_jobs, claim_next_job, requeue_interrupted_jobs,
+    run_next_job, open_worker_connection, WORKER_BUSY_TIMEOUT,
+};
+pub use fx::{FixedRates, FxRateProvider, HistoricalRates, convert};
+pub use replay::{
+    ImportOutcome, ImportRecording, OutcomeDifference, ReplayReport,
+    record_import, get_import_recording, replay_import, compare_outcomes, transaction_values,
+};
+pub use archive::{
+    ArchivedSource, Provenance, SOURCE_HASH_KEY, SOURCE_LINE_KEY,
+    archive_source, get_archived_source, list_archived_sources, content_sha256, tag_source, source_excerpt,
+    transaction_provenance,
+};
+pub use parser_changes::{
+    OutdatedSource, ParserChangeReport, ParserDiscrepancy, PARSER_FIELDS, outdated_sources, compare_with_stored,
+};
+pub use provenance::{
+    ImportSessionRef, NormalizationSteps, ProvenanceTrace, RawLine, ReportAggregate, SourceFile, VersionStep,
+};
+pub use confidence::{
+    ConfidenceInputs, DEFAULT_PARSER_CONFIDENCE, UNCLASSIFIED_CONFIDENCE, combine as combine_confidence,
+};
+pub use field_provenance::{
+    TrackedField, FieldOrigin, FieldSource, FIELD_PROVENANCE_KEY, attribute_changes, field_origins, inherit_origins,
+    record_origin,
+};
+pub use signing::{
+    ArtifactSignature, SigningIdentity, SIGNING_KEY_ENV, SIGNING_KEY_SECRET, generate_signing_key, load_signing_key,
+    save_signing_key, signing_key_hex, public_key_hex, parse_public_key, sign_artifact, sign_file, verify_artifact,
+    signature_path, read_signature,
+};
+pub use secrets::{
+    MemoryKeyring, OsKeyring, SecretSource, SecretStore, KEYRING_SERVICE, KNOWN_SECRETS, resolve_secret, secret,
+    secret_env_var, validate_secret_name,
+};
+pub use pool::{ConnectionPool, PooledConnection, SqliteConnectionManager, DEFAULT_POOL_SIZE, POOL_CHECKOUT_TIMEOUT};
+pub use snapshot::{DatabaseSnapshot, is_read_only, open_read_only, set_query_only};
+pub use manifest::{
+    ManifestEntry, SourceScorecard, StatementCheck, TOTAL_TOLERANCE,
+    compute_manifest, ledger_manifest, verify_sources,
+};
+pub use source_stats::{SourceStats, compute_source_stats, duplicates_by_file, source_stats};
+pub use event_schema::{
+    registered_event_types, validate_event_data, EventPayload, AccountStatusChanged, AliasesDecided, ApiRequestLogged,
+    BulkActionApplied, ChangesetImported, ConflictLogged, DisputeChanged, DuplicatesDecided, EnvelopeChanged,
+    ImportRecorded, LedgerConfigUpdated,
+    LedgerCreated, LedgerMerged, ManualTransactionCreated, NoteAdded, OpeningBalanceSet, PendingChangeLogged,
+    ChartMappingChanged, PeriodLockChanged, QuarantineReviewed,
+    PositionsRecorded, ProjectAssignmentChanged, RuleChanged,
+    RuleRetired, TransactionAdded, TransactionCorrected, TransactionSettled, TransferCreated, UserCreated,
+    UserRoleChanged,
+    UserTokenRotated, WebhookChanged,
+};
+pub use outbox::{
+    add_webhook, deliver_due, get_webhook, list_outbox, list_webhooks, remove_webhook, retry_delay, retry_failed,
+    sign_payload, DeliveryReport, DeliveryStatus, OutboxEntry, Webhook, WebhookTransport, MAX_DELIVERY_ATTEMPTS,
+};
+#[cfg(feature = "webhooks")]
+pub use outbox::HttpTransport;
+pub use merge::{merge_changeset, MergeOptions, MergeSummary};
+pub use bootstrap::{bootstrap_entities, bootstrap_registries, BootstrapReport, EntityBootstrap};
+pub use aliases::{
+    cluster_spellings, confirmed_alias_groups, get_alias_decision, merchant_spellings, propose_alias_groups,
+    record_alias_decision, AliasDecision, AliasGroup, ConfirmedAliases, MerchantSpelling,
+};
+pub use merchant_categories::{
+    name_similarity, suggest_merchant_category, suggest_missing_categories, MerchantCategorySuggestion,
+    SuggestionEvidence, SuggestionSource,
+};
+pub use analytics::{
+    merchant_profile, profile_merchant, AccountUsage, CategoryUsage, MerchantProfile, MonthlySpend,
+};
+pub use envelopes::{
+    budgeting_start, current_envelopes, envelope_history, envelope_months, get_current_envelope,
+    ledger_envelope_months, ledger_envelope_versions, retire_envelope, save_envelope, CarryoverRule, Envelope,
+    EnvelopeBalance, EnvelopeMonth, VersionedEnvelope,
+};
+pub use manual::{
+    create_manual_transaction, is_manual, ManualEntry, ManualKind, MANUAL_SOURCE_FILE, MANUAL_SOURCE_TYPE,
+    SOURCE_TYPE_KEY,
+};
+pub use transfers::{
+    complete_transfer, create_transfer, record_fx_slippage, transfer_id, unmatched_transfer_legs, Transfer,
+    TransferAccount, FX_SLIPPAGE_CATEGORY, TRANSFER_ID_KEY,
+};
+pub use settlement::{
+    already_settled, settle_pending, settle_stored, settled_version, SettlementMatcher, PENDING_AMOUNT_KEY,
+    PENDING_DATE_KEY, SETTLED_HASH_KEY,
+};
+pub use positions::{
+    net_worth, parse_positions_csv, positions_as_of, record_positions, value_positions, BrokerageStatement, NetWorth,
+    NetWorthLine, Position, PositionValue, CASH_SYMBOL,
+};
+pub use explanations::{explain_gap, explain_report, Hypothesis, HypothesisKind, MAX_FEE, TIMING_WINDOW_DAYS};
+pub use period_close::{close_period, closed_periods, reopen_period, ClosedPeriod};
+pub use chart_of_accounts::{
+    current_chart, get_current_mapping, mapping_history, retire_chart_mapping, save_chart_mapping, ChartAccountType,
+    ChartMapping, ChartOfAccounts, MappedFrom, VersionedMapping,
+};
+pub use journal::{
+    journal_entries, render_journal, JournalEntry, JournalFormat, Posting, OPENING_BALANCES_ACCOUNT, TRANSFERS_ACCOUNT,
+};
+pub use accounting_export::{export_accounting, AccountingExport, ExportFile};
+pub use quarantine::{
+    discard_quarantine, quarantined_transactions, release_quarantine, ImportGate, ImportPolicy, DEFAULT_MIN_CONFIDENCE,
+    DEFAULT_MIN_QUALITY,
+};
+pub use system::{Correction, EntityKind, TrustSystem, TrustSystemBuilder, DEFAULT_BASE_CURRENCY, SYSTEM_ACTOR};
+pub use system_archive::{
+    create_archive, event_chain_head, read_manifest, restore_archive, ArchiveManifest, EventChainHead, RestoredArchive,
+};
+pub use purge::{plan_purge, purge_counterparty, ChecksumChange, PurgeMode, PurgePlan, PurgeReport};
+pub use pii::{find_pii, mask_pii, mask_transaction, PiiKind, PiiMatch, PII_MASKED_KEY};
+#[cfg(feature = "grpc")]
+pub use grpc::LedgerService;
+#[cfg(feature = "report-pdf")]
+pub use report_render::render_pdf;
+pub use users::{
+    Role, User,
+    create_user, set_user_role, rotate_token, authenticate, get_user, list_users, user_count,
 };
 pub use entities::{
     Bank, BankType, BankRegistry,
-    Merchant, MerchantType, MerchantRegistry,
+    Merchant, MerchantType, MerchantRegistry, shared_registry,
     Category, CategoryType, CategoryRegistry,
-    Account, AccountType, AccountRegistry,
+    Account, AccountStatus, AccountType, AccountRegistry,
+    load_accounts, load_banks, load_categories, load_merchants, load_versions, save_registries, save_versions,
+    VersionedEntity,
 };
 
 /// Library version
@@ -0,0 +1,177 @@
+// 🌐 Locale - How amounts and dates are shown to people
+//
+// Problem solved:
+// - Amounts rendered as bare `{:.2}` (no thousands separators, no currency
+//   symbol) and dates stayed in whatever format the bank's CSV used, so one
+//   screen mixed 01/15/2025 and 2025-01-15
+//
+// `Locale` is chosen by the `locale` setting of the config file ("en-US",
+// "de-DE", ...) and used by the TUI and the report renderers. Without a
+// setting the plain locale applies: no grouping, a '.' decimal point and ISO
+// dates. Machine-readable exports (CSV, journals, JSON) never go through it.
+
+use crate::db::Transaction;
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateStyle {
+    /// 2025-01-31
+    Iso,
+    /// 01/31/2025
+    MonthFirst,
+    /// 31/01/2025 (or 31.01.2025 with a '.' separator)
+    DayFirst(char),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    /// Tag the locale was parsed from ("plain" by default)
+    pub tag: String,
+    pub decimal: char,
+    /// Thousands separator (None = no grouping)
+    pub group: Option<char>,
+    /// Currency symbol after the number ("1.234,56 €") instead of before
+    pub symbol_after: bool,
+    pub date_style: DateStyle,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale {
+            tag: "plain".to_string(),
+            decimal: '.',
+            group: None,
+            symbol_after: false,
+            date_style: DateStyle::Iso,
+        }
+    }
+}
+
+impl Locale {
+    /// Parse a locale tag: en-US, en-GB, es-MX, es-ES, de-DE, fr-FR, or
+    /// plain. POSIX spellings work too ("de_DE.UTF-8").
+    pub fn parse(tag: &str) -> Result<Self> {
+        let normalized = tag.split('.').next().unwrap_or_default().replace('_', "-");
+        let (group, decimal, symbol_after, date_style) = match normalized.to_lowercase().as_str() {
+            "" | "plain" | "c" | "posix" => return Ok(Locale::default()),
+            "en-us" => (',', '.', false, DateStyle::MonthFirst),
+            "en-gb" => (',', '.', false, DateStyle::DayFirst('/')),
+            "es-mx" => (',', '.', false, DateStyle::DayFirst('/')),
+            "es-es" => ('.', ',', true, DateStyle::DayFirst('/')),
+            "de-de" => ('.', ',', true, DateStyle::DayFirst('.')),
+            "fr-fr" => ('\u{a0}', ',', true, DateStyle::DayFirst('/')),
+            _ => {
+                return Err(anyhow!(
+                    "Unknown locale: {} (en-US, en-GB, es-MX, es-ES, de-DE, fr-FR or plain)",
+                    tag
+                ))
+            }
+        };
+        Ok(Locale { tag: normalized, decimal, group: Some(group), symbol_after, date_style })
+    }
+
+    /// A number with `decimals` places: 1,234.56 / 1.234,56
+    pub fn number(&self, value: f64, decimals: usize) -> String {
+        let fixed = format!("{:.*}", decimals, value.abs());
+        let (whole, fraction) = fixed.split_once('.').unwrap_or((&fixed, ""));
+
+        let mut out = String::new();
+        if value < 0.0 && fixed.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            out.push('-');
+        }
+        for (i, digit) in whole.chars().enumerate() {
+            if let Some(group) = self.group {
+                if i > 0 && (whole.len() - i) % 3 == 0 {
+                    out.push(group);
+                }
+            }
+            out.push(digit);
+        }
+        if !fraction.is_empty() {
+            out.push(self.decimal);
+            out.push_str(fraction);
+        }
+        out
+    }
+
+    /// An amount with two decimals, without currency
+    pub fn amount(&self, value: f64) -> String {
+        self.number(value, 2)
+    }
+
+    /// An amount with its currency: "-$1,234.56", "1.234,56 €"; currencies
+    /// without a symbol (and the plain locale) show the code: "12.00 CHF"
+    pub fn money(&self, value: f64, currency: &str) -> String {
+        let amount = self.amount(value);
+        match currency_symbol(currency) {
+            Some(symbol) if self.group.is_some() && !self.symbol_after => match amount.strip_prefix('-') {
+                Some(positive) => format!("-{}{}", symbol, positive),
+                None => format!("{}{}", symbol, amount),
+            },
+            Some(symbol) if self.group.is_some() => format!("{} {}", amount, symbol),
+            _ => format!("{} {}", amount, currency),
+        }
+    }
+
+    pub fn date(&self, date: NaiveDate) -> String {
+        match self.date_style {
+            DateStyle::Iso => date.format("%Y-%m-%d").to_string(),
+            DateStyle::MonthFirst => date.format("%m/%d/%Y").to_string(),
+            DateStyle::DayFirst(separator) => date.format(&format!("%d{0}%m{0}%Y", separator)).to_string(),
+        }
+    }
+
+    /// The transaction's date in this locale (as the source wrote it when it
+    /// can't be read)
+    pub fn transaction_date(&self, tx: &Transaction) -> String {
+        tx.parsed_date().map_or_else(|| tx.date.clone(), |date| self.date(date))
+    }
+}
+
+fn currency_symbol(currency: &str) -> Option<&'static str> {
+    match currency.to_uppercase().as_str() {
+        "USD" | "MXN" | "CAD" | "AUD" => Some("$"),
+        "EUR" => Some("€"),
+        "GBP" => Some("£"),
+        "JPY" => Some("¥"),
+        "BRL" => Some("R$"),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbers_and_money() {
+        let us = Locale::parse("en-US").unwrap();
+        assert_eq!(us.amount(1234567.891), "1,234,567.89");
+        assert_eq!(us.money(-45.5, "USD"), "-$45.50");
+        assert_eq!(us.money(12.0, "CHF"), "12.00 CHF");
+
+        let de = Locale::parse("de_DE.UTF-8").unwrap();
+        assert_eq!(de.money(-1234.5, "EUR"), "-1.234,50 €");
+        assert_eq!(de.number(999.0, 0), "999");
+
+        let plain = Locale::default();
+        assert_eq!(plain.amount(-1234.5), "-1234.50");
+        assert_eq!(plain.amount(-0.001), "0.00");
+        assert_eq!(plain.money(1234.5, "USD"), "1234.50 USD");
+        assert!(Locale::parse("xx-YY").is_err());
+    }
+
+    #[test]
+    fn test_dates() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 31).unwrap();
+        assert_eq!(Locale::default().date(date), "2025-01-31");
+        assert_eq!(Locale::parse("en-US").unwrap().date(date), "01/31/2025");
+        assert_eq!(Locale::parse("es-MX").unwrap().date(date), "31/01/2025");
+        assert_eq!(Locale::parse("de-DE").unwrap().date(date), "31.01.2025");
+    }
+}
@@ -0,0 +1,287 @@
+// 📍 Location - City and country hints from card descriptions
+//
+// Problem solved:
+// - Card descriptions end with where the charge happened ("AMSTERDAM 1097 DP NH NLD",
+//   "STARBUCKS 1234 SEATTLE WA") but only as free text
+// - Travel expenses could not be grouped by country
+//
+// Extraction is a heuristic over the END of the description: an ISO alpha-3
+// country code, or a US state code, marks a location; the words before any
+// postal/region tokens are the city. Results go to metadata ("city",
+// "country_code") at import, and reports fall back to extracting on the fly
+// for rows imported before this existed.
+
+use crate::db::Transaction;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    pub city: Option<String>,
+
+    /// ISO 3166-1 alpha-2 (e.g. "NL")
+    pub country_code: String,
+}
+
+// ============================================================================
+// CODE TABLES
+// ============================================================================
+
+/// ISO alpha-3 → alpha-2 for countries that show up on statements
+const COUNTRIES: &[(&str, &str)] = &[
+    ("USA", "US"), ("MEX", "MX"), ("CAN", "CA"), ("NLD", "NL"), ("GBR", "GB"),
+    ("DEU", "DE"), ("FRA", "FR"), ("ESP", "ES"), ("ITA", "IT"), ("PRT", "PT"),
+    ("BEL", "BE"), ("CHE", "CH"), ("AUT", "AT"), ("IRL", "IE"), ("DNK", "DK"),
+    ("SWE", "SE"), ("NOR", "NO"), ("FIN", "FI"), ("POL", "PL"), ("CZE", "CZ"),
+    ("GRC", "GR"), ("TUR", "TR"), ("JPN", "JP"), ("KOR", "KR"), ("CHN", "CN"),
+    ("HKG", "HK"), ("SGP", "SG"), ("THA", "TH"), ("AUS", "AU"), ("NZL", "NZ"),
+    ("BRA", "BR"), ("ARG", "AR"), ("CHL", "CL"), ("COL", "CO"), ("PER", "PE"),
+    ("CRI", "CR"), ("GTM", "GT"), ("ISR", "IL"), ("ARE", "AE"), ("ZAF", "ZA"),
+    ("IND", "IN"),
+];
+
+const US_STATES: &[&str] = &[
+    "AL", "AK", "AZ", "AR", "CA", "CO", "CT", "DE", "DC", "FL", "GA", "HI", "ID", "IL", "IN",
+    "IA", "KS", "KY", "LA", "ME", "MD", "MA", "MI", "MN", "MS", "MO", "MT", "NE", "NV", "NH",
+    "NJ", "NM", "NY", "NC", "ND", "OH", "OK", "OR", "PA", "RI", "SC", "SD", "TN", "TX", "UT",
+    "VT", "VA", "WA", "WV", "WI", "WY",
+];
+
+/// Cities whose names are more than one word
+const MULTI_WORD_CITIES: &[&str] = &[
+    "NEW YORK", "SAN FRANCISCO", "LOS ANGELES", "SAN DIEGO", "SAN JOSE", "LAS VEGAS",
+    "SALT LAKE CITY", "MEXICO CITY", "CIUDAD DE MEXICO", "BUENOS AIRES", "HONG KONG",
+    "RIO DE JANEIRO", "SAO PAULO", "PLAYA DEL CARMEN", "SAN MIGUEL DE ALLENDE",
+];
+
+// ============================================================================
+// EXTRACTION
+// ============================================================================
+
+/// Pull a location hint off the end of a description
+pub fn extract_location(description: &str) -> Option<Location> {
+    let tokens: Vec<&str> = description.split_whitespace().collect();
+    let (last, rest) = tokens.split_last()?;
+    let last = last.to_uppercase();
+
+    let country_code = if let Some((_, alpha2)) = COUNTRIES.iter().find(|(alpha3, _)| *alpha3 == last) {
+        alpha2.to_string()
+    } else if US_STATES.contains(&last.as_str()) && rest.len() >= 2 {
+        "US".to_string()
+    } else {
+        return None;
+    };
+
+    Some(Location { city: extract_city(rest), country_code })
+}
+
+/// The city is the run of words just before postal/region tokens
+fn extract_city(tokens: &[&str]) -> Option<String> {
+    let is_filler = |token: &str| token.len() <= 2 || token.chars().any(|c| c.is_ascii_digit());
+
+    let mut end = tokens.len();
+    while end > 0 && is_filler(tokens[end - 1]) {
+        end -= 1;
+    }
+    let word = |token: &str| token.chars().all(|c| c.is_alphabetic() || c == '\'' || c == '-');
+    if end == 0 || !word(tokens[end - 1]) {
+        return None;
+    }
+
+    let upper: Vec<String> = tokens[..end].iter().map(|t| t.to_uppercase()).collect();
+    let words = MULTI_WORD_CITIES
+        .iter()
+        .map(|city| city.split(' ').count())
+        .filter(|&n| n <= end && MULTI_WORD_CITIES.contains(&upper[end - n..].join(" ").as_str()))
+        .max()
+        .unwrap_or(1);
+
+    // A lone word before the country is the merchant ("SPOTIFY USA"), not a city
+    if end == words {
+        return None;
+    }
+
+    Some(title_case(&upper[end - words..].join(" ")))
+}
+
+fn title_case(text: &str) -> String {
+    text.split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Store the extracted location in metadata (unless one is already set)
+///
+/// Returns true if a location was added.
+pub fn annotate_location(tx: &mut Transaction) -> bool {
+    if tx.has_metadata("country_code") {
+        return false;
+    }
+    let Some(location) = extract_location(&tx.description) else {
+        return false;
+    };
+    if let Some(city) = &location.city {
+        tx.metadata.insert("city".to_string(), serde_json::json!(city));
+    }
+    tx.metadata.insert("country_code".to_string(), serde_json::json!(location.country_code));
+    true
+}
+
+/// Location from metadata, else extracted from the description
+pub fn transaction_location(tx: &Transaction) -> Option<Location> {
+    match tx.get_metadata("country_code").and_then(|v| v.as_str()) {
+        Some(country_code) => Some(Location {
+            city: tx.get_metadata("city").and_then(|v| v.as_str()).map(str::to_string),
+            country_code: country_code.to_string(),
+        }),
+        None => extract_location(&tx.description),
+    }
+}
+
+// ============================================================================
+// REPORT
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CountrySpend {
+    /// None = no location hint in the description
+    pub country_code: Option<String>,
+    pub transactions: usize,
+
+    /// Spend per currency (positive numbers)
+    pub totals: BTreeMap<String, f64>,
+
+    /// Cities seen, most charges first
+    pub cities: Vec<(String, usize)>,
+}
+
+/// Group spending (negative amounts, not transfers or card payments) by country
+///
+/// Countries are ordered by number of charges; the "unknown" bucket comes last.
+pub fn spend_by_country(transactions: &[Transaction]) -> Vec<CountrySpend> {
+    let mut groups: BTreeMap<Option<String>, CountrySpend> = BTreeMap::new();
+    let mut city_counts: BTreeMap<Option<String>, BTreeMap<String, usize>> = BTreeMap::new();
+
+    let spending = transactions.iter().filter(|tx| {
+        tx.is_active()
+            && tx.amount_numeric < 0.0
+            && tx.transaction_type != "TRASPASO"
+            && tx.transaction_type != "PAGO_TARJETA"
+    });
+    for tx in spending {
+        let location = transaction_location(tx);
+        let country_code = location.as_ref().map(|l| l.country_code.clone());
+
+        let group = groups.entry(country_code.clone()).or_insert_with(|| CountrySpend {
+            country_code: country_code.clone(),
+            transactions: 0,
+            totals: BTreeMap::new(),
+            cities: Vec::new(),
+        });
+        group.transactions += 1;
+        *group.totals.entry(tx.currency.clone()).or_default() += tx.amount_numeric.abs();
+
+        if let Some(city) = location.and_then(|l| l.city) {
+            *city_counts.entry(country_code).or_default().entry(city).or_default() += 1;
+        }
+    }
+
+    let mut report: Vec<CountrySpend> = groups
+        .into_values()
+        .map(|mut spend| {
+            spend.cities = city_counts.remove(&spend.country_code).unwrap_or_default().into_iter().collect();
+            spend.cities.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+            spend
+        })
+        .collect();
+    report.sort_by_key(|spend| (spend.country_code.is_none(), std::cmp::Reverse(spend.transactions)));
+    report
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+
+    fn card_charge(description: &str, amount: f64, currency: &str) -> Transaction {
+        Transaction {
+            date: "06/10/2025".to_string(),
+            description: description.to_string(),
+            amount_original: format!("{:.2}", amount),
+            amount_numeric: amount,
+            category: "Travel".to_string(),
+            currency: currency.to_string(),
+            account_name: "Apple Card".to_string(),
+            bank: "AppleCard".to_string(),
+            source_file: "applecard.csv".to_string(),
+            version: 1,
+            ..test_support::sample_transaction()
+        }
+    }
+
+    #[test]
+    fn test_extract_location() {
+        let amsterdam = extract_location("ALBERT HEIJN 1411 AMSTERDAM 1097 DP NH NLD").unwrap();
+        assert_eq!(amsterdam.city.as_deref(), Some("Amsterdam"));
+        assert_eq!(amsterdam.country_code, "NL");
+
+        let seattle = extract_location("STARBUCKS STORE 1234 SEATTLE WA").unwrap();
+        assert_eq!(seattle, Location { city: Some("Seattle".to_string()), country_code: "US".to_string() });
+
+        let cdmx = extract_location("OXXO INSURGENTES CIUDAD DE MEXICO MEX").unwrap();
+        assert_eq!(cdmx.city.as_deref(), Some("Ciudad De Mexico"));
+        assert_eq!(cdmx.country_code, "MX");
+
+        let spotify = extract_location("SPOTIFY USA").unwrap();
+        assert_eq!(spotify.city, None);
+        assert_eq!(spotify.country_code, "US");
+
+        assert!(extract_location("NETFLIX.COM").is_none());
+        assert!(extract_location("Wise Us Inc, Des:transfer").is_none());
+    }
+
+    #[test]
+    fn test_annotate_keeps_existing_location() {
+        let mut tx = card_charge("CAFE DE REGT AMSTERDAM NLD", -4.5, "EUR");
+        assert!(annotate_location(&mut tx));
+        assert_eq!(tx.get_metadata("city"), Some(&serde_json::json!("Amsterdam")));
+
+        tx.metadata.insert("country_code".to_string(), serde_json::json!("BE"));
+        assert!(!annotate_location(&mut tx));
+        assert_eq!(transaction_location(&tx).unwrap().country_code, "BE");
+    }
+
+    #[test]
+    fn test_spend_by_country() {
+        let mut payment = card_charge("APPLECARD GSBANK PAYMENT SEATTLE WA", -900.0, "USD");
+        payment.transaction_type = "PAGO_TARJETA".to_string();
+        let transactions = vec![
+            card_charge("CAFE DE REGT AMSTERDAM NLD", -4.5, "EUR"),
+            card_charge("ALBERT HEIJN 1411 AMSTERDAM 1097 DP NH NLD", -20.0, "EUR"),
+            card_charge("HEMA UTRECHT NLD", -10.0, "EUR"),
+            card_charge("STARBUCKS STORE 1234 SEATTLE WA", -6.0, "USD"),
+            card_charge("NETFLIX.COM", -15.49, "USD"),
+            card_charge("REFUND AMSTERDAM NLD", 20.0, "EUR"),
+            payment,
+        ];
+
+        let report = spend_by_country(&transactions);
+        let countries: Vec<Option<&str>> = report.iter().map(|r| r.country_code.as_deref()).collect();
+        assert_eq!(countries, vec![Some("NL"), Some("US"), None]);
+
+        assert_eq!(report[0].transactions, 3);
+        assert!((report[0].totals["EUR"] - 34.5).abs() < 0.001);
+        assert_eq!(report[0].cities[0], ("Amsterdam".to_string(), 2));
+    }
+}
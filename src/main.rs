@@ -2,50 +2,378 @@
 #[cfg(feature = "tui")]
 mod ui;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use rusqlite::Connection;
+use chrono::Datelike;
 use std::env;
+use std::io::{self, BufRead, IsTerminal, Write};
 use std::path::Path;
+use trust_construction::parser::testing as parser_testing;
 
 // Use library instead of local modules
-use trust_construction::{load_csv, setup_database, insert_transactions, get_all_transactions, verify_count};
+use trust_construction::{load_csv, setup_database, insert_transactions_as, verify_count};
+use trust_construction::{get_current_rules, save_rules, AppConfig, ClassificationRule, Locale};
+use trust_construction::{apply_fixes, diagnose, explain_common_queries, CheckStatus};
+use trust_construction::{add_note, get_notes, thread_notes};
+use trust_construction::spend_by_country;
+use trust_construction::{totals_by_bank, totals_by_category, totals_by_month, totals_by_type};
+use trust_construction::{open_read_only, DatabaseSnapshot};
+use trust_construction::{create_archive, read_manifest, restore_archive, ArchiveManifest};
+use trust_construction::{plan_purge, purge_counterparty, ManifestEntry, PurgeMode};
+use trust_construction::mask_transaction;
+use trust_construction::{
+    build_period_report, render_html, summarize_by_fiscal_year, summarize_by_period, DateBasis, PeriodReport,
+    ReportCalendar,
+};
+use trust_construction::{
+    account_balances, find_account, list_accounts, set_account_status, set_opening_balance, Account, AccountStatus,
+    AccountType,
+};
+use trust_construction::{
+    balance_history, record_statement_close, snapshot_accounts, ReconciliationEngine,
+    StatementMetadata, verify_sources,
+};
+use trust_construction::{
+    bill_amount_history, bill_occurrences, create_expected_transaction, ledger_transactions,
+    list_expected_transactions, record_price_changes, remove_expected_transaction, BillOccurrence,
+    BillStatus, ExpectedTransaction,
+};
+use trust_construction::{
+    budgeting_start, current_envelopes, envelope_history, get_current_envelope, ledger_envelope_months,
+    retire_envelope, save_envelope, CarryoverRule, Envelope,
+};
+use trust_construction::{
+    apply_project_rules, assign_transaction, create_project, list_projects, project_report,
+    unassign_transaction, Project, ProjectRules,
+};
+use trust_construction::{EnrichmentPipeline, LocalRulesEnricher, Merchant, MerchantRegistry, MerchantType};
+use trust_construction::{
+    confirmed_alias_groups, propose_alias_groups, record_alias_decision, AliasDecision, AliasGroup,
+    MerchantCategorySuggestion,
+};
+use trust_construction::{
+    advance_dispute, get_dispute_history, list_disputes, open_dispute, stale_disputes, Dispute,
+    DisputeStatus,
+};
+#[cfg(feature = "tui")]
+use trust_construction::get_ledger_notes;
+#[cfg(feature = "tui")]
+use trust_construction::EnvelopeMonth;
+#[cfg(feature = "tui")]
+use trust_construction::pending_clusters;
+use trust_construction::DeduplicationEngine;
+#[cfg(feature = "tui")]
+use trust_construction::{seed_demo_database, set_query_only};
+use trust_construction::{apply_reclassification, migrate_add_uuids, RuleEngine, TransactionFilter};
+use trust_construction::{get_active_transactions, submit_redo, submit_undo};
+use trust_construction::{create_manual_transaction, is_manual, ManualEntry};
+use trust_construction::{
+    complete_transfer, create_transfer, record_fx_slippage, unmatched_transfer_legs, TransferAccount,
+};
+use trust_construction::{settle_stored, SettlementMatcher, PENDING_AMOUNT_KEY};
+use trust_construction::{close_period, closed_periods, reopen_period};
+use trust_construction::{journal_entries, render_journal, JournalFormat};
+use trust_construction::{export_accounting, AccountingExport};
+use trust_construction::{discard_quarantine, quarantined_transactions, release_quarantine, ImportGate, QUARANTINE_KEY};
+use trust_construction::{current_chart, mapping_history, retire_chart_mapping, save_chart_mapping};
+use trust_construction::{ChartAccountType, ChartMapping, MappedFrom};
+use trust_construction::{
+    net_worth, parse_positions_csv, positions_as_of, record_positions, value_positions, HistoricalRates,
+};
+use trust_construction::{
+    approve_change, list_pending_changes, reject_change, WriteOutcome,
+};
+use trust_construction::{count_events, parse_as_of, query_events, EventFilter};
+use trust_construction::{
+    add_webhook, list_outbox, list_webhooks, remove_webhook, retry_failed, DeliveryStatus,
+};
+use trust_construction::{
+    create_ledger, get_current_transaction, list_ledgers, require_ledger, update_ledger_config,
+    Ledger, DEFAULT_LEDGER_ID,
+};
+use trust_construction::{create_user, get_user, list_users, rotate_token, set_user_role, Role};
+use trust_construction::{compare_with_stored, outdated_sources, replay_import, EntityKind, FileOutcome, TrustSystem};
+use trust_construction::{
+    archive_source, get_archived_source, list_archived_sources, tag_source, transaction_provenance, field_origins,
+};
+use trust_construction::{
+    export_changeset, import_changeset, instance_id, merge_changeset, repair_current_conflicts, Changeset, Checkpoint,
+    ConflictPolicy, MergeOptions,
+};
+use trust_construction::{
+    config_dir, generate_signing_key, load_signing_key, parse_public_key, public_key_hex, read_signature,
+    save_signing_key, sign_file, signature_path, verify_artifact, SigningIdentity, SIGNING_KEY_ENV,
+    SIGNING_KEY_SECRET, signing_key_hex,
+};
+use trust_construction::{
+    resolve_secret, secret, secret_env_var, validate_secret_name, OsKeyring, SecretStore, KEYRING_SERVICE,
+    KNOWN_SECRETS,
+};
+
+const DEFAULT_RULES_PATH: &str = "rules/merchants.json";
+
+/// Rules seeded into a fresh database by `init`
+const BUNDLED_RULES: &str = include_str!("../rules/merchants.json");
 
 fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    let ledger_id = take_ledger_flag(&mut args)?;
 
     if args.len() > 1 && args[1] == "import" {
         // Import mode
-        run_import()?;
+        run_import(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "reclassify" {
+        run_reclassify(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && (args[1] == "undo" || args[1] == "redo") {
+        run_undo_redo(&ledger_id, &args[1], &args[2..])?;
+    } else if args.len() > 1 && args[1] == "void" {
+        run_void(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "ledger" {
+        run_ledger(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "user" {
+        run_user(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "changes" {
+        run_changes(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "webhook" {
+        run_webhook(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "events" {
+        run_events(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "note" {
+        run_note(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "dispute" {
+        run_dispute(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "enrich" {
+        run_enrich(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "aliases" {
+        run_aliases(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "entities" {
+        run_entities(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "merchants" {
+        run_merchants(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "categories" {
+        run_categories(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "locations" {
+        run_locations(&ledger_id)?;
+    } else if args.len() > 1 && args[1] == "project" {
+        run_project(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "bills" {
+        run_bills(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "envelopes" {
+        run_envelopes(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "manual" {
+        run_manual(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "transfers" {
+        run_transfers(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "pending" {
+        run_pending(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "statement" {
+        run_statement(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "quarantine" {
+        run_quarantine(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "period" {
+        run_period(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "account" {
+        run_account(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "accounts" {
+        run_accounts(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "positions" {
+        run_positions(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "networth" {
+        run_networth(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "journal" {
+        run_journal(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "export" {
+        run_export(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "chart" {
+        run_chart(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "report" {
+        run_report(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "sync" {
+        run_sync(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "demo" {
+        run_demo(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "init" {
+        run_init()?;
+    } else if args.len() > 1 && args[1] == "doctor" {
+        run_doctor(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "replay" {
+        run_replay(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "parsers" {
+        run_parsers(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "parser" {
+        run_parser(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "provenance" {
+        run_provenance(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "signing" {
+        run_signing(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "secret" {
+        run_secret(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "archive" {
+        run_archive(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "purge" {
+        run_purge(&ledger_id, &args[2..])?;
+    } else if args.len() > 1 && args[1] == "verify" {
+        run_verify(&ledger_id, &args[2..])?;
     } else {
         // UI mode (default)
-        run_ui_mode()?;
+        run_ui_mode(&ledger_id)?;
     }
 
     Ok(())
 }
 
-fn run_import() -> Result<()> {
-    println!("🗄️  Badge 1: Data Import - CSV → SQLite + WAL");
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+/// Open the database configured by `init` (creating its directory); a
+/// ":memory:" db_path gives a fresh in-memory database that is gone on exit
+fn open_database() -> Result<Connection> {
+    let config = AppConfig::load()?;
+    if config.in_memory() {
+        return Ok(Connection::open_in_memory()?);
+    }
+    if let Some(parent) = Path::new(&config.db_path).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(Connection::open(&config.db_path)?)
+}
 
-    // Paths
-    let csv_path = Path::new("/Users/darwinborges/finance/transactions_ALL_SOURCES.csv");
-    let db_path = Path::new("/Users/darwinborges/finance/trust-construction/transactions.db");
+/// Open the configured database for reading only (no setup, no migrations;
+/// in memory there is nothing to protect, so it is an empty migrated one)
+fn open_database_read_only() -> Result<Connection> {
+    let config = AppConfig::load()?;
+    if config.in_memory() {
+        let conn = Connection::open_in_memory()?;
+        setup_database(&conn)?;
+        return Ok(conn);
+    }
+    open_read_only(Path::new(&config.db_path))
+}
 
-    // 1. Load CSV
-    println!("\n📂 Loading CSV...");
-    let transactions = load_csv(csv_path)?;
-    println!("✓ Loaded {} transactions from CSV", transactions.len());
+/// Remove a global `--ledger <id>` flag from the args (any position)
+fn take_ledger_flag(args: &mut Vec<String>) -> Result<String> {
+    match args.iter().position(|arg| arg == "--ledger") {
+        Some(index) => {
+            if index + 1 >= args.len() {
+                return Err(anyhow!("--ledger requires a ledger id"));
+            }
+            let ledger_id = args.remove(index + 1);
+            args.remove(index);
+            Ok(ledger_id)
+        }
+        None => Ok(DEFAULT_LEDGER_ID.to_string()),
+    }
+}
+
+/// Actor recorded on CLI writes
+///
+/// `TRUST_USER` must name a user with at least `required`; otherwise the OS
+/// user is recorded as `cli:<name>`.
+fn cli_actor(conn: &Connection, required: Role) -> Result<String> {
+    match env::var("TRUST_USER") {
+        Ok(username) => {
+            let user = get_user(conn, &username)?
+                .ok_or_else(|| anyhow!("TRUST_USER '{}' is not a known user", username))?;
+            if !user.can(required) {
+                return Err(anyhow!(
+                    "User '{}' ({}) needs the {} role",
+                    user.username,
+                    user.role.as_str(),
+                    required.as_str()
+                ));
+            }
+            Ok(user.username)
+        }
+        Err(_) => Ok(format!(
+            "cli:{}",
+            env::var("USER").unwrap_or_else(|_| "unknown".to_string())
+        )),
+    }
+}
+
+/// Fail if a transaction belongs to a different ledger than the one selected
+fn ensure_in_ledger(conn: &Connection, tx_uuid: &str, ledger_id: &str) -> Result<()> {
+    let current = get_current_transaction(conn, tx_uuid)?
+        .ok_or_else(|| anyhow!("Transaction {} not found", tx_uuid))?;
+    if current.ledger_id != ledger_id {
+        return Err(anyhow!(
+            "Transaction {} belongs to ledger '{}' (use --ledger {})",
+            tx_uuid,
+            current.ledger_id,
+            current.ledger_id
+        ));
+    }
+    Ok(())
+}
+
+/// Open the configured database as a TrustSystem working in `ledger_id` as
+/// the CLI actor
+fn open_system(ledger_id: &str, required: Role) -> Result<TrustSystem> {
+    let system = TrustSystem::from_connection(open_database()?)?.with_ledger(ledger_id)?;
+    let actor = cli_actor(system.conn(), required)?;
+    Ok(system.with_actor(&actor))
+}
+
+/// Usage: import [statement-file [--upsert]] | import --dir <path> [--upsert]
+///
+/// With a file, imports that statement (CSV, JSON or OFX; format detected);
+/// `--upsert` fills blank fields of rows already imported instead of skipping
+/// them. `--dir` imports every statement under a directory, one session per
+/// file. Without either, imports the ledger's configured canonical CSV.
+fn run_import(ledger_id: &str, args: &[String]) -> Result<()> {
+    let upsert = args.iter().any(|arg| arg == "--upsert");
+    if args.first().map(String::as_str) == Some("--dir") {
+        let dir = args.get(1).ok_or_else(|| anyhow!("Usage: import --dir <path> [--upsert]"))?;
+        return run_import_dir(ledger_id, dir, upsert);
+    }
+    if let Some(path) = args.first() {
+        return run_import_file(ledger_id, path, upsert);
+    }
+
+    println!("🗄️  Badge 1: Data Import - CSV → SQLite + WAL");
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 
-    // 2. Setup database
+    // 1. Setup database
     println!("\n🔧 Setting up database...");
-    let conn = Connection::open(db_path)?;
+    let conn = open_database()?;
     setup_database(&conn)?;
     println!("✓ Database initialized with WAL mode");
 
+    let ledger = require_ledger(&conn, ledger_id)?;
+    println!("✓ Ledger: {} ({})", ledger.name, ledger.id);
+
+    // 2. Load CSV
+    let csv_path = ledger.config.import_path.clone().ok_or_else(|| {
+        anyhow!(
+            "No import file configured for ledger '{}' (run: ledger set {} import <path>)",
+            ledger.id,
+            ledger.id
+        )
+    })?;
+    println!("\n📂 Loading CSV...");
+    let mut transactions = load_csv(Path::new(&csv_path))?;
+    let content = std::fs::read(&csv_path)?;
+    let filename = Path::new(&csv_path).file_name().and_then(|name| name.to_str()).unwrap_or(&csv_path);
+    let source_hash = archive_source(&conn, filename, &content)?;
+    let mut masked = 0;
+    for (i, tx) in transactions.iter_mut().enumerate() {
+        tx.ledger_id = ledger.id.clone();
+        tag_source(tx, &source_hash, i + 2);
+        if !mask_transaction(tx).is_empty() {
+            masked += 1;
+        }
+    }
+    println!("✓ Loaded {} transactions from CSV", transactions.len());
+    if masked > 0 {
+        println!("✓ Masked card, IBAN or account numbers in {} descriptions", masked);
+    }
+
     // 3. Insert transactions
     println!("\n💾 Inserting transactions...");
-    insert_transactions(&conn, &transactions)?;
+    let actor = cli_actor(&conn, Role::Editor)?;
+    let summary = insert_transactions_as(&conn, &transactions, &actor)?;
+    println!("✓ Inserted: {} transactions", summary.inserted);
+    println!("✓ Skipped duplicates: {}", summary.duplicates);
+    for failure in &summary.failed {
+        println!("✗ Line {}: {}", failure.line_number, failure.message);
+    }
 
     // 4. Verify count
     println!("\n🔍 Verifying database...");
@@ -66,41 +394,3276 @@ fn run_import() -> Result<()> {
     Ok(())
 }
 
+fn run_import_file(ledger_id: &str, path: &str, upsert: bool) -> Result<()> {
+    let system = open_system(ledger_id, Role::Editor)?;
+    let session = if upsert { system.upsert_file(path)? } else { system.import_file(path)? };
+
+    println!(
+        "📥 {} ({}) → {}: {} rows, {} inserted, {} updated, {} settled, {} duplicates, {} repeated in file, {} failed",
+        session.filename,
+        session.source,
+        session.ledger_id,
+        session.rows,
+        session.inserted,
+        session.updated,
+        session.settled,
+        session.duplicates,
+        session.repeated,
+        session.failed
+    );
+    for issue in &session.issues {
+        println!("  line {:>4}  {:<8?} {}: {}", issue.line, issue.severity, issue.field, issue.message);
+    }
+    if let Some(shortfall) = &session.quarantine {
+        println!("🧪 Quarantined ({}): kept out of reports until reviewed", shortfall);
+        println!("   quarantine release {}  |  quarantine discard {} --reason <why>", session.id, session.id);
+    }
+    if require_ledger(system.conn(), system.ledger_id())?.config.record_imports {
+        println!("🎞️  Recorded as {} (replay with: replay {})", session.id, session.id);
+    }
+
+    Ok(())
+}
+
+/// Every statement under a directory: one line per file, then the totals
+fn run_import_dir(ledger_id: &str, dir: &str, upsert: bool) -> Result<()> {
+    let system = open_system(ledger_id, Role::Editor)?;
+    let import = system.import_directory(dir, upsert)?;
+
+    println!("📂 {} → {}: {} files", dir, ledger_id, import.files.len());
+    for file in &import.files {
+        let path = file.path.strip_prefix(dir).unwrap_or(&file.path).display();
+        match &file.outcome {
+            FileOutcome::Imported(session) => {
+                println!(
+                    "  ✓ {:<32} {:<16} {:>5} rows {:>5} inserted {:>4} updated {:>4} duplicates {:>4} failed{}",
+                    path,
+                    session.source,
+                    session.rows,
+                    session.inserted,
+                    session.updated,
+                    session.duplicates,
+                    session.failed,
+                    if session.quarantine.is_some() { "  🧪 quarantined" } else { "" }
+                );
+            }
+            FileOutcome::Skipped(reason) => println!("  – {:<32} skipped: {}", path, reason),
+            FileOutcome::Failed(error) => println!("  ✗ {:<32} failed: {}", path, error),
+        }
+    }
+
+    let totals = import.totals();
+    println!(
+        "📥 {} imported, {} skipped, {} failed: {} rows, {} inserted, {} updated, {} settled, {} duplicates, \
+         {} repeated in file, {} failed",
+        totals.imported,
+        totals.skipped,
+        totals.failed,
+        totals.rows,
+        totals.inserted,
+        totals.updated,
+        totals.settled,
+        totals.duplicates,
+        totals.repeated,
+        totals.failed_rows
+    );
+    if totals.quarantined > 0 {
+        println!("🧪 {} files quarantined: kept out of reports until reviewed (see: quarantine)", totals.quarantined);
+    }
+    Ok(())
+}
+
+/// Re-run classification over stored transactions
+///
+/// Usage: reclassify [--filter k=v,...] [--rules path] [--dry-run] [--yes]
+fn run_reclassify(ledger_id: &str, args: &[String]) -> Result<()> {
+    let mut filter = TransactionFilter::new();
+    let mut rules_path: Option<String> = None;
+    let mut dry_run = false;
+    let mut assume_yes = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--filter" => {
+                let expr = iter.next().ok_or_else(|| anyhow!("--filter requires a value"))?;
+                filter = TransactionFilter::parse(expr)?;
+            }
+            "--rules" => {
+                rules_path = Some(iter.next().ok_or_else(|| anyhow!("--rules requires a path"))?.clone());
+            }
+            "--dry-run" => dry_run = true,
+            "--yes" | "-y" => assume_yes = true,
+            other => return Err(anyhow!("Unknown reclassify option: {}", other)),
+        }
+    }
+
+    println!("🔁 Reclassify - Re-run rules over stored transactions");
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+    let conn = open_database()?;
+    setup_database(&conn)?;
+    migrate_add_uuids(&conn)?;
+    let ledger = require_ledger(&conn, ledger_id)?;
+    let filter = filter.in_ledger(&ledger.id);
+
+    // Rules: explicit file > database > ledger rules file > default file
+    let engine = match rules_path {
+        Some(path) => RuleEngine::from_file(path)?,
+        None => {
+            let engine = RuleEngine::from_database(&conn)?;
+            if engine.rule_count() > 0 {
+                engine
+            } else {
+                let fallback = AppConfig::load()?.rules_path;
+                RuleEngine::from_file(
+                    ledger
+                        .config
+                        .rules_path
+                        .as_deref()
+                        .or(fallback.as_deref())
+                        .unwrap_or(DEFAULT_RULES_PATH),
+                )?
+            }
+        }
+    };
+    println!("✓ Loaded {} rules", engine.rule_count());
+
+    let transactions = filter.apply(&get_active_transactions(&conn)?);
+    println!("✓ {} transactions match filter", transactions.len());
+
+    let changes = engine.reclassify(&transactions);
+    if changes.is_empty() {
+        println!("\n✅ Nothing to reclassify");
+        return Ok(());
+    }
+
+    println!("\n📋 {} transactions would change:\n", changes.len());
+    for change in &changes {
+        println!(
+            "  {:<40} {:>10.2}  {} / {} → {} / {}  [{}]",
+            change.description.chars().take(40).collect::<String>(),
+            change.amount,
+            change.before.merchant,
+            change.before.category,
+            change.after.merchant,
+            change.after.category,
+            change.after.rule_id.as_deref().unwrap_or("-"),
+        );
+    }
+
+    if dry_run {
+        println!("\n🧪 Dry run - no changes written");
+        return Ok(());
+    }
+
+    if !assume_yes {
+        print!("\nWrite {} new versions? [y/N] ", changes.len());
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().lock().read_line(&mut answer)?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            println!("Aborted");
+            return Ok(());
+        }
+    }
+
+    let actor = cli_actor(&conn, Role::Editor)?;
+    let outcomes = apply_reclassification(&conn, &transactions, &changes, &actor)?;
+    let pending = outcomes.iter().filter(|o| o.is_pending()).count();
+    println!("\n✅ Wrote {} new transaction versions", outcomes.len() - pending);
+    if pending > 0 {
+        println!("⏳ {} changes above the approval threshold await approval (see: changes list)", pending);
+    }
+
+    Ok(())
+}
+
+/// Undo or redo the last change to one transaction
+///
+/// Usage: undo <tx_uuid> | redo <tx_uuid>
+fn run_undo_redo(ledger_id: &str, command: &str, args: &[String]) -> Result<()> {
+    let tx_uuid = args
+        .first()
+        .ok_or_else(|| anyhow!("Usage: {} <transaction-uuid>", command))?;
+
+    let conn = open_database()?;
+    setup_database(&conn)?;
+    ensure_in_ledger(&conn, tx_uuid, ledger_id)?;
+    let actor = cli_actor(&conn, Role::Editor)?;
+
+    let outcome = if command == "undo" {
+        submit_undo(&conn, tx_uuid, &actor)?
+    } else {
+        submit_redo(&conn, tx_uuid, &actor)?
+    };
+
+    match outcome {
+        WriteOutcome::Applied(updated) => println!(
+            "✅ {} → v{}: {} / {} / {}",
+            command, updated.version, updated.merchant, updated.category, updated.transaction_type
+        ),
+        WriteOutcome::Pending(change) => println!(
+            "⏳ {} of {} ({:.2}) needs approval by another user: change {}",
+            command, change.tx_uuid, change.proposed.amount_numeric, change.id
+        ),
+    }
+
+    Ok(())
+}
+
+/// Void a transaction (kept in history, excluded from reports)
+///
+/// Usage: void <tx_uuid> <reason...>
+fn run_void(ledger_id: &str, args: &[String]) -> Result<()> {
+    let (tx_uuid, reason) = args
+        .split_first()
+        .ok_or_else(|| anyhow!("Usage: void <transaction-uuid> <reason>"))?;
+    let reason = reason.join(" ");
+
+    let system = open_system(ledger_id, Role::Editor)?;
+    ensure_in_ledger(system.conn(), tx_uuid, ledger_id)?;
+
+    match system.void(tx_uuid, &reason)? {
+        WriteOutcome::Applied(voided) => println!(
+            "🚫 Voided {} ({} {:.2}) → v{}: {}",
+            voided.id, voided.merchant, voided.amount_numeric, voided.version, reason
+        ),
+        WriteOutcome::Pending(change) => println!(
+            "⏳ Void of {} ({:.2}) needs approval by another user: change {}",
+            change.tx_uuid, change.proposed.amount_numeric, change.id
+        ),
+    }
+
+    Ok(())
+}
+
+/// Show or add notes on a transaction
+///
+/// Usage: note <transaction-uuid> [--reply <note-id>] [text...]
+/// Without text, prints the transaction's notes as threads.
+fn run_note(ledger_id: &str, args: &[String]) -> Result<()> {
+    let (tx_uuid, rest) = args
+        .split_first()
+        .ok_or_else(|| anyhow!("Usage: note <transaction-uuid> [--reply <note-id>] [text...]"))?;
+
+    let conn = open_database()?;
+    setup_database(&conn)?;
+    ensure_in_ledger(&conn, tx_uuid, ledger_id)?;
+
+    let (parent_id, words) = match rest.first().map(String::as_str) {
+        Some("--reply") => {
+            let parent = rest.get(1).ok_or_else(|| anyhow!("--reply requires a note id"))?;
+            (Some(parent.as_str()), &rest[2..])
+        }
+        _ => (None, rest),
+    };
+
+    if words.is_empty() {
+        let notes = get_notes(&conn, tx_uuid)?;
+        println!("📝 {} notes on {}", notes.len(), tx_uuid);
+        for (depth, note) in thread_notes(&notes) {
+            let indent = "  ".repeat(depth + 1);
+            println!(
+                "{}[{}] {} ({})",
+                indent,
+                note.id,
+                note.author,
+                note.created_at.format("%Y-%m-%d %H:%M")
+            );
+            println!("{}  {}", indent, note.text);
+        }
+        return Ok(());
+    }
+
+    let actor = cli_actor(&conn, Role::Viewer)?;
+    let note = add_note(&conn, tx_uuid, &actor, &words.join(" "), parent_id)?;
+    println!("📝 Added note {} to {}", note.id, tx_uuid);
+
+    Ok(())
+}
+
+/// Track disputed charges
+///
+/// Usage: dispute open <transaction-uuid> [note...]
+///        dispute set <dispute-id> <bank_contacted|resolved_refunded|resolved_upheld> [note...]
+///        dispute list [--all] | dispute history <dispute-id> | dispute report [--days N]
+fn run_dispute(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    let note_from = |words: &[String]| {
+        let note = words.join(" ");
+        if note.is_empty() { None } else { Some(note) }
+    };
+
+    match args.first().map(String::as_str) {
+        Some("open") => {
+            let tx_uuid = args.get(1).ok_or_else(|| anyhow!("Usage: dispute open <transaction-uuid> [note]"))?;
+            ensure_in_ledger(&conn, tx_uuid, ledger_id)?;
+            let note = note_from(&args[2..]);
+            let dispute = open_dispute(&conn, tx_uuid, note.as_deref(), &cli_actor(&conn, Role::Editor)?)?;
+            println!("⚖️  Opened dispute {} on {}", dispute.id, tx_uuid);
+        }
+        Some("set") => {
+            let (id, status) = match (args.get(1), args.get(2)) {
+                (Some(id), Some(status)) => (id, DisputeStatus::parse(status)?),
+                _ => return Err(anyhow!("Usage: dispute set <dispute-id> <status> [note]")),
+            };
+            let note = note_from(&args[3..]);
+            let dispute = advance_dispute(&conn, id, status, note.as_deref(), &cli_actor(&conn, Role::Editor)?)?;
+            println!("⚖️  Dispute {} is now {}", dispute.id, dispute.status.as_str());
+        }
+        Some("list") | None => {
+            let open_only = !args.iter().any(|arg| arg == "--all");
+            let disputes = list_disputes(&conn, ledger_id, open_only)?;
+            println!(
+                "⚖️  {} {}disputes in ledger '{}'",
+                disputes.len(),
+                if open_only { "open " } else { "" },
+                ledger_id
+            );
+            for dispute in &disputes {
+                print_dispute(&conn, dispute)?;
+            }
+        }
+        Some("history") => {
+            let id = args.get(1).ok_or_else(|| anyhow!("Usage: dispute history <dispute-id>"))?;
+            for event in get_dispute_history(&conn, id)? {
+                println!(
+                    "  {}  {:<16} {:<18} {}",
+                    event.timestamp.format("%Y-%m-%d %H:%M"),
+                    event.event_type,
+                    event.data["to"].as_str().unwrap_or(""),
+                    event.data["note"].as_str().unwrap_or("")
+                );
+            }
+        }
+        Some("report") => {
+            let days = match args.iter().position(|arg| arg == "--days") {
+                Some(index) => args
+                    .get(index + 1)
+                    .and_then(|d| d.parse::<i64>().ok())
+                    .ok_or_else(|| anyhow!("--days requires a number"))?,
+                None => 30,
+            };
+            let stale = stale_disputes(&conn, ledger_id, days, chrono::Utc::now())?;
+            println!("⚖️  {} disputes open for more than {} days", stale.len(), days);
+            for dispute in &stale {
+                print_dispute(&conn, dispute)?;
+            }
+        }
+        Some(other) => return Err(anyhow!("Unknown dispute command: {}", other)),
+    }
+
+    Ok(())
+}
+
+fn print_dispute(conn: &Connection, dispute: &Dispute) -> Result<()> {
+    let (merchant, amount) = match get_current_transaction(conn, &dispute.tx_uuid)? {
+        Some(tx) => (tx.merchant, tx.amount_numeric),
+        None => (String::from("?"), 0.0),
+    };
+    println!(
+        "  {}  {:<15} {:>4}d  {:>10.2}  {}  {}",
+        dispute.id,
+        dispute.status.as_str(),
+        dispute.age_days(chrono::Utc::now()),
+        amount,
+        merchant,
+        dispute.note.as_deref().unwrap_or("")
+    );
+    Ok(())
+}
+
+/// Look up website, type and logo for the merchants in a ledger
+///
+/// Usage: enrich [--web <endpoint>]
+/// The web lookup needs the `enrichment-web` feature; the `enrich-key` secret
+/// (keyring or TRUST_ENRICH_KEY) is sent as a bearer token when set. Answers
+/// are cached in the database.
+fn run_enrich(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    let mut pipeline = EnrichmentPipeline::new().with_provider(Box::new(LocalRulesEnricher::new()));
+    if let Some(index) = args.iter().position(|arg| arg == "--web") {
+        let endpoint = args.get(index + 1).ok_or_else(|| anyhow!("--web requires an endpoint URL"))?;
+        pipeline = with_web_enricher(pipeline, endpoint)?;
+    }
+
+    let transactions = TransactionFilter::new()
+        .in_ledger(ledger_id)
+        .apply(&get_active_transactions(&conn)?);
+    let mut names: Vec<&str> = transactions.iter().map(|tx| tx.merchant.as_str()).collect();
+    names.sort_unstable();
+    names.dedup();
+
+    let registry = MerchantRegistry::with_defaults();
+    let mut enriched = 0;
+    println!("🔎 Enriching {} merchants", names.len());
+    for name in names.into_iter().filter(|name| !name.trim().is_empty()) {
+        let mut merchant = registry
+            .find_by_string(name)
+            .unwrap_or_else(|| Merchant::new(name.to_string(), MerchantType::Other, None));
+        if pipeline.enrich(&conn, &mut merchant)? {
+            enriched += 1;
+            let details = &merchant.metadata["enrichment"];
+            println!(
+                "  {:<28} {:<16} {}",
+                name,
+                details["merchant_type"].as_str().unwrap_or("-"),
+                details["website"].as_str().unwrap_or("-")
+            );
+        }
+    }
+    println!("✓ {} merchants enriched", enriched);
+
+    Ok(())
+}
+
+#[cfg(feature = "enrichment-web")]
+fn with_web_enricher(pipeline: EnrichmentPipeline, endpoint: &str) -> Result<EnrichmentPipeline> {
+    let api_key = secret("enrich-key")?;
+    Ok(pipeline.with_provider(Box::new(trust_construction::WebApiEnricher::new(endpoint, api_key))))
+}
+
+#[cfg(not(feature = "enrichment-web"))]
+fn with_web_enricher(_pipeline: EnrichmentPipeline, _endpoint: &str) -> Result<EnrichmentPipeline> {
+    Err(anyhow!("Web lookups need a build with --features enrichment-web"))
+}
+
+/// Merchants, banks and accounts found in the ledger's transactions, saved
+/// with the registries so ids stay the same on the next run. `list` shows the
+/// saved registries without scanning; `rename` gives an entity a new name
+/// (a new version, saved).
+///
+/// Usage: entities [merchants|banks|accounts]
+///        | entities list [merchants|banks|categories|accounts]
+///        | entities rename <merchant|bank|category> <name> <new name>
+fn run_entities(ledger_id: &str, args: &[String]) -> Result<()> {
+    match args.first().map(String::as_str) {
+        Some("list") => {
+            let system = open_system(ledger_id, Role::Viewer)?;
+            let kinds = match args.get(1) {
+                Some(kind) => vec![kind.as_str()],
+                None => vec!["merchants", "banks", "categories", "accounts"],
+            };
+            for kind in kinds {
+                print_entities(&system, kind)?;
+            }
+            return Ok(());
+        }
+        Some("rename") => {
+            let usage = || anyhow!("Usage: entities rename <merchant|bank|category> <name> <new name>");
+            let kind = args.get(1).ok_or_else(usage)?;
+            let kind = EntityKind::parse(kind)
+                .ok_or_else(|| anyhow!("Unknown entity kind: {} (merchant, bank, category)", kind))?;
+            let (name, new_name) = (args.get(2).ok_or_else(usage)?, args.get(3).ok_or_else(usage)?);
+            let mut system = open_system(ledger_id, Role::Editor)?;
+            let id = system.rename_entity(kind, name, new_name)?;
+            println!("✏️  Renamed {} '{}' to '{}' ({})", kind.as_str(), name, new_name, id);
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let mut system = TrustSystem::from_connection(open_database()?)?.with_ledger(ledger_id)?;
+    let report = system.bootstrap_entities()?;
+    println!(
+        "🌱 Found {} new merchants ({} spellings as aliases), {} banks, {} accounts in ledger '{}'",
+        report.merchants_added, report.aliases_added, report.banks_added, report.accounts_added, ledger_id
+    );
+    println!("   {} merchants got a suggested category", report.categories_suggested);
+    println!("💾 Saved {} entity versions", system.save_entities()?);
+
+    match args.first().map(String::as_str) {
+        None => Ok(()),
+        Some(kind @ ("merchants" | "banks" | "accounts")) => print_entities(&system, kind),
+        Some(other) => Err(anyhow!("Unknown entities list: {} (merchants, banks, accounts)", other)),
+    }
+}
+
+/// One line per current entity of a registry
+fn print_entities(system: &TrustSystem, kind: &str) -> Result<()> {
+    match kind {
+        "merchants" => {
+            println!("🏪 Merchants ({})", system.merchants.count());
+            for merchant in system.merchants.all_merchants() {
+                // Confidence only for categories suggested from the data
+                let confidence = MerchantCategorySuggestion::recorded(&merchant)
+                    .map_or_else(String::new, |suggestion| format!("{:.0}%", suggestion.confidence * 100.0));
+                println!(
+                    "  {:<28} {:<16} {:>4}  {}",
+                    merchant.canonical_name,
+                    merchant.suggested_category.as_deref().unwrap_or("-"),
+                    confidence,
+                    merchant.aliases.join(", ")
+                );
+            }
+        }
+        "banks" => {
+            println!("🏦 Banks ({})", system.banks.all_banks().len());
+            for bank in system.banks.all_banks() {
+                println!("  {:<28} {:<18} {}", bank.canonical_name, bank.bank_type.as_str(), bank.aliases.join(", "));
+            }
+        }
+        "categories" => {
+            println!("🏷️  Categories ({})", system.categories.count());
+            for category in system.categories.all_categories() {
+                println!(
+                    "  {:<28} {:<10} {}",
+                    category.name,
+                    category.category_type.as_str(),
+                    system.categories.get_path_string(&category)
+                );
+            }
+        }
+        "accounts" => {
+            println!("💳 Accounts ({})", system.accounts.all_accounts().len());
+            for account in system.accounts.all_accounts() {
+                println!(
+                    "  {:<28} {:<8} {:<10} {:>12.2} {}",
+                    account.name,
+                    account.account_number,
+                    account.account_type.as_str(),
+                    account.current_balance,
+                    account.currency
+                );
+            }
+        }
+        other => return Err(anyhow!("Unknown entities list: {} (merchants, banks, categories, accounts)", other)),
+    }
+    Ok(())
+}
+
+/// Saved merchants and their spellings
+///
+/// Usage: merchants [list] | merchants add-alias <merchant> <alias>
+fn run_merchants(ledger_id: &str, args: &[String]) -> Result<()> {
+    match args.first().map(String::as_str) {
+        Some("list") | None => print_entities(&open_system(ledger_id, Role::Viewer)?, "merchants"),
+        Some("add-alias") => {
+            let usage = || anyhow!("Usage: merchants add-alias <merchant> <alias>");
+            let (merchant, alias) = (args.get(1).ok_or_else(usage)?, args.get(2).ok_or_else(usage)?);
+            let mut system = open_system(ledger_id, Role::Editor)?;
+            let merchant = system.add_merchant_alias(merchant, alias)?;
+            println!(
+                "🏷️  {} (v{}) now also matches: {}",
+                merchant.canonical_name,
+                merchant.version,
+                merchant.aliases.join(", ")
+            );
+            Ok(())
+        }
+        Some(other) => Err(anyhow!("Unknown merchants command: {} (list, add-alias)", other)),
+    }
+}
+
+/// Saved categories as a tree
+///
+/// Usage: categories [tree]
+fn run_categories(ledger_id: &str, args: &[String]) -> Result<()> {
+    match args.first().map(String::as_str) {
+        Some("tree") | None => {
+            let system = open_system(ledger_id, Role::Viewer)?;
+            for (depth, category) in system.categories.tree() {
+                let kind = if depth == 0 { format!(" ({})", category.category_type.as_str()) } else { String::new() };
+                println!(
+                    "{}{} {}{}",
+                    "  ".repeat(depth + 1),
+                    category.icon.as_deref().unwrap_or("•"),
+                    category.name,
+                    kind
+                );
+            }
+            Ok(())
+        }
+        Some(other) => Err(anyhow!("Unknown categories command: {} (tree)", other)),
+    }
+}
+
+/// Account balances from the ledger's rows; other subcommands are the
+/// `account` ones
+///
+/// Usage: accounts balance [--as-of YYYY-MM-DD] | accounts <account subcommand>
+fn run_accounts(ledger_id: &str, args: &[String]) -> Result<()> {
+    if args.first().map(String::as_str) != Some("balance") {
+        return run_account(ledger_id, args);
+    }
+    let as_of = match args.iter().position(|arg| arg == "--as-of") {
+        Some(index) => {
+            let raw = args.get(index + 1).ok_or_else(|| anyhow!("--as-of needs a date (YYYY-MM-DD)"))?;
+            Some(chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d").map_err(|_| anyhow!("Bad date: {}", raw))?)
+        }
+        None => None,
+    };
+
+    let system = open_system(ledger_id, Role::Viewer)?;
+    let balances = account_balances(system.conn(), ledger_id, as_of)?;
+    match as_of {
+        Some(as_of) => println!("💰 Account balances in ledger '{}' as of {}", ledger_id, as_of),
+        None => println!("💰 Account balances in ledger '{}'", ledger_id),
+    }
+    let mut totals: std::collections::BTreeMap<&str, f64> = std::collections::BTreeMap::new();
+    for balance in &balances {
+        println!(
+            "  {:<28} {:>5} rows {:>12.2} {}{}",
+            balance.account,
+            balance.rows,
+            balance.balance,
+            balance.currency,
+            if balance.stored { "" } else { "  (no opening balance)" }
+        );
+        *totals.entry(balance.currency.as_str()).or_default() += balance.balance;
+    }
+    for (currency, total) in totals {
+        println!("  Total: {:.2} {}", total, currency);
+    }
+    Ok(())
+}
+
+/// Review merchant spellings that look like one merchant
+///
+/// Usage: aliases [list] | aliases confirm <group> [--canonical <name>] | aliases reject <group>
+///        | aliases confirmed
+/// <group> is the start of a group key as printed by `aliases list`.
+fn run_aliases(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    let find_group = |prefix: Option<&String>| -> Result<AliasGroup> {
+        let prefix = prefix.ok_or_else(|| anyhow!("Usage: aliases confirm|reject <group>"))?;
+        let mut matches: Vec<AliasGroup> = propose_alias_groups(&conn, ledger_id)?
+            .into_iter()
+            .filter(|group| group.key.starts_with(prefix.as_str()))
+            .collect();
+        match matches.len() {
+            1 => Ok(matches.remove(0)),
+            0 => Err(anyhow!("No pending alias group starts with '{}'", prefix)),
+            n => Err(anyhow!("'{}' matches {} groups; use more of the key", prefix, n)),
+        }
+    };
+
+    match args.first().map(String::as_str) {
+        None | Some("list") => {
+            let groups = propose_alias_groups(&conn, ledger_id)?;
+            println!("🔗 {} alias groups to review (ledger '{}')", groups.len(), ledger_id);
+            for group in groups {
+                println!(
+                    "\n  {}  {}  ({} rows, similarity {:.2})",
+                    &group.key[..12],
+                    group.canonical,
+                    group.rows(),
+                    group.similarity
+                );
+                for spelling in &group.spellings {
+                    println!("      {:>5}  {}", spelling.rows, spelling.name);
+                }
+            }
+        }
+        Some("confirm") => {
+            let group = find_group(args.get(1))?;
+            let canonical = args
+                .iter()
+                .position(|arg| arg == "--canonical")
+                .and_then(|i| args.get(i + 1))
+                .map(String::as_str);
+            let actor = cli_actor(&conn, Role::Editor)?;
+            record_alias_decision(&conn, ledger_id, &group, AliasDecision::Confirmed, canonical, &actor)?;
+            println!(
+                "✓ {} spellings are now aliases of {}",
+                group.spellings.len(),
+                canonical.unwrap_or(&group.canonical)
+            );
+        }
+        Some("reject") => {
+            let group = find_group(args.get(1))?;
+            let actor = cli_actor(&conn, Role::Editor)?;
+            record_alias_decision(&conn, ledger_id, &group, AliasDecision::Rejected, None, &actor)?;
+            println!("✗ Kept {} spellings apart", group.spellings.len());
+        }
+        Some("confirmed") => {
+            for group in confirmed_alias_groups(&conn, Some(ledger_id))? {
+                println!("  {:<28} {}", group.canonical, group.aliases.join(", "));
+            }
+        }
+        Some(other) => return Err(anyhow!("Unknown aliases command: {}", other)),
+    }
+
+    Ok(())
+}
+
+/// Spending grouped by the country in card descriptions (travel expenses)
+///
+/// Usage: locations
+fn run_locations(ledger_id: &str) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    let transactions = TransactionFilter::new()
+        .in_ledger(ledger_id)
+        .apply(&get_active_transactions(&conn)?);
+
+    println!("📍 Spending by country (ledger '{}')", ledger_id);
+    for spend in spend_by_country(&transactions) {
+        let totals: Vec<String> = spend
+            .totals
+            .iter()
+            .map(|(currency, total)| format!("{:.2} {}", total, currency))
+            .collect();
+        let cities: Vec<&str> = spend.cities.iter().take(3).map(|(city, _)| city.as_str()).collect();
+        println!(
+            "  {:<8} {:>5} charges  {:<28} {}",
+            spend.country_code.as_deref().unwrap_or("unknown"),
+            spend.transactions,
+            totals.join(", "),
+            cities.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Group transactions into trips/projects
+///
+/// Usage: project list | project create <name> <start> <end> [--country XX] [--city C] [--text T]
+///        | project add|remove <project-id> <transaction-uuid> | project apply <project-id>
+///        | project report <project-id>
+fn run_project(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    let flag = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|index| args.get(index + 1).cloned())
+    };
+    let parse_date = |raw: Option<&String>| {
+        raw.and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+            .ok_or_else(|| anyhow!("Usage: project create <name> <YYYY-MM-DD> <YYYY-MM-DD> [--country XX] [--city C] [--text T]"))
+    };
+
+    match args.first().map(String::as_str) {
+        Some("list") | None => {
+            println!("🧳 Projects in ledger '{}'", ledger_id);
+            for project in list_projects(&conn, ledger_id)? {
+                println!(
+                    "  {}  {:<28} {} → {}",
+                    project.id, project.name, project.start_date, project.end_date
+                );
+            }
+        }
+        Some("create") => {
+            let name = args.get(1).ok_or_else(|| anyhow!("Usage: project create <name> <start> <end>"))?;
+            let (start, end) = (parse_date(args.get(2))?, parse_date(args.get(3))?);
+            let rules = ProjectRules {
+                country_code: flag("--country"),
+                city: flag("--city"),
+                text: flag("--text"),
+            };
+            let project = Project::new(name, start, end).in_ledger(ledger_id).with_rules(rules);
+            let actor = cli_actor(&conn, Role::Editor)?;
+            create_project(&conn, &project, &actor)?;
+            println!("🧳 Created project {} ({})", project.name, project.id);
+
+            let transactions = get_active_transactions(&conn)?;
+            let added = apply_project_rules(&conn, &project.id, &transactions, &actor)?;
+            println!("   {} transactions matched its rules", added);
+        }
+        Some(command @ ("add" | "remove")) => {
+            let (project_id, tx_uuid) = match (args.get(1), args.get(2)) {
+                (Some(project_id), Some(tx_uuid)) => (project_id, tx_uuid),
+                _ => return Err(anyhow!("Usage: project {} <project-id> <transaction-uuid>", command)),
+            };
+            ensure_in_ledger(&conn, tx_uuid, ledger_id)?;
+            let actor = cli_actor(&conn, Role::Editor)?;
+            if command == "add" {
+                assign_transaction(&conn, project_id, tx_uuid, &actor)?;
+                println!("🧳 Added {} to project {}", tx_uuid, project_id);
+            } else if unassign_transaction(&conn, project_id, tx_uuid, &actor)? {
+                println!("🧳 Removed {} from project {}", tx_uuid, project_id);
+            } else {
+                println!("{} was not in project {}", tx_uuid, project_id);
+            }
+        }
+        Some("apply") => {
+            let project_id = args.get(1).ok_or_else(|| anyhow!("Usage: project apply <project-id>"))?;
+            let transactions = get_active_transactions(&conn)?;
+            let added = apply_project_rules(&conn, project_id, &transactions, &cli_actor(&conn, Role::Editor)?)?;
+            println!("🧳 {} transactions added to project {}", added, project_id);
+        }
+        Some("report") => {
+            let project_id = args.get(1).ok_or_else(|| anyhow!("Usage: project report <project-id>"))?;
+            let report = project_report(&conn, project_id)?;
+            println!(
+                "🧳 {} ({} → {}): {} transactions",
+                report.project.name,
+                report.project.start_date,
+                report.project.end_date,
+                report.transactions.len()
+            );
+            for (currency, total) in &report.spend {
+                println!("  Total  {:>12.2} {}", total, currency);
+            }
+            for (category, total) in &report.by_category {
+                println!("  {:<20} {:>12.2}", category, total);
+            }
+            for tx in &report.transactions {
+                println!("    {}  {:<30} {:>10.2}", tx.date, tx.merchant, tx.amount_numeric);
+            }
+        }
+        Some(other) => return Err(anyhow!("Unknown project command: {}", other)),
+    }
+
+    Ok(())
+}
+
+/// Expected transactions (recurring bills) and whether they showed up
+///
+/// Usage: bills list | bills add <name> <day> <amount> <merchant-text> [--tolerance T] [--grace N]
+///        | bills remove <bill-id> | bills report [--months N] | bills prices
+fn run_bills(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    let flag = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|index| args.get(index + 1))
+    };
+
+    match args.first().map(String::as_str) {
+        Some("list") | None => {
+            println!("📅 Bills in ledger '{}'", ledger_id);
+            for bill in list_expected_transactions(&conn, ledger_id)? {
+                println!(
+                    "  {}  day {:>2}  {:<20} {:>10.2} {} (±{:.2}, ±{} days)  \"{}\"",
+                    bill.id,
+                    bill.day_of_month,
+                    bill.name,
+                    bill.amount,
+                    bill.currency,
+                    bill.tolerance,
+                    bill.grace_days,
+                    bill.merchant_pattern
+                );
+            }
+        }
+        Some("add") => {
+            let usage = || anyhow!("Usage: bills add <name> <day> <amount> <merchant-text> [--tolerance T] [--grace N]");
+            let name = args.get(1).ok_or_else(usage)?;
+            let day = args.get(2).and_then(|d| d.parse::<u32>().ok()).ok_or_else(usage)?;
+            let amount = args.get(3).and_then(|a| a.parse::<f64>().ok()).ok_or_else(usage)?;
+            let pattern = args.get(4).ok_or_else(usage)?;
+            let currency = require_ledger(&conn, ledger_id)?
+                .config
+                .default_currency
+                .unwrap_or_else(|| "USD".to_string());
+
+            let mut bill = ExpectedTransaction::new(name, pattern, amount, &currency, day).in_ledger(ledger_id);
+            if let Some(tolerance) = flag("--tolerance") {
+                bill = bill.with_tolerance(tolerance.parse().map_err(|_| anyhow!("--tolerance requires a number"))?);
+            }
+            if let Some(grace) = flag("--grace") {
+                bill = bill.with_grace_days(grace.parse().map_err(|_| anyhow!("--grace requires a number of days"))?);
+            }
+            create_expected_transaction(&conn, &bill, &cli_actor(&conn, Role::Editor)?)?;
+            println!("📅 Expecting {} on day {} ({})", bill.name, bill.day_of_month, bill.id);
+        }
+        Some("remove") => {
+            let id = args.get(1).ok_or_else(|| anyhow!("Usage: bills remove <bill-id>"))?;
+            remove_expected_transaction(&conn, id, &cli_actor(&conn, Role::Editor)?)?;
+            println!("📅 Removed bill {}", id);
+        }
+        Some("report") => {
+            let months = match flag("--months") {
+                Some(months) => months.parse::<u32>().map_err(|_| anyhow!("--months requires a number"))?,
+                None => 1,
+            };
+            let occurrences = ledger_bill_occurrences(&conn, ledger_id, months)?;
+            for status in [BillStatus::Missed, BillStatus::Upcoming, BillStatus::Matched] {
+                let group: Vec<&BillOccurrence> = occurrences.iter().filter(|o| o.status == status).collect();
+                println!("📅 {} {} bills", group.len(), status.as_str());
+                for occurrence in group {
+                    println!(
+                        "  {}  {:<20} {:>10.2} {}  {}",
+                        occurrence.due_date,
+                        occurrence.bill.name,
+                        occurrence.actual_amount.unwrap_or(occurrence.bill.amount),
+                        occurrence.bill.currency,
+                        occurrence.tx_uuid.as_deref().unwrap_or("")
+                    );
+                }
+            }
+        }
+        Some("prices") => {
+            // Scan every bill for new price changes, then show each amount history
+            let transactions = ledger_transactions(&conn, ledger_id)?;
+            let actor = cli_actor(&conn, Role::Editor)?;
+            for bill in list_expected_transactions(&conn, ledger_id)? {
+                for change in record_price_changes(&conn, &bill, &transactions, &actor)? {
+                    println!(
+                        "🔔 {} changed price on {}: {:.2} → {:.2} {} ({:+.1}%)",
+                        bill.name,
+                        change.effective_date,
+                        change.previous_amount,
+                        change.new_amount,
+                        bill.currency,
+                        change.percent_change() * 100.0
+                    );
+                }
+            }
+            for bill in list_expected_transactions(&conn, ledger_id)? {
+                let history = bill_amount_history(&conn, &bill.id)?;
+                println!("📅 {} now {:.2} {} ({} price changes)", bill.name, bill.amount, bill.currency, history.len());
+                for change in history {
+                    let (from, to) = (change.previous_amount, change.new_amount);
+                    println!("  {}  {:>10.2} → {:>10.2}", change.effective_date, from, to);
+                }
+            }
+        }
+        Some(other) => return Err(anyhow!("Unknown bills command: {}", other)),
+    }
+
+    Ok(())
+}
+
+/// Bill occurrences from the start of the last `months` months through the next 30 days
+fn ledger_bill_occurrences(conn: &Connection, ledger_id: &str, months: u32) -> Result<Vec<BillOccurrence>> {
+    let today = chrono::Local::now().date_naive();
+    let this_month = today.with_day(1).unwrap_or(today);
+    let from = this_month
+        .checked_sub_months(chrono::Months::new(months.saturating_sub(1)))
+        .unwrap_or(this_month);
+    let to = today + chrono::Duration::days(30);
+
+    let bills = list_expected_transactions(conn, ledger_id)?;
+    let transactions = TransactionFilter::new()
+        .in_ledger(ledger_id)
+        .apply(&get_active_transactions(conn)?);
+    Ok(bill_occurrences(&bills, &transactions, from, to, today))
+}
+
+/// Envelope budgets: monthly funding, spending drawn from them, the overspend
+/// buffer (`ledger set <id> envelope_buffer <amount>`) and carryover
+///
+/// Usage: envelopes list | envelopes add <name> <monthly-funding> <category>[,<category>...] [--carryover R]
+///        | envelopes fund <envelope-id> <monthly-funding> | envelopes carryover <envelope-id> <R>
+///        | envelopes retire <envelope-id> | envelopes history <envelope-id> | envelopes report [--since YYYY-MM]
+///        (R: full, reset or cap:<amount>)
+fn run_envelopes(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    let flag = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|index| args.get(index + 1))
+    };
+    let current = |id: Option<&String>, usage: &str| -> Result<Envelope> {
+        let id = id.ok_or_else(|| anyhow!("Usage: {}", usage))?;
+        Ok(get_current_envelope(&conn, id)?
+            .ok_or_else(|| anyhow!("Envelope not found or retired: {}", id))?
+            .envelope)
+    };
+
+    match args.first().map(String::as_str) {
+        Some("list") | None => {
+            println!("✉️  Envelopes in ledger '{}'", ledger_id);
+            for version in current_envelopes(&conn, ledger_id)? {
+                let envelope = version.envelope;
+                println!(
+                    "  {}  {:<20} {:>10.2}/month  v{}  {:?}  [{}]",
+                    envelope.id,
+                    envelope.name,
+                    envelope.monthly_funding,
+                    version.version,
+                    envelope.carryover,
+                    envelope.categories.join(", ")
+                );
+            }
+        }
+        Some("add") => {
+            let usage = || anyhow!("Usage: envelopes add <name> <monthly-funding> <category>[,...] [--carryover R]");
+            let name = args.get(1).ok_or_else(usage)?;
+            let funding = args.get(2).and_then(|a| a.parse::<f64>().ok()).ok_or_else(usage)?;
+            let categories = args.get(3).ok_or_else(usage)?;
+
+            let mut envelope = Envelope::new(name, funding).in_ledger(ledger_id);
+            for category in categories.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+                envelope = envelope.with_category(category);
+            }
+            if let Some(rule) = flag("--carryover") {
+                envelope = envelope.with_carryover(CarryoverRule::parse(rule)?);
+            }
+            save_envelope(&conn, &envelope, &cli_actor(&conn, Role::Editor)?, None)?;
+            println!("✉️  Funding {} with {:.2} a month ({})", envelope.name, envelope.monthly_funding, envelope.id);
+        }
+        Some("fund") => {
+            let usage = "envelopes fund <envelope-id> <monthly-funding>";
+            let mut envelope = current(args.get(1), usage)?;
+            envelope.monthly_funding = args
+                .get(2)
+                .and_then(|a| a.parse::<f64>().ok())
+                .ok_or_else(|| anyhow!("Usage: {}", usage))?;
+            let version = save_envelope(&conn, &envelope, &cli_actor(&conn, Role::Editor)?, Some("funding changed"))?;
+            println!("✉️  {} now gets {:.2} a month (v{})", envelope.name, envelope.monthly_funding, version);
+        }
+        Some("carryover") => {
+            let usage = "envelopes carryover <envelope-id> <full|reset|cap:N>";
+            let mut envelope = current(args.get(1), usage)?;
+            envelope.carryover = CarryoverRule::parse(args.get(2).ok_or_else(|| anyhow!("Usage: {}", usage))?)?;
+            let version = save_envelope(&conn, &envelope, &cli_actor(&conn, Role::Editor)?, Some("carryover changed"))?;
+            println!("✉️  {} carries over {:?} (v{})", envelope.name, envelope.carryover, version);
+        }
+        Some("retire") => {
+            let id = args.get(1).ok_or_else(|| anyhow!("Usage: envelopes retire <envelope-id>"))?;
+            retire_envelope(&conn, id, &cli_actor(&conn, Role::Editor)?, None)?;
+            println!("✉️  Retired envelope {}", id);
+        }
+        Some("history") => {
+            let id = args.get(1).ok_or_else(|| anyhow!("Usage: envelopes history <envelope-id>"))?;
+            for version in envelope_history(&conn, id)? {
+                println!(
+                    "  v{}  {}  {:>10.2}/month  {:?}  {} {}",
+                    version.version,
+                    version.valid_from.format("%Y-%m-%d %H:%M"),
+                    version.envelope.monthly_funding,
+                    version.envelope.carryover,
+                    version.changed_by,
+                    version.change_reason.as_deref().unwrap_or("")
+                );
+            }
+        }
+        Some("report") => {
+            let since = match flag("--since") {
+                Some(month) => Some(
+                    chrono::NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+                        .map_err(|_| anyhow!("--since requires a month like 2025-01"))?,
+                ),
+                None => budgeting_start(&conn, ledger_id)?,
+            };
+            let Some(since) = since else {
+                println!("✉️  No envelopes in ledger '{}'", ledger_id);
+                return Ok(());
+            };
+            let today = chrono::Local::now().date_naive();
+            for month in ledger_envelope_months(&conn, ledger_id, since, today)? {
+                println!("✉️  {}  buffer {:.2} → {:.2}", month.month, month.buffer_opening, month.buffer_closing);
+                for envelope in month.envelopes {
+                    println!(
+                        "  {} {:<20} {:>10.2} + {:>9.2} - {:>9.2} + {:>8.2} = {:>10.2}",
+                        if envelope.is_overspent() { "⚠️ " } else { "  " },
+                        envelope.name,
+                        envelope.opening,
+                        envelope.funded,
+                        envelope.spent,
+                        envelope.borrowed,
+                        envelope.closing
+                    );
+                }
+            }
+        }
+        Some(other) => return Err(anyhow!("Unknown envelopes command: {}", other)),
+    }
+
+    Ok(())
+}
+
+/// Enter cash spending or a balance adjustment by hand (dedup-exempt, with
+/// the actor and reason recorded on the row)
+///
+/// Usage: manual add <YYYY-MM-DD> <amount> <description> --reason <why> [--category C] [--merchant M]
+///        [--currency CUR] | manual adjust <YYYY-MM-DD> <amount> <bank> <account> --reason <why>
+///        | manual list
+fn run_manual(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    let flag = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|index| args.get(index + 1))
+    };
+    let date = |arg: Option<&String>| {
+        arg.and_then(|a| chrono::NaiveDate::parse_from_str(a, "%Y-%m-%d").ok())
+    };
+    let amount = |arg: Option<&String>| arg.and_then(|a| a.parse::<f64>().ok());
+    let reason = || flag("--reason").cloned().ok_or_else(|| anyhow!("Manual entries need --reason <why>"));
+
+    match args.first().map(String::as_str) {
+        Some("add") => {
+            let usage = || anyhow!("Usage: manual add <YYYY-MM-DD> <amount> <description> --reason <why>");
+            let mut entry = ManualEntry::cash(
+                date(args.get(1)).ok_or_else(usage)?,
+                amount(args.get(2)).ok_or_else(usage)?,
+                args.get(3).ok_or_else(usage)?,
+            );
+            entry.category = flag("--category").cloned();
+            entry.merchant = flag("--merchant").cloned();
+            entry.currency = flag("--currency").map(|c| c.to_uppercase());
+            let tx = create_manual_transaction(&conn, ledger_id, &entry, &cli_actor(&conn, Role::Editor)?, &reason()?)?;
+            println!("✍️  {} {} {:.2} {} ({})", tx.date, tx.merchant, tx.amount_numeric, tx.currency, tx.id);
+        }
+        Some("adjust") => {
+            let usage = || anyhow!("Usage: manual adjust <YYYY-MM-DD> <amount> <bank> <account> --reason <why>");
+            let (bank, account) = (args.get(3).ok_or_else(usage)?, args.get(4).ok_or_else(usage)?);
+            let entry = ManualEntry::adjustment(
+                date(args.get(1)).ok_or_else(usage)?,
+                amount(args.get(2)).ok_or_else(usage)?,
+                &format!("Balance adjustment for {}", account),
+            )
+            .on_account(bank, account);
+            let tx = create_manual_transaction(&conn, ledger_id, &entry, &cli_actor(&conn, Role::Editor)?, &reason()?)?;
+            println!("✍️  Adjusted {} {} by {:.2} on {} ({})", bank, account, tx.amount_numeric, tx.date, tx.id);
+        }
+        Some("list") | None => {
+            println!("✍️  Manual entries in ledger '{}'", ledger_id);
+            let entries = get_active_transactions(&conn)?
+                .into_iter()
+                .filter(|tx| tx.ledger_id == ledger_id && is_manual(tx));
+            for tx in entries {
+                let text = |key: &str| tx.get_metadata(key).and_then(|v| v.as_str()).unwrap_or("").to_string();
+                println!(
+                    "  {}  {:<24} {:>10.2} {}  {:<10} by {}: {}",
+                    tx.date,
+                    tx.merchant,
+                    tx.amount_numeric,
+                    tx.currency,
+                    text("manual_kind"),
+                    text("entered_by"),
+                    text("entry_reason")
+                );
+            }
+        }
+        Some(other) => return Err(anyhow!("Unknown manual command: {}", other)),
+    }
+
+    Ok(())
+}
+
+/// Move money between two accounts (both legs written together), or write
+/// the missing leg of a transfer whose other statement was never imported
+///
+/// Usage: transfers add <YYYY-MM-DD> <amount> <from-bank:account> <to-bank:account> --reason <why>
+///        | transfers unmatched | transfers complete <tx-uuid> <other-bank:account>
+///        | transfers fx [--tolerance <percent>] [--apply]
+fn run_transfers(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    let flag = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|index| args.get(index + 1))
+    };
+
+    match args.first().map(String::as_str) {
+        Some("add") => {
+            let usage = || anyhow!("Usage: transfers add <YYYY-MM-DD> <amount> <from> <to> --reason <why>");
+            let date = args
+                .get(1)
+                .and_then(|a| chrono::NaiveDate::parse_from_str(a, "%Y-%m-%d").ok())
+                .ok_or_else(usage)?;
+            let amount = args.get(2).and_then(|a| a.parse::<f64>().ok()).ok_or_else(usage)?;
+            let from = TransferAccount::parse(args.get(3).ok_or_else(usage)?)?;
+            let to = TransferAccount::parse(args.get(4).ok_or_else(usage)?)?;
+            let reason = flag("--reason").ok_or_else(|| anyhow!("Transfers need --reason <why>"))?;
+            let actor = cli_actor(&conn, Role::Editor)?;
+            let transfer = create_transfer(&conn, ledger_id, &from, &to, amount, date, &actor, reason)?;
+            println!("🔁 {:.2} from {} to {} on {} ({})", amount, from.label(), to.label(), date, transfer.transfer_id);
+        }
+        Some("unmatched") | None => {
+            let transactions = get_active_transactions(&conn)?
+                .into_iter()
+                .filter(|tx| tx.ledger_id == ledger_id)
+                .collect::<Vec<_>>();
+            let unmatched = unmatched_transfer_legs(&DeduplicationEngine::new(), &transactions);
+            println!("🔁 {} transfer legs without their other side", unmatched.len());
+            for tx in unmatched {
+                println!(
+                    "  {}  {}  {:>10.2}  {} {}  {}",
+                    tx.id, tx.date, tx.amount_numeric, tx.bank, tx.account_name, tx.description
+                );
+            }
+        }
+        Some("complete") => {
+            let usage = || anyhow!("Usage: transfers complete <tx-uuid> <other-bank:account>");
+            let id = args.get(1).ok_or_else(usage)?;
+            let counterpart = TransferAccount::parse(args.get(2).ok_or_else(usage)?)?;
+            let leg = get_current_transaction(&conn, id)?.ok_or_else(|| anyhow!("Transaction not found: {}", id))?;
+            let transfer = complete_transfer(&conn, &leg, &counterpart, &cli_actor(&conn, Role::Editor)?)?;
+            println!(
+                "🔁 Wrote the {} leg on {} ({})",
+                if transfer.incoming.id == leg.id { "outgoing" } else { "incoming" },
+                counterpart.label(),
+                transfer.transfer_id
+            );
+        }
+        Some("fx") => {
+            let mut engine = ReconciliationEngine::new();
+            if let Some(percent) = flag("--tolerance") {
+                let percent = percent.parse().map_err(|_| anyhow!("Bad --tolerance: {}", percent))?;
+                engine = engine.with_fx_tolerance(percent);
+            }
+            let transactions = get_active_transactions(&conn)?
+                .into_iter()
+                .filter(|tx| tx.ledger_id == ledger_id)
+                .collect::<Vec<_>>();
+            let matches = engine.match_fx_converted(&transactions);
+            let apply = args.iter().any(|arg| arg == "--apply");
+            let actor = if apply { Some(cli_actor(&conn, Role::Editor)?) } else { None };
+            println!("💱 {} FX-converted transfers within {:.2}%", matches.len(), engine.fx_tolerance * 100.0);
+            for fx in &matches {
+                println!(
+                    "  {} {} {:>10.2} ↔ {} {} {:>10.2}  slippage {:+.2} ({:.2}%)",
+                    fx.converted.date,
+                    fx.converted.bank,
+                    fx.converted.amount_numeric,
+                    fx.counterpart.date,
+                    fx.counterpart.bank,
+                    fx.counterpart.amount_numeric,
+                    fx.slippage,
+                    fx.percent()
+                );
+                if let Some(actor) = &actor {
+                    let transfer = record_fx_slippage(&conn, fx, actor)?;
+                    println!("    🔁 linked as {}", transfer.transfer_id);
+                }
+            }
+        }
+        Some(other) => return Err(anyhow!("Unknown transfers command: {}", other)),
+    }
+
+    Ok(())
+}
+
+/// Charges a live source reported before they settled
+///
+/// Usage: pending [list] | pending settle
+///
+/// Imports settle pending rows as their settled rows arrive; `settle` pairs
+/// the ones already stored side by side (e.g. imported before this existed).
+fn run_pending(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    match args.first().map(String::as_str) {
+        Some("list") | None => {
+            let pending: Vec<_> = get_active_transactions(&conn)?
+                .into_iter()
+                .filter(|tx| tx.ledger_id == ledger_id && tx.is_pending())
+                .collect();
+            println!("⏳ {} pending transactions (left out of reconciliation)", pending.len());
+            for tx in pending {
+                println!(
+                    "  {}  {}  {:>10.2}  {} {}  {}",
+                    tx.id, tx.date, tx.amount_numeric, tx.bank, tx.account_name, tx.description
+                );
+            }
+        }
+        Some("settle") => {
+            let actor = cli_actor(&conn, Role::Editor)?;
+            let settled = settle_stored(&conn, ledger_id, &SettlementMatcher::new(), &actor)?;
+            println!("⏳ Settled {} pending transactions", settled.len());
+            for tx in settled {
+                let was = tx.get_metadata(PENDING_AMOUNT_KEY).and_then(|v| v.as_f64()).unwrap_or(tx.amount_numeric);
+                println!("  {}  {}  {:>10.2} → {:.2}  {}", tx.id, tx.date, was, tx.amount_numeric, tx.description);
+            }
+        }
+        Some(other) => return Err(anyhow!("Unknown pending command: {}", other)),
+    }
+
+    Ok(())
+}
+
+/// This month's envelope balances (carried from the oldest envelope's month)
 #[cfg(feature = "tui")]
-fn run_ui_mode() -> Result<()> {
-    println!("🖥️  Loading Trust Construction System UI...\n");
+fn current_envelope_month(conn: &Connection, ledger_id: &str) -> Result<Option<EnvelopeMonth>> {
+    let Some(since) = budgeting_start(conn, ledger_id)? else {
+        return Ok(None);
+    };
+    let today = chrono::Local::now().date_naive();
+    Ok(ledger_envelope_months(conn, ledger_id, since, today)?.pop())
+}
 
-    // Open database
-    let db_path = Path::new("/Users/darwinborges/finance/trust-construction/transactions.db");
+/// Income/expense summaries per period (the ledger's period and fiscal-year settings)
+///
+/// Usage: report [summary] [--fiscal-year] [--date-basis transaction|posted]
+///        | report render [--date YYYY-MM-DD] [--out FILE] [--pdf] [--sign]
+///        | report totals [--by type|bank|category|month]
+///        (any of them with --snapshot)
+///
+/// `render` writes the period containing `--date` (default: the last complete
+/// period), so a monthly cron job can archive each report as it closes.
+/// `--sign` adds a detached `<file>.sig` (see `verify`). `totals` groups the
+/// whole ledger in SQL, by calendar month rather than by period.
+/// `--date-basis` overrides the ledger's `date_basis` for the summary: card
+/// charges counted by the day they posted rather than the day they were made.
+///
+/// Reports read through a read-only connection; `--snapshot` copies the
+/// database first, so every number comes from the same moment even while an
+/// import runs.
+fn run_report(ledger_id: &str, args: &[String]) -> Result<()> {
+    // Migrations (and the instance id `--sign` may create) need a writer
+    let writer = open_database()?;
+    setup_database(&writer)?;
+    let reader = open_database_read_only()?;
+    let snapshot = if args.iter().any(|arg| arg == "--snapshot") {
+        Some(DatabaseSnapshot::take(&reader)?)
+    } else {
+        None
+    };
+    let conn: &Connection = snapshot.as_deref().unwrap_or(&reader);
 
-    if !db_path.exists() {
-        eprintln!("❌ Database not found!");
-        eprintln!("   Run: cargo run import");
-        eprintln!("   to import transactions first.");
-        std::process::exit(1);
+    match args.first().map(String::as_str) {
+        Some("render") => {
+            let flag = |name: &str| {
+                args.iter()
+                    .position(|arg| arg == name)
+                    .and_then(|index| args.get(index + 1))
+            };
+            let calendar = ReportCalendar::from_ledger_config(&require_ledger(conn, ledger_id)?.config);
+            let date = match flag("--date") {
+                Some(raw) => chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                    .map_err(|_| anyhow!("--date must be YYYY-MM-DD, got '{}'", raw))?,
+                None => {
+                    let current = calendar.period_containing(chrono::Local::now().date_naive());
+                    current.start.pred_opt().unwrap_or(current.start)
+                }
+            };
+            let period = calendar.period_containing(date);
+            let report = build_period_report(conn, ledger_id, &period)?;
+
+            let pdf = args.iter().any(|arg| arg == "--pdf");
+            let out = match flag("--out") {
+                Some(path) => path.clone(),
+                None => format!("report-{}-{}.{}", ledger_id, period.start, if pdf { "pdf" } else { "html" }),
+            };
+            let locale = AppConfig::load()?.locale();
+            let content =
+                if pdf { render_pdf_bytes(&report, &locale)? } else { render_html(&report, &locale).into_bytes() };
+            std::fs::write(&out, content)?;
+            println!("📈 {} report for {} written to {}", period.label, ledger_id, out);
+            if args.iter().any(|arg| arg == "--sign") {
+                sign_cli_artifact(&writer, Some(ledger_id), "report", Path::new(&out))?;
+            }
+        }
+        Some("summary") | Some("--fiscal-year") | None => {
+            let mut calendar = ReportCalendar::from_ledger_config(&require_ledger(conn, ledger_id)?.config);
+            if let Some(index) = args.iter().position(|arg| arg == "--date-basis") {
+                let basis = args.get(index + 1).ok_or_else(|| anyhow!("--date-basis needs transaction or posted"))?;
+                calendar = calendar.with_date_basis(DateBasis::parse(basis)?);
+            }
+            let transactions = TransactionFilter::new()
+                .in_ledger(ledger_id)
+                .apply(&get_active_transactions(conn)?);
+
+            let summaries = if args.iter().any(|arg| arg == "--fiscal-year") {
+                summarize_by_fiscal_year(&transactions, &calendar)
+            } else {
+                summarize_by_period(&transactions, &calendar)
+            };
+
+            println!("📈 Summary for ledger '{}'", ledger_id);
+            println!("  {:<28} {:>6} {:>12} {:>12} {:>12}", "Period", "Txs", "Income", "Expenses", "Net");
+            for summary in &summaries {
+                println!(
+                    "  {:<28} {:>6} {:>12.2} {:>12.2} {:>12.2}",
+                    summary.period.label, summary.transactions, summary.income, summary.expenses, summary.net()
+                );
+            }
+        }
+        Some("totals") => {
+            let by = args
+                .iter()
+                .position(|arg| arg == "--by")
+                .and_then(|index| args.get(index + 1))
+                .map_or("category", String::as_str);
+            let totals = match by {
+                "type" => totals_by_type(conn, Some(ledger_id))?,
+                "bank" => totals_by_bank(conn, Some(ledger_id))?,
+                "category" => totals_by_category(conn, Some(ledger_id))?,
+                "month" => totals_by_month(conn, Some(ledger_id))?,
+                other => return Err(anyhow!("--by must be type, bank, category or month, got '{}'", other)),
+            };
+
+            println!("📈 Totals by {} for ledger '{}'", by, ledger_id);
+            println!("  {:<28} {:>6} {:>12} {:>12}", by, "Txs", "Net", "Volume");
+            for group in &totals {
+                println!(
+                    "  {:<28} {:>6} {:>12.2} {:>12.2}",
+                    group.key, group.count, group.total, group.absolute_total
+                );
+            }
+        }
+        Some(other) => return Err(anyhow!("Unknown report command: {}", other)),
     }
 
-    let conn = Connection::open(db_path)?;
+    Ok(())
+}
 
-    // Load transactions
-    println!("📊 Loading transactions...");
-    let transactions = get_all_transactions(&conn)?;
-    let total_count = verify_count(&conn)?;
+#[cfg(feature = "report-pdf")]
+fn render_pdf_bytes(report: &PeriodReport, locale: &Locale) -> Result<Vec<u8>> {
+    Ok(trust_construction::render_pdf(report, locale))
+}
 
-    println!("✓ Loaded {} transactions\n", transactions.len());
-    println!("Starting UI... (Press 'q' to quit)\n");
+#[cfg(not(feature = "report-pdf"))]
+fn render_pdf_bytes(_report: &PeriodReport, _locale: &Locale) -> Result<Vec<u8>> {
+    Err(anyhow!("PDF output needs a build with --features report-pdf"))
+}
 
-    // Create and run app
-    let mut app = ui::App::new(transactions, total_count);
-    ui::run_ui(&mut app)?;
+/// Accounts, their opening balances and lifecycle
+///
+/// Usage: account list
+///        | account set-opening-balance <name> <amount> <YYYY-MM-DD>
+///          [--bank B] [--number N] [--type checking|savings|credit|investment|other] [--currency C]
+///        | account close <name> <YYYY-MM-DD> | account freeze <name> | account reopen <name>
+fn run_account(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
 
-    println!("\n✅ UI closed successfully");
+    let flag = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|index| args.get(index + 1))
+    };
+
+    match args.first().map(String::as_str) {
+        Some("list") | None => {
+            println!("🏦 Accounts in ledger '{}'", ledger_id);
+            let accounts = list_accounts(&conn, ledger_id)?;
+            for account in &accounts {
+                let status = match &account.status {
+                    AccountStatus::Closed { closed_on } => format!("closed {}", closed_on),
+                    status => status.as_str().to_string(),
+                };
+                println!(
+                    "  {:<28} {:<10} opening {:>12.2} {} (as of {})  {:<17} v{}",
+                    account.name,
+                    account.account_type.as_str(),
+                    account.opening_balance,
+                    account.currency,
+                    account.metadata["opening_balance"].as_str().unwrap_or("?"),
+                    status,
+                    account.version
+                );
+            }
+            let mut totals: std::collections::BTreeMap<&str, f64> = std::collections::BTreeMap::new();
+            for account in accounts.iter().filter(|account| !account.is_closed()) {
+                *totals.entry(account.currency.as_str()).or_default() += account.current_balance;
+            }
+            for (currency, total) in totals {
+                println!("  Open accounts: {:.2} {}", total, currency);
+            }
+        }
+        Some("set-opening-balance") => {
+            let usage = || anyhow!("Usage: account set-opening-balance <name> <amount> <YYYY-MM-DD> [--bank B] [--number N] [--type T] [--currency C]");
+            let name = args.get(1).ok_or_else(usage)?;
+            let balance: f64 = args.get(2).and_then(|a| a.parse().ok()).ok_or_else(usage)?;
+            let as_of = args
+                .get(3)
+                .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                .ok_or_else(usage)?;
+            let account_type = match flag("--type") {
+                Some(raw) => AccountType::parse(raw).ok_or_else(|| anyhow!("Unknown account type: {}", raw))?,
+                None => AccountType::Checking,
+            };
+            let currency = match flag("--currency") {
+                Some(currency) => currency.clone(),
+                None => require_ledger(&conn, ledger_id)?
+                    .config
+                    .default_currency
+                    .unwrap_or_else(|| "USD".to_string()),
+            };
+
+            let template = Account::new(
+                name.clone(),
+                flag("--number").cloned().unwrap_or_default(),
+                flag("--bank").cloned().unwrap_or_default(),
+                account_type,
+                currency,
+                balance,
+            )
+            .in_ledger(ledger_id);
+            let opening = set_opening_balance(&conn, template, balance, as_of, &cli_actor(&conn, Role::Editor)?)?;
+            println!(
+                "🏦 {} (v{}) opens at {:.2} {} on {}",
+                opening.account.name, opening.account.version, balance, opening.account.currency, as_of
+            );
+            println!("   Balancing transaction: {}", opening.transaction.id);
+        }
+        Some(command @ ("close" | "freeze" | "reopen")) => {
+            let name = args.get(1).ok_or_else(|| anyhow!("Usage: account {} <name>", command))?;
+            let status = match command {
+                "close" => AccountStatus::Closed {
+                    closed_on: args
+                        .get(2)
+                        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                        .ok_or_else(|| anyhow!("Usage: account close <name> <YYYY-MM-DD>"))?,
+                },
+                "freeze" => AccountStatus::Frozen,
+                _ => AccountStatus::Active,
+            };
+            let account = set_account_status(&conn, ledger_id, name, status, &cli_actor(&conn, Role::Editor)?)?;
+            println!("🏦 {} is {} (v{})", account.name, account.status.as_str(), account.version);
+        }
+        Some(other) => return Err(anyhow!("Unknown account command: {}", other)),
+    }
+
+    Ok(())
+}
+
+/// Holdings of investment accounts, from brokerage positions exports
+///
+/// Usage: positions import <file.csv> --account <name> [--as-of YYYY-MM-DD] [--currency C]
+///        | positions list [--date YYYY-MM-DD] [--prices prices.csv]
+///
+/// `--prices` is a `date,from,to,rate` CSV read as `date,SYMBOL,USD,price`;
+/// symbols it doesn't price use the price printed on their statement.
+fn run_positions(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    let flag = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|index| args.get(index + 1))
+    };
+    let date_flag = |name: &str| -> Result<Option<chrono::NaiveDate>> {
+        flag(name)
+            .map(|raw| {
+                chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                    .map_err(|_| anyhow!("{} must be YYYY-MM-DD, got '{}'", name, raw))
+            })
+            .transpose()
+    };
+
+    match args.first().map(String::as_str) {
+        Some("import") => {
+            let usage = || anyhow!("Usage: positions import <file.csv> --account <name> [--as-of YYYY-MM-DD] [--currency C]");
+            let path = args.get(1).ok_or_else(usage)?;
+            let account = flag("--account").ok_or_else(usage)?;
+            let currency = match (flag("--currency"), find_account(&conn, ledger_id, account)?) {
+                (Some(currency), _) => currency.clone(),
+                (None, Some(stored)) => stored.currency,
+                (None, None) => require_ledger(&conn, ledger_id)?
+                    .config
+                    .default_currency
+                    .unwrap_or_else(|| "USD".to_string()),
+            };
+            let content = std::fs::read_to_string(path)?;
+            let statement = parse_positions_csv(&content, account, &currency, date_flag("--as-of")?)?;
+
+            let actor = cli_actor(&conn, Role::Editor)?;
+            let source_file = Path::new(path).file_name().and_then(|n| n.to_str()).unwrap_or(path);
+            let (as_of, positions) = (statement.as_of, &statement.positions);
+            let stored = record_positions(&conn, ledger_id, account, as_of, positions, source_file, &actor)?;
+            println!("📈 {} positions of {} as of {} ({} export)", stored, account, statement.as_of, statement.broker);
+        }
+        Some("list") | None => {
+            let date = date_flag("--date")?.unwrap_or_else(|| chrono::Local::now().date_naive());
+            let prices = load_prices(flag("--prices"))?;
+            let values = value_positions(&positions_as_of(&conn, ledger_id, date)?, &prices, date);
+            println!("📈 Positions in ledger '{}' as of {}", ledger_id, date);
+            for value in values {
+                let position = &value.position;
+                let show = |amount: Option<f64>| amount.map(|a| format!("{:.2}", a)).unwrap_or_else(|| "?".to_string());
+                println!(
+                    "  {:<20} {:<8} {:>12.4} @ {:>10}{}  value {:>12} {}  gain {:>10}  (as of {})",
+                    position.account,
+                    position.symbol,
+                    position.quantity,
+                    show(value.price),
+                    if value.statement_priced { "*" } else { " " },
+                    show(value.market_value),
+                    position.currency,
+                    show(value.unrealized_gain),
+                    position.as_of
+                );
+            }
+            println!("  (* priced from the statement)");
+        }
+        Some(other) => return Err(anyhow!("Unknown positions command: {}", other)),
+    }
 
     Ok(())
 }
 
+/// Security prices for `--prices` (empty without one)
+fn load_prices(path: Option<&String>) -> Result<HistoricalRates> {
+    match path {
+        Some(path) => {
+            let text = std::fs::read_to_string(path)?;
+            HistoricalRates::from_csv(&text)
+        }
+        None => Ok(HistoricalRates::new()),
+    }
+}
+
+/// What the ledger's open accounts are worth, investments at market value
+///
+/// Usage: networth [--date YYYY-MM-DD] [--prices prices.csv]
+fn run_networth(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    let flag = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|index| args.get(index + 1))
+    };
+    let date = match flag("--date") {
+        Some(raw) => chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+            .map_err(|_| anyhow!("--date must be YYYY-MM-DD, got '{}'", raw))?,
+        None => chrono::Local::now().date_naive(),
+    };
+    let worth = net_worth(&conn, ledger_id, &load_prices(flag("--prices"))?, date)?;
+
+    println!("💰 Net worth of ledger '{}' on {}", ledger_id, worth.as_of);
+    for line in &worth.accounts {
+        println!(
+            "  {:<28} {:<10} {:>14.2} {}{}",
+            line.account,
+            line.account_type.as_str(),
+            line.value,
+            line.currency,
+            if line.from_positions { "  (positions)" } else { "" }
+        );
+    }
+    for (currency, total) in &worth.totals {
+        println!("  Total: {:.2} {}", total, currency);
+    }
+    if !worth.unpriced.is_empty() {
+        println!("⚠️  No price for {} (left out): pass --prices", worth.unpriced.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Double-entry journal of the ledger for accounting tools
+///
+/// Usage: journal [--format ledger|beancount|quickbooks] [--from YYYY-MM-DD] [--to YYYY-MM-DD] [--out <file>]
+///
+/// Categories and accounts mapped with `chart map` post to the chart's accounts.
+fn run_journal(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    let flag = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|index| args.get(index + 1))
+    };
+    let date = |name: &str| -> Result<Option<chrono::NaiveDate>> {
+        flag(name)
+            .map(|raw| {
+                chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                    .map_err(|_| anyhow!("{} must be YYYY-MM-DD, got '{}'", name, raw))
+            })
+            .transpose()
+    };
+    let format = JournalFormat::parse(flag("--format").map_or("ledger", String::as_str))?;
+    let (from, to) = (date("--from")?, date("--to")?);
+
+    let transactions: Vec<_> = ledger_transactions(&conn, ledger_id)?
+        .into_iter()
+        .filter(|tx| {
+            tx.parsed_date()
+                .is_none_or(|d| from.is_none_or(|from| d >= from) && to.is_none_or(|to| d <= to))
+        })
+        .collect();
+    let entries = journal_entries(&transactions, &list_accounts(&conn, ledger_id)?, &current_chart(&conn, ledger_id)?)?;
+    let journal = render_journal(&entries, format);
+
+    match flag("--out") {
+        Some(path) => {
+            std::fs::write(path, journal)?;
+            println!("📒 {} balanced entries written to {}", entries.len(), path);
+        }
+        None => print!("{}", journal),
+    }
+
+    Ok(())
+}
+
+/// Import files for QuickBooks or Xero from a ledger query
+///
+/// Usage: export <quickbooks|quickbooks-iif|xero> [--filter k=v,...] [--out <file>]
+///        (Xero gets one statement per bank account: <file> becomes <stem>-<account>.csv when there are several)
+fn run_export(ledger_id: &str, args: &[String]) -> Result<()> {
+    let usage = "Usage: export <quickbooks|quickbooks-iif|xero> [--filter k=v,...] [--out <file>]";
+    let format = AccountingExport::parse(args.first().ok_or_else(|| anyhow!(usage))?)?;
+    let mut filter = TransactionFilter::new();
+    let mut out: Option<&String> = None;
+
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--filter" => {
+                let expr = iter.next().ok_or_else(|| anyhow!("--filter requires a value"))?;
+                filter = TransactionFilter::parse(expr)?;
+            }
+            "--out" => out = Some(iter.next().ok_or_else(|| anyhow!("--out requires a path"))?),
+            other => return Err(anyhow!("Unknown export option: {}", other)),
+        }
+    }
+
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    let transactions = filter.in_ledger(ledger_id).apply(&ledger_transactions(&conn, ledger_id)?);
+    let chart = current_chart(&conn, ledger_id)?;
+    let entries = journal_entries(&transactions, &list_accounts(&conn, ledger_id)?, &chart)?;
+    let files = export_accounting(&entries, &chart, format)?;
+
+    match (out, files.as_slice()) {
+        (None, [file]) => print!("{}", file.contents),
+        (None, _) => {
+            let accounts: Vec<_> = files.iter().filter_map(|file| file.account.as_deref()).collect();
+            return Err(anyhow!(
+                "{} statements ({}): pass --out <file>, or narrow --filter to one account",
+                files.len(),
+                accounts.join(", ")
+            ));
+        }
+        (Some(path), [file]) => {
+            std::fs::write(path, &file.contents)?;
+            println!("🧾 {} entries written to {}", entries.len(), path);
+        }
+        (Some(path), _) => {
+            let stem = std::path::Path::new(path).with_extension("");
+            for file in &files {
+                let account: String = file
+                    .account
+                    .as_deref()
+                    .unwrap_or_default()
+                    .chars()
+                    .map(|c| if c.is_alphanumeric() { c } else { '-' })
+                    .collect();
+                let path = format!("{}-{}.{}", stem.display(), account, format.extension());
+                std::fs::write(&path, &file.contents)?;
+                println!("🧾 {} statement written to {}", file.account.as_deref().unwrap_or_default(), path);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Map categories and bank accounts to the accountant's chart of accounts
+///
+/// Usage: chart list | chart map <category|account> <name> <code> <type> <chart-name...>
+///        | chart unmap <category|account> <name> | chart history <category|account> <name>
+///        (type: asset, liability, equity, income or expense)
+fn run_chart(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    let chart = current_chart(&conn, ledger_id)?;
+    let target = |usage: &str| -> Result<(MappedFrom, &String)> {
+        let from = MappedFrom::parse(args.get(1).ok_or_else(|| anyhow!("Usage: {}", usage))?)?;
+        Ok((from, args.get(2).ok_or_else(|| anyhow!("Usage: {}", usage))?))
+    };
+
+    match args.first().map(String::as_str) {
+        Some("list") | None => {
+            println!("🗂️  Chart of accounts of ledger '{}' ({} mappings)", ledger_id, chart.mappings.len());
+            for mapping in &chart.mappings {
+                println!(
+                    "  {:<8} {:<32} {:<10} ← {} {}",
+                    mapping.code,
+                    mapping.name,
+                    mapping.account_type.root(),
+                    mapping.mapped_from.as_str(),
+                    mapping.internal
+                );
+            }
+        }
+        Some("map") => {
+            let usage = "chart map <category|account> <name> <code> <type> <chart-name...>";
+            let (from, internal) = target(usage)?;
+            let code = args.get(3).ok_or_else(|| anyhow!("Usage: {}", usage))?;
+            let account_type = ChartAccountType::parse(args.get(4).ok_or_else(|| anyhow!("Usage: {}", usage))?)?;
+            let name = args.get(5..).map(|words| words.join(" ")).unwrap_or_default();
+
+            // Mapping it again versions the existing mapping
+            let mapping = match chart.lookup(from, internal) {
+                Some(existing) => ChartMapping {
+                    code: code.clone(),
+                    name: name.trim().to_string(),
+                    account_type,
+                    ..existing.clone()
+                },
+                None => ChartMapping::new(from, internal, code, &name, account_type).in_ledger(ledger_id),
+            };
+            let version = save_chart_mapping(&conn, &mapping, &cli_actor(&conn, Role::Editor)?, None)?;
+            println!("🗂️  {} {} → {} {} (v{})", from.as_str(), mapping.internal, mapping.code, mapping.name, version);
+        }
+        Some("unmap") => {
+            let (from, internal) = target("chart unmap <category|account> <name>")?;
+            let mapping = chart.lookup(from, internal).ok_or_else(|| anyhow!("{} is not mapped", internal))?;
+            retire_chart_mapping(&conn, &mapping.id, &cli_actor(&conn, Role::Editor)?, None)?;
+            println!("🗂️  {} {} no longer maps to {}", from.as_str(), mapping.internal, mapping.code);
+        }
+        Some("history") => {
+            let (from, internal) = target("chart history <category|account> <name>")?;
+            let mapping = chart.lookup(from, internal).ok_or_else(|| anyhow!("{} is not mapped", internal))?;
+            for version in mapping_history(&conn, &mapping.id)? {
+                println!(
+                    "  v{}  {}  {} {}  by {}{}",
+                    version.version,
+                    version.valid_from.format("%Y-%m-%d %H:%M"),
+                    version.mapping.code,
+                    version.mapping.name,
+                    version.changed_by,
+                    version.change_reason.map(|reason| format!(": {}", reason)).unwrap_or_default()
+                );
+            }
+        }
+        Some(other) => return Err(anyhow!("Unknown chart command: {} (list, map, unmap, history)", other)),
+    }
+
+    Ok(())
+}
+
+/// Balance snapshots at statement close and their continuity
+///
+/// Usage: statement close <account-id> <period> <YYYY-MM-DD> <opening> <closing>
+///          [--count N] [--total X]
+///        | statement history [account-id]
+///
+/// `--count` and `--total` record what the statement says it lists (number of
+/// transactions, their net) for `verify manifest`.
+fn run_statement(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+    let engine = ReconciliationEngine::new();
+
+    match args.first().map(String::as_str) {
+        Some("close") => {
+            let usage = || anyhow!("Usage: statement close <account-id> <period> <YYYY-MM-DD> <opening> <closing>");
+            let account_id = args.get(1).ok_or_else(usage)?;
+            let mut statement = StatementMetadata {
+                account_name: account_id.clone(),
+                statement_period: args.get(2).ok_or_else(usage)?.clone(),
+                statement_date: args
+                    .get(3)
+                    .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                    .ok_or_else(usage)?,
+                opening_balance: args.get(4).and_then(|b| b.parse().ok()).ok_or_else(usage)?,
+                closing_balance: args.get(5).and_then(|b| b.parse().ok()).ok_or_else(usage)?,
+                declared_count: None,
+                declared_total: None,
+            };
+            let mut i = 6;
+            while i < args.len() {
+                match (args[i].as_str(), args.get(i + 1)) {
+                    ("--count", Some(value)) => {
+                        statement.declared_count = Some(value.parse().map_err(|_| anyhow!("--count must be a number"))?)
+                    }
+                    ("--total", Some(value)) => {
+                        statement.declared_total = Some(value.parse().map_err(|_| anyhow!("--total must be an amount"))?)
+                    }
+                    _ => return Err(usage()),
+                }
+                i += 2;
+            }
+            let actor = cli_actor(&conn, Role::Editor)?;
+            let snapshot = record_statement_close(&conn, account_id, ledger_id, &statement, &actor)?;
+            println!(
+                "🧾 {} {}: {:.2} → {:.2}",
+                snapshot.account_id, snapshot.statement_period, snapshot.opening_balance, snapshot.closing_balance
+            );
+            for discrepancy in engine.check_continuity(&balance_history(&conn, account_id, ledger_id)?) {
+                println!("  ⚠️  {}", discrepancy.description);
+            }
+        }
+        Some("history") | None => {
+            let accounts = match args.get(1) {
+                Some(account_id) => vec![account_id.clone()],
+                None => snapshot_accounts(&conn, ledger_id)?,
+            };
+            for account_id in accounts {
+                let history = balance_history(&conn, &account_id, ledger_id)?;
+                println!("🧾 {} ({} statements)", account_id, history.len());
+                for snapshot in &history {
+                    println!(
+                        "  {}  {:<20} {:>12.2} → {:>12.2}",
+                        snapshot.statement_date, snapshot.statement_period, snapshot.opening_balance, snapshot.closing_balance
+                    );
+                }
+                let breaks = engine.check_continuity(&history);
+                if breaks.is_empty() {
+                    println!("  ✓ Balances chain from statement to statement");
+                }
+                for discrepancy in breaks {
+                    println!("  ⚠️  {} (gap {:.2})", discrepancy.description, discrepancy.amount);
+                }
+            }
+        }
+        Some(other) => return Err(anyhow!("Unknown statement command: {}", other)),
+    }
+
+    Ok(())
+}
+
+/// Lock reconciled statement periods against corrections
+///
+/// Usage: period close <account-id> <period> | period reopen <account-id> <period> --reason <why>
+///        | period list
+///
+/// `<period>` is the statement's period label or its close date (see
+/// `statement history`).
+fn run_period(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    match args.first().map(String::as_str) {
+        Some(command @ ("close" | "reopen")) => {
+            let usage = || anyhow!("Usage: period {} <account-id> <period>", command);
+            let account_id = args.get(1).ok_or_else(usage)?;
+            let period = args.get(2).ok_or_else(usage)?;
+            let actor = cli_actor(&conn, Role::Editor)?;
+            if command == "close" {
+                let closed = close_period(&conn, ledger_id, account_id, period, &actor)?;
+                println!(
+                    "🔒 Closed {} {} ({} – {})",
+                    closed.account_id, closed.statement_period, closed.from, closed.to
+                );
+            } else {
+                let reason = args
+                    .iter()
+                    .position(|arg| arg == "--reason")
+                    .and_then(|index| args.get(index + 1))
+                    .ok_or_else(|| anyhow!("Reopening a period needs --reason <why>"))?;
+                let reopened = reopen_period(&conn, ledger_id, account_id, period, reason, &actor)?;
+                println!("🔓 Reopened {} {}: {}", reopened.account_id, reopened.statement_period, reason);
+            }
+        }
+        Some("list") | None => {
+            let closed = closed_periods(&conn, ledger_id)?;
+            println!("🔒 {} closed periods", closed.len());
+            for period in closed {
+                println!(
+                    "  {:<20} {:<16} {} – {}  by {}",
+                    period.account_id, period.statement_period, period.from, period.to, period.closed_by
+                );
+            }
+        }
+        Some(other) => return Err(anyhow!("Unknown period command: {} (close, reopen, list)", other)),
+    }
+
+    Ok(())
+}
+
+/// Review imports held back by the ledger's import policy
+///
+/// Usage: quarantine list | quarantine release <import-id> | quarantine discard <import-id> --reason <why>
+fn run_quarantine(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    match args.first().map(String::as_str) {
+        Some(command @ ("release" | "discard")) => {
+            let session_id = args.get(1).ok_or_else(|| anyhow!("Usage: quarantine {} <import-id>", command))?;
+            let actor = cli_actor(&conn, Role::Editor)?;
+            if command == "release" {
+                let released = release_quarantine(&conn, ledger_id, session_id, &actor)?;
+                println!("✅ Released {} rows of import {} into '{}'", released, session_id, ledger_id);
+            } else {
+                let reason = args
+                    .iter()
+                    .position(|arg| arg == "--reason")
+                    .and_then(|index| args.get(index + 1))
+                    .ok_or_else(|| anyhow!("Discarding an import needs --reason <why>"))?;
+                let discarded = discard_quarantine(&conn, ledger_id, session_id, reason, &actor)?;
+                println!("🗑️  Voided {} rows of import {}: {}", discarded, session_id, reason);
+            }
+        }
+        Some("list") | None => {
+            let rows = quarantined_transactions(&conn, ledger_id, None)?;
+            let mut sessions: std::collections::BTreeMap<&str, Vec<&trust_construction::Transaction>> =
+                std::collections::BTreeMap::new();
+            for tx in &rows {
+                let session = tx.get_metadata(QUARANTINE_KEY).and_then(|id| id.as_str()).unwrap_or_default();
+                sessions.entry(session).or_default().push(tx);
+            }
+            println!("🧪 {} quarantined rows in {} imports", rows.len(), sessions.len());
+            for (session, rows) in sessions {
+                let total: f64 = rows.iter().map(|tx| tx.amount_numeric).sum();
+                println!("  {}  {} rows from {}, net {:.2}", session, rows.len(), rows[0].source_file, total);
+            }
+        }
+        Some(other) => return Err(anyhow!("Unknown quarantine command: {} (list, release, discard)", other)),
+    }
+
+    Ok(())
+}
+
+/// Manage ledgers
+///
+/// Usage: ledger list | ledger create <id> <name...> | ledger set <id> <key> <value>
+fn run_ledger(args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    match args.first().map(String::as_str) {
+        Some("list") | None => {
+            println!("📒 Ledgers");
+            for ledger in list_ledgers(&conn)? {
+                println!("  {:<16} {}", ledger.id, ledger.name);
+                if let Some(currency) = &ledger.config.default_currency {
+                    println!("  {:<16}   currency: {}", "", currency);
+                }
+                if let Some(rules) = &ledger.config.rules_path {
+                    println!("  {:<16}   rules: {}", "", rules);
+                }
+                if let Some(import) = &ledger.config.import_path {
+                    println!("  {:<16}   import: {}", "", import);
+                }
+                if let Some(buffer) = ledger.config.envelope_buffer {
+                    println!("  {:<16}   envelope buffer: {:.2}", "", buffer);
+                }
+                if let Some(policy) = ledger.config.import_policy {
+                    let gate = ImportGate::from_config(&ledger.config);
+                    println!(
+                        "  {:<16}   import policy: {} (quality ≥ {:.0}%, confidence ≥ {:.0}%)",
+                        "",
+                        policy.as_str(),
+                        gate.min_quality * 100.0,
+                        gate.min_confidence * 100.0
+                    );
+                }
+            }
+        }
+        Some("create") => {
+            let id = args.get(1).ok_or_else(|| anyhow!("Usage: ledger create <id> <name>"))?;
+            let name = if args.len() > 2 { args[2..].join(" ") } else { id.clone() };
+            create_ledger(&conn, &Ledger::new(id, &name), &cli_actor(&conn, Role::Admin)?)?;
+            println!("✅ Created ledger '{}' ({})", id, name);
+        }
+        Some("set") => {
+            let (id, key, value) = match &args[1..] {
+                [id, key, value] => (id, key, value),
+                _ => return Err(anyhow!("Usage: ledger set <id> <key> <value>")),
+            };
+            let mut config = require_ledger(&conn, id)?.config;
+            config.set(key, value)?;
+            update_ledger_config(&conn, id, &config, &cli_actor(&conn, Role::Admin)?)?;
+            println!("✅ {}.{} = {}", id, key, value);
+        }
+        Some(other) => return Err(anyhow!("Unknown ledger command: {}", other)),
+    }
+
+    Ok(())
+}
+
+/// Manage server users
+///
+/// Usage: user list | user add <name> <role> | user role <name> <role> | user token <name>
+fn run_user(args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    match args.first().map(String::as_str) {
+        Some("list") | None => {
+            println!("👥 Users");
+            for user in list_users(&conn)? {
+                println!("  {:<20} {}", user.username, user.role.as_str());
+            }
+        }
+        Some("add") => {
+            let (name, role) = match &args[1..] {
+                [name, role] => (name, Role::parse(role)?),
+                _ => return Err(anyhow!("Usage: user add <name> <viewer|editor|admin>")),
+            };
+            let (user, token) = create_user(&conn, name, role, &cli_actor(&conn, Role::Admin)?)?;
+            println!("✅ Created {} ({})", user.username, user.role.as_str());
+            println!("   API token (shown once): {}", token);
+        }
+        Some("role") => {
+            let (name, role) = match &args[1..] {
+                [name, role] => (name, Role::parse(role)?),
+                _ => return Err(anyhow!("Usage: user role <name> <viewer|editor|admin>")),
+            };
+            let user = set_user_role(&conn, name, role, &cli_actor(&conn, Role::Admin)?)?;
+            println!("✅ {} is now {}", user.username, user.role.as_str());
+        }
+        Some("token") => {
+            let name = args.get(1).ok_or_else(|| anyhow!("Usage: user token <name>"))?;
+            let token = rotate_token(&conn, name, &cli_actor(&conn, Role::Admin)?)?;
+            println!("✅ New API token for {} (shown once): {}", name, token);
+        }
+        Some(other) => return Err(anyhow!("Unknown user command: {}", other)),
+    }
+
+    Ok(())
+}
+
+/// Review corrections/voids awaiting approval
+///
+/// Usage: changes list | changes approve <id> | changes reject <id> [note...]
+fn run_changes(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    match args.first().map(String::as_str) {
+        Some("list") | None => {
+            let changes = list_pending_changes(&conn, Some(ledger_id))?;
+            println!("⏳ {} pending changes in ledger '{}'", changes.len(), ledger_id);
+            for change in changes {
+                println!(
+                    "  {}  {:<10} {:>10.2}  {} / {}  by {}",
+                    change.id,
+                    change.kind.as_str(),
+                    change.proposed.amount_numeric,
+                    change.proposed.merchant,
+                    change.proposed.category,
+                    change.proposed_by
+                );
+            }
+        }
+        Some("approve") => {
+            let id = args.get(1).ok_or_else(|| anyhow!("Usage: changes approve <id>"))?;
+            let tx = approve_change(&conn, id, &cli_actor(&conn, Role::Editor)?)?;
+            println!("✅ Approved: {} is now v{}", tx.id, tx.version);
+        }
+        Some("reject") => {
+            let id = args.get(1).ok_or_else(|| anyhow!("Usage: changes reject <id> [note]"))?;
+            let note = args[2..].join(" ");
+            let note = if note.is_empty() { None } else { Some(note.as_str()) };
+            reject_change(&conn, id, &cli_actor(&conn, Role::Editor)?, note)?;
+            println!("❌ Rejected change {}", id);
+        }
+        Some(other) => return Err(anyhow!("Unknown changes command: {}", other)),
+    }
+
+    Ok(())
+}
+
+/// Audit trail: events of the ledger, newest first
+///
+/// Usage: events [--type <event_type>] [--actor <user>] [--entity <entity_type>]
+///        [--since <date>] [--until <date>] [--month YYYY-MM]
+///        [--limit N] [--page N] [--all-ledgers] [--json]
+///
+/// Dates are YYYY-MM-DD (a whole day) or RFC 3339. Example, every correction
+/// made in September: `events --type transaction_corrected --month 2025-09`
+fn run_events(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    let mut filter = EventFilter { ledger_id: Some(ledger_id.to_string()), limit: Some(50), ..Default::default() };
+    let mut page = 1;
+    let mut json = false;
+    let mut i = 0;
+    while i < args.len() {
+        let flag = args[i].as_str();
+        match flag {
+            "--all-ledgers" => filter.ledger_id = None,
+            "--json" => json = true,
+            _ => {
+                let value = args.get(i + 1).ok_or_else(|| anyhow!("{} requires a value", flag))?;
+                match flag {
+                    "--type" => filter.event_type = Some(value.clone()),
+                    "--actor" => filter.actor = Some(value.clone()),
+                    "--entity" => filter.entity_type = Some(value.clone()),
+                    "--since" => filter.since = Some(parse_since(value)?),
+                    "--until" => filter.until = Some(parse_as_of(value)?),
+                    "--month" => {
+                        let first = chrono::NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d")
+                            .map_err(|_| anyhow!("--month expects YYYY-MM"))?;
+                        let last = first
+                            .checked_add_months(chrono::Months::new(1))
+                            .and_then(|next| next.pred_opt())
+                            .ok_or_else(|| anyhow!("--month out of range"))?;
+                        filter.since = Some(parse_since(&first.to_string())?);
+                        filter.until = Some(parse_as_of(&last.to_string())?);
+                    }
+                    "--limit" => filter.limit = Some(value.parse().map_err(|_| anyhow!("--limit must be a number"))?),
+                    "--page" => {
+                        page = value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|page| *page > 0)
+                            .ok_or_else(|| anyhow!("--page must be 1 or more"))?
+                    }
+                    other => return Err(anyhow!("Unknown events option: {}", other)),
+                }
+                i += 1;
+            }
+        }
+        i += 1;
+    }
+    filter.offset = (page - 1) * filter.limit.unwrap_or_default();
+
+    let events = query_events(&conn, &filter)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&events)?);
+        return Ok(());
+    }
+
+    let total = count_events(&conn, &filter)?;
+    let scope = filter.ledger_id.as_deref().map_or("all ledgers".to_string(), |id| format!("ledger '{}'", id));
+    println!(
+        "📜 {} events in {} (showing {}-{})",
+        total,
+        scope,
+        (filter.offset + 1).min(total),
+        filter.offset + events.len()
+    );
+    for event in &events {
+        println!(
+            "  {}  {:<24} {:<12} {:<38} by {}",
+            event.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            event.event_type,
+            event.entity_type,
+            event.entity_id,
+            event.actor
+        );
+    }
+    if filter.offset + events.len() < total {
+        println!("  … more: --page {}", page + 1);
+    }
+
+    Ok(())
+}
+
+/// Push events to external systems
+///
+/// Usage: webhook list | webhook add <url> [event_type...] [--all-ledgers]
+///        | webhook remove <id> | webhook outbox [pending|delivered|failed]
+///        | webhook retry [id] | webhook deliver
+///
+/// Without event types the webhook receives every event. `deliver` sends the
+/// due outbox rows once (the server does this continuously); it needs a build
+/// with `--features webhooks`.
+fn run_webhook(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    match args.first().map(String::as_str) {
+        Some("list") | None => {
+            let webhooks = list_webhooks(&conn)?;
+            println!("🪝 {} webhooks", webhooks.len());
+            for webhook in webhooks {
+                let types = if webhook.event_types.is_empty() { "all events".to_string() } else { webhook.event_types.join(", ") };
+                println!(
+                    "  {}  {}  [{}] ledger {}",
+                    webhook.id,
+                    webhook.url,
+                    types,
+                    webhook.ledger_id.as_deref().unwrap_or("*")
+                );
+            }
+        }
+        Some("add") => {
+            let url = args.get(1).ok_or_else(|| anyhow!("Usage: webhook add <url> [event_type...] [--all-ledgers]"))?;
+            let all_ledgers = args.iter().any(|arg| arg == "--all-ledgers");
+            let event_types: Vec<String> = args[2..].iter().filter(|arg| !arg.starts_with("--")).cloned().collect();
+            let actor = cli_actor(&conn, Role::Admin)?;
+            let webhook = add_webhook(&conn, url, &event_types, (!all_ledgers).then_some(ledger_id), &actor)?;
+            println!("🪝 Added webhook {}", webhook.id);
+            println!("   Secret (shown once, verifies X-Trust-Signature): {}", webhook.secret);
+        }
+        Some("remove") => {
+            let id = args.get(1).ok_or_else(|| anyhow!("Usage: webhook remove <id>"))?;
+            let webhook = remove_webhook(&conn, id, &cli_actor(&conn, Role::Admin)?)?;
+            println!("🗑️  Removed webhook {} ({})", webhook.id, webhook.url);
+        }
+        Some("outbox") => {
+            let status = args.get(1).map(|status| DeliveryStatus::parse(status)).transpose()?;
+            for entry in list_outbox(&conn, status, 50)? {
+                println!(
+                    "  {:>6}  {:<9} {:<24} tries {}  next {}  {}",
+                    entry.id,
+                    entry.status.as_str(),
+                    entry.event_type,
+                    entry.attempts,
+                    entry.next_attempt_at.format("%Y-%m-%d %H:%M:%S"),
+                    entry.last_error.as_deref().unwrap_or("")
+                );
+            }
+        }
+        Some("retry") => {
+            let requeued = retry_failed(&conn, args.get(1).map(String::as_str))?;
+            println!("🔁 {} failed deliveries queued again", requeued);
+        }
+        Some("deliver") => {
+            let report = deliver_webhooks(&conn)?;
+            println!(
+                "📤 {} delivered, {} to retry, {} failed",
+                report.delivered, report.retrying, report.failed
+            );
+        }
+        Some(other) => return Err(anyhow!("Unknown webhook command: {}", other)),
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "webhooks")]
+fn deliver_webhooks(conn: &Connection) -> Result<trust_construction::DeliveryReport> {
+    let transport = trust_construction::HttpTransport::default();
+    trust_construction::deliver_due(conn, &transport, chrono::Utc::now())
+}
+
+#[cfg(not(feature = "webhooks"))]
+fn deliver_webhooks(_conn: &Connection) -> Result<trust_construction::DeliveryReport> {
+    Err(anyhow!("Webhook delivery needs a build with --features webhooks"))
+}
+
+/// "2025-01-31T12:00:00Z", or a date meaning the start of that day (UTC)
+fn parse_since(text: &str) -> Result<chrono::DateTime<chrono::Utc>> {
+    match chrono::NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d") {
+        Ok(date) => Ok(date.and_hms_opt(0, 0, 0).expect("midnight exists").and_utc()),
+        Err(_) => parse_as_of(text),
+    }
+}
+
+/// Exchange signed changesets with another instance
+///
+/// Usage: sync export [--since <checkpoint>] [--out <file>] | sync import <file> | sync check
+///        sync merge <file> [--map <source>=<target>]... [--policy last-writer-wins|manual]
+///
+/// Both instances must share the `sync-key` secret (OS keyring, else
+/// `TRUST_SYNC_KEY`). `sync check`
+/// resolves transactions that ended up with more than one current version.
+/// `sync merge` folds a separately kept instance into this one: its ledgers
+/// are renamed through `--map`, and lines already imported here are mapped
+/// onto the local transactions.
+fn run_sync(args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    if args.first().map(String::as_str) == Some("check") {
+        let conflicts = repair_current_conflicts(&conn, &cli_actor(&conn, Role::Editor)?)?;
+        println!("⚔️  Resolved {} conflicting current versions", conflicts.len());
+        for conflict in conflicts {
+            println!(
+                "  {}  kept v{} ({}), expired v{} ({})",
+                conflict.local.id,
+                conflict.local.version,
+                conflict.local.category,
+                conflict.incoming.version,
+                conflict.incoming.category
+            );
+        }
+        return Ok(());
+    }
+
+    let key = secret("sync-key")?.ok_or_else(|| {
+        anyhow!("Store the key shared by both instances with `secret set sync-key` (or set TRUST_SYNC_KEY)")
+    })?;
+
+    match args.first().map(String::as_str) {
+        Some("export") => {
+            let mut since = Checkpoint::default();
+            let mut out = None;
+            let mut sign = false;
+            let mut i = 1;
+            while i < args.len() {
+                match (args[i].as_str(), args.get(i + 1)) {
+                    ("--sign", _) => {
+                        sign = true;
+                        i += 1;
+                        continue;
+                    }
+                    ("--since", Some(value)) => since = Checkpoint::parse(value)?,
+                    ("--out", Some(value)) => out = Some(value.clone()),
+                    (other, _) => return Err(anyhow!("Unknown or incomplete sync export option: {}", other)),
+                }
+                i += 2;
+            }
+            if sign && out.is_none() {
+                return Err(anyhow!("--sign needs --out: the signature is written next to the file"));
+            }
+
+            let changeset = export_changeset(&conn, since, &key)?;
+            let json = serde_json::to_string_pretty(&changeset)?;
+            match &out {
+                Some(path) => std::fs::write(path, json)?,
+                None => println!("{}", json),
+            }
+            if let (true, Some(path)) = (sign, &out) {
+                sign_cli_artifact(&conn, None, "changeset", Path::new(path))?;
+            }
+
+            // Status goes to stderr so stdout can be redirected to a file
+            eprintln!(
+                "📤 Exported {} versions, {} events",
+                changeset.versions.len(),
+                changeset.events.len()
+            );
+            eprintln!("   Next time: sync export --since {}", changeset.until);
+        }
+        Some("import") => {
+            let path = args.get(1).ok_or_else(|| anyhow!("Usage: sync import <file>"))?;
+            let content = std::fs::read(path)?;
+            // A signed changeset must still match its signature
+            let sig_path = signature_path(Path::new(path));
+            if sig_path.exists() {
+                let signature = read_signature(&sig_path)?;
+                verify_artifact(&signature, &content, None)?;
+                println!("✍️  Signature OK (key {})", signature.public_key);
+            }
+            let changeset: Changeset = serde_json::from_slice(&content)?;
+            let summary = import_changeset(&conn, &changeset, &key, &cli_actor(&conn, Role::Editor)?)?;
+
+            println!("📥 Imported changeset from {}", changeset.instance_id);
+            println!(
+                "   Versions: {} applied, {} already present, {} duplicates",
+                summary.versions_applied, summary.versions_skipped, summary.versions_duplicate
+            );
+            println!(
+                "   Events:   {} applied, {} already present",
+                summary.events_applied, summary.events_skipped
+            );
+            if summary.conflicts > 0 {
+                println!(
+                    "   ⚔️  {} conflicting versions resolved by ledger policy (see: changes list)",
+                    summary.conflicts
+                );
+            }
+        }
+        Some("merge") => {
+            let path = args.get(1).ok_or_else(|| anyhow!("Usage: sync merge <file> [--map <source>=<target>]..."))?;
+            let mut options = MergeOptions::default();
+            let mut i = 2;
+            while i < args.len() {
+                match (args[i].as_str(), args.get(i + 1)) {
+                    ("--map", Some(value)) => options.map_ledger(value)?,
+                    ("--policy", Some(value)) => options.policy = Some(ConflictPolicy::parse(value)?),
+                    (other, _) => return Err(anyhow!("Unknown or incomplete sync merge option: {}", other)),
+                }
+                i += 2;
+            }
+
+            let changeset: Changeset = serde_json::from_slice(&std::fs::read(path)?)?;
+            let summary = merge_changeset(&conn, &changeset, &key, &options, &cli_actor(&conn, Role::Editor)?)?;
+
+            println!("🔀 Merged log of {}", changeset.instance_id);
+            for (source, target) in &options.ledger_map {
+                println!("   Ledger {} → {}", source, target);
+            }
+            println!(
+                "   Versions: {} applied, {} already present; {} lines mapped onto local transactions",
+                summary.versions_applied, summary.versions_skipped, summary.identities_mapped
+            );
+            println!(
+                "   Events:   {} applied, {} already present",
+                summary.events_applied, summary.events_skipped
+            );
+            if summary.conflicts > 0 {
+                println!("   ⚔️  {} mapped lines disagreed, resolved by policy (see: changes list)", summary.conflicts);
+            }
+        }
+        _ => {
+            return Err(anyhow!(
+                "Usage: sync export [--since <checkpoint>] [--out <file> [--sign]] | sync import <file> | sync merge <file> | sync check"
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "tui")]
+fn run_ui_mode(ledger_id: &str) -> Result<()> {
+    println!("🖥️  Loading Trust Construction System UI...\n");
+
+    // Open database
+    let config = AppConfig::load()?;
+    let db_path = Path::new(&config.db_path);
+
+    if !config.in_memory() && !db_path.exists() {
+        eprintln!("❌ Database not found at {:?}", db_path);
+        eprintln!("   Run: cargo run init");
+        eprintln!("   to set up the database and import a first statement.");
+        std::process::exit(1);
+    }
+
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    // Load transactions
+    println!("📊 Loading transactions...");
+    let ledger = require_ledger(&conn, ledger_id)?;
+    let transactions = TransactionFilter::new()
+        .in_ledger(ledger_id)
+        .apply(&get_active_transactions(&conn)?);
+    let total_count = verify_count(&conn)?;
+
+    println!("✓ Loaded {} transactions\n", transactions.len());
+    println!("Starting UI... (Press 'q' to quit)\n");
+
+    let duplicates = pending_clusters(&conn, &DeduplicationEngine::new(), &transactions, chrono::Utc::now())?;
+
+    // Create and run app
+    // Writes (undo/redo) need the editor role; otherwise the session is read-only
+    let app = ui::App::new(transactions, total_count)
+        .with_notes(get_ledger_notes(&conn, ledger_id)?)
+        .with_bills(ledger_bill_occurrences(&conn, ledger_id, 1)?)
+        .with_envelopes(current_envelope_month(&conn, ledger_id)?)
+        .with_duplicates(duplicates)
+        .with_ledger(ledger_id, ledger.config.import_path.clone())
+        .with_layout(config.ledger_layout.clone().unwrap_or_default())
+        .with_locale(config.locale());
+    let mut app = match cli_actor(&conn, Role::Editor) {
+        Ok(actor) => app.with_connection(conn, &actor),
+        Err(_) => app,
+    };
+    ui::run_ui(&mut app)?;
+
+    println!("\n✅ UI closed successfully");
+
+    Ok(())
+}
+
+/// First-run setup: config file, database, base currency, rules, first import
+///
+/// Usage: init
+fn run_init() -> Result<()> {
+    println!("🧭 Trust Construction - First-run setup");
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+    let config_path = AppConfig::path();
+    if config_path.exists() {
+        let answer = prompt(&format!("Config {:?} exists. Overwrite? [y/N]", config_path), "n")?;
+        if !answer.eq_ignore_ascii_case("y") {
+            println!("Aborted");
+            return Ok(());
+        }
+    }
+
+    let defaults = AppConfig::load().unwrap_or_default();
+    let config = AppConfig {
+        db_path: prompt("Database location", &defaults.db_path)?,
+        base_currency: prompt("Base currency", &defaults.base_currency)?.to_uppercase(),
+        rules_path: defaults.rules_path,
+        ledger_layout: defaults.ledger_layout,
+        api_writes_per_minute: defaults.api_writes_per_minute,
+        signing_key_path: defaults.signing_key_path,
+        locale: Some(prompt(
+            "Locale for amounts and dates (e.g. en-US, de-DE)",
+            defaults.locale.as_deref().unwrap_or("plain"),
+        )?)
+        .filter(|tag| !tag.is_empty() && tag != "plain"),
+    };
+    config.validate()?;
+    let first_statement = prompt("First statement CSV to import (blank to skip)", "")?;
+
+    config.save_to(&config_path)?;
+    println!("\n✓ Config written to {:?}", config_path);
+
+    let conn = open_database()?;
+    setup_database(&conn)?;
+    println!("✓ Database ready at {}", config.db_path);
+
+    let actor = cli_actor(&conn, Role::Admin)?;
+    let mut ledger = require_ledger(&conn, DEFAULT_LEDGER_ID)?;
+    ledger.config.default_currency = Some(config.base_currency.clone());
+    if !first_statement.is_empty() {
+        ledger.config.import_path = Some(first_statement.clone());
+    }
+    update_ledger_config(&conn, &ledger.id, &ledger.config, &actor)?;
+    println!("✓ Base currency: {}", config.base_currency);
+
+    // Default registries: classification rules live in the database
+    if get_current_rules(&conn)?.is_empty() {
+        let rules: Vec<ClassificationRule> = serde_json::from_str(BUNDLED_RULES)?;
+        let seeded = save_rules(&conn, &rules, &actor, Some("Seeded by init"))?;
+        println!("✓ Seeded {} classification rules", seeded);
+    }
+
+    if !first_statement.is_empty() {
+        println!();
+        run_import(DEFAULT_LEDGER_ID, &[])?;
+    }
+
+    println!("\n✅ Setup complete. Start the UI with: cargo run");
+
+    Ok(())
+}
+
+/// Re-run a recorded import and compare it with what happened then
+///
+/// Usage: replay <session-id> [--report <file>]
+fn run_replay(args: &[String]) -> Result<()> {
+    let session_id = args
+        .first()
+        .ok_or_else(|| anyhow!("Usage: replay <session-id> [--report <file>]"))?;
+    let report_path = match args.get(1).map(String::as_str) {
+        Some("--report") => Some(args.get(2).ok_or_else(|| anyhow!("--report requires a file"))?),
+        None => None,
+        Some(other) => return Err(anyhow!("Unknown replay option: {}", other)),
+    };
+
+    let conn = open_database()?;
+    setup_database(&conn)?;
+    let report = replay_import(&conn, session_id)?;
+    print!("{}", report.render());
+
+    if let Some(path) = report_path {
+        std::fs::write(path, report.render())?;
+        println!("📝 Report written to {}", path);
+    }
+    if !report.reproduced {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Find files imported with an older parser and preview what today's parser
+/// reads differently (nothing is changed)
+///
+/// Usage: parsers check | parsers diff <file>...
+fn run_parsers(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+    require_ledger(&conn, ledger_id)?;
+
+    match args.first().map(String::as_str) {
+        Some("check") | None => {
+            let outdated = outdated_sources(&conn, ledger_id)?;
+            if outdated.is_empty() {
+                println!("✅ Every imported file was parsed by the current parser version");
+            }
+            for source in &outdated {
+                println!(
+                    "⚠️  {} ({}): {} rows from {}, current {}",
+                    source.source_file, source.bank, source.rows, source.stored_version, source.current_version
+                );
+            }
+            if !outdated.is_empty() {
+                println!("\nPreview the differences with: parsers diff <file>...");
+            }
+        }
+        Some("diff") => {
+            if args.len() < 2 {
+                return Err(anyhow!("Usage: parsers diff <file>..."));
+            }
+            let mut changed = 0;
+            for path in &args[1..] {
+                let content = std::fs::read(path).map_err(|e| anyhow!("Failed to read {}: {}", path, e))?;
+                let report = compare_with_stored(&conn, ledger_id, path, &content)?;
+                println!(
+                    "📄 {} ({}): {} stored rows, {} re-parsed, parser {} → {}",
+                    report.source_file,
+                    report.source,
+                    report.stored_rows,
+                    report.reparsed_rows,
+                    report.stored_versions.join(", "),
+                    report.current_version
+                );
+                for d in &report.discrepancies {
+                    println!("  line {:>4}  {:<16} stored {} / re-parsed {}", d.line, d.field, d.stored, d.reparsed);
+                }
+                if report.discrepancies.is_empty() {
+                    println!("  ✅ no differences");
+                } else {
+                    changed += 1;
+                }
+            }
+            if changed > 0 {
+                std::process::exit(1);
+            }
+        }
+        Some(other) => return Err(anyhow!("Unknown parsers command: {} (check, diff)", other)),
+    }
+
+    Ok(())
+}
+
+/// Check parsers against golden files: every fixture in <dir> is parsed (the
+/// parser detected from its name) and compared with <fixture>.golden.json;
+/// --bless writes the golden files from what the parsers read today
+///
+/// Usage: parser check <dir> [--bless]
+fn run_parser(args: &[String]) -> Result<()> {
+    let usage = || anyhow!("Usage: parser check <dir> [--bless]");
+    if args.first().map(String::as_str) != Some("check") {
+        return Err(usage());
+    }
+    let dir = Path::new(args.get(1).filter(|dir| !dir.starts_with("--")).ok_or_else(usage)?);
+
+    if args.iter().any(|arg| arg == "--bless") {
+        for input in parser_testing::fixture_inputs(dir)? {
+            let golden = parser_testing::write_golden(parser_testing::detect_parser(&input)?.as_ref(), &input)?;
+            println!("📝 {}", golden.display());
+        }
+        return Ok(());
+    }
+
+    let results = parser_testing::check_dir(dir, parser_testing::detect_parser)?;
+    let failed = results.iter().filter(|result| !result.passed()).count();
+    for result in &results {
+        let name = result.input.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+        match &result.error {
+            Some(error) => println!("❌ {}: {}", name, error),
+            None if result.passed() => println!("✅ {}", name),
+            None => {
+                println!("❌ {} ({} differences)", name, result.differences.len());
+                for difference in &result.differences {
+                    println!("     {}", difference);
+                }
+            }
+        }
+    }
+    println!("\n{} fixtures, {} passed, {} failed", results.len(), results.len() - failed, failed);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Where a transaction came from, down to the archived source line
+///
+/// Usage: provenance show <tx_id> | provenance list | provenance extract <sha256> <file>
+fn run_provenance(args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+
+    match args.first().map(String::as_str) {
+        Some("show") => {
+            let tx_id = args.get(1).ok_or_else(|| anyhow!("Usage: provenance show <tx_id>"))?;
+            let provenance = transaction_provenance(&conn, tx_id)?
+                .ok_or_else(|| anyhow!("Transaction {} not found", tx_id))?;
+            let tx = &provenance.transaction;
+            let unknown = || "(unknown)".to_string();
+
+            println!("🧾 {} {} {:.2} {} (v{})", tx.id, tx.date, tx.amount_numeric, tx.merchant, tx.version);
+            println!("   source file:  {} line {}", provenance.source_file, tx.line_number);
+            println!("   parser:       {}", provenance.parser_version.clone().unwrap_or_else(unknown));
+            println!("   extracted at: {}", provenance.extracted_at.clone().unwrap_or_else(unknown));
+            for (field, origin) in field_origins(tx) {
+                println!(
+                    "   {:<13} {} ({})",
+                    format!("{}:", field),
+                    origin.source.label(),
+                    origin.at.format("%Y-%m-%d %H:%M")
+                );
+            }
+            match (&provenance.source_sha256, &provenance.archived) {
+                (Some(sha256), Some(source)) => {
+                    println!("   archived:     {} ({}, {} bytes)", sha256, source.filename, source.size);
+                    if let Some(header) = &provenance.header {
+                        println!("\n   {:>5} │ {}", 1, header);
+                    }
+                    match (&provenance.excerpt, provenance.source_line) {
+                        (Some(excerpt), Some(line)) => println!("   {:>5} │ {}", line, excerpt),
+                        _ => println!("   (line not found in the archived file)"),
+                    }
+                }
+                (Some(sha256), None) => println!("   archived:     {} (missing from the archive)", sha256),
+                (None, _) => println!("   archived:     no (imported before the source archive existed)"),
+            }
+        }
+        Some("list") => {
+            for source in list_archived_sources(&conn)? {
+                println!(
+                    "{}  {:>9} bytes  {}  {}",
+                    source.sha256,
+                    source.size,
+                    source.archived_at.format("%Y-%m-%d %H:%M"),
+                    source.filename
+                );
+            }
+        }
+        Some("extract") => {
+            let (Some(sha256), Some(path)) = (args.get(1), args.get(2)) else {
+                return Err(anyhow!("Usage: provenance extract <sha256> <file>"));
+            };
+            let source = get_archived_source(&conn, sha256)?
+                .ok_or_else(|| anyhow!("No archived file with hash {}", sha256))?;
+            std::fs::write(path, &source.content)?;
+            println!("✓ Wrote {} ({} bytes) to {}", source.filename, source.size, path);
+        }
+        _ => return Err(anyhow!("Usage: provenance show <tx_id> | provenance list | provenance extract <sha256> <file>")),
+    }
+
+    Ok(())
+}
+
+/// This installation's signing key, or an error saying how to set one up
+fn cli_signing_key() -> Result<ed25519_dalek::SigningKey> {
+    load_signing_key(&AppConfig::load()?)?
+        .ok_or_else(|| anyhow!("No signing key: run `signing init [--keyring]` or set {}", SIGNING_KEY_ENV))
+}
+
+/// Sign a file written by a command (`<file>.sig` next to it)
+fn sign_cli_artifact(conn: &Connection, ledger_id: Option<&str>, kind: &str, path: &Path) -> Result<()> {
+    let key = cli_signing_key()?;
+    let instance = instance_id(conn)?;
+    let signer = SigningIdentity { key: &key, instance_id: &instance, ledger_id };
+    sign_file(&signer, kind, path)?;
+    println!("✍️  Signed: {}", signature_path(path).display());
+    Ok(())
+}
+
+/// Ed25519 key used by `--sign` on report render and sync export
+///
+/// Usage: signing init [--key-file <file> | --keyring] | signing public-key
+///
+/// `--keyring` keeps the key in the OS keyring (the `signing-key` secret)
+/// instead of a key file.
+fn run_signing(args: &[String]) -> Result<()> {
+    match args.first().map(String::as_str) {
+        Some("init") if args.get(1).map(String::as_str) == Some("--keyring") => {
+            let keyring = OsKeyring;
+            if keyring.get(SIGNING_KEY_SECRET)?.is_some() {
+                return Err(anyhow!("The {} already holds a signing key", keyring.name()));
+            }
+            let key = generate_signing_key();
+            keyring.set(SIGNING_KEY_SECRET, &signing_key_hex(&key))?;
+            println!("🔑 Signing key stored in the {} as '{}'", keyring.name(), SIGNING_KEY_SECRET);
+            println!("   Public key: {}", public_key_hex(&key.verifying_key()));
+            println!("   Give the public key to whoever verifies your reports");
+        }
+        Some("init") => {
+            let mut config = AppConfig::load()?;
+            if let Some(path) = &config.signing_key_path {
+                return Err(anyhow!("A signing key is already configured: {}", path));
+            }
+            let path = match args.get(1).map(String::as_str) {
+                Some("--key-file") => std::path::PathBuf::from(
+                    args.get(2).ok_or_else(|| anyhow!("--key-file requires a file"))?,
+                ),
+                None => config_dir().join("signing.key"),
+                Some(other) => return Err(anyhow!("Unknown signing init option: {}", other)),
+            };
+
+            let key = generate_signing_key();
+            save_signing_key(&path, &key)?;
+            config.signing_key_path = Some(path.to_string_lossy().to_string());
+            config.save_to(&AppConfig::path())?;
+            println!("🔑 Signing key written to {}", path.display());
+            println!("   Public key: {}", public_key_hex(&key.verifying_key()));
+            println!("   Give the public key to whoever verifies your reports");
+        }
+        Some("public-key") => println!("{}", public_key_hex(&cli_signing_key()?.verifying_key())),
+        _ => return Err(anyhow!("Usage: signing init [--key-file <file> | --keyring] | signing public-key")),
+    }
+    Ok(())
+}
+
+/// API and signing keys kept in the OS keyring instead of plaintext files
+/// or shell profiles. Values are read from stdin, never from arguments, so
+/// they stay out of shell history.
+///
+/// Usage: secret set <name> | secret get <name> | secret delete <name> | secret list
+fn run_secret(args: &[String]) -> Result<()> {
+    let keyring = OsKeyring;
+    let name = || -> Result<&String> {
+        let name = args.get(1).ok_or_else(|| anyhow!("Usage: secret set|get|delete <name>"))?;
+        validate_secret_name(name)?;
+        Ok(name)
+    };
+
+    match args.first().map(String::as_str) {
+        Some("set") => {
+            let name = name()?;
+            if io::stdin().is_terminal() {
+                print!("Value for {}: ", name);
+                io::stdout().flush()?;
+            }
+            let mut value = String::new();
+            io::stdin().lock().read_line(&mut value)?;
+            let value = value.trim_end_matches(['\r', '\n']);
+            if value.is_empty() {
+                return Err(anyhow!("No value given for {}", name));
+            }
+            keyring.set(name, value)?;
+            println!("🔐 Stored '{}' in the {}", name, keyring.name());
+        }
+        Some("get") => {
+            let name = name()?;
+            let (value, source) = resolve_secret(&keyring, name, |var| env::var(var).ok())?
+                .ok_or_else(|| anyhow!("'{}' is not set (keyring or {})", name, secret_env_var(name)))?;
+            eprintln!("({} from the {})", name, source.as_str());
+            println!("{}", value);
+        }
+        Some("delete") => {
+            let name = name()?;
+            if keyring.delete(name)? {
+                println!("🗑️  Removed '{}' from the {}", name, keyring.name());
+            } else {
+                println!("'{}' is not in the {}", name, keyring.name());
+            }
+        }
+        Some("list") | None => {
+            println!("🔐 Secrets (keyring: {}, service '{}')", keyring.name(), KEYRING_SERVICE);
+            for (name, purpose) in KNOWN_SECRETS {
+                let found = resolve_secret(&keyring, name, |var| env::var(var).ok())?;
+                let source = found.map_or("not set", |(_, source)| source.as_str());
+                println!("  {:<12} {:<22} {:<12} {}", name, secret_env_var(name), source, purpose);
+            }
+        }
+        Some(other) => return Err(anyhow!("Unknown secret command: {} (set, get, delete, list)", other)),
+    }
+    Ok(())
+}
+
+/// Erase a counterparty: pseudonymize (default) or remove its transactions,
+/// events, notes and registry entries, after showing what would change
+///
+/// Usage: purge <name> [--remove] [--dry-run] [--yes]
+fn run_purge(ledger_id: &str, args: &[String]) -> Result<()> {
+    let mut name: Option<&str> = None;
+    let mut mode = PurgeMode::Pseudonymize;
+    let mut dry_run = false;
+    let mut assume_yes = false;
+    for arg in args {
+        match arg.as_str() {
+            "--remove" => mode = PurgeMode::Remove,
+            "--dry-run" => dry_run = true,
+            "--yes" | "-y" => assume_yes = true,
+            other if other.starts_with("--") => return Err(anyhow!("Unknown purge option: {}", other)),
+            other if name.is_none() => name = Some(other),
+            other => return Err(anyhow!("Unexpected argument: {} (quote a name with spaces)", other)),
+        }
+    }
+    let name = name.ok_or_else(|| anyhow!("Usage: purge <name> [--remove] [--dry-run] [--yes]"))?;
+
+    let conn = open_database()?;
+    setup_database(&conn)?;
+    let plan = plan_purge(&conn, ledger_id, name)?;
+    if plan.is_empty() {
+        println!("Nothing in ledger '{}' matches '{}' ({})", ledger_id, plan.counterparty, plan.names.join(", "));
+        return Ok(());
+    }
+    println!("🧹 '{}' in ledger '{}'", plan.counterparty, ledger_id);
+    println!("   Spellings:    {}", plan.names.join(", "));
+    println!("   Transactions: {} ({} versions), net {:.2}", plan.tx_uuids.len(), plan.versions, plan.total);
+    if !plan.closed_periods.is_empty() {
+        println!("   In closed periods: {}", plan.closed_periods.join(", "));
+    }
+    if !plan.archived_sources.is_empty() {
+        println!("   ⚠️  {} archived statement files keep the original lines", plan.archived_sources.len());
+    }
+
+    if dry_run {
+        println!("\n🧪 Dry run - nothing changed");
+        return Ok(());
+    }
+    if !assume_yes {
+        print!("\n{} everything above? This cannot be undone [y/N] ", mode.as_str());
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().lock().read_line(&mut answer)?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            println!("Aborted");
+            return Ok(());
+        }
+    }
+
+    let actor = cli_actor(&conn, Role::Admin)?;
+    let report = purge_counterparty(&conn, ledger_id, name, mode, &actor)?;
+    println!("\n✅ {} as {}", mode.as_str(), report.purge_id);
+    println!(
+        "   {} versions, {} events removed, {} scrubbed, {} related rows, {} registry/cache rows, {} import inputs",
+        report.versions,
+        report.events_removed,
+        report.events_scrubbed,
+        report.related_rows,
+        report.entity_rows,
+        report.import_inputs
+    );
+    if report.checksums.is_empty() {
+        println!("   Manifest checksums unchanged");
+    }
+    for change in &report.checksums {
+        let side = |entry: &Option<ManifestEntry>| {
+            entry.as_ref().map_or_else(
+                || "none".to_string(),
+                |entry| format!("{} rows {:.2} {}", entry.count, entry.total, &entry.checksum[..12]),
+            )
+        };
+        println!("   {} {}: {} → {}", change.bank, change.period, side(&change.before), side(&change.after));
+    }
+    println!("   Event chain {} → {}", &report.event_chain_before[..12], &report.event_chain_after[..12]);
+    Ok(())
+}
+
+/// The whole installation as one tar.zst: database snapshot, registries,
+/// config, rules file and event-chain head
+///
+/// Usage: archive create <file.tar.zst> | archive restore <file> <dir> | archive show <file>
+///
+/// `restore` writes into a new directory and never touches the configured
+/// database; run against it with TRUST_CONFIG=<dir>/config.json.
+fn run_archive(args: &[String]) -> Result<()> {
+    let usage = || anyhow!("Usage: archive create <file.tar.zst> | archive restore <file> <dir> | archive show <file>");
+    let file = Path::new(args.get(1).ok_or_else(usage)?);
+    let print_manifest = |manifest: &ArchiveManifest| {
+        println!("   Taken {} (schema {})", manifest.created_at.format("%Y-%m-%d %H:%M UTC"), manifest.schema_version);
+        println!(
+            "   Event log: {} events, head {}",
+            manifest.event_chain.events,
+            &manifest.event_chain.sha256[..16]
+        );
+        for name in manifest.files.keys() {
+            println!("   • {}", name);
+        }
+    };
+
+    match (args[0].as_str(), args.get(2)) {
+        ("create", None) => {
+            let conn = open_database()?;
+            setup_database(&conn)?;
+            let manifest = create_archive(&conn, &AppConfig::load()?, file)?;
+            println!("📦 Archive written to {}", file.display());
+            print_manifest(&manifest);
+        }
+        ("restore", Some(dir)) => {
+            let restored = restore_archive(file, Path::new(dir))?;
+            println!("📦 Restored {} into {}", file.display(), dir);
+            print_manifest(&restored.manifest);
+            println!("   Checksums and event log verified");
+            println!("   Use it with: TRUST_CONFIG={} cargo run", restored.config.display());
+        }
+        ("show", None) => {
+            println!("📦 {}", file.display());
+            print_manifest(&read_manifest(file)?);
+        }
+        _ => return Err(usage()),
+    }
+    Ok(())
+}
+
+/// Check a signed report or changeset against its `<file>.sig`
+///
+/// Usage: verify <file> [--sig <file>] [--key <public-key>]
+///        | verify manifest [--json]
+///
+/// Without `--key` the signer must be this installation's own key when one is
+/// configured; otherwise only integrity is checked and the signer is printed.
+/// `manifest` instead checks the ledger's imports against the counts and
+/// totals of recorded statements (see `statement close`), per bank.
+/// Exits with status 1 when the file (or a statement) does not verify.
+fn run_verify(ledger_id: &str, args: &[String]) -> Result<()> {
+    if args.first().map(String::as_str) == Some("manifest") {
+        return run_verify_manifest(ledger_id, &args[1..]);
+    }
+    let usage = || anyhow!("Usage: verify <file> [--sig <file>] [--key <public-key>]");
+    let path = Path::new(args.first().ok_or_else(usage)?);
+    let mut sig_path = signature_path(path);
+    let mut trusted = None;
+    let mut i = 1;
+    while i < args.len() {
+        match (args[i].as_str(), args.get(i + 1)) {
+            ("--sig", Some(value)) => sig_path = value.into(),
+            ("--key", Some(value)) => trusted = Some(parse_public_key(value)?),
+            _ => return Err(usage()),
+        }
+        i += 2;
+    }
+    if trusted.is_none() {
+        trusted = load_signing_key(&AppConfig::load()?)?.map(|key| key.verifying_key());
+    }
+
+    let signature = read_signature(&sig_path)?;
+    let content = std::fs::read(path)?;
+    match verify_artifact(&signature, &content, trusted.as_ref()) {
+        Ok(()) => {
+            println!("✅ {} verifies", path.display());
+            println!("   Kind:      {}", signature.kind);
+            println!("   Signed by: {} ({})", signature.instance_id, signature.public_key);
+            if let Some(ledger_id) = &signature.ledger_id {
+                println!("   Ledger:    {}", ledger_id);
+            }
+            println!("   Signed at: {}", signature.signed_at.format("%Y-%m-%d %H:%M UTC"));
+            println!("   SHA-256:   {}", signature.sha256);
+            if trusted.is_none() {
+                println!("   ⚠️  Signer not checked against a trusted key (pass --key <public-key>)");
+            }
+            Ok(())
+        }
+        Err(e) => {
+            println!("❌ {} does not verify: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Trust scorecard per bank: manifest periods and statement checks
+fn run_verify_manifest(ledger_id: &str, args: &[String]) -> Result<()> {
+    let conn = open_database()?;
+    setup_database(&conn)?;
+    let scorecards = verify_sources(&conn, ledger_id)?;
+
+    if args.iter().any(|arg| arg == "--json") {
+        println!("{}", serde_json::to_string_pretty(&scorecards)?);
+    } else {
+        println!("🧮 Source scorecards for ledger '{}'", ledger_id);
+        for card in &scorecards {
+            let score = card.score().map_or("no statements".to_string(), |score| format!("{:.0}%", score * 100.0));
+            println!("\n🏦 {} ({}, {}/{} statements verify)", card.bank, score, card.verified(), card.statements.len());
+            for entry in &card.periods {
+                println!("  {}  {:>5} txs {:>12.2}  {}", entry.period, entry.count, entry.total, &entry.checksum[..12]);
+            }
+            for check in &card.statements {
+                let declared = check.declared_count.map_or("-".to_string(), |count| count.to_string());
+                let mark = if check.verified() { "✓" } else { "⚠️ " };
+                println!(
+                    "  {} {} ({} → {}): {} of {} txs, {:.2} of {:.2}",
+                    mark, check.statement_period, check.from, check.to, check.count, declared, check.total, check.expected_total
+                );
+            }
+            let uncovered = card.unverified_periods();
+            if !uncovered.is_empty() {
+                println!("  ℹ️  No statement for {}", uncovered.join(", "));
+            }
+        }
+    }
+
+    if scorecards.iter().flat_map(|card| &card.statements).any(|check| !check.verified()) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Diagnose config, database and data; `--fix` applies the safe fixes
+///
+/// Usage: doctor [--fix] | doctor explain
+///
+/// `explain` prints SQLite's plan for each common filter, flagging full scans.
+fn run_doctor(args: &[String]) -> Result<()> {
+    let fix = match args.first().map(String::as_str) {
+        Some("--fix") => true,
+        Some("explain") => {
+            let conn = open_database()?;
+            setup_database(&conn)?;
+            for plan in explain_common_queries(&conn)? {
+                let icon = if plan.full_scan { CheckStatus::Warn.icon() } else { CheckStatus::Ok.icon() };
+                println!("{} {:<18} {}", icon, plan.name, plan.sql);
+                for step in &plan.steps {
+                    println!("   {:<18} {}", "", step);
+                }
+            }
+            return Ok(());
+        }
+        None => false,
+        Some(other) => return Err(anyhow!("Unknown doctor option: {}", other)),
+    };
+
+    println!("🩺 Trust Construction - Doctor");
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+    if fix {
+        let conn = open_database()?;
+        for action in apply_fixes(&conn)? {
+            println!("🔧 {}", action);
+        }
+        println!();
+    }
+
+    let checks = diagnose(&AppConfig::path());
+    for check in &checks {
+        println!("{} {:<10} {}", check.status.icon(), check.name, check.detail);
+        if let Some(fix) = &check.fix {
+            println!("   {:<10} → {}", "", fix);
+        }
+    }
+
+    let failed = checks.iter().filter(|c| c.status == CheckStatus::Fail).count();
+    let warned = checks.iter().filter(|c| c.status == CheckStatus::Warn).count();
+    println!("\n{} failed, {} warnings", failed, warned);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Ask a question on stdin; empty answer = `default`
+fn prompt(question: &str, default: &str) -> Result<String> {
+    if default.is_empty() {
+        print!("{}: ", question);
+    } else {
+        print!("{} [{}]: ", question, default);
+    }
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default.to_string() } else { answer.to_string() })
+}
+
+/// Explore the UI on synthetic data: in-memory database, read-only session
+///
+/// Usage: demo | demo --bench [--rows N] [--frames N]
+#[cfg(feature = "tui")]
+fn run_demo(args: &[String]) -> Result<()> {
+    if args.first().map(String::as_str) == Some("--bench") {
+        return run_ui_bench(&args[1..]);
+    }
+
+    println!("🎭 Starting demo with synthetic data (nothing is read from or written to disk)...\n");
+
+    let conn = Connection::open_in_memory()?;
+    seed_demo_database(&conn, 42)?;
+    set_query_only(&conn)?;
+    let transactions = get_active_transactions(&conn)?;
+    let total_count = verify_count(&conn)?;
+
+    println!("Starting UI... (Press 'q' to quit)\n");
+
+    // No connection attached: undo/redo stay disabled
+    let mut app = ui::App::new(transactions, total_count);
+    ui::run_ui(&mut app)?;
+
+    println!("\n✅ Demo closed");
+
+    Ok(())
+}
+
+/// Frame times of the ledger table over a large synthetic ledger
+#[cfg(feature = "tui")]
+fn run_ui_bench(args: &[String]) -> Result<()> {
+    let number = |name: &str, default: usize| -> Result<usize> {
+        match args.iter().position(|arg| arg == name).and_then(|index| args.get(index + 1)) {
+            Some(raw) => raw.parse().map_err(|_| anyhow!("{} must be a number, got '{}'", name, raw)),
+            None => Ok(default),
+        }
+    };
+    let (rows, frames) = (number("--rows", 200_000)?, number("--frames", 200)?);
+
+    // About 25 rows a month: generate enough months, then trim
+    let months = (rows / 20 + 1) as u32;
+    let today = chrono::Local::now().date_naive();
+    let mut transactions = trust_construction::demo::generate_transactions(42, months, today);
+    transactions.truncate(rows);
+    for (index, tx) in transactions.iter_mut().enumerate() {
+        tx.id = format!("bench-{}", index);
+    }
+    let count = transactions.len();
+
+    let mut app = ui::App::new(transactions, count as i64).with_locale(AppConfig::load()?.locale());
+    let mut times = ui::measure_frames(&mut app, frames, 160, 50)?;
+    times.sort();
+
+    let total: std::time::Duration = times.iter().sum();
+    let percentile = |p: usize| times.get((times.len() * p / 100).min(times.len().saturating_sub(1)));
+    println!("⏱️  {} frames over {} rows (160x50)", times.len(), count);
+    println!("   mean {:?}", total / times.len().max(1) as u32);
+    if let (Some(median), Some(p99), Some(max)) = (percentile(50), percentile(99), times.last()) {
+        println!("   p50 {:?}  p99 {:?}  max {:?}", median, p99, max);
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_demo(_args: &[String]) -> Result<()> {
+    run_ui_mode(DEFAULT_LEDGER_ID)
+}
+
 #[cfg(not(feature = "tui"))]
-fn run_ui_mode() -> Result<()> {
+fn run_ui_mode(_ledger_id: &str) -> Result<()> {
     eprintln!("❌ TUI mode not available!");
     eprintln!("   Rebuild with: cargo build --features tui");
     eprintln!("   Or use web UI: cargo run --bin trust-server --features server");
@@ -0,0 +1,325 @@
+// 🧮 Manifest - Per-bank counts and checksums, checked against statements
+//
+// Problem solved:
+// - Reconciliation only compared balances: a dropped row and a duplicated row
+//   of the same amount cancel out, and nothing said which source to trust less
+// - A bank statement prints how many transactions it lists and their total,
+//   but that figure was never kept or compared to what was imported
+//
+// `ledger_manifest` computes, per bank and calendar month, the number of
+// transactions, their net and a checksum (SHA-256 of the sorted date/amount
+// lines, so it can be recomputed from the bank's own file). `verify_sources`
+// checks every recorded statement close against the imported rows in its
+// window (previous close + 1 day through this close) and returns a scorecard
+// per source: which statements match their declared count and total.
+
+use crate::archive::content_sha256;
+use crate::accounts::OPENING_BALANCE_FLAG;
+use crate::db::Transaction;
+use crate::jobs::ledger_transactions;
+use crate::statements::{balance_history, snapshot_accounts, BalanceSnapshot};
+use anyhow::Result;
+use chrono::{Months, NaiveDate};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Largest difference between declared and imported totals still counted as a match
+pub const TOTAL_TOLERANCE: f64 = 0.01;
+
+// ============================================================================
+// MANIFEST
+// ============================================================================
+
+/// Imported transactions of one bank in one month
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub bank: String,
+    /// "2025-01"
+    pub period: String,
+    pub count: usize,
+    pub total: f64,
+    pub checksum: String,
+}
+
+/// Count, net and checksum of a set of transactions
+fn tally(transactions: &[&Transaction]) -> (usize, f64, String) {
+    let mut lines: Vec<String> = transactions
+        .iter()
+        .map(|tx| {
+            let date = tx.parsed_date().map_or_else(|| tx.date.clone(), |date| date.to_string());
+            format!("{}|{}", date, (tx.amount_numeric * 100.0).round() as i64)
+        })
+        .collect();
+    lines.sort();
+    // fold, not sum: an empty f64 sum is -0.0, printed "-0.00"
+    let total = transactions.iter().fold(0.0, |total, tx| total + tx.amount_numeric);
+    (transactions.len(), total, content_sha256(lines.join("\n").as_bytes()))
+}
+
+/// Rows a bank statement lists (opening balances are ours, not the bank's)
+fn counted(tx: &Transaction) -> bool {
+    !tx.is_voided() && !tx.has_metadata(OPENING_BALANCE_FLAG)
+}
+
+/// Per-bank, per-month entries, by bank then month
+pub fn compute_manifest(transactions: &[Transaction]) -> Vec<ManifestEntry> {
+    let mut groups: BTreeMap<(String, String), Vec<&Transaction>> = BTreeMap::new();
+    for tx in transactions.iter().filter(|tx| counted(tx)) {
+        let Some(date) = tx.parsed_date() else { continue };
+        groups
+            .entry((tx.bank.clone(), date.format("%Y-%m").to_string()))
+            .or_default()
+            .push(tx);
+    }
+
+    groups
+        .into_iter()
+        .map(|((bank, period), transactions)| {
+            let (count, total, checksum) = tally(&transactions);
+            ManifestEntry { bank, period, count, total, checksum }
+        })
+        .collect()
+}
+
+pub fn ledger_manifest(conn: &Connection, ledger_id: &str) -> Result<Vec<ManifestEntry>> {
+    Ok(compute_manifest(&ledger_transactions(conn, ledger_id)?))
+}
+
+// ============================================================================
+// STATEMENT CHECKS
+// ============================================================================
+
+/// One statement close compared with the rows imported for its window
+#[derive(Debug, Clone, Serialize)]
+pub struct StatementCheck {
+    pub account_id: String,
+    pub statement_period: String,
+    /// Window covered (inclusive)
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub declared_count: Option<usize>,
+    pub count: usize,
+    /// Declared total, else closing - opening
+    pub expected_total: f64,
+    pub total: f64,
+    pub checksum: String,
+}
+
+impl StatementCheck {
+    /// True when the statement declared no count
+    pub fn count_matches(&self) -> bool {
+        self.declared_count.is_none_or(|declared| declared == self.count)
+    }
+
+    pub fn total_matches(&self) -> bool {
+        (self.expected_total - self.total).abs() < TOTAL_TOLERANCE
+    }
+
+    pub fn verified(&self) -> bool {
+        self.count_matches() && self.total_matches()
+    }
+}
+
+/// How far one source (bank) can be trusted
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceScorecard {
+    pub bank: String,
+    pub periods: Vec<ManifestEntry>,
+    pub statements: Vec<StatementCheck>,
+}
+
+impl SourceScorecard {
+    pub fn verified(&self) -> usize {
+        self.statements.iter().filter(|check| check.verified()).count()
+    }
+
+    /// Share of statements that verify (None without statements)
+    pub fn score(&self) -> Option<f64> {
+        match self.statements.len() {
+            0 => None,
+            total => Some(self.verified() as f64 / total as f64),
+        }
+    }
+
+    /// Months with imported rows that no statement window covers
+    pub fn unverified_periods(&self) -> Vec<&str> {
+        self.periods
+            .iter()
+            .filter(|entry| {
+                !self.statements.iter().any(|check| {
+                    let (from, to) = (check.from.format("%Y-%m").to_string(), check.to.format("%Y-%m").to_string());
+                    from <= entry.period && entry.period <= to
+                })
+            })
+            .map(|entry| entry.period.as_str())
+            .collect()
+    }
+}
+
+/// Whether `tx` belongs to the statement's account (bank, account name or number)
+pub(crate) fn from_account(tx: &Transaction, account_id: &str) -> bool {
+    [&tx.bank, &tx.account_name, &tx.account_number]
+        .iter()
+        .any(|field| field.eq_ignore_ascii_case(account_id))
+}
+
+/// Check each statement of one account; returns (bank, check) pairs
+pub(crate) fn check_account(
+    history: &[BalanceSnapshot],
+    transactions: &[Transaction],
+) -> Vec<(String, StatementCheck)> {
+    let mut checks = Vec::new();
+    let mut previous: Option<NaiveDate> = None;
+    for snapshot in history {
+        let to = snapshot.statement_date;
+        let from = match previous {
+            Some(close) => close.succ_opt().unwrap_or(close),
+            // First statement: assume a monthly cycle
+            None => to
+                .checked_sub_months(Months::new(1))
+                .and_then(|date| date.succ_opt())
+                .unwrap_or(to),
+        };
+        previous = Some(to);
+
+        let rows: Vec<&Transaction> = transactions
+            .iter()
+            .filter(|tx| counted(tx) && from_account(tx, &snapshot.account_id))
+            .filter(|tx| tx.parsed_date().is_some_and(|date| from <= date && date <= to))
+            .collect();
+        let mut banks: HashMap<&str, usize> = HashMap::new();
+        for tx in &rows {
+            *banks.entry(tx.bank.as_str()).or_default() += 1;
+        }
+        let bank = banks
+            .into_iter()
+            .max_by_key(|(bank, count)| (*count, std::cmp::Reverse(*bank)))
+            .map_or_else(|| snapshot.account_id.clone(), |(bank, _)| bank.to_string());
+
+        let (count, total, checksum) = tally(&rows);
+        checks.push((
+            bank,
+            StatementCheck {
+                account_id: snapshot.account_id.clone(),
+                statement_period: snapshot.statement_period.clone(),
+                from,
+                to,
+                declared_count: snapshot.declared_count,
+                count,
+                expected_total: snapshot.expected_total(),
+                total,
+                checksum,
+            },
+        ));
+    }
+    checks
+}
+
+fn card<'a>(scorecards: &'a mut BTreeMap<String, SourceScorecard>, bank: &str) -> &'a mut SourceScorecard {
+    scorecards.entry(bank.to_string()).or_insert_with(|| SourceScorecard {
+        bank: bank.to_string(),
+        periods: Vec::new(),
+        statements: Vec::new(),
+    })
+}
+
+/// Scorecard per source of a ledger: its manifest and every statement close
+/// checked against the imported rows
+pub fn verify_sources(conn: &Connection, ledger_id: &str) -> Result<Vec<SourceScorecard>> {
+    let transactions = ledger_transactions(conn, ledger_id)?;
+    let mut scorecards: BTreeMap<String, SourceScorecard> = BTreeMap::new();
+    for entry in compute_manifest(&transactions) {
+        card(&mut scorecards, &entry.bank).periods.push(entry);
+    }
+    for account_id in snapshot_accounts(conn, ledger_id)? {
+        for (bank, check) in check_account(&balance_history(conn, &account_id, ledger_id)?, &transactions) {
+            card(&mut scorecards, &bank).statements.push(check);
+        }
+    }
+    Ok(scorecards.into_values().collect())
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{setup_database, void_transaction};
+    use crate::imports::import_statement;
+    use crate::reconciliation::StatementMetadata;
+    use crate::statements::record_statement_close;
+
+    const BOFA: &str = "Date,Description,Amount\n\
+        01/02/2025,STARBUCKS STORE 123,-5.25\n\
+        01/03/2025,SHELL OIL 5521,-40.00\n\
+        01/15/2025,PAYROLL ACME,2000.00\n\
+        02/03/2025,SHELL OIL 5521,-35.00\n";
+
+    fn imported() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        import_statement(&conn, "bofa.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+        conn
+    }
+
+    fn close(conn: &Connection, date: &str, opening: f64, closing: f64, declared_count: Option<usize>) {
+        let statement = StatementMetadata {
+            account_name: "Bank of America".to_string(),
+            statement_period: date[..7].to_string(),
+            opening_balance: opening,
+            closing_balance: closing,
+            statement_date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            declared_count,
+            declared_total: None,
+        };
+        record_statement_close(conn, "Bank of America", "default", &statement, "ana").unwrap();
+    }
+
+    #[test]
+    fn test_manifest_counts_and_checksums_per_bank_and_month() {
+        let conn = imported();
+        let manifest = ledger_manifest(&conn, "default").unwrap();
+        let periods: Vec<(&str, usize)> = manifest.iter().map(|e| (e.period.as_str(), e.count)).collect();
+        assert_eq!(periods, vec![("2025-01", 3), ("2025-02", 1)]);
+        assert_eq!(manifest[0].bank, "Bank of America");
+        assert!((manifest[0].total - 1954.75).abs() < 1e-9);
+
+        // Same rows in another order: same checksum; a voided row changes it
+        let mut reversed = ledger_transactions(&conn, "default").unwrap();
+        reversed.reverse();
+        assert_eq!(compute_manifest(&reversed), manifest);
+        let shell = reversed.iter().find(|tx| tx.date == "01/03/2025").unwrap();
+        void_transaction(&conn, &shell.id, "duplicate", "ana").unwrap();
+        let after = ledger_manifest(&conn, "default").unwrap();
+        assert_eq!(after[0].count, 2);
+        assert_ne!(after[0].checksum, manifest[0].checksum);
+    }
+
+    #[test]
+    fn test_scorecard_flags_statements_that_disagree() {
+        let conn = imported();
+        close(&conn, "2025-01-31", 100.0, 2054.75, Some(3));
+        // February declares two rows; only one was imported
+        close(&conn, "2025-02-28", 2054.75, 1969.75, Some(2));
+
+        let scorecards = verify_sources(&conn, "default").unwrap();
+        assert_eq!(scorecards.len(), 1);
+        let card = &scorecards[0];
+        assert_eq!(card.bank, "Bank of America");
+        assert!(card.statements[0].verified());
+        assert_eq!(card.statements[0].from, NaiveDate::from_ymd_opt(2025, 1, 1).unwrap());
+
+        let february = &card.statements[1];
+        assert_eq!(february.from, NaiveDate::from_ymd_opt(2025, 2, 1).unwrap());
+        assert!(!february.count_matches());
+        assert!(!february.total_matches());
+        assert_eq!(card.score(), Some(0.5));
+        assert!(card.unverified_periods().is_empty());
+
+        let history = balance_history(&conn, "Bank of America", "default").unwrap();
+        assert_eq!(history[1].declared_count, Some(2));
+    }
+}
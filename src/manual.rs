@@ -0,0 +1,320 @@
+// ✍️ Manual - Cash spending and adjustments entered by hand
+//
+// Problem solved:
+// - Every transaction came from a bank feed, so cash spending never reached
+//   the ledger and a wrong balance could only be fixed by editing a statement
+// - Nothing recorded who typed a row in, or why
+//
+// `create_manual_transaction` stores a hand-entered row marked with
+// source_type "Manual", the actor who entered it and their reason, and logs a
+// `manual_transaction_created` event. Manual rows are exempt from
+// deduplication: two $4.50 coffees paid in cash on the same day are both
+// real, so each row's idempotency hash is its own UUID and the duplicate
+// scans (DeduplicationEngine) never pair a manual row with anything.
+
+use crate::accounts::{account_of, list_accounts};
+use crate::db::{insert_event, insert_transaction_row, Event, Transaction};
+use crate::event_schema::ManualTransactionCreated;
+use crate::ledger::require_ledger;
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Metadata key holding where a row came from
+pub const SOURCE_TYPE_KEY: &str = "source_type";
+
+/// `source_type` of rows entered by hand
+pub const MANUAL_SOURCE_TYPE: &str = "Manual";
+
+/// `source_file` of rows entered by hand
+pub const MANUAL_SOURCE_FILE: &str = "manual";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ManualKind {
+    /// Spending (or income) paid in cash
+    Cash,
+    /// Correction that brings an account to its real balance
+    Adjustment,
+    /// One leg of a transfer between two accounts (see transfers.rs)
+    Transfer,
+}
+
+impl ManualKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ManualKind::Cash => "cash",
+            ManualKind::Adjustment => "adjustment",
+            ManualKind::Transfer => "transfer",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "cash" => Ok(ManualKind::Cash),
+            "adjustment" | "adjust" => Ok(ManualKind::Adjustment),
+            "transfer" => Ok(ManualKind::Transfer),
+            other => Err(anyhow!("Unknown manual entry kind: {} (cash, adjustment or transfer)", other)),
+        }
+    }
+}
+
+/// What the user typed in
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManualEntry {
+    pub kind: ManualKind,
+    pub date: NaiveDate,
+    /// Signed like bank rows: negative is money out
+    pub amount: f64,
+    pub description: String,
+    /// Defaults to the description
+    pub merchant: Option<String>,
+    /// Adjustments default to "Adjustment", transfers to "Transfer"; cash is
+    /// left for triage
+    pub category: Option<String>,
+    /// Account the row belongs to ("Cash" / "Cash" when unset)
+    pub bank: Option<String>,
+    pub account_name: Option<String>,
+    /// Defaults to the ledger's default currency
+    pub currency: Option<String>,
+}
+
+impl ManualEntry {
+    pub fn cash(date: NaiveDate, amount: f64, description: &str) -> Self {
+        ManualEntry {
+            kind: ManualKind::Cash,
+            date,
+            amount,
+            description: description.to_string(),
+            merchant: None,
+            category: None,
+            bank: None,
+            account_name: None,
+            currency: None,
+        }
+    }
+
+    pub fn adjustment(date: NaiveDate, amount: f64, description: &str) -> Self {
+        ManualEntry { kind: ManualKind::Adjustment, ..ManualEntry::cash(date, amount, description) }
+    }
+
+    pub fn transfer_leg(date: NaiveDate, amount: f64, description: &str) -> Self {
+        ManualEntry { kind: ManualKind::Transfer, ..ManualEntry::cash(date, amount, description) }
+    }
+
+    pub fn with_merchant(mut self, merchant: &str) -> Self {
+        self.merchant = Some(merchant.to_string());
+        self
+    }
+
+    pub fn with_category(mut self, category: &str) -> Self {
+        self.category = Some(category.to_string());
+        self
+    }
+
+    pub fn on_account(mut self, bank: &str, account_name: &str) -> Self {
+        self.bank = Some(bank.to_string());
+        self.account_name = Some(account_name.to_string());
+        self
+    }
+
+    pub fn with_currency(mut self, currency: &str) -> Self {
+        self.currency = Some(currency.to_uppercase());
+        self
+    }
+}
+
+/// Whether `tx` was entered by hand
+pub fn is_manual(tx: &Transaction) -> bool {
+    tx.get_metadata(SOURCE_TYPE_KEY).and_then(|v| v.as_str()) == Some(MANUAL_SOURCE_TYPE)
+}
+
+// ============================================================================
+// CREATE
+// ============================================================================
+
+/// Store `entry` in `ledger_id` as entered by `actor`, for `reason`
+pub fn create_manual_transaction(
+    conn: &Connection,
+    ledger_id: &str,
+    entry: &ManualEntry,
+    actor: &str,
+    reason: &str,
+) -> Result<Transaction> {
+    let tx = manual_transaction(conn, ledger_id, entry, actor, reason)?;
+    store_manual_transaction(conn, &tx, entry.kind, actor, reason)?;
+    Ok(tx)
+}
+
+/// The row `entry` becomes, validated but not stored
+pub(crate) fn manual_transaction(
+    conn: &Connection,
+    ledger_id: &str,
+    entry: &ManualEntry,
+    actor: &str,
+    reason: &str,
+) -> Result<Transaction> {
+    let ledger = require_ledger(conn, ledger_id)?;
+    if actor.trim().is_empty() {
+        return Err(anyhow!("Refusing to write without an actor"));
+    }
+    if reason.trim().is_empty() {
+        return Err(anyhow!("A manual entry needs a reason"));
+    }
+    if entry.description.trim().is_empty() {
+        return Err(anyhow!("A manual entry needs a description"));
+    }
+    if !entry.amount.is_finite() || entry.amount == 0.0 {
+        return Err(anyhow!("A manual entry needs a non-zero amount"));
+    }
+
+    let text = |value: &Option<String>, default: &str| {
+        value.as_deref().map(str::trim).filter(|v| !v.is_empty()).unwrap_or(default).to_string()
+    };
+    let description = entry.description.trim();
+    let transaction_type = match entry.kind {
+        ManualKind::Transfer => "TRASPASO",
+        _ if entry.amount < 0.0 => "GASTO",
+        _ => "INGRESO",
+    };
+    let default_category = match entry.kind {
+        ManualKind::Cash => "",
+        ManualKind::Adjustment => "Adjustment",
+        ManualKind::Transfer => "Transfer",
+    };
+    let currency = ledger.config.default_currency.clone().unwrap_or_else(|| "USD".to_string());
+
+    let mut metadata = HashMap::new();
+    metadata.insert(SOURCE_TYPE_KEY.to_string(), serde_json::json!(MANUAL_SOURCE_TYPE));
+    metadata.insert("manual_kind".to_string(), serde_json::json!(entry.kind.as_str()));
+    metadata.insert("entered_by".to_string(), serde_json::json!(actor));
+    metadata.insert("entered_at".to_string(), serde_json::json!(Utc::now().to_rfc3339()));
+    metadata.insert("entry_reason".to_string(), serde_json::json!(reason.trim()));
+
+    let mut tx = Transaction {
+        date: entry.date.format("%m/%d/%Y").to_string(),
+        description: description.to_string(),
+        amount_original: format!("{:.2}", entry.amount),
+        amount_numeric: entry.amount,
+        transaction_type: transaction_type.to_string(),
+        category: text(&entry.category, default_category),
+        merchant: text(&entry.merchant, description),
+        currency: text(&entry.currency, &currency),
+        account_name: text(&entry.account_name, "Cash"),
+        account_number: String::new(),
+        bank: text(&entry.bank, "Cash"),
+        source_file: MANUAL_SOURCE_FILE.to_string(),
+        line_number: "0".to_string(),
+        classification_notes: format!("Entered by {}: {}", actor, reason.trim()),
+        id: String::new(),
+        version: 0,
+        system_time: None,
+        valid_from: None,
+        valid_until: None,
+        previous_version_id: None,
+        ledger_id: ledger.id.clone(),
+        metadata,
+    };
+    tx.init_temporal_fields();
+
+    let accounts = list_accounts(conn, &ledger.id)?;
+    if let Some(account) = account_of(&accounts, &tx).filter(|account| !account.accepts_transaction_on(entry.date)) {
+        return Err(anyhow!("{} is closed; {} is after its close date", account.name, entry.date));
+    }
+    Ok(tx)
+}
+
+/// Insert a row built by `manual_transaction` and log its event
+pub(crate) fn store_manual_transaction(
+    conn: &Connection,
+    tx: &Transaction,
+    kind: ManualKind,
+    actor: &str,
+    reason: &str,
+) -> Result<()> {
+    // Dedup-exempt: the UUID makes every manual row's hash unique
+    insert_transaction_row(conn, tx, &format!("manual:{}", tx.id))
+        .map_err(|e| anyhow!("Could not store manual entry {}: {}", tx.description, e))?;
+
+    let payload = ManualTransactionCreated {
+        kind: kind.as_str().to_string(),
+        amount: tx.amount_numeric,
+        currency: tx.currency.clone(),
+        reason: reason.trim().to_string(),
+    };
+    let event = Event::typed("manual_transaction_created", "transaction", &tx.id, &payload, actor)?
+        .with_ledger(&tx.ledger_id);
+    insert_event(conn, &event)?;
+    Ok(())
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{get_active_transactions, get_events_for_entity, insert_transaction_as, setup_database};
+    use crate::deduplication::DeduplicationEngine;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_manual_entry_records_provenance() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let entry = ManualEntry::cash(date("2025-03-02"), -4.5, "Coffee cart").with_category("Coffee");
+        let tx = create_manual_transaction(&conn, "default", &entry, "ana", "paid in cash").unwrap();
+        assert!(is_manual(&tx));
+        assert_eq!(
+            (tx.date.as_str(), tx.merchant.as_str(), tx.bank.as_str()),
+            ("03/02/2025", "Coffee cart", "Cash")
+        );
+        assert_eq!(tx.transaction_type, "GASTO");
+        assert_eq!(tx.get_metadata("entered_by").and_then(|v| v.as_str()), Some("ana"));
+        assert_eq!(tx.get_metadata("entry_reason").and_then(|v| v.as_str()), Some("paid in cash"));
+
+        let events = get_events_for_entity(&conn, "transaction", &tx.id).unwrap();
+        assert_eq!(events[0].event_type, "manual_transaction_created");
+        assert_eq!(events[0].actor, "ana");
+
+        let adjustment =
+            ManualEntry::adjustment(date("2025-03-31"), 12.0, "Balance fix").on_account("BofA", "Checking");
+        let fixed = create_manual_transaction(&conn, "default", &adjustment, "ana", "statement says so").unwrap();
+        assert_eq!((fixed.category.as_str(), fixed.transaction_type.as_str()), ("Adjustment", "INGRESO"));
+
+        assert!(create_manual_transaction(&conn, "default", &entry, "ana", " ").is_err());
+        assert!(create_manual_transaction(&conn, "nope", &entry, "ana", "cash").is_err());
+        let free = ManualEntry::cash(date("2025-03-02"), 0.0, "Nothing");
+        assert!(create_manual_transaction(&conn, "default", &free, "ana", "cash").is_err());
+    }
+
+    #[test]
+    fn test_manual_entries_are_dedup_exempt() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        // The same cash coffee twice is two coffees
+        let entry = ManualEntry::cash(date("2025-03-02"), -4.5, "Coffee cart");
+        let first = create_manual_transaction(&conn, "default", &entry, "ana", "cash").unwrap();
+        create_manual_transaction(&conn, "default", &entry, "ana", "cash again").unwrap();
+
+        // A bank row with the same content is not taken for either of them
+        let mut bank = first.clone();
+        bank.id = String::new();
+        bank.init_temporal_fields();
+        bank.metadata.clear();
+        bank.source_file = "cash.csv".to_string();
+        assert!(insert_transaction_as(&conn, &bank, "ana").unwrap());
+
+        let active = get_active_transactions(&conn).unwrap();
+        assert_eq!(active.len(), 3);
+        assert!(DeduplicationEngine::new().find_duplicates(&active).is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+// 🔢 MCC - Merchant category codes from card networks
+//
+// Problem solved:
+// - Some feeds (OFX, card exports) carry a 4-digit MCC the card network assigned
+// - Guessing "Restaurants" from "SQ *BLUE BOTTLE" is worse than reading 5812
+//
+// When a transaction has an MCC, its category comes from this table before any
+// text rule is consulted. Codes the table doesn't know fall through to the rules.
+
+use crate::entities::{CategoryType, MerchantType};
+use crate::rules::ClassificationResult;
+use CategoryType::{Expense, Transfer};
+use MerchantType::{
+    Entertainment, Financial, Government, Healthcare, OnlineService, Restaurant, Retail,
+    Transportation, Utility,
+};
+
+/// Confidence given to MCC-derived categories (higher than typical text rules)
+pub const MCC_CONFIDENCE: f64 = 0.95;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MccEntry {
+    /// Inclusive code range (most entries are a single code)
+    pub first: u16,
+    pub last: u16,
+    pub description: &'static str,
+    pub category: &'static str,
+    pub category_type: CategoryType,
+    pub merchant_type: MerchantType,
+}
+
+const fn entry(
+    first: u16,
+    last: u16,
+    description: &'static str,
+    category: &'static str,
+    category_type: CategoryType,
+    merchant_type: MerchantType,
+) -> MccEntry {
+    MccEntry { first, last, description, category, category_type, merchant_type }
+}
+
+/// Bundled MCC → category mapping (ISO 18245 groups we see in personal finances)
+pub const MCC_TABLE: &[MccEntry] = &[
+    entry(3000, 3299, "Airlines", "Travel", Expense, Transportation),
+    entry(3351, 3441, "Car rental", "Transportation", Expense, Transportation),
+    entry(3501, 3999, "Hotels and lodging", "Travel", Expense, Retail),
+    entry(4111, 4111, "Commuter transport", "Transportation", Expense, Transportation),
+    entry(4121, 4121, "Taxis and rideshare", "Uber/Lyft", Expense, Transportation),
+    entry(4511, 4511, "Airlines", "Travel", Expense, Transportation),
+    entry(4814, 4814, "Telecommunication services", "Utilities", Expense, Utility),
+    entry(4899, 4899, "Cable and streaming services", "Entertainment", Expense, Entertainment),
+    entry(4900, 4900, "Utilities", "Utilities", Expense, Utility),
+    entry(5311, 5311, "Department stores", "Shopping", Expense, Retail),
+    entry(5411, 5411, "Grocery stores and supermarkets", "Groceries", Expense, Retail),
+    entry(5499, 5499, "Miscellaneous food stores", "Groceries", Expense, Retail),
+    entry(5541, 5542, "Service stations and fuel", "Gas & Fuel", Expense, Transportation),
+    entry(5732, 5732, "Electronics stores", "Shopping", Expense, Retail),
+    entry(5812, 5812, "Restaurants", "Restaurants", Expense, Restaurant),
+    entry(5813, 5813, "Bars and taverns", "Restaurants", Expense, Restaurant),
+    entry(5814, 5814, "Fast food", "Fast Food", Expense, Restaurant),
+    entry(5912, 5912, "Drug stores and pharmacies", "Health", Expense, Healthcare),
+    entry(5942, 5942, "Book stores", "Shopping", Expense, Retail),
+    entry(5968, 5968, "Subscription merchants", "Subscriptions", Expense, OnlineService),
+    entry(5999, 5999, "Miscellaneous retail", "Shopping", Expense, Retail),
+    entry(6011, 6012, "Cash and financial institutions", "Transfer", Transfer, Financial),
+    entry(7011, 7011, "Hotels and lodging", "Travel", Expense, Retail),
+    entry(7832, 7832, "Movie theaters", "Entertainment", Expense, Entertainment),
+    entry(8011, 8099, "Medical services", "Health", Expense, Healthcare),
+    entry(9311, 9311, "Tax payments", "Taxes", Expense, Government),
+    entry(9399, 9399, "Government services", "Taxes", Expense, Government),
+];
+
+/// Parse an MCC as found in feeds ("5812", " 5812 ", 5812.0 as text)
+pub fn parse_mcc(raw: &str) -> Option<u16> {
+    let raw = raw.trim();
+    let code = raw.strip_suffix(".0").unwrap_or(raw);
+    if code.len() != 4 {
+        return None;
+    }
+    code.parse().ok()
+}
+
+/// Table entry for a code, if we know it
+pub fn lookup_mcc(code: u16) -> Option<&'static MccEntry> {
+    MCC_TABLE.iter().find(|entry| (entry.first..=entry.last).contains(&code))
+}
+
+/// Category signal from an MCC (only the category is set)
+pub fn classify_mcc(code: u16) -> Option<ClassificationResult> {
+    lookup_mcc(code).map(|entry| ClassificationResult {
+        merchant: None,
+        category: Some(entry.category.to_string()),
+        transaction_type: None,
+        confidence: MCC_CONFIDENCE,
+        rule_id: Some(format!("mcc:{}", code)),
+    })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_single_codes_and_ranges() {
+        assert_eq!(lookup_mcc(5812).unwrap().category, "Restaurants");
+        assert_eq!(lookup_mcc(3058).unwrap().description, "Airlines");
+        assert_eq!(lookup_mcc(6011).unwrap().category_type, CategoryType::Transfer);
+        assert!(lookup_mcc(1234).is_none());
+    }
+
+    #[test]
+    fn test_parse_mcc() {
+        assert_eq!(parse_mcc("5812"), Some(5812));
+        assert_eq!(parse_mcc(" 5411 "), Some(5411));
+        assert_eq!(parse_mcc("5411.0"), Some(5411));
+        assert_eq!(parse_mcc("58"), None);
+        assert_eq!(parse_mcc("food"), None);
+    }
+}
@@ -0,0 +1,255 @@
+// 🏷️ Merchant Categories - A suggested category for every merchant
+//
+// Problem solved:
+// - Merchants found in imported data (bootstrap.rs) start without a
+//   suggested_category, so nothing pre-fills the category of their next
+//   transaction
+// - Some of them were never categorized at all, but a merchant with a similar
+//   name usually was ("Philz Coffee" next to "Blue Bottle Coffee")
+//
+// `suggest_merchant_category` weighs two signals: the categories the user
+// gave the merchant's own transactions, and the suggested categories of its
+// nearest-name peers (edit-distance or shared-word similarity). The winner is
+// stored as the merchant's suggested_category, and its confidence and the
+// evidence behind it under `metadata.category_suggestion`, so a reviewer can
+// see why a merchant got its category.
+
+use crate::aliases::{key_similarity, spelling_key};
+use crate::db::Transaction;
+use crate::entities::{Merchant, MerchantRegistry};
+use crate::triage::needs_triage;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Weight of the merchant's own categorized transactions (split by count)
+const HISTORY_WEIGHT: f64 = 2.0;
+
+/// Weight of one peer merchant (scaled by its similarity)
+const PEER_WEIGHT: f64 = 1.0;
+
+/// Peers considered per merchant, nearest first
+pub const PEER_LIMIT: usize = 3;
+
+/// Lowest name similarity (0.0-1.0) for a merchant to count as a peer
+pub const MIN_PEER_SIMILARITY: f64 = 0.5;
+
+/// Evidence a confidence is discounted by: one peer alone is not certainty
+const EVIDENCE_PRIOR: f64 = 0.5;
+
+/// Metadata key holding the suggestion's confidence and provenance
+pub const SUGGESTION_METADATA_KEY: &str = "category_suggestion";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionSource {
+    /// Categories assigned to the merchant's own transactions
+    History,
+    /// Suggested category of a merchant with a similar name
+    Peer,
+}
+
+/// One piece of evidence for a category
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SuggestionEvidence {
+    pub source: SuggestionSource,
+    pub category: String,
+    /// "4× on its transactions", "Blue Bottle Coffee (0.50)"
+    pub detail: String,
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MerchantCategorySuggestion {
+    pub category: String,
+    /// Share of the evidence for `category`, discounted when there is little
+    pub confidence: f64,
+    /// Everything that was weighed, strongest first (all categories)
+    pub evidence: Vec<SuggestionEvidence>,
+}
+
+impl MerchantCategorySuggestion {
+    /// Suggestion recorded on a merchant by `suggest_missing_categories`
+    pub fn recorded(merchant: &Merchant) -> Option<Self> {
+        serde_json::from_value(merchant.metadata.get(SUGGESTION_METADATA_KEY)?.clone()).ok()
+    }
+}
+
+/// Name similarity of two merchants: edit distance of their normalized keys,
+/// or the share of the shorter name's words that the other one also has
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (spelling_key(a), spelling_key(b));
+    let words = |key: &str| -> BTreeSet<String> {
+        key.split_whitespace().filter(|word| word.len() >= 3).map(str::to_string).collect()
+    };
+    let (words_a, words_b) = (words(&a), words(&b));
+    let fewest = words_a.len().min(words_b.len());
+    let overlap = match fewest {
+        0 => 0.0,
+        n => words_a.intersection(&words_b).count() as f64 / n as f64,
+    };
+    key_similarity(&a, &b).max(overlap)
+}
+
+/// Category for `merchant` from its categorized transactions in `history` and
+/// from the nearest of `peers` that have a suggested category
+pub fn suggest_merchant_category(
+    merchant: &Merchant,
+    peers: &[Merchant],
+    history: &[Transaction],
+) -> Option<MerchantCategorySuggestion> {
+    let mut evidence = Vec::new();
+
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for tx in history.iter().filter(|tx| tx.is_active() && !needs_triage(tx)) {
+        if !tx.merchant.trim().is_empty() && merchant.matches(&tx.merchant) {
+            *counts.entry(tx.category.trim()).or_default() += 1;
+        }
+    }
+    let total: usize = counts.values().sum();
+    for (category, count) in counts {
+        evidence.push(SuggestionEvidence {
+            source: SuggestionSource::History,
+            category: category.to_string(),
+            detail: format!("{}× on its transactions", count),
+            weight: HISTORY_WEIGHT * count as f64 / total as f64,
+        });
+    }
+
+    let mut nearest: Vec<(f64, &Merchant, &String)> = peers
+        .iter()
+        .filter(|peer| peer.id != merchant.id)
+        .filter_map(|peer| {
+            let category = peer.suggested_category.as_ref()?;
+            let similarity = name_similarity(&merchant.canonical_name, &peer.canonical_name);
+            (similarity >= MIN_PEER_SIMILARITY).then_some((similarity, peer, category))
+        })
+        .collect();
+    nearest.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.canonical_name.cmp(&b.1.canonical_name)));
+    for (similarity, peer, category) in nearest.into_iter().take(PEER_LIMIT) {
+        evidence.push(SuggestionEvidence {
+            source: SuggestionSource::Peer,
+            category: category.clone(),
+            detail: format!("{} ({:.2})", peer.canonical_name, similarity),
+            weight: PEER_WEIGHT * similarity,
+        });
+    }
+
+    let mut points: BTreeMap<&str, f64> = BTreeMap::new();
+    for item in &evidence {
+        *points.entry(item.category.as_str()).or_default() += item.weight;
+    }
+    // Ties go to the alphabetically first category, so reruns agree
+    let (category, best) = points
+        .iter()
+        .max_by(|a, b| a.1.total_cmp(b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(category, best)| (category.to_string(), *best))?;
+    let all = evidence.iter().fold(0.0, |total, item| total + item.weight);
+
+    evidence.sort_by(|a, b| b.weight.total_cmp(&a.weight).then_with(|| a.category.cmp(&b.category)));
+    Some(MerchantCategorySuggestion {
+        category,
+        confidence: best / (all + EVIDENCE_PRIOR),
+        evidence,
+    })
+}
+
+/// Give every current merchant without a suggested category one (when there
+/// is any evidence); returns the merchants that got one
+pub fn suggest_missing_categories(
+    registry: &mut MerchantRegistry,
+    history: &[Transaction],
+) -> Vec<(String, MerchantCategorySuggestion)> {
+    let merchants = registry.all_merchants();
+    let mut suggested = Vec::new();
+    for merchant in merchants.iter().filter(|merchant| merchant.suggested_category.is_none()) {
+        let Some(suggestion) = suggest_merchant_category(merchant, &merchants, history) else {
+            continue;
+        };
+        let recorded = serde_json::to_value(&suggestion).unwrap_or_default();
+        let updated = registry.update_merchant(&merchant.id, |next| {
+            next.suggested_category = Some(suggestion.category.clone());
+            if let Some(metadata) = next.metadata.as_object_mut() {
+                metadata.insert(SUGGESTION_METADATA_KEY.to_string(), recorded.clone());
+            }
+        });
+        if updated.is_ok() {
+            suggested.push((merchant.id.clone(), suggestion));
+        }
+    }
+    suggested
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::entities::MerchantType;
+
+    fn tx(merchant: &str, category: &str) -> Transaction {
+        Transaction {
+            date: "01/05/2025".to_string(),
+            description: merchant.to_uppercase(),
+            amount_original: "-9.00".to_string(),
+            amount_numeric: -9.0,
+            category: category.to_string(),
+            merchant: merchant.to_string(),
+            account_number: "1234".to_string(),
+            ..test_support::sample_transaction()
+        }
+    }
+
+    fn merchant(name: &str, category: Option<&str>) -> Merchant {
+        Merchant::new(name.to_string(), MerchantType::Other, category.map(str::to_string))
+    }
+
+    #[test]
+    fn test_history_outweighs_peers() {
+        let philz = merchant("Philz Coffee", None);
+        let peers = vec![merchant("Blue Bottle Coffee", Some("Coffee")), merchant("Amazon", Some("Shopping"))];
+
+        // Only the peer: a suggestion, but not a confident one
+        let from_peer = suggest_merchant_category(&philz, &peers, &[]).unwrap();
+        assert_eq!(from_peer.category, "Coffee");
+        assert_eq!(from_peer.evidence.len(), 1);
+        assert_eq!(from_peer.evidence[0].source, SuggestionSource::Peer);
+        assert!(from_peer.confidence <= 0.5);
+
+        // Its own transactions say otherwise (uncategorized rows don't count)
+        let history = vec![
+            tx("Philz Coffee", "Restaurants"),
+            tx("PHILZ COFFEE", "Restaurants"),
+            tx("Philz Coffee", "Unknown"),
+            tx("Amazon", "Shopping"),
+        ];
+        let suggestion = suggest_merchant_category(&philz, &peers, &history).unwrap();
+        assert_eq!(suggestion.category, "Restaurants");
+        assert!(suggestion.confidence > from_peer.confidence && suggestion.confidence <= 1.0);
+        assert_eq!(suggestion.evidence[0].detail, "2× on its transactions");
+
+        assert!(suggest_merchant_category(&merchant("Zzyzx Ltd", None), &peers, &[]).is_none());
+    }
+
+    #[test]
+    fn test_missing_categories_are_filled_with_provenance() {
+        let mut registry = MerchantRegistry::new();
+        registry.register(merchant("Blue Bottle Coffee", Some("Coffee")));
+        registry.register(merchant("Philz Coffee", None));
+        registry.register(merchant("Zzyzx Ltd", None));
+
+        let suggested = suggest_missing_categories(&mut registry, &[]);
+        assert_eq!(suggested.len(), 1);
+        let philz = registry.find_by_string("Philz Coffee").unwrap();
+        assert_eq!(philz.suggested_category.as_deref(), Some("Coffee"));
+        assert_eq!(philz.version, 2);
+        let recorded = MerchantCategorySuggestion::recorded(&philz).unwrap();
+        assert_eq!(recorded, suggested[0].1);
+        assert!(recorded.evidence[0].detail.starts_with("Blue Bottle Coffee"));
+
+        // Already suggested: left alone
+        assert!(suggest_missing_categories(&mut registry, &[]).is_empty());
+    }
+}
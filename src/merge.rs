@@ -0,0 +1,362 @@
+// 🔀 Merge - Fold another instance's event log into this one
+//
+// Problem solved:
+// - `sync import` assumes both sides are one ledger kept in two places: same
+//   ledger ids, and a line imported on both sides is simply a duplicate
+// - Two installations that grew apart (a personal laptop ledger and the
+//   household server's "default") had no way to become one ledger
+//
+// `merge_changeset` replays a changeset exported by `sync export` into the
+// local store. Source ledgers are renamed through a ledger map (unmapped ones
+// keep their id). A transaction whose statement line is already current in
+// the target ledger is mapped onto the local identity: its values are
+// compared with the local ones and, when they disagree, resolved by the merge
+// policy (or the target ledger's own, see conflicts.rs). Every other identity
+// is inserted with its full version history. Events keep their event_id, so
+// merging the same file twice is a no-op, but are relabeled with the mapped
+// ledger and transaction ids.
+
+use crate::conflicts::{is_conflict, resolve_conflict, Conflict, ConflictPolicy};
+use crate::db::{get_current_transaction, insert_event, insert_transaction_row, Event, Transaction};
+use crate::event_schema::LedgerMerged;
+use crate::ledger::get_ledger;
+use crate::sync::{instance_id, Changeset, SyncedVersion};
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    /// Source ledger id → local ledger id (unmapped ledgers keep their id)
+    pub ledger_map: BTreeMap<String, String>,
+
+    /// Overrides each target ledger's configured conflict policy
+    pub policy: Option<ConflictPolicy>,
+}
+
+impl MergeOptions {
+    /// Parse "personal=default" into a ledger mapping
+    pub fn map_ledger(&mut self, mapping: &str) -> Result<()> {
+        let (source, target) = mapping
+            .split_once('=')
+            .filter(|(source, target)| !source.trim().is_empty() && !target.trim().is_empty())
+            .ok_or_else(|| anyhow!("Invalid ledger mapping '{}' (expected <source>=<target>)", mapping))?;
+        self.ledger_map.insert(source.trim().to_string(), target.trim().to_string());
+        Ok(())
+    }
+
+    fn target_ledger<'a>(&'a self, source: &'a str) -> &'a str {
+        self.ledger_map.get(source).map_or(source, String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MergeSummary {
+    /// Versions inserted (identities new to this instance)
+    pub versions_applied: usize,
+
+    /// Versions already here (same tx_uuid + version)
+    pub versions_skipped: usize,
+
+    /// Source identities whose statement line was already here (mapped onto
+    /// the local identity)
+    pub identities_mapped: usize,
+
+    /// Mapped identities whose values disagreed (resolved by policy)
+    pub conflicts: usize,
+
+    pub events_applied: usize,
+    pub events_skipped: usize,
+}
+
+/// Verify `changeset` and merge it into the local store (all-or-nothing)
+pub fn merge_changeset(
+    conn: &Connection,
+    changeset: &Changeset,
+    key: &str,
+    options: &MergeOptions,
+    actor: &str,
+) -> Result<MergeSummary> {
+    changeset.verify(key)?;
+    if changeset.instance_id == instance_id(conn)? {
+        return Err(anyhow!("Changeset was exported by this instance"));
+    }
+
+    let db_tx = conn.unchecked_transaction()?;
+    let mut summary = MergeSummary::default();
+
+    for ledger in &changeset.ledgers {
+        let target = options.target_ledger(&ledger.id);
+        if target != ledger.id {
+            if get_ledger(conn, target)?.is_none() {
+                return Err(anyhow!("Target ledger '{}' (for '{}') does not exist", target, ledger.id));
+            }
+            continue;
+        }
+        conn.execute(
+            "INSERT OR IGNORE INTO ledgers (id, name, config, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                ledger.id,
+                ledger.name,
+                serde_json::to_string(&ledger.config)?,
+                ledger.created_at.to_rfc3339(),
+            ],
+        )?;
+    }
+
+    let mut identities: BTreeMap<&str, Vec<&SyncedVersion>> = BTreeMap::new();
+    for synced in changeset.versions.iter().filter(|synced| !synced.transaction.id.is_empty()) {
+        identities.entry(synced.transaction.id.as_str()).or_default().push(synced);
+    }
+
+    // Identities a previous merge of this log already handled
+    let mut merged_before = HashSet::new();
+    for event in changeset.events.iter().filter(|event| event.entity_type == "transaction") {
+        if event_exists(conn, &event.event_id)? {
+            merged_before.insert(event.entity_id.as_str());
+        }
+    }
+
+    // Source tx_uuid → local tx_uuid, for identities mapped onto a local one
+    let mut id_map: HashMap<String, String> = HashMap::new();
+    for (source_id, mut versions) in identities {
+        versions.sort_by_key(|synced| synced.transaction.version);
+        let relabeled: Vec<Transaction> = versions
+            .iter()
+            .map(|synced| {
+                let mut tx = synced.transaction.clone();
+                tx.ledger_id = options.target_ledger(&tx.ledger_id).to_string();
+                tx
+            })
+            .collect();
+        let latest = relabeled.last().expect("identity has at least one version");
+
+        let local_id = local_identity(conn, latest, &versions[0].idempotency_hash)?;
+        match local_id {
+            Some(local_id) if merged_before.contains(source_id) => {
+                summary.versions_skipped += versions.len();
+                id_map.insert(source_id.to_string(), local_id);
+            }
+            Some(local_id) => {
+                summary.identities_mapped += 1;
+                merge_into_local(conn, latest, &local_id, options, actor, &mut summary)?;
+                id_map.insert(source_id.to_string(), local_id);
+            }
+            None => {
+                for (tx, synced) in relabeled.iter().zip(&versions) {
+                    let exists: bool = conn.query_row(
+                        "SELECT EXISTS(SELECT 1 FROM transactions WHERE tx_uuid = ?1 AND version = ?2)",
+                        params![tx.id, tx.version],
+                        |row| row.get(0),
+                    )?;
+                    if exists {
+                        summary.versions_skipped += 1;
+                    } else {
+                        insert_transaction_row(conn, tx, &synced.idempotency_hash)?;
+                        summary.versions_applied += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    for event in &changeset.events {
+        if event_exists(conn, &event.event_id)? {
+            summary.events_skipped += 1;
+            continue;
+        }
+
+        let mut relabeled = event.clone();
+        relabeled.ledger_id = options.target_ledger(&event.ledger_id).to_string();
+        if event.entity_type == "transaction" {
+            if let Some(local_id) = id_map.get(&event.entity_id) {
+                relabeled.entity_id = local_id.clone();
+            }
+        }
+        insert_event(conn, &relabeled)?;
+        summary.events_applied += 1;
+    }
+
+    let payload = LedgerMerged {
+        ledger_map: options.ledger_map.clone(),
+        policy: options.policy,
+        summary: summary.clone(),
+    };
+    let event = Event::typed("ledger_merged", "sync", &changeset.instance_id, &payload, actor)?;
+    insert_event(conn, &event)?;
+
+    db_tx.commit()?;
+    Ok(summary)
+}
+
+fn event_exists(conn: &Connection, event_id: &str) -> Result<bool> {
+    Ok(conn.query_row("SELECT EXISTS(SELECT 1 FROM events WHERE event_id = ?1)", [event_id], |row| {
+        row.get(0)
+    })?)
+}
+
+/// Another identity current for `latest`'s statement line in its target ledger
+fn local_identity(conn: &Connection, latest: &Transaction, hash: &str) -> Result<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT tx_uuid FROM transactions
+             WHERE ledger_id = ?1 AND idempotency_hash = ?2 AND valid_until IS NULL AND tx_uuid != ?3",
+            params![latest.ledger_id, hash, latest.id],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+/// Compare the source's latest values with the local identity's current ones
+fn merge_into_local(
+    conn: &Connection,
+    latest: &Transaction,
+    local_id: &str,
+    options: &MergeOptions,
+    actor: &str,
+    summary: &mut MergeSummary,
+) -> Result<()> {
+    let Some(local) = get_current_transaction(conn, local_id)? else {
+        return Ok(());
+    };
+
+    // Judged as a competing version of the local identity
+    let mut incoming = latest.clone();
+    incoming.id = local.id.clone();
+    incoming.version = local.version;
+    incoming.previous_version_id = local.previous_version_id.clone();
+    if !is_conflict(&local, &incoming) {
+        return Ok(());
+    }
+
+    let policy = match options.policy {
+        Some(policy) => policy,
+        None => ConflictPolicy::for_ledger(conn, &local.ledger_id)?,
+    };
+    resolve_conflict(conn, &Conflict { local, incoming }, policy, actor)?;
+    summary.conflicts += 1;
+    Ok(())
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::db::{
+        get_active_transactions, get_events_for_entity, insert_transaction_version, insert_transactions_as,
+        setup_database,
+    };
+    use crate::ledger::{create_ledger, Ledger};
+    use crate::sync::{export_changeset, Checkpoint};
+
+    const KEY: &str = "shared-secret";
+
+    fn new_instance() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        conn
+    }
+
+    fn coffee(line: &str, amount: f64, ledger_id: &str) -> Transaction {
+        let mut tx = Transaction {
+            date: "05/01/2025".to_string(),
+            amount_original: format!("-${:.2}", amount),
+            amount_numeric: -amount,
+            category: "Food".to_string(),
+            merchant: "Coffee".to_string(),
+            account_number: "1234".to_string(),
+            line_number: line.to_string(),
+            ledger_id: ledger_id.to_string(),
+            ..test_support::sample_transaction()
+        };
+        tx.init_temporal_fields();
+        tx
+    }
+
+    #[test]
+    fn test_merge_maps_ledgers_and_duplicate_lines() {
+        let laptop = new_instance();
+        create_ledger(&laptop, &Ledger::new("personal", "Personal"), "ana").unwrap();
+        let shared = coffee("1", 4.5, "personal");
+        let own = coffee("2", 9.0, "personal");
+        insert_transactions_as(&laptop, &[shared.clone(), own.clone()], "test").unwrap();
+        let mut corrected = own.next_version(Some("Wrong category".to_string()));
+        corrected.category = "Coffee".to_string();
+        insert_transaction_version(&laptop, &corrected, "ana").unwrap();
+        insert_transaction_version(&laptop, &shared.next_version(None), "ana").unwrap();
+
+        // The server imported line 1 on its own, into its default ledger
+        let server = new_instance();
+        let local = coffee("1", 4.5, "default");
+        insert_transactions_as(&server, std::slice::from_ref(&local), "test").unwrap();
+
+        let changeset = export_changeset(&laptop, Checkpoint::default(), KEY).unwrap();
+        let mut options = MergeOptions::default();
+        options.map_ledger("personal=default").unwrap();
+        let summary = merge_changeset(&server, &changeset, KEY, &options, "bob").unwrap();
+        assert_eq!(summary.identities_mapped, 1);
+        assert_eq!(summary.versions_applied, 2);
+        assert_eq!(summary.conflicts, 0);
+
+        let active = get_active_transactions(&server).unwrap();
+        assert_eq!(active.len(), 2);
+        assert!(active.iter().all(|tx| tx.ledger_id == "default"));
+        let merged = get_current_transaction(&server, &own.id).unwrap().unwrap();
+        assert_eq!((merged.version, merged.category.as_str()), (2, "Coffee"));
+        assert!(get_ledger(&server, "personal").unwrap().is_none());
+
+        // The laptop's events about line 1 now describe the server's identity
+        let events = get_events_for_entity(&server, "transaction", &local.id).unwrap();
+        assert!(events.iter().any(|e| e.event_type == "transaction_corrected" && e.actor == "ana"));
+        assert!(get_events_for_entity(&server, "transaction", &shared.id).unwrap().is_empty());
+
+        // Merging the same file again changes nothing
+        let again = merge_changeset(&server, &changeset, KEY, &options, "bob").unwrap();
+        assert_eq!((again.versions_applied, again.versions_skipped, again.events_applied), (0, 4, 0));
+        assert_eq!(get_active_transactions(&server).unwrap().len(), 2);
+
+        let mut unknown = MergeOptions::default();
+        unknown.map_ledger("personal=household").unwrap();
+        assert!(merge_changeset(&new_instance(), &changeset, KEY, &unknown, "bob").is_err());
+        assert!(unknown.map_ledger("personal").is_err());
+    }
+
+    #[test]
+    fn test_merge_resolves_disagreeing_duplicates_by_policy() {
+        let laptop = new_instance();
+        let tx = coffee("1", 4.5, "default");
+        insert_transactions_as(&laptop, std::slice::from_ref(&tx), "test").unwrap();
+        let mut recategorized = tx.next_version(None);
+        recategorized.category = "Coffee".to_string();
+        insert_transaction_version(&laptop, &recategorized, "ana").unwrap();
+
+        let server = new_instance();
+        let local = coffee("1", 4.5, "default");
+        insert_transactions_as(&server, std::slice::from_ref(&local), "test").unwrap();
+        let changeset = export_changeset(&laptop, Checkpoint::default(), KEY).unwrap();
+
+        // Manual: the local values stay, the laptop's wait for approval
+        let options = MergeOptions { policy: Some(ConflictPolicy::Manual), ..Default::default() };
+        let summary = merge_changeset(&server, &changeset, KEY, &options, "bob").unwrap();
+        assert_eq!((summary.identities_mapped, summary.conflicts), (1, 1));
+        let current = get_current_transaction(&server, &local.id).unwrap().unwrap();
+        assert_eq!(current.category, "Food");
+        let pending: i64 = server
+            .query_row("SELECT COUNT(*) FROM pending_changes", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(pending, 1);
+
+        let merged = get_events_for_entity(&server, "sync", &changeset.instance_id).unwrap();
+        assert_eq!(merged.last().unwrap().payload::<LedgerMerged>().unwrap().summary, summary);
+
+        // Merged before: not queued a second time
+        let again = merge_changeset(&server, &changeset, KEY, &options, "bob").unwrap();
+        assert_eq!((again.identities_mapped, again.conflicts, again.versions_skipped), (0, 0, 2));
+    }
+}
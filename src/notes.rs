@@ -0,0 +1,247 @@
+// 📝 Notes - Comments attached to a transaction
+//
+// Problem solved:
+// - classification_notes holds ONE machine-written string, overwritten by every import
+// - There was nowhere to record "asked Ana about this" or "refund promised by phone"
+//
+// Notes live in their own table keyed by transaction identity (tx_uuid), so they
+// survive corrections: every version of a transaction shares the same thread.
+// A note may reply to another note on the same transaction.
+
+use crate::db::{get_current_transaction, insert_event, Event};
+use crate::event_schema::NoteAdded;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Note {
+    pub id: String,
+    pub tx_uuid: String,
+    pub ledger_id: String,
+
+    /// Note this one replies to (None = top of a thread)
+    pub parent_id: Option<String>,
+
+    pub author: String,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// ============================================================================
+// WRITES
+// ============================================================================
+
+/// Add a note to a transaction, optionally as a reply to `parent_id`
+pub fn add_note(
+    conn: &Connection,
+    tx_uuid: &str,
+    author: &str,
+    text: &str,
+    parent_id: Option<&str>,
+) -> Result<Note> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(anyhow!("Note text must not be empty"));
+    }
+
+    let tx = get_current_transaction(conn, tx_uuid)?
+        .ok_or_else(|| anyhow!("Transaction {} not found", tx_uuid))?;
+
+    if let Some(parent_id) = parent_id {
+        let parent = get_note(conn, parent_id)?
+            .ok_or_else(|| anyhow!("Note {} not found", parent_id))?;
+        if parent.tx_uuid != tx_uuid {
+            return Err(anyhow!("Note {} belongs to another transaction", parent_id));
+        }
+    }
+
+    let note = Note {
+        id: uuid::Uuid::new_v4().to_string(),
+        tx_uuid: tx_uuid.to_string(),
+        ledger_id: tx.ledger_id,
+        parent_id: parent_id.map(str::to_string),
+        author: author.to_string(),
+        text: text.to_string(),
+        created_at: Utc::now(),
+    };
+
+    conn.execute(
+        "INSERT INTO transaction_notes (id, tx_uuid, ledger_id, parent_id, author, text, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            note.id,
+            note.tx_uuid,
+            note.ledger_id,
+            note.parent_id,
+            note.author,
+            note.text,
+            note.created_at.to_rfc3339(),
+        ],
+    )?;
+
+    let payload = NoteAdded { note_id: note.id.clone(), parent_id: note.parent_id.clone() };
+    let event = Event::typed("note_added", "transaction", tx_uuid, &payload, author)?.with_ledger(&note.ledger_id);
+    insert_event(conn, &event)?;
+
+    Ok(note)
+}
+
+// ============================================================================
+// QUERIES
+// ============================================================================
+
+const NOTE_COLUMNS: &str = "id, tx_uuid, ledger_id, parent_id, author, text, created_at";
+
+pub fn get_note(conn: &Connection, note_id: &str) -> Result<Option<Note>> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT {} FROM transaction_notes WHERE id = ?1", NOTE_COLUMNS),
+            [note_id],
+            row_to_note,
+        )
+        .optional()?)
+}
+
+/// All notes on a transaction, oldest first
+pub fn get_notes(conn: &Connection, tx_uuid: &str) -> Result<Vec<Note>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM transaction_notes WHERE tx_uuid = ?1 ORDER BY created_at ASC, rowid ASC",
+        NOTE_COLUMNS
+    ))?;
+    let notes = stmt.query_map([tx_uuid], row_to_note)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(notes)
+}
+
+/// Every note in a ledger, oldest first (the TUI loads these up front)
+pub fn get_ledger_notes(conn: &Connection, ledger_id: &str) -> Result<Vec<Note>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM transaction_notes WHERE ledger_id = ?1 ORDER BY created_at ASC, rowid ASC",
+        NOTE_COLUMNS
+    ))?;
+    let notes = stmt.query_map([ledger_id], row_to_note)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(notes)
+}
+
+fn row_to_note(row: &rusqlite::Row) -> rusqlite::Result<Note> {
+    let created_at: String = row.get(6)?;
+    Ok(Note {
+        id: row.get(0)?,
+        tx_uuid: row.get(1)?,
+        ledger_id: row.get(2)?,
+        parent_id: row.get(3)?,
+        author: row.get(4)?,
+        text: row.get(5)?,
+        created_at: DateTime::parse_from_rfc3339(&created_at)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+// ============================================================================
+// THREADING
+// ============================================================================
+
+/// Order notes as threads: each note is followed by its replies
+///
+/// Returns (depth, note) pairs; top-level notes have depth 0. Replies whose
+/// parent is missing from `notes` are shown at the top level.
+pub fn thread_notes(notes: &[Note]) -> Vec<(usize, &Note)> {
+    fn walk<'a>(notes: &'a [Note], parent: &str, depth: usize, out: &mut Vec<(usize, &'a Note)>) {
+        for note in notes.iter().filter(|n| n.parent_id.as_deref() == Some(parent)) {
+            out.push((depth, note));
+            walk(notes, &note.id, depth + 1, out);
+        }
+    }
+
+    let mut out = Vec::with_capacity(notes.len());
+    let is_root = |note: &Note| match &note.parent_id {
+        None => true,
+        Some(parent) => !notes.iter().any(|n| &n.id == parent),
+    };
+    for note in notes.iter().filter(|n| is_root(n)) {
+        out.push((0, note));
+        walk(notes, &note.id, 1, &mut out);
+    }
+    out
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::db::{get_events_for_entity, insert_transactions_as, setup_database, Transaction};
+
+    fn setup() -> (Connection, String) {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let mut tx = Transaction {
+            date: "03/01/2025".to_string(),
+            description: "STARBUCKS STORE #1234".to_string(),
+            amount_original: "-$5.00".to_string(),
+            amount_numeric: -5.0,
+            merchant: "Starbucks".to_string(),
+            account_name: "BofA Checking".to_string(),
+            account_number: "4321".to_string(),
+            ..test_support::sample_transaction()
+        };
+        tx.init_temporal_fields();
+        insert_transactions_as(&conn, std::slice::from_ref(&tx), "test").unwrap();
+        (conn, tx.id)
+    }
+
+    #[test]
+    fn test_add_and_list_notes() {
+        let (conn, tx_uuid) = setup();
+
+        let first = add_note(&conn, &tx_uuid, "ana", "Asked the bank about this", None).unwrap();
+        add_note(&conn, &tx_uuid, "ben", "  Refund promised  ", None).unwrap();
+
+        let notes = get_notes(&conn, &tx_uuid).unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0], first);
+        assert_eq!(notes[1].text, "Refund promised");
+
+        let events = get_events_for_entity(&conn, "transaction", &tx_uuid).unwrap();
+        assert_eq!(events.iter().filter(|e| e.event_type == "note_added").count(), 2);
+    }
+
+    #[test]
+    fn test_add_note_validation() {
+        let (conn, tx_uuid) = setup();
+
+        assert!(add_note(&conn, &tx_uuid, "ana", "   ", None).is_err());
+        assert!(add_note(&conn, "no-such-tx", "ana", "hello", None).is_err());
+        assert!(add_note(&conn, &tx_uuid, "ana", "hello", Some("no-such-note")).is_err());
+    }
+
+    #[test]
+    fn test_replies_are_threaded() {
+        let (conn, tx_uuid) = setup();
+
+        let question = add_note(&conn, &tx_uuid, "ana", "Is this a duplicate?", None).unwrap();
+        let other = add_note(&conn, &tx_uuid, "ben", "Receipt is in the drive", None).unwrap();
+        let answer = add_note(&conn, &tx_uuid, "ben", "No, two coffees", Some(&question.id)).unwrap();
+        add_note(&conn, &tx_uuid, "ana", "Thanks", Some(&answer.id)).unwrap();
+
+        let notes = get_notes(&conn, &tx_uuid).unwrap();
+        let threaded: Vec<(usize, &str)> =
+            thread_notes(&notes).into_iter().map(|(depth, n)| (depth, n.text.as_str())).collect();
+
+        assert_eq!(
+            threaded,
+            vec![
+                (0, "Is this a duplicate?"),
+                (1, "No, two coffees"),
+                (2, "Thanks"),
+                (0, other.text.as_str()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,546 @@
+// 📤 Outbox - Events pushed to external systems through webhooks
+//
+// Problem solved:
+// - Integrations (a spreadsheet of new transactions, an accounting SaaS) had
+//   to poll the database or the API to notice that anything happened
+// - Calling them from the write path would make an import fail, or hang,
+//   whenever the receiving end was down
+//
+// A webhook subscribes a URL to some event types (every type when none are
+// given), for one ledger or all of them. `insert_event` adds an outbox row per
+// matching webhook on the same connection as the event, so a write that rolls
+// back sends nothing. `deliver_due` POSTs the event JSON of due rows through a
+// `WebhookTransport`, signed with the webhook's secret:
+//
+//   X-Trust-Signature: sha256=<hex HMAC-SHA256(secret, "<timestamp>.<body>")>
+//
+// A failed delivery is retried with exponential backoff and marked failed
+// after MAX_DELIVERY_ATTEMPTS (`retry_failed` queues it again). The HTTP
+// transport needs the `webhooks` feature; the server then runs a worker.
+
+use crate::db::{get_event, insert_event, Event};
+use crate::event_schema::{registered_event_types, WebhookChanged};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use sha2::Sha256;
+
+/// Attempts before a delivery is marked failed
+pub const MAX_DELIVERY_ATTEMPTS: i64 = 8;
+
+/// Wait after the first failed attempt; doubles with each further failure
+pub const FIRST_RETRY_DELAY_SECS: i64 = 30;
+
+/// Longest wait between two attempts
+pub const MAX_RETRY_DELAY_SECS: i64 = 6 * 60 * 60;
+
+/// Rows sent per `deliver_due` call
+pub const DELIVERY_BATCH: usize = 50;
+
+// ============================================================================
+// TYPES
+// ============================================================================
+
+/// A URL that receives some event types
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    /// Empty = every event type
+    pub event_types: Vec<String>,
+    /// None = every ledger
+    pub ledger_id: Option<String>,
+    /// HMAC key of the signature header (shown once, when added)
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "pending" => Ok(DeliveryStatus::Pending),
+            "delivered" => Ok(DeliveryStatus::Delivered),
+            "failed" => Ok(DeliveryStatus::Failed),
+            other => Err(anyhow!("Unknown delivery status '{}'", other)),
+        }
+    }
+}
+
+/// One event waiting for (or done with) delivery to one webhook
+#[derive(Debug, Clone, Serialize)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub webhook_id: String,
+    pub event_id: String,
+    pub event_type: String,
+    pub status: DeliveryStatus,
+    pub attempts: i64,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// Outcome of one `deliver_due` call
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DeliveryReport {
+    pub delivered: usize,
+    /// Failed this time, will be tried again
+    pub retrying: usize,
+    /// Failed for the last time
+    pub failed: usize,
+}
+
+/// Sends one signed POST; returns the HTTP status
+pub trait WebhookTransport {
+    fn post(&self, url: &str, headers: &[(&str, String)], body: &str) -> Result<u16>;
+}
+
+// ============================================================================
+// WEBHOOKS
+// ============================================================================
+
+/// Subscribe `url` to `event_types` (all when empty) of one ledger (all when None)
+pub fn add_webhook(
+    conn: &Connection,
+    url: &str,
+    event_types: &[String],
+    ledger_id: Option<&str>,
+    actor: &str,
+) -> Result<Webhook> {
+    let url = url.trim();
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        return Err(anyhow!("Webhook URL must start with http:// or https://: {}", url));
+    }
+    let known = registered_event_types();
+    if let Some(unknown) = event_types.iter().find(|event_type| !known.contains(&event_type.as_str())) {
+        return Err(anyhow!("Unknown event type '{}'", unknown));
+    }
+
+    let webhook = Webhook {
+        id: uuid::Uuid::new_v4().to_string(),
+        url: url.to_string(),
+        event_types: event_types.to_vec(),
+        ledger_id: ledger_id.map(str::to_string),
+        secret: format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple()),
+        active: true,
+        created_at: Utc::now(),
+    };
+    conn.execute(
+        "INSERT INTO webhooks (id, url, event_types, ledger_id, secret, active, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6)",
+        params![
+            webhook.id,
+            webhook.url,
+            serde_json::to_string(&webhook.event_types)?,
+            webhook.ledger_id,
+            webhook.secret,
+            webhook.created_at.to_rfc3339(),
+        ],
+    )?;
+
+    log_webhook_event(conn, "webhook_added", &webhook, actor)?;
+    Ok(webhook)
+}
+
+/// Stop sending to a webhook; its undelivered rows are marked failed
+pub fn remove_webhook(conn: &Connection, webhook_id: &str, actor: &str) -> Result<Webhook> {
+    let webhook = get_webhook(conn, webhook_id)?
+        .filter(|webhook| webhook.active)
+        .ok_or_else(|| anyhow!("Webhook {} not found", webhook_id))?;
+
+    conn.execute("UPDATE webhooks SET active = 0 WHERE id = ?1", [webhook_id])?;
+    conn.execute(
+        "UPDATE outbox SET status = 'failed', last_error = 'webhook removed'
+         WHERE webhook_id = ?1 AND status = 'pending'",
+        [webhook_id],
+    )?;
+
+    log_webhook_event(conn, "webhook_removed", &webhook, actor)?;
+    Ok(Webhook { active: false, ..webhook })
+}
+
+fn log_webhook_event(conn: &Connection, event_type: &str, webhook: &Webhook, actor: &str) -> Result<()> {
+    let payload = WebhookChanged { url: webhook.url.clone(), event_types: webhook.event_types.clone() };
+    let mut event = Event::typed(event_type, "webhook", &webhook.id, &payload, actor)?;
+    if let Some(ledger_id) = &webhook.ledger_id {
+        event = event.with_ledger(ledger_id);
+    }
+    insert_event(conn, &event)
+}
+
+const WEBHOOK_COLUMNS: &str = "id, url, event_types, ledger_id, secret, active, created_at";
+
+pub fn get_webhook(conn: &Connection, webhook_id: &str) -> Result<Option<Webhook>> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT {} FROM webhooks WHERE id = ?1", WEBHOOK_COLUMNS),
+            [webhook_id],
+            row_to_webhook,
+        )
+        .optional()?)
+}
+
+/// Active webhooks, oldest first
+pub fn list_webhooks(conn: &Connection) -> Result<Vec<Webhook>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM webhooks WHERE active = 1 ORDER BY created_at",
+        WEBHOOK_COLUMNS
+    ))?;
+    let webhooks = stmt.query_map([], row_to_webhook)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(webhooks)
+}
+
+fn row_to_webhook(row: &rusqlite::Row) -> rusqlite::Result<Webhook> {
+    let event_types: String = row.get(2)?;
+    let created_at: String = row.get(6)?;
+    Ok(Webhook {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        event_types: serde_json::from_str(&event_types).unwrap_or_default(),
+        ledger_id: row.get(3)?,
+        secret: row.get(4)?,
+        active: row.get(5)?,
+        created_at: parse_time(&created_at),
+    })
+}
+
+fn parse_time(s: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now())
+}
+
+// ============================================================================
+// OUTBOX
+// ============================================================================
+
+/// Queue `event` for every active webhook that wants it (called by insert_event)
+pub(crate) fn enqueue_event(conn: &Connection, event: &Event) -> Result<usize> {
+    let queued = conn.execute(
+        "INSERT OR IGNORE INTO outbox (webhook_id, event_id, event_type, status, attempts, next_attempt_at, created_at)
+         SELECT id, ?1, ?2, 'pending', 0, ?3, ?3 FROM webhooks
+         WHERE active = 1
+           AND (ledger_id IS NULL OR ledger_id = ?4)
+           AND (json_array_length(event_types) = 0
+                OR EXISTS (SELECT 1 FROM json_each(webhooks.event_types) WHERE value = ?2))",
+        params![event.event_id, event.event_type, Utc::now().to_rfc3339(), event.ledger_id],
+    )?;
+    Ok(queued)
+}
+
+const OUTBOX_COLUMNS: &str =
+    "id, webhook_id, event_id, event_type, status, attempts, next_attempt_at, last_error, delivered_at";
+
+/// Outbox rows, newest first (all statuses when None)
+pub fn list_outbox(conn: &Connection, status: Option<DeliveryStatus>, limit: usize) -> Result<Vec<OutboxEntry>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM outbox WHERE (?1 IS NULL OR status = ?1) ORDER BY id DESC LIMIT ?2",
+        OUTBOX_COLUMNS
+    ))?;
+    let entries = stmt
+        .query_map(params![status.map(|s| s.as_str()), limit as i64], row_to_entry)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+/// Queue failed deliveries again (of one webhook, or all); returns how many
+pub fn retry_failed(conn: &Connection, webhook_id: Option<&str>) -> Result<usize> {
+    let requeued = conn.execute(
+        "UPDATE outbox SET status = 'pending', attempts = 0, next_attempt_at = ?1
+         WHERE status = 'failed' AND (?2 IS NULL OR webhook_id = ?2)
+           AND webhook_id IN (SELECT id FROM webhooks WHERE active = 1)",
+        params![Utc::now().to_rfc3339(), webhook_id],
+    )?;
+    Ok(requeued)
+}
+
+fn row_to_entry(row: &rusqlite::Row) -> rusqlite::Result<OutboxEntry> {
+    let status: String = row.get(4)?;
+    let next_attempt_at: String = row.get(6)?;
+    let delivered_at: Option<String> = row.get(8)?;
+    Ok(OutboxEntry {
+        id: row.get(0)?,
+        webhook_id: row.get(1)?,
+        event_id: row.get(2)?,
+        event_type: row.get(3)?,
+        status: DeliveryStatus::parse(&status).unwrap_or(DeliveryStatus::Failed),
+        attempts: row.get(5)?,
+        next_attempt_at: parse_time(&next_attempt_at),
+        last_error: row.get(7)?,
+        delivered_at: delivered_at.as_deref().map(parse_time),
+    })
+}
+
+// ============================================================================
+// DELIVERY
+// ============================================================================
+
+/// Signature header value for `body` sent at `timestamp` (Unix seconds)
+pub fn sign_payload(secret: &str, timestamp: i64, body: &str) -> Result<String> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|e| anyhow!("Invalid webhook secret: {}", e))?;
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    let digest = mac.finalize().into_bytes();
+    Ok(format!("sha256={}", digest.iter().map(|b| format!("{:02x}", b)).collect::<String>()))
+}
+
+/// Wait before the next attempt after `attempts` failed ones
+pub fn retry_delay(attempts: i64) -> Duration {
+    let exponent = attempts.clamp(1, 20) - 1;
+    Duration::seconds((FIRST_RETRY_DELAY_SECS << exponent).min(MAX_RETRY_DELAY_SECS))
+}
+
+/// Send every pending row due at `now` (up to DELIVERY_BATCH)
+pub fn deliver_due(conn: &Connection, transport: &dyn WebhookTransport, now: DateTime<Utc>) -> Result<DeliveryReport> {
+    let mut stmt = conn.prepare(
+        "SELECT o.id, o.attempts, o.event_id, w.url, w.secret
+         FROM outbox o JOIN webhooks w ON w.id = o.webhook_id
+         WHERE o.status = 'pending' AND o.next_attempt_at <= ?1
+         ORDER BY o.next_attempt_at, o.id
+         LIMIT ?2",
+    )?;
+    let due = stmt
+        .query_map(params![now.to_rfc3339(), DELIVERY_BATCH as i64], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut report = DeliveryReport::default();
+    for (id, attempts, event_id, url, secret) in due {
+        let error = match get_event(conn, &event_id)? {
+            Some(event) => post_event(transport, id, &url, &secret, &event, now.timestamp())?,
+            None => Some(format!("event {} not found", event_id)),
+        };
+
+        let attempts = attempts + 1;
+        match error {
+            None => {
+                conn.execute(
+                    "UPDATE outbox SET status = 'delivered', attempts = ?1, delivered_at = ?2, last_error = NULL
+                     WHERE id = ?3",
+                    params![attempts, now.to_rfc3339(), id],
+                )?;
+                report.delivered += 1;
+            }
+            Some(error) if attempts >= MAX_DELIVERY_ATTEMPTS => {
+                conn.execute(
+                    "UPDATE outbox SET status = 'failed', attempts = ?1, last_error = ?2 WHERE id = ?3",
+                    params![attempts, error, id],
+                )?;
+                report.failed += 1;
+            }
+            Some(error) => {
+                conn.execute(
+                    "UPDATE outbox SET attempts = ?1, last_error = ?2, next_attempt_at = ?3 WHERE id = ?4",
+                    params![attempts, error, (now + retry_delay(attempts)).to_rfc3339(), id],
+                )?;
+                report.retrying += 1;
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// POST one event; returns the error when it was not accepted (2xx)
+fn post_event(
+    transport: &dyn WebhookTransport,
+    delivery_id: i64,
+    url: &str,
+    secret: &str,
+    event: &Event,
+    timestamp: i64,
+) -> Result<Option<String>> {
+    let body = serde_json::to_string(event)?;
+    let headers = [
+        ("Content-Type", "application/json".to_string()),
+        ("X-Trust-Event", event.event_type.clone()),
+        ("X-Trust-Delivery", delivery_id.to_string()),
+        ("X-Trust-Timestamp", timestamp.to_string()),
+        ("X-Trust-Signature", sign_payload(secret, timestamp, &body)?),
+    ];
+
+    Ok(match transport.post(url, &headers, &body) {
+        Ok(status) if (200..300).contains(&status) => None,
+        Ok(status) => Some(format!("HTTP {}", status)),
+        Err(e) => Some(e.to_string()),
+    })
+}
+
+// ============================================================================
+// HTTP TRANSPORT (feature = "webhooks")
+// ============================================================================
+
+/// Blocking HTTP POSTs (ureq)
+#[cfg(feature = "webhooks")]
+pub struct HttpTransport {
+    timeout: std::time::Duration,
+}
+
+#[cfg(feature = "webhooks")]
+impl HttpTransport {
+    pub fn new(timeout: std::time::Duration) -> Self {
+        HttpTransport { timeout }
+    }
+}
+
+#[cfg(feature = "webhooks")]
+impl Default for HttpTransport {
+    fn default() -> Self {
+        HttpTransport::new(std::time::Duration::from_secs(10))
+    }
+}
+
+#[cfg(feature = "webhooks")]
+impl WebhookTransport for HttpTransport {
+    fn post(&self, url: &str, headers: &[(&str, String)], body: &str) -> Result<u16> {
+        let mut request = ureq::post(url).timeout(self.timeout);
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        match request.send_string(body) {
+            Ok(response) => Ok(response.status()),
+            Err(ureq::Error::Status(status, _)) => Ok(status),
+            Err(e) => Err(anyhow!("POST {} failed: {}", url, e)),
+        }
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::setup_database;
+    use crate::imports::import_statement;
+    use crate::ledger::{create_ledger, Ledger};
+    use std::cell::RefCell;
+
+    const BOFA: &str = "Date,Description,Amount\n\
+        01/02/2025,STARBUCKS STORE 123,-5.25\n\
+        01/03/2025,SHELL OIL 5521,-40.00\n";
+
+    /// (url, headers, body)
+    type Request = (String, Vec<(String, String)>, String);
+
+    /// Answers with the given statuses in turn (then the last one) and keeps the requests
+    struct FakeTransport {
+        statuses: RefCell<Vec<u16>>,
+        requests: RefCell<Vec<Request>>,
+    }
+
+    impl FakeTransport {
+        fn new(statuses: &[u16]) -> Self {
+            FakeTransport { statuses: RefCell::new(statuses.to_vec()), requests: RefCell::new(Vec::new()) }
+        }
+    }
+
+    impl WebhookTransport for FakeTransport {
+        fn post(&self, url: &str, headers: &[(&str, String)], body: &str) -> Result<u16> {
+            let headers = headers.iter().map(|(name, value)| (name.to_string(), value.clone())).collect();
+            self.requests.borrow_mut().push((url.to_string(), headers, body.to_string()));
+            let mut statuses = self.statuses.borrow_mut();
+            Ok(if statuses.len() > 1 { statuses.remove(0) } else { statuses[0] })
+        }
+    }
+
+    fn database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_matching_events_are_queued_signed_and_delivered() {
+        let conn = database();
+        let imports = add_webhook(&conn, "https://sheets.example/hook", &["import_session".to_string()], Some("default"), "ana").unwrap();
+        let everything = add_webhook(&conn, "https://books.example/hook", &[], None, "ana").unwrap();
+        assert!(add_webhook(&conn, "ftp://example", &[], None, "ana").is_err());
+        assert!(add_webhook(&conn, "https://example", &["no_such_event".to_string()], None, "ana").is_err());
+
+        create_ledger(&conn, &Ledger::new("business", "Business"), "ana").unwrap();
+        import_statement(&conn, "bofa_jan.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+        import_statement(&conn, "bofa_jan.csv", BOFA.as_bytes(), "business", "ana").unwrap();
+        let queued = list_outbox(&conn, Some(DeliveryStatus::Pending), 100).unwrap();
+        let for_imports: Vec<&OutboxEntry> = queued.iter().filter(|entry| entry.webhook_id == imports.id).collect();
+        assert_eq!(for_imports.len(), 1, "one import in its ledger");
+        // The other one gets its own creation, the new ledger, both imports and their four rows
+        assert_eq!(queued.iter().filter(|entry| entry.webhook_id == everything.id).count(), 8);
+
+        let transport = FakeTransport::new(&[200]);
+        let report = deliver_due(&conn, &transport, Utc::now()).unwrap();
+        assert_eq!(report, DeliveryReport { delivered: 9, retrying: 0, failed: 0 });
+        assert!(list_outbox(&conn, Some(DeliveryStatus::Pending), 100).unwrap().is_empty());
+
+        let requests = transport.requests.borrow();
+        let (_, headers, body) = requests.iter().find(|(url, _, _)| url == "https://sheets.example/hook").unwrap();
+        let header = |name: &str| headers.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone()).unwrap();
+        assert_eq!(header("X-Trust-Event"), "import_session");
+        let timestamp: i64 = header("X-Trust-Timestamp").parse().unwrap();
+        assert_eq!(header("X-Trust-Signature"), sign_payload(&imports.secret, timestamp, body).unwrap());
+        let event: Event = serde_json::from_str(body).unwrap();
+        assert_eq!(event.data["filename"], "bofa_jan.csv");
+    }
+
+    #[test]
+    fn test_failed_deliveries_back_off_then_give_up() {
+        let conn = database();
+        let webhook = add_webhook(&conn, "https://books.example/hook", &["rule_retired".to_string()], None, "ana").unwrap();
+        let event = Event::typed("rule_retired", "rule", "starbucks", &crate::event_schema::RuleRetired { reason: None }, "ana").unwrap();
+        insert_event(&conn, &event).unwrap();
+
+        let down = FakeTransport::new(&[503]);
+        let mut now = Utc::now();
+        let first = deliver_due(&conn, &down, now).unwrap();
+        assert_eq!(first.retrying, 1);
+        let entry = &list_outbox(&conn, None, 10).unwrap()[0];
+        assert_eq!((entry.attempts, entry.last_error.as_deref()), (1, Some("HTTP 503")));
+        assert_eq!(deliver_due(&conn, &down, now).unwrap(), DeliveryReport::default(), "not due yet");
+        assert_eq!(retry_delay(2), Duration::seconds(60));
+        assert_eq!(retry_delay(30), Duration::seconds(MAX_RETRY_DELAY_SECS));
+
+        for _ in 1..MAX_DELIVERY_ATTEMPTS {
+            now += Duration::seconds(MAX_RETRY_DELAY_SECS);
+            deliver_due(&conn, &down, now).unwrap();
+        }
+        let entry = &list_outbox(&conn, None, 10).unwrap()[0];
+        assert_eq!((entry.status, entry.attempts), (DeliveryStatus::Failed, MAX_DELIVERY_ATTEMPTS));
+        assert_eq!(down.requests.borrow().len() as i64, MAX_DELIVERY_ATTEMPTS);
+
+        assert_eq!(retry_failed(&conn, Some(&webhook.id)).unwrap(), 1);
+        let report = deliver_due(&conn, &FakeTransport::new(&[204]), Utc::now()).unwrap();
+        assert_eq!(report.delivered, 1);
+
+        remove_webhook(&conn, &webhook.id, "ana").unwrap();
+        insert_event(&conn, &Event::typed("rule_retired", "rule", "shell", &crate::event_schema::RuleRetired { reason: None }, "ana").unwrap()).unwrap();
+        assert!(list_outbox(&conn, Some(DeliveryStatus::Pending), 10).unwrap().is_empty());
+        assert!(list_webhooks(&conn).unwrap().is_empty());
+    }
+}
@@ -1,10 +1,12 @@
 // 🏗️ Parser Framework - Badge 6
-// Polymorphic parser system for 5 banks
+// Polymorphic parser system for banks, card feeds, crypto exchanges, payment apps and CFDI invoices
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
+pub mod testing; // Golden-file checks for any BankParser (fixtures/parsers, `parser check`)
+
 // ============================================================================
 // CORE TYPES
 // ============================================================================
@@ -17,9 +19,36 @@ pub enum SourceType {
     Stripe,
     Wise,
     Scotiabank,
+    Ofx,
+    Coinbase,
+    Kraken,
+    PayPal,
+    Venmo,
+    Bbva,
+    Banorte,
+    Cfdi,
 }
 
 impl SourceType {
+    /// Every source with a parser
+    pub fn all() -> [SourceType; 13] {
+        [
+            SourceType::BankOfAmerica,
+            SourceType::AppleCard,
+            SourceType::Stripe,
+            SourceType::Wise,
+            SourceType::Scotiabank,
+            SourceType::Ofx,
+            SourceType::Coinbase,
+            SourceType::Kraken,
+            SourceType::PayPal,
+            SourceType::Venmo,
+            SourceType::Bbva,
+            SourceType::Banorte,
+            SourceType::Cfdi,
+        ]
+    }
+
     /// Human-readable name for display
     pub fn name(&self) -> &str {
         match self {
@@ -28,6 +57,14 @@ impl SourceType {
             SourceType::Stripe => "Stripe",
             SourceType::Wise => "Wise",
             SourceType::Scotiabank => "Scotiabank",
+            SourceType::Ofx => "OFX",
+            SourceType::Coinbase => "Coinbase",
+            SourceType::Kraken => "Kraken",
+            SourceType::PayPal => "PayPal",
+            SourceType::Venmo => "Venmo",
+            SourceType::Bbva => "BBVA México",
+            SourceType::Banorte => "Banorte",
+            SourceType::Cfdi => "CFDI",
         }
     }
 
@@ -39,6 +76,14 @@ impl SourceType {
             SourceType::Stripe => "Stripe",
             SourceType::Wise => "Wise",
             SourceType::Scotiabank => "Scotia",
+            SourceType::Ofx => "OFX",
+            SourceType::Coinbase => "Coinbase",
+            SourceType::Kraken => "Kraken",
+            SourceType::PayPal => "PayPal",
+            SourceType::Venmo => "Venmo",
+            SourceType::Bbva => "BBVA",
+            SourceType::Banorte => "Banorte",
+            SourceType::Cfdi => "CFDI",
         }
     }
 }
@@ -56,6 +101,17 @@ pub struct RawTransaction {
     pub merchant: Option<String>,  // Extracted merchant name
     pub category: Option<String>,  // If source provides category
     pub account: Option<String>,   // Account name/number
+    pub mcc: Option<u16>,          // Merchant category code (OFX/card feeds)
+    pub external_id: Option<String>, // Source's own row id (OFX FITID, Stripe txn id)
+    pub posted_date: Option<String>, // Date the card posted the charge (Apple Card clearing date)
+    #[serde(default)]
+    pub pending: bool,             // Not settled yet (live API sources)
+    #[serde(default)]
+    pub currency: Option<String>,  // Currency of `amount` when not USD (crypto exports)
+    #[serde(default)]
+    pub asset: Option<String>,     // Crypto asset moved ("BTC"), with its quantity
+    #[serde(default)]
+    pub quantity: Option<String>,  // Signed units of `asset`; `amount` is their fiat value
 
     // Provenance (siempre presente)
     pub source_type: SourceType,   // Which bank
@@ -85,6 +141,13 @@ impl RawTransaction {
             merchant: None,
             category: None,
             account: None,
+            mcc: None,
+            external_id: None,
+            posted_date: None,
+            pending: false,
+            currency: None,
+            asset: None,
+            quantity: None,
             source_type,
             source_file,
             line_number,
@@ -99,6 +162,37 @@ impl RawTransaction {
         self
     }
 
+    /// Builder pattern: add the source's own id for the row
+    pub fn with_external_id(mut self, external_id: String) -> Self {
+        self.external_id = Some(external_id);
+        self
+    }
+
+    /// Builder pattern: add the date the card posted the charge
+    pub fn with_posted_date(mut self, posted_date: String) -> Self {
+        self.posted_date = Some(posted_date);
+        self
+    }
+
+    /// Builder pattern: mark a charge the source hasn't settled yet
+    pub fn with_pending(mut self, pending: bool) -> Self {
+        self.pending = pending;
+        self
+    }
+
+    /// Builder pattern: currency `amount` is in
+    pub fn with_currency(mut self, currency: String) -> Self {
+        self.currency = Some(currency);
+        self
+    }
+
+    /// Builder pattern: crypto asset and the (signed) quantity moved
+    pub fn with_asset(mut self, asset: String, quantity: String) -> Self {
+        self.asset = Some(asset);
+        self.quantity = Some(quantity);
+        self
+    }
+
     /// Builder pattern: add optional category
     pub fn with_category(mut self, category: String) -> Self {
         self.category = Some(category);
@@ -111,6 +205,12 @@ impl RawTransaction {
         self
     }
 
+    /// Builder pattern: add merchant category code
+    pub fn with_mcc(mut self, mcc: u16) -> Self {
+        self.mcc = Some(mcc);
+        self
+    }
+
     /// Builder pattern: add confidence score
     pub fn with_confidence(mut self, confidence: f64) -> Self {
         self.confidence = Some(confidence);
@@ -197,9 +297,10 @@ pub trait AmountValidator {
     fn validate_amount(&self, amount: &str) -> Result<f64>;
 }
 
-/// DateNormalizer - Future extension: Normalize dates
+/// DateNormalizer - Optional capability: put the source's dates in the
+/// canonical MM/DD/YYYY
 ///
-/// Otro ejemplo de extensión futura.
+/// Day-first sources (Mexican banks) share `normalize_day_first_date`.
 pub trait DateNormalizer {
     fn normalize_date(&self, date: &str) -> Result<String>;
 }
@@ -257,6 +358,39 @@ pub fn detect_source(file_path: &Path) -> Result<SourceType> {
         return Ok(SourceType::Scotiabank);
     }
 
+    if filename_lower.contains("coinbase") {
+        return Ok(SourceType::Coinbase);
+    }
+
+    if filename_lower.contains("kraken") {
+        return Ok(SourceType::Kraken);
+    }
+
+    if filename_lower.contains("paypal") {
+        return Ok(SourceType::PayPal);
+    }
+
+    if filename_lower.contains("venmo") {
+        return Ok(SourceType::Venmo);
+    }
+
+    if filename_lower.contains("bbva") || filename_lower.contains("bancomer") {
+        return Ok(SourceType::Bbva);
+    }
+
+    if filename_lower.contains("banorte") {
+        return Ok(SourceType::Banorte);
+    }
+
+    if filename_lower.contains("cfdi") || filename_lower.contains("factura") {
+        return Ok(SourceType::Cfdi);
+    }
+
+    // OFX/QFX downloads come from any bank; the format is the source
+    if filename_lower.ends_with(".ofx") || filename_lower.ends_with(".qfx") {
+        return Ok(SourceType::Ofx);
+    }
+
     // TODO: If filename is ambiguous, peek at file content
     // For now, return error
     Err(anyhow::anyhow!(
@@ -282,9 +416,44 @@ pub fn get_parser(source_type: SourceType) -> Box<dyn BankParser> {
         SourceType::Stripe => Box::new(StripeParser::new()),
         SourceType::Wise => Box::new(WiseParser::new()),
         SourceType::Scotiabank => Box::new(ScotiabankParser::new()),
+        SourceType::Ofx => Box::new(OfxParser::new()),
+        SourceType::Coinbase => Box::new(CoinbaseParser::new()),
+        SourceType::Kraken => Box::new(KrakenParser::new()),
+        SourceType::PayPal => Box::new(PayPalParser::new()),
+        SourceType::Venmo => Box::new(VenmoParser::new()),
+        SourceType::Bbva => Box::new(BbvaParser::new()),
+        SourceType::Banorte => Box::new(BanorteParser::new()),
+        SourceType::Cfdi => Box::new(CfdiParser::new()),
+    }
+}
+
+/// Get the type classifier of a source's parser (every parser has one)
+pub fn get_type_classifier(source_type: SourceType) -> Box<dyn TypeClassifier> {
+    match source_type {
+        SourceType::BankOfAmerica => Box::new(BofAParser::new()),
+        SourceType::AppleCard => Box::new(AppleCardParser::new()),
+        SourceType::Stripe => Box::new(StripeParser::new()),
+        SourceType::Wise => Box::new(WiseParser::new()),
+        SourceType::Scotiabank => Box::new(ScotiabankParser::new()),
+        SourceType::Ofx => Box::new(OfxParser::new()),
+        SourceType::Coinbase => Box::new(CoinbaseParser::new()),
+        SourceType::Kraken => Box::new(KrakenParser::new()),
+        SourceType::PayPal => Box::new(PayPalParser::new()),
+        SourceType::Venmo => Box::new(VenmoParser::new()),
+        SourceType::Bbva => Box::new(BbvaParser::new()),
+        SourceType::Banorte => Box::new(BanorteParser::new()),
+        SourceType::Cfdi => Box::new(CfdiParser::new()),
     }
 }
 
+/// Provenance tag of the current parser for a source, e.g. "bofa_parser_v1.0.0"
+///
+/// Stored as the `parser_version` metadata of imported rows, so rows parsed
+/// by an older parser can be found (see parser_changes.rs).
+pub fn parser_version_tag(source_type: SourceType) -> String {
+    format!("{}_parser_v{}", source_type.code().to_lowercase(), get_parser(source_type).version())
+}
+
 // ============================================================================
 // STUB PARSERS (will be implemented in future badges)
 // ============================================================================
@@ -442,18 +611,40 @@ impl BankParser for AppleCardParser {
             .unwrap_or("unknown.csv")
             .to_string();
 
+        // Apple's own export names its columns (Transaction Date, Clearing Date,
+        // Description, Merchant, Category, Type, Amount (USD), Purchased By);
+        // older exports are positional: Date,Description,Amount,Category,Merchant
+        let headers = reader
+            .headers()
+            .with_context(|| format!("Failed to read CSV header of {}", filename))?
+            .clone();
+        let column = |name: &str, fallback: Option<usize>| {
+            match headers.iter().position(|header| header.trim().eq_ignore_ascii_case(name)) {
+                Some(index) => Some(index),
+                None if headers.iter().any(|header| header.trim().eq_ignore_ascii_case("Clearing Date")) => None,
+                None => fallback,
+            }
+        };
+        let date_col = column("Transaction Date", Some(0));
+        let posted_col = column("Clearing Date", None);
+        let description_col = column("Description", Some(1));
+        let amount_col = column("Amount (USD)", Some(2));
+        let category_col = column("Category", Some(3));
+        let merchant_col = column("Merchant", Some(4));
+
         for (line_num, result) in reader.records().enumerate() {
             let record = result.with_context(|| {
                 format!("Failed to parse CSV line {} in {}", line_num + 2, filename)
             })?;
 
-            // AppleCard CSV format: Date,Description,Amount,Category,Merchant
             // Example: "10/26/2024","UBER *EATS MR TREUBLAAN...","3.74","Restaurants","Uber Eats"
-            let date = record.get(0).unwrap_or("").to_string();
-            let description = record.get(1).unwrap_or("").to_string();
-            let amount = record.get(2).unwrap_or("").to_string();
-            let category = record.get(3).map(|s| s.to_string());
-            let merchant = record.get(4).map(|s| s.to_string());
+            let field = |col: Option<usize>| col.and_then(|index| record.get(index));
+            let date = field(date_col).unwrap_or("").to_string();
+            let description = field(description_col).unwrap_or("").to_string();
+            let amount = field(amount_col).unwrap_or("").to_string();
+            let category = field(category_col).map(|s| s.to_string());
+            let merchant = field(merchant_col).map(|s| s.to_string());
+            let posted_date = field(posted_col).map(str::trim).filter(|d| !d.is_empty());
 
             let raw_line = format!("{},{},{}", date, description, amount);
 
@@ -477,6 +668,11 @@ impl BankParser for AppleCardParser {
                 tx = tx.with_category(c);
             }
 
+            // Clearing date, when the export has one
+            if let Some(posted) = posted_date {
+                tx = tx.with_posted_date(posted.to_string());
+            }
+
             transactions.push(tx);
         }
 
@@ -572,7 +768,8 @@ impl BankParser for StripeParser {
             //   "created": 1735084800,  // Unix timestamp
             //   "currency": "usd",
             //   "description": "Payment from eugenio Castro Garza",
-            //   "type": "payout"
+            //   "type": "payout",
+            //   "status": "available"  // "pending" until the funds settle
             // }
 
             let id = item.get("id")
@@ -634,6 +831,9 @@ impl BankParser for StripeParser {
             } else {
                 tx
             };
+            let tx = if id != "unknown" { tx.with_external_id(id) } else { tx };
+            let pending = item.get("status").and_then(|v| v.as_str()) == Some("pending");
+            let tx = tx.with_pending(pending);
 
             transactions.push(tx);
         }
@@ -714,6 +914,42 @@ impl WiseParser {
     }
 }
 
+/// Whether money entered or left the Wise balance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WiseDirection {
+    Incoming,
+    Outgoing,
+}
+
+impl WiseDirection {
+    /// Wise writes outgoing amounts negative, in whatever currency they were sent
+    pub fn of(amount: f64) -> Self {
+        if amount < 0.0 {
+            WiseDirection::Outgoing
+        } else {
+            WiseDirection::Incoming
+        }
+    }
+
+    /// `magnitude` with this direction's sign (money in positive)
+    pub fn signed(self, magnitude: f64) -> f64 {
+        match self {
+            WiseDirection::Incoming => magnitude.abs(),
+            WiseDirection::Outgoing => -magnitude.abs(),
+        }
+    }
+}
+
+/// USD value of `magnitude` units of `currency` (rate = units per USD)
+fn wise_usd_magnitude(magnitude: f64, currency: &str, exchange_rate: f64) -> f64 {
+    if currency == "USD" || exchange_rate == 0.0 {
+        magnitude
+    } else {
+        // EUR (EUR/USD rate), MXN (MXN/USD rate), others alike
+        magnitude / exchange_rate
+    }
+}
+
 impl BankParser for WiseParser {
     fn parse(&self, file_path: &Path) -> Result<Vec<RawTransaction>> {
         use csv::ReaderBuilder;
@@ -751,33 +987,20 @@ impl BankParser for WiseParser {
             let fee_str = record.get(7).unwrap_or("0.0");
 
             // Parse amount
-            let amount = amount_str.trim().parse::<f64>()
-                .unwrap_or_else(|_| {
-                    // Try removing commas
-                    amount_str.replace(",", "").parse::<f64>().unwrap_or(0.0)
-                });
+            let amount = export_number(&amount_str).unwrap_or(0.0);
 
             // Parse exchange rate
-            let exchange_rate = exchange_rate_str.trim().parse::<f64>().unwrap_or(1.0);
+            let exchange_rate = export_number(exchange_rate_str).unwrap_or(1.0);
 
             // Parse fee (for future use)
-            let _fee = fee_str.trim().parse::<f64>().unwrap_or(0.0);
-
-            // Convert to USD if needed
-            let amount_usd = if currency == "USD" {
-                amount
-            } else if currency == "EUR" {
-                // EUR to USD: divide by exchange rate (EUR/USD rate)
-                amount / exchange_rate
-            } else if currency == "MXN" {
-                // MXN to USD: divide by exchange rate (MXN/USD rate)
-                amount / exchange_rate
-            } else {
-                // Unknown currency, use exchange rate as is
-                amount / exchange_rate
-            };
+            let _fee = export_number(fee_str).unwrap_or(0.0);
 
-            let amount_usd_str = format!("{:.2}", amount_usd.abs());
+            // Convert the magnitude to USD, then put the direction back:
+            // a payment out stays negative whatever currency it was sent in
+            let direction = WiseDirection::of(amount);
+            let amount_usd = direction.signed(wise_usd_magnitude(amount.abs(), &currency, exchange_rate));
+
+            let amount_usd_str = format!("{:.2}", amount_usd);
 
             let raw_line = format!("{},{},{},{},{}", id, date, amount_str, currency, description);
 
@@ -821,6 +1044,11 @@ impl BankParser for WiseParser {
     fn source_type(&self) -> SourceType {
         SourceType::Wise
     }
+
+    /// 1.1.0: outgoing amounts keep their sign (were made positive)
+    fn version(&self) -> &str {
+        "1.1.0"
+    }
 }
 
 impl MerchantExtractor for WiseParser {
@@ -889,7 +1117,14 @@ impl TypeClassifier for WiseParser {
     }
 }
 
-/// Scotiabank Parser (Badge 11)
+/// Scotiabank México Parser - account movements CSV, read by the shared
+/// Mexican bank reader
+///
+/// Two layouts are exported: separate Cargo and Abono columns (Fecha,
+/// Concepto, Referencia, Cargo, Abono, Saldo), or one unsigned Importe with a
+/// Tipo column saying Cargo or Abono. Dates are DD/MM/YYYY (or "05-ENE-2025"),
+/// amounts MXN with thousands separators.
+#[derive(Default)]
 pub struct ScotiabankParser;
 
 impl ScotiabankParser {
@@ -899,137 +1134,1305 @@ impl ScotiabankParser {
 }
 
 impl BankParser for ScotiabankParser {
-    fn parse(&self, _file_path: &Path) -> Result<Vec<RawTransaction>> {
-        // TODO: Implement in Badge 11
-        Ok(Vec::new())
+    fn parse(&self, file_path: &Path) -> Result<Vec<RawTransaction>> {
+        let mut transactions = parse_mexican_bank_csv(file_path, SourceType::Scotiabank)?;
+        for tx in &mut transactions {
+            tx.merchant = self.extract_merchant(&tx.description);
+        }
+        Ok(transactions)
     }
 
     fn source_type(&self) -> SourceType {
         SourceType::Scotiabank
     }
+
+    /// 1.1.0: reads the statement (1.0.0 returned no rows)
+    fn version(&self) -> &str {
+        "1.1.0"
+    }
+}
+
+impl DateNormalizer for ScotiabankParser {
+    fn normalize_date(&self, date: &str) -> Result<String> {
+        normalize_day_first_date(date)
+    }
 }
 
 impl MerchantExtractor for ScotiabankParser {
-    fn extract_merchant(&self, _description: &str) -> Option<String> {
-        // TODO: Implement in Badge 11
-        None
+    /// "COMPRA EN COMERCIO OXXO INSURGENTES REF 0012345" → "OXXO INSURGENTES";
+    /// SPEI transfers name the bank first and the counterparty after the
+    /// slash ("SPEI ENVIADO BANORTE/JUAN PEREZ" → "JUAN PEREZ"); bank fees
+    /// are Scotiabank's and cash withdrawals have no merchant
+    fn extract_merchant(&self, description: &str) -> Option<String> {
+        const OPERATIONS: [&str; 7] = [
+            "compra en comercio ",
+            "compra tdd ",
+            "compra tdc ",
+            "pago servicio ",
+            "pago de servicio ",
+            "cargo domiciliado ",
+            "transf interbancaria ",
+        ];
+        let folded = fold_accents(description);
+        if folded.starts_with("comision") || folded.starts_with("iva comision") {
+            return Some("Scotiabank".to_string());
+        }
+        if folded.starts_with("retiro cajero") || folded.starts_with("disposicion efectivo") {
+            return None;
+        }
+        if folded.contains("spei") {
+            if let Some((_, counterparty)) = description.split_once('/') {
+                let counterparty = counterparty.trim();
+                if !counterparty.is_empty() {
+                    return Some(counterparty.to_string());
+                }
+            }
+        }
+
+        // Matched on an ASCII-lowercased copy, whose byte offsets are the
+        // original's (fold_accents shortens accented letters)
+        let mut text = description.trim();
+        if let Some(operation) = OPERATIONS.iter().find(|op| text.to_ascii_lowercase().starts_with(*op)) {
+            text = text[operation.len()..].trim_start();
+        }
+        // Trailing reference number ("... REF 0012345")
+        if let Some(at) = text.to_ascii_lowercase().find(" ref ") {
+            text = text[..at].trim_end();
+        }
+        spanish_merchant(text)
     }
 }
 
 impl TypeClassifier for ScotiabankParser {
-    fn classify_type(&self, _description: &str, _amount: f64) -> String {
-        "GASTO".to_string()
+    /// Scotia's abbreviations first ("TRANSF ENTRE CTAS PROPIAS", "DISPOSICION
+    /// EFECTIVO"), then the shared Spanish rules
+    fn classify_type(&self, description: &str, amount: f64) -> String {
+        let desc = fold_accents(description);
+        if ["ctas propias", "ctas. propias", "disposicion efectivo"].iter().any(|word| desc.contains(word)) {
+            "TRASPASO".to_string()
+        } else {
+            classify_spanish(description, amount)
+        }
     }
 }
 
-// ============================================================================
-// TESTS
-// ============================================================================
-
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// OFX/QFX Parser - statement downloads offered by most banks
+///
+/// Reads the `<STMTTRN>` blocks of both OFX 1.x (SGML, closing tags optional)
+/// and OFX 2.x (XML). Amounts are already signed (negative = money out).
+#[derive(Default)]
+pub struct OfxParser;
 
-    #[test]
-    fn test_source_type_names() {
-        assert_eq!(SourceType::BankOfAmerica.name(), "Bank of America");
-        assert_eq!(SourceType::AppleCard.name(), "AppleCard");
-        assert_eq!(SourceType::Stripe.name(), "Stripe");
-        assert_eq!(SourceType::Wise.name(), "Wise");
-        assert_eq!(SourceType::Scotiabank.name(), "Scotiabank");
+impl OfxParser {
+    pub fn new() -> Self {
+        OfxParser
+    }
+
+    /// Value of `<TAG>` inside `block`: text up to the next tag or line end
+    fn field(block: &str, tag: &str) -> Option<String> {
+        let start = block.find(&format!("<{}>", tag))? + tag.len() + 2;
+        let value = block[start..]
+            .split(['<', '\n', '\r'])
+            .next()
+            .unwrap_or("")
+            .trim();
+        if value.is_empty() {
+            None
+        } else {
+            Some(value.to_string())
+        }
     }
 
-    #[test]
-    fn test_source_type_codes() {
-        assert_eq!(SourceType::BankOfAmerica.code(), "BofA");
-        assert_eq!(SourceType::AppleCard.code(), "Apple");
-        assert_eq!(SourceType::Stripe.code(), "Stripe");
-        assert_eq!(SourceType::Wise.code(), "Wise");
-        assert_eq!(SourceType::Scotiabank.code(), "Scotia");
+    /// "20250105120000[-5:EST]" → "01/05/2025"
+    fn format_date(value: &str) -> String {
+        let digits: String = value.chars().take(8).collect();
+        match chrono::NaiveDate::parse_from_str(&digits, "%Y%m%d") {
+            Ok(date) => date.format("%m/%d/%Y").to_string(),
+            Err(_) => value.to_string(),
+        }
     }
+}
 
-    #[test]
-    fn test_detect_source_bofa() {
-        let path = Path::new("bofa_march_2024.csv");
-        let result = detect_source(path);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), SourceType::BankOfAmerica);
-    }
+impl BankParser for OfxParser {
+    fn parse(&self, file_path: &Path) -> Result<Vec<RawTransaction>> {
+        let content = std::fs::read_to_string(file_path)
+            .with_context(|| format!("Failed to open file: {}", file_path.display()))?;
 
-    #[test]
-    fn test_detect_source_apple() {
-        let path = Path::new("Apple Card Activity.csv");
-        let result = detect_source(path);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), SourceType::AppleCard);
-    }
+        let filename = file_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown.ofx")
+            .to_string();
 
-    #[test]
-    fn test_detect_source_stripe() {
-        let path = Path::new("stripe_january_2024.json");
-        let result = detect_source(path);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), SourceType::Stripe);
+        if !content.contains("<OFX>") {
+            return Err(anyhow::anyhow!("{} is not an OFX file (no <OFX> element)", filename));
+        }
+
+        let account = Self::field(&content, "ACCTID");
+        let mut transactions = Vec::new();
+
+        let mut offset = 0;
+        while let Some(found) = content[offset..].find("<STMTTRN>") {
+            let start = offset + found;
+            let end = content[start..]
+                .find("</STMTTRN>")
+                .map(|e| start + e)
+                .unwrap_or(content.len());
+            let block = &content[start..end];
+            offset = end;
+
+            let line_number = content[..start].matches('\n').count() + 1;
+            let date = Self::field(block, "DTPOSTED").map(|d| Self::format_date(&d)).unwrap_or_default();
+            let amount = Self::field(block, "TRNAMT").unwrap_or_default();
+            let name = Self::field(block, "NAME");
+            let memo = Self::field(block, "MEMO");
+
+            let description = match (&name, &memo) {
+                (Some(name), Some(memo)) => format!("{} {}", name, memo),
+                (Some(text), None) | (None, Some(text)) => text.clone(),
+                (None, None) => Self::field(block, "TRNTYPE").unwrap_or_default(),
+            };
+
+            let raw_line = block.split_whitespace().collect::<Vec<_>>().join(" ");
+
+            let mut tx = RawTransaction::new(
+                date,
+                description.clone(),
+                amount,
+                SourceType::Ofx,
+                filename.clone(),
+                line_number,
+                raw_line,
+            );
+
+            if let Some(merchant) = name.or_else(|| self.extract_merchant(&description)) {
+                tx = tx.with_merchant(merchant);
+            }
+            if let Some(account) = &account {
+                tx = tx.with_account(account.clone());
+            }
+            if let Some(mcc) = Self::field(block, "SIC").and_then(|sic| sic.parse::<u16>().ok()) {
+                tx = tx.with_mcc(mcc);
+            }
+            if let Some(fitid) = Self::field(block, "FITID") {
+                tx = tx.with_external_id(fitid);
+            }
+
+            transactions.push(tx);
+        }
+
+        Ok(transactions)
     }
 
-    #[test]
-    fn test_detect_source_wise() {
-        let path = Path::new("wise_statement.csv");
-        let result = detect_source(path);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), SourceType::Wise);
+    fn source_type(&self) -> SourceType {
+        SourceType::Ofx
     }
+}
 
-    #[test]
-    fn test_detect_source_scotia() {
-        let path = Path::new("scotia_feb_2024.csv");
-        let result = detect_source(path);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), SourceType::Scotiabank);
+impl MerchantExtractor for OfxParser {
+    fn extract_merchant(&self, description: &str) -> Option<String> {
+        // OFX has a NAME field; without it use the description as-is
+        let merchant = description.trim();
+        if merchant.is_empty() {
+            None
+        } else {
+            Some(merchant.to_string())
+        }
     }
+}
 
-    #[test]
-    fn test_detect_source_unknown() {
-        let path = Path::new("unknown_bank.csv");
-        let result = detect_source(path);
-        assert!(result.is_err());
+impl TypeClassifier for OfxParser {
+    fn classify_type(&self, description: &str, amount: f64) -> String {
+        let desc_lower = description.to_lowercase();
+
+        if desc_lower.contains("transfer") || desc_lower.contains("xfer") {
+            return "TRASPASO".to_string();
+        }
+
+        if amount > 0.0 {
+            return "INGRESO".to_string();
+        }
+
+        "GASTO".to_string()
     }
+}
 
-    #[test]
-    fn test_get_parser_bofa() {
-        let parser = get_parser(SourceType::BankOfAmerica);
-        assert_eq!(parser.source_type(), SourceType::BankOfAmerica);
+// ============================================================================
+// CRYPTO EXCHANGES
+// ============================================================================
+
+/// "2025-01-15T10:23:45Z" / "2025-01-15 10:23:45" → "01/15/2025"
+pub(crate) fn iso_timestamp_date(value: &str) -> String {
+    let day: String = value.trim().chars().take(10).collect();
+    match chrono::NaiveDate::parse_from_str(&day, "%Y-%m-%d") {
+        Ok(date) => date.format("%m/%d/%Y").to_string(),
+        Err(_) => value.to_string(),
     }
+}
 
-    #[test]
-    fn test_get_parser_apple() {
-        let parser = get_parser(SourceType::AppleCard);
-        assert_eq!(parser.source_type(), SourceType::AppleCard);
+/// "$1,234.56" → 1234.56 (never NaN or infinite: "NaN", "inf" and
+/// overflowing digits are unreadable, not amounts)
+fn export_number(value: &str) -> Option<f64> {
+    let cleaned: String = value.chars().filter(|c| !matches!(c, '$' | ',' | ' ')).collect();
+    cleaned.parse::<f64>().ok().filter(|number| number.is_finite())
+}
+
+/// Rewards, staking and interest are income; buying, selling and moving
+/// assets only moves money between the user's own holdings
+fn classify_crypto(description: &str) -> String {
+    let desc_lower = description.to_lowercase();
+    let income = ["reward", "staking", "interest", "income", "earn"];
+    if income.iter().any(|word| desc_lower.contains(word)) {
+        "INGRESO".to_string()
+    } else {
+        "TRASPASO".to_string()
     }
+}
 
-    #[test]
-    fn test_raw_transaction_builder() {
-        let tx = RawTransaction::new(
-            "2024-03-20".to_string(),
-            "STARBUCKS".to_string(),
-            "-45.99".to_string(),
-            SourceType::BankOfAmerica,
-            "bofa_march.csv".to_string(),
-            23,
-            "03/20/2024,STARBUCKS,-45.99".to_string(),
-        )
-        .with_merchant("STARBUCKS".to_string())
-        .with_category("Food".to_string())
-        .with_confidence(0.95);
+/// Coinbase Parser - "Transaction history" CSV of a Coinbase account
+///
+/// The export opens with a few lines of preamble before its header (ID,
+/// Timestamp, Transaction Type, Asset, Quantity Transacted, Spot Price
+/// Currency, Spot Price at Transaction, Subtotal, Total, Fees, Notes), so
+/// columns are found by name. The amount is the fiat value of what moved:
+/// positive when the asset arrives (buy, receive, rewards), negative when it
+/// leaves (sell, send, convert). Asset and quantity go with it.
+#[derive(Default)]
+pub struct CoinbaseParser;
+
+impl CoinbaseParser {
+    pub fn new() -> Self {
+        CoinbaseParser
+    }
 
-        assert_eq!(tx.date, "2024-03-20");
-        assert_eq!(tx.merchant, Some("STARBUCKS".to_string()));
-        assert_eq!(tx.category, Some("Food".to_string()));
-        assert_eq!(tx.confidence, Some(0.95));
+    /// Transaction types that take the asset out of the account
+    fn is_outflow(kind: &str) -> bool {
+        let kind = kind.to_lowercase();
+        ["sell", "send", "convert", "withdraw"].iter().any(|word| kind.contains(word))
     }
+}
 
-    #[test]
-    fn test_bofa_parser_parse_csv() {
+impl BankParser for CoinbaseParser {
+    fn parse(&self, file_path: &Path) -> Result<Vec<RawTransaction>> {
+        use csv::{ReaderBuilder, StringRecord};
+        use std::fs::File;
+
+        let file = File::open(file_path)
+            .with_context(|| format!("Failed to open file: {}", file_path.display()))?;
+
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(file);
+
+        let mut transactions = Vec::new();
+        let filename = file_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown.csv")
+            .to_string();
+
+        let mut header: Option<StringRecord> = None;
+        for (index, result) in reader.records().enumerate() {
+            let record = result.with_context(|| {
+                format!("Failed to parse CSV line {} in {}", index + 1, filename)
+            })?;
+            let line_number = record.position().map(|p| p.line() as usize).unwrap_or(index + 1);
+
+            let Some(columns) = &header else {
+                let names: Vec<&str> = record.iter().map(str::trim).collect();
+                if names.contains(&"Timestamp") && names.contains(&"Transaction Type") {
+                    header = Some(record);
+                }
+                continue;
+            };
+            let field = |prefix: &str| {
+                columns
+                    .iter()
+                    .position(|name| name.trim().starts_with(prefix))
+                    .and_then(|i| record.get(i))
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+            };
+
+            let kind = field("Transaction Type").unwrap_or("Unknown").to_string();
+            let asset = field("Asset").unwrap_or("").to_uppercase();
+            let quantity = field("Quantity Transacted").and_then(export_number).unwrap_or(0.0).abs();
+            let spot = field("Spot Price at Transaction").and_then(export_number);
+            let value = field("Total")
+                .or_else(|| field("Subtotal"))
+                .and_then(export_number)
+                .or_else(|| spot.map(|price| price * quantity))
+                .map(f64::abs);
+
+            let sign = if Self::is_outflow(&kind) { -1.0 } else { 1.0 };
+            let amount = value.map(|v| format!("{:.2}", sign * v)).unwrap_or_default();
+            let description = match field("Notes") {
+                Some(notes) => format!("{} - {}", kind, notes),
+                None => format!("{} {} {}", kind, quantity, asset),
+            };
+
+            let mut tx = RawTransaction::new(
+                iso_timestamp_date(field("Timestamp").unwrap_or("")),
+                description,
+                amount,
+                SourceType::Coinbase,
+                filename.clone(),
+                line_number,
+                record.iter().collect::<Vec<_>>().join(","),
+            )
+            .with_currency(field("Spot Price Currency").unwrap_or("USD").to_uppercase());
+
+            if !asset.is_empty() {
+                tx = tx.with_asset(asset, format!("{}", sign * quantity));
+            }
+            if let Some(id) = field("ID") {
+                tx = tx.with_external_id(id.to_string());
+            }
+
+            transactions.push(tx);
+        }
+
+        if header.is_none() {
+            return Err(anyhow::anyhow!("{} has no Coinbase header (Timestamp, Transaction Type, ...)", filename));
+        }
+
+        Ok(transactions)
+    }
+
+    fn source_type(&self) -> SourceType {
+        SourceType::Coinbase
+    }
+}
+
+impl TypeClassifier for CoinbaseParser {
+    fn classify_type(&self, description: &str, _amount: f64) -> String {
+        classify_crypto(description)
+    }
+}
+
+/// Kraken Parser - ledger export (ledgers.csv)
+///
+/// Columns: txid, refid, time, type, subtype, aclass, asset, amount, fee,
+/// balance. Every balance change is a row; a trade is two (the asset sold and
+/// the one bought, sharing a refid). Kraken's asset codes are normalised
+/// (XXBT → BTC, ZUSD → USD). Fiat rows carry their amount, net of fee; crypto
+/// rows only a quantity, valued at import from the pricing provider.
+#[derive(Default)]
+pub struct KrakenParser;
+
+impl KrakenParser {
+    pub fn new() -> Self {
+        KrakenParser
+    }
+
+    /// "XXBT" → "BTC", "ZUSD" → "USD", "DOT.S" → "DOT"
+    pub fn normalize_asset(code: &str) -> String {
+        let code = code.trim().to_uppercase();
+        let code = code.split('.').next().unwrap_or("").to_string();
+        let code = match code.len() {
+            4 if code.starts_with('X') || code.starts_with('Z') => code[1..].to_string(),
+            _ => code,
+        };
+        match code.as_str() {
+            "XBT" => "BTC".to_string(),
+            "XDG" => "DOGE".to_string(),
+            _ => code,
+        }
+    }
+
+    fn is_fiat(asset: &str) -> bool {
+        ["USD", "EUR", "GBP", "CAD", "CHF", "JPY", "AUD", "MXN"].contains(&asset)
+    }
+}
+
+impl BankParser for KrakenParser {
+    fn parse(&self, file_path: &Path) -> Result<Vec<RawTransaction>> {
+        use csv::ReaderBuilder;
+        use std::fs::File;
+
+        let file = File::open(file_path)
+            .with_context(|| format!("Failed to open file: {}", file_path.display()))?;
+
+        let mut reader = ReaderBuilder::new()
+            .has_headers(true)
+            .from_reader(file);
+
+        let mut transactions = Vec::new();
+        let filename = file_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown.csv")
+            .to_string();
+
+        let headers = reader
+            .headers()
+            .with_context(|| format!("Failed to read CSV header of {}", filename))?
+            .clone();
+        let column = |name: &str| headers.iter().position(|header| header.trim() == name);
+        let (txid, time, kind, asset_col, amount_col, fee_col) =
+            (column("txid"), column("time"), column("type"), column("asset"), column("amount"), column("fee"));
+
+        for (line_num, result) in reader.records().enumerate() {
+            let record = result.with_context(|| {
+                format!("Failed to parse CSV line {} in {}", line_num + 2, filename)
+            })?;
+            let field = |col: Option<usize>| col.and_then(|i| record.get(i)).map(str::trim).unwrap_or("");
+
+            let asset = Self::normalize_asset(field(asset_col));
+            let fee = export_number(field(fee_col)).unwrap_or(0.0);
+            let net = export_number(field(amount_col)).unwrap_or(0.0) - fee;
+            let kind = field(kind);
+
+            let (amount, currency) = if Self::is_fiat(&asset) {
+                (format!("{:.2}", net), asset.clone())
+            } else {
+                (String::new(), "USD".to_string())
+            };
+
+            let mut tx = RawTransaction::new(
+                iso_timestamp_date(field(time)),
+                format!("Kraken {} {} {}", kind, net, asset),
+                amount,
+                SourceType::Kraken,
+                filename.clone(),
+                line_num + 2,
+                record.iter().collect::<Vec<_>>().join(","),
+            )
+            .with_currency(currency);
+
+            if !Self::is_fiat(&asset) {
+                tx = tx.with_asset(asset, format!("{}", net));
+            }
+            if !field(txid).is_empty() {
+                tx = tx.with_external_id(field(txid).to_string());
+            }
+
+            transactions.push(tx);
+        }
+
+        Ok(transactions)
+    }
+
+    fn source_type(&self) -> SourceType {
+        SourceType::Kraken
+    }
+}
+
+impl TypeClassifier for KrakenParser {
+    fn classify_type(&self, description: &str, _amount: f64) -> String {
+        classify_crypto(description)
+    }
+}
+
+// ============================================================================
+// MEXICAN BANKS
+// ============================================================================
+
+/// Day-first dates (Mexican banks) → "MM/DD/YYYY"
+///
+/// Reads "05/01/2025", "05-01-25", "05/ENE/2025", "05-ene-25" (Spanish month
+/// abbreviations) and ISO "2025-01-05".
+pub fn normalize_day_first_date(value: &str) -> Result<String> {
+    const MONTHS: [&str; 12] = ["ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sep", "oct", "nov", "dic"];
+    let month_of = |text: &str| -> Option<u32> {
+        text.parse::<u32>().ok().or_else(|| {
+            let text = text.to_lowercase();
+            MONTHS.iter().position(|name| text.starts_with(name)).map(|index| index as u32 + 1)
+        })
+    };
+    let year_of = |text: &str| -> Option<i32> {
+        let year = text.parse::<i32>().ok()?;
+        Some(if text.len() == 2 { 2000 + year } else { year })
+    };
+
+    let parts: Vec<&str> = value.trim().split(['/', '-', ' ']).filter(|part| !part.is_empty()).collect();
+    let date = match parts[..] {
+        [year, month, day] if year.len() == 4 => Some((day, month, year)),
+        [day, month, year, ..] => Some((day, month, year)),
+        _ => None,
+    }
+    .and_then(|(day, month, year)| {
+        chrono::NaiveDate::from_ymd_opt(year_of(year)?, month_of(month)?, day.parse::<u32>().ok()?)
+    });
+    date.map(|date| date.format("%m/%d/%Y").to_string())
+        .ok_or_else(|| anyhow::anyhow!("Unreadable date '{}' (expected DD/MM/YYYY)", value))
+}
+
+/// "Depósito" → "deposito": lowercase without accents, for matching Spanish text
+fn fold_accents(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' => 'a',
+            'é' => 'e',
+            'í' => 'i',
+            'ó' => 'o',
+            'ú' | 'ü' => 'u',
+            'ñ' => 'n',
+            other => other,
+        })
+        .collect()
+}
+
+/// Spanish statement descriptions: card payments, own-account transfers and
+/// cash withdrawals, payroll; anything else is spend or income by its sign
+fn classify_spanish(description: &str, amount: f64) -> String {
+    let desc = fold_accents(description);
+    let has = |words: &[&str]| words.iter().any(|word| desc.contains(word));
+    if has(&["pago tarjeta", "pago a tarjeta", "pago tdc", "pago de tarjeta"]) {
+        "PAGO_TARJETA".to_string()
+    } else if has(&["traspaso", "entre cuentas", "cuenta propia", "retiro cajero", "retiro en efectivo"]) {
+        "TRASPASO".to_string()
+    } else if has(&["nomina"]) || amount > 0.0 {
+        "INGRESO".to_string()
+    } else {
+        "GASTO".to_string()
+    }
+}
+
+/// "COMPRA OXXO SANTA FE ; Tarjeta Digital ***1234" → "OXXO SANTA FE"
+fn spanish_merchant(description: &str) -> Option<String> {
+    const OPERATIONS: [&str; 8] =
+        ["compra ", "pago ", "cargo ", "deposito ", "spei enviado ", "spei recibido ", "domiciliacion ", "abono "];
+    let mut text = description.trim();
+    while let Some(operation) = OPERATIONS.iter().find(|op| fold_accents(text).starts_with(*op)) {
+        let skip = operation.chars().count();
+        text = text.char_indices().nth(skip).map(|(i, _)| text[i..].trim_start()).unwrap_or("");
+    }
+    let merchant = text.split([';', '/', '*']).next().unwrap_or("").trim();
+    if merchant.is_empty() {
+        None
+    } else {
+        Some(merchant.to_string())
+    }
+}
+
+/// Rows of a Mexican bank's CSV export
+///
+/// The header is found by name (accents and case ignored) below any account
+/// preamble. Amounts come either as separate debit (Cargo/Retiros) and credit
+/// (Abono/Depósitos) columns or as one Importe, signed or with a Tipo column
+/// (Cargo/Abono) giving the direction; dates are day-first.
+/// When the export has both an operation date and a posting date, the
+/// operation date is the transaction's and the other its posted date.
+fn parse_mexican_bank_csv(file_path: &Path, source_type: SourceType) -> Result<Vec<RawTransaction>> {
+    use csv::{ReaderBuilder, StringRecord};
+    use std::fs::File;
+
+    let file = File::open(file_path)
+        .with_context(|| format!("Failed to open file: {}", file_path.display()))?;
+
+    let mut reader = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(file);
+
+    let mut transactions = Vec::new();
+    let filename = file_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown.csv")
+        .to_string();
+
+    let mut header: Option<Vec<String>> = None;
+    for (index, result) in reader.records().enumerate() {
+        let record: StringRecord = result.with_context(|| {
+            format!("Failed to parse CSV line {} in {}", index + 1, filename)
+        })?;
+        let line_number = record.position().map(|p| p.line() as usize).unwrap_or(index + 1);
+
+        let Some(columns) = &header else {
+            let names: Vec<String> =
+                record.iter().map(|name| fold_accents(name.trim_start_matches('\u{feff}').trim())).collect();
+            let amounts = ["cargo", "retiro", "importe", "monto"];
+            if names.iter().any(|name| name.starts_with("fecha"))
+                && names.iter().any(|name| amounts.iter().any(|amount| name.starts_with(amount)))
+            {
+                header = Some(names);
+            }
+            continue;
+        };
+        let at = |names: &[&str]| {
+            names.iter().find_map(|name| columns.iter().position(|column| column.starts_with(name)))
+        };
+        let field = |column: Option<usize>| column.and_then(|i| record.get(i)).map(str::trim).unwrap_or("");
+
+        let date_at = at(&["fecha de operacion", "fecha operacion", "fecha"]);
+        let posted_at = columns
+            .iter()
+            .position(|column| column == "fecha" || column.starts_with("fecha de aplicacion"))
+            .filter(|posted| Some(*posted) != date_at);
+        let date = field(date_at);
+        if date.is_empty() {
+            continue;
+        }
+
+        let debit = export_number(field(at(&["cargo", "retiro"])));
+        let credit = export_number(field(at(&["abono", "deposito"])));
+        let amount = match (debit, credit) {
+            (Some(debit), _) if debit != 0.0 => -debit.abs(),
+            (_, Some(credit)) if credit != 0.0 => credit.abs(),
+            _ => match export_number(field(at(&["importe", "monto"]))) {
+                // Scotiabank: unsigned Importe, with Tipo saying which way it went
+                Some(amount) if fold_accents(field(at(&["tipo"]))).starts_with("cargo") => -amount.abs(),
+                Some(amount) if fold_accents(field(at(&["tipo"]))).starts_with("abono") => amount.abs(),
+                Some(signed) => signed,
+                None if debit.is_some() || credit.is_some() => 0.0,
+                None => continue,
+            },
+        };
+
+        let description = match field(at(&["descripcion detallada"])) {
+            "" => field(at(&["descripcion", "concepto"])),
+            detailed => detailed,
+        }
+        .to_string();
+
+        let mut tx = RawTransaction::new(
+            normalize_day_first_date(date).unwrap_or_else(|_| date.to_string()),
+            description.clone(),
+            format!("{:.2}", amount),
+            source_type.clone(),
+            filename.clone(),
+            line_number,
+            record.iter().collect::<Vec<_>>().join(","),
+        )
+        .with_currency("MXN".to_string());
+
+        if let Some(merchant) = spanish_merchant(&description) {
+            tx = tx.with_merchant(merchant);
+        }
+        if let Ok(posted) = normalize_day_first_date(field(posted_at)) {
+            tx = tx.with_posted_date(posted);
+        }
+        if !field(at(&["cuenta"])).is_empty() {
+            tx = tx.with_account(field(at(&["cuenta"])).to_string());
+        }
+
+        transactions.push(tx);
+    }
+
+    if header.is_none() {
+        return Err(anyhow::anyhow!(
+            "{} has no {} header (Fecha, Descripción, Cargo/Abono, ...)",
+            filename,
+            source_type.name()
+        ));
+    }
+
+    Ok(transactions)
+}
+
+/// BBVA México Parser - account movements CSV (Fecha, Descripción, Cargo,
+/// Abono, Saldo), read by the shared Mexican bank reader
+#[derive(Default)]
+pub struct BbvaParser;
+
+impl BbvaParser {
+    pub fn new() -> Self {
+        BbvaParser
+    }
+}
+
+impl BankParser for BbvaParser {
+    fn parse(&self, file_path: &Path) -> Result<Vec<RawTransaction>> {
+        parse_mexican_bank_csv(file_path, SourceType::Bbva)
+    }
+
+    fn source_type(&self) -> SourceType {
+        SourceType::Bbva
+    }
+}
+
+impl DateNormalizer for BbvaParser {
+    fn normalize_date(&self, date: &str) -> Result<String> {
+        normalize_day_first_date(date)
+    }
+}
+
+impl MerchantExtractor for BbvaParser {
+    fn extract_merchant(&self, description: &str) -> Option<String> {
+        spanish_merchant(description)
+    }
+}
+
+impl TypeClassifier for BbvaParser {
+    fn classify_type(&self, description: &str, amount: f64) -> String {
+        classify_spanish(description, amount)
+    }
+}
+
+/// Banorte Parser - account movements CSV (Cuenta, Fecha De Operación, Fecha,
+/// Referencia, Descripción, Depósitos, Retiros, Saldo, Descripción
+/// Detallada); the detailed description is used when present
+#[derive(Default)]
+pub struct BanorteParser;
+
+impl BanorteParser {
+    pub fn new() -> Self {
+        BanorteParser
+    }
+}
+
+impl BankParser for BanorteParser {
+    fn parse(&self, file_path: &Path) -> Result<Vec<RawTransaction>> {
+        parse_mexican_bank_csv(file_path, SourceType::Banorte)
+    }
+
+    fn source_type(&self) -> SourceType {
+        SourceType::Banorte
+    }
+}
+
+impl DateNormalizer for BanorteParser {
+    fn normalize_date(&self, date: &str) -> Result<String> {
+        normalize_day_first_date(date)
+    }
+}
+
+impl MerchantExtractor for BanorteParser {
+    fn extract_merchant(&self, description: &str) -> Option<String> {
+        spanish_merchant(description)
+    }
+}
+
+impl TypeClassifier for BanorteParser {
+    fn classify_type(&self, description: &str, amount: f64) -> String {
+        classify_spanish(description, amount)
+    }
+}
+
+/// CFDI Parser - Mexican electronic invoices (CFDI 3.3/4.0 XML)
+///
+/// Each `<cfdi:Comprobante>` is one transaction: the issuer (Emisor) is the
+/// merchant, Total the amount, Moneda the currency and the fiscal stamp's UUID
+/// the external id. An invoice (TipoDeComprobante I) is money out; a credit
+/// note (E) or payroll receipt (N) money in. Payment and transfer complements
+/// (P, T) don't move money themselves and are skipped.
+#[derive(Default)]
+pub struct CfdiParser;
+
+impl CfdiParser {
+    pub fn new() -> Self {
+        CfdiParser
+    }
+
+    /// Value of ` name="..."` in the tag starting at `tag`
+    fn attribute(xml: &str, tag: &str, name: &str) -> Option<String> {
+        let start = xml.find(tag)?;
+        let end = xml[start..].find('>').map(|e| start + e)?;
+        let element = &xml[start..end];
+        let key = format!(" {}=\"", name);
+        let value_start = element.find(&key)? + key.len();
+        let value = &element[value_start..];
+        Some(value[..value.find('"')?].trim().to_string()).filter(|value| !value.is_empty())
+    }
+}
+
+impl BankParser for CfdiParser {
+    fn parse(&self, file_path: &Path) -> Result<Vec<RawTransaction>> {
+        let content = std::fs::read_to_string(file_path)
+            .with_context(|| format!("Failed to open file: {}", file_path.display()))?;
+
+        let filename = file_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown.xml")
+            .to_string();
+
+        if !content.contains("<cfdi:Comprobante") {
+            return Err(anyhow::anyhow!("{} is not a CFDI (no <cfdi:Comprobante> element)", filename));
+        }
+
+        let mut transactions = Vec::new();
+        let mut offset = 0;
+        while let Some(found) = content[offset..].find("<cfdi:Comprobante") {
+            let start = offset + found;
+            let end = content[start..]
+                .find("</cfdi:Comprobante>")
+                .map(|e| start + e)
+                .unwrap_or(content.len());
+            let invoice = &content[start..end];
+            offset = end;
+
+            let sign = match Self::attribute(invoice, "<cfdi:Comprobante", "TipoDeComprobante").as_deref() {
+                Some("I") | None => -1.0,
+                Some("E") | Some("N") => 1.0,
+                Some(_) => continue,
+            };
+            let total = Self::attribute(invoice, "<cfdi:Comprobante", "Total").and_then(|t| export_number(&t));
+            let Some(total) = total else {
+                continue;
+            };
+            let issuer = Self::attribute(invoice, "<cfdi:Emisor", "Nombre")
+                .or_else(|| Self::attribute(invoice, "<cfdi:Emisor", "Rfc"))
+                .unwrap_or_else(|| "CFDI".to_string());
+            let description = match Self::attribute(invoice, "<cfdi:Concepto ", "Descripcion") {
+                Some(concept) => format!("{} - {}", issuer, concept),
+                None => issuer.clone(),
+            };
+            let date = Self::attribute(invoice, "<cfdi:Comprobante", "Fecha").unwrap_or_default();
+
+            let mut tx = RawTransaction::new(
+                iso_timestamp_date(&date),
+                description,
+                format!("{:.2}", sign * total.abs()),
+                SourceType::Cfdi,
+                filename.clone(),
+                content[..start].matches('\n').count() + 1,
+                invoice.split_whitespace().take(40).collect::<Vec<_>>().join(" "),
+            )
+            .with_merchant(issuer)
+            .with_currency(
+                Self::attribute(invoice, "<cfdi:Comprobante", "Moneda").unwrap_or_else(|| "MXN".to_string()),
+            );
+
+            if let Some(uuid) = Self::attribute(invoice, "<tfd:TimbreFiscalDigital", "UUID") {
+                tx = tx.with_external_id(uuid.to_uppercase());
+            }
+            if let Some(rfc) = Self::attribute(invoice, "<cfdi:Receptor", "Rfc") {
+                tx = tx.with_account(rfc);
+            }
+
+            transactions.push(tx);
+        }
+
+        Ok(transactions)
+    }
+
+    fn source_type(&self) -> SourceType {
+        SourceType::Cfdi
+    }
+}
+
+impl MerchantExtractor for CfdiParser {
+    fn extract_merchant(&self, description: &str) -> Option<String> {
+        description.split(" - ").next().map(str::trim).filter(|m| !m.is_empty()).map(str::to_string)
+    }
+}
+
+impl TypeClassifier for CfdiParser {
+    fn classify_type(&self, description: &str, amount: f64) -> String {
+        classify_spanish(description, amount)
+    }
+}
+
+// ============================================================================
+// PAYMENT APPS
+// ============================================================================
+
+/// "Payment: Blue Bottle" → "Blue Bottle" (payment app descriptions are
+/// "<type>: <counterparty>")
+fn wallet_counterparty(description: &str) -> Option<String> {
+    description
+        .split_once(": ")
+        .map(|(_, name)| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Fees are expenses; moving money to or from a bank or card is a transfer;
+/// anything else is spend or income by its sign
+fn classify_wallet(description: &str, amount: f64) -> String {
+    let kind = description.split(": ").next().unwrap_or("").to_lowercase();
+    let transfer = ["transfer", "withdrawal", "deposit", "conversion", "hold"];
+    if kind == "fee" {
+        "GASTO".to_string()
+    } else if transfer.iter().any(|word| kind.contains(word)) {
+        "TRASPASO".to_string()
+    } else if amount < 0.0 {
+        "GASTO".to_string()
+    } else {
+        "INGRESO".to_string()
+    }
+}
+
+/// The separate row for the fee a payment app took out of `tx` (None when
+/// there was no fee)
+fn fee_row(tx: &RawTransaction, fee: f64, counterparty: &str) -> Option<RawTransaction> {
+    if fee == 0.0 {
+        return None;
+    }
+    let mut row = RawTransaction::new(
+        tx.date.clone(),
+        format!("Fee: {}", counterparty),
+        format!("{:.2}", -fee.abs()),
+        tx.source_type.clone(),
+        tx.source_file.clone(),
+        tx.line_number,
+        tx.raw_line.clone(),
+    )
+    .with_merchant(tx.source_type.name().to_string())
+    .with_category("Fees".to_string())
+    .with_pending(tx.pending);
+    row.currency = tx.currency.clone();
+    row.external_id = tx.external_id.as_ref().map(|id| format!("{}-fee", id));
+    Some(row)
+}
+
+/// Statuses of rows that never moved money
+fn is_void_status(status: &str) -> bool {
+    let status = status.to_lowercase();
+    ["denied", "canceled", "cancelled", "failed", "removed", "reversed"].iter().any(|word| status.contains(word))
+}
+
+/// PayPal Parser - "Activity download" CSV
+///
+/// Columns are found by name (Date, Name, Type, Status, Currency, Gross, Fee,
+/// Transaction ID, Balance Impact). The row's amount is the gross; a fee
+/// becomes its own row (category Fees) so the payment keeps its real amount
+/// and the two still add up to the net. The Name column is the counterparty.
+/// Memo rows (authorizations that didn't touch the balance) and denied or
+/// cancelled payments are skipped; pending ones are marked pending.
+#[derive(Default)]
+pub struct PayPalParser;
+
+impl PayPalParser {
+    pub fn new() -> Self {
+        PayPalParser
+    }
+}
+
+impl BankParser for PayPalParser {
+    fn parse(&self, file_path: &Path) -> Result<Vec<RawTransaction>> {
+        use csv::ReaderBuilder;
+        use std::fs::File;
+
+        let file = File::open(file_path)
+            .with_context(|| format!("Failed to open file: {}", file_path.display()))?;
+
+        let mut reader = ReaderBuilder::new()
+            .has_headers(true)
+            .flexible(true)
+            .from_reader(file);
+
+        let mut transactions = Vec::new();
+        let filename = file_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown.csv")
+            .to_string();
+
+        let headers = reader
+            .headers()
+            .with_context(|| format!("Failed to read CSV header of {}", filename))?
+            .clone();
+        let column =
+            |name: &str| headers.iter().position(|header| header.trim_start_matches('\u{feff}').trim() == name);
+        if column("Gross").is_none() {
+            return Err(anyhow::anyhow!("{} has no PayPal header (Date, Name, Type, Gross, Fee, ...)", filename));
+        }
+
+        for (line_num, result) in reader.records().enumerate() {
+            let record = result.with_context(|| {
+                format!("Failed to parse CSV line {} in {}", line_num + 2, filename)
+            })?;
+            let field = |name: &str| column(name).and_then(|i| record.get(i)).map(str::trim).unwrap_or("");
+
+            let status = field("Status");
+            if field("Balance Impact").eq_ignore_ascii_case("memo") || is_void_status(status) {
+                continue;
+            }
+            let Some(gross) = export_number(field("Gross")) else {
+                continue;
+            };
+            let kind = field("Type");
+            let counterparty = match field("Name") {
+                "" => kind,
+                name => name,
+            };
+
+            let mut tx = RawTransaction::new(
+                field("Date").to_string(),
+                format!("{}: {}", kind, counterparty),
+                format!("{:.2}", gross),
+                SourceType::PayPal,
+                filename.clone(),
+                line_num + 2,
+                record.iter().collect::<Vec<_>>().join(","),
+            )
+            .with_merchant(counterparty.to_string())
+            .with_currency(match field("Currency") {
+                "" => "USD".to_string(),
+                currency => currency.to_uppercase(),
+            })
+            .with_pending(status.eq_ignore_ascii_case("pending"));
+            if !field("Transaction ID").is_empty() {
+                tx = tx.with_external_id(field("Transaction ID").to_string());
+            }
+
+            let fee = fee_row(&tx, export_number(field("Fee")).unwrap_or(0.0), counterparty);
+            transactions.push(tx);
+            transactions.extend(fee);
+        }
+
+        Ok(transactions)
+    }
+
+    fn source_type(&self) -> SourceType {
+        SourceType::PayPal
+    }
+}
+
+impl MerchantExtractor for PayPalParser {
+    fn extract_merchant(&self, description: &str) -> Option<String> {
+        wallet_counterparty(description)
+    }
+}
+
+impl TypeClassifier for PayPalParser {
+    fn classify_type(&self, description: &str, amount: f64) -> String {
+        classify_wallet(description, amount)
+    }
+}
+
+/// Venmo Parser - account statement CSV
+///
+/// The statement opens with the account name and an "Account Activity" line
+/// before its header (ID, Datetime, Type, Status, Note, From, To, Amount
+/// (total), Amount (fee), Funding Source, ...), and ends with a balance row
+/// that has no ID. Amounts look like "- $15.00". The total includes the fee,
+/// so the payment row gets the total minus the fee and the fee is its own
+/// row. The counterparty is whichever of From/To isn't the statement's owner
+/// (the name on every row); without one, the payee of money sent and the
+/// payer of money received.
+#[derive(Default)]
+pub struct VenmoParser;
+
+impl VenmoParser {
+    pub fn new() -> Self {
+        VenmoParser
+    }
+}
+
+impl BankParser for VenmoParser {
+    fn parse(&self, file_path: &Path) -> Result<Vec<RawTransaction>> {
+        use csv::{ReaderBuilder, StringRecord};
+        use std::fs::File;
+
+        let file = File::open(file_path)
+            .with_context(|| format!("Failed to open file: {}", file_path.display()))?;
+
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(file);
+
+        let filename = file_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown.csv")
+            .to_string();
+
+        let mut header: Option<StringRecord> = None;
+        let mut rows = Vec::new();
+        for (index, result) in reader.records().enumerate() {
+            let record = result.with_context(|| {
+                format!("Failed to parse CSV line {} in {}", index + 1, filename)
+            })?;
+            let line_number = record.position().map(|p| p.line() as usize).unwrap_or(index + 1);
+            if header.is_none() {
+                let names: Vec<&str> = record.iter().map(str::trim).collect();
+                if names.contains(&"ID") && names.contains(&"Datetime") {
+                    header = Some(record);
+                }
+                continue;
+            }
+            rows.push((line_number, record));
+        }
+        let Some(columns) = header else {
+            return Err(anyhow::anyhow!("{} has no Venmo header (ID, Datetime, Type, ...)", filename));
+        };
+        let field = |record: &StringRecord, prefix: &str| -> String {
+            columns
+                .iter()
+                .position(|name| name.trim().starts_with(prefix))
+                .and_then(|i| record.get(i))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        rows.retain(|(_, record)| !field(record, "ID").is_empty() && !is_void_status(&field(record, "Status")));
+
+        // The owner is the one name present on every row
+        let owner = rows.first().and_then(|(_, first)| {
+            [field(first, "From"), field(first, "To")].into_iter().find(|name| {
+                !name.is_empty()
+                    && rows.len() > 1
+                    && rows.iter().all(|(_, record)| field(record, "From") == *name || field(record, "To") == *name)
+            })
+        });
+
+        let mut transactions = Vec::new();
+        for (line_number, record) in &rows {
+            let Some(total) = export_number(&field(record, "Amount (total)")) else {
+                continue;
+            };
+            let fee = export_number(&field(record, "Amount (fee)")).unwrap_or(0.0).abs();
+            let amount = if total < 0.0 { total + fee } else { total };
+            let (from, to) = (field(record, "From"), field(record, "To"));
+            let kind = field(record, "Type");
+            let counterparty = match &owner {
+                Some(owner) if from == *owner => to,
+                Some(_) => from,
+                None if total < 0.0 => to,
+                None => from,
+            };
+            let counterparty = if counterparty.is_empty() { kind.clone() } else { counterparty };
+            let note = field(record, "Note");
+            let description = if note.is_empty() {
+                format!("{}: {}", kind, counterparty)
+            } else {
+                format!("{}: {} ({})", kind, counterparty, note)
+            };
+
+            let tx = RawTransaction::new(
+                iso_timestamp_date(&field(record, "Datetime")),
+                description,
+                format!("{:.2}", amount),
+                SourceType::Venmo,
+                filename.clone(),
+                *line_number,
+                record.iter().collect::<Vec<_>>().join(","),
+            )
+            .with_merchant(counterparty.clone())
+            .with_currency("USD".to_string())
+            .with_pending(field(record, "Status").eq_ignore_ascii_case("pending"))
+            .with_external_id(field(record, "ID"));
+
+            let fee = fee_row(&tx, fee, &counterparty);
+            transactions.push(tx);
+            transactions.extend(fee);
+        }
+
+        Ok(transactions)
+    }
+
+    fn source_type(&self) -> SourceType {
+        SourceType::Venmo
+    }
+}
+
+impl MerchantExtractor for VenmoParser {
+    fn extract_merchant(&self, description: &str) -> Option<String> {
+        wallet_counterparty(description)
+            .map(|name| name.split(" (").next().unwrap_or(&name).to_string())
+    }
+}
+
+impl TypeClassifier for VenmoParser {
+    fn classify_type(&self, description: &str, amount: f64) -> String {
+        classify_wallet(description, amount)
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_type_names() {
+        assert_eq!(SourceType::BankOfAmerica.name(), "Bank of America");
+        assert_eq!(SourceType::AppleCard.name(), "AppleCard");
+        assert_eq!(SourceType::Stripe.name(), "Stripe");
+        assert_eq!(SourceType::Wise.name(), "Wise");
+        assert_eq!(SourceType::Scotiabank.name(), "Scotiabank");
+    }
+
+    #[test]
+    fn test_source_type_codes() {
+        assert_eq!(SourceType::BankOfAmerica.code(), "BofA");
+        assert_eq!(SourceType::AppleCard.code(), "Apple");
+        assert_eq!(SourceType::Stripe.code(), "Stripe");
+        assert_eq!(SourceType::Wise.code(), "Wise");
+        assert_eq!(SourceType::Scotiabank.code(), "Scotia");
+    }
+
+    #[test]
+    fn test_detect_source_bofa() {
+        let path = Path::new("bofa_march_2024.csv");
+        let result = detect_source(path);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), SourceType::BankOfAmerica);
+    }
+
+    #[test]
+    fn test_detect_source_apple() {
+        let path = Path::new("Apple Card Activity.csv");
+        let result = detect_source(path);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), SourceType::AppleCard);
+    }
+
+    #[test]
+    fn test_detect_source_stripe() {
+        let path = Path::new("stripe_january_2024.json");
+        let result = detect_source(path);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), SourceType::Stripe);
+    }
+
+    #[test]
+    fn test_detect_source_wise() {
+        let path = Path::new("wise_statement.csv");
+        let result = detect_source(path);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), SourceType::Wise);
+    }
+
+    #[test]
+    fn test_detect_source_scotia() {
+        let path = Path::new("scotia_feb_2024.csv");
+        let result = detect_source(path);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), SourceType::Scotiabank);
+    }
+
+    #[test]
+    fn test_detect_source_unknown() {
+        let path = Path::new("unknown_bank.csv");
+        let result = detect_source(path);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_get_parser_bofa() {
+        let parser = get_parser(SourceType::BankOfAmerica);
+        assert_eq!(parser.source_type(), SourceType::BankOfAmerica);
+    }
+
+    #[test]
+    fn test_get_parser_apple() {
+        let parser = get_parser(SourceType::AppleCard);
+        assert_eq!(parser.source_type(), SourceType::AppleCard);
+    }
+
+    #[test]
+    fn test_raw_transaction_builder() {
+        let tx = RawTransaction::new(
+            "2024-03-20".to_string(),
+            "STARBUCKS".to_string(),
+            "-45.99".to_string(),
+            SourceType::BankOfAmerica,
+            "bofa_march.csv".to_string(),
+            23,
+            "03/20/2024,STARBUCKS,-45.99".to_string(),
+        )
+        .with_merchant("STARBUCKS".to_string())
+        .with_category("Food".to_string())
+        .with_confidence(0.95);
+
+        assert_eq!(tx.date, "2024-03-20");
+        assert_eq!(tx.merchant, Some("STARBUCKS".to_string()));
+        assert_eq!(tx.category, Some("Food".to_string()));
+        assert_eq!(tx.confidence, Some(0.95));
+    }
+
+    #[test]
+    fn test_bofa_parser_parse_csv() {
         let parser = BofAParser::new();
         let path = Path::new("test_bofa.csv");
         let result = parser.parse(path);
@@ -1115,6 +2518,26 @@ mod tests {
         assert_eq!(txs[0].category, Some("Restaurants".to_string()));
     }
 
+    #[test]
+    fn test_apple_parser_reads_clearing_date() {
+        let path = std::env::temp_dir().join(format!("apple-{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "Transaction Date,Clearing Date,Description,Merchant,Category,Type,Amount (USD),Purchased By\n\
+             01/14/2025,01/16/2025,BLUE BOTTLE COFFEE,Blue Bottle,Restaurants,Purchase,6.50,Ana\n\
+             01/15/2025,,UBER *TRIP,Uber,Transportation,Purchase,18.20,Ana\n",
+        )
+        .unwrap();
+        let txs = AppleCardParser::new().parse(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(txs.len(), 2);
+        assert_eq!((txs[0].date.as_str(), txs[0].amount.as_str()), ("01/14/2025", "6.50"));
+        assert_eq!(txs[0].posted_date.as_deref(), Some("01/16/2025"));
+        assert_eq!(txs[0].merchant.as_deref(), Some("Blue Bottle"));
+        assert_eq!(txs[1].posted_date, None);
+    }
+
     #[test]
     fn test_apple_extract_merchant_uber() {
         let parser = AppleCardParser::new();
@@ -1253,11 +2676,40 @@ mod tests {
 
         // Fourth transaction: -41000 MXN → USD
         // Exchange rate 20.00 means 1 USD = 20 MXN
-        // So 41000 MXN / 20 = 2050 USD
+        // So 41000 MXN / 20 = 2050 USD, paid out
         assert!(txs[3].description.contains("MXN"));
         assert!(txs[3].description.contains("USD"));
         let amount: f64 = txs[3].amount.parse().unwrap();
-        assert_eq!(amount, 2050.00, "MXN conversion should be exactly 2050 USD");
+        assert_eq!(amount, -2050.00, "MXN payment out should be exactly -2050 USD");
+    }
+
+    #[test]
+    fn test_wise_outgoing_payments_keep_their_sign() {
+        let path = std::env::temp_dir().join(format!("wise-{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "TransferWise ID,Date,Amount,Currency,Description,Payee Name,Exchange Rate,Fee Amount,Total Amount\n\
+             TRANSFER-1,01/10/2025,-8200.00,MXN,Payment to supplier,Proveedor SA de CV,20.50,40.00,-8240.00\n\
+             TRANSFER-2,01/11/2025,\"-1,025.00\",MXN,Rent,Casa,20.50,0.00,-1025.00\n\
+             TRANSFER-3,01/12/2025,410.00,MXN,Payment from client,Cliente,20.50,0.00,410.00\n\
+             TRANSFER-4,01/13/2025,-75.00,USD,Payment to designer,Studio,1.00,0.00,-75.00\n",
+        )
+        .unwrap();
+        let txs = WiseParser::new().parse(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let amounts: Vec<&str> = txs.iter().map(|tx| tx.amount.as_str()).collect();
+        assert_eq!(amounts, vec!["-400.00", "-50.00", "20.00", "-75.00"]);
+        assert_eq!(WiseDirection::of(-8200.0), WiseDirection::Outgoing);
+        assert_eq!(WiseDirection::Outgoing.signed(400.0), -400.0);
+
+        // Through normalization: money out, classified as an expense
+        let rules = crate::rules::RuleEngine::new();
+        let payment = crate::imports::normalize_raw(&txs[0], &rules, "default").unwrap();
+        assert_eq!(payment.amount_numeric, -400.0);
+        assert_eq!(payment.transaction_type, "GASTO");
+        let income = crate::imports::normalize_raw(&txs[2], &rules, "default").unwrap();
+        assert_eq!((income.amount_numeric, income.transaction_type.as_str()), (20.0, "INGRESO"));
     }
 
     #[test]
@@ -1296,4 +2748,304 @@ mod tests {
 
         assert_eq!(type_result, "GASTO");
     }
+
+    // ============================================================================
+    // Crypto Exchange Tests
+    // ============================================================================
+
+    #[test]
+    fn test_coinbase_parser_skips_preamble_and_signs_by_direction() {
+        let path = std::env::temp_dir().join(format!("coinbase-{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "You can use this transaction report to inform your likely tax obligations.\n\
+             \n\
+             Transactions\n\
+             User,ana@example.com,abc123\n\
+             ID,Timestamp,Transaction Type,Asset,Quantity Transacted,Spot Price Currency,Spot Price at Transaction,\
+             Subtotal,Total (inclusive of fees and/or spread),Fees and/or Spread,Notes\n\
+             cb-1,2025-01-15 10:23:45 UTC,Buy,BTC,0.001,USD,$98000.00,$98.00,$99.99,$1.99,Bought 0.001 BTC\n\
+             cb-2,2025-01-20T08:00:00Z,Send,ETH,0.5,USD,$3300.00,,,,\n\
+             cb-3,2025-01-31T00:00:00Z,Staking Income,SOL,0.02,USD,$200.00,$4.00,$4.00,$0.00,\n",
+        )
+        .unwrap();
+        let txs = CoinbaseParser::new().parse(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(txs.len(), 3);
+        assert_eq!((txs[0].date.as_str(), txs[0].amount.as_str()), ("01/15/2025", "99.99"));
+        assert_eq!((txs[0].asset.as_deref(), txs[0].quantity.as_deref()), (Some("BTC"), Some("0.001")));
+        assert_eq!(txs[0].external_id.as_deref(), Some("cb-1"));
+        assert_eq!(txs[0].line_number, 6);
+        assert_eq!((txs[1].amount.as_str(), txs[1].quantity.as_deref()), ("-1650.00", Some("-0.5")));
+
+        let parser = CoinbaseParser::new();
+        assert_eq!(parser.classify_type(&txs[0].description, 99.99), "TRASPASO");
+        assert_eq!(parser.classify_type(&txs[2].description, 4.0), "INGRESO");
+    }
+
+    #[test]
+    fn test_kraken_asset_codes_and_fiat_rows() {
+        assert_eq!(KrakenParser::normalize_asset("XXBT"), "BTC");
+        assert_eq!(KrakenParser::normalize_asset("ZUSD"), "USD");
+        assert_eq!(KrakenParser::normalize_asset("DOT.S"), "DOT");
+        assert_eq!(KrakenParser::normalize_asset("SOL"), "SOL");
+        assert_eq!(detect_source(Path::new("kraken_ledgers.csv")).unwrap(), SourceType::Kraken);
+        assert_eq!(detect_source(Path::new("Coinbase-2025.csv")).unwrap(), SourceType::Coinbase);
+    }
+
+    #[test]
+    fn test_paypal_parser_separates_fees() {
+        let path = std::env::temp_dir().join(format!("paypal-{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "\u{feff}\"Date\",\"Time\",\"TimeZone\",\"Name\",\"Type\",\"Status\",\"Currency\",\"Gross\",\"Fee\",\"Net\",\
+             \"Transaction ID\",\"Balance Impact\"\n\
+             \"01/15/2025\",\"10:00:00\",\"PST\",\"Ana Diaz\",\"Mobile Payment\",\"Completed\",\"USD\",\"100.00\",\
+             \"-3.20\",\"96.80\",\"PP-1\",\"Credit\"\n\
+             \"01/16/2025\",\"11:00:00\",\"PST\",\"Spotify\",\"PreApproved Payment Bill User Payment\",\"Completed\",\
+             \"USD\",\"-10.99\",\"0.00\",\"-10.99\",\"PP-2\",\"Debit\"\n\
+             \"01/16/2025\",\"11:00:00\",\"PST\",\"\",\"General Card Deposit\",\"Completed\",\"USD\",\"10.99\",\"0.00\",\
+             \"10.99\",\"PP-3\",\"Credit\"\n\
+             \"01/17/2025\",\"09:00:00\",\"PST\",\"eBay\",\"General Authorization\",\"Pending\",\"USD\",\"-50.00\",\
+             \"0.00\",\"-50.00\",\"PP-4\",\"Memo\"\n",
+        )
+        .unwrap();
+        let txs = PayPalParser::new().parse(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(txs.len(), 4);
+        assert_eq!((txs[0].amount.as_str(), txs[0].merchant.as_deref()), ("100.00", Some("Ana Diaz")));
+        assert_eq!((txs[1].amount.as_str(), txs[1].category.as_deref()), ("-3.20", Some("Fees")));
+        assert_eq!(txs[1].external_id.as_deref(), Some("PP-1-fee"));
+        assert_eq!(txs[2].merchant.as_deref(), Some("Spotify"));
+
+        let parser = PayPalParser::new();
+        assert_eq!(parser.classify_type(&txs[0].description, 100.0), "INGRESO");
+        assert_eq!(parser.classify_type(&txs[1].description, -3.2), "GASTO");
+        assert_eq!(parser.classify_type(&txs[2].description, -10.99), "GASTO");
+        assert_eq!(parser.classify_type(&txs[3].description, 10.99), "TRASPASO");
+        assert_eq!(parser.extract_merchant(&txs[2].description).as_deref(), Some("Spotify"));
+    }
+
+    #[test]
+    fn test_venmo_parser_counterparty_and_fee() {
+        let path = std::env::temp_dir().join(format!("venmo-{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "Account Statement - (@ana-diaz) ,,,,,,,,,,\n\
+             Account Activity,,,,,,,,,,\n\
+             ,ID,Datetime,Type,Status,Note,From,To,Amount (total),Amount (fee),Funding Source\n\
+             ,,,,,,,,,,\n\
+             ,3001,2025-01-05T18:22:31,Payment,Complete,Pizza,Ana Diaz,Bob Jones,- $15.00,,Venmo balance\n\
+             ,3002,2025-01-06T09:00:00,Charge,Complete,Rent,Carla Ruiz,Ana Diaz,- $800.00,,Venmo balance\n\
+             ,3003,2025-01-07T12:00:00,Instant Transfer,Issued,,Ana Diaz,,- $101.75,- $1.75,\n\
+             ,3004,2025-01-08T12:00:00,Payment,Complete,Tacos,Bob Jones,Ana Diaz,+ $12.00,,\n",
+        )
+        .unwrap();
+        let txs = VenmoParser::new().parse(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(txs.len(), 5);
+        assert_eq!((txs[0].date.as_str(), txs[0].merchant.as_deref()), ("01/05/2025", Some("Bob Jones")));
+        assert_eq!(txs[1].merchant.as_deref(), Some("Carla Ruiz"));
+        assert_eq!((txs[2].amount.as_str(), txs[3].amount.as_str()), ("-100.00", "-1.75"));
+        assert_eq!(txs[3].external_id.as_deref(), Some("3003-fee"));
+        assert_eq!((txs[4].amount.as_str(), txs[4].merchant.as_deref()), ("12.00", Some("Bob Jones")));
+
+        let parser = VenmoParser::new();
+        assert_eq!(parser.classify_type(&txs[2].description, -100.0), "TRASPASO");
+        assert_eq!(parser.classify_type(&txs[0].description, -15.0), "GASTO");
+        assert_eq!(parser.extract_merchant(&txs[0].description).as_deref(), Some("Bob Jones"));
+        assert_eq!(detect_source(Path::new("venmo_statement.csv")).unwrap(), SourceType::Venmo);
+        assert_eq!(detect_source(Path::new("PayPal-Activity.CSV")).unwrap(), SourceType::PayPal);
+    }
+
+    #[test]
+    fn test_normalize_day_first_dates() {
+        assert_eq!(normalize_day_first_date("05/01/2025").unwrap(), "01/05/2025");
+        assert_eq!(normalize_day_first_date("31-12-24").unwrap(), "12/31/2024");
+        assert_eq!(normalize_day_first_date("05/ENE/2025").unwrap(), "01/05/2025");
+        assert_eq!(normalize_day_first_date("07-sept-2025").unwrap(), "09/07/2025");
+        assert_eq!(normalize_day_first_date("2025-01-05").unwrap(), "01/05/2025");
+        assert!(normalize_day_first_date("31/02/2025").is_err());
+        assert_eq!(BanorteParser::new().normalize_date("15/03/2025").unwrap(), "03/15/2025");
+    }
+
+    #[test]
+    fn test_mexican_bank_csvs() {
+        let bbva = std::env::temp_dir().join(format!("bbva-{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &bbva,
+            "Cuenta: ***1234\n\
+             Fecha,Descripción,Cargo,Abono,Saldo\n\
+             05/01/2025,OXXO SANTA FE ; Tarjeta Digital ***1234,\"1,250.50\",,8749.50\n\
+             15/01/2025,DEPÓSITO DE NÓMINA EMPRESA SA,,\"25,000.00\",33749.50\n\
+             20/01/2025,PAGO TARJETA DE CREDITO,5000.00,,28749.50\n",
+        )
+        .unwrap();
+        let txs = BbvaParser::new().parse(&bbva).unwrap();
+        std::fs::remove_file(&bbva).unwrap();
+        assert_eq!(txs.len(), 3);
+        assert_eq!((txs[0].date.as_str(), txs[0].amount.as_str()), ("01/05/2025", "-1250.50"));
+        assert_eq!((txs[0].merchant.as_deref(), txs[0].currency.as_deref()), (Some("OXXO SANTA FE"), Some("MXN")));
+        assert_eq!(txs[1].amount, "25000.00");
+        let parser = BbvaParser::new();
+        assert_eq!(parser.classify_type(&txs[0].description, -1250.5), "GASTO");
+        assert_eq!(parser.classify_type(&txs[1].description, 25000.0), "INGRESO");
+        assert_eq!(parser.classify_type(&txs[2].description, -5000.0), "PAGO_TARJETA");
+
+        let banorte = std::env::temp_dir().join(format!("banorte-{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &banorte,
+            "Cuenta,Fecha De Operación,Fecha,Referencia,Descripción,Depósitos,Retiros,Saldo,Descripción Detallada\n\
+             0123,30/01/2025,31/01/2025,99,COMPRA,,$320.00,$1000.00,COMPRA LIVERPOOL POLANCO\n\
+             0123,31/01/2025,31/01/2025,100,TRASPASO,$500.00,,$1500.00,TRASPASO ENTRE CUENTAS PROPIAS\n",
+        )
+        .unwrap();
+        let txs = BanorteParser::new().parse(&banorte).unwrap();
+        std::fs::remove_file(&banorte).unwrap();
+        assert_eq!((txs[0].date.as_str(), txs[0].posted_date.as_deref()), ("01/30/2025", Some("01/31/2025")));
+        assert_eq!((txs[0].amount.as_str(), txs[0].merchant.as_deref()), ("-320.00", Some("LIVERPOOL POLANCO")));
+        assert_eq!(BanorteParser::new().classify_type(&txs[1].description, 500.0), "TRASPASO");
+        assert_eq!(detect_source(Path::new("movimientos_banorte.csv")).unwrap(), SourceType::Banorte);
+    }
+
+    #[test]
+    fn test_scotiabank_csv() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/parsers/scotiabank_movimientos.csv");
+        let parser = ScotiabankParser::new();
+        let txs = parser.parse(&fixture).unwrap();
+        assert_eq!(txs.len(), 6);
+        assert_eq!((txs[0].date.as_str(), txs[0].amount.as_str()), ("01/03/2025", "-1250.50"));
+        let merchants: Vec<_> = txs.iter().map(|tx| tx.merchant.as_deref()).collect();
+        assert_eq!(
+            merchants[..4],
+            [Some("OXXO INSURGENTES"), Some("EMPRESA SA DE CV"), Some("CFE SUMINISTRADOR"), None]
+        );
+        assert_eq!(merchants[5], Some("Scotiabank"));
+        let types: Vec<_> =
+            txs.iter().map(|tx| parser.classify_type(&tx.description, tx.amount.parse().unwrap())).collect();
+        assert_eq!(types[0], "GASTO");
+        assert_eq!(types[1], "INGRESO");
+        assert_eq!((types[3].as_str(), types[4].as_str()), ("TRASPASO", "TRASPASO"));
+
+        // Unsigned Importe with a Tipo column
+        let path = std::env::temp_dir().join(format!("scotia-{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "Fecha,Concepto,Tipo,Importe\n\
+             05/02/2025,COMPRA TDD LIVERPOOL PERISUR,Cargo,\"3,499.00\"\n\
+             07/02/2025,DEPOSITO EFECTIVO,Abono,500.00\n",
+        )
+        .unwrap();
+        let txs = parser.parse(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((txs[0].amount.as_str(), txs[0].merchant.as_deref()), ("-3499.00", Some("LIVERPOOL PERISUR")));
+        assert_eq!(txs[1].amount, "500.00");
+        assert_eq!(parser.normalize_date("05/02/2025").unwrap(), "02/05/2025");
+
+        // Accents before the reference don't shift where it is cut
+        for (description, merchant) in [
+            ("COMPRA EN COMERCIO CAFÉ ÑANDÚ REF 123", "CAFÉ ÑANDÚ"),
+            ("PAGO SERVICIO TELÉFONOS DE MÉXICO REF 88120", "TELÉFONOS DE MÉXICO"),
+        ] {
+            assert_eq!(parser.extract_merchant(description).as_deref(), Some(merchant), "{}", description);
+        }
+    }
+
+    #[test]
+    fn test_cfdi_invoice_receipt() {
+        let path = std::env::temp_dir().join(format!("cfdi-{}.xml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<cfdi:Comprobante Version="4.0" Fecha="2025-01-05T12:30:00" SubTotal="100.00" Moneda="MXN" Total="116.00"
+    TipoDeComprobante="I">
+  <cfdi:Emisor Rfc="CCO8605231N4" Nombre="CADENA COMERCIAL OXXO"/>
+  <cfdi:Receptor Rfc="DIAA800101XX0" Nombre="ANA DIAZ"/>
+  <cfdi:Conceptos><cfdi:Concepto Cantidad="1" Descripcion="Café americano" Importe="100.00"/></cfdi:Conceptos>
+  <cfdi:Complemento><tfd:TimbreFiscalDigital UUID="ab12cd34-0000-4000-8000-000000000001"/></cfdi:Complemento>
+</cfdi:Comprobante>
+"#,
+        )
+        .unwrap();
+        let txs = CfdiParser::new().parse(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(txs.len(), 1);
+        assert_eq!((txs[0].date.as_str(), txs[0].amount.as_str()), ("01/05/2025", "-116.00"));
+        assert_eq!(txs[0].merchant.as_deref(), Some("CADENA COMERCIAL OXXO"));
+        assert_eq!(txs[0].external_id.as_deref(), Some("AB12CD34-0000-4000-8000-000000000001"));
+        assert_eq!(txs[0].description, "CADENA COMERCIAL OXXO - Café americano");
+    }
+
+    // ============================================================================
+    // OFX Parser Tests
+    // ============================================================================
+
+    #[test]
+    fn test_detect_source_ofx() {
+        assert_eq!(detect_source(Path::new("checking_2025-01.ofx")).unwrap(), SourceType::Ofx);
+        assert_eq!(detect_source(Path::new("Export.QFX")).unwrap(), SourceType::Ofx);
+    }
+
+    #[test]
+    fn test_ofx_parser_parse_sgml() {
+        let path = std::env::temp_dir().join(format!("parser-{}.ofx", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "OFXHEADER:100\nDATA:OFXSGML\n\n<OFX>\n<BANKACCTFROM>\n<ACCTID>12345\n</BANKACCTFROM>\n\
+             <STMTTRN>\n<TRNTYPE>DEBIT\n<DTPOSTED>20250105120000[-5:EST]\n<TRNAMT>-4.50\n\
+             <FITID>1\n<SIC>5814\n<NAME>BLUE BOTTLE\n<MEMO>COFFEE\n</STMTTRN>\n\
+             <STMTTRN>\n<TRNTYPE>XFER\n<DTPOSTED>20250106\n<TRNAMT>250.00\n<FITID>2\n\
+             <NAME>ONLINE TRANSFER</NAME>\n</STMTTRN>\n</OFX>\n",
+        )
+        .unwrap();
+
+        let txs = OfxParser::new().parse(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(txs.len(), 2);
+        assert_eq!(txs[0].date, "01/05/2025");
+        assert_eq!(txs[0].amount, "-4.50");
+        assert_eq!(txs[0].description, "BLUE BOTTLE COFFEE");
+        assert_eq!(txs[0].merchant, Some("BLUE BOTTLE".to_string()));
+        assert_eq!(txs[0].account, Some("12345".to_string()));
+        assert_eq!(txs[0].mcc, Some(5814));
+        assert_eq!(txs[0].line_number, 8);
+        assert_eq!(txs[1].merchant, Some("ONLINE TRANSFER".to_string()));
+
+        let parser = OfxParser::new();
+        assert_eq!(parser.classify_type(&txs[0].description, -4.50), "GASTO");
+        assert_eq!(parser.classify_type(&txs[1].description, 250.0), "TRASPASO");
+    }
+
+    #[test]
+    fn test_corrupt_files_are_errors_not_panics() {
+        let corrupt: [&[u8]; 5] = [
+            b"Date,Description,Amount\n01/02/2025,\"unterminated,-5\n\xff\xfe,\xc3",
+            b"{\"data\": [{\"amount\": 100, \"created\": 9223372036854775807}]}",
+            b"<STMTTRN><TRNAMT>NaN<DTPOSTED>2025",
+            b"<cfdi:Comprobante Fecha=\"2025-01-05\" Total=\"inf\"",
+            b"Id,Date,Amount,Currency,Description\nT-1,05-01-2025 10:00:00.000,1e999,EUR,Payment to \xe2\x98",
+        ];
+        let rules = crate::rules::RuleEngine::new();
+        for source in SourceType::all() {
+            for (index, content) in corrupt.iter().enumerate() {
+                let path = std::env::temp_dir().join(format!("corrupt-{}-{}", index, uuid::Uuid::new_v4()));
+                std::fs::write(&path, content).unwrap();
+                let parsed = get_parser(source.clone()).parse(&path);
+                std::fs::remove_file(&path).unwrap();
+                for row in parsed.unwrap_or_default() {
+                    // Whatever was read, an import gets a finite amount or an error
+                    if let Ok(tx) = crate::imports::normalize_raw(&row, &rules, "default") {
+                        assert!(tx.amount_numeric.is_finite(), "{:?} {:?}", source, row);
+                    }
+                }
+            }
+        }
+        assert_eq!(export_number("$1,234.56"), Some(1234.56));
+        assert_eq!((export_number("NaN"), export_number("-inf"), export_number("1e999")), (None, None, None));
+    }
 }
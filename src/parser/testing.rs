@@ -0,0 +1,220 @@
+// 🧪 Parser Testing - Golden-file regression checks for any BankParser
+//
+// Problem solved:
+// - A parser change that shifted one column or flipped one sign was only
+//   caught if a hand-written unit test happened to assert that field
+// - Users adding their own parsers had no way to pin down what a parser
+//   should read from a file, other than importing it and looking
+//
+// A fixture is an input file next to its golden output, the same name plus
+// `.golden.json` (`bofa_jan.csv` → `bofa_jan.csv.golden.json`): the JSON of
+// every RawTransaction the parser should read, field by field. `check_golden`
+// runs a parser on the input and lists each difference; `check_dir` does it
+// for every fixture of a directory (the parser chosen per file, as imports
+// do); `write_golden` (re)writes a golden file from what the parser reads
+// today, for new fixtures and deliberate changes. The built-in parsers are
+// checked against fixtures/parsers, and `parser check <dir>` runs the same
+// check from the CLI.
+
+use super::{detect_source, get_parser, BankParser, RawTransaction};
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Appended to an input file's name to get its golden file
+pub const GOLDEN_SUFFIX: &str = ".golden.json";
+
+/// The golden file of `input`
+pub fn golden_path(input: &Path) -> PathBuf {
+    let mut name = input.as_os_str().to_os_string();
+    name.push(GOLDEN_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// One fixture's result; no differences means it passed
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoldenResult {
+    pub input: PathBuf,
+    /// "line 3 amount: expected \"-5.25\", got \"5.25\""
+    pub differences: Vec<String>,
+    /// Set when the parser failed or the golden file is missing or unreadable
+    pub error: Option<String>,
+}
+
+impl GoldenResult {
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.differences.is_empty()
+    }
+}
+
+// ============================================================================
+// CHECK
+// ============================================================================
+
+/// The rows as golden JSON: one object per RawTransaction
+fn to_values(rows: &[RawTransaction]) -> Result<Vec<Value>> {
+    rows.iter().map(|row| Ok(serde_json::to_value(row)?)).collect()
+}
+
+fn row_label(row: &Value, index: usize) -> String {
+    match row.get("line_number").and_then(Value::as_u64) {
+        Some(line) => format!("line {}", line),
+        None => format!("row {}", index + 1),
+    }
+}
+
+/// Field-by-field differences between golden rows and parsed rows
+pub fn compare_rows(expected: &[Value], actual: &[Value]) -> Vec<String> {
+    let mut differences = Vec::new();
+    if expected.len() != actual.len() {
+        differences.push(format!("expected {} rows, got {}", expected.len(), actual.len()));
+    }
+    for (index, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+        let (Some(expected_fields), Some(actual_fields)) = (expected.as_object(), actual.as_object()) else {
+            if expected != actual {
+                differences.push(format!("{}: expected {}, got {}", row_label(expected, index), expected, actual));
+            }
+            continue;
+        };
+        let mut fields: Vec<&String> = expected_fields.keys().chain(actual_fields.keys()).collect();
+        fields.sort();
+        fields.dedup();
+        for field in fields {
+            let expected_value = expected_fields.get(field).unwrap_or(&Value::Null);
+            let actual_value = actual_fields.get(field).unwrap_or(&Value::Null);
+            if expected_value != actual_value {
+                differences.push(format!(
+                    "{} {}: expected {}, got {}",
+                    row_label(expected, index),
+                    field,
+                    expected_value,
+                    actual_value
+                ));
+            }
+        }
+    }
+    differences
+}
+
+/// Run `parser` on `input` and compare with the input's golden file
+pub fn check_golden(parser: &dyn BankParser, input: &Path) -> GoldenResult {
+    let outcome = (|| -> Result<Vec<String>> {
+        let golden = golden_path(input);
+        let content = fs::read_to_string(&golden)
+            .with_context(|| format!("No golden file {} (write it with --bless)", golden.display()))?;
+        let expected: Vec<Value> =
+            serde_json::from_str(&content).with_context(|| format!("Unreadable golden file {}", golden.display()))?;
+        let actual = to_values(&parser.parse(input)?)?;
+        Ok(compare_rows(&expected, &actual))
+    })();
+
+    match outcome {
+        Ok(differences) => GoldenResult { input: input.to_path_buf(), differences, error: None },
+        Err(e) => GoldenResult { input: input.to_path_buf(), differences: Vec::new(), error: Some(format!("{:#}", e)) },
+    }
+}
+
+/// Panic with every difference unless `parser` reads `input` as its golden
+/// file says (for use in tests)
+pub fn assert_golden(parser: &dyn BankParser, input: &Path) {
+    let result = check_golden(parser, input);
+    if let Some(error) = &result.error {
+        panic!("{}: {}", input.display(), error);
+    }
+    assert!(
+        result.differences.is_empty(),
+        "{} differs from its golden file:\n  {}",
+        input.display(),
+        result.differences.join("\n  ")
+    );
+}
+
+/// Write the golden file of `input` from what `parser` reads today
+pub fn write_golden(parser: &dyn BankParser, input: &Path) -> Result<PathBuf> {
+    let golden = golden_path(input);
+    let rows = to_values(&parser.parse(input)?)?;
+    fs::write(&golden, serde_json::to_string_pretty(&rows)? + "\n")
+        .with_context(|| format!("Failed to write {}", golden.display()))?;
+    Ok(golden)
+}
+
+/// Input files of a fixture directory: every file that isn't a golden file,
+/// sorted by name
+pub fn fixture_inputs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut inputs = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if path.is_file() && !name.ends_with(GOLDEN_SUFFIX) && !name.starts_with('.') {
+            inputs.push(path);
+        }
+    }
+    inputs.sort();
+    Ok(inputs)
+}
+
+/// The built-in parser for a fixture, detected from its name like an import
+pub fn detect_parser(input: &Path) -> Result<Box<dyn BankParser>> {
+    Ok(get_parser(detect_source(input)?))
+}
+
+/// Check every fixture of `dir` with the parser `parser_for` picks for it
+/// (pass `detect_parser` for the built-ins)
+pub fn check_dir<F>(dir: &Path, parser_for: F) -> Result<Vec<GoldenResult>>
+where
+    F: Fn(&Path) -> Result<Box<dyn BankParser>>,
+{
+    let inputs = fixture_inputs(dir)?;
+    if inputs.is_empty() {
+        return Err(anyhow!("No fixtures in {}", dir.display()));
+    }
+    Ok(inputs
+        .iter()
+        .map(|input| match parser_for(input) {
+            Ok(parser) => check_golden(parser.as_ref(), input),
+            Err(e) => GoldenResult { input: input.clone(), differences: Vec::new(), error: Some(e.to_string()) },
+        })
+        .collect())
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::BofAParser;
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/parsers")
+    }
+
+    #[test]
+    fn test_builtin_parsers_match_their_golden_files() {
+        let results = check_dir(&fixtures(), detect_parser).unwrap();
+        assert!(results.len() >= 4);
+        for result in &results {
+            assert!(result.passed(), "{}: {:?} {:?}", result.input.display(), result.error, result.differences);
+        }
+        assert_golden(&BofAParser::new(), &fixtures().join("bofa_statement.csv"));
+    }
+
+    #[test]
+    fn test_differences_name_the_line_and_field() {
+        let dir = std::env::temp_dir().join(format!("golden-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("bofa_jan.csv");
+        fs::write(&input, "Date,Description,Amount\n01/02/2025,STARBUCKS STORE 123,-5.25\n").unwrap();
+
+        assert!(check_golden(&BofAParser::new(), &input).error.unwrap().contains("--bless"));
+        write_golden(&BofAParser::new(), &input).unwrap();
+        assert!(check_golden(&BofAParser::new(), &input).passed());
+
+        fs::write(&input, "Date,Description,Amount\n01/02/2025,STARBUCKS STORE 123,5.25\n").unwrap();
+        let result = check_golden(&BofAParser::new(), &input);
+        assert!(result.differences.contains(&"line 2 amount: expected \"-5.25\", got \"5.25\"".to_string()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
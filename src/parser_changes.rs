@@ -0,0 +1,274 @@
+// 🧪 Parser Changes - Re-parse imported files with the current parsers
+//
+// Problem solved:
+// - `BankParser::version()` existed but nothing read it: a parser fix could
+//   change the dates, amounts or descriptions of files imported earlier and
+//   nobody would know which rows were affected
+// - Re-importing to pick up a fix skips rows as duplicates (or adds
+//   near-copies), with no preview of what the new parser does differently
+//
+// Imported rows carry their parser's tag in the `parser_version` metadata.
+// `outdated_sources` lists the files whose rows came from another version of
+// the parser. `compare_with_stored` re-parses a file with today's parser and
+// diffs the parser-level fields against the rows as first imported, line by
+// line. Nothing is written; the report is for deciding what to fix before
+// re-importing or correcting anything.
+
+use crate::db::{get_transaction_history, Transaction};
+use crate::deduplication::DeduplicationEngine;
+use crate::imports::{parse_statement, ImportContext, StatementFormat};
+use crate::jobs::ledger_transactions;
+use crate::parser::{parser_version_tag, SourceType};
+use crate::rules::RuleEngine;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Fields a parser decides (as named in the CSV export); the rest comes from
+/// rules and enrichment
+pub const PARSER_FIELDS: &[&str] =
+    &["Date", "Description", "Amount_Original", "Amount_Numeric", "Account_Number", "Currency"];
+
+/// The source whose parser wrote `tag` (e.g. "bofa_parser_v1.0")
+fn source_for_tag(tag: &str) -> Option<SourceType> {
+    SourceType::all()
+        .into_iter()
+        .find(|source| tag.starts_with(&format!("{}_parser_v", source.code().to_lowercase())))
+}
+
+fn stored_tag(tx: &Transaction) -> Option<&str> {
+    tx.metadata.get("parser_version").and_then(Value::as_str)
+}
+
+// ============================================================================
+// OUTDATED SOURCES
+// ============================================================================
+
+/// A file whose rows were parsed by another version of its parser
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutdatedSource {
+    pub source_file: String,
+    pub bank: String,
+    /// Tag stored on the rows
+    pub stored_version: String,
+    /// Tag of today's parser for the same source
+    pub current_version: String,
+    pub rows: usize,
+}
+
+/// Files of the ledger imported with a parser version that is not current
+pub fn outdated_sources(conn: &rusqlite::Connection, ledger_id: &str) -> Result<Vec<OutdatedSource>> {
+    let mut counts: BTreeMap<(String, String), (String, usize)> = BTreeMap::new();
+    for tx in ledger_transactions(conn, ledger_id)? {
+        let Some(tag) = stored_tag(&tx) else {
+            continue;
+        };
+        let key = (tx.source_file.clone(), tag.to_string());
+        counts.entry(key).or_insert_with(|| (tx.bank.clone(), 0)).1 += 1;
+    }
+
+    Ok(counts
+        .into_iter()
+        .filter_map(|((source_file, stored_version), (bank, rows))| {
+            let current_version = parser_version_tag(source_for_tag(&stored_version)?);
+            (current_version != stored_version).then_some(OutdatedSource {
+                source_file,
+                bank,
+                stored_version,
+                current_version,
+                rows,
+            })
+        })
+        .collect())
+}
+
+// ============================================================================
+// RE-PARSE AND DIFF
+// ============================================================================
+
+/// One field (or whole row) that today's parser reads differently
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParserDiscrepancy {
+    pub line: usize,
+    /// One of PARSER_FIELDS, or "row" when a side has no such line
+    pub field: String,
+    pub stored: String,
+    pub reparsed: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParserChangeReport {
+    pub source_file: String,
+    pub source: String,
+    /// Tags found on the stored rows of the file
+    pub stored_versions: Vec<String>,
+    pub current_version: String,
+    pub stored_rows: usize,
+    pub reparsed_rows: usize,
+    pub discrepancies: Vec<ParserDiscrepancy>,
+}
+
+impl ParserChangeReport {
+    pub fn version_changed(&self) -> bool {
+        self.stored_versions.iter().any(|version| *version != self.current_version)
+    }
+}
+
+fn field_values(tx: &Transaction) -> serde_json::Map<String, Value> {
+    match serde_json::to_value(tx) {
+        Ok(Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    }
+}
+
+fn show(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(other) => other.to_string(),
+        None => "(none)".to_string(),
+    }
+}
+
+/// Re-parse `content` (a file imported into the ledger as `filename`) with the
+/// current parser and compare it with the stored rows of that file
+///
+/// Stored rows are compared as first imported, so later corrections don't
+/// count as parser changes.
+pub fn compare_with_stored(
+    conn: &rusqlite::Connection,
+    ledger_id: &str,
+    filename: &str,
+    content: &[u8],
+) -> Result<ParserChangeReport> {
+    let (rules, deduplication) = (RuleEngine::new(), DeduplicationEngine::new());
+    let parsed = parse_statement(filename, content, &ImportContext::new(&rules, &deduplication), ledger_id)?;
+    let source = match parsed.format {
+        StatementFormat::Bank(source) => source,
+        StatementFormat::Canonical => {
+            return Err(anyhow!("{} is a canonical CSV export; only bank exports have parsers", parsed.filename))
+        }
+    };
+
+    let mut stored: BTreeMap<usize, Transaction> = BTreeMap::new();
+    for tx in ledger_transactions(conn, ledger_id)? {
+        if tx.source_file != parsed.filename {
+            continue;
+        }
+        let Ok(line) = tx.line_number.parse::<usize>() else {
+            continue;
+        };
+        let original = get_transaction_history(conn, &tx.id)?.into_iter().next().unwrap_or(tx);
+        stored.insert(line, original);
+    }
+    let reparsed: BTreeMap<usize, Result<Transaction>> = parsed.rows.into_iter().collect();
+
+    let mut discrepancies = Vec::new();
+    let row = |line: usize, stored: &str, reparsed: String| ParserDiscrepancy {
+        line,
+        field: "row".to_string(),
+        stored: stored.to_string(),
+        reparsed,
+    };
+    let lines: BTreeSet<usize> = stored.keys().chain(reparsed.keys()).copied().collect();
+    for line in lines {
+        match (stored.get(&line), reparsed.get(&line)) {
+            (Some(old), Some(Ok(new))) => {
+                let (old, new) = (field_values(old), field_values(new));
+                for field in PARSER_FIELDS {
+                    if old.get(*field) != new.get(*field) {
+                        discrepancies.push(ParserDiscrepancy {
+                            line,
+                            field: field.to_string(),
+                            stored: show(old.get(*field)),
+                            reparsed: show(new.get(*field)),
+                        });
+                    }
+                }
+            }
+            (Some(_), Some(Err(e))) => discrepancies.push(row(line, "imported", format!("unreadable: {}", e))),
+            (Some(_), None) => discrepancies.push(row(line, "imported", "(no such row)".to_string())),
+            (None, Some(Ok(_))) => discrepancies.push(row(line, "(not imported)", "new row".to_string())),
+            // Unreadable then and now (or skipped as a duplicate): nothing to compare
+            (None, Some(Err(_))) | (None, None) => {}
+        }
+    }
+
+    let stored_versions: BTreeSet<String> = stored.values().filter_map(stored_tag).map(str::to_string).collect();
+    Ok(ParserChangeReport {
+        source_file: parsed.filename,
+        source: source.name().to_string(),
+        stored_versions: stored_versions.into_iter().collect(),
+        current_version: parser_version_tag(source),
+        stored_rows: stored.len(),
+        reparsed_rows: reparsed.len(),
+        discrepancies,
+    })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::setup_database;
+    use crate::imports::import_statement;
+    use rusqlite::Connection;
+
+    const BOFA: &str = "Date,Description,Amount\n\
+        01/02/2025,STARBUCKS STORE 123,-5.25\n\
+        01/03/2025,SHELL OIL 5521,-40.00\n";
+
+    /// Database with BOFA imported, its rows tagged as parsed by "v0.9"
+    fn imported_with_old_parser() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        import_statement(&conn, "bofa_jan.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+        conn.execute(
+            "UPDATE transactions SET metadata = json_set(metadata, '$.parser_version', 'bofa_parser_v0.9')",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_outdated_sources() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        import_statement(&conn, "bofa_jan.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+        assert!(outdated_sources(&conn, "default").unwrap().is_empty());
+
+        let conn = imported_with_old_parser();
+        let outdated = outdated_sources(&conn, "default").unwrap();
+        assert_eq!(outdated.len(), 1);
+        assert_eq!(outdated[0].source_file, "bofa_jan.csv");
+        assert_eq!(outdated[0].stored_version, "bofa_parser_v0.9");
+        assert_eq!(outdated[0].current_version, parser_version_tag(SourceType::BankOfAmerica));
+        assert_eq!(outdated[0].rows, 2);
+    }
+
+    #[test]
+    fn test_compare_with_stored_reports_discrepancies() {
+        let conn = imported_with_old_parser();
+        let unchanged = compare_with_stored(&conn, "default", "bofa_jan.csv", BOFA.as_bytes()).unwrap();
+        assert!(unchanged.version_changed());
+        assert!(unchanged.discrepancies.is_empty(), "{:?}", unchanged.discrepancies);
+        assert_eq!((unchanged.stored_rows, unchanged.reparsed_rows), (2, 2));
+
+        // As if the new parser read the file differently
+        let changed = "Date,Description,Amount\n\
+            01/02/2025,STARBUCKS STORE 123,-5.52\n\
+            01/03/2025,SHELL OIL 5521,-40.00\n\
+            01/04/2025,AMAZON MKTPLACE,-12.00\n";
+        let report = compare_with_stored(&conn, "default", "bofa_jan.csv", changed.as_bytes()).unwrap();
+        let fields: Vec<(usize, &str)> = report.discrepancies.iter().map(|d| (d.line, d.field.as_str())).collect();
+        assert_eq!(fields, vec![(2, "Amount_Original"), (2, "Amount_Numeric"), (4, "row")]);
+        assert_eq!((report.discrepancies[1].stored.as_str(), report.discrepancies[1].reparsed.as_str()), ("-5.25", "-5.52"));
+
+        let canonical = "Date,Description,Amount_Original,Amount_Numeric\n";
+        assert!(compare_with_stored(&conn, "default", "export.csv", canonical.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,324 @@
+// 🔒 Period Close - Reconciled statement periods locked against corrections
+//
+// Problem solved:
+// - A statement reconciled in March could be changed in June (a recategorised
+//   row, a void, an undo) and its closing balance no longer matched the rows,
+//   with nothing saying the period had been signed off
+//
+// `close_period` locks one statement period of an account - the window from
+// the previous close + 1 day through this close, as manifest.rs checks it -
+// once its imported rows verify against the statement. From then on a new
+// version of any row dated in that window (correction, void, undo) is refused
+// until `reopen_period` opens it again with a reason. Closing and reopening
+// are both events, so the audit trail shows every change made after a close.
+
+use crate::db::{get_current_transaction, insert_event, Event, Transaction};
+use crate::event_schema::PeriodLockChanged;
+use crate::jobs::ledger_transactions;
+use crate::manifest::{check_account, from_account};
+use crate::statements::balance_history;
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+/// A statement period closed to corrections
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClosedPeriod {
+    pub account_id: String,
+    pub ledger_id: String,
+    pub statement_period: String,
+    /// Window locked (inclusive)
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub closed_by: String,
+    pub closed_at: String,
+}
+
+impl ClosedPeriod {
+    /// Whether `tx` is one of the rows this close locked
+    pub fn covers(&self, tx: &Transaction) -> bool {
+        tx.ledger_id == self.ledger_id
+            && from_account(tx, &self.account_id)
+            && tx.parsed_date().is_some_and(|date| self.from <= date && date <= self.to)
+    }
+}
+
+fn parse_date(value: String) -> NaiveDate {
+    NaiveDate::parse_from_str(&value, "%Y-%m-%d").unwrap_or_default()
+}
+
+/// Periods of a ledger that are closed now (reopened ones are left out),
+/// oldest first
+pub fn closed_periods(conn: &Connection, ledger_id: &str) -> Result<Vec<ClosedPeriod>> {
+    let mut stmt = conn.prepare(
+        "SELECT account_id, ledger_id, statement_period, period_start, period_end, closed_by, closed_at
+         FROM closed_periods
+         WHERE ledger_id = ?1 AND reopened_at IS NULL
+         ORDER BY period_end, account_id",
+    )?;
+    let periods = stmt
+        .query_map([ledger_id], |row| {
+            Ok(ClosedPeriod {
+                account_id: row.get(0)?,
+                ledger_id: row.get(1)?,
+                statement_period: row.get(2)?,
+                from: parse_date(row.get(3)?),
+                to: parse_date(row.get(4)?),
+                closed_by: row.get(5)?,
+                closed_at: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(periods)
+}
+
+// ============================================================================
+// CLOSE / REOPEN
+// ============================================================================
+
+/// Lock a reconciled statement period of an account
+///
+/// `period` is the statement's period label ("January 2025") or its close
+/// date (2025-01-31). Fails unless the statement close was recorded and the
+/// imported rows in its window match its declared count and total.
+pub fn close_period(
+    conn: &Connection,
+    ledger_id: &str,
+    account_id: &str,
+    period: &str,
+    actor: &str,
+) -> Result<ClosedPeriod> {
+    let history = balance_history(conn, account_id, ledger_id)?;
+    let check = check_account(&history, &ledger_transactions(conn, ledger_id)?)
+        .into_iter()
+        .map(|(_, check)| check)
+        .find(|check| check.statement_period == period || check.to.to_string() == period)
+        .ok_or_else(|| anyhow!("No statement close for {} {} (record it with `statement close`)", account_id, period))?;
+
+    if !check.verified() {
+        return Err(anyhow!(
+            "{} {} doesn't reconcile: {} rows totalling {:.2}, the statement says {}{:.2}",
+            account_id,
+            check.statement_period,
+            check.count,
+            check.total,
+            check.declared_count.map_or_else(String::new, |count| format!("{} rows totalling ", count)),
+            check.expected_total
+        ));
+    }
+    if closed_periods(conn, ledger_id)?
+        .iter()
+        .any(|closed| closed.account_id == account_id && closed.to == check.to)
+    {
+        return Err(anyhow!("{} {} is already closed", account_id, check.statement_period));
+    }
+
+    let closed = ClosedPeriod {
+        account_id: account_id.to_string(),
+        ledger_id: ledger_id.to_string(),
+        statement_period: check.statement_period.clone(),
+        from: check.from,
+        to: check.to,
+        closed_by: actor.to_string(),
+        closed_at: Utc::now().to_rfc3339(),
+    };
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO closed_periods
+            (ledger_id, account_id, statement_period, period_start, period_end, closed_by, closed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(ledger_id, account_id, period_end) DO UPDATE SET
+            statement_period = excluded.statement_period,
+            period_start = excluded.period_start,
+            closed_by = excluded.closed_by,
+            closed_at = excluded.closed_at,
+            reopened_by = NULL,
+            reopened_at = NULL,
+            reopen_reason = NULL",
+        params![
+            closed.ledger_id,
+            closed.account_id,
+            closed.statement_period,
+            closed.from.to_string(),
+            closed.to.to_string(),
+            closed.closed_by,
+            closed.closed_at,
+        ],
+    )?;
+    record_lock_change(&tx, "period_closed", &closed, None, actor)?;
+    tx.commit()?;
+
+    Ok(closed)
+}
+
+/// Open a closed period to corrections again; the reason is kept with the event
+pub fn reopen_period(
+    conn: &Connection,
+    ledger_id: &str,
+    account_id: &str,
+    period: &str,
+    reason: &str,
+    actor: &str,
+) -> Result<ClosedPeriod> {
+    if reason.trim().is_empty() {
+        return Err(anyhow!("A reason is required to reopen a closed period"));
+    }
+    let closed = closed_periods(conn, ledger_id)?
+        .into_iter()
+        .find(|closed| {
+            closed.account_id == account_id && (closed.statement_period == period || closed.to.to_string() == period)
+        })
+        .ok_or_else(|| anyhow!("{} {} is not closed", account_id, period))?;
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "UPDATE closed_periods SET reopened_by = ?1, reopened_at = ?2, reopen_reason = ?3
+         WHERE ledger_id = ?4 AND account_id = ?5 AND period_end = ?6",
+        params![actor, Utc::now().to_rfc3339(), reason, ledger_id, account_id, closed.to.to_string()],
+    )?;
+    record_lock_change(&tx, "period_reopened", &closed, Some(reason), actor)?;
+    tx.commit()?;
+
+    Ok(closed)
+}
+
+fn record_lock_change(
+    conn: &Connection,
+    event_type: &str,
+    closed: &ClosedPeriod,
+    reason: Option<&str>,
+    actor: &str,
+) -> Result<()> {
+    let payload = PeriodLockChanged {
+        statement_period: closed.statement_period.clone(),
+        from: closed.from,
+        to: closed.to,
+        reason: reason.map(str::to_string),
+    };
+    let event = Event::typed(event_type, "account", &closed.account_id, &payload, actor)?;
+    insert_event(conn, &event.with_ledger(&closed.ledger_id))
+}
+
+// ============================================================================
+// ENFORCEMENT
+// ============================================================================
+
+/// Fail when writing `next` would change a row in a closed period: either the
+/// stored version or `next` itself is dated inside one
+pub(crate) fn require_open_period(conn: &Connection, next: &Transaction) -> Result<()> {
+    let has_closes: Option<i64> = conn
+        .query_row(
+            "SELECT 1 FROM closed_periods WHERE ledger_id = ?1 AND reopened_at IS NULL LIMIT 1",
+            [&next.ledger_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if has_closes.is_none() {
+        return Ok(());
+    }
+
+    let current = get_current_transaction(conn, &next.id)?;
+    for closed in closed_periods(conn, &next.ledger_id)? {
+        if current.iter().chain(std::iter::once(next)).any(|tx| closed.covers(tx)) {
+            return Err(anyhow!(
+                "Transaction {} is in {} {}, which is closed (reopen it with a reason first)",
+                next.id,
+                closed.account_id,
+                closed.statement_period
+            ));
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_transaction_version, query_events, setup_database, void_transaction, EventFilter};
+    use crate::imports::import_statement;
+    use crate::reconciliation::StatementMetadata;
+    use crate::statements::record_statement_close;
+
+    const BOFA: &str = "Date,Description,Amount\n\
+        01/02/2025,STARBUCKS STORE 123,-5.25\n\
+        01/15/2025,PAYROLL ACME,2000.00\n\
+        02/03/2025,SHELL OIL 5521,-35.00\n";
+
+    fn january(conn: &Connection, closing: f64) {
+        let statement = StatementMetadata {
+            account_name: "Bank of America".to_string(),
+            statement_period: "January 2025".to_string(),
+            opening_balance: 100.0,
+            closing_balance: closing,
+            statement_date: NaiveDate::from_ymd_opt(2025, 1, 31).unwrap(),
+            declared_count: Some(2),
+            declared_total: None,
+        };
+        record_statement_close(conn, "Bank of America", "default", &statement, "ana").unwrap();
+    }
+
+    fn row(conn: &Connection, date: &str) -> Transaction {
+        ledger_transactions(conn, "default").unwrap().into_iter().find(|tx| tx.date == date).unwrap()
+    }
+
+    #[test]
+    fn test_closing_requires_a_reconciled_statement() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        import_statement(&conn, "bofa.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+
+        assert!(close_period(&conn, "default", "Bank of America", "January 2025", "ana").is_err());
+        january(&conn, 2000.0);
+        let error = close_period(&conn, "default", "Bank of America", "January 2025", "ana").unwrap_err();
+        assert!(error.to_string().contains("doesn't reconcile"), "{}", error);
+
+        january(&conn, 2094.75);
+        let closed = close_period(&conn, "default", "Bank of America", "2025-01-31", "ana").unwrap();
+        assert_eq!(closed.from, NaiveDate::from_ymd_opt(2025, 1, 1).unwrap());
+        assert!(close_period(&conn, "default", "Bank of America", "January 2025", "ana").is_err());
+        assert_eq!(closed_periods(&conn, "default").unwrap(), vec![closed]);
+    }
+
+    #[test]
+    fn test_closed_period_refuses_corrections_until_reopened() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        import_statement(&conn, "bofa.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+        january(&conn, 2094.75);
+        close_period(&conn, "default", "Bank of America", "January 2025", "ana").unwrap();
+
+        let coffee = row(&conn, "01/02/2025");
+        let mut next = coffee.next_version(Some("recategorise".to_string()));
+        next.category = "Dining".to_string();
+        let error = insert_transaction_version(&conn, &next, "ana").unwrap_err();
+        assert!(error.to_string().contains("closed"), "{}", error);
+        assert!(void_transaction(&conn, &coffee.id, "duplicate", "ana").is_err());
+
+        // Moving a February row into January is a change to January too
+        let shell = row(&conn, "02/03/2025");
+        let mut moved = shell.next_version(Some("wrong date".to_string()));
+        moved.date = "01/30/2025".to_string();
+        assert!(insert_transaction_version(&conn, &moved, "ana").is_err());
+        void_transaction(&conn, &shell.id, "duplicate", "ana").unwrap();
+
+        assert!(reopen_period(&conn, "default", "Bank of America", "January 2025", " ", "ana").is_err());
+        reopen_period(&conn, "default", "Bank of America", "January 2025", "late refund", "ana").unwrap();
+        insert_transaction_version(&conn, &next, "ana").unwrap();
+        assert!(closed_periods(&conn, "default").unwrap().is_empty());
+
+        let events = query_events(&conn, &EventFilter::default()).unwrap();
+        let types: Vec<&str> = events
+            .iter()
+            .filter(|event| event.event_type.starts_with("period_"))
+            .map(|event| event.event_type.as_str())
+            .collect();
+        assert_eq!(types.len(), 2);
+        assert!(types.contains(&"period_closed") && types.contains(&"period_reopened"));
+    }
+}
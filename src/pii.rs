@@ -0,0 +1,312 @@
+// 🕵️ PII Masking - Card, IBAN and account numbers masked before storage
+//
+// Problem solved:
+// - Most banks print "ACCOUNT ENDING IN 5226", but some put the whole card
+//   number, IBAN or account number in the description, and it was stored,
+//   exported and sent to enrichment providers as is
+//
+// `find_pii` scans text for three patterns:
+// - Card numbers: 13-19 digits (groups split by spaces or dashes allowed)
+//   passing the Luhn check
+// - IBANs: country code, check digits and 11-30 letters or digits (groups
+//   allowed) passing the ISO 13616 mod-97 check
+// - Account numbers: 6 or more digits right after an account word ("ACCT",
+//   "ACCOUNT NO.", "A/C", "CUENTA", "CLABE"). Shorter numbers, such as the
+//   usual last four digits, are left alone.
+//
+// The checksums keep phone numbers, dates and reference numbers unmasked.
+// `mask_transaction` replaces each match in the description with "****" plus
+// its last four characters. It records the kinds it masked under the
+// `pii_masked` metadata key, and imports note the masking in the row's
+// provenance log and as an import issue. The merchant is left alone: it feeds
+// the idempotency hash, and masking it would make rows imported before masking
+// existed look new on re-import. Archived statement files (archive.rs) keep
+// the bytes as uploaded.
+
+use crate::db::Transaction;
+use serde::{Deserialize, Serialize};
+
+/// Metadata key listing the kinds of numbers masked in a row
+pub const PII_MASKED_KEY: &str = "pii_masked";
+
+/// Words that make the number after them an account number
+const ACCOUNT_WORDS: &[&str] = &["ACCT", "ACCOUNT", "ACC", "A/C", "CUENTA", "CTA", "CLABE"];
+
+/// Words allowed between an account word and the number ("ACCOUNT NO. 1234567")
+const NUMBER_WORDS: &[&str] = &["NO", "NUM", "NUMBER", "NR", "NBR"];
+
+/// Fewest digits of an account number (last-four references stay visible)
+const MIN_ACCOUNT_DIGITS: usize = 6;
+
+// ============================================================================
+// DETECTION
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    CardNumber,
+    Iban,
+    AccountNumber,
+}
+
+impl PiiKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PiiKind::CardNumber => "card number",
+            PiiKind::Iban => "IBAN",
+            PiiKind::AccountNumber => "account number",
+        }
+    }
+}
+
+/// One number found in a text (byte range)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PiiMatch {
+    pub kind: PiiKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Card, IBAN and account numbers in `text`, in order
+pub fn find_pii(text: &str) -> Vec<PiiMatch> {
+    let mut matches = find_ibans(text);
+    for (start, end, digits) in digit_runs(text) {
+        if matches.iter().any(|m| start < m.end && m.start < end) {
+            continue;
+        }
+        let kind = if (13..=19).contains(&digits.len()) && luhn_valid(&digits) {
+            PiiKind::CardNumber
+        } else if digits.len() >= MIN_ACCOUNT_DIGITS && follows_account_word(&text[..start]) {
+            PiiKind::AccountNumber
+        } else {
+            continue;
+        };
+        matches.push(PiiMatch { kind, start, end });
+    }
+    matches.sort_by_key(|m| m.start);
+    matches
+}
+
+/// Runs of digits, allowing single spaces or dashes between groups, as
+/// (start, end, digits)
+fn digit_runs(text: &str) -> Vec<(usize, usize, String)> {
+    let bytes = text.as_bytes();
+    let mut runs = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let at_word_start = i == 0 || !bytes[i - 1].is_ascii_alphanumeric();
+        if !bytes[i].is_ascii_digit() || !at_word_start {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let mut digits = String::new();
+        let mut end = i;
+        while i < bytes.len() {
+            if bytes[i].is_ascii_digit() {
+                digits.push(bytes[i] as char);
+                i += 1;
+                end = i;
+            } else if matches!(bytes[i], b' ' | b'-') && bytes.get(i + 1).is_some_and(u8::is_ascii_digit) {
+                i += 1;
+            } else {
+                break;
+            }
+        }
+        // A number glued to letters ("REF123456789X") is an identifier
+        if !bytes.get(end).is_some_and(u8::is_ascii_alphabetic) {
+            runs.push((start, end, digits));
+        }
+        i = end.max(start + 1);
+    }
+    runs
+}
+
+fn find_ibans(text: &str) -> Vec<PiiMatch> {
+    let bytes = text.as_bytes();
+    let mut matches = Vec::new();
+    let mut i = 0;
+    while i + 4 <= bytes.len() {
+        let at_word_start = i == 0 || !bytes[i - 1].is_ascii_alphanumeric();
+        let prefix = &bytes[i..i + 4];
+        if !(at_word_start
+            && prefix[..2].iter().all(u8::is_ascii_alphabetic)
+            && prefix[2..].iter().all(u8::is_ascii_digit))
+        {
+            i += 1;
+            continue;
+        }
+        let mut compact = String::new();
+        let mut j = i;
+        let mut end = i;
+        while j < bytes.len() {
+            if bytes[j].is_ascii_alphanumeric() {
+                compact.push(bytes[j].to_ascii_uppercase() as char);
+                j += 1;
+                end = j;
+            } else if bytes[j] == b' ' && bytes.get(j + 1).is_some_and(u8::is_ascii_alphanumeric) && compact.len() < 34
+            {
+                j += 1;
+            } else {
+                break;
+            }
+        }
+        // Groups may have swallowed a following word: try the longest valid prefix
+        let found = (15..=compact.len().min(34)).rev().find(|&len| iban_valid(&compact[..len]));
+        match found {
+            Some(len) => {
+                let end = byte_end(text, i, len);
+                matches.push(PiiMatch { kind: PiiKind::Iban, start: i, end });
+                i = end;
+            }
+            None => i = end.max(i + 1),
+        }
+    }
+    matches
+}
+
+/// End of the range starting at `start` that holds `count` alphanumerics
+fn byte_end(text: &str, start: usize, count: usize) -> usize {
+    let mut seen = 0;
+    for (offset, byte) in text.as_bytes()[start..].iter().enumerate() {
+        if byte.is_ascii_alphanumeric() {
+            seen += 1;
+            if seen == count {
+                return start + offset + 1;
+            }
+        }
+    }
+    text.len()
+}
+
+fn luhn_valid(digits: &str) -> bool {
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, byte)| {
+            let digit = u32::from(byte - b'0');
+            match (i % 2 == 1, digit * 2) {
+                (false, _) => digit,
+                (true, doubled) if doubled > 9 => doubled - 9,
+                (true, doubled) => doubled,
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// ISO 13616: move the first four characters to the end, letters as 10-35,
+/// and the number mod 97 must be 1
+fn iban_valid(iban: &str) -> bool {
+    if !iban[4..].bytes().any(|byte| byte.is_ascii_digit()) {
+        return false;
+    }
+    let rearranged = iban[4..].bytes().chain(iban[..4].bytes());
+    let mut remainder: u32 = 0;
+    for byte in rearranged {
+        let value = match byte {
+            b'0'..=b'9' => u32::from(byte - b'0'),
+            b'A'..=b'Z' => u32::from(byte - b'A') + 10,
+            _ => return false,
+        };
+        let width = if value >= 10 { 100 } else { 10 };
+        remainder = (remainder * width + value) % 97;
+    }
+    remainder == 1
+}
+
+/// Whether `before` ends with an account word, optionally followed by a
+/// number word ("ACCT#", "ACCOUNT NO.:")
+fn follows_account_word(before: &str) -> bool {
+    let words: Vec<String> = before
+        .split(|c: char| !(c.is_alphanumeric() || c == '/'))
+        .filter(|word| !word.is_empty())
+        .rev()
+        .take(2)
+        .map(str::to_uppercase)
+        .collect();
+    match words.as_slice() {
+        [last, ..] if ACCOUNT_WORDS.contains(&last.as_str()) => true,
+        [last, previous] => NUMBER_WORDS.contains(&last.as_str()) && ACCOUNT_WORDS.contains(&previous.as_str()),
+        _ => false,
+    }
+}
+
+// ============================================================================
+// MASKING
+// ============================================================================
+
+/// `text` with every match replaced by "****" and its last four characters
+pub fn mask_pii(text: &str) -> (String, Vec<PiiKind>) {
+    let matches = find_pii(text);
+    let mut masked = String::with_capacity(text.len());
+    let mut last = 0;
+    for m in &matches {
+        let tail: String = text[m.start..m.end].chars().filter(char::is_ascii_alphanumeric).collect();
+        masked.push_str(&text[last..m.start]);
+        masked.push_str("****");
+        masked.push_str(&tail[tail.len().saturating_sub(4)..]);
+        last = m.end;
+    }
+    masked.push_str(&text[last..]);
+    (masked, matches.into_iter().map(|m| m.kind).collect())
+}
+
+/// Mask the description of a row about to be stored (the merchant is part of
+/// its idempotency hash and stays as parsed); returns the kinds masked (also
+/// kept under `pii_masked`), empty when none
+pub fn mask_transaction(tx: &mut Transaction) -> Vec<PiiKind> {
+    let (description, mut kinds) = mask_pii(&tx.description);
+    kinds.sort();
+    kinds.dedup();
+    if !kinds.is_empty() {
+        tx.description = description;
+        tx.metadata.insert(PII_MASKED_KEY.to_string(), serde_json::json!(kinds));
+    }
+    kinds
+}
+
+/// "card number, IBAN" for a log line
+pub fn describe_kinds(kinds: &[PiiKind]) -> String {
+    kinds.iter().map(PiiKind::as_str).collect::<Vec<_>>().join(", ")
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cards_ibans_and_account_numbers_are_masked() {
+        let cases = [
+            ("PAYMENT FROM CARD 4111 1111 1111 1111 THANK YOU", "PAYMENT FROM CARD ****1111 THANK YOU"),
+            ("WISE TO DE89 3704 0044 0532 0130 00 RENT", "WISE TO ****3000 RENT"),
+            ("TRANSFER GB82WEST12345698765432", "TRANSFER ****5432"),
+            ("ONLINE TRANSFER TO ACCT# 004412345678", "ONLINE TRANSFER TO ACCT# ****5678"),
+            ("DEPOSITO CUENTA NO. 0123-456789", "DEPOSITO CUENTA NO. ****6789"),
+        ];
+        for (raw, masked) in cases {
+            assert_eq!(mask_pii(raw).0, masked, "{}", raw);
+        }
+        assert_eq!(mask_pii("CARD 5555-5555-5555-4444").1, vec![PiiKind::CardNumber]);
+    }
+
+    #[test]
+    fn test_ordinary_numbers_stay_readable() {
+        for text in [
+            "ACH DEPOSIT INTERNET TRANSFER FROM ACCOUNT ENDING IN 5226",
+            "AMZN MKTP US 888-280-4331 WA",
+            "POS 20250105 STARBUCKS STORE 12",
+            "CARD 4111 1111 1111 1112",
+            "REF 1234567890123456789 ORDER",
+            "DE00 1234 5678 9012 3456 78",
+        ] {
+            assert_eq!(mask_pii(text), (text.to_string(), vec![]), "{}", text);
+        }
+    }
+}
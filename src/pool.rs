@@ -0,0 +1,278 @@
+// 🏊 Connection Pool - Several SQLite connections to one database file
+//
+// Problem solved:
+// - The server and the gRPC service shared one Connection behind a Mutex: a
+//   slow report or upload held it, and every other request (even a read that
+//   SQLite could answer at the same time) queued behind it
+// - Each place that opened its own connection picked its own busy timeout, or
+//   none, so a concurrent write surfaced as "database is locked"
+//
+// `ConnectionPool` (r2d2) hands out connections to the same file. Each one is
+// opened with WAL and a busy timeout: readers run side by side, and a writer
+// waits for the write lock instead of failing. The library keeps taking a
+// plain `&Connection`; a pooled connection derefs to one. Async callers (the
+// server, feature "async-storage") use `run`, which does the same work on
+// tokio's blocking threads so a big query doesn't stall the runtime.
+
+use crate::job_queue::WORKER_BUSY_TIMEOUT;
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Connections kept by the server
+pub const DEFAULT_POOL_SIZE: u32 = 8;
+
+/// How long `get` waits for a free connection before giving up
+pub const POOL_CHECKOUT_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub type PooledConnection = r2d2::PooledConnection<SqliteConnectionManager>;
+
+// ============================================================================
+// MANAGER
+// ============================================================================
+
+/// Opens connections to one database (a path or a SQLite URI)
+#[derive(Debug, Clone)]
+pub struct SqliteConnectionManager {
+    path: PathBuf,
+    busy_timeout: Duration,
+    read_only: bool,
+}
+
+impl SqliteConnectionManager {
+    pub fn new(path: &Path) -> Self {
+        SqliteConnectionManager { path: path.to_path_buf(), busy_timeout: WORKER_BUSY_TIMEOUT, read_only: false }
+    }
+
+    /// Open connections that refuse writes (see snapshot.rs) (builder)
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Wait this long for another connection's write lock (builder)
+    pub fn with_busy_timeout(mut self, busy_timeout: Duration) -> Self {
+        self.busy_timeout = busy_timeout;
+        self
+    }
+}
+
+impl r2d2::ManageConnection for SqliteConnectionManager {
+    type Connection = Connection;
+    type Error = rusqlite::Error;
+
+    fn connect(&self) -> Result<Connection, rusqlite::Error> {
+        if self.read_only {
+            let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+            let conn = Connection::open_with_flags(&self.path, flags)?;
+            conn.busy_timeout(self.busy_timeout)?;
+            conn.pragma_update(None, "query_only", true)?;
+            return Ok(conn);
+        }
+
+        // Default flags include SQLITE_OPEN_URI, so in-memory URIs work too
+        let conn = Connection::open(&self.path)?;
+        conn.busy_timeout(self.busy_timeout)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Ok(conn)
+    }
+
+    fn is_valid(&self, conn: &mut Connection) -> Result<(), rusqlite::Error> {
+        conn.execute_batch("SELECT 1")
+    }
+
+    fn has_broken(&self, _conn: &mut Connection) -> bool {
+        false
+    }
+}
+
+// ============================================================================
+// POOL
+// ============================================================================
+
+/// Shared handle to a pool of connections (cheap to clone)
+#[derive(Debug, Clone)]
+pub struct ConnectionPool {
+    pool: r2d2::Pool<SqliteConnectionManager>,
+    location: PathBuf,
+}
+
+impl ConnectionPool {
+    /// Pool of `size` connections to the database file at `path`
+    pub fn open(path: &Path, size: u32) -> Result<Self> {
+        Self::build(SqliteConnectionManager::new(path), size)
+            .with_context(|| format!("Failed to open a connection pool for {:?}", path))
+    }
+
+    /// Pool of `size` read-only connections (report and API read paths)
+    pub fn open_read_only(path: &Path, size: u32) -> Result<Self> {
+        Self::build(SqliteConnectionManager::new(path).read_only(), size)
+            .with_context(|| format!("Failed to open a read-only connection pool for {:?}", path))
+    }
+
+    /// Pool over a private in-memory database (tests, demo)
+    ///
+    /// The connections share one database through SQLite's shared cache,
+    /// which locks per table: fine for tests, not for concurrent writers.
+    pub fn in_memory(size: u32) -> Result<Self> {
+        let uri = format!("file:trust-pool-{}?mode=memory&cache=shared", uuid::Uuid::new_v4());
+        Self::build(SqliteConnectionManager::new(Path::new(&uri)), size)
+    }
+
+    fn build(manager: SqliteConnectionManager, size: u32) -> Result<Self> {
+        let location = manager.path.clone();
+        let pool = r2d2::Pool::builder()
+            .max_size(size.max(1))
+            .connection_timeout(POOL_CHECKOUT_TIMEOUT)
+            .build(manager)?;
+        Ok(ConnectionPool { pool, location })
+    }
+
+    /// The file, or for `in_memory` the URI, the connections open: workers
+    /// that open their own connection there see the same database (an
+    /// in-memory one lives as long as the pool)
+    pub fn location(&self) -> &Path {
+        &self.location
+    }
+
+    /// A free connection, waiting up to POOL_CHECKOUT_TIMEOUT for one
+    pub fn get(&self) -> Result<PooledConnection> {
+        self.pool.get().context("No database connection free")
+    }
+
+    /// Connections open and idle right now
+    pub fn status(&self) -> (u32, u32) {
+        let state = self.pool.state();
+        (state.connections, state.idle_connections)
+    }
+}
+
+// ============================================================================
+// ASYNC (feature "async-storage")
+// ============================================================================
+
+#[cfg(feature = "async-storage")]
+impl ConnectionPool {
+    /// Run `work` with a pooled connection on tokio's blocking threads
+    ///
+    /// Waiting for a free connection happens there too, so the runtime's
+    /// workers keep serving other requests meanwhile.
+    pub async fn run<T, F>(&self, work: F) -> Result<T>
+    where
+        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let pool = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool.get()?;
+            work(&conn)
+        })
+            .await
+            .context("Database task panicked")?
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{get_all_transactions, setup_database};
+    use crate::imports::import_statement;
+    use std::sync::mpsc;
+    use std::thread;
+
+    const BOFA: &str = "Date,Description,Amount\n\
+        01/02/2025,STARBUCKS STORE 123,-5.25\n\
+        01/03/2025,SHELL OIL 5521,-40.00\n";
+
+    fn temp_pool(size: u32) -> (ConnectionPool, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("trust-pool-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pool = ConnectionPool::open(&dir.join("trust.db"), size).unwrap();
+        setup_database(&pool.get().unwrap()).unwrap();
+        (pool, dir)
+    }
+
+    #[test]
+    fn test_readers_share_the_database_while_a_write_is_open() {
+        let (pool, dir) = temp_pool(4);
+        import_statement(&pool.get().unwrap(), "bofa_jan.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+
+        // A write transaction stays open while two other connections read
+        let writer = pool.get().unwrap();
+        writer.execute_batch("BEGIN IMMEDIATE; DELETE FROM transactions;").unwrap();
+        let readers: Vec<_> = (0..2)
+            .map(|_| {
+                let pool = pool.clone();
+                thread::spawn(move || get_all_transactions(&pool.get().unwrap()).unwrap().len())
+            })
+            .collect();
+        for reader in readers {
+            assert_eq!(reader.join().unwrap(), 2, "readers see the last committed state");
+        }
+        writer.execute_batch("ROLLBACK").unwrap();
+        drop(writer);
+
+        let reader = ConnectionPool::open_read_only(&dir.join("trust.db"), 2).unwrap();
+        assert_eq!(get_all_transactions(&reader.get().unwrap()).unwrap().len(), 2);
+        assert!(reader.get().unwrap().execute("DELETE FROM transactions", []).is_err());
+
+        let in_memory = ConnectionPool::in_memory(2).unwrap();
+        setup_database(&in_memory.get().unwrap()).unwrap();
+        let (first, second) = (in_memory.get().unwrap(), in_memory.get().unwrap());
+        import_statement(&first, "bofa_jan.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+        assert_eq!(get_all_transactions(&second).unwrap().len(), 2);
+        // A worker's own connection to the pool's location sees the same rows
+        let worker = crate::job_queue::open_worker_connection(in_memory.location()).unwrap();
+        assert_eq!(get_all_transactions(&worker).unwrap().len(), 2);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_second_writer_waits_for_the_lock() {
+        let (pool, dir) = temp_pool(2);
+        let writer = pool.get().unwrap();
+        writer.execute_batch("BEGIN IMMEDIATE").unwrap();
+
+        let (started, wait_for_start) = mpsc::channel();
+        let other = pool.clone();
+        let second = thread::spawn(move || {
+            let conn = other.get().unwrap();
+            started.send(()).unwrap();
+            import_statement(&conn, "bofa_jan.csv", BOFA.as_bytes(), "default", "ana").map(|session| session.inserted)
+        });
+        wait_for_start.recv().unwrap();
+        thread::sleep(Duration::from_millis(200));
+        writer.execute_batch("COMMIT").unwrap();
+
+        assert_eq!(second.join().unwrap().unwrap(), 2, "busy timeout waits instead of failing");
+        assert_eq!(pool.status().0, 2);
+        drop(writer);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "async-storage")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_run_queries_off_the_runtime() {
+        let pool = ConnectionPool::in_memory(2).unwrap();
+        pool.run(setup_database).await.unwrap();
+        let inserted = pool
+            .run(|conn| Ok(import_statement(conn, "bofa_jan.csv", BOFA.as_bytes(), "default", "ana")?.inserted))
+            .await
+            .unwrap();
+        assert_eq!(inserted, 2);
+
+        let (first, second) = tokio::join!(
+            pool.run(get_all_transactions),
+            pool.run(|conn| crate::db::totals_by_bank(conn, None))
+        );
+        assert_eq!(first.unwrap().len(), 2);
+        assert_eq!(second.unwrap()[0].count, 2);
+        assert!(pool.run(|conn| conn.execute("SELECT nope", []).map_err(Into::into)).await.is_err());
+    }
+}
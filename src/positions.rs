@@ -0,0 +1,511 @@
+// 📈 Positions - Holdings of investment accounts, valued for net worth
+//
+// Problem solved:
+// - AccountType::Investment existed, but a brokerage account's worth is its
+//   holdings (shares of a symbol), not the sum of its cash transactions, so
+//   nothing could say what an investment account was worth
+// - There was no net worth at all: `account list` only added up stored
+//   balances of the open accounts
+//
+// A brokerage positions export (Fidelity, Schwab, Vanguard or any CSV with
+// Symbol and Quantity/Shares columns) becomes one snapshot of an account's
+// positions as of a date; `record_positions` stores it in the `positions`
+// table (recording a snapshot again for the same date replaces it).
+// `value_positions` prices them with any FxRateProvider read as
+// symbol → currency (the same provider imports use for crypto assets), falling
+// back to the price printed on the statement. `net_worth` adds each open
+// account: investment accounts at the market value of their latest snapshot,
+// the others at the sum of their transactions.
+
+use crate::accounts::{account_of, list_accounts};
+use crate::db::{insert_event, Event};
+use crate::entities::AccountType;
+use crate::event_schema::PositionsRecorded;
+use crate::fx::FxRateProvider;
+use crate::jobs::ledger_transactions;
+use anyhow::{anyhow, Context, Result};
+use chrono::{NaiveDate, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Symbol of uninvested cash (sweep funds, "Cash & Cash Investments")
+pub const CASH_SYMBOL: &str = "CASH";
+
+/// Holding of one symbol in an account on a date
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub account: String,
+    pub symbol: String,
+    pub quantity: f64,
+    /// Total paid for the holding, when the statement has it
+    pub cost_basis: Option<f64>,
+    pub currency: String,
+    pub as_of: NaiveDate,
+    /// Unit price printed on the statement
+    pub statement_price: Option<f64>,
+}
+
+impl Position {
+    pub fn is_cash(&self) -> bool {
+        self.symbol == CASH_SYMBOL || self.symbol.eq_ignore_ascii_case(&self.currency)
+    }
+}
+
+// ============================================================================
+// BROKERAGE EXPORTS
+// ============================================================================
+
+/// Positions read from one brokerage export
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokerageStatement {
+    /// "Fidelity", "Schwab", "Vanguard" or "Generic" (from the header)
+    pub broker: &'static str,
+    /// Date printed above the header (Schwab), else the one passed in
+    pub as_of: NaiveDate,
+    pub positions: Vec<Position>,
+}
+
+/// Read a positions CSV for `account`; `as_of` is used when the export
+/// doesn't print its own date
+pub fn parse_positions_csv(
+    content: &str,
+    account: &str,
+    currency: &str,
+    as_of: Option<NaiveDate>,
+) -> Result<BrokerageStatement> {
+    use csv::{ReaderBuilder, StringRecord};
+
+    let mut reader = ReaderBuilder::new().has_headers(false).flexible(true).from_reader(content.as_bytes());
+    let mut header: Option<StringRecord> = None;
+    let mut printed_date = None;
+    let mut rows = Vec::new();
+
+    for (index, result) in reader.records().enumerate() {
+        let record = result.with_context(|| format!("Failed to parse CSV line {}", index + 1))?;
+        let Some(columns) = &header else {
+            let names: Vec<String> = record.iter().map(|name| name.trim().to_lowercase()).collect();
+            if names.iter().any(|name| name == "symbol") && column(&record, QUANTITY_COLUMNS).is_some() {
+                header = Some(record);
+            } else if printed_date.is_none() {
+                printed_date = record.iter().find_map(preamble_date);
+            }
+            continue;
+        };
+        // A second section (Vanguard lists transactions below the holdings)
+        if record.get(0).map(str::trim) == columns.get(0).map(str::trim) {
+            break;
+        }
+        rows.push(record);
+    }
+    let columns = header.ok_or_else(|| anyhow!("No positions header (Symbol and Quantity/Shares columns) found"))?;
+    let as_of = printed_date
+        .or(as_of)
+        .ok_or_else(|| anyhow!("The export has no date; pass the date its positions are as of"))?;
+
+    let index_of = |names: &[&str]| column(&columns, names);
+    let (symbol_at, quantity_at) = (index_of(&["symbol"]), index_of(QUANTITY_COLUMNS));
+    let (price_at, value_at) = (index_of(PRICE_COLUMNS), index_of(VALUE_COLUMNS));
+    let cost_at = index_of(COST_BASIS_COLUMNS);
+
+    let mut positions = Vec::new();
+    for record in &rows {
+        let field = |at: Option<usize>| at.and_then(|i| record.get(i)).map(str::trim).filter(|v| !v.is_empty());
+        let number = |at: Option<usize>| field(at).and_then(statement_number);
+        let Some(symbol) = field(symbol_at).map(|s| s.trim_end_matches('*').to_string()) else {
+            continue;
+        };
+        let lower = symbol.to_lowercase();
+        if lower.contains("total") || lower.contains("pending") {
+            continue;
+        }
+
+        let position = match number(quantity_at) {
+            Some(quantity) => Position {
+                account: account.to_string(),
+                symbol: if lower.contains("cash") { CASH_SYMBOL.to_string() } else { symbol.to_uppercase() },
+                quantity,
+                cost_basis: number(cost_at),
+                currency: currency.to_string(),
+                as_of,
+                statement_price: number(price_at),
+            },
+            // Money market and cash rows only print a value
+            None => match number(value_at) {
+                Some(value) => Position {
+                    account: account.to_string(),
+                    symbol: CASH_SYMBOL.to_string(),
+                    quantity: value,
+                    cost_basis: Some(value),
+                    currency: currency.to_string(),
+                    as_of,
+                    statement_price: Some(1.0),
+                },
+                None => continue,
+            },
+        };
+        positions.push(position);
+    }
+
+    let has = |name: &str| columns.iter().any(|column| column.trim().eq_ignore_ascii_case(name));
+    let broker = if has("Cost Basis Total") || has("Last Price") {
+        "Fidelity"
+    } else if columns.iter().any(|column| column.trim().starts_with("Qty")) {
+        "Schwab"
+    } else if has("Investment Name") {
+        "Vanguard"
+    } else {
+        "Generic"
+    };
+    Ok(BrokerageStatement { broker, as_of, positions })
+}
+
+const QUANTITY_COLUMNS: &[&str] = &["quantity", "qty", "shares"];
+const PRICE_COLUMNS: &[&str] = &["last price", "price", "share price"];
+const VALUE_COLUMNS: &[&str] = &["current value", "mkt val", "market value", "total value"];
+const COST_BASIS_COLUMNS: &[&str] = &["cost basis total", "cost basis", "total cost"];
+
+/// First column whose name starts with one of `names` (tried in order)
+fn column(header: &csv::StringRecord, names: &[&str]) -> Option<usize> {
+    names.iter().find_map(|name| {
+        header.iter().position(|column| column.trim().to_lowercase().starts_with(name))
+    })
+}
+
+/// "$1,234.56", "+12.5" or "(3.00)"
+fn statement_number(value: &str) -> Option<f64> {
+    let negative = value.starts_with('(') && value.ends_with(')');
+    let cleaned: String = value.chars().filter(|c| !matches!(c, '$' | ',' | '+' | ' ' | '(' | ')')).collect();
+    cleaned.parse::<f64>().ok().map(|n| if negative { -n } else { n })
+}
+
+/// "Positions for account ... as of 09:41 AM ET, 2025/01/15"
+fn preamble_date(cell: &str) -> Option<NaiveDate> {
+    cell.split(|c: char| c.is_whitespace() || c == ',').find_map(|token| {
+        ["%Y/%m/%d", "%m/%d/%Y", "%Y-%m-%d"]
+            .iter()
+            .find_map(|format| NaiveDate::parse_from_str(token, format).ok())
+    })
+}
+
+// ============================================================================
+// STORAGE
+// ============================================================================
+
+/// Store `positions` as the snapshot of `account` on `as_of` (replacing one
+/// already recorded for that date); returns how many were stored
+pub fn record_positions(
+    conn: &Connection,
+    ledger_id: &str,
+    account: &str,
+    as_of: NaiveDate,
+    positions: &[Position],
+    source_file: &str,
+    actor: &str,
+) -> Result<usize> {
+    if let Some(other) = positions.iter().find(|p| p.account != account || p.as_of != as_of) {
+        return Err(anyhow!("Position {} belongs to {} as of {}", other.symbol, other.account, other.as_of));
+    }
+
+    let db_tx = conn.unchecked_transaction()?;
+    let replaced = conn.execute(
+        "DELETE FROM positions WHERE ledger_id = ?1 AND account = ?2 AND as_of = ?3",
+        params![ledger_id, account, as_of.to_string()],
+    )?;
+    let now = Utc::now().to_rfc3339();
+    for position in positions {
+        conn.execute(
+            "INSERT INTO positions (ledger_id, account, symbol, as_of, quantity, cost_basis, currency,
+                                    statement_price, source_file, recorded_by, recorded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+             ON CONFLICT (ledger_id, account, as_of, symbol) DO UPDATE SET
+                quantity = quantity + excluded.quantity,
+                cost_basis = CASE WHEN cost_basis IS NULL OR excluded.cost_basis IS NULL THEN NULL
+                                  ELSE cost_basis + excluded.cost_basis END",
+            params![
+                ledger_id,
+                account,
+                position.symbol,
+                as_of.to_string(),
+                position.quantity,
+                position.cost_basis,
+                position.currency,
+                position.statement_price,
+                source_file,
+                actor,
+                now,
+            ],
+        )?;
+    }
+    let payload = PositionsRecorded {
+        account: account.to_string(),
+        as_of,
+        positions: positions.len(),
+        replaced,
+        source_file: source_file.to_string(),
+    };
+    let event = Event::typed("positions_recorded", "positions", account, &payload, actor)?.with_ledger(ledger_id);
+    insert_event(conn, &event)?;
+    db_tx.commit()?;
+
+    Ok(positions.len())
+}
+
+/// Each account's latest snapshot on or before `date`, by account and symbol
+pub fn positions_as_of(conn: &Connection, ledger_id: &str, date: NaiveDate) -> Result<Vec<Position>> {
+    let mut stmt = conn.prepare(
+        "SELECT account, symbol, quantity, cost_basis, currency, as_of, statement_price FROM positions p
+         WHERE ledger_id = ?1 AND as_of = (
+             SELECT MAX(as_of) FROM positions q
+             WHERE q.ledger_id = p.ledger_id AND q.account = p.account AND q.as_of <= ?2
+         )
+         ORDER BY account, symbol",
+    )?;
+    let rows = stmt.query_map(params![ledger_id, date.to_string()], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, f64>(2)?,
+            row.get::<_, Option<f64>>(3)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, String>(5)?,
+            row.get::<_, Option<f64>>(6)?,
+        ))
+    })?;
+
+    let mut positions = Vec::new();
+    for row in rows {
+        let (account, symbol, quantity, cost_basis, currency, as_of, statement_price) = row?;
+        let as_of = NaiveDate::parse_from_str(&as_of, "%Y-%m-%d")
+            .with_context(|| format!("Bad as-of date '{}' for {} {}", as_of, account, symbol))?;
+        positions.push(Position { account, symbol, quantity, cost_basis, currency, as_of, statement_price });
+    }
+    Ok(positions)
+}
+
+// ============================================================================
+// VALUATION
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionValue {
+    pub position: Position,
+    /// None when neither the provider nor the statement has a price
+    pub price: Option<f64>,
+    pub market_value: Option<f64>,
+    /// Market value minus cost basis
+    pub unrealized_gain: Option<f64>,
+    /// The price came from the statement, not the provider
+    pub statement_priced: bool,
+}
+
+/// Price `positions` on `date` (symbol → position currency in `prices`)
+pub fn value_positions(positions: &[Position], prices: &dyn FxRateProvider, date: NaiveDate) -> Vec<PositionValue> {
+    positions
+        .iter()
+        .map(|position| {
+            let quoted = if position.is_cash() {
+                Some(1.0)
+            } else {
+                prices.rate(&position.symbol, &position.currency, date)
+            };
+            let price = quoted.or(position.statement_price);
+            let market_value = price.map(|price| price * position.quantity);
+            PositionValue {
+                position: position.clone(),
+                price,
+                market_value,
+                unrealized_gain: market_value.zip(position.cost_basis).map(|(value, cost)| value - cost),
+                statement_priced: quoted.is_none() && price.is_some(),
+            }
+        })
+        .collect()
+}
+
+// ============================================================================
+// NET WORTH
+// ============================================================================
+
+/// What one account adds to net worth
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetWorthLine {
+    pub account: String,
+    pub account_type: AccountType,
+    pub currency: String,
+    pub value: f64,
+    /// Valued from positions rather than transactions
+    pub from_positions: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetWorth {
+    pub as_of: NaiveDate,
+    pub accounts: Vec<NetWorthLine>,
+    /// Sum of the accounts per currency
+    pub totals: BTreeMap<String, f64>,
+    /// "account SYMBOL" of positions without a price (left out of the totals)
+    pub unpriced: Vec<String>,
+}
+
+/// Net worth of the ledger on `date`: open accounts at the sum of their
+/// transactions, investment accounts (and positions recorded for accounts
+/// the ledger doesn't have) at their positions' market value
+pub fn net_worth(conn: &Connection, ledger_id: &str, prices: &dyn FxRateProvider, date: NaiveDate) -> Result<NetWorth> {
+    let accounts = list_accounts(conn, ledger_id)?;
+    let transactions = ledger_transactions(conn, ledger_id)?;
+    let mut valued = value_positions(&positions_as_of(conn, ledger_id, date)?, prices, date);
+
+    let mut lines = Vec::new();
+    let mut unpriced = Vec::new();
+    let mut add_positions = |lines: &mut Vec<NetWorthLine>, name: &str, kind: AccountType, values: Vec<PositionValue>| {
+        let mut by_currency: BTreeMap<String, f64> = BTreeMap::new();
+        for value in values {
+            match value.market_value {
+                Some(market_value) => *by_currency.entry(value.position.currency).or_default() += market_value,
+                None => unpriced.push(format!("{} {}", name, value.position.symbol)),
+            }
+        }
+        for (currency, value) in by_currency {
+            lines.push(NetWorthLine {
+                account: name.to_string(),
+                account_type: kind.clone(),
+                currency,
+                value,
+                from_positions: true,
+            });
+        }
+    };
+
+    let open = accounts.iter().filter(|account| account.accepts_transaction_on(date));
+    for account in open {
+        let (held, rest): (Vec<PositionValue>, Vec<PositionValue>) =
+            valued.into_iter().partition(|value| value.position.account.eq_ignore_ascii_case(&account.name));
+        valued = rest;
+        if account.account_type == AccountType::Investment && !held.is_empty() {
+            add_positions(&mut lines, &account.name, account.account_type.clone(), held);
+            continue;
+        }
+        let balance = transactions
+            .iter()
+            .filter(|tx| tx.parsed_date().is_some_and(|tx_date| tx_date <= date))
+            .filter(|tx| account_of(&accounts, tx).is_some_and(|booked_on| booked_on.id == account.id))
+            .map(|tx| tx.amount_numeric)
+            .sum();
+        lines.push(NetWorthLine {
+            account: account.name.clone(),
+            account_type: account.account_type.clone(),
+            currency: account.currency.clone(),
+            value: balance,
+            from_positions: false,
+        });
+    }
+
+    // Positions of accounts the ledger has no account entity for
+    let mut orphans: BTreeMap<String, Vec<PositionValue>> = BTreeMap::new();
+    let known = |name: &str| accounts.iter().any(|account| account.name.eq_ignore_ascii_case(name));
+    for value in valued.into_iter().filter(|value| !known(&value.position.account)) {
+        orphans.entry(value.position.account.clone()).or_default().push(value);
+    }
+    for (name, values) in orphans {
+        add_positions(&mut lines, &name, AccountType::Investment, values);
+    }
+
+    let mut totals = BTreeMap::new();
+    for line in &lines {
+        *totals.entry(line.currency.clone()).or_default() += line.value;
+    }
+    Ok(NetWorth { as_of: date, accounts: lines, totals, unpriced })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::set_opening_balance;
+    use crate::db::{get_events_for_entity, setup_database};
+    use crate::entities::Account;
+    use crate::fx::HistoricalRates;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_brokerage_exports() {
+        let fidelity = "\
+Account Number,Account Name,Symbol,Description,Quantity,Last Price,Current Value,Cost Basis Total,Type
+Z123,Individual,SPAXX**,HELD IN MONEY MARKET,,,$1500.00,,Cash
+Z123,Individual,VTI,VANGUARD TOTAL STOCK MKT ETF,10,$250.00,\"$2,500.00\",\"$2,000.00\",Margin
+Z123,Individual,Pending Activity,,,,$-20.00,,
+
+\"Date downloaded Jan-15-2025 9:41 a.m ET\"
+";
+        let statement = parse_positions_csv(fidelity, "Brokerage", "USD", Some(date("2025-01-15"))).unwrap();
+        assert_eq!(statement.broker, "Fidelity");
+        assert_eq!(statement.positions.len(), 2);
+        let cash = &statement.positions[0];
+        assert_eq!((cash.symbol.as_str(), cash.quantity), (CASH_SYMBOL, 1500.0));
+        let vti = &statement.positions[1];
+        assert_eq!((vti.symbol.as_str(), vti.quantity, vti.cost_basis), ("VTI", 10.0, Some(2000.0)));
+        assert_eq!(vti.statement_price, Some(250.0));
+
+        let schwab = r#""Positions for account Individual ...123 as of 09:41 AM ET, 2025/01/15"
+"Symbol","Description","Qty (Quantity)","Price","Mkt Val (Market Value)","Cost Basis"
+"AAPL","APPLE INC","5","$230.00","$1,150.00","$900.00"
+"Cash & Cash Investments","--","--","--","$300.00","--"
+"Account Total","--","--","--","$1,450.00","$900.00"
+"#;
+        let statement = parse_positions_csv(schwab, "Schwab IRA", "USD", None).unwrap();
+        assert_eq!((statement.broker, statement.as_of), ("Schwab", date("2025-01-15")));
+        let symbols: Vec<&str> = statement.positions.iter().map(|p| p.symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["AAPL", CASH_SYMBOL]);
+        assert!(parse_positions_csv("Date,Amount\n", "x", "USD", Some(date("2025-01-15"))).is_err());
+    }
+
+    #[test]
+    fn test_net_worth_values_positions_and_balances() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let checking = Account::new(
+            "Checking".to_string(), String::new(), "BofA".to_string(), AccountType::Checking, "USD".to_string(), 0.0,
+        );
+        set_opening_balance(&conn, checking, 1000.0, date("2025-01-01"), "ana").unwrap();
+        let brokerage = Account::new(
+            "Brokerage".to_string(), String::new(), String::new(), AccountType::Investment, "USD".to_string(), 0.0,
+        );
+        set_opening_balance(&conn, brokerage, 0.0, date("2025-01-01"), "ana").unwrap();
+
+        let holding = |symbol: &str, quantity: f64, as_of: &str| Position {
+            account: "Brokerage".to_string(),
+            symbol: symbol.to_string(),
+            quantity,
+            cost_basis: Some(quantity * 100.0),
+            currency: "USD".to_string(),
+            as_of: date(as_of),
+            statement_price: Some(200.0),
+        };
+        let old = [holding("VTI", 10.0, "2025-01-31"), holding("GME", 3.0, "2025-01-31")];
+        record_positions(&conn, "default", "Brokerage", date("2025-01-31"), &old, "jan.csv", "ana").unwrap();
+        let new = [holding("VTI", 12.0, "2025-02-28"), holding("XYZ", 1.0, "2025-02-28")];
+        record_positions(&conn, "default", "Brokerage", date("2025-02-28"), &new, "feb.csv", "ana").unwrap();
+        assert_eq!(get_events_for_entity(&conn, "positions", "Brokerage").unwrap().len(), 2);
+
+        // The latest snapshot replaces the old one (GME was sold)
+        let mut prices = HistoricalRates::new();
+        prices.insert("VTI", "USD", date("2025-02-01"), 250.0);
+        let worth = net_worth(&conn, "default", &prices, date("2025-03-01")).unwrap();
+        let brokerage_line = worth.accounts.iter().find(|line| line.account == "Brokerage").unwrap();
+        assert!(brokerage_line.from_positions);
+        assert_eq!(brokerage_line.value, 12.0 * 250.0 + 200.0);
+        assert_eq!(worth.totals["USD"], 1000.0 + 3200.0);
+
+        let january_positions = positions_as_of(&conn, "default", date("2025-01-31")).unwrap();
+        let january = value_positions(&january_positions, &prices, date("2025-01-31"));
+        let gme = january.iter().find(|value| value.position.symbol == "GME").unwrap();
+        assert!(gme.statement_priced);
+        assert_eq!(gme.unrealized_gain, Some(300.0));
+    }
+}
@@ -0,0 +1,441 @@
+// 🧳 Projects - Grouping transactions into trips and projects
+//
+// Problem solved:
+// - "How much did the Amsterdam trip cost?" meant filtering by hand and adding up
+// - Categories answer WHAT money went to, not WHICH trip or project it belonged to
+//
+// A project has a name, a date range and optional rules (country, city, text).
+// Transactions are assigned manually or by applying the rules; assignments are
+// keyed by transaction identity (tx_uuid), so corrections keep them.
+
+use crate::db::{get_current_transaction, insert_event, Event, Transaction};
+use crate::event_schema::ProjectAssignmentChanged;
+use crate::location::transaction_location;
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+// ============================================================================
+// TYPES
+// ============================================================================
+
+/// Which transactions in the date range belong to the project
+///
+/// Every rule that is set must match; a project with no rules takes every
+/// transaction in its date range.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectRules {
+    /// ISO alpha-2 country (from the description's location hint)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country_code: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+
+    /// Substring of description or merchant (case-insensitive)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl ProjectRules {
+    pub fn is_empty(&self) -> bool {
+        self.country_code.is_none() && self.city.is_none() && self.text.is_none()
+    }
+
+    pub fn matches(&self, tx: &Transaction) -> bool {
+        let location = transaction_location(tx);
+
+        if let Some(country) = &self.country_code {
+            if !location.as_ref().is_some_and(|l| l.country_code.eq_ignore_ascii_case(country)) {
+                return false;
+            }
+        }
+        if let Some(city) = &self.city {
+            let tx_city = location.as_ref().and_then(|l| l.city.as_deref());
+            if !tx_city.is_some_and(|c| c.eq_ignore_ascii_case(city)) {
+                return false;
+            }
+        }
+        if let Some(text) = &self.text {
+            let text = text.to_lowercase();
+            if !tx.description.to_lowercase().contains(&text) && !tx.merchant.to_lowercase().contains(&text) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Project {
+    pub id: String,
+    pub ledger_id: String,
+
+    /// e.g. "Amsterdam trip Oct 2024"
+    pub name: String,
+
+    /// Inclusive date range
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+
+    #[serde(default)]
+    pub rules: ProjectRules,
+}
+
+impl Project {
+    pub fn new(name: &str, start_date: NaiveDate, end_date: NaiveDate) -> Self {
+        Project {
+            id: uuid::Uuid::new_v4().to_string(),
+            ledger_id: crate::ledger::default_ledger_id(),
+            name: name.to_string(),
+            start_date,
+            end_date,
+            rules: ProjectRules::default(),
+        }
+    }
+
+    /// Place this project in a ledger (builder)
+    pub fn in_ledger(mut self, ledger_id: &str) -> Self {
+        self.ledger_id = ledger_id.to_string();
+        self
+    }
+
+    /// Set the assignment rules (builder)
+    pub fn with_rules(mut self, rules: ProjectRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn covers(&self, date: NaiveDate) -> bool {
+        self.start_date <= date && date <= self.end_date
+    }
+
+    /// In the date range, in the ledger, and matching every rule
+    pub fn matches(&self, tx: &Transaction) -> bool {
+        tx.ledger_id == self.ledger_id
+            && tx.parsed_date().is_some_and(|date| self.covers(date))
+            && self.rules.matches(tx)
+    }
+}
+
+/// How a transaction came to belong to a project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssignmentMethod {
+    Manual,
+    Rule,
+}
+
+impl AssignmentMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AssignmentMethod::Manual => "manual",
+            AssignmentMethod::Rule => "rule",
+        }
+    }
+}
+
+// ============================================================================
+// PROJECTS
+// ============================================================================
+
+pub fn create_project(conn: &Connection, project: &Project, actor: &str) -> Result<()> {
+    if project.name.trim().is_empty() {
+        return Err(anyhow!("Project name must not be empty"));
+    }
+    if project.end_date < project.start_date {
+        return Err(anyhow!(
+            "Project '{}' ends ({}) before it starts ({})",
+            project.name,
+            project.end_date,
+            project.start_date
+        ));
+    }
+
+    conn.execute(
+        "INSERT INTO projects (id, ledger_id, name, start_date, end_date, rules, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            project.id,
+            project.ledger_id,
+            project.name,
+            project.start_date.to_string(),
+            project.end_date.to_string(),
+            serde_json::to_string(&project.rules)?,
+            Utc::now().to_rfc3339(),
+        ],
+    )?;
+
+    let event = Event::typed("project_created", "project", &project.id, project, actor)?.with_ledger(&project.ledger_id);
+    insert_event(conn, &event)
+}
+
+const PROJECT_COLUMNS: &str = "id, ledger_id, name, start_date, end_date, rules";
+
+pub fn get_project(conn: &Connection, project_id: &str) -> Result<Option<Project>> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT {} FROM projects WHERE id = ?1", PROJECT_COLUMNS),
+            [project_id],
+            row_to_project,
+        )
+        .optional()?)
+}
+
+/// Projects in a ledger, most recent first
+pub fn list_projects(conn: &Connection, ledger_id: &str) -> Result<Vec<Project>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM projects WHERE ledger_id = ?1 ORDER BY start_date DESC, name",
+        PROJECT_COLUMNS
+    ))?;
+    let projects = stmt.query_map([ledger_id], row_to_project)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(projects)
+}
+
+fn row_to_project(row: &rusqlite::Row) -> rusqlite::Result<Project> {
+    let start: String = row.get(3)?;
+    let end: String = row.get(4)?;
+    let rules: String = row.get(5)?;
+    let parse_date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap_or_default();
+
+    Ok(Project {
+        id: row.get(0)?,
+        ledger_id: row.get(1)?,
+        name: row.get(2)?,
+        start_date: parse_date(&start),
+        end_date: parse_date(&end),
+        rules: serde_json::from_str(&rules).unwrap_or_default(),
+    })
+}
+
+// ============================================================================
+// ASSIGNMENTS
+// ============================================================================
+
+/// Assign a transaction to a project by hand (any date)
+pub fn assign_transaction(conn: &Connection, project_id: &str, tx_uuid: &str, actor: &str) -> Result<()> {
+    let project = get_project(conn, project_id)?
+        .ok_or_else(|| anyhow!("Project {} not found", project_id))?;
+    let tx = get_current_transaction(conn, tx_uuid)?
+        .ok_or_else(|| anyhow!("Transaction {} not found", tx_uuid))?;
+    if tx.ledger_id != project.ledger_id {
+        return Err(anyhow!(
+            "Transaction {} is in ledger '{}', project '{}' is in '{}'",
+            tx_uuid,
+            tx.ledger_id,
+            project.name,
+            project.ledger_id
+        ));
+    }
+
+    insert_assignment(conn, &project, tx_uuid, AssignmentMethod::Manual, actor)?;
+    Ok(())
+}
+
+/// Remove a transaction from a project
+pub fn unassign_transaction(conn: &Connection, project_id: &str, tx_uuid: &str, actor: &str) -> Result<bool> {
+    let project = get_project(conn, project_id)?
+        .ok_or_else(|| anyhow!("Project {} not found", project_id))?;
+    let removed = conn.execute(
+        "DELETE FROM project_transactions WHERE project_id = ?1 AND tx_uuid = ?2",
+        params![project_id, tx_uuid],
+    )?;
+    if removed > 0 {
+        log_assignment_event(conn, "project_transaction_removed", &project, tx_uuid, None, actor)?;
+    }
+    Ok(removed > 0)
+}
+
+/// Assign every matching transaction not yet in the project
+///
+/// Returns how many were added. Manual removals are not remembered: running
+/// the rules again re-adds a removed transaction that still matches.
+pub fn apply_project_rules(
+    conn: &Connection,
+    project_id: &str,
+    transactions: &[Transaction],
+    actor: &str,
+) -> Result<usize> {
+    let project = get_project(conn, project_id)?
+        .ok_or_else(|| anyhow!("Project {} not found", project_id))?;
+    let assigned: HashSet<String> = project_transaction_ids(conn, project_id)?.into_iter().collect();
+
+    let mut added = 0;
+    for tx in transactions.iter().filter(|tx| tx.is_active() && project.matches(tx)) {
+        if !assigned.contains(&tx.id) && insert_assignment(conn, &project, &tx.id, AssignmentMethod::Rule, actor)? {
+            added += 1;
+        }
+    }
+    Ok(added)
+}
+
+fn insert_assignment(
+    conn: &Connection,
+    project: &Project,
+    tx_uuid: &str,
+    method: AssignmentMethod,
+    actor: &str,
+) -> Result<bool> {
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO project_transactions (project_id, tx_uuid, method, assigned_by, assigned_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![project.id, tx_uuid, method.as_str(), actor, Utc::now().to_rfc3339()],
+    )?;
+    if inserted > 0 {
+        log_assignment_event(conn, "project_transaction_added", project, tx_uuid, Some(method), actor)?;
+    }
+    Ok(inserted > 0)
+}
+
+fn log_assignment_event(
+    conn: &Connection,
+    event_type: &str,
+    project: &Project,
+    tx_uuid: &str,
+    method: Option<AssignmentMethod>,
+    actor: &str,
+) -> Result<()> {
+    let payload = ProjectAssignmentChanged { tx_uuid: tx_uuid.to_string(), method };
+    let event = Event::typed(event_type, "project", &project.id, &payload, actor)?.with_ledger(&project.ledger_id);
+    insert_event(conn, &event)
+}
+
+/// Transaction UUIDs assigned to a project
+pub fn project_transaction_ids(conn: &Connection, project_id: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT tx_uuid FROM project_transactions WHERE project_id = ?1 ORDER BY assigned_at",
+    )?;
+    let ids = stmt.query_map([project_id], |row| row.get(0))?.collect::<Result<Vec<_>, _>>()?;
+    Ok(ids)
+}
+
+// ============================================================================
+// REPORT
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectReport {
+    pub project: Project,
+    pub transactions: Vec<Transaction>,
+
+    /// Net spend per currency (positive = money out)
+    pub spend: BTreeMap<String, f64>,
+
+    /// Net spend per category (all currencies added together)
+    pub by_category: BTreeMap<String, f64>,
+}
+
+/// Spend for a project's (current, non-voided) transactions
+pub fn project_report(conn: &Connection, project_id: &str) -> Result<ProjectReport> {
+    let project = get_project(conn, project_id)?
+        .ok_or_else(|| anyhow!("Project {} not found", project_id))?;
+
+    let mut transactions = Vec::new();
+    for tx_uuid in project_transaction_ids(conn, project_id)? {
+        if let Some(tx) = get_current_transaction(conn, &tx_uuid)?.filter(|tx| !tx.is_voided()) {
+            transactions.push(tx);
+        }
+    }
+    transactions.sort_by_key(|tx| tx.parsed_date());
+
+    let mut spend = BTreeMap::new();
+    let mut by_category = BTreeMap::new();
+    for tx in &transactions {
+        *spend.entry(tx.currency.clone()).or_insert(0.0) -= tx.amount_numeric;
+        *by_category.entry(tx.category.clone()).or_insert(0.0) -= tx.amount_numeric;
+    }
+
+    Ok(ProjectReport { project, transactions, spend, by_category })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::db::{insert_transactions_as, setup_database, void_transaction};
+
+    fn charge(date: &str, description: &str, amount: f64, category: &str) -> Transaction {
+        let mut tx = Transaction {
+            date: date.to_string(),
+            description: description.to_string(),
+            amount_original: format!("{:.2}", amount),
+            amount_numeric: amount,
+            category: category.to_string(),
+            currency: "EUR".to_string(),
+            account_name: "Apple Card".to_string(),
+            bank: "AppleCard".to_string(),
+            source_file: "applecard.csv".to_string(),
+            ..test_support::sample_transaction()
+        };
+        tx.init_temporal_fields();
+        tx
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn setup() -> (Connection, Vec<Transaction>) {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let transactions = vec![
+            charge("10/03/2024", "KLM TICKET OFFICE AMSTERDAM NLD", -450.0, "Travel"),
+            charge("10/04/2024", "CAFE DE REGT AMSTERDAM NLD", -4.5, "Restaurants"),
+            charge("10/05/2024", "HEMA UTRECHT NLD", -12.0, "Shopping"),
+            charge("10/04/2024", "NETFLIX.COM", -15.49, "Subscriptions"),
+            charge("11/20/2024", "CAFE DE REGT AMSTERDAM NLD", -6.0, "Restaurants"),
+        ];
+        insert_transactions_as(&conn, &transactions, "test").unwrap();
+        (conn, transactions)
+    }
+
+    #[test]
+    fn test_rules_assign_by_date_and_location() {
+        let (conn, transactions) = setup();
+        let project = Project::new("Amsterdam trip Oct 2024", date("2024-10-01"), date("2024-10-10"))
+            .with_rules(ProjectRules { country_code: Some("NL".to_string()), ..ProjectRules::default() });
+        create_project(&conn, &project, "ana").unwrap();
+
+        assert_eq!(apply_project_rules(&conn, &project.id, &transactions, "ana").unwrap(), 3);
+        // Idempotent
+        assert_eq!(apply_project_rules(&conn, &project.id, &transactions, "ana").unwrap(), 0);
+
+        let report = project_report(&conn, &project.id).unwrap();
+        assert_eq!(report.transactions.len(), 3);
+        assert!((report.spend["EUR"] - 466.5).abs() < 0.001);
+        assert!((report.by_category["Travel"] - 450.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_manual_assignment_and_voids() {
+        let (conn, transactions) = setup();
+        let project = Project::new("Side project", date("2024-01-01"), date("2024-12-31"))
+            .with_rules(ProjectRules { text: Some("nothing matches this".to_string()), ..ProjectRules::default() });
+        create_project(&conn, &project, "ana").unwrap();
+
+        let netflix = &transactions[3];
+        assign_transaction(&conn, &project.id, &netflix.id, "ana").unwrap();
+        assign_transaction(&conn, &project.id, &transactions[0].id, "ana").unwrap();
+        assert_eq!(project_report(&conn, &project.id).unwrap().transactions.len(), 2);
+
+        void_transaction(&conn, &transactions[0].id, "duplicate", "ana").unwrap();
+        assert_eq!(project_report(&conn, &project.id).unwrap().transactions.len(), 1);
+
+        assert!(unassign_transaction(&conn, &project.id, &netflix.id, "ana").unwrap());
+        assert!(project_report(&conn, &project.id).unwrap().transactions.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_projects_are_rejected() {
+        let (conn, _) = setup();
+        let backwards = Project::new("Backwards", date("2024-10-10"), date("2024-10-01"));
+        assert!(create_project(&conn, &backwards, "ana").is_err());
+        assert!(list_projects(&conn, "default").unwrap().is_empty());
+    }
+}
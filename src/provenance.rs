@@ -0,0 +1,305 @@
+// 🔗 Provenance - From a report number back to the bank's raw line
+//
+// Problem solved:
+// - "Where does this $1,240 of Groceries come from?" took four lookups: the
+//   report, the transaction's versions, its metadata, then the archived file
+// - The TUI and the API each showed a different slice of that chain (source
+//   file and line here, history there) and neither reached the import session
+//
+// `trace` walks the whole chain for one transaction and returns it as a single
+// serializable value: the report lines it adds to, its versions, how it was
+// normalized (the `transformation_log` written at import), which component set
+// each tracked field of the current version (field_provenance.rs), the raw line
+// in the archived file, that file's hash, and the import session that inserted
+// it.
+// Links that don't exist (rows from before archiving, voided rows that count
+// in no report) are simply None or empty.
+
+use crate::archive::transaction_provenance;
+use crate::db::{get_events_for_entity, get_transaction_history, Transaction};
+use crate::field_provenance::{field_origins, FieldOrigin};
+use crate::imports::IMPORT_SESSION_KEY;
+use crate::jobs::ledger_transactions;
+use crate::ledger::require_ledger;
+use crate::reports::{summarize_by_fiscal_year, summarize_by_period, Period, PeriodSummary, ReportCalendar};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+// ============================================================================
+// TRACE
+// ============================================================================
+
+/// A report line the transaction is counted in
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportAggregate {
+    /// "period" or "fiscal_year"
+    pub report: String,
+    pub period: Period,
+    /// "expenses / Groceries", "income", "card payments", "transfers"
+    pub line: String,
+    /// The line's total in that report
+    pub total: f64,
+    /// What this transaction contributes to it (positive)
+    pub amount: f64,
+}
+
+/// One version of the transaction, oldest first
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionStep {
+    pub version: i64,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_until: Option<DateTime<Utc>>,
+    pub change_reason: Option<String>,
+    pub category: String,
+    pub merchant: String,
+    pub amount: f64,
+    pub voided: bool,
+}
+
+/// How the raw row became the first version
+#[derive(Debug, Clone, Serialize)]
+pub struct NormalizationSteps {
+    pub parser_version: Option<String>,
+    pub extracted_at: Option<String>,
+    /// The `transformation_log` written at import
+    pub steps: Vec<String>,
+    /// Classification notes of the first version ("rule coffee", "MCC")
+    pub classification: String,
+}
+
+/// The row as the bank wrote it
+#[derive(Debug, Clone, Serialize)]
+pub struct RawLine {
+    pub line: Option<usize>,
+    /// CSV header of the file, for context
+    pub header: Option<String>,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceFile {
+    pub filename: String,
+    pub sha256: Option<String>,
+    pub size: Option<usize>,
+    /// Whether the archive holds (a verified copy of) the file
+    pub archived: bool,
+}
+
+/// The import that inserted the transaction
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportSessionRef {
+    pub id: String,
+    pub actor: Option<String>,
+    pub imported_at: Option<DateTime<Utc>>,
+    /// The session's import event data (rows, inserted, duplicates, ...)
+    pub details: Value,
+}
+
+/// The full lineage of a transaction
+#[derive(Debug, Clone, Serialize)]
+pub struct ProvenanceTrace {
+    pub transaction_id: String,
+    pub ledger_id: String,
+    pub aggregates: Vec<ReportAggregate>,
+    pub versions: Vec<VersionStep>,
+    pub normalization: NormalizationSteps,
+    /// Who set merchant, category, type and amount of the current version
+    pub fields: BTreeMap<String, FieldOrigin>,
+    pub raw_line: RawLine,
+    pub source_file: SourceFile,
+    pub import_session: Option<ImportSessionRef>,
+}
+
+fn text(tx: &Transaction, key: &str) -> Option<String> {
+    tx.metadata.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+/// The line of a summary `tx` adds to, with that line's total
+fn report_line(summary: &PeriodSummary, tx: &Transaction) -> Option<(String, f64)> {
+    match tx.transaction_type.as_str() {
+        "GASTO" => Some((
+            format!("expenses / {}", tx.category),
+            summary.by_category.get(&tx.category).copied().unwrap_or_default(),
+        )),
+        "INGRESO" => Some(("income".to_string(), summary.income)),
+        "PAGO_TARJETA" => Some(("card payments".to_string(), summary.card_payments)),
+        "TRASPASO" => Some(("transfers".to_string(), summary.transfers)),
+        _ => None,
+    }
+}
+
+/// Report lines of the transaction's ledger that include `tx`
+fn aggregates(conn: &Connection, tx: &Transaction) -> Result<Vec<ReportAggregate>> {
+    let Some(date) = tx.parsed_date().filter(|_| !tx.is_voided()) else {
+        return Ok(Vec::new());
+    };
+    let calendar = ReportCalendar::from_ledger_config(&require_ledger(conn, &tx.ledger_id)?.config);
+    let transactions = ledger_transactions(conn, &tx.ledger_id)?;
+
+    let mut aggregates = Vec::new();
+    for (report, summaries) in [
+        ("period", summarize_by_period(&transactions, &calendar)),
+        ("fiscal_year", summarize_by_fiscal_year(&transactions, &calendar)),
+    ] {
+        let Some(summary) = summaries.iter().find(|summary| summary.period.contains(date)) else {
+            continue;
+        };
+        if let Some((line, total)) = report_line(summary, tx) {
+            aggregates.push(ReportAggregate {
+                report: report.to_string(),
+                period: summary.period.clone(),
+                line,
+                total,
+                amount: tx.amount_numeric.abs(),
+            });
+        }
+    }
+    Ok(aggregates)
+}
+
+fn import_session(conn: &Connection, tx: &Transaction) -> Result<Option<ImportSessionRef>> {
+    let Some(id) = text(tx, IMPORT_SESSION_KEY) else {
+        return Ok(None);
+    };
+    let event = get_events_for_entity(conn, "import", &id)?
+        .into_iter()
+        .find(|event| event.event_type == "import_session");
+    Ok(Some(ImportSessionRef {
+        actor: event.as_ref().map(|event| event.actor.clone()),
+        imported_at: event.as_ref().map(|event| event.timestamp),
+        details: event.map(|event| event.data).unwrap_or(Value::Null),
+        id,
+    }))
+}
+
+/// Lineage of a transaction, from the reports it counts in back to its raw
+/// line (None if the id is unknown)
+pub fn trace(conn: &Connection, tx_id: &str) -> Result<Option<ProvenanceTrace>> {
+    let Some(provenance) = transaction_provenance(conn, tx_id)? else {
+        return Ok(None);
+    };
+    let current = &provenance.transaction;
+    let history = get_transaction_history(conn, tx_id)?;
+    let first = history.first().unwrap_or(current);
+
+    let versions = history
+        .iter()
+        .map(|tx| VersionStep {
+            version: tx.version,
+            valid_from: tx.valid_from,
+            valid_until: tx.valid_until,
+            change_reason: text(tx, "change_reason"),
+            category: tx.category.clone(),
+            merchant: tx.merchant.clone(),
+            amount: tx.amount_numeric,
+            voided: tx.is_voided(),
+        })
+        .collect();
+    let normalization = NormalizationSteps {
+        parser_version: text(first, "parser_version"),
+        extracted_at: text(first, "extracted_at"),
+        steps: first
+            .metadata
+            .get("transformation_log")
+            .and_then(Value::as_array)
+            .map(|steps| steps.iter().filter_map(Value::as_str).map(str::to_string).collect())
+            .unwrap_or_default(),
+        classification: first.classification_notes.clone(),
+    };
+
+    Ok(Some(ProvenanceTrace {
+        transaction_id: current.id.clone(),
+        ledger_id: current.ledger_id.clone(),
+        aggregates: aggregates(conn, current)?,
+        versions,
+        normalization,
+        fields: field_origins(current),
+        raw_line: RawLine {
+            line: provenance.source_line,
+            header: provenance.header.clone(),
+            text: provenance.excerpt.clone(),
+        },
+        source_file: SourceFile {
+            filename: provenance.source_file.clone(),
+            sha256: provenance.source_sha256.clone(),
+            size: provenance.archived.as_ref().map(|source| source.size),
+            archived: provenance.archived.is_some(),
+        },
+        import_session: import_session(conn, current)?,
+    }))
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_transaction_version, setup_database};
+    use crate::imports::import_statement;
+
+    const BOFA: &str = "Date,Description,Amount\n\
+        01/02/2025,STARBUCKS STORE 123,-5.25\n\
+        01/03/2025,SHELL OIL 5521,-40.00\n\
+        01/09/2025,SHELL OIL 5521,-20.00\n";
+
+    fn imported() -> (Connection, Vec<Transaction>) {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        import_statement(&conn, "bofa_jan.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+        let transactions = ledger_transactions(&conn, "default").unwrap();
+        (conn, transactions)
+    }
+
+    #[test]
+    fn test_trace_reaches_raw_line_and_import_session() {
+        let (conn, transactions) = imported();
+        let shell = transactions.iter().find(|tx| tx.line_number == "3").unwrap();
+        let trace = trace(&conn, &shell.id).unwrap().unwrap();
+        let json = serde_json::to_value(&trace).unwrap();
+        assert_eq!(json["raw_line"]["line"], 3);
+
+        assert_eq!(trace.raw_line.text.as_deref(), Some("01/03/2025,SHELL OIL 5521,-40.00"));
+        assert_eq!(trace.raw_line.header.as_deref(), Some("Date,Description,Amount"));
+        assert!(trace.source_file.archived);
+        assert_eq!(trace.source_file.filename, "bofa_jan.csv");
+
+        let session = trace.import_session.unwrap();
+        assert_eq!(session.actor.as_deref(), Some("ana"));
+        assert_eq!(session.details["inserted"], 3);
+
+        assert!(trace.normalization.parser_version.is_some());
+        assert!(trace.normalization.steps.iter().any(|step| step.contains("-40.00")));
+        assert_eq!(trace.versions.len(), 1);
+        assert!(super::trace(&conn, "nope").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_trace_aggregates_follow_corrections() {
+        let (conn, transactions) = imported();
+        let shell = transactions.iter().find(|tx| tx.line_number == "3").unwrap();
+        let before = trace(&conn, &shell.id).unwrap().unwrap();
+        let line = before.aggregates.iter().find(|a| a.report == "period").unwrap();
+        assert!(line.line.starts_with("expenses / "));
+        assert_eq!(line.amount, 40.0);
+        assert!(before.aggregates.iter().any(|a| a.report == "fiscal_year"));
+
+        let mut corrected = shell.next_version(Some("recategorized".to_string()));
+        corrected.category = "Travel".to_string();
+        insert_transaction_version(&conn, &corrected, "ana").unwrap();
+
+        let after = trace(&conn, &shell.id).unwrap().unwrap();
+        assert_eq!(after.versions.len(), 2);
+        assert_eq!(after.versions[1].change_reason.as_deref(), Some("recategorized"));
+        let period = after.aggregates.iter().find(|a| a.report == "period").unwrap();
+        assert_eq!((period.line.as_str(), period.total), ("expenses / Travel", 40.0));
+        assert_eq!(after.fields["category"].source.label(), "corrected by ana");
+        // Normalization still describes the import, not the correction
+        assert_eq!(after.normalization.steps, before.normalization.steps);
+    }
+}
@@ -0,0 +1,693 @@
+// 🧹 Purge - Remove or pseudonymize everything held about one counterparty
+//
+// Problem solved:
+// - A request to erase a merchant or person ("delete everything you hold on
+//   me") had no answer short of hand-written SQL: the name sits in every
+//   version of its transactions, in event payloads, notes, disputes, the
+//   merchant registry and the enrichment cache
+// - Deleting rows by hand silently changed monthly totals and checksums, and
+//   left nothing saying why
+//
+// `plan_purge` finds the counterparty: every transaction identity of the
+// ledger whose merchant or description (any version) is one of its spellings
+// - the name given plus, when it is a registered merchant, its canonical name
+// and aliases - compared after merchant normalization, or a description that
+// starts with one ("starbucks store 12"). `purge_counterparty` then, in one
+// database transaction:
+//
+// - Pseudonymize: rewrites every version's merchant and description to a
+//   random purge id ("purged-1a2b3c4d"), scrubs the spellings out of notes,
+//   metadata, pending changes, bill names and event payloads, and recomputes
+//   idempotency hashes (re-keying their `transaction_added` events). Amounts
+//   and dates are untouched, so totals, manifests and closed periods still
+//   verify.
+// - Remove: deletes every version, the identities' events, notes, disputes,
+//   pending changes and project / bill links, and scrubs the rest. Refused
+//   while a matched row sits in a closed period (reopen it first).
+//
+// Both drop the merchant's registry versions (pseudonymized: renamed and
+// stripped of aliases), its enrichment cache entries and alias decisions
+// naming it. A `counterparty_purged` event records the mode, the counts, the
+// manifest entries (per bank and month) whose count, total or checksum
+// changed, and the event chain head before and after the rewrite. It never
+// records the name. Import recordings (replay.rs) and import jobs
+// (job_queue.rs) that hold a spelling in their file or outcome are deleted
+// on removal and scrubbed when pseudonymizing. Archived statement files
+// (archive.rs) are hash-addressed and still hold the original lines; the
+// report lists them.
+
+use crate::archive::{content_sha256, SOURCE_HASH_KEY};
+use crate::db::{insert_event, row_to_transaction, Event, Transaction, TRANSACTION_SELECT_COLUMNS};
+use crate::entities::merchant::normalize_merchant_string;
+use crate::entities::{load_merchants, load_versions, save_versions, Merchant};
+use crate::manifest::{ledger_manifest, ManifestEntry};
+use crate::period_close::closed_periods;
+use crate::system_archive::event_chain_head;
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Spellings shorter than this are never scrubbed out of free text
+const MIN_SCRUB_LEN: usize = 3;
+
+/// Tables tied to a transaction identity, and their free-text columns
+const RELATED_TABLES: &[(&str, &[&str])] = &[
+    ("transaction_notes", &["text"]),
+    ("disputes", &["note"]),
+    ("pending_changes", &["proposed", "decision_note"]),
+    ("bill_price_changes", &["bill_name"]),
+    ("project_transactions", &[]),
+];
+
+// ============================================================================
+// PLAN
+// ============================================================================
+
+/// What to do with the counterparty's data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PurgeMode {
+    /// Replace the name everywhere, keep the amounts
+    Pseudonymize,
+    /// Delete the rows and everything tied to them
+    Remove,
+}
+
+impl PurgeMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PurgeMode::Pseudonymize => "pseudonymize",
+            PurgeMode::Remove => "remove",
+        }
+    }
+}
+
+/// What a purge of one counterparty would touch
+#[derive(Debug, Clone, Serialize)]
+pub struct PurgePlan {
+    pub counterparty: String,
+    /// Registered merchant the name resolved to (its spellings are matched too)
+    pub merchant_id: Option<String>,
+    /// Normalized spellings a merchant or description is compared with
+    pub names: Vec<String>,
+    pub tx_uuids: Vec<String>,
+    /// Stored versions of those transactions
+    pub versions: usize,
+    /// Net of their current versions (what a removal takes out of the totals)
+    pub total: f64,
+    /// Closed periods holding a matched row ("<account> <period>")
+    pub closed_periods: Vec<String>,
+    /// SHA-256 of archived statement files the rows came from
+    pub archived_sources: Vec<String>,
+    /// Raw strings scrubbed from free text, lowercase, longest first
+    #[serde(skip)]
+    needles: Vec<String>,
+    /// Every stored version of those transactions, with its rowid
+    #[serde(skip)]
+    rows: Vec<(i64, Transaction)>,
+}
+
+impl PurgePlan {
+    pub fn is_empty(&self) -> bool {
+        self.tx_uuids.is_empty()
+    }
+}
+
+/// Find everything stored about `counterparty` in a ledger
+pub fn plan_purge(conn: &Connection, ledger_id: &str, counterparty: &str) -> Result<PurgePlan> {
+    let wanted = normalize_merchant_string(counterparty);
+    if wanted.is_empty() {
+        return Err(anyhow!("Counterparty name must not be empty"));
+    }
+    let merchant = load_merchants(conn)?
+        .all_merchants()
+        .into_iter()
+        .find(|m| m.all_names().iter().any(|name| normalize_merchant_string(name) == wanted));
+
+    let mut names: BTreeSet<String> = BTreeSet::from([wanted]);
+    let mut raw: BTreeSet<String> = BTreeSet::from([counterparty.trim().to_string()]);
+    if let Some(merchant) = &merchant {
+        names.extend(merchant.all_names().iter().map(|name| normalize_merchant_string(name)));
+        raw.extend(merchant.all_names());
+    }
+    names.remove("");
+
+    let matches = |text: &str| {
+        let text = normalize_merchant_string(text);
+        names.iter().any(|name| text == *name || text.starts_with(&format!("{} ", name)))
+    };
+    let ledger_rows = ledger_rows(conn, ledger_id)?;
+    let tx_uuids: BTreeSet<String> = ledger_rows
+        .iter()
+        .filter(|(_, tx)| matches(&tx.merchant) || matches(&tx.description))
+        .map(|(_, tx)| tx.id.clone())
+        .collect();
+    let rows: Vec<(i64, Transaction)> =
+        ledger_rows.into_iter().filter(|(_, tx)| tx_uuids.contains(&tx.id)).collect();
+
+    let current: Vec<&Transaction> = rows.iter().map(|(_, tx)| tx).filter(|tx| tx.valid_until.is_none()).collect();
+    let closed = closed_periods(conn, ledger_id)?
+        .into_iter()
+        .filter(|period| rows.iter().any(|(_, tx)| period.covers(tx)))
+        .map(|period| format!("{} {}", period.account_id, period.statement_period))
+        .collect();
+    let archived_sources: BTreeSet<String> = rows
+        .iter()
+        .filter_map(|(_, tx)| tx.metadata.get(SOURCE_HASH_KEY).and_then(|hash| hash.as_str()))
+        .map(str::to_string)
+        .collect();
+    for (_, tx) in &rows {
+        raw.insert(tx.merchant.clone());
+        raw.insert(tx.description.clone());
+    }
+    let mut needles: Vec<String> = raw
+        .into_iter()
+        .map(|text| text.trim().to_ascii_lowercase())
+        .filter(|text| text.len() >= MIN_SCRUB_LEN)
+        .collect();
+    needles.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+    needles.dedup();
+
+    Ok(PurgePlan {
+        counterparty: counterparty.trim().to_string(),
+        merchant_id: merchant.map(|m| m.id),
+        names: names.into_iter().collect(),
+        tx_uuids: tx_uuids.into_iter().collect(),
+        versions: rows.len(),
+        total: current.iter().map(|tx| tx.amount_numeric).sum(),
+        closed_periods: closed,
+        archived_sources: archived_sources.into_iter().collect(),
+        needles,
+        rows,
+    })
+}
+
+/// Every stored version in the ledger, with its rowid
+fn ledger_rows(conn: &Connection, ledger_id: &str) -> Result<Vec<(i64, Transaction)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, id FROM transactions WHERE ledger_id = ?1 ORDER BY id",
+        TRANSACTION_SELECT_COLUMNS
+    ))?;
+    let rows = stmt
+        .query_map([ledger_id], |row| Ok((row.get(22)?, row_to_transaction(row)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+// ============================================================================
+// PURGE
+// ============================================================================
+
+/// One manifest entry (bank and month) changed by a purge
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChecksumChange {
+    pub bank: String,
+    pub period: String,
+    /// None when the bank had no rows that month before / after
+    pub before: Option<ManifestEntry>,
+    pub after: Option<ManifestEntry>,
+}
+
+/// What a purge did; also the payload of its `counterparty_purged` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PurgeReport {
+    /// Stands in for the counterparty from now on; the event's entity id
+    pub purge_id: String,
+    pub mode: PurgeMode,
+    pub transactions: usize,
+    pub versions: usize,
+    pub events_removed: usize,
+    pub events_scrubbed: usize,
+    /// Notes, disputes, pending changes, project and bill links
+    pub related_rows: usize,
+    /// Registry versions, enrichment cache entries and alias decisions
+    pub entity_rows: usize,
+    /// Import recordings and queued import jobs holding the raw lines
+    #[serde(default)]
+    pub import_inputs: usize,
+    pub hashes_recomputed: usize,
+    pub checksums: Vec<ChecksumChange>,
+    pub event_chain_before: String,
+    pub event_chain_after: String,
+    pub archived_sources: Vec<String>,
+}
+
+/// Remove or pseudonymize everything `plan_purge` finds, and record it
+pub fn purge_counterparty(
+    conn: &Connection,
+    ledger_id: &str,
+    counterparty: &str,
+    mode: PurgeMode,
+    actor: &str,
+) -> Result<PurgeReport> {
+    let plan = plan_purge(conn, ledger_id, counterparty)?;
+    if plan.is_empty() {
+        return Err(anyhow!("Nothing in ledger '{}' matches '{}'", ledger_id, plan.counterparty));
+    }
+    if mode == PurgeMode::Remove && !plan.closed_periods.is_empty() {
+        return Err(anyhow!(
+            "Rows to remove are in closed periods ({}): reopen them first, or pseudonymize",
+            plan.closed_periods.join(", ")
+        ));
+    }
+
+    let purge_id = format!("purged-{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    let scrubber = Scrubber { needles: &plan.needles, replacement: &purge_id };
+    let db_tx = conn.unchecked_transaction()?;
+    let manifest_before = ledger_manifest(&db_tx, ledger_id)?;
+    let event_chain_before = event_chain_head(&db_tx)?.sha256;
+
+    let mut report = PurgeReport {
+        purge_id: purge_id.clone(),
+        mode,
+        transactions: plan.tx_uuids.len(),
+        versions: plan.versions,
+        events_removed: 0,
+        events_scrubbed: 0,
+        related_rows: 0,
+        entity_rows: 0,
+        import_inputs: 0,
+        hashes_recomputed: 0,
+        checksums: Vec::new(),
+        event_chain_before,
+        event_chain_after: String::new(),
+        archived_sources: plan.archived_sources.clone(),
+    };
+
+    // Transactions, and the events keyed by them (transaction_added uses the hash)
+    let mut rekeyed: BTreeMap<String, String> = BTreeMap::new();
+    for (rowid, tx) in &plan.rows {
+        let old_hash: String =
+            db_tx.query_row("SELECT idempotency_hash FROM transactions WHERE id = ?1", [rowid], |row| row.get(0))?;
+        match mode {
+            PurgeMode::Remove => {
+                db_tx.execute("DELETE FROM transactions WHERE id = ?1", [rowid])?;
+                report.events_removed += db_tx.execute(
+                    "DELETE FROM events WHERE ledger_id = ?1 AND entity_id = ?2",
+                    params![ledger_id, old_hash],
+                )?;
+            }
+            PurgeMode::Pseudonymize => {
+                let mut tx = tx.clone();
+                tx.merchant = purge_id.clone();
+                tx.description = purge_id.clone();
+                tx.classification_notes = scrubber.scrub(&tx.classification_notes);
+                for value in tx.metadata.values_mut() {
+                    scrubber.scrub_value(value);
+                }
+                let new_hash = tx.compute_idempotency_hash();
+                db_tx.execute(
+                    "UPDATE transactions
+                     SET merchant = ?1, description = ?2, classification_notes = ?3, metadata = ?4,
+                         idempotency_hash = ?5
+                     WHERE id = ?6",
+                    params![
+                        tx.merchant,
+                        tx.description,
+                        tx.classification_notes,
+                        serde_json::to_string(&tx.metadata)?,
+                        new_hash,
+                        rowid
+                    ],
+                )?;
+                report.hashes_recomputed += 1;
+                rekeyed.insert(old_hash, new_hash);
+            }
+        }
+    }
+    for (old_hash, new_hash) in &rekeyed {
+        db_tx.execute(
+            "UPDATE events SET entity_id = ?1 WHERE ledger_id = ?2 AND entity_id = ?3",
+            params![new_hash, ledger_id, old_hash],
+        )?;
+    }
+
+    for tx_uuid in &plan.tx_uuids {
+        if mode == PurgeMode::Remove {
+            report.events_removed += db_tx.execute(
+                "DELETE FROM events WHERE ledger_id = ?1 AND entity_id = ?2",
+                params![ledger_id, tx_uuid],
+            )?;
+        }
+        for (table, columns) in RELATED_TABLES {
+            report.related_rows += match mode {
+                PurgeMode::Remove => db_tx.execute(&format!("DELETE FROM {} WHERE tx_uuid = ?1", table), [tx_uuid])?,
+                PurgeMode::Pseudonymize => scrub_rows(&db_tx, table, columns, tx_uuid, &scrubber)?,
+            };
+        }
+    }
+    report.events_scrubbed = scrub_events(&db_tx, ledger_id, &scrubber)?;
+    report.entity_rows = purge_entities(&db_tx, ledger_id, &plan, mode, &scrubber)?;
+    report.import_inputs = purge_import_inputs(&db_tx, ledger_id, mode, &scrubber)?;
+
+    report.checksums = checksum_changes(&manifest_before, &ledger_manifest(&db_tx, ledger_id)?);
+    report.event_chain_after = event_chain_head(&db_tx)?.sha256;
+    let event = Event::typed("counterparty_purged", "counterparty", &purge_id, &report, actor)?;
+    insert_event(&db_tx, &event.with_ledger(ledger_id))?;
+    db_tx.commit()?;
+    Ok(report)
+}
+
+/// Scrub the free-text columns of one identity's rows in `table`
+fn scrub_rows(conn: &Connection, table: &str, columns: &[&str], tx_uuid: &str, scrubber: &Scrubber) -> Result<usize> {
+    let mut changed = 0;
+    for column in columns {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT rowid, {} FROM {} WHERE tx_uuid = ?1 AND {} IS NOT NULL",
+            column, table, column
+        ))?;
+        let rows = stmt
+            .query_map([tx_uuid], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (rowid, text) in rows {
+            let scrubbed = scrubber.scrub(&text);
+            if scrubbed != text {
+                let sql = format!("UPDATE {} SET {} = ?1 WHERE rowid = ?2", table, column);
+                conn.execute(&sql, params![scrubbed, rowid])?;
+                changed += 1;
+            }
+        }
+    }
+    Ok(changed)
+}
+
+/// Scrub the spellings out of every event payload left in the ledger
+fn scrub_events(conn: &Connection, ledger_id: &str, scrubber: &Scrubber) -> Result<usize> {
+    let mut stmt = conn.prepare("SELECT id, data FROM events WHERE ledger_id = ?1")?;
+    let events = stmt
+        .query_map([ledger_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut changed = 0;
+    for (id, data) in events {
+        let mut value: serde_json::Value = serde_json::from_str(&data)?;
+        if scrubber.scrub_value(&mut value) {
+            conn.execute("UPDATE events SET data = ?1 WHERE id = ?2", params![serde_json::to_string(&value)?, id])?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+/// Registry versions of the merchant, enrichment cache entries and alias
+/// decisions that name it
+fn purge_entities(
+    conn: &Connection,
+    ledger_id: &str,
+    plan: &PurgePlan,
+    mode: PurgeMode,
+    scrubber: &Scrubber,
+) -> Result<usize> {
+    let mut changed = 0;
+    if let Some(merchant_id) = &plan.merchant_id {
+        match mode {
+            PurgeMode::Remove => {
+                changed += conn.execute(
+                    "DELETE FROM entity_versions WHERE entity_type = 'merchant' AND id = ?1",
+                    [merchant_id],
+                )?;
+            }
+            PurgeMode::Pseudonymize => {
+                let versions: Vec<Merchant> = load_versions::<Merchant>(conn)?
+                    .into_iter()
+                    .filter(|m| &m.id == merchant_id)
+                    .map(|mut m| {
+                        m.canonical_name = scrubber.replacement.to_string();
+                        m.aliases.clear();
+                        m.metadata = serde_json::json!({});
+                        m
+                    })
+                    .collect();
+                changed += save_versions(conn, &versions)?;
+            }
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT provider, merchant_key FROM merchant_enrichment_cache")?;
+    let cached = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (provider, key) in cached {
+        if plan.names.contains(&normalize_merchant_string(&key)) {
+            changed += conn.execute(
+                "DELETE FROM merchant_enrichment_cache WHERE provider = ?1 AND merchant_key = ?2",
+                params![provider, key],
+            )?;
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT group_key, canonical, names FROM alias_decisions WHERE ledger_id = ?1")?;
+    let decisions = stmt
+        .query_map([ledger_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (group_key, canonical, names) in decisions {
+        if scrubber.scrub(&canonical) != canonical || scrubber.scrub(&names) != names {
+            changed += conn.execute(
+                "DELETE FROM alias_decisions WHERE ledger_id = ?1 AND group_key = ?2",
+                params![ledger_id, group_key],
+            )?;
+        }
+    }
+    Ok(changed)
+}
+
+/// Import recordings and import jobs whose file or outcome holds a spelling
+///
+/// Removal deletes them; pseudonymizing scrubs them, or deletes the ones whose
+/// file isn't text.
+fn purge_import_inputs(conn: &Connection, ledger_id: &str, mode: PurgeMode, scrubber: &Scrubber) -> Result<usize> {
+    let mut changed = 0;
+
+    let mut stmt = conn.prepare("SELECT session_id, content, outcome FROM import_recordings WHERE ledger_id = ?1")?;
+    let recordings = stmt
+        .query_map([ledger_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?, row.get::<_, String>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (session_id, content, outcome) in recordings {
+        let text = String::from_utf8_lossy(&content);
+        let scrubbed = scrubber.scrub(&text);
+        let mut outcome: serde_json::Value = serde_json::from_str(&outcome)?;
+        if !scrubber.scrub_value(&mut outcome) && scrubbed == text {
+            continue;
+        }
+        changed += match mode {
+            PurgeMode::Pseudonymize if std::str::from_utf8(&content).is_ok() => conn.execute(
+                "UPDATE import_recordings SET content = ?1, content_hash = ?2, outcome = ?3 WHERE session_id = ?4",
+                params![
+                    scrubbed.as_bytes(),
+                    content_sha256(scrubbed.as_bytes()),
+                    serde_json::to_string(&outcome)?,
+                    session_id
+                ],
+            )?,
+            _ => conn.execute("DELETE FROM import_recordings WHERE session_id = ?1", [&session_id])?,
+        };
+    }
+
+    let mut stmt = conn.prepare("SELECT id, payload, result FROM job_queue WHERE ledger_id = ?1")?;
+    let jobs = stmt
+        .query_map([ledger_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<Vec<u8>>>(1)?, row.get::<_, Option<String>>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (job_id, payload, result) in jobs {
+        let text = payload.as_deref().map(String::from_utf8_lossy);
+        let scrubbed = text.as_deref().map(|text| scrubber.scrub(text));
+        let mut result: Option<serde_json::Value> = result.as_deref().map(serde_json::from_str).transpose()?;
+        let result_changed = result.as_mut().is_some_and(|value| scrubber.scrub_value(value));
+        if !result_changed && scrubbed.as_deref() == text.as_deref() {
+            continue;
+        }
+        let text_payload = payload.as_deref().is_none_or(|bytes| std::str::from_utf8(bytes).is_ok());
+        changed += match mode {
+            PurgeMode::Pseudonymize if text_payload => conn.execute(
+                "UPDATE job_queue SET payload = ?1, result = ?2 WHERE id = ?3",
+                params![
+                    scrubbed.map(String::into_bytes),
+                    result.map(|value| serde_json::to_string(&value)).transpose()?,
+                    job_id
+                ],
+            )?,
+            _ => conn.execute("DELETE FROM job_queue WHERE id = ?1", [&job_id])?,
+        };
+    }
+    Ok(changed)
+}
+
+/// Manifest entries whose count, total or checksum differ
+fn checksum_changes(before: &[ManifestEntry], after: &[ManifestEntry]) -> Vec<ChecksumChange> {
+    let key = |entry: &ManifestEntry| (entry.bank.clone(), entry.period.clone());
+    let before: BTreeMap<_, _> = before.iter().map(|entry| (key(entry), entry)).collect();
+    let after: BTreeMap<_, _> = after.iter().map(|entry| (key(entry), entry)).collect();
+    let keys: BTreeSet<_> = before.keys().chain(after.keys()).cloned().collect();
+    keys.into_iter()
+        .filter(|key| before.get(key) != after.get(key))
+        .map(|(bank, period)| ChecksumChange {
+            before: before.get(&(bank.clone(), period.clone())).map(|entry| (*entry).clone()),
+            after: after.get(&(bank.clone(), period.clone())).map(|entry| (*entry).clone()),
+            bank,
+            period,
+        })
+        .collect()
+}
+
+// ============================================================================
+// SCRUBBING
+// ============================================================================
+
+/// Replaces every spelling (ASCII case-insensitive) with the purge id
+struct Scrubber<'a> {
+    needles: &'a [String],
+    replacement: &'a str,
+}
+
+impl Scrubber<'_> {
+    fn scrub(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for needle in self.needles {
+            let lower = text.to_ascii_lowercase();
+            if !lower.contains(needle.as_str()) {
+                continue;
+            }
+            // ASCII lowercasing keeps byte offsets, so they index `text` too
+            let mut scrubbed = String::with_capacity(text.len());
+            let mut last = 0;
+            for (start, _) in lower.match_indices(needle.as_str()) {
+                scrubbed.push_str(&text[last..start]);
+                scrubbed.push_str(self.replacement);
+                last = start + needle.len();
+            }
+            scrubbed.push_str(&text[last..]);
+            text = scrubbed;
+        }
+        text
+    }
+
+    /// Scrub every string in a JSON value; true when anything changed
+    fn scrub_value(&self, value: &mut serde_json::Value) -> bool {
+        match value {
+            serde_json::Value::String(text) => {
+                let scrubbed = self.scrub(text);
+                let changed = scrubbed != *text;
+                *text = scrubbed;
+                changed
+            }
+            serde_json::Value::Array(items) => {
+                items.iter_mut().fold(false, |changed, item| self.scrub_value(item) | changed)
+            }
+            serde_json::Value::Object(fields) => {
+                fields.values_mut().fold(false, |changed, field| self.scrub_value(field) | changed)
+            }
+            _ => false,
+        }
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::get_events_for_entity;
+    use crate::job_queue::{enqueue_job, JobSpec};
+    use crate::ledger::{require_ledger, update_ledger_config};
+    use crate::notes::add_note;
+    use crate::system::TrustSystem;
+
+    const CSV: &str = "Date,Description,Amount_Original,Amount_Numeric,Transaction_Type,Category,Merchant,Currency,Account_Name,Account_Number,Bank,Source_File,Line_Number,Classification_Notes
+01/05/2025,STARBUCKS STORE 12,-5.00,-5.00,GASTO,Restaurants,Starbucks,USD,Apple Card,0001,AppleCard,a.csv,2,
+01/07/2025,STARBUCKS STORE 40,-7.50,-7.50,GASTO,Restaurants,Starbucks,USD,Apple Card,0001,AppleCard,a.csv,3,
+01/20/2025,PAYROLL,100.00,100.00,INGRESO,Income,Acme,USD,Apple Card,0001,AppleCard,a.csv,4,
+";
+
+    fn ledger(record_imports: bool) -> TrustSystem {
+        let system = TrustSystem::open_in_memory().unwrap().with_actor("ana");
+        let mut config = require_ledger(system.conn(), "default").unwrap().config;
+        config.record_imports = record_imports;
+        update_ledger_config(system.conn(), "default", &config, "ana").unwrap();
+        let path = std::env::temp_dir().join(format!("purge-{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(&path, CSV).unwrap();
+        assert_eq!(system.import_file(&path).unwrap().inserted, 3);
+        std::fs::remove_file(&path).unwrap();
+        system
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    fn current_hash(conn: &Connection, tx_uuid: &str) -> String {
+        conn.query_row(
+            "SELECT idempotency_hash FROM transactions WHERE tx_uuid = ?1 AND valid_until IS NULL",
+            [tx_uuid],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_pseudonymize_keeps_totals_and_leaves_no_name() {
+        let system = ledger(true);
+        let conn = system.conn();
+        let plan = plan_purge(conn, "default", "starbucks").unwrap();
+        assert_eq!(plan.tx_uuids.len(), 2);
+        assert!(plan.merchant_id.is_some(), "resolved through the default merchant registry");
+        add_note(conn, &plan.tx_uuids[0], "ana", "Coffee at Starbucks with Bo", None).unwrap();
+        let manifest = ledger_manifest(conn, "default").unwrap();
+
+        let report = purge_counterparty(conn, "default", "Starbucks", PurgeMode::Pseudonymize, "ana").unwrap();
+        assert_eq!((report.transactions, report.hashes_recomputed, report.related_rows), (2, 2, 1));
+        assert!(report.checksums.is_empty(), "amounts and dates are unchanged");
+        assert_ne!(report.event_chain_before, report.event_chain_after);
+        assert_eq!(ledger_manifest(conn, "default").unwrap(), manifest);
+
+        let like = "'%starbucks%'";
+        for sql in [
+            format!("SELECT COUNT(*) FROM transactions WHERE lower(merchant || description) LIKE {}", like),
+            format!("SELECT COUNT(*) FROM transaction_notes WHERE lower(text) LIKE {}", like),
+            format!("SELECT COUNT(*) FROM events WHERE lower(data) LIKE {}", like),
+            format!(
+                "SELECT COUNT(*) FROM import_recordings WHERE lower(CAST(content AS TEXT) || outcome) LIKE {}",
+                like
+            ),
+        ] {
+            assert_eq!(count(conn, &sql), 0, "{}", sql);
+        }
+        let current = system.transaction(&plan.tx_uuids[0]).unwrap();
+        assert_eq!(current.merchant, report.purge_id);
+        assert_eq!(current.compute_idempotency_hash(), current_hash(conn, &plan.tx_uuids[0]));
+        let events = get_events_for_entity(conn, "counterparty", &report.purge_id).unwrap();
+        assert_eq!(events[0].payload::<PurgeReport>().unwrap(), report);
+        assert!(plan_purge(conn, "default", "starbucks").unwrap().is_empty());
+        assert_eq!((report.import_inputs, count(conn, "SELECT COUNT(*) FROM import_recordings")), (1, 1));
+    }
+
+    #[test]
+    fn test_remove_deletes_rows_and_notes_the_changed_checksums() {
+        let system = ledger(true);
+        let conn = system.conn();
+        let spec = JobSpec::Import { filename: "a.csv".to_string() };
+        enqueue_job(conn, &spec, Some(CSV.as_bytes()), "default", "ana").unwrap();
+        assert_eq!(count(conn, "SELECT COUNT(*) FROM import_recordings"), 1);
+
+        let report = purge_counterparty(conn, "default", "STARBUCKS STORE 12", PurgeMode::Remove, "ana").unwrap();
+        assert_eq!(report.transactions, 1, "an unregistered name matches only itself");
+        assert_eq!(report.import_inputs, 2, "the recorded import and the queued job hold the raw line");
+        assert_eq!(count(conn, "SELECT COUNT(*) FROM import_recordings"), 0);
+        assert_eq!(count(conn, "SELECT COUNT(*) FROM job_queue"), 0);
+        let report = purge_counterparty(conn, "default", "Starbucks", PurgeMode::Remove, "ana").unwrap();
+        assert_eq!(report.transactions, 1);
+        assert!(report.events_removed >= 1, "its transaction_added event goes too");
+
+        assert_eq!(system.transactions().unwrap().len(), 1);
+        assert_eq!(report.checksums.len(), 1);
+        let change = &report.checksums[0];
+        assert_eq!((change.before.as_ref().unwrap().count, change.after.as_ref().unwrap().count), (2, 1));
+        assert!(purge_counterparty(conn, "default", "Starbucks", PurgeMode::Remove, "ana").is_err());
+    }
+}
@@ -0,0 +1,237 @@
+// 🧪 Quarantine - Quality gate on imports
+//
+// Problem solved:
+// - A garbage feed (the wrong parser, shifted columns, a bank changing its
+//   export) was imported like any other file and silently polluted reports
+//
+// Every import validates its rows as a batch (data_quality.rs). When the
+// batch's average quality or confidence falls below the ledger's thresholds,
+// the ledger's `import_policy` decides what happens: `strict` rejects the file
+// (nothing is written), `quarantine` stores the rows marked with their import
+// session (QUARANTINE_KEY) so reports, balances and the TUI skip them until
+// `release_quarantine` lets them in or `discard_quarantine` voids them, and
+// `lenient` (the default) imports them with the issues reported.
+
+use crate::data_quality::BatchSummary;
+use crate::db::{
+    get_all_transactions, insert_event, insert_transaction_version, void_transaction, Event, Transaction,
+    QUARANTINE_KEY,
+};
+use crate::event_schema::QuarantineReviewed;
+use crate::ledger::LedgerConfig;
+use anyhow::{anyhow, Result};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+/// Thresholds used when the policy is set without its own
+pub const DEFAULT_MIN_QUALITY: f64 = 0.7;
+pub const DEFAULT_MIN_CONFIDENCE: f64 = 0.7;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportPolicy {
+    /// Import everything; issues are only reported
+    #[default]
+    Lenient,
+    /// Store a failing batch, held out of reports until reviewed
+    Quarantine,
+    /// Refuse a failing batch
+    Strict,
+}
+
+impl ImportPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportPolicy::Lenient => "lenient",
+            ImportPolicy::Quarantine => "quarantine",
+            ImportPolicy::Strict => "strict",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "lenient" => Ok(ImportPolicy::Lenient),
+            "quarantine" => Ok(ImportPolicy::Quarantine),
+            "strict" => Ok(ImportPolicy::Strict),
+            other => Err(anyhow!("Unknown import policy '{}' (strict, quarantine, lenient)", other)),
+        }
+    }
+}
+
+/// A ledger's import policy with the thresholds it applies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImportGate {
+    pub policy: ImportPolicy,
+    /// Lowest accepted average quality of a batch (0.0-1.0)
+    pub min_quality: f64,
+    /// Lowest accepted average confidence of a batch (0.0-1.0)
+    pub min_confidence: f64,
+}
+
+impl ImportGate {
+    pub fn from_config(config: &LedgerConfig) -> Self {
+        ImportGate {
+            policy: config.import_policy.unwrap_or_default(),
+            min_quality: config.min_import_quality.unwrap_or(DEFAULT_MIN_QUALITY),
+            min_confidence: config.min_import_confidence.unwrap_or(DEFAULT_MIN_CONFIDENCE),
+        }
+    }
+
+    /// Why a batch falls short of the thresholds (None = it passes; an empty
+    /// batch always does)
+    pub fn shortfall(&self, summary: &BatchSummary) -> Option<String> {
+        if summary.total_transactions == 0 {
+            return None;
+        }
+        let mut reasons = Vec::new();
+        if summary.average_quality < self.min_quality {
+            reasons.push(format!(
+                "average quality {:.1}% < {:.1}%",
+                summary.average_quality * 100.0,
+                self.min_quality * 100.0
+            ));
+        }
+        if summary.average_confidence < self.min_confidence {
+            reasons.push(format!(
+                "average confidence {:.1}% < {:.1}%",
+                summary.average_confidence * 100.0,
+                self.min_confidence * 100.0
+            ));
+        }
+        if reasons.is_empty() { None } else { Some(reasons.join(", ")) }
+    }
+}
+
+// ============================================================================
+// REVIEW
+// ============================================================================
+
+/// Rows of a ledger waiting in quarantine (all sessions, or one)
+pub fn quarantined_transactions(
+    conn: &Connection,
+    ledger_id: &str,
+    session_id: Option<&str>,
+) -> Result<Vec<Transaction>> {
+    Ok(get_all_transactions(conn)?
+        .into_iter()
+        .filter(|tx| tx.ledger_id == ledger_id && tx.is_current() && !tx.is_voided())
+        .filter(|tx| match (tx.get_metadata(QUARANTINE_KEY).and_then(|id| id.as_str()), session_id) {
+            (Some(held), Some(session_id)) => held == session_id,
+            (Some(_), None) => true,
+            (None, _) => false,
+        })
+        .collect())
+}
+
+fn session_rows(conn: &Connection, ledger_id: &str, session_id: &str) -> Result<Vec<Transaction>> {
+    let rows = quarantined_transactions(conn, ledger_id, Some(session_id))?;
+    if rows.is_empty() {
+        return Err(anyhow!("No quarantined rows from import {} in ledger '{}'", session_id, ledger_id));
+    }
+    Ok(rows)
+}
+
+/// Let a quarantined import into the ledger: each row gets a new version
+/// without the mark. Returns the number of rows released.
+pub fn release_quarantine(conn: &Connection, ledger_id: &str, session_id: &str, actor: &str) -> Result<usize> {
+    let rows = session_rows(conn, ledger_id, session_id)?;
+
+    let db_tx = conn.unchecked_transaction()?;
+    for tx in &rows {
+        let mut next = tx.next_version(Some("Released from quarantine".to_string()));
+        next.metadata.remove(QUARANTINE_KEY);
+        insert_transaction_version(&db_tx, &next, actor)?;
+    }
+    let payload = QuarantineReviewed { rows: rows.len(), reason: None };
+    let event = Event::typed("quarantine_released", "import", session_id, &payload, actor)?.with_ledger(ledger_id);
+    insert_event(&db_tx, &event)?;
+    db_tx.commit()?;
+
+    Ok(rows.len())
+}
+
+/// Void every row of a quarantined import (a reason is required). Returns
+/// the number of rows discarded.
+pub fn discard_quarantine(
+    conn: &Connection,
+    ledger_id: &str,
+    session_id: &str,
+    reason: &str,
+    actor: &str,
+) -> Result<usize> {
+    if reason.trim().is_empty() {
+        return Err(anyhow!("A reason is required to discard a quarantined import"));
+    }
+    let rows = session_rows(conn, ledger_id, session_id)?;
+
+    let db_tx = conn.unchecked_transaction()?;
+    for tx in &rows {
+        void_transaction(&db_tx, &tx.id, reason, actor)?;
+    }
+    let payload = QuarantineReviewed { rows: rows.len(), reason: Some(reason.to_string()) };
+    let event = Event::typed("quarantine_discarded", "import", session_id, &payload, actor)?.with_ledger(ledger_id);
+    insert_event(&db_tx, &event)?;
+    db_tx.commit()?;
+
+    Ok(rows.len())
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::setup_database;
+    use crate::imports::import_statement;
+    use crate::jobs::ledger_transactions;
+    use crate::ledger::{require_ledger, update_ledger_config};
+
+    /// Rows with no merchant and an unknown category score low
+    const POOR: &str = "Date,Description,Amount\n01/02/2025,X,-5.25\n01/03/2025,Y,-40.00\n";
+
+    fn ledger_with(conn: &Connection, policy: ImportPolicy) {
+        let mut config = require_ledger(conn, "default").unwrap().config;
+        config.import_policy = Some(policy);
+        config.min_import_quality = Some(0.99);
+        update_ledger_config(conn, "default", &config, "ana").unwrap();
+    }
+
+    #[test]
+    fn test_strict_rejects_and_quarantine_holds_back() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        ledger_with(&conn, ImportPolicy::Strict);
+        let error = import_statement(&conn, "bofa_jan.csv", POOR.as_bytes(), "default", "ana").unwrap_err();
+        assert!(error.to_string().contains("average quality"), "{}", error);
+        assert!(ledger_transactions(&conn, "default").unwrap().is_empty());
+
+        ledger_with(&conn, ImportPolicy::Quarantine);
+        let session = import_statement(&conn, "bofa_jan.csv", POOR.as_bytes(), "default", "ana").unwrap();
+        assert!(session.quarantine.is_some());
+        assert_eq!(session.inserted, 2);
+        assert!(ledger_transactions(&conn, "default").unwrap().is_empty());
+        assert_eq!(quarantined_transactions(&conn, "default", None).unwrap().len(), 2);
+
+        assert_eq!(release_quarantine(&conn, "default", &session.id, "ana").unwrap(), 2);
+        assert_eq!(ledger_transactions(&conn, "default").unwrap().len(), 2);
+        assert!(quarantined_transactions(&conn, "default", None).unwrap().is_empty());
+        assert!(release_quarantine(&conn, "default", &session.id, "ana").is_err());
+    }
+
+    #[test]
+    fn test_discard_voids_the_batch() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        ledger_with(&conn, ImportPolicy::Quarantine);
+
+        let session = import_statement(&conn, "bofa_jan.csv", POOR.as_bytes(), "default", "ana").unwrap();
+        assert!(discard_quarantine(&conn, "default", &session.id, " ", "ana").is_err());
+        assert_eq!(discard_quarantine(&conn, "default", &session.id, "Wrong parser", "ana").unwrap(), 2);
+        assert!(quarantined_transactions(&conn, "default", None).unwrap().is_empty());
+        assert!(ledger_transactions(&conn, "default").unwrap().is_empty());
+        assert_eq!(ImportPolicy::parse("STRICT").unwrap(), ImportPolicy::Strict);
+    }
+}
@@ -0,0 +1,515 @@
+// 🔎 Transaction Query - Filters shared by CLI, server and TUI
+// One filter definition instead of ad-hoc matching in every caller
+//
+// `TransactionQuery` adds sorting and cursor pagination on top of the filter,
+// so the server can page through a ledger instead of returning all of it.
+
+use crate::bulk::tags;
+use crate::db::Transaction;
+use crate::entities::shared_registry;
+use crate::history::parse_as_of;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+// ============================================================================
+// TRANSACTION FILTER
+// ============================================================================
+
+/// TransactionFilter - Conjunction of optional field filters
+///
+/// Empty filter matches everything. All comparisons are case-insensitive:
+/// - ledger, bank, category, transaction_type, source_file, tag: exact match
+/// - merchant, text (description): substring match
+/// - merchant_id: merchant resolves to that id in the shared MerchantRegistry
+/// - date_from/date_to, min_amount/max_amount: inclusive ranges; amounts
+///   compare by magnitude (a $12 expense is -12.00 but matches min_amount=10)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransactionFilter {
+    pub ledger: Option<String>,
+    pub bank: Option<String>,
+    pub category: Option<String>,
+    pub merchant: Option<String>,
+    pub transaction_type: Option<String>,
+    pub source_file: Option<String>,
+    pub text: Option<String>,
+    #[serde(default)]
+    pub merchant_id: Option<String>,
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub date_from: Option<NaiveDate>,
+    #[serde(default)]
+    pub date_to: Option<NaiveDate>,
+    #[serde(default)]
+    pub min_amount: Option<f64>,
+    #[serde(default)]
+    pub max_amount: Option<f64>,
+}
+
+/// "2025-01-31" or "01/31/2025"
+fn parse_filter_date(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%m/%d/%Y"))
+        .map_err(|_| anyhow!("Invalid date '{}': expected YYYY-MM-DD", value))
+}
+
+fn parse_filter_amount(value: &str) -> Result<f64> {
+    value
+        .trim_start_matches('$')
+        .parse::<f64>()
+        .with_context(|| format!("Invalid amount '{}'", value))
+}
+
+impl TransactionFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a CLI filter expression
+    ///
+    /// Format: comma-separated `key=value` pairs
+    /// Keys: ledger, bank, category, merchant, type, source, text, merchant_id,
+    /// tag, from, to, min_amount, max_amount
+    ///
+    /// Example: "bank=BofA,type=GASTO,merchant=starbucks,from=2025-01-01"
+    pub fn parse(expr: &str) -> Result<Self> {
+        let mut filter = TransactionFilter::new();
+
+        for pair in expr.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid filter '{}': expected key=value", pair))?;
+            if !filter.set(key, value)? {
+                return Err(anyhow!("Unknown filter key: {}", key.trim().to_lowercase()));
+            }
+        }
+
+        Ok(filter)
+    }
+
+    /// Set one criterion by key; `false` when the key is not a filter key
+    pub fn set(&mut self, key: &str, value: &str) -> Result<bool> {
+        let value = value.trim();
+        let text = Some(value.to_string());
+
+        match key.trim().to_lowercase().as_str() {
+            "ledger" => self.ledger = text,
+            "bank" => self.bank = text,
+            "category" => self.category = text,
+            "merchant" => self.merchant = text,
+            "type" | "transaction_type" => self.transaction_type = text,
+            "source" | "source_file" => self.source_file = text,
+            "text" | "description" | "q" => self.text = text,
+            "merchant_id" => self.merchant_id = text,
+            "tag" => self.tag = text,
+            "from" | "date_from" => self.date_from = Some(parse_filter_date(value)?),
+            "to" | "date_to" => self.date_to = Some(parse_filter_date(value)?),
+            "min_amount" => self.min_amount = Some(parse_filter_amount(value)?),
+            "max_amount" => self.max_amount = Some(parse_filter_amount(value)?),
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Check if no criteria are set
+    pub fn is_empty(&self) -> bool {
+        *self == TransactionFilter::default()
+    }
+
+    /// Check if a transaction satisfies every criterion
+    pub fn matches(&self, tx: &Transaction) -> bool {
+        fn equals(expected: &Option<String>, actual: &str) -> bool {
+            expected
+                .as_ref()
+                .is_none_or(|e| e.eq_ignore_ascii_case(actual))
+        }
+
+        fn contains(expected: &Option<String>, actual: &str) -> bool {
+            expected
+                .as_ref()
+                .is_none_or(|e| actual.to_lowercase().contains(&e.to_lowercase()))
+        }
+
+        let in_dates = self.date_from.is_none() && self.date_to.is_none()
+            || tx.parsed_date().is_some_and(|date| {
+                self.date_from.is_none_or(|from| date >= from) && self.date_to.is_none_or(|to| date <= to)
+            });
+        let amount = tx.amount_numeric.abs();
+
+        equals(&self.ledger, &tx.ledger_id)
+            && equals(&self.bank, &tx.bank)
+            && equals(&self.category, &tx.category)
+            && equals(&self.transaction_type, &tx.transaction_type)
+            && equals(&self.source_file, &tx.source_file)
+            && contains(&self.merchant, &tx.merchant)
+            && contains(&self.text, &tx.description)
+            && in_dates
+            && self.min_amount.is_none_or(|min| amount >= min)
+            && self.max_amount.is_none_or(|max| amount <= max)
+            && self.tag.as_ref().is_none_or(|tag| tags(tx).iter().any(|t| t.eq_ignore_ascii_case(tag)))
+            && self.merchant_id.as_ref().is_none_or(|id| {
+                shared_registry().get_id(&tx.merchant).as_deref() == Some(id.as_str())
+            })
+    }
+
+    /// Restrict to one ledger (builder)
+    pub fn in_ledger(mut self, ledger_id: &str) -> Self {
+        self.ledger = Some(ledger_id.to_string());
+        self
+    }
+
+    /// Return the matching transactions
+    pub fn apply(&self, transactions: &[Transaction]) -> Vec<Transaction> {
+        transactions
+            .iter()
+            .filter(|tx| self.matches(tx))
+            .cloned()
+            .collect()
+    }
+}
+
+// ============================================================================
+// SORTING AND PAGINATION
+// ============================================================================
+
+/// Page size when the caller doesn't ask for one
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+/// Largest page a caller can ask for
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    Date,
+    Amount,
+    Merchant,
+    Category,
+    Bank,
+    Type,
+}
+
+impl SortKey {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "date" => Ok(SortKey::Date),
+            "amount" => Ok(SortKey::Amount),
+            "merchant" => Ok(SortKey::Merchant),
+            "category" => Ok(SortKey::Category),
+            "bank" => Ok(SortKey::Bank),
+            "type" | "transaction_type" => Ok(SortKey::Type),
+            other => Err(anyhow!("Unknown sort key: {}", other)),
+        }
+    }
+
+    fn value(&self, tx: &Transaction) -> SortValue {
+        match self {
+            SortKey::Date => SortValue::Date(tx.parsed_date()),
+            SortKey::Amount => SortValue::Amount(tx.amount_numeric),
+            SortKey::Merchant => SortValue::Text(tx.merchant.to_lowercase()),
+            SortKey::Category => SortValue::Text(tx.category.to_lowercase()),
+            SortKey::Bank => SortValue::Text(tx.bank.to_lowercase()),
+            SortKey::Type => SortValue::Text(tx.transaction_type.to_lowercase()),
+        }
+    }
+}
+
+/// Sort order: key plus direction, written "date" or "-date" (descending)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl Default for Sort {
+    /// Newest first
+    fn default() -> Self {
+        Sort { key: SortKey::Date, descending: true }
+    }
+}
+
+impl Sort {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let expr = expr.trim();
+        match expr.strip_prefix('-') {
+            Some(key) => Ok(Sort { key: SortKey::parse(key)?, descending: true }),
+            None => Ok(Sort { key: SortKey::parse(expr)?, descending: false }),
+        }
+    }
+
+    /// Position of (value, id) in this order; the id breaks ties, so the
+    /// order is total and cursors are stable
+    fn compare(&self, a: (&SortValue, &str), b: (&SortValue, &str)) -> Ordering {
+        let ordering = a.0.partial_cmp(b.0).unwrap_or(Ordering::Equal).then_with(|| a.1.cmp(b.1));
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+/// Sortable value of one transaction (undated rows sort before dated ones)
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+enum SortValue {
+    Date(Option<NaiveDate>),
+    Amount(f64),
+    Text(String),
+}
+
+/// Where the previous page ended; handed out hex-encoded (opaque, URL-safe)
+#[derive(Debug, Serialize, Deserialize)]
+struct Cursor {
+    key: SortKey,
+    descending: bool,
+    value: SortValue,
+    id: String,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        json.bytes().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn decode(text: &str) -> Result<Self> {
+        let bytes = (0..text.len())
+            .step_by(2)
+            .map(|i| text.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| anyhow!("Invalid cursor"))?;
+        serde_json::from_slice(&bytes).map_err(|_| anyhow!("Invalid cursor"))
+    }
+}
+
+/// One page of a query
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionPage {
+    pub transactions: Vec<Transaction>,
+    /// Matching transactions across all pages
+    pub total: usize,
+    /// Pass as `cursor` to get the next page; None on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Filter + sort + page
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionQuery {
+    pub filter: TransactionFilter,
+    pub sort: Sort,
+    pub limit: usize,
+    pub cursor: Option<String>,
+    /// Query the ledger as it was then; the caller loads those versions
+    /// (history::transactions_as_of) before `run`
+    pub as_of: Option<DateTime<Utc>>,
+}
+
+impl Default for TransactionQuery {
+    fn default() -> Self {
+        TransactionQuery {
+            filter: TransactionFilter::default(),
+            sort: Sort::default(),
+            limit: DEFAULT_PAGE_SIZE,
+            cursor: None,
+            as_of: None,
+        }
+    }
+}
+
+impl TransactionQuery {
+    /// Build from URL query parameters: `sort`, `limit`, `cursor`, `as_of`,
+    /// plus any filter key (see `TransactionFilter::parse`)
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Self> {
+        let mut query = TransactionQuery::default();
+
+        for (key, value) in params {
+            match key.as_str() {
+                "sort" => query.sort = Sort::parse(value)?,
+                "limit" => {
+                    let limit: usize = value.parse().with_context(|| format!("Invalid limit '{}'", value))?;
+                    query.limit = limit.clamp(1, MAX_PAGE_SIZE);
+                }
+                "cursor" => query.cursor = Some(value.clone()).filter(|cursor| !cursor.is_empty()),
+                "as_of" => query.as_of = Some(parse_as_of(value)?),
+                _ => {
+                    if !query.filter.set(key, value)? {
+                        return Err(anyhow!("Unknown query parameter: {}", key));
+                    }
+                }
+            }
+        }
+
+        Ok(query)
+    }
+
+    /// Run over `transactions`: the page after `cursor`, at most `limit` rows
+    pub fn run(&self, transactions: &[Transaction]) -> Result<TransactionPage> {
+        let mut matching: Vec<(SortValue, &Transaction)> = transactions
+            .iter()
+            .filter(|tx| self.filter.matches(tx))
+            .map(|tx| (self.sort.key.value(tx), tx))
+            .collect();
+        matching.sort_by(|a, b| self.sort.compare((&a.0, &a.1.id), (&b.0, &b.1.id)));
+        let total = matching.len();
+
+        let start = match &self.cursor {
+            None => 0,
+            Some(text) => {
+                let cursor = Cursor::decode(text)?;
+                if cursor.key != self.sort.key || cursor.descending != self.sort.descending {
+                    return Err(anyhow!("Cursor belongs to a different sort order"));
+                }
+                matching.partition_point(|(value, tx)| {
+                    self.sort.compare((value, &tx.id), (&cursor.value, &cursor.id)) != Ordering::Greater
+                })
+            }
+        };
+
+        let page: Vec<&(SortValue, &Transaction)> = matching.iter().skip(start).take(self.limit).collect();
+        let next_cursor = match page.last() {
+            Some((value, tx)) if start + page.len() < total => Some(
+                Cursor {
+                    key: self.sort.key,
+                    descending: self.sort.descending,
+                    value: value.clone(),
+                    id: tx.id.clone(),
+                }
+                .encode(),
+            ),
+            _ => None,
+        };
+
+        Ok(TransactionPage {
+            transactions: page.into_iter().map(|(_, tx)| (*tx).clone()).collect(),
+            total,
+            next_cursor,
+        })
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use std::collections::HashMap;
+
+    fn create_test_transaction(bank: &str, merchant: &str, tx_type: &str) -> Transaction {
+        Transaction {
+            description: format!("{} PURCHASE", merchant.to_uppercase()),
+            amount_original: "$10.00".to_string(),
+            amount_numeric: -10.0,
+            transaction_type: tx_type.to_string(),
+            merchant: merchant.to_string(),
+            account_name: "Test Account".to_string(),
+            account_number: "1234".to_string(),
+            bank: bank.to_string(),
+            source_file: "test.csv".to_string(),
+            ..test_support::sample_transaction()
+        }
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        let filter = TransactionFilter::parse("bank=BofA, type=GASTO,merchant=star").unwrap();
+
+        assert_eq!(filter.bank, Some("BofA".to_string()));
+        assert_eq!(filter.transaction_type, Some("GASTO".to_string()));
+        assert_eq!(filter.merchant, Some("star".to_string()));
+        assert!(!filter.is_empty());
+
+        assert!(TransactionFilter::parse("").unwrap().is_empty());
+        assert!(TransactionFilter::parse("bank").is_err());
+        assert!(TransactionFilter::parse("color=red").is_err());
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let starbucks = create_test_transaction("BofA", "Starbucks", "GASTO");
+        let salary = create_test_transaction("Wise", "Employer", "INGRESO");
+
+        let filter = TransactionFilter::parse("bank=bofa,merchant=STAR").unwrap();
+        assert!(filter.matches(&starbucks));
+        assert!(!filter.matches(&salary));
+
+        let by_text = TransactionFilter::parse("text=employer purchase").unwrap();
+        assert_eq!(by_text.apply(&[starbucks.clone(), salary.clone()]).len(), 1);
+
+        let mut business = salary;
+        business.ledger_id = "business".to_string();
+        let by_ledger = TransactionFilter::new().in_ledger("business");
+        assert!(by_ledger.matches(&business));
+        assert!(!by_ledger.matches(&starbucks));
+    }
+
+    #[test]
+    fn test_ranges_tags_and_merchant_id() {
+        let mut coffee = create_test_transaction("BofA", "STARBUCKS", "GASTO");
+        coffee.date = "2025-01-10".to_string();
+        coffee.metadata.insert("tags".to_string(), serde_json::json!(["Trip"]));
+        let mut rent = create_test_transaction("BofA", "Landlord", "GASTO");
+        rent.amount_numeric = -1500.0;
+        let all = [coffee.clone(), rent.clone()];
+
+        let in_january = TransactionFilter::parse("from=2025-01-01,to=01/12/2025").unwrap();
+        assert_eq!(in_january.apply(&all).len(), 1);
+        assert_eq!(TransactionFilter::parse("min_amount=100").unwrap().apply(&all)[0].merchant, "Landlord");
+        assert_eq!(TransactionFilter::parse("max_amount=$20").unwrap().apply(&all)[0].merchant, "STARBUCKS");
+        assert!(TransactionFilter::parse("tag=trip").unwrap().matches(&coffee));
+        assert!(!TransactionFilter::parse("tag=trip").unwrap().matches(&rent));
+
+        let starbucks_id = shared_registry().get_id("Starbucks Coffee").unwrap();
+        let by_merchant = TransactionFilter::parse(&format!("merchant_id={}", starbucks_id)).unwrap();
+        assert_eq!(by_merchant.apply(&all).len(), 1);
+
+        assert!(TransactionFilter::parse("from=yesterday").is_err());
+        assert!(TransactionFilter::parse("min_amount=lots").is_err());
+    }
+
+    #[test]
+    fn test_query_pages_with_cursor() {
+        let transactions: Vec<Transaction> = (1..=5)
+            .map(|day| {
+                let mut tx = create_test_transaction("BofA", "Shop", "GASTO");
+                tx.id = format!("tx-{}", day);
+                tx.date = format!("01/{:02}/2025", day);
+                tx.amount_numeric = -(day as f64);
+                tx
+            })
+            .collect();
+
+        let params: HashMap<String, String> =
+            [("limit", "2"), ("sort", "-date"), ("type", "gasto")].iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let mut query = TransactionQuery::from_params(&params).unwrap();
+
+        let mut seen = Vec::new();
+        loop {
+            let page = query.run(&transactions).unwrap();
+            assert_eq!(page.total, 5);
+            seen.extend(page.transactions.iter().map(|tx| tx.id.clone()));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen, vec!["tx-5", "tx-4", "tx-3", "tx-2", "tx-1"]);
+
+        // A cursor only works with the sort order that produced it
+        let first = TransactionQuery { limit: 1, ..TransactionQuery::default() }.run(&transactions).unwrap();
+        let by_amount = TransactionQuery {
+            sort: Sort::parse("amount").unwrap(),
+            cursor: first.next_cursor,
+            ..TransactionQuery::default()
+        };
+        assert!(by_amount.run(&transactions).is_err());
+
+        let bad: HashMap<String, String> = [("color".to_string(), "red".to_string())].into_iter().collect();
+        assert!(TransactionQuery::from_params(&bad).is_err());
+    }
+}
@@ -7,7 +7,11 @@
 // This is CRITICAL for Trust Construction - without reconciliation,
 // you cannot validate that your transaction sums are correct.
 
-use crate::db::Transaction;
+use crate::data_quality::embedded_fx;
+use crate::db::{DateBasis, Transaction};
+use crate::explanations::Hypothesis;
+use crate::statements::BalanceSnapshot;
+use crate::transfers::transfer_id;
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
@@ -71,6 +75,15 @@ pub struct StatementMetadata {
     pub opening_balance: f64,
     pub closing_balance: f64,
     pub statement_date: NaiveDate,
+
+    /// Number of transactions the statement lists, when it says (see manifest.rs)
+    #[serde(default)]
+    pub declared_count: Option<usize>,
+
+    /// Net of the statement's transactions, when it says
+    /// (default: closing - opening)
+    #[serde(default)]
+    pub declared_total: Option<f64>,
 }
 
 // ============================================================================
@@ -112,6 +125,10 @@ pub struct Discrepancy {
     pub description: String,
     pub amount: f64,
     pub category: DiscrepancyCategory,
+
+    /// Most likely cause, with the rows behind it (see explanations.rs)
+    #[serde(default)]
+    pub explanation: Option<Hypothesis>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -120,6 +137,55 @@ pub enum DiscrepancyCategory {
     DuplicateTransaction,
     AmountMismatch,
     DateMismatch,
+    /// Closing balance of one statement != opening balance of the next
+    ContinuityBreak,
+    /// A converted amount and its other side differ by rate timing (an
+    /// explained difference, recorded as an adjustment)
+    FxSlippage,
+}
+
+// ============================================================================
+// FX-CONVERTED FEEDS
+// ============================================================================
+
+/// Days a converted row and its other side may be apart
+pub const FX_WINDOW_DAYS: i64 = 3;
+
+/// A row a source converted to this currency (Wise, Scotiabank) and the row
+/// on the other account that is the same money, off by FX slippage
+#[derive(Debug, Clone)]
+pub struct FxMatch {
+    pub converted: Transaction,
+    pub counterpart: Transaction,
+    /// converted + counterpart: what the conversion lost (negative) or gained
+    pub slippage: f64,
+}
+
+impl FxMatch {
+    /// Slippage as a percentage of the counterpart's amount
+    pub fn percent(&self) -> f64 {
+        match self.counterpart.amount_numeric.abs() {
+            0.0 => 0.0,
+            base => self.slippage.abs() / base * 100.0,
+        }
+    }
+
+    pub fn discrepancy(&self) -> Discrepancy {
+        Discrepancy {
+            description: format!(
+                "FX slippage {:.2} ({:.2}%) between {} {} and {} {}",
+                self.slippage,
+                self.percent(),
+                self.converted.bank,
+                self.converted.amount_numeric,
+                self.counterpart.bank,
+                self.counterpart.amount_numeric
+            ),
+            amount: self.slippage,
+            category: DiscrepancyCategory::FxSlippage,
+            explanation: None,
+        }
+    }
 }
 
 // ============================================================================
@@ -132,6 +198,10 @@ pub struct ReconciliationEngine {
 
     /// Threshold for minor vs major discrepancy (default: $10.00)
     pub major_discrepancy_threshold: f64,
+
+    /// How far a converted amount may be from its other side, as a fraction
+    /// of it (default: 0.02 = 2%)
+    pub fx_tolerance: f64,
 }
 
 impl ReconciliationEngine {
@@ -139,13 +209,14 @@ impl ReconciliationEngine {
         ReconciliationEngine {
             tolerance: 0.01,
             major_discrepancy_threshold: 10.0,
+            fx_tolerance: 0.02,
         }
     }
 
     pub fn with_tolerance(tolerance: f64) -> Self {
         ReconciliationEngine {
             tolerance,
-            major_discrepancy_threshold: 10.0,
+            ..Self::new()
         }
     }
 
@@ -153,9 +224,16 @@ impl ReconciliationEngine {
         ReconciliationEngine {
             tolerance,
             major_discrepancy_threshold: major_threshold,
+            ..Self::new()
         }
     }
 
+    /// Accept converted amounts up to `percent`% away from their other side
+    pub fn with_fx_tolerance(mut self, percent: f64) -> Self {
+        self.fx_tolerance = percent / 100.0;
+        self
+    }
+
     /// Reconcile transactions against statement metadata
     ///
     /// Formula: opening_balance + credits - debits = closing_balance
@@ -172,6 +250,8 @@ impl ReconciliationEngine {
     ///     opening_balance: 1000.0,
     ///     closing_balance: 2200.0,
     ///     statement_date: NaiveDate::from_ymd_opt(2025, 1, 31).unwrap(),
+    ///     declared_count: None,
+    ///     declared_total: None,
     /// };
     ///
     /// let report = engine.reconcile(&transactions, &statement);
@@ -220,7 +300,7 @@ impl ReconciliationEngine {
         ReconciliationReport {
             statement: statement.clone(),
             result,
-            transaction_count: transactions.len(),
+            transaction_count: transactions.iter().filter(|tx| !tx.is_voided()).count(),
             total_credits,
             total_debits,
             calculated_balance,
@@ -231,6 +311,8 @@ impl ReconciliationEngine {
 
     /// Calculate total credits (INGRESO transactions)
     ///
+    /// Voided transactions never count toward balances.
+    ///
     /// Credits are positive transactions that increase your balance:
     /// - Salary deposits
     /// - Income from Stripe
@@ -238,7 +320,7 @@ impl ReconciliationEngine {
     fn calculate_credits(&self, transactions: &[Transaction]) -> f64 {
         transactions
             .iter()
-            .filter(|tx| tx.transaction_type == "INGRESO")
+            .filter(|tx| !tx.is_voided() && tx.transaction_type == "INGRESO")
             .map(|tx| tx.amount_numeric.abs())
             .sum()
     }
@@ -252,7 +334,8 @@ impl ReconciliationEngine {
         transactions
             .iter()
             .filter(|tx| {
-                tx.transaction_type == "GASTO" || tx.transaction_type == "PAGO_TARJETA"
+                !tx.is_voided()
+                    && (tx.transaction_type == "GASTO" || tx.transaction_type == "PAGO_TARJETA")
             })
             .map(|tx| tx.amount_numeric.abs())
             .sum()
@@ -277,6 +360,7 @@ impl ReconciliationEngine {
                 description: format!("Balance mismatch: ${:.2} difference", difference),
                 amount: difference,
                 category: DiscrepancyCategory::AmountMismatch,
+                explanation: None,
             });
         }
 
@@ -305,6 +389,101 @@ impl ReconciliationEngine {
 
         (calculated - expected_balance).abs() < self.tolerance
     }
+
+    /// The transactions a statement covers: the account's (matched by name or
+    /// number), not voided, dated (on `basis`) from `from` through the
+    /// statement date. Card statements cut on the posted date, so a charge
+    /// made before the close but posted after it belongs to the next one.
+    /// Pending charges are left out: no statement lists them until they settle.
+    pub fn statement_transactions(
+        transactions: &[Transaction],
+        statement: &StatementMetadata,
+        from: NaiveDate,
+        basis: DateBasis,
+    ) -> Vec<Transaction> {
+        let account = &statement.account_name;
+        transactions
+            .iter()
+            .filter(|tx| !tx.is_voided() && !tx.is_pending())
+            .filter(|tx| &tx.account_name == account || &tx.account_number == account)
+            .filter(|tx| tx.date_on(basis).is_some_and(|date| date >= from && date <= statement.statement_date))
+            .cloned()
+            .collect()
+    }
+
+    /// Pair each converted row (one carrying its conversion, see
+    /// `embedded_fx`) with the row on another account moving the same money
+    /// the other way: within `FX_WINDOW_DAYS` and `fx_tolerance` of its
+    /// amount, closest amount first. Rows already linked to a transfer, and
+    /// voided or pending rows, are left out; each row pairs once.
+    pub fn match_fx_converted(&self, transactions: &[Transaction]) -> Vec<FxMatch> {
+        let open: Vec<&Transaction> = transactions
+            .iter()
+            .filter(|tx| tx.is_active() && !tx.is_voided() && !tx.is_pending() && transfer_id(tx).is_none())
+            .collect();
+        let mut taken = vec![false; open.len()];
+
+        let mut matches = Vec::new();
+        for converted in open.iter().filter(|tx| embedded_fx(tx).is_some()) {
+            let Some(date) = converted.parsed_date() else {
+                continue;
+            };
+            let best = open
+                .iter()
+                .enumerate()
+                .filter(|(index, tx)| !taken[*index] && embedded_fx(tx).is_none())
+                .filter(|(_, tx)| tx.bank != converted.bank || tx.account_name != converted.account_name)
+                .filter(|(_, tx)| tx.amount_numeric * converted.amount_numeric < 0.0)
+                .filter(|(_, tx)| {
+                    tx.parsed_date().is_some_and(|other| (other - date).num_days().abs() <= FX_WINDOW_DAYS)
+                })
+                .map(|(index, tx)| (index, (tx.amount_numeric + converted.amount_numeric).abs()))
+                .filter(|(index, gap)| *gap <= open[*index].amount_numeric.abs() * self.fx_tolerance)
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+
+            if let Some((index, _)) = best {
+                taken[index] = true;
+                let counterpart = open[index].clone();
+                matches.push(FxMatch {
+                    slippage: converted.amount_numeric + counterpart.amount_numeric,
+                    converted: (*converted).clone(),
+                    counterpart,
+                });
+            }
+        }
+        matches
+    }
+
+    /// Validate that consecutive statement snapshots chain together
+    ///
+    /// Formula: closing_balance(N) = opening_balance(N+1)
+    ///
+    /// Snapshots are compared in statement-date order. A break usually means a
+    /// missing statement or a mistyped balance.
+    pub fn check_continuity(&self, snapshots: &[BalanceSnapshot]) -> Vec<Discrepancy> {
+        let mut ordered: Vec<&BalanceSnapshot> = snapshots.iter().collect();
+        ordered.sort_by_key(|snapshot| snapshot.statement_date);
+
+        ordered
+            .windows(2)
+            .filter_map(|pair| {
+                let (previous, next) = (pair[0], pair[1]);
+                let gap = next.opening_balance - previous.closing_balance;
+                (gap.abs() >= self.tolerance).then(|| Discrepancy {
+                    description: format!(
+                        "{} closed at ${:.2} but {} opened at ${:.2}",
+                        previous.statement_period,
+                        previous.closing_balance,
+                        next.statement_period,
+                        next.opening_balance
+                    ),
+                    amount: gap,
+                    category: DiscrepancyCategory::ContinuityBreak,
+                    explanation: None,
+                })
+            })
+            .collect()
+    }
 }
 
 impl Default for ReconciliationEngine {
@@ -320,7 +499,7 @@ impl Default for ReconciliationEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
+    use crate::db::test_support;
 
     fn create_test_transaction(date: &str, amount: f64, tx_type: &str) -> Transaction {
         Transaction {
@@ -331,20 +510,11 @@ mod tests {
             transaction_type: tx_type.to_string(),
             category: "Test".to_string(),
             merchant: "Test Merchant".to_string(),
-            currency: "USD".to_string(),
             account_name: "Test Account".to_string(),
             account_number: "1234".to_string(),
             bank: "Test Bank".to_string(),
             source_file: "test.csv".to_string(),
-            line_number: "1".to_string(),
-            classification_notes: "".to_string(),
-            id: String::new(),
-            version: 0,
-            system_time: None,
-            valid_from: None,
-            valid_until: None,
-            previous_version_id: None,
-            metadata: HashMap::new(),
+            ..test_support::sample_transaction()
         }
     }
 
@@ -364,6 +534,8 @@ mod tests {
             opening_balance: 1000.0,
             closing_balance: 2200.0, // 1000 + 2000 - 500 - 300 = 2200 ✅
             statement_date: NaiveDate::from_ymd_opt(2025, 1, 31).unwrap(),
+            declared_count: None,
+            declared_total: None,
         };
 
         let report = engine.reconcile(&transactions, &statement);
@@ -393,6 +565,8 @@ mod tests {
             opening_balance: 1000.0,
             closing_balance: 2495.0, // Off by $5 (should be 2500)
             statement_date: NaiveDate::from_ymd_opt(2025, 1, 31).unwrap(),
+            declared_count: None,
+            declared_total: None,
         };
 
         let report = engine.reconcile(&transactions, &statement);
@@ -429,6 +603,8 @@ mod tests {
             opening_balance: 1000.0,
             closing_balance: 3100.0, // Off by $100 (should be 3000)
             statement_date: NaiveDate::from_ymd_opt(2025, 1, 31).unwrap(),
+            declared_count: None,
+            declared_total: None,
         };
 
         let report = engine.reconcile(&transactions, &statement);
@@ -523,4 +699,85 @@ mod tests {
 
         println!("✅ ReconciliationResult methods test passed");
     }
+
+    #[test]
+    fn test_check_continuity() {
+        let engine = ReconciliationEngine::new();
+        let snapshot = |period: &str, month: u32, opening: f64, closing: f64| BalanceSnapshot {
+            account_id: "acct-1".to_string(),
+            ledger_id: "default".to_string(),
+            statement_period: period.to_string(),
+            statement_date: NaiveDate::from_ymd_opt(2025, month, 28).unwrap(),
+            opening_balance: opening,
+            closing_balance: closing,
+            declared_count: None,
+            declared_total: None,
+        };
+
+        // Out of order on purpose: continuity follows statement dates
+        let chained = vec![
+            snapshot("February 2025", 2, 2200.0, 1900.0),
+            snapshot("January 2025", 1, 1000.0, 2200.0),
+        ];
+        assert!(engine.check_continuity(&chained).is_empty());
+
+        let broken = vec![
+            snapshot("January 2025", 1, 1000.0, 2200.0),
+            snapshot("March 2025", 3, 1750.0, 1800.0), // February missing
+        ];
+        let discrepancies = engine.check_continuity(&broken);
+        assert_eq!(discrepancies.len(), 1);
+        assert_eq!(discrepancies[0].category, DiscrepancyCategory::ContinuityBreak);
+        assert!((discrepancies[0].amount + 450.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_reconciliation_ignores_voided() {
+        let engine = ReconciliationEngine::new();
+
+        let mut duplicate = create_test_transaction("01/02/2025", -500.0, "GASTO");
+        duplicate.set_voided("duplicate charge", "test", chrono::Utc::now());
+
+        let transactions = vec![
+            create_test_transaction("01/01/2025", 2000.0, "INGRESO"),
+            create_test_transaction("01/02/2025", -500.0, "GASTO"),
+            duplicate,
+        ];
+
+        let statement = StatementMetadata {
+            account_name: "Test Account".to_string(),
+            statement_period: "January 2025".to_string(),
+            opening_balance: 1000.0,
+            closing_balance: 2500.0, // 1000 + 2000 - 500 (voided -500 ignored)
+            statement_date: NaiveDate::from_ymd_opt(2025, 1, 31).unwrap(),
+            declared_count: None,
+            declared_total: None,
+        };
+
+        let report = engine.reconcile(&transactions, &statement);
+
+        assert_eq!(report.transaction_count, 2);
+        assert_eq!(report.total_debits, 500.0);
+        assert!(report.is_balanced());
+    }
+
+    #[test]
+    fn test_fx_converted_rows_match_within_tolerance() {
+        let mut wise = create_test_transaction("01/10/2025", -55.10, "TRASPASO");
+        wise.bank = "Wise".to_string();
+        wise.metadata.insert("fx_rate".to_string(), serde_json::json!(18.15));
+        wise.metadata.insert("original_currency".to_string(), serde_json::json!("MXN"));
+        let bank_side = create_test_transaction("01/11/2025", 55.0, "TRASPASO");
+        let far_off = create_test_transaction("01/11/2025", 60.0, "TRASPASO");
+        let transactions = vec![far_off, wise, bank_side];
+
+        let matches = ReconciliationEngine::new().match_fx_converted(&transactions);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].counterpart.amount_numeric, 55.0);
+        assert!((matches[0].slippage + 0.10).abs() < 1e-9);
+        assert_eq!(matches[0].discrepancy().category, DiscrepancyCategory::FxSlippage);
+
+        // 0.18% apart: nothing pairs under a 0.1% tolerance
+        assert!(ReconciliationEngine::new().with_fx_tolerance(0.1).match_fx_converted(&transactions).is_empty());
+    }
 }
@@ -0,0 +1,528 @@
+// 🔁 Import Replay - Re-run a recorded import and compare the outcome
+//
+// Problem solved:
+// - "My numbers changed and I don't know why": what an import produces depends
+//   on the file, the ledger config, the rules in force, what was already in
+//   the ledger and the clock, and none of that was kept
+// - Reproducing a user's import meant guessing which rules they had that day
+//
+// With `record_imports` set on a ledger (`ledger set <id> record_imports true`)
+// every import stores an `ImportRecording`: the file bytes and their SHA-256,
+// the ledger config, the rules used (and their stored versions), the clock and
+// the outcome. `replay_import` runs the same import again in a scratch
+// in-memory database holding the ledger as it was at that instant, compares
+// the outcome field by field, and lists the rules and config that changed
+// since. Replays use the built-in duplicate strategies.
+
+use crate::archive::content_sha256;
+use crate::db::{insert_transaction_as, setup_database, Transaction};
+use crate::deduplication::DeduplicationEngine;
+use crate::history::transactions_as_of;
+use crate::fx::HistoricalRates;
+use crate::imports::{
+    import_statement_with, ImportContext, ImportSession, RowIssue, ASSET_KEY, ASSET_PRICE_KEY, IMPORT_SESSION_KEY,
+};
+use crate::ledger::{create_ledger, get_ledger, list_ledgers, require_ledger, update_ledger_config, LedgerConfig};
+use crate::rules::{get_current_rules, ClassificationRule, RuleEngine};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Actor of the rows and ledgers copied into the scratch database
+const REPLAY_ACTOR: &str = "replay";
+
+// ============================================================================
+// RECORDING
+// ============================================================================
+
+/// What an import produced, without ids and storage times
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportOutcome {
+    pub source: String,
+    pub rows: usize,
+    pub inserted: usize,
+    pub duplicates: usize,
+    /// Older recordings predate the count
+    #[serde(default)]
+    pub repeated: usize,
+    /// Ran as an upsert (see ImportContext::upsert); replays do the same
+    #[serde(default)]
+    pub upsert: bool,
+    #[serde(default)]
+    pub updated: usize,
+    #[serde(default)]
+    pub settled: usize,
+    pub failed: usize,
+    pub issues: Vec<RowIssue>,
+    /// Inserted rows in file order (see `transaction_values`)
+    pub transactions: Vec<Value>,
+}
+
+impl ImportOutcome {
+    pub fn new(session: &ImportSession, inserted: &[Transaction]) -> Self {
+        ImportOutcome {
+            source: session.source.clone(),
+            rows: session.rows,
+            inserted: session.inserted,
+            duplicates: session.duplicates,
+            repeated: session.repeated,
+            upsert: session.upsert,
+            updated: session.updated,
+            settled: session.settled,
+            failed: session.failed,
+            issues: session.issues.clone(),
+            transactions: inserted.iter().map(transaction_values).collect(),
+        }
+    }
+}
+
+/// A transaction's values as JSON, without the fields that differ on every
+/// run (uuid, version, storage times and import session id)
+pub fn transaction_values(tx: &Transaction) -> Value {
+    let mut values = tx.clone();
+    values.metadata.remove(IMPORT_SESSION_KEY);
+    values.id = String::new();
+    values.version = 0;
+    values.system_time = None;
+    values.valid_from = None;
+    values.valid_until = None;
+    values.previous_version_id = None;
+    serde_json::to_value(&values).unwrap_or_default()
+}
+
+/// Every input of one import session, and what it produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRecording {
+    pub session_id: String,
+    pub ledger_id: String,
+    pub filename: String,
+    #[serde(skip)]
+    pub content: Vec<u8>,
+    /// SHA-256 of `content`, hex
+    pub content_hash: String,
+    pub config: LedgerConfig,
+    /// Rules the import classified with, highest priority first
+    pub rules: Vec<ClassificationRule>,
+    /// rule id → version stored at the time of the import
+    pub rule_versions: BTreeMap<String, i64>,
+    /// The import's clock
+    pub recorded_at: DateTime<Utc>,
+    pub actor: String,
+    pub outcome: ImportOutcome,
+}
+
+/// Store the inputs and outcome of an import (called by `import_statement_with`)
+pub fn record_import(
+    conn: &Connection,
+    session: &ImportSession,
+    content: &[u8],
+    context: &ImportContext,
+    actor: &str,
+    config: &LedgerConfig,
+    inserted: &[Transaction],
+) -> Result<()> {
+    let rule_versions: BTreeMap<String, i64> = get_current_rules(conn)?
+        .into_iter()
+        .map(|versioned| (versioned.rule.id, versioned.version))
+        .collect();
+
+    conn.execute(
+        "INSERT INTO import_recordings
+            (session_id, ledger_id, filename, content, content_hash, config, rules, rule_versions,
+             recorded_at, actor, outcome)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            session.id,
+            session.ledger_id,
+            session.filename,
+            content,
+            content_sha256(content),
+            serde_json::to_string(config)?,
+            serde_json::to_string(context.rules.rules())?,
+            serde_json::to_string(&rule_versions)?,
+            context.now.to_rfc3339(),
+            actor,
+            serde_json::to_string(&ImportOutcome::new(session, inserted))?,
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn get_import_recording(conn: &Connection, session_id: &str) -> Result<Option<ImportRecording>> {
+    Ok(conn
+        .query_row(
+            "SELECT session_id, ledger_id, filename, content, content_hash, config, rules, rule_versions,
+                    recorded_at, actor, outcome
+             FROM import_recordings WHERE session_id = ?1",
+            [session_id],
+            row_to_recording,
+        )
+        .optional()?)
+}
+
+fn row_to_recording(row: &rusqlite::Row) -> rusqlite::Result<ImportRecording> {
+    fn json<T: serde::de::DeserializeOwned>(row: &rusqlite::Row, index: usize) -> rusqlite::Result<T> {
+        let text: String = row.get(index)?;
+        serde_json::from_str(&text).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))
+        })
+    }
+
+    let recorded_at: String = row.get(8)?;
+    Ok(ImportRecording {
+        session_id: row.get(0)?,
+        ledger_id: row.get(1)?,
+        filename: row.get(2)?,
+        content: row.get(3)?,
+        content_hash: row.get(4)?,
+        config: json(row, 5)?,
+        rules: json(row, 6)?,
+        rule_versions: json(row, 7)?,
+        recorded_at: DateTime::parse_from_rfc3339(&recorded_at)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(8, rusqlite::types::Type::Text, Box::new(e)))?,
+        actor: row.get(9)?,
+        outcome: json(row, 10)?,
+    })
+}
+
+// ============================================================================
+// REPLAY
+// ============================================================================
+
+/// One place where the replay differs from the recording
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutcomeDifference {
+    /// e.g. "inserted", "issue 2", "row 3 Category"
+    pub field: String,
+    pub recorded: String,
+    pub replayed: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayReport {
+    pub session_id: String,
+    pub ledger_id: String,
+    pub filename: String,
+    pub recorded_at: DateTime<Utc>,
+    pub content_hash: String,
+    /// The stored bytes still hash to the recorded value
+    pub content_intact: bool,
+    /// The replay produced exactly the recorded outcome
+    pub reproduced: bool,
+    pub differences: Vec<OutcomeDifference>,
+    /// Stored rules added, changed or retired since the import
+    pub rule_changes: Vec<String>,
+    /// Ledger config keys changed since the import
+    pub config_changes: Vec<String>,
+}
+
+impl ReplayReport {
+    /// Plain-text report, one finding per line
+    pub fn render(&self) -> String {
+        let mut lines = vec![
+            format!("Replay of import {} ({})", self.session_id, self.filename),
+            format!("  ledger:      {}", self.ledger_id),
+            format!("  recorded at: {}", self.recorded_at.to_rfc3339()),
+            format!(
+                "  input:       sha256 {} ({})",
+                self.content_hash,
+                if self.content_intact { "intact" } else { "DOES NOT MATCH the stored bytes" }
+            ),
+            format!(
+                "  outcome:     {}",
+                if self.reproduced { "reproduced exactly" } else { "DIFFERENT from the recording" }
+            ),
+        ];
+        for difference in &self.differences {
+            lines.push(format!(
+                "    {}: recorded {} / replayed {}",
+                difference.field, difference.recorded, difference.replayed
+            ));
+        }
+
+        lines.push("Changed since the import:".to_string());
+        if self.rule_changes.is_empty() && self.config_changes.is_empty() {
+            lines.push("  nothing (same rules, same ledger config)".to_string());
+        }
+        lines.extend(self.rule_changes.iter().map(|change| format!("  {}", change)));
+        lines.extend(self.config_changes.iter().map(|change| format!("  config {}", change)));
+        lines.join("\n") + "\n"
+    }
+}
+
+/// Run a recorded import again and compare it with the recording
+///
+/// The scratch database gets every ledger, the recorded config for the
+/// imported one, and the transactions that were valid just before the import.
+pub fn replay_import(conn: &Connection, session_id: &str) -> Result<ReplayReport> {
+    let recording = get_import_recording(conn, session_id)?
+        .ok_or_else(|| anyhow!("No recording for import {} (is record_imports set on the ledger?)", session_id))?;
+
+    let scratch = Connection::open_in_memory()?;
+    setup_database(&scratch)?;
+    for ledger in list_ledgers(conn)? {
+        if get_ledger(&scratch, &ledger.id)?.is_none() {
+            create_ledger(&scratch, &ledger, REPLAY_ACTOR)?;
+        }
+    }
+    let mut config = recording.config.clone();
+    config.record_imports = true;
+    update_ledger_config(&scratch, &recording.ledger_id, &config, REPLAY_ACTOR)?;
+
+    for tx in transactions_as_of(conn, recording.recorded_at - Duration::microseconds(1))? {
+        insert_transaction_as(&scratch, &tx, REPLAY_ACTOR)?;
+    }
+
+    let rules = RuleEngine::from_rules(recording.rules.clone());
+    let deduplication = DeduplicationEngine::new();
+    let prices = recorded_prices(&recording.outcome.transactions);
+    let context = ImportContext {
+        rules: &rules,
+        deduplication: &deduplication,
+        prices: Some(&prices),
+        now: recording.recorded_at,
+        upsert: recording.outcome.upsert,
+    };
+    let session = import_statement_with(
+        &scratch,
+        &recording.filename,
+        &recording.content,
+        &recording.ledger_id,
+        &recording.actor,
+        &context,
+    )?;
+    let replayed = get_import_recording(&scratch, &session.id)?
+        .ok_or_else(|| anyhow!("Replay of import {} was not recorded", session_id))?
+        .outcome;
+
+    let differences = compare_outcomes(&recording.outcome, &replayed);
+    let rule_changes = rule_changes(&recording.rule_versions, conn)?;
+    let current_config = require_ledger(conn, &recording.ledger_id)?.config;
+    Ok(ReplayReport {
+        session_id: recording.session_id,
+        ledger_id: recording.ledger_id,
+        filename: recording.filename,
+        recorded_at: recording.recorded_at,
+        content_intact: content_sha256(&recording.content) == recording.content_hash,
+        content_hash: recording.content_hash,
+        reproduced: differences.is_empty(),
+        differences,
+        rule_changes,
+        config_changes: config_changes(&recording.config, &current_config),
+    })
+}
+
+fn difference(field: &str, recorded: impl ToString, replayed: impl ToString) -> OutcomeDifference {
+    OutcomeDifference { field: field.to_string(), recorded: recorded.to_string(), replayed: replayed.to_string() }
+}
+
+fn describe_issue(issue: Option<&RowIssue>) -> String {
+    match issue {
+        Some(issue) => format!("line {} {:?} {}: {}", issue.line, issue.severity, issue.field, issue.message),
+        None => "(none)".to_string(),
+    }
+}
+
+/// Crypto prices the recorded rows were valued at, so a replay values them
+/// the same way
+fn recorded_prices(transactions: &[Value]) -> HistoricalRates {
+    let mut prices = HistoricalRates::new();
+    for tx in transactions.iter().filter_map(|values| serde_json::from_value::<Transaction>(values.clone()).ok()) {
+        let asset = tx.get_metadata(ASSET_KEY).and_then(|v| v.as_str());
+        let price = tx.get_metadata(ASSET_PRICE_KEY).and_then(|v| v.as_f64());
+        if let (Some(asset), Some(price), Some(date)) = (asset, price, tx.parsed_date()) {
+            prices.insert(asset, &tx.currency, date, price);
+        }
+    }
+    prices
+}
+
+/// Field-by-field differences between two outcomes (empty when identical)
+pub fn compare_outcomes(recorded: &ImportOutcome, replayed: &ImportOutcome) -> Vec<OutcomeDifference> {
+    let mut differences = Vec::new();
+    if recorded.source != replayed.source {
+        differences.push(difference("source", &recorded.source, &replayed.source));
+    }
+    for (field, a, b) in [
+        ("rows", recorded.rows, replayed.rows),
+        ("inserted", recorded.inserted, replayed.inserted),
+        ("duplicates", recorded.duplicates, replayed.duplicates),
+        ("repeated", recorded.repeated, replayed.repeated),
+        ("updated", recorded.updated, replayed.updated),
+        ("settled", recorded.settled, replayed.settled),
+        ("failed", recorded.failed, replayed.failed),
+    ] {
+        if a != b {
+            differences.push(difference(field, a, b));
+        }
+    }
+
+    for i in 0..recorded.issues.len().max(replayed.issues.len()) {
+        let (a, b) = (recorded.issues.get(i), replayed.issues.get(i));
+        if a != b {
+            differences.push(difference(&format!("issue {}", i + 1), describe_issue(a), describe_issue(b)));
+        }
+    }
+
+    let empty = serde_json::Map::new();
+    for i in 0..recorded.transactions.len().max(replayed.transactions.len()) {
+        let a = recorded.transactions.get(i).and_then(Value::as_object);
+        let b = replayed.transactions.get(i).and_then(Value::as_object);
+        let line = a.or(b).and_then(|row| row.get("Line_Number")).and_then(Value::as_str).unwrap_or("?");
+        if a.is_none() || b.is_none() {
+            let present = |row: Option<&serde_json::Map<String, Value>>| if row.is_some() { "inserted" } else { "(none)" };
+            differences.push(difference(&format!("row {}", line), present(a), present(b)));
+            continue;
+        }
+
+        let (a, b) = (a.unwrap_or(&empty), b.unwrap_or(&empty));
+        let keys: std::collections::BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+        for key in keys {
+            let (x, y) = (a.get(key).unwrap_or(&Value::Null), b.get(key).unwrap_or(&Value::Null));
+            if x != y {
+                differences.push(difference(&format!("row {} {}", line, key), x, y));
+            }
+        }
+    }
+
+    differences
+}
+
+/// Stored rule versions now vs. at the time of the import
+fn rule_changes(recorded: &BTreeMap<String, i64>, conn: &Connection) -> Result<Vec<String>> {
+    let current: BTreeMap<String, i64> = get_current_rules(conn)?
+        .into_iter()
+        .map(|versioned| (versioned.rule.id, versioned.version))
+        .collect();
+
+    let mut changes = Vec::new();
+    for (id, version) in recorded {
+        match current.get(id) {
+            None => changes.push(format!("rule {} retired (was v{})", id, version)),
+            Some(now) if now != version => changes.push(format!("rule {} changed: v{} → v{}", id, version, now)),
+            Some(_) => {}
+        }
+    }
+    for (id, version) in &current {
+        if !recorded.contains_key(id) {
+            changes.push(format!("rule {} added (v{})", id, version));
+        }
+    }
+    Ok(changes)
+}
+
+/// Config keys whose value differs, as "key: old → new"
+fn config_changes(recorded: &LedgerConfig, current: &LedgerConfig) -> Vec<String> {
+    let as_map = |config: &LedgerConfig| match serde_json::to_value(config) {
+        Ok(Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    };
+    let (before, after) = (as_map(recorded), as_map(current));
+    let keys: std::collections::BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+
+    keys.into_iter()
+        .filter(|key| before.get(*key) != after.get(*key))
+        .map(|key| {
+            let show = |value: Option<&Value>| value.map(Value::to_string).unwrap_or_else(|| "(unset)".to_string());
+            format!("{}: {} → {}", key, show(before.get(key)), show(after.get(key)))
+        })
+        .collect()
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::DEFAULT_LEDGER_ID;
+    use crate::rules::save_rule;
+
+    const BOFA: &str = "Date,Description,Amount\n\
+        01/02/2025,STARBUCKS STORE 123,-5.25\n\
+        01/03/2025,SHELL OIL 5521,-40.00\n";
+
+    fn coffee_rule(category: &str) -> ClassificationRule {
+        ClassificationRule {
+            id: "coffee".to_string(),
+            pattern: "STARBUCKS".to_string(),
+            merchant: Some("Starbucks".to_string()),
+            category: Some(category.to_string()),
+            transaction_type: None,
+            confidence: 0.9,
+            description: None,
+            priority: 0,
+        }
+    }
+
+    /// Database recording imports, with one stored rule
+    fn recording_database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let mut ledger = require_ledger(&conn, DEFAULT_LEDGER_ID).unwrap();
+        ledger.config.set("record_imports", "true").unwrap();
+        update_ledger_config(&conn, DEFAULT_LEDGER_ID, &ledger.config, "ana").unwrap();
+        save_rule(&conn, &coffee_rule("Coffee"), "ana", None).unwrap();
+        conn
+    }
+
+    fn import(conn: &Connection, content: &str) -> ImportSession {
+        let rules = RuleEngine::from_database(conn).unwrap();
+        let deduplication = DeduplicationEngine::new();
+        let context = ImportContext::new(&rules, &deduplication);
+        import_statement_with(conn, "bofa_jan.csv", content.as_bytes(), DEFAULT_LEDGER_ID, "ana", &context).unwrap()
+    }
+
+    #[test]
+    fn test_replay_reproduces_the_recorded_import() {
+        let conn = recording_database();
+        // Rows already in the ledger decide what counts as a duplicate
+        import(&conn, "Date,Description,Amount\n01/02/2025,STARBUCKS STORE 123,-5.25\n");
+        let session = import(&conn, BOFA);
+        assert_eq!((session.inserted, session.duplicates), (1, 1));
+
+        let recording = get_import_recording(&conn, &session.id).unwrap().unwrap();
+        assert_eq!(recording.rule_versions.get("coffee"), Some(&1));
+        assert_eq!(recording.content, BOFA.as_bytes());
+
+        let report = replay_import(&conn, &session.id).unwrap();
+        assert!(report.content_intact);
+        assert!(report.reproduced, "{:?}", report.differences);
+        assert!(report.rule_changes.is_empty() && report.config_changes.is_empty());
+        assert!(report.render().contains("reproduced exactly"));
+
+        assert!(replay_import(&conn, "nope").is_err());
+    }
+
+    #[test]
+    fn test_replay_reports_differences_and_what_changed() {
+        let conn = recording_database();
+        let session = import(&conn, BOFA);
+
+        // Tamper with the recording: as if the import had used another rule
+        conn.execute(
+            "UPDATE import_recordings SET rules = ?1 WHERE session_id = ?2",
+            params![serde_json::to_string(&[coffee_rule("Restaurants")]).unwrap(), session.id],
+        )
+        .unwrap();
+        save_rule(&conn, &coffee_rule("Cafe"), "ana", Some("rename")).unwrap();
+        let mut config = require_ledger(&conn, DEFAULT_LEDGER_ID).unwrap().config;
+        config.set("approval_threshold", "500").unwrap();
+        update_ledger_config(&conn, DEFAULT_LEDGER_ID, &config, "ana").unwrap();
+
+        let report = replay_import(&conn, &session.id).unwrap();
+        assert!(!report.reproduced);
+        assert!(report
+            .differences
+            .iter()
+            .any(|d| d.field == "row 2 Category" && d.recorded == "\"Coffee\"" && d.replayed == "\"Restaurants\""));
+        assert_eq!(report.rule_changes, vec!["rule coffee changed: v1 → v2".to_string()]);
+        assert_eq!(report.config_changes, vec!["approval_threshold: (unset) → 500.0".to_string()]);
+        assert!(report.render().contains("DIFFERENT from the recording"));
+    }
+}
@@ -0,0 +1,368 @@
+// 🖨️ Report rendering - Self-contained HTML (and optional PDF) period reports
+//
+// Problem solved:
+// - Reports only existed as terminal output, which can't be emailed or archived
+// - A month's numbers were spread over several commands (summary, doctor, statements)
+//
+// `render_html` produces one file with inline CSS and no external assets, so it
+// opens the same way from an inbox or an archive years later. With the
+// `report-pdf` feature, `render_pdf` writes the same content as a plain-text PDF
+// (built-in Courier font, no extra dependencies). Amounts and dates follow the
+// given locale.
+
+use crate::locale::Locale;
+use crate::reports::PeriodReport;
+use std::fmt::Write as _;
+
+/// Escape text for HTML element content and attribute values
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn percent(value: f64) -> String {
+    if value.is_finite() { format!("{:.1}%", value * 100.0) } else { "n/a".to_string() }
+}
+
+/// Categories by spend, largest first
+fn categories_by_spend(report: &PeriodReport) -> Vec<(&str, f64)> {
+    let mut categories: Vec<(&str, f64)> = report
+        .summary
+        .by_category
+        .iter()
+        .map(|(category, total)| (category.as_str(), *total))
+        .collect();
+    categories.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    categories
+}
+
+const STYLE: &str = "body{font-family:-apple-system,Helvetica,Arial,sans-serif;color:#222;max-width:760px;margin:2em auto;padding:0 1em}\
+h1{font-size:1.5em;margin-bottom:0}h2{font-size:1.1em;border-bottom:1px solid #ddd;padding-bottom:.2em;margin-top:2em}\
+.muted{color:#777;font-size:.9em}table{border-collapse:collapse;width:100%}td,th{padding:.3em .5em;text-align:left}\
+td.num{text-align:right;font-variant-numeric:tabular-nums}.bar{background:#4a7bd0;height:.8em;border-radius:2px}\
+.ok{color:#1a7f37}.warn{color:#b35900}.bad{color:#c62828}";
+
+/// Render a period report as a standalone HTML document
+pub fn render_html(report: &PeriodReport, locale: &Locale) -> String {
+    let summary = &report.summary;
+    let mut html = String::new();
+
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{} – {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(&report.ledger_name),
+        escape(&summary.period.label),
+        STYLE
+    );
+    let _ = writeln!(
+        html,
+        "<h1>{}</h1>\n<p class=\"muted\">{} ({} to {}) · generated {}</p>",
+        escape(&report.ledger_name),
+        escape(&summary.period.label),
+        locale.date(summary.period.start),
+        locale.date(summary.period.end),
+        report.generated_at.format("%Y-%m-%d %H:%M UTC")
+    );
+
+    // Cash flow
+    html.push_str("<h2>Cash flow</h2>\n<table>\n");
+    let net_class = if summary.net() < 0.0 { "bad" } else { "ok" };
+    for (label, amount, class) in [
+        ("Income", summary.income, ""),
+        ("Expenses", -summary.expenses, ""),
+        ("Net", summary.net(), net_class),
+        ("Card payments", summary.card_payments, "muted"),
+        ("Transfers", summary.transfers, "muted"),
+    ] {
+        let _ = writeln!(
+            html,
+            "<tr class=\"{}\"><td>{}</td><td class=\"num\">{}</td></tr>",
+            class,
+            label,
+            locale.amount(amount)
+        );
+    }
+    let _ = writeln!(html, "</table>\n<p class=\"muted\">{} transactions</p>", summary.transactions);
+
+    // Category breakdown
+    html.push_str("<h2>Spending by category</h2>\n");
+    let categories = categories_by_spend(report);
+    if categories.is_empty() {
+        html.push_str("<p class=\"muted\">No expenses in this period.</p>\n");
+    } else {
+        let largest = categories[0].1.max(f64::EPSILON);
+        html.push_str("<table>\n");
+        for (category, total) in &categories {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td class=\"num\">{}</td><td style=\"width:45%\"><div class=\"bar\" style=\"width:{:.1}%\"></div></td></tr>",
+                escape(category),
+                locale.amount(*total),
+                total / largest * 100.0
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    // Data quality
+    let quality = &report.quality;
+    let _ = writeln!(
+        html,
+        "<h2>Data quality</h2>\n<table>\n<tr><td>Average quality</td><td class=\"num\">{}</td></tr>\n<tr><td>Average confidence</td><td class=\"num\">{}</td></tr>\n<tr><td>Need review</td><td class=\"num {}\">{}</td></tr>\n<tr><td>Critical issues</td><td class=\"num {}\">{}</td></tr>\n</table>",
+        percent(quality.average_quality),
+        percent(quality.average_confidence),
+        if quality.needs_review_count > 0 { "warn" } else { "ok" },
+        quality.needs_review_count,
+        if quality.critical_issues_count > 0 { "bad" } else { "ok" },
+        quality.critical_issues_count
+    );
+
+    // Reconciliation
+    html.push_str("<h2>Reconciliation</h2>\n");
+    if report.reconciliation.is_empty() {
+        html.push_str("<p class=\"muted\">No statement balances recorded (see <code>statement close</code>).</p>\n");
+    } else {
+        html.push_str("<table>\n<tr><th>Account</th><th>Last statement</th><th class=\"num\">Closing</th><th>Status</th></tr>\n");
+        for account in &report.reconciliation {
+            let (statement, closing) = match &account.last_statement {
+                Some(snapshot) => (
+                    format!("{} ({})", escape(&snapshot.statement_period), locale.date(snapshot.statement_date)),
+                    locale.amount(snapshot.closing_balance),
+                ),
+                None => ("none".to_string(), String::new()),
+            };
+            let status = if account.is_reconciled() {
+                "<span class=\"ok\">✓ continuous</span>".to_string()
+            } else if account.last_statement.is_none() {
+                "<span class=\"warn\">no statement yet</span>".to_string()
+            } else {
+                let details: Vec<String> = account.breaks.iter().map(|b| escape(&b.description)).collect();
+                format!("<span class=\"bad\">{} break(s)</span><br><span class=\"muted\">{}</span>", account.breaks.len(), details.join("<br>"))
+            };
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td class=\"num\">{}</td><td>{}</td></tr>",
+                escape(&account.account_id),
+                statement,
+                closing,
+                status
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Plain-text lines of a report (used for PDF output)
+pub fn report_lines(report: &PeriodReport, locale: &Locale) -> Vec<String> {
+    let summary = &report.summary;
+    let mut lines = vec![
+        format!("{} - {}", report.ledger_name, summary.period.label),
+        format!(
+            "{} to {}, generated {}",
+            locale.date(summary.period.start),
+            locale.date(summary.period.end),
+            report.generated_at.format("%Y-%m-%d %H:%M UTC")
+        ),
+        String::new(),
+        "CASH FLOW".to_string(),
+        format!("  Income         {:>14}", locale.amount(summary.income)),
+        format!("  Expenses       {:>14}", locale.amount(-summary.expenses)),
+        format!("  Net            {:>14}", locale.amount(summary.net())),
+        format!("  Card payments  {:>14}", locale.amount(summary.card_payments)),
+        format!("  Transfers      {:>14}", locale.amount(summary.transfers)),
+        format!("  {} transactions", summary.transactions),
+        String::new(),
+        "SPENDING BY CATEGORY".to_string(),
+    ];
+    for (category, total) in categories_by_spend(report) {
+        lines.push(format!("  {:<28} {:>12}", category, locale.amount(total)));
+    }
+
+    let quality = &report.quality;
+    lines.extend([
+        String::new(),
+        "DATA QUALITY".to_string(),
+        format!(
+            "  Quality {}, confidence {}, {} need review, {} critical",
+            percent(quality.average_quality),
+            percent(quality.average_confidence),
+            quality.needs_review_count,
+            quality.critical_issues_count
+        ),
+        String::new(),
+        "RECONCILIATION".to_string(),
+    ]);
+    for account in &report.reconciliation {
+        let state = match (&account.last_statement, account.breaks.len()) {
+            (None, _) => "no statement yet".to_string(),
+            (Some(snapshot), 0) => format!("continuous through {}", snapshot.statement_period),
+            (Some(_), breaks) => format!("{} break(s)", breaks),
+        };
+        lines.push(format!("  {:<28} {}", account.account_id, state));
+        for discrepancy in &account.breaks {
+            lines.push(format!("    {}", discrepancy.description));
+        }
+    }
+    lines
+}
+
+/// Render a period report as a simple text PDF (A4 pages, Courier)
+#[cfg(feature = "report-pdf")]
+pub fn render_pdf(report: &PeriodReport, locale: &Locale) -> Vec<u8> {
+    const LINES_PER_PAGE: usize = 54;
+
+    // The built-in fonts only cover WinAnsi: keep text to printable ASCII
+    let pdf_text = |line: &str| -> String {
+        line.chars()
+            .map(|c| match c {
+                '(' | ')' | '\\' => format!("\\{}", c),
+                '–' | '—' => "-".to_string(),
+                '\u{a0}' => " ".to_string(),
+                c if c.is_ascii() && !c.is_ascii_control() => c.to_string(),
+                _ => "?".to_string(),
+            })
+            .collect()
+    };
+
+    let lines = report_lines(report, locale);
+    let pages: Vec<&[String]> = lines.chunks(LINES_PER_PAGE).collect();
+
+    // Objects: 1 catalog, 2 page tree, 3 font, then (page, content) per page
+    let mut objects: Vec<String> = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        String::new(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier >>".to_string(),
+    ];
+    let mut kids = Vec::new();
+    for page in &pages {
+        let page_id = objects.len() + 1;
+        kids.push(format!("{} 0 R", page_id));
+
+        let mut stream = String::from("BT /F1 10 Tf 12 TL 50 800 Td\n");
+        for line in page.iter() {
+            let _ = writeln!(stream, "({}) '", pdf_text(line));
+        }
+        stream.push_str("ET");
+
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            page_id + 1
+        ));
+        objects.push(format!("<< /Length {} >>\nstream\n{}\nendstream", stream.len(), stream));
+    }
+    objects[1] = format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len());
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        let _ = write!(pdf, "{} 0 obj\n{}\nendobj\n", index + 1, object);
+    }
+    let xref_offset = pdf.len();
+    let _ = write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(pdf, "{:010} 00000 n ", offset);
+    }
+    let _ = write!(
+        pdf,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref_offset
+    );
+    pdf.into_bytes()
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_quality::BatchSummary;
+    use crate::reports::{AccountReconciliation, Period, PeriodSummary};
+    use crate::statements::BalanceSnapshot;
+    use chrono::{NaiveDate, Utc};
+    use std::collections::BTreeMap;
+
+    fn report() -> PeriodReport {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        PeriodReport {
+            ledger_id: "default".to_string(),
+            ledger_name: "Home <Books>".to_string(),
+            summary: PeriodSummary {
+                period: Period { start: date("2025-01-01"), end: date("2025-01-31"), label: "Jan 2025".to_string() },
+                transactions: 3,
+                income: 2000.0,
+                expenses: 150.0,
+                card_payments: 0.0,
+                transfers: 0.0,
+                by_category: BTreeMap::from([("Groceries".to_string(), 100.0), ("Restaurants".to_string(), 50.0)]),
+            },
+            quality: BatchSummary {
+                total_transactions: 3,
+                high_quality_count: 3,
+                needs_review_count: 0,
+                critical_issues_count: 0,
+                average_quality: 0.95,
+                average_confidence: 0.9,
+            },
+            reconciliation: vec![AccountReconciliation {
+                account_id: "bofa-checking".to_string(),
+                last_statement: Some(BalanceSnapshot {
+                    account_id: "bofa-checking".to_string(),
+                    ledger_id: "default".to_string(),
+                    statement_period: "January 2025".to_string(),
+                    statement_date: date("2025-01-31"),
+                    opening_balance: 1000.0,
+                    closing_balance: 2850.0,
+                    declared_count: None,
+                    declared_total: None,
+                }),
+                breaks: vec![],
+            }],
+            generated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_html_is_self_contained_and_escaped() {
+        let html = render_html(&report(), &Locale::default());
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("Home &lt;Books&gt;"));
+        assert!(!html.contains("<Books>"));
+        assert!(html.contains("1850.00"));
+        assert!(html.contains("✓ continuous"));
+        assert!(!html.contains("<link") && !html.contains("<script"));
+
+        // Largest category first
+        assert!(html.find("Groceries").unwrap() < html.find("Restaurants").unwrap());
+
+        let german = render_html(&report(), &Locale::parse("de-DE").unwrap());
+        assert!(german.contains("1.850,00") && german.contains("2.850,00"));
+        assert!(german.contains("(01.01.2025 to 31.01.2025)"));
+    }
+
+    #[cfg(feature = "report-pdf")]
+    #[test]
+    fn test_pdf_structure() {
+        let pdf = String::from_utf8(render_pdf(&report(), &Locale::default())).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4"));
+        assert!(pdf.trim_end().ends_with("%%EOF"));
+        assert!(pdf.contains("/Count 1"));
+        assert!(pdf.contains("Groceries"));
+    }
+}
@@ -0,0 +1,434 @@
+// 📈 Reports - Period summaries on calendar, statement or fiscal boundaries
+//
+// Problem solved:
+// - "Monthly" totals used calendar months, but a card statement closing on the
+//   15th never lined up with them, so summaries couldn't be checked against it
+// - Businesses with a fiscal year starting in July had no yearly view at all
+//
+// A ReportCalendar (built from the ledger config) says where periods start and
+// end. Every summary groups transactions by the period containing their date,
+// or their posted date when the ledger reports on the posted basis.
+
+use crate::data_quality::{BatchSummary, DataQualityEngine};
+use crate::db::{get_active_transactions, DateBasis, Transaction};
+use crate::ledger::{require_ledger, LedgerConfig};
+use crate::query::TransactionFilter;
+use crate::reconciliation::{Discrepancy, ReconciliationEngine};
+use crate::statements::{balance_history, snapshot_accounts, BalanceSnapshot};
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// ============================================================================
+// PERIOD DEFINITIONS
+// ============================================================================
+
+/// How a year is cut into reporting periods
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum PeriodDefinition {
+    /// 1st to last day of each month
+    #[default]
+    CalendarMonth,
+
+    /// Statement cycles closing on this day of each month (clamped in short
+    /// months); a cycle runs from the day after the previous close
+    StatementCycle { closing_day: u32 },
+}
+
+/// A reporting period (both ends inclusive)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Period {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub label: String,
+}
+
+impl Period {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && date <= self.end
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportCalendar {
+    /// Month (1-12) the fiscal year starts in; 1 = calendar year
+    pub fiscal_year_start_month: u32,
+    pub period: PeriodDefinition,
+
+    /// Transaction or posted date decides a transaction's period
+    #[serde(default)]
+    pub date_basis: DateBasis,
+}
+
+impl Default for ReportCalendar {
+    fn default() -> Self {
+        ReportCalendar {
+            fiscal_year_start_month: 1,
+            period: PeriodDefinition::CalendarMonth,
+            date_basis: DateBasis::Transaction,
+        }
+    }
+}
+
+impl ReportCalendar {
+    /// Calendar configured for a ledger (`fiscal_year_start_month`,
+    /// `statement_closing_day`, `date_basis`)
+    pub fn from_ledger_config(config: &LedgerConfig) -> Self {
+        ReportCalendar {
+            fiscal_year_start_month: config.fiscal_year_start_month.unwrap_or(1).clamp(1, 12),
+            period: match config.statement_closing_day {
+                Some(closing_day) => PeriodDefinition::StatementCycle { closing_day },
+                None => PeriodDefinition::CalendarMonth,
+            },
+            date_basis: config.date_basis.unwrap_or_default(),
+        }
+    }
+
+    /// The same calendar placing transactions by `date_basis`
+    pub fn with_date_basis(mut self, date_basis: DateBasis) -> Self {
+        self.date_basis = date_basis;
+        self
+    }
+
+    /// The period a date falls in
+    pub fn period_containing(&self, date: NaiveDate) -> Period {
+        match self.period {
+            PeriodDefinition::CalendarMonth => {
+                let start = first_of_month(date);
+                let end = last_of_month(start);
+                Period { start, end, label: start.format("%b %Y").to_string() }
+            }
+            PeriodDefinition::StatementCycle { closing_day } => {
+                let this_close = closing_date(first_of_month(date), closing_day);
+                let end = if date <= this_close {
+                    this_close
+                } else {
+                    closing_date(add_months(first_of_month(date), 1), closing_day)
+                };
+                let previous_close = closing_date(sub_months(first_of_month(end), 1), closing_day);
+                let start = previous_close.succ_opt().unwrap_or(previous_close);
+                Period {
+                    start,
+                    end,
+                    label: format!("{} – {}", start.format("%b %d"), end.format("%b %d %Y")),
+                }
+            }
+        }
+    }
+
+    /// Every period overlapping `from..=to`, in order
+    pub fn periods_between(&self, from: NaiveDate, to: NaiveDate) -> Vec<Period> {
+        let mut periods = Vec::new();
+        let mut date = from;
+        while date <= to {
+            let period = self.period_containing(date);
+            date = match period.end.succ_opt() {
+                Some(next) => next,
+                None => break,
+            };
+            periods.push(period);
+        }
+        periods
+    }
+
+    /// The fiscal year a date falls in, labelled by the year it ends in
+    /// (a July-June fiscal year from Jul 2024 to Jun 2025 is "FY2025")
+    pub fn fiscal_year_containing(&self, date: NaiveDate) -> Period {
+        let start_month = self.fiscal_year_start_month.clamp(1, 12);
+        let start_year = if date.month() >= start_month { date.year() } else { date.year() - 1 };
+        let start = NaiveDate::from_ymd_opt(start_year, start_month, 1).unwrap_or(date);
+        let end = sub_days(add_months(start, 12), 1);
+
+        let label = if start_month == 1 {
+            format!("{}", start.year())
+        } else {
+            format!("FY{}", end.year())
+        };
+        Period { start, end, label }
+    }
+}
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+fn add_months(date: NaiveDate, months: u32) -> NaiveDate {
+    date.checked_add_months(Months::new(months)).unwrap_or(date)
+}
+
+fn sub_months(date: NaiveDate, months: u32) -> NaiveDate {
+    date.checked_sub_months(Months::new(months)).unwrap_or(date)
+}
+
+fn sub_days(date: NaiveDate, days: u64) -> NaiveDate {
+    date.checked_sub_days(chrono::Days::new(days)).unwrap_or(date)
+}
+
+fn last_of_month(first: NaiveDate) -> NaiveDate {
+    sub_days(add_months(first, 1), 1)
+}
+
+/// Closing date in the month starting at `first` (day 31 → last day)
+fn closing_date(first: NaiveDate, closing_day: u32) -> NaiveDate {
+    let last = last_of_month(first);
+    first.with_day(closing_day.clamp(1, last.day())).unwrap_or(last)
+}
+
+// ============================================================================
+// SUMMARIES
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PeriodSummary {
+    pub period: Period,
+    pub transactions: usize,
+
+    /// INGRESO total (positive)
+    pub income: f64,
+
+    /// GASTO total (positive)
+    pub expenses: f64,
+
+    /// PAGO_TARJETA and TRASPASO totals (positive; money moved, not spent)
+    pub card_payments: f64,
+    pub transfers: f64,
+
+    /// Expenses per category (positive)
+    pub by_category: BTreeMap<String, f64>,
+}
+
+impl PeriodSummary {
+    fn new(period: Period) -> Self {
+        PeriodSummary {
+            period,
+            transactions: 0,
+            income: 0.0,
+            expenses: 0.0,
+            card_payments: 0.0,
+            transfers: 0.0,
+            by_category: BTreeMap::new(),
+        }
+    }
+
+    pub fn net(&self) -> f64 {
+        self.income - self.expenses
+    }
+
+    fn add(&mut self, tx: &Transaction) {
+        self.transactions += 1;
+        match tx.transaction_type.as_str() {
+            "INGRESO" => self.income += tx.amount_numeric.abs(),
+            "GASTO" => {
+                self.expenses += tx.amount_numeric.abs();
+                *self.by_category.entry(tx.category.clone()).or_insert(0.0) += tx.amount_numeric.abs();
+            }
+            "PAGO_TARJETA" => self.card_payments += tx.amount_numeric.abs(),
+            "TRASPASO" => self.transfers += tx.amount_numeric.abs(),
+            _ => {}
+        }
+    }
+}
+
+/// Summaries per reporting period (calendar month or statement cycle)
+pub fn summarize_by_period(transactions: &[Transaction], calendar: &ReportCalendar) -> Vec<PeriodSummary> {
+    summarize(transactions, calendar.date_basis, |date| calendar.period_containing(date))
+}
+
+/// Summaries per fiscal year
+pub fn summarize_by_fiscal_year(transactions: &[Transaction], calendar: &ReportCalendar) -> Vec<PeriodSummary> {
+    summarize(transactions, calendar.date_basis, |date| calendar.fiscal_year_containing(date))
+}
+
+/// Group current, non-voided transactions by period (oldest first)
+fn summarize(
+    transactions: &[Transaction],
+    basis: DateBasis,
+    period_of: impl Fn(NaiveDate) -> Period,
+) -> Vec<PeriodSummary> {
+    let mut summaries: BTreeMap<NaiveDate, PeriodSummary> = BTreeMap::new();
+    for tx in transactions.iter().filter(|tx| tx.is_active() && !tx.is_voided()) {
+        let Some(date) = tx.date_on(basis) else { continue };
+        let period = period_of(date);
+        summaries
+            .entry(period.start)
+            .or_insert_with(|| PeriodSummary::new(period))
+            .add(tx);
+    }
+    summaries.into_values().collect()
+}
+
+// ============================================================================
+// PERIOD REPORT (rendered by report_render.rs)
+// ============================================================================
+
+/// Reconciliation state of one account as of a report's period end
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountReconciliation {
+    pub account_id: String,
+
+    /// Latest statement closed on or before the period end
+    pub last_statement: Option<BalanceSnapshot>,
+
+    /// Continuity breaks up to the period end
+    pub breaks: Vec<Discrepancy>,
+}
+
+impl AccountReconciliation {
+    pub fn is_reconciled(&self) -> bool {
+        self.last_statement.is_some() && self.breaks.is_empty()
+    }
+}
+
+/// Everything a rendered period report shows
+#[derive(Debug, Clone, Serialize)]
+pub struct PeriodReport {
+    pub ledger_id: String,
+    pub ledger_name: String,
+    pub summary: PeriodSummary,
+    pub quality: BatchSummary,
+    pub reconciliation: Vec<AccountReconciliation>,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Gather cash flow, categories, quality and reconciliation for one period
+pub fn build_period_report(conn: &Connection, ledger_id: &str, period: &Period) -> Result<PeriodReport> {
+    let ledger = require_ledger(conn, ledger_id)?;
+    let basis = ledger.config.date_basis.unwrap_or_default();
+    let transactions: Vec<Transaction> = TransactionFilter::new()
+        .in_ledger(ledger_id)
+        .apply(&get_active_transactions(conn)?)
+        .into_iter()
+        .filter(|tx| !tx.is_voided() && tx.date_on(basis).is_some_and(|date| period.contains(date)))
+        .collect();
+
+    let summary = summarize(&transactions, basis, |_| period.clone())
+        .pop()
+        .unwrap_or_else(|| PeriodSummary::new(period.clone()));
+
+    let engine = DataQualityEngine::new();
+    let quality = engine.batch_summary(&engine.validate_batch(&transactions));
+
+    let continuity = ReconciliationEngine::new();
+    let mut reconciliation = Vec::new();
+    for account_id in snapshot_accounts(conn, ledger_id)? {
+        let history: Vec<BalanceSnapshot> = balance_history(conn, &account_id, ledger_id)?
+            .into_iter()
+            .filter(|snapshot| snapshot.statement_date <= period.end)
+            .collect();
+        reconciliation.push(AccountReconciliation {
+            account_id,
+            breaks: continuity.check_continuity(&history),
+            last_statement: history.last().cloned(),
+        });
+    }
+
+    Ok(PeriodReport {
+        ledger_id: ledger.id,
+        ledger_name: ledger.name,
+        summary,
+        quality,
+        reconciliation,
+        generated_at: Utc::now(),
+    })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn charge(date: &str, amount: f64, tx_type: &str, category: &str) -> Transaction {
+        let mut tx = Transaction {
+            date: date.to_string(),
+            description: "TEST".to_string(),
+            amount_original: format!("{:.2}", amount),
+            amount_numeric: amount,
+            transaction_type: tx_type.to_string(),
+            category: category.to_string(),
+            account_name: "Apple Card".to_string(),
+            bank: "AppleCard".to_string(),
+            source_file: "applecard.csv".to_string(),
+            ..test_support::sample_transaction()
+        };
+        tx.init_temporal_fields();
+        tx
+    }
+
+    #[test]
+    fn test_statement_cycle_periods() {
+        let calendar = ReportCalendar { period: PeriodDefinition::StatementCycle { closing_day: 15 }, ..Default::default() };
+
+        let period = calendar.period_containing(date("2025-01-15"));
+        assert_eq!((period.start, period.end), (date("2024-12-16"), date("2025-01-15")));
+        let period = calendar.period_containing(date("2025-01-16"));
+        assert_eq!((period.start, period.end), (date("2025-01-16"), date("2025-02-15")));
+
+        // Closing on the 31st clamps to the end of February
+        let calendar = ReportCalendar { period: PeriodDefinition::StatementCycle { closing_day: 31 }, ..Default::default() };
+        let period = calendar.period_containing(date("2025-02-10"));
+        assert_eq!((period.start, period.end), (date("2025-02-01"), date("2025-02-28")));
+
+        let periods = calendar.periods_between(date("2025-01-01"), date("2025-03-31"));
+        assert_eq!(periods.len(), 3);
+    }
+
+    #[test]
+    fn test_fiscal_year_boundaries() {
+        let calendar = ReportCalendar { fiscal_year_start_month: 7, ..Default::default() };
+        let fy = calendar.fiscal_year_containing(date("2025-03-10"));
+        assert_eq!((fy.start, fy.end, fy.label.as_str()), (date("2024-07-01"), date("2025-06-30"), "FY2025"));
+        assert_eq!(calendar.fiscal_year_containing(date("2025-07-01")).label, "FY2026");
+
+        let calendar_year = ReportCalendar::default().fiscal_year_containing(date("2025-03-10"));
+        assert_eq!((calendar_year.start, calendar_year.label.as_str()), (date("2025-01-01"), "2025"));
+    }
+
+    #[test]
+    fn test_summaries_follow_the_calendar() {
+        let transactions = vec![
+            charge("01/10/2025", -100.0, "GASTO", "Groceries"),
+            charge("01/20/2025", -50.0, "GASTO", "Restaurants"),
+            charge("01/31/2025", 2000.0, "INGRESO", "Salary"),
+        ];
+
+        let monthly = summarize_by_period(&transactions, &ReportCalendar::default());
+        assert_eq!(monthly.len(), 1);
+        assert_eq!(monthly[0].net(), 1850.0);
+
+        let cycles = summarize_by_period(
+            &transactions,
+            &ReportCalendar { period: PeriodDefinition::StatementCycle { closing_day: 15 }, ..Default::default() },
+        );
+        assert_eq!(cycles.len(), 2);
+        assert_eq!(cycles[0].expenses, 100.0);
+        assert_eq!(cycles[1].by_category["Restaurants"], 50.0);
+        assert_eq!(cycles[1].income, 2000.0);
+    }
+
+    #[test]
+    fn test_posted_basis_moves_charges_across_the_close() {
+        let mut late = charge("01/15/2025", -80.0, "GASTO", "Travel");
+        late.metadata.insert(crate::db::POSTED_DATE_KEY.to_string(), serde_json::json!("01/17/2025"));
+        let transactions = vec![charge("01/10/2025", -20.0, "GASTO", "Groceries"), late];
+        let calendar = ReportCalendar { period: PeriodDefinition::StatementCycle { closing_day: 15 }, ..Default::default() };
+
+        let made = summarize_by_period(&transactions, &calendar);
+        assert_eq!((made.len(), made[0].expenses), (1, 100.0));
+
+        let posted = summarize_by_period(&transactions, &calendar.with_date_basis(DateBasis::Posted));
+        assert_eq!(posted.len(), 2);
+        assert_eq!((posted[0].expenses, posted[1].expenses), (20.0, 80.0));
+        assert_eq!(posted[1].period.start, date("2025-01-16"));
+    }
+}
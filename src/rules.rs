@@ -1,8 +1,16 @@
 // 🏷️ Classification Rules - Rules as Data
 // Pattern matching and normalization rules for merchant names and categories
 
+use crate::approvals::{submit_correction, WriteOutcome};
+use crate::db::{insert_event, Event, Transaction};
+use crate::event_schema::{RuleChanged, RuleRetired};
+use crate::field_provenance::{record_origin, FieldSource, TrackedField};
+use crate::mcc::classify_mcc;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use anyhow::{Result, Context as AnyhowContext};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
@@ -10,7 +18,7 @@ use std::path::Path;
 // RULE DEFINITION
 // ============================================================================
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ClassificationRule {
     /// Rule ID for tracking
     pub id: String,
@@ -170,10 +178,126 @@ impl RuleEngine {
         ClassificationResult::default()
     }
 
+    /// Classify a stored transaction (description first, then merchant)
+    ///
+    /// A known MCC on the transaction decides the category, overriding any
+    /// rule; rules still supply the merchant and type.
+    pub fn classify_transaction(&self, tx: &Transaction) -> ClassificationResult {
+        let mut result = self.classify(&tx.description);
+        if result.rule_id.is_none() && !tx.merchant.is_empty() {
+            result = self.classify(&tx.merchant);
+        }
+
+        match tx.mcc().and_then(classify_mcc) {
+            Some(by_mcc) if result.rule_id.is_none() => by_mcc,
+            Some(by_mcc) => ClassificationResult {
+                category: by_mcc.category,
+                confidence: result.confidence.max(by_mcc.confidence),
+                ..result
+            },
+            None => result,
+        }
+    }
+
+    /// What-if simulation: apply a rule change to a COPY of this engine and
+    /// report which transactions would be classified differently.
+    ///
+    /// Nothing is written - the engine itself and the transactions are untouched.
+    pub fn simulate(&self, rule_change: &RuleChange, transactions: &[Transaction]) -> SimulationReport {
+        let mut rules = self.rules.clone();
+        match rule_change {
+            RuleChange::Add(rule) | RuleChange::Update(rule) => {
+                rules.retain(|r| r.id != rule.id);
+                rules.push(rule.clone());
+            }
+            RuleChange::Remove(rule_id) => rules.retain(|r| &r.id != rule_id),
+        }
+        let simulated = RuleEngine::from_rules(rules);
+
+        let mut changes = Vec::new();
+        let mut category_deltas: BTreeMap<String, f64> = BTreeMap::new();
+
+        for (index, tx) in transactions.iter().enumerate() {
+            let before = Classification::effective(tx, &self.classify_transaction(tx));
+            let after = Classification::effective(tx, &simulated.classify_transaction(tx));
+
+            if before == after {
+                continue;
+            }
+
+            if before.category != after.category {
+                let amount = tx.amount_numeric.abs();
+                *category_deltas.entry(before.category.clone()).or_insert(0.0) -= amount;
+                *category_deltas.entry(after.category.clone()).or_insert(0.0) += amount;
+            }
+
+            changes.push(ClassificationChange {
+                tx_index: index,
+                transaction_id: tx.id.clone(),
+                description: tx.description.clone(),
+                amount: tx.amount_numeric,
+                before,
+                after,
+            });
+        }
+
+        SimulationReport {
+            rule_change: rule_change.clone(),
+            transactions_checked: transactions.len(),
+            changes,
+            category_deltas,
+        }
+    }
+
+    /// Re-run classification over stored transactions
+    ///
+    /// Returns only transactions where a matching rule disagrees with the
+    /// STORED merchant/category/type. Transactions no rule matches are left alone.
+    pub fn reclassify(&self, transactions: &[Transaction]) -> Vec<ClassificationChange> {
+        transactions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, tx)| {
+                let result = self.classify_transaction(tx);
+                result.rule_id.as_ref()?;
+
+                let before = Classification::stored(tx);
+                let after = Classification::effective(tx, &result);
+                if before.differs_from(&after) {
+                    Some(ClassificationChange {
+                        tx_index: index,
+                        transaction_id: tx.id.clone(),
+                        description: tx.description.clone(),
+                        amount: tx.amount_numeric,
+                        before,
+                        after,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
     /// Get number of rules loaded
     pub fn rule_count(&self) -> usize {
         self.rules.len()
     }
+
+    /// Loaded rules, highest priority first
+    pub fn rules(&self) -> &[ClassificationRule] {
+        &self.rules
+    }
+
+    /// Load the current version of every active rule from the database
+    pub fn from_database(conn: &Connection) -> Result<Self> {
+        let rules = get_current_rules(conn)?
+            .into_iter()
+            .map(|versioned| versioned.rule)
+            .collect();
+
+        Ok(RuleEngine::from_rules(rules))
+    }
 }
 
 impl Default for RuleEngine {
@@ -182,6 +306,390 @@ impl Default for RuleEngine {
     }
 }
 
+// ============================================================================
+// WHAT-IF SIMULATION
+// ============================================================================
+
+/// A proposed edit to the rule set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RuleChange {
+    /// Add a new rule
+    Add(ClassificationRule),
+
+    /// Replace the rule with the same id
+    Update(ClassificationRule),
+
+    /// Remove the rule with this id
+    Remove(String),
+}
+
+/// Effective classification of a transaction
+///
+/// Rule output where a rule matched, the stored value otherwise.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Classification {
+    pub merchant: String,
+    pub category: String,
+    pub transaction_type: String,
+    pub rule_id: Option<String>,
+}
+
+impl Classification {
+    fn effective(tx: &Transaction, result: &ClassificationResult) -> Self {
+        Classification {
+            merchant: result.merchant.clone().unwrap_or_else(|| tx.merchant.clone()),
+            category: result.category.clone().unwrap_or_else(|| tx.category.clone()),
+            transaction_type: result
+                .transaction_type
+                .clone()
+                .unwrap_or_else(|| tx.transaction_type.clone()),
+            rule_id: result.rule_id.clone(),
+        }
+    }
+
+    fn stored(tx: &Transaction) -> Self {
+        Classification {
+            merchant: tx.merchant.clone(),
+            category: tx.category.clone(),
+            transaction_type: tx.transaction_type.clone(),
+            rule_id: None,
+        }
+    }
+
+    /// Compare classification values, ignoring which rule produced them
+    pub fn differs_from(&self, other: &Classification) -> bool {
+        self.merchant != other.merchant
+            || self.category != other.category
+            || self.transaction_type != other.transaction_type
+    }
+}
+
+/// One transaction whose classification would change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassificationChange {
+    /// Index into the simulated transaction slice
+    pub tx_index: usize,
+    pub transaction_id: String,
+    pub description: String,
+    pub amount: f64,
+    pub before: Classification,
+    pub after: Classification,
+}
+
+/// Result of RuleEngine::simulate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationReport {
+    pub rule_change: RuleChange,
+    pub transactions_checked: usize,
+    pub changes: Vec<ClassificationChange>,
+
+    /// Net change in absolute amount per category (negative = category shrinks)
+    pub category_deltas: BTreeMap<String, f64>,
+}
+
+impl SimulationReport {
+    pub fn changed_count(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} of {} transactions would change classification, {} categories affected",
+            self.changes.len(),
+            self.transactions_checked,
+            self.category_deltas.len()
+        )
+    }
+}
+
+/// Write a new version for every reclassified transaction
+///
+/// `transactions` must be the same slice passed to `RuleEngine::reclassify`.
+/// Each new version carries change_reason "reclassified by rule X".
+/// Changes above the ledger's approval threshold become pending changes.
+pub fn apply_reclassification(
+    conn: &Connection,
+    transactions: &[Transaction],
+    changes: &[ClassificationChange],
+    actor: &str,
+) -> Result<Vec<WriteOutcome>> {
+    let mut outcomes = Vec::with_capacity(changes.len());
+
+    for change in changes {
+        let tx = transactions
+            .get(change.tx_index)
+            .ok_or_else(|| anyhow::anyhow!("Change refers to missing transaction #{}", change.tx_index))?;
+
+        let reason = format!(
+            "reclassified by rule {}",
+            change.after.rule_id.as_deref().unwrap_or("unknown")
+        );
+
+        let mut next = tx.next_version(Some(reason));
+        next.merchant = change.after.merchant.clone();
+        next.category = change.after.category.clone();
+        next.transaction_type = change.after.transaction_type.clone();
+        if let Some(rule_id) = &change.after.rule_id {
+            let at = next.valid_from.unwrap_or_else(Utc::now);
+            for field in [TrackedField::Merchant, TrackedField::Category, TrackedField::Type] {
+                if field.value(&next) != field.value(tx) {
+                    let source = FieldSource::for_classification(&next, rule_id, field);
+                    record_origin(&mut next, field, source, at);
+                }
+            }
+        }
+
+        outcomes.push(submit_correction(conn, &next, actor)?);
+    }
+
+    Ok(outcomes)
+}
+
+// ============================================================================
+// RULE PERSISTENCE (Badge 30 - Rules as Data, versioned like entities)
+// ============================================================================
+
+/// VersionedRule - One immutable version of a classification rule
+///
+/// Identity: rule.id (never changes)
+/// Value: the rule definition at this version
+/// Time: valid_from / valid_until, same model as Bank/Merchant/Category/Account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionedRule {
+    pub rule: ClassificationRule,
+    pub version: i64,
+    pub system_time: DateTime<Utc>,
+    pub valid_from: DateTime<Utc>,
+    pub valid_until: Option<DateTime<Utc>>,
+
+    /// Who created this version
+    pub changed_by: String,
+
+    /// Why this version was created
+    pub change_reason: Option<String>,
+}
+
+impl VersionedRule {
+    /// Check if this version is current
+    pub fn is_current(&self) -> bool {
+        self.valid_until.is_none()
+    }
+
+    /// Check if this version was active at a specific time
+    pub fn was_valid_at(&self, time: DateTime<Utc>) -> bool {
+        self.valid_from <= time && self.valid_until.is_none_or(|until| until > time)
+    }
+}
+
+/// Save a rule as a new version (append-only, never overwrites)
+///
+/// - Unknown rule id → version 1
+/// - Known rule id → current version is expired, version N+1 is inserted
+/// - Retired rule id → version N+1 after the last retired version
+/// - Definition identical to the current version → no-op, returns current version
+///
+/// Returns the version number that is current after the call.
+pub fn save_rule(
+    conn: &Connection,
+    rule: &ClassificationRule,
+    actor: &str,
+    reason: Option<&str>,
+) -> Result<i64> {
+    let now = Utc::now();
+    let now_str = now.to_rfc3339();
+    let current = get_current_rule(conn, &rule.id)?;
+
+    if let Some(ref current) = current {
+        if current.rule == *rule {
+            return Ok(current.version);
+        }
+    }
+
+    let db_tx = conn.unchecked_transaction()?;
+    let last_version: i64 = db_tx.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM rules WHERE rule_id = ?1",
+        params![rule.id],
+        |row| row.get(0),
+    )?;
+    let next_version = last_version + 1;
+
+    if current.is_some() {
+        db_tx.execute(
+            "UPDATE rules SET valid_until = ?1 WHERE rule_id = ?2 AND valid_until IS NULL",
+            params![now_str, rule.id],
+        )?;
+    }
+
+    db_tx.execute(
+        "INSERT INTO rules (
+            rule_id, version, definition, changed_by, change_reason,
+            system_time, valid_from, valid_until
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, NULL)",
+        params![
+            rule.id,
+            next_version,
+            serde_json::to_string(rule)?,
+            actor,
+            reason,
+            now_str,
+            now_str,
+        ],
+    )?;
+
+    let payload = RuleChanged { version: next_version, pattern: rule.pattern.clone(), reason: reason.map(str::to_string) };
+    let event = Event::typed(
+        if next_version == 1 { "rule_created" } else { "rule_updated" },
+        "rule",
+        &rule.id,
+        &payload,
+        actor,
+    )?;
+    insert_event(&db_tx, &event)?;
+    db_tx.commit()?;
+
+    Ok(next_version)
+}
+
+/// Save a batch of rules (e.g. seeding the table from rules/merchants.json)
+///
+/// Returns how many rules got a new version.
+pub fn save_rules(
+    conn: &Connection,
+    rules: &[ClassificationRule],
+    actor: &str,
+    reason: Option<&str>,
+) -> Result<usize> {
+    let mut changed = 0;
+
+    for rule in rules {
+        let before = get_current_rule(conn, &rule.id)?.map(|r| r.version);
+        let after = save_rule(conn, rule, actor, reason)?;
+        if before != Some(after) {
+            changed += 1;
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Retire a rule: expire its current version without creating a new one
+///
+/// History is preserved, so past classifications remain explainable.
+pub fn retire_rule(conn: &Connection, rule_id: &str, actor: &str, reason: Option<&str>) -> Result<()> {
+    let updated = conn.execute(
+        "UPDATE rules SET valid_until = ?1 WHERE rule_id = ?2 AND valid_until IS NULL",
+        params![Utc::now().to_rfc3339(), rule_id],
+    )?;
+
+    if updated == 0 {
+        return Err(anyhow::anyhow!("Rule not found or already retired: {}", rule_id));
+    }
+
+    let payload = RuleRetired { reason: reason.map(str::to_string) };
+    let event = Event::typed("rule_retired", "rule", rule_id, &payload, actor)?;
+    insert_event(conn, &event)?;
+
+    Ok(())
+}
+
+/// Get the current version of a rule (None if unknown or retired)
+pub fn get_current_rule(conn: &Connection, rule_id: &str) -> Result<Option<VersionedRule>> {
+    let rule = conn
+        .query_row(
+            "SELECT definition, version, changed_by, change_reason, system_time, valid_from, valid_until
+             FROM rules
+             WHERE rule_id = ?1 AND valid_until IS NULL",
+            params![rule_id],
+            row_to_versioned_rule,
+        )
+        .optional()?;
+
+    Ok(rule)
+}
+
+/// Get the current version of every active rule
+pub fn get_current_rules(conn: &Connection) -> Result<Vec<VersionedRule>> {
+    let mut stmt = conn.prepare(
+        "SELECT definition, version, changed_by, change_reason, system_time, valid_from, valid_until
+         FROM rules
+         WHERE valid_until IS NULL
+         ORDER BY rule_id",
+    )?;
+
+    let rules = stmt
+        .query_map([], row_to_versioned_rule)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(rules)
+}
+
+/// Get ALL versions of a rule, oldest first
+pub fn get_rule_history(conn: &Connection, rule_id: &str) -> Result<Vec<VersionedRule>> {
+    let mut stmt = conn.prepare(
+        "SELECT definition, version, changed_by, change_reason, system_time, valid_from, valid_until
+         FROM rules
+         WHERE rule_id = ?1
+         ORDER BY version ASC",
+    )?;
+
+    let rules = stmt
+        .query_map(params![rule_id], row_to_versioned_rule)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(rules)
+}
+
+/// Get the rule version that was active at a specific time (temporal query)
+///
+/// "Which version of rule X classified this transaction on date T?"
+pub fn get_rule_at_time(
+    conn: &Connection,
+    rule_id: &str,
+    as_of: DateTime<Utc>,
+) -> Result<Option<VersionedRule>> {
+    Ok(get_rule_history(conn, rule_id)?
+        .into_iter()
+        .find(|r| r.was_valid_at(as_of)))
+}
+
+/// Every rule as it was at a specific time (retired rules included while they were active)
+pub fn get_rules_at_time(conn: &Connection, as_of: DateTime<Utc>) -> Result<Vec<VersionedRule>> {
+    let mut stmt = conn.prepare(
+        "SELECT definition, version, changed_by, change_reason, system_time, valid_from, valid_until
+         FROM rules
+         ORDER BY rule_id, version",
+    )?;
+
+    let rules = stmt
+        .query_map([], row_to_versioned_rule)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(rules.into_iter().filter(|r| r.was_valid_at(as_of)).collect())
+}
+
+fn row_to_versioned_rule(row: &rusqlite::Row) -> rusqlite::Result<VersionedRule> {
+    let definition: String = row.get(0)?;
+    let system_time_str: String = row.get(4)?;
+    let valid_from_str: String = row.get(5)?;
+    let valid_until_str: Option<String> = row.get(6)?;
+
+    let parse = |s: &str| {
+        DateTime::parse_from_rfc3339(s)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|_| rusqlite::Error::InvalidQuery)
+    };
+
+    Ok(VersionedRule {
+        rule: serde_json::from_str(&definition).map_err(|_| rusqlite::Error::InvalidQuery)?,
+        version: row.get(1)?,
+        changed_by: row.get(2)?,
+        change_reason: row.get(3)?,
+        system_time: parse(&system_time_str)?,
+        valid_from: parse(&valid_from_str)?,
+        valid_until: valid_until_str.as_deref().map(parse).transpose()?,
+    })
+}
+
 // ============================================================================
 // TESTS
 // ============================================================================
@@ -189,6 +697,7 @@ impl Default for RuleEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::test_support;
 
     #[test]
     fn test_exact_pattern_match() {
@@ -294,4 +803,217 @@ mod tests {
         assert_eq!(result.confidence, 0.0);
         assert_eq!(result.rule_id, None);
     }
+
+    // ========================================================================
+    // RULE PERSISTENCE TESTS
+    // ========================================================================
+
+    fn starbucks_rule(category: &str) -> ClassificationRule {
+        ClassificationRule {
+            id: "starbucks".to_string(),
+            pattern: "STARBUCKS*".to_string(),
+            merchant: Some("Starbucks".to_string()),
+            category: Some(category.to_string()),
+            transaction_type: Some("GASTO".to_string()),
+            confidence: 0.95,
+            description: None,
+            priority: 10,
+        }
+    }
+
+    #[test]
+    fn test_save_rule_creates_versions() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::setup_database(&conn).unwrap();
+
+        assert_eq!(save_rule(&conn, &starbucks_rule("Restaurants"), "test", None).unwrap(), 1);
+
+        // Same definition again → no new version
+        assert_eq!(save_rule(&conn, &starbucks_rule("Restaurants"), "test", None).unwrap(), 1);
+
+        // Changed definition → version 2, version 1 expired
+        let v = save_rule(&conn, &starbucks_rule("Café"), "darwin", Some("more specific")).unwrap();
+        assert_eq!(v, 2);
+
+        let history = get_rule_history(&conn, "starbucks").unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(!history[0].is_current());
+        assert!(history[1].is_current());
+        assert_eq!(history[1].changed_by, "darwin");
+        assert_eq!(history[1].change_reason.as_deref(), Some("more specific"));
+
+        let engine = RuleEngine::from_database(&conn).unwrap();
+        assert_eq!(engine.rule_count(), 1);
+        assert_eq!(engine.classify("STARBUCKS #12").category, Some("Café".to_string()));
+    }
+
+    #[test]
+    fn test_rule_at_time_explains_past_classification() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::setup_database(&conn).unwrap();
+
+        save_rule(&conn, &starbucks_rule("Restaurants"), "test", None).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        let classified_at = Utc::now();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        save_rule(&conn, &starbucks_rule("Café"), "test", None).unwrap();
+
+        let then = get_rule_at_time(&conn, "starbucks", classified_at).unwrap().unwrap();
+        assert_eq!(then.version, 1);
+        assert_eq!(then.rule.category, Some("Restaurants".to_string()));
+
+        let now = get_rule_at_time(&conn, "starbucks", Utc::now()).unwrap().unwrap();
+        assert_eq!(now.version, 2);
+    }
+
+    #[test]
+    fn test_retire_rule() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::setup_database(&conn).unwrap();
+
+        save_rule(&conn, &starbucks_rule("Restaurants"), "test", None).unwrap();
+        retire_rule(&conn, "starbucks", "test", Some("merchant closed")).unwrap();
+
+        assert!(get_current_rule(&conn, "starbucks").unwrap().is_none());
+        assert_eq!(get_rule_history(&conn, "starbucks").unwrap().len(), 1);
+        assert!(retire_rule(&conn, "starbucks", "test", None).is_err());
+
+        let events = crate::db::get_events_for_entity(&conn, "rule", "starbucks").unwrap();
+        assert_eq!(events.len(), 2);
+    }
+
+    #[test]
+    fn test_retired_rule_can_be_saved_again() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::setup_database(&conn).unwrap();
+
+        save_rule(&conn, &starbucks_rule("Restaurants"), "test", None).unwrap();
+        save_rule(&conn, &starbucks_rule("Café"), "test", None).unwrap();
+        retire_rule(&conn, "starbucks", "test", None).unwrap();
+
+        assert_eq!(save_rule(&conn, &starbucks_rule("Café"), "test", Some("reopened")).unwrap(), 3);
+        assert_eq!(get_current_rule(&conn, "starbucks").unwrap().unwrap().version, 3);
+        let history = get_rule_history(&conn, "starbucks").unwrap();
+        assert_eq!(history.iter().filter(|v| v.is_current()).count(), 1);
+        assert_eq!(history.len(), 3);
+    }
+
+    // ========================================================================
+    // WHAT-IF SIMULATION TESTS
+    // ========================================================================
+
+    fn sim_transaction(description: &str, amount: f64, category: &str) -> Transaction {
+        Transaction {
+            description: description.to_string(),
+            amount_original: format!("{:.2}", amount),
+            amount_numeric: amount,
+            category: category.to_string(),
+            account_name: "Test".to_string(),
+            account_number: "1234".to_string(),
+            bank: "Test Bank".to_string(),
+            source_file: "test.csv".to_string(),
+            ..test_support::sample_transaction()
+        }
+    }
+
+    #[test]
+    fn test_simulate_rule_update() {
+        let engine = RuleEngine::from_rules(vec![starbucks_rule("Restaurants")]);
+        let transactions = vec![
+            sim_transaction("STARBUCKS #123", -5.50, "Restaurants"),
+            sim_transaction("STARBUCKS #456", -4.50, "Restaurants"),
+            sim_transaction("AMAZON", -20.00, "Shopping"),
+        ];
+
+        let report = engine.simulate(&RuleChange::Update(starbucks_rule("Café")), &transactions);
+
+        assert_eq!(report.transactions_checked, 3);
+        assert_eq!(report.changed_count(), 2);
+        assert_eq!(report.changes[0].before.category, "Restaurants");
+        assert_eq!(report.changes[0].after.category, "Café");
+        assert!((report.category_deltas["Restaurants"] + 10.0).abs() < 0.001);
+        assert!((report.category_deltas["Café"] - 10.0).abs() < 0.001);
+
+        // Engine itself is untouched
+        assert_eq!(engine.classify("STARBUCKS").category, Some("Restaurants".to_string()));
+    }
+
+    #[test]
+    fn test_simulate_rule_removal_falls_back_to_stored_values() {
+        let engine = RuleEngine::from_rules(vec![starbucks_rule("Café")]);
+        let transactions = vec![sim_transaction("STARBUCKS #123", -5.50, "Restaurants")];
+
+        let report = engine.simulate(&RuleChange::Remove("starbucks".to_string()), &transactions);
+
+        assert_eq!(report.changed_count(), 1);
+        assert_eq!(report.changes[0].after.category, "Restaurants");
+        assert_eq!(report.changes[0].after.rule_id, None);
+    }
+
+    #[test]
+    fn test_mcc_is_highest_priority_category_signal() {
+        let engine = RuleEngine::from_rules(vec![starbucks_rule("Café")]);
+
+        let mut coffee = sim_transaction("STARBUCKS #123", -5.50, "Café");
+        coffee.metadata.insert("mcc".to_string(), serde_json::json!(5814));
+        let result = engine.classify_transaction(&coffee);
+        assert_eq!(result.category.as_deref(), Some("Fast Food"));
+        assert_eq!(result.merchant.as_deref(), Some("Starbucks"));
+        assert_eq!(result.rule_id.as_deref(), Some("starbucks"));
+
+        // No rule matches: the MCC alone classifies it
+        let mut unknown = sim_transaction("SQ *BLUE BOTTLE", -6.00, "");
+        unknown.metadata.insert("mcc".to_string(), serde_json::json!("5812"));
+        let result = engine.classify_transaction(&unknown);
+        assert_eq!(result.category.as_deref(), Some("Restaurants"));
+        assert_eq!(result.rule_id.as_deref(), Some("mcc:5812"));
+
+        // Unknown codes fall through to the rules
+        coffee.metadata.insert("mcc".to_string(), serde_json::json!(1234));
+        assert_eq!(engine.classify_transaction(&coffee).category.as_deref(), Some("Café"));
+    }
+
+    #[test]
+    fn test_reclassify_writes_new_versions() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::setup_database(&conn).unwrap();
+
+        let mut coffee = sim_transaction("STARBUCKS #123", -5.50, "Restaurants");
+        coffee.merchant = "STARBUCKS #123".to_string();
+        coffee.init_temporal_fields();
+        let mut other = sim_transaction("LOCAL DINER", -12.00, "Restaurants");
+        other.init_temporal_fields();
+        crate::db::insert_transactions_as(&conn, &[coffee.clone(), other], "test").unwrap();
+
+        let engine = RuleEngine::from_rules(vec![starbucks_rule("Café")]);
+        let stored: Vec<Transaction> = crate::db::get_all_transactions(&conn).unwrap();
+        let changes = engine.reclassify(&stored);
+
+        // Only the Starbucks row has a matching rule that disagrees
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].after.category, "Café");
+
+        let outcomes = apply_reclassification(&conn, &stored, &changes, "test").unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert!(!outcomes[0].is_pending());
+
+        let history = crate::db::get_transaction_history(&conn, &coffee.id).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].category, "Restaurants");
+        assert!(!history[0].is_current());
+        assert_eq!(history[1].category, "Café");
+        assert_eq!(history[1].version, 2);
+        assert_eq!(
+            history[1].get_metadata("change_reason"),
+            Some(&serde_json::json!("reclassified by rule starbucks"))
+        );
+
+        // Running again finds nothing left to change
+        let current: Vec<Transaction> = crate::db::get_all_transactions(&conn)
+            .unwrap()
+            .into_iter()
+            .filter(|tx| tx.is_current())
+            .collect();
+        assert!(engine.reclassify(&current).is_empty());
+    }
 }
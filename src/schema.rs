@@ -395,6 +395,7 @@ impl Default for SchemaValidator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::test_support;
     use std::collections::HashMap;
     
     fn create_test_transaction() -> Transaction {
@@ -408,24 +409,12 @@ mod tests {
             description: "STARBUCKS".to_string(),
             amount_original: "$45.99".to_string(),
             amount_numeric: 45.99,
-            transaction_type: "GASTO".to_string(),
-            category: "Restaurants".to_string(),
             merchant: "STARBUCKS".to_string(),
-            currency: "USD".to_string(),
-            account_name: "Checking".to_string(),
             account_number: "1234".to_string(),
-            bank: "BofA".to_string(),
             source_file: "test.csv".to_string(),
             line_number: "23".to_string(),
-            classification_notes: String::new(),
-            // Badge 19 fields - defaults
-            id: String::new(),
-            version: 0,
-            system_time: None,
-            valid_from: None,
-            valid_until: None,
-            previous_version_id: None,
             metadata,
+            ..test_support::sample_transaction()
         }
     }
     
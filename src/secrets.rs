@@ -0,0 +1,222 @@
+// 🔐 Secrets - API keys and signing keys from the OS keyring, else the environment
+//
+// Problem solved:
+// - The sync key, the enrichment API key and the signing key could only be
+//   given as environment variables or plaintext files, so they ended up in
+//   shell profiles, CI dumps and backups
+//
+// A secret has a name ("sync-key") and an environment variable derived from
+// it ($TRUST_SYNC_KEY). `secret` looks in the OS keyring first (service
+// "trust-construction", account = the name) and falls back to the variable,
+// so existing setups keep working. `secret set` stores into the keyring.
+//
+// The keyring crate talks to the Keychain on macOS, the Credential Manager on
+// Windows and the Secret Service (GNOME Keyring, KWallet) over D-Bus on Linux
+// and the BSDs, in-process: values never appear on a command line. Where no
+// keyring is running (servers, CI), lookups fall through to the environment
+// and storing reports why it can't.
+
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+
+/// Keyring service every secret is stored under
+pub const KEYRING_SERVICE: &str = "trust-construction";
+
+/// Secrets this tool reads, and what for
+pub const KNOWN_SECRETS: &[(&str, &str)] = &[
+    ("signing-key", "Ed25519 key signing exported reports and changesets"),
+    ("sync-key", "HMAC key shared by instances that sync changesets"),
+    ("enrich-key", "API key of the web enrichment provider"),
+];
+
+/// Where a secret was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretSource {
+    Keyring,
+    Env,
+}
+
+impl SecretSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecretSource::Keyring => "keyring",
+            SecretSource::Env => "environment",
+        }
+    }
+}
+
+/// Names are lowercase letters, digits and dashes ("stripe-api-key")
+pub fn validate_secret_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('-')
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!("Invalid secret name '{}': use lowercase letters, digits and dashes", name))
+    }
+}
+
+/// Environment variable a secret falls back to: "sync-key" → TRUST_SYNC_KEY
+pub fn secret_env_var(name: &str) -> String {
+    format!("TRUST_{}", name.to_uppercase().replace('-', "_"))
+}
+
+// ============================================================================
+// STORES
+// ============================================================================
+
+/// Somewhere secrets can be kept
+pub trait SecretStore: Send + Sync {
+    /// Human name, for messages ("macOS Keychain")
+    fn name(&self) -> &str;
+    /// The stored value (None when there is none, or the store is unavailable)
+    fn get(&self, name: &str) -> Result<Option<String>>;
+    fn set(&self, name: &str, value: &str) -> Result<()>;
+    /// Remove the value; false when there was none
+    fn delete(&self, name: &str) -> Result<bool>;
+}
+
+/// The OS keyring (Keychain, Credential Manager or Secret Service)
+#[derive(Debug, Clone, Default)]
+pub struct OsKeyring;
+
+impl OsKeyring {
+    fn entry(&self, name: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(KEYRING_SERVICE, name).with_context(|| format!("Invalid keyring entry '{}'", name))
+    }
+}
+
+/// Whether `error` means there is no keyring to talk to (no Secret Service on
+/// the bus, no D-Bus session) rather than a failed lookup
+fn keyring_unavailable(error: &keyring::Error) -> bool {
+    matches!(error, keyring::Error::PlatformFailure(_) | keyring::Error::NoStorageAccess(_))
+}
+
+impl SecretStore for OsKeyring {
+    fn name(&self) -> &str {
+        if cfg!(target_os = "macos") {
+            "macOS Keychain"
+        } else if cfg!(windows) {
+            "Windows Credential Manager"
+        } else {
+            "Secret Service keyring"
+        }
+    }
+
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        match self.entry(name)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) if keyring_unavailable(&e) => Ok(None),
+            Err(e) => Err(anyhow!("Could not read '{}' from the {}: {}", name, self.name(), e)),
+        }
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        self.entry(name)?.set_password(value).map_err(|e| {
+            if keyring_unavailable(&e) {
+                anyhow!("No {} available ({}); set {} instead", self.name(), e, secret_env_var(name))
+            } else {
+                anyhow!("Could not store '{}' in the {}: {}", name, self.name(), e)
+            }
+        })
+    }
+
+    fn delete(&self, name: &str) -> Result<bool> {
+        match self.entry(name)?.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(anyhow!("Could not remove '{}' from the {}: {}", name, self.name(), e)),
+        }
+    }
+}
+
+/// Secrets held in memory (tests, embedding the library)
+#[derive(Debug, Default)]
+pub struct MemoryKeyring {
+    values: Mutex<HashMap<String, String>>,
+}
+
+impl SecretStore for MemoryKeyring {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        Ok(self.values.lock().unwrap().get(name).cloned())
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        self.values.lock().unwrap().insert(name.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<bool> {
+        Ok(self.values.lock().unwrap().remove(name).is_some())
+    }
+}
+
+// ============================================================================
+// LOOKUP
+// ============================================================================
+
+/// A secret from `store`, else from `env` (given the variable name), with
+/// where it was found; blank values count as unset
+pub fn resolve_secret(
+    store: &dyn SecretStore,
+    name: &str,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<Option<(String, SecretSource)>> {
+    validate_secret_name(name)?;
+    if let Some(value) = store.get(name)?.filter(|value| !value.trim().is_empty()) {
+        return Ok(Some((value, SecretSource::Keyring)));
+    }
+    Ok(env(&secret_env_var(name)).filter(|value| !value.trim().is_empty()).map(|value| (value, SecretSource::Env)))
+}
+
+/// A secret from the OS keyring, else its environment variable
+pub fn secret(name: &str) -> Result<Option<String>> {
+    Ok(resolve_secret(&OsKeyring, name, |var| env::var(var).ok())?.map(|(value, _)| value))
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyring_wins_over_the_environment() {
+        let store = MemoryKeyring::default();
+        let env = |var: &str| (var == "TRUST_SYNC_KEY").then(|| "from-env".to_string());
+
+        let found = resolve_secret(&store, "sync-key", env).unwrap();
+        assert_eq!(found, Some(("from-env".to_string(), SecretSource::Env)));
+
+        store.set("sync-key", "from-keyring").unwrap();
+        let found = resolve_secret(&store, "sync-key", env).unwrap();
+        assert_eq!(found, Some(("from-keyring".to_string(), SecretSource::Keyring)));
+
+        assert!(store.delete("sync-key").unwrap());
+        assert!(!store.delete("sync-key").unwrap());
+        assert_eq!(resolve_secret(&store, "enrich-key", env).unwrap(), None);
+    }
+
+    #[test]
+    fn test_names_map_to_environment_variables() {
+        assert_eq!(secret_env_var("signing-key"), crate::signing::SIGNING_KEY_ENV);
+        assert_eq!(secret_env_var("stripe-api-key"), "TRUST_STRIPE_API_KEY");
+        for bad in ["", "Sync-Key", "-x", "sync key", "../etc"] {
+            assert!(validate_secret_name(bad).is_err(), "{}", bad);
+        }
+        assert!(resolve_secret(&MemoryKeyring::default(), "Bad Name", |_| None).is_err());
+        for (name, _) in KNOWN_SECRETS {
+            assert!(validate_secret_name(name).is_ok(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,285 @@
+// ⏳ Settlement - Pending charges and the settled rows that replace them
+//
+// Problem solved:
+// - Live API sources (Stripe balance transactions, card feeds) return a charge
+//   while it is still pending, then again once it settles - with a new id and
+//   often a different amount (tips, released holds) - so the ledger ended up
+//   with both rows
+// - Pending rows were reconciled against statements that never list them
+//
+// A pending row carries `pending: true` in its metadata. The
+// SettlementMatcher pairs it with the settled row for the same charge (same
+// account and currency, same direction, a close amount, a few days later, the
+// same merchant). `settle_pending` upgrades the pending row to a new version
+// with the settled values, so it keeps its identity, notes and corrections;
+// the import does this as settled rows arrive, and `settle_stored` does it for
+// pairs already stored side by side (voiding the extra settled row).
+
+use crate::db::{
+    insert_event, insert_transaction_version, void_transaction, Event, Transaction, PENDING_KEY, POSTED_DATE_KEY,
+};
+use crate::event_schema::TransactionSettled;
+use crate::field_provenance::{inherit_origins, TrackedField};
+use crate::imports::EXTERNAL_ID_KEY;
+use crate::jobs::ledger_transactions;
+use anyhow::Result;
+use rusqlite::Connection;
+use std::collections::HashSet;
+
+/// Metadata of a settled version: what the pending row said before
+pub const PENDING_AMOUNT_KEY: &str = "pending_amount";
+pub const PENDING_DATE_KEY: &str = "pending_date";
+
+/// Metadata of a settled version: idempotency hash of the settled row, so a
+/// re-import of that row is recognised as already stored
+pub const SETTLED_HASH_KEY: &str = "settled_hash";
+
+// ============================================================================
+// MATCHING
+// ============================================================================
+
+#[derive(Debug, Clone, Copy)]
+pub struct SettlementMatcher {
+    /// Days a charge may take to settle after its pending date
+    pub window_days: i64,
+
+    /// Largest change between pending and settled amounts, as a fraction of
+    /// the pending amount (tips, holds released for less)
+    pub amount_tolerance: f64,
+}
+
+impl Default for SettlementMatcher {
+    fn default() -> Self {
+        SettlementMatcher { window_days: 7, amount_tolerance: 0.30 }
+    }
+}
+
+impl SettlementMatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `settled` is the settled form of the pending row `pending`
+    pub fn matches(&self, pending: &Transaction, settled: &Transaction) -> bool {
+        if !pending.is_pending() || settled.is_pending() {
+            return false;
+        }
+        let same_account = pending.ledger_id == settled.ledger_id
+            && pending.bank == settled.bank
+            && pending.account_number == settled.account_number
+            && pending.currency == settled.currency;
+        let same_direction = pending.amount_numeric.signum() == settled.amount_numeric.signum();
+        let days = match (pending.parsed_date(), settled.parsed_date()) {
+            (Some(from), Some(to)) => (to - from).num_days(),
+            _ => return false,
+        };
+
+        same_account
+            && same_direction
+            && (0..=self.window_days).contains(&days)
+            && amount_change(pending, settled) <= self.amount_tolerance
+            && same_merchant(pending, settled)
+    }
+
+    /// The pending row `incoming` settles: the closest in amount, then date
+    pub fn find_pending<'a>(&self, incoming: &Transaction, pending: &'a [Transaction]) -> Option<&'a Transaction> {
+        pending
+            .iter()
+            .filter(|candidate| candidate.is_active() && self.matches(candidate, incoming))
+            .min_by(|a, b| {
+                let key = |tx: &Transaction| (amount_change(tx, incoming), day_gap(tx, incoming));
+                key(a).partial_cmp(&key(b)).unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+
+    /// Pending rows and the stored settled rows they match, each used once
+    pub fn pair_stored(&self, transactions: &[Transaction]) -> Vec<(Transaction, Transaction)> {
+        let pending: Vec<Transaction> =
+            transactions.iter().filter(|tx| tx.is_active() && tx.is_pending()).cloned().collect();
+        let mut used = HashSet::new();
+        let mut pairs = Vec::new();
+        for settled in transactions.iter().filter(|tx| tx.is_active() && !tx.is_pending()) {
+            let open: Vec<Transaction> = pending.iter().filter(|tx| !used.contains(&tx.id)).cloned().collect();
+            if let Some(found) = self.find_pending(settled, &open) {
+                used.insert(found.id.clone());
+                pairs.push((found.clone(), settled.clone()));
+            }
+        }
+        pairs
+    }
+}
+
+/// Relative change from the pending amount to the settled one
+fn amount_change(pending: &Transaction, settled: &Transaction) -> f64 {
+    let base = pending.amount_numeric.abs();
+    if base == 0.0 {
+        return if settled.amount_numeric == 0.0 { 0.0 } else { f64::INFINITY };
+    }
+    (settled.amount_numeric - pending.amount_numeric).abs() / base
+}
+
+fn day_gap(pending: &Transaction, settled: &Transaction) -> i64 {
+    match (pending.parsed_date(), settled.parsed_date()) {
+        (Some(from), Some(to)) => (to - from).num_days().abs(),
+        _ => i64::MAX,
+    }
+}
+
+/// Same merchant name, else the same first word of the description
+/// ("UBER *TRIP PENDING" / "UBER *TRIP 8XK2")
+fn same_merchant(a: &Transaction, b: &Transaction) -> bool {
+    let (ma, mb) = (a.merchant.trim(), b.merchant.trim());
+    if !ma.is_empty() && !mb.is_empty() {
+        return ma.eq_ignore_ascii_case(mb);
+    }
+    let first_word = |tx: &Transaction| tx.description.split_whitespace().next().map(str::to_uppercase);
+    first_word(a).is_some() && first_word(a) == first_word(b)
+}
+
+/// Whether `tx` was already absorbed into a settled version among `stored`
+pub fn already_settled<'a>(tx: &Transaction, stored: &'a [Transaction]) -> Option<&'a Transaction> {
+    let hash = tx.compute_idempotency_hash();
+    stored
+        .iter()
+        .find(|row| row.is_current() && row.get_metadata(SETTLED_HASH_KEY).and_then(|v| v.as_str()) == Some(&hash))
+}
+
+// ============================================================================
+// SETTLE
+// ============================================================================
+
+/// Next version of `pending` carrying the settled row's date, amount,
+/// description and ids; its classification is kept
+pub fn settled_version(pending: &Transaction, settled: &Transaction) -> Transaction {
+    let mut next = pending.next_version(Some("settled".to_string()));
+    next.date = settled.date.clone();
+    next.description = settled.description.clone();
+    next.amount_original = settled.amount_original.clone();
+    next.amount_numeric = settled.amount_numeric;
+    inherit_origins(&mut next, settled, &[TrackedField::Amount]);
+    if !settled.merchant.trim().is_empty() {
+        next.merchant = settled.merchant.clone();
+        inherit_origins(&mut next, settled, &[TrackedField::Merchant]);
+    }
+
+    next.metadata.remove(PENDING_KEY);
+    next.metadata.insert(PENDING_AMOUNT_KEY.to_string(), serde_json::json!(pending.amount_numeric));
+    next.metadata.insert(PENDING_DATE_KEY.to_string(), serde_json::json!(pending.date));
+    next.metadata.insert(SETTLED_HASH_KEY.to_string(), serde_json::json!(settled.compute_idempotency_hash()));
+    for key in [EXTERNAL_ID_KEY, POSTED_DATE_KEY] {
+        if let Some(value) = settled.get_metadata(key) {
+            next.metadata.insert(key.to_string(), value.clone());
+        }
+    }
+    next
+}
+
+/// Upgrade `pending` to its settled version and log `transaction_settled`
+/// (not wrapped in a SQLite transaction; callers group it with their writes)
+pub fn settle_pending(conn: &Connection, pending: &Transaction, settled: &Transaction, actor: &str) -> Result<Transaction> {
+    let next = settled_version(pending, settled);
+    insert_transaction_version(conn, &next, actor)?;
+
+    let payload = TransactionSettled {
+        pending_amount: pending.amount_numeric,
+        settled_amount: settled.amount_numeric,
+        pending_date: pending.date.clone(),
+        settled_date: settled.date.clone(),
+        settled_row: (!settled.id.is_empty()).then(|| settled.id.clone()),
+    };
+    let event = Event::typed("transaction_settled", "transaction", &next.id, &payload, actor)?.with_ledger(&next.ledger_id);
+    insert_event(conn, &event)?;
+    Ok(next)
+}
+
+/// Settle every pending row of the ledger whose settled row is already
+/// stored; the settled row is voided once its values moved to the pending
+/// row. Returns the settled versions.
+pub fn settle_stored(conn: &Connection, ledger_id: &str, matcher: &SettlementMatcher, actor: &str) -> Result<Vec<Transaction>> {
+    let pairs = matcher.pair_stored(&ledger_transactions(conn, ledger_id)?);
+
+    let db_tx = conn.unchecked_transaction()?;
+    let mut settled = Vec::new();
+    for (pending, row) in pairs {
+        let next = settle_pending(&db_tx, &pending, &row, actor)?;
+        void_transaction(&db_tx, &row.id, &format!("settled pending transaction {}", pending.id), actor)?;
+        settled.push(next);
+    }
+    db_tx.commit()?;
+    Ok(settled)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::db::{get_current_transaction, insert_transaction_as, setup_database};
+
+    fn charge(date: &str, amount: f64, description: &str, pending: bool) -> Transaction {
+        let mut tx = Transaction {
+            date: date.to_string(),
+            description: description.to_string(),
+            amount_original: format!("{:.2}", amount),
+            amount_numeric: amount,
+            account_name: "Stripe".to_string(),
+            account_number: String::new(),
+            bank: "Stripe".to_string(),
+            source_file: "stripe.json".to_string(),
+            ..test_support::sample_transaction()
+        };
+        if pending {
+            tx.metadata.insert(PENDING_KEY.to_string(), serde_json::json!(true));
+        }
+        tx.init_temporal_fields();
+        tx
+    }
+
+    #[test]
+    fn test_matcher_pairs_pending_with_later_settled_row() {
+        let matcher = SettlementMatcher::new();
+        let pending = charge("01/10/2025", -40.0, "BISTRO 22 PENDING", true);
+
+        assert!(matcher.matches(&pending, &charge("01/12/2025", -46.0, "BISTRO 22 SF", false)));
+        // Too late, too different, other merchant, or earlier than the hold
+        assert!(!matcher.matches(&pending, &charge("01/20/2025", -46.0, "BISTRO 22 SF", false)));
+        assert!(!matcher.matches(&pending, &charge("01/12/2025", -90.0, "BISTRO 22 SF", false)));
+        assert!(!matcher.matches(&pending, &charge("01/12/2025", -40.0, "TAQUERIA", false)));
+        assert!(!matcher.matches(&pending, &charge("01/09/2025", -40.0, "BISTRO 22 SF", false)));
+
+        let rows = vec![pending.clone(), charge("01/11/2025", -40.0, "BISTRO 22 SF", false)];
+        let pairs = matcher.pair_stored(&rows);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].0.id, pending.id);
+    }
+
+    #[test]
+    fn test_settle_stored_upgrades_pending_and_voids_settled_row() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let mut pending = charge("01/10/2025", -40.0, "BISTRO 22 PENDING", true);
+        pending.metadata.insert(EXTERNAL_ID_KEY.to_string(), serde_json::json!("txn_pending"));
+        let mut row = charge("01/12/2025", -46.0, "BISTRO 22 SF", false);
+        row.metadata.insert(EXTERNAL_ID_KEY.to_string(), serde_json::json!("txn_settled"));
+        insert_transaction_as(&conn, &pending, "tester").unwrap();
+        insert_transaction_as(&conn, &row, "tester").unwrap();
+
+        let ledger = crate::ledger::default_ledger_id();
+        let settled = settle_stored(&conn, &ledger, &SettlementMatcher::new(), "tester").unwrap();
+        assert_eq!(settled.len(), 1);
+
+        let current = get_current_transaction(&conn, &pending.id).unwrap().unwrap();
+        assert!(!current.is_pending());
+        assert_eq!((current.version, current.amount_numeric, current.date.as_str()), (2, -46.0, "01/12/2025"));
+        assert_eq!(current.get_metadata(PENDING_AMOUNT_KEY), Some(&serde_json::json!(-40.0)));
+        assert_eq!(current.get_metadata(EXTERNAL_ID_KEY), Some(&serde_json::json!("txn_settled")));
+        assert!(get_current_transaction(&conn, &row.id).unwrap().unwrap().is_voided());
+        assert!(already_settled(&row, &ledger_transactions(&conn, &ledger).unwrap()).is_some());
+
+        // Nothing left to settle
+        assert!(settle_stored(&conn, &ledger, &SettlementMatcher::new(), "tester").unwrap().is_empty());
+    }
+}
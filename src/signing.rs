@@ -0,0 +1,280 @@
+// ✍️ Signing - Ed25519 signatures on exported reports and changesets
+//
+// Problem solved:
+// - A rendered report or a sync changeset handed to an accountant was just a
+//   file: nothing showed it came from this ledger, or that nobody edited it
+//   on the way
+// - The sync HMAC only proves the file to someone holding the same secret,
+//   which an auditor must never be given
+//
+// An installation holds one Ed25519 key (a hex file named in the config, or
+// the `signing-key` secret: OS keyring or $TRUST_SIGNING_KEY, see
+// secrets.rs). Signing writes a
+// detached `<file>.sig` next to the artifact: who signed it (instance and
+// ledger), when, the SHA-256 of the bytes, and the signature over all of that.
+// Anyone with the public key (`signing public-key`) can run `verify <file>`.
+
+use crate::archive::content_sha256;
+use crate::config::AppConfig;
+use crate::secrets::secret;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer as _, SigningKey, Verifier as _, VerifyingKey};
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Secret holding the hex key; wins over the config's key file
+pub const SIGNING_KEY_SECRET: &str = "signing-key";
+
+/// Environment variable the secret falls back to
+pub const SIGNING_KEY_ENV: &str = "TRUST_SIGNING_KEY";
+
+pub const SIGNATURE_ALGORITHM: &str = "ed25519";
+
+// ============================================================================
+// KEYS
+// ============================================================================
+
+/// A fresh random key
+pub fn generate_signing_key() -> SigningKey {
+    SigningKey::generate(&mut OsRng)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex<const N: usize>(text: &str, what: &str) -> Result<[u8; N]> {
+    let text = text.trim();
+    if text.len() != N * 2 {
+        return Err(anyhow!("{} must be {} hex characters", what, N * 2));
+    }
+    let mut bytes = [0u8; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[i * 2..i * 2 + 2], 16).map_err(|_| anyhow!("{} is not valid hex", what))?;
+    }
+    Ok(bytes)
+}
+
+pub fn public_key_hex(key: &VerifyingKey) -> String {
+    to_hex(key.as_bytes())
+}
+
+pub fn parse_public_key(text: &str) -> Result<VerifyingKey> {
+    VerifyingKey::from_bytes(&from_hex::<32>(text, "Public key")?).map_err(|e| anyhow!("Invalid public key: {}", e))
+}
+
+/// The hex secret, as key files and the `signing-key` secret hold it
+pub fn signing_key_hex(key: &SigningKey) -> String {
+    to_hex(&key.to_bytes())
+}
+
+pub fn parse_signing_key(text: &str) -> Result<SigningKey> {
+    Ok(SigningKey::from_bytes(&from_hex::<32>(text, "Signing key")?))
+}
+
+/// Write a key file (hex secret), readable by the owner only
+pub fn save_signing_key(path: &Path, key: &SigningKey) -> Result<()> {
+    if path.exists() {
+        return Err(anyhow!("{:?} already exists; refusing to overwrite a signing key", path));
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, signing_key_hex(key))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+/// This installation's key: the `signing-key` secret (keyring or
+/// $TRUST_SIGNING_KEY), else the config's key file (None when neither is set)
+pub fn load_signing_key(config: &AppConfig) -> Result<Option<SigningKey>> {
+    if let Some(hex) = secret(SIGNING_KEY_SECRET)? {
+        return parse_signing_key(&hex)
+            .map(Some)
+            .with_context(|| format!("Invalid {} secret (keyring or {})", SIGNING_KEY_SECRET, SIGNING_KEY_ENV));
+    }
+    match &config.signing_key_path {
+        Some(path) => {
+            let hex = fs::read_to_string(path).with_context(|| format!("Failed to read signing key {:?}", path))?;
+            parse_signing_key(&hex).map(Some).with_context(|| format!("Invalid signing key file {:?}", path))
+        }
+        None => Ok(None),
+    }
+}
+
+// ============================================================================
+// SIGNATURES
+// ============================================================================
+
+/// Detached signature of one artifact (stored as `<file>.sig`, JSON)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactSignature {
+    pub algorithm: String,
+    /// Signer's public key (hex)
+    pub public_key: String,
+    /// Installation that signed (sync instance id)
+    pub instance_id: String,
+    /// Ledger the artifact was produced from, when it is about one ledger
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ledger_id: Option<String>,
+    /// "report", "changeset", ...
+    pub kind: String,
+    /// File name when signed
+    pub artifact: String,
+    pub sha256: String,
+    pub signed_at: DateTime<Utc>,
+    /// Ed25519 signature (hex) over the JSON of this struct with this field empty
+    #[serde(default)]
+    pub signature: String,
+}
+
+impl ArtifactSignature {
+    /// Bytes covered by the signature (Value maps are sorted, so the form
+    /// survives a round trip through the file)
+    fn message(&self) -> Result<Vec<u8>> {
+        let mut canonical = serde_json::to_value(self)?;
+        canonical["signature"] = serde_json::Value::String(String::new());
+        Ok(canonical.to_string().into_bytes())
+    }
+
+    pub fn verifying_key(&self) -> Result<VerifyingKey> {
+        parse_public_key(&self.public_key)
+    }
+}
+
+/// Who is signing: installation and, for ledger-scoped artifacts, the ledger
+#[derive(Debug, Clone)]
+pub struct SigningIdentity<'a> {
+    pub key: &'a SigningKey,
+    pub instance_id: &'a str,
+    pub ledger_id: Option<&'a str>,
+}
+
+/// Sign `content` (an artifact of `kind` named `artifact`)
+pub fn sign_artifact(signer: &SigningIdentity, kind: &str, artifact: &str, content: &[u8]) -> Result<ArtifactSignature> {
+    let mut signature = ArtifactSignature {
+        algorithm: SIGNATURE_ALGORITHM.to_string(),
+        public_key: public_key_hex(&signer.key.verifying_key()),
+        instance_id: signer.instance_id.to_string(),
+        ledger_id: signer.ledger_id.map(str::to_string),
+        kind: kind.to_string(),
+        artifact: artifact.to_string(),
+        sha256: content_sha256(content),
+        signed_at: Utc::now(),
+        signature: String::new(),
+    };
+    signature.signature = to_hex(&signer.key.sign(&signature.message()?).to_bytes());
+    Ok(signature)
+}
+
+/// Check that `content` is what `signature` signed, and (when given) that the
+/// signer is `trusted`
+pub fn verify_artifact(signature: &ArtifactSignature, content: &[u8], trusted: Option<&VerifyingKey>) -> Result<()> {
+    if signature.algorithm != SIGNATURE_ALGORITHM {
+        return Err(anyhow!("Unsupported signature algorithm '{}'", signature.algorithm));
+    }
+    let key = signature.verifying_key()?;
+    if let Some(trusted) = trusted {
+        if key != *trusted {
+            return Err(anyhow!("Signed by another key ({}), not the trusted one", signature.public_key));
+        }
+    }
+    let bytes = Signature::from_bytes(&from_hex::<64>(&signature.signature, "Signature")?);
+    key.verify(&signature.message()?, &bytes)
+        .map_err(|_| anyhow!("Signature does not match its details (signature file modified)"))?;
+    if content_sha256(content) != signature.sha256 {
+        return Err(anyhow!("{} was modified after it was signed (SHA-256 differs)", signature.artifact));
+    }
+    Ok(())
+}
+
+/// Where the detached signature of `artifact` lives
+pub fn signature_path(artifact: &Path) -> PathBuf {
+    let mut name = artifact.as_os_str().to_os_string();
+    name.push(".sig");
+    PathBuf::from(name)
+}
+
+/// Sign the file at `path` and write `<path>.sig` next to it
+pub fn sign_file(signer: &SigningIdentity, kind: &str, path: &Path) -> Result<ArtifactSignature> {
+    let content = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let signature = sign_artifact(signer, kind, &name, &content)?;
+    fs::write(signature_path(path), serde_json::to_string_pretty(&signature)?)?;
+    Ok(signature)
+}
+
+pub fn read_signature(path: &Path) -> Result<ArtifactSignature> {
+    let json = fs::read_to_string(path).with_context(|| format!("Failed to read signature {:?}", path))?;
+    serde_json::from_str(&json).with_context(|| format!("Invalid signature file {:?}", path))
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    const REPORT: &[u8] = b"<html>January: expenses 45.25</html>";
+
+    fn signer(key: &SigningKey) -> SigningIdentity<'_> {
+        SigningIdentity { key, instance_id: "laptop", ledger_id: Some("personal") }
+    }
+
+    #[test]
+    fn test_sign_and_verify_detect_tampering() {
+        let key = generate_signing_key();
+        let signature = sign_artifact(&signer(&key), "report", "report.html", REPORT).unwrap();
+        assert_eq!(signature.ledger_id.as_deref(), Some("personal"));
+        verify_artifact(&signature, REPORT, Some(&key.verifying_key())).unwrap();
+
+        // Round trip through the .sig file format
+        let stored: ArtifactSignature = serde_json::from_str(&serde_json::to_string(&signature).unwrap()).unwrap();
+        verify_artifact(&stored, REPORT, None).unwrap();
+
+        assert!(verify_artifact(&signature, b"<html>January: expenses 4.25</html>", None).is_err());
+
+        let mut relabeled = signature.clone();
+        relabeled.ledger_id = Some("business".to_string());
+        assert!(verify_artifact(&relabeled, REPORT, None).is_err());
+
+        let other = generate_signing_key();
+        assert!(verify_artifact(&signature, REPORT, Some(&other.verifying_key())).is_err());
+    }
+
+    #[test]
+    fn test_keys_round_trip_through_hex_and_files() {
+        let key = generate_signing_key();
+        let public = public_key_hex(&key.verifying_key());
+        assert_eq!(parse_public_key(&public).unwrap(), key.verifying_key());
+        assert!(parse_public_key("abc").is_err());
+
+        let dir = env::temp_dir().join(format!("trust-signing-{}", uuid::Uuid::new_v4()));
+        let key_path = dir.join("signing.key");
+        save_signing_key(&key_path, &key).unwrap();
+        assert!(save_signing_key(&key_path, &generate_signing_key()).is_err());
+        let config = AppConfig { signing_key_path: Some(key_path.to_string_lossy().to_string()), ..AppConfig::default() };
+        if env::var(SIGNING_KEY_ENV).is_err() {
+            assert_eq!(load_signing_key(&config).unwrap().unwrap().to_bytes(), key.to_bytes());
+        }
+
+        let artifact = dir.join("changeset.json");
+        fs::write(&artifact, b"{}").unwrap();
+        sign_file(&signer(&key), "changeset", &artifact).unwrap();
+        let signature = read_signature(&signature_path(&artifact)).unwrap();
+        assert_eq!((signature.kind.as_str(), signature.artifact.as_str()), ("changeset", "changeset.json"));
+        verify_artifact(&signature, &fs::read(&artifact).unwrap(), Some(&key.verifying_key())).unwrap();
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
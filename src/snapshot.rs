@@ -0,0 +1,169 @@
+// 📸 Read-only Access - Report runs that cannot change the ledger
+//
+// Problem solved:
+// - Reports and the API's read endpoints used the same read-write connection
+//   as imports (and ran migrations on the way in), so "just render January"
+//   could still write to the ledger if any code on that path did
+// - A report built from several queries could mix two states of the ledger
+//   when an import committed halfway through
+//
+// `open_read_only` opens the file with SQLITE_OPEN_READONLY plus `query_only`:
+// a write on that connection is an error, never a change. `DatabaseSnapshot`
+// goes one step further for long runs: VACUUM INTO a temporary copy, opened
+// immutable, so the report sees one point in time and holds no lock on the
+// live file. The copy is deleted when the snapshot is dropped.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// Open the database at `path` for reading only
+pub fn open_read_only(path: &Path) -> Result<Connection> {
+    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+    let conn = Connection::open_with_flags(path, flags)
+        .with_context(|| format!("Cannot open {:?} read-only", path))?;
+    set_query_only(&conn)?;
+    Ok(conn)
+}
+
+/// Refuse writes on an open connection (e.g. an in-memory demo once seeded)
+pub fn set_query_only(conn: &Connection) -> Result<()> {
+    conn.pragma_update(None, "query_only", true)?;
+    Ok(())
+}
+
+/// Whether writes on `conn` are refused
+pub fn is_read_only(conn: &Connection) -> Result<bool> {
+    let query_only: bool = conn.query_row("PRAGMA query_only", [], |row| row.get(0))?;
+    Ok(query_only || conn.is_readonly(DatabaseName::Main)?)
+}
+
+// ============================================================================
+// SNAPSHOT
+// ============================================================================
+
+/// Point-in-time, immutable copy of a database (derefs to its Connection)
+#[derive(Debug)]
+pub struct DatabaseSnapshot {
+    conn: Option<Connection>,
+    path: PathBuf,
+}
+
+impl DatabaseSnapshot {
+    /// Copy the database behind `conn` (read-only connections work too)
+    pub fn take(conn: &Connection) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("trust-snapshot-{}.db", uuid::Uuid::new_v4()));
+        // Dropped (copy deleted) if any step below fails
+        let mut snapshot = DatabaseSnapshot { conn: None, path };
+
+        // query_only also blocks VACUUM INTO: copy through a plain read-only
+        // connection to the same file (in-memory databases copy directly)
+        let source = match conn.path().filter(|file| !file.is_empty()) {
+            Some(file) => Some(Connection::open_with_flags(
+                file,
+                OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            )?),
+            None => None,
+        };
+        source
+            .as_ref()
+            .unwrap_or(conn)
+            .execute("VACUUM INTO ?1", [snapshot.path.to_string_lossy()])
+            .context("Failed to snapshot the database")?;
+
+        // immutable=1: no locks, no WAL; nothing can change the copy anyway
+        let uri = format!("file:{}?immutable=1", uri_escape(&snapshot.path.to_string_lossy()));
+        snapshot.conn = Some(open_read_only(Path::new(&uri))?);
+        Ok(snapshot)
+    }
+
+    /// Temporary file holding the copy
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Deref for DatabaseSnapshot {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("snapshot connection is open until drop")
+    }
+}
+
+impl Drop for DatabaseSnapshot {
+    fn drop(&mut self) {
+        // Close before deleting: Windows won't remove an open file
+        drop(self.conn.take());
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Characters SQLite's URI parser would otherwise read as delimiters
+fn uri_escape(path: &str) -> String {
+    path.replace('%', "%25").replace('?', "%3f").replace('#', "%23")
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{get_all_transactions, setup_database};
+    use crate::imports::import_statement;
+
+    const BOFA: &str = "Date,Description,Amount\n\
+        01/02/2025,STARBUCKS STORE 123,-5.25\n\
+        01/03/2025,SHELL OIL 5521,-40.00\n";
+
+    fn ledger_file() -> (PathBuf, Connection) {
+        let dir = std::env::temp_dir().join(format!("trust-readonly-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let conn = Connection::open(dir.join("trust.db")).unwrap();
+        setup_database(&conn).unwrap();
+        import_statement(&conn, "bofa_jan.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+        (dir, conn)
+    }
+
+    #[test]
+    fn test_read_only_connection_refuses_writes() {
+        let (dir, live) = ledger_file();
+        let conn = open_read_only(&dir.join("trust.db")).unwrap();
+        assert!(is_read_only(&conn).unwrap());
+        assert!(!is_read_only(&live).unwrap());
+        assert_eq!(get_all_transactions(&conn).unwrap().len(), 2);
+
+        assert!(conn.execute("DELETE FROM transactions", []).is_err());
+        assert!(import_statement(&conn, "more.csv", BOFA.as_bytes(), "business", "ana").is_err());
+        assert_eq!(get_all_transactions(&live).unwrap().len(), 2);
+
+        let demo = Connection::open_in_memory().unwrap();
+        setup_database(&demo).unwrap();
+        set_query_only(&demo).unwrap();
+        assert!(demo.execute("DELETE FROM transactions", []).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_is_a_fixed_point_in_time() {
+        let (dir, live) = ledger_file();
+        let reader = open_read_only(&dir.join("trust.db")).unwrap();
+        let snapshot = DatabaseSnapshot::take(&reader).unwrap();
+        let copy = snapshot.path().to_path_buf();
+        assert!(copy.exists());
+
+        live.execute("DELETE FROM transactions WHERE line_number = '2'", []).unwrap();
+        assert_eq!(get_all_transactions(&live).unwrap().len(), 1);
+        assert_eq!(get_all_transactions(&snapshot).unwrap().len(), 2);
+        assert!(is_read_only(&snapshot).unwrap());
+        assert!(snapshot.execute("DELETE FROM transactions", []).is_err());
+
+        drop(snapshot);
+        assert!(!copy.exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
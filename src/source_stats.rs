@@ -0,0 +1,185 @@
+// 📊 Source Stats - How trustworthy each imported file currently is
+//
+// Problem solved:
+// - `get_source_file_stats` answered "how much money came from this file",
+//   not "how good is this feed": no quality, no duplicates, no gaps
+// - Its date range compared MM/DD/YYYY strings, so December sorted before
+//   February and the range of a multi-year file was wrong
+//
+// `source_stats` returns one `SourceStats` per source file of the current,
+// non-voided rows: counts by type, first and last date, the average data
+// quality score, how many rows its imports skipped as duplicates (from the
+// `import_session` events) and how many rows still have no merchant. The TUI's
+// Sources page and `GET /api/sources` show it.
+
+use crate::data_quality::DataQualityEngine;
+use crate::db::{get_active_transactions, Transaction};
+use anyhow::Result;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Statistics of one source file
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceStats {
+    pub source_file: String,
+    pub bank: String,
+    pub transaction_count: usize,
+    /// Transaction type → rows
+    pub by_type: BTreeMap<String, usize>,
+    pub first_date: Option<NaiveDate>,
+    pub last_date: Option<NaiveDate>,
+    pub total_expenses: f64,
+    pub total_income: f64,
+    /// Mean data quality score of its rows (0.0-1.0)
+    pub average_quality: f64,
+    /// Rows its imports skipped as already imported
+    pub duplicates: usize,
+    /// Rows without a merchant (empty or "Unknown")
+    pub unresolved_merchants: usize,
+}
+
+impl SourceStats {
+    /// "2025-01-02 - 2025-01-31" (empty when no row has a readable date)
+    pub fn date_range(&self) -> String {
+        match (self.first_date, self.last_date) {
+            (Some(first), Some(last)) => format!("{} - {}", first, last),
+            _ => String::new(),
+        }
+    }
+}
+
+fn unresolved(tx: &Transaction) -> bool {
+    let merchant = tx.merchant.trim();
+    merchant.is_empty() || merchant.eq_ignore_ascii_case("unknown")
+}
+
+/// Stats per (source file, bank) of `transactions`, by bank then file
+///
+/// `duplicates` maps a file name to the rows its imports skipped.
+pub fn compute_source_stats(transactions: &[Transaction], duplicates: &HashMap<String, usize>) -> Vec<SourceStats> {
+    let quality = DataQualityEngine::new();
+    let mut groups: BTreeMap<(String, String), Vec<&Transaction>> = BTreeMap::new();
+    for tx in transactions.iter().filter(|tx| tx.is_active()) {
+        groups.entry((tx.bank.clone(), tx.source_file.clone())).or_default().push(tx);
+    }
+
+    groups
+        .into_iter()
+        .map(|((bank, source_file), rows)| {
+            let mut by_type: BTreeMap<String, usize> = BTreeMap::new();
+            for tx in &rows {
+                *by_type.entry(tx.transaction_type.clone()).or_default() += 1;
+            }
+            let dates: Vec<NaiveDate> = rows.iter().filter_map(|tx| tx.parsed_date()).collect();
+            let total_of = |kind: &str| {
+                rows.iter()
+                    .filter(|tx| tx.transaction_type == kind)
+                    .fold(0.0, |total, tx| total + tx.amount_numeric.abs())
+            };
+            let quality_sum: f64 = rows.iter().map(|tx| quality.validate(tx).overall_quality).sum();
+
+            SourceStats {
+                transaction_count: rows.len(),
+                by_type,
+                first_date: dates.iter().min().copied(),
+                last_date: dates.iter().max().copied(),
+                total_expenses: total_of("GASTO"),
+                total_income: total_of("INGRESO"),
+                average_quality: quality_sum / rows.len() as f64,
+                duplicates: duplicates.get(&source_file).copied().unwrap_or_default(),
+                unresolved_merchants: rows.iter().filter(|tx| unresolved(tx)).count(),
+                bank,
+                source_file,
+            }
+        })
+        .collect()
+}
+
+/// Rows skipped as duplicates per imported file name (all ledgers when None)
+pub fn duplicates_by_file(conn: &Connection, ledger_id: Option<&str>) -> Result<HashMap<String, usize>> {
+    let mut stmt = conn.prepare(
+        "SELECT json_extract(data, '$.filename'), SUM(COALESCE(json_extract(data, '$.duplicates'), 0))
+         FROM events
+         WHERE event_type = 'import_session' AND json_valid(data) AND (?1 IS NULL OR ledger_id = ?1)
+         GROUP BY 1",
+    )?;
+    let rows = stmt.query_map([ledger_id], |row| {
+        Ok((row.get::<_, Option<String>>(0)?, row.get::<_, i64>(1)?))
+    })?;
+
+    let mut duplicates = HashMap::new();
+    for row in rows {
+        if let (Some(filename), count) = row? {
+            duplicates.insert(filename, count as usize);
+        }
+    }
+    Ok(duplicates)
+}
+
+/// Stats of every source file (of one ledger, or all when None)
+pub fn source_stats(conn: &Connection, ledger_id: Option<&str>) -> Result<Vec<SourceStats>> {
+    let transactions: Vec<Transaction> = get_active_transactions(conn)?
+        .into_iter()
+        .filter(|tx| ledger_id.is_none_or(|ledger_id| tx.ledger_id == ledger_id))
+        .collect();
+    Ok(compute_source_stats(&transactions, &duplicates_by_file(conn, ledger_id)?))
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{setup_database, void_transaction};
+    use crate::imports::import_statement;
+
+    const BOFA: &str = "Date,Description,Amount\n\
+        12/30/2024,STARBUCKS STORE 123,-5.25\n\
+        01/03/2025,SHELL OIL 5521,-40.00\n\
+        02/15/2025,PAYROLL ACME,2000.00\n";
+
+    fn imported() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        import_statement(&conn, "bofa.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_source_stats_count_types_dates_and_duplicates() {
+        let conn = imported();
+        // Importing the same file again skips all three rows
+        import_statement(&conn, "bofa.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+
+        let stats = source_stats(&conn, Some("default")).unwrap();
+        assert_eq!(stats.len(), 1);
+        let bofa = &stats[0];
+        assert_eq!((bofa.source_file.as_str(), bofa.transaction_count), ("bofa.csv", 3));
+        assert_eq!(bofa.by_type.get("INGRESO"), Some(&1));
+        assert_eq!(bofa.date_range(), "2024-12-30 - 2025-02-15");
+        assert_eq!((bofa.total_expenses, bofa.total_income), (45.25, 2000.0));
+        assert_eq!(bofa.duplicates, 3);
+        assert!(bofa.average_quality > 0.0 && bofa.average_quality <= 1.0);
+
+        assert!(source_stats(&conn, Some("business")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unresolved_merchants_and_voided_rows() {
+        let conn = imported();
+        let mut transactions = get_active_transactions(&conn).unwrap();
+        assert_eq!(compute_source_stats(&transactions, &HashMap::new())[0].unresolved_merchants, 0);
+
+        transactions[0].merchant = "Unknown".to_string();
+        transactions[1].merchant = String::new();
+        assert_eq!(compute_source_stats(&transactions, &HashMap::new())[0].unresolved_merchants, 2);
+
+        void_transaction(&conn, &transactions[2].id, "wrong file", "ana").unwrap();
+        let stats = source_stats(&conn, None).unwrap();
+        assert_eq!(stats[0].transaction_count, 2);
+    }
+}
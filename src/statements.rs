@@ -0,0 +1,208 @@
+// 🧾 Statements - Balance snapshots at each statement close
+//
+// Problem solved:
+// - Reconciliation checked one statement at a time, so a missing month (or a
+//   statement entered with the wrong opening balance) went unnoticed
+// - "What was the balance at the end of March?" had no stored answer
+//
+// Every statement close (StatementMetadata) is persisted as a snapshot per
+// account. The history is ordered by statement date, and consecutive snapshots
+// must chain: closing balance of N == opening balance of N+1
+// (see ReconciliationEngine::check_continuity).
+
+use crate::db::{insert_event, Event};
+use crate::reconciliation::StatementMetadata;
+use anyhow::Result;
+use chrono::{NaiveDate, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceSnapshot {
+    pub account_id: String,
+    pub ledger_id: String,
+
+    /// e.g. "January 2025"
+    pub statement_period: String,
+
+    /// Statement close date
+    pub statement_date: NaiveDate,
+
+    pub opening_balance: f64,
+    pub closing_balance: f64,
+
+    /// What the statement says it contains (see manifest.rs)
+    #[serde(default)]
+    pub declared_count: Option<usize>,
+    #[serde(default)]
+    pub declared_total: Option<f64>,
+}
+
+impl BalanceSnapshot {
+    pub fn from_statement(account_id: &str, ledger_id: &str, statement: &StatementMetadata) -> Self {
+        BalanceSnapshot {
+            account_id: account_id.to_string(),
+            ledger_id: ledger_id.to_string(),
+            statement_period: statement.statement_period.clone(),
+            statement_date: statement.statement_date,
+            opening_balance: statement.opening_balance,
+            closing_balance: statement.closing_balance,
+            declared_count: statement.declared_count,
+            declared_total: statement.declared_total,
+        }
+    }
+
+    /// Net the statement's transactions must add up to
+    pub fn expected_total(&self) -> f64 {
+        self.declared_total.unwrap_or(self.closing_balance - self.opening_balance)
+    }
+}
+
+/// Persist the balance at a statement close
+///
+/// One snapshot per ledger, account and close date: recording the same
+/// statement again (a re-import or a corrected statement) replaces it.
+pub fn record_statement_close(
+    conn: &Connection,
+    account_id: &str,
+    ledger_id: &str,
+    statement: &StatementMetadata,
+    actor: &str,
+) -> Result<BalanceSnapshot> {
+    let snapshot = BalanceSnapshot::from_statement(account_id, ledger_id, statement);
+
+    conn.execute(
+        "INSERT INTO balance_snapshots
+            (account_id, ledger_id, statement_period, statement_date, opening_balance, closing_balance, recorded_at,
+             declared_count, declared_total)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+         ON CONFLICT(ledger_id, account_id, statement_date) DO UPDATE SET
+            statement_period = excluded.statement_period,
+            opening_balance = excluded.opening_balance,
+            closing_balance = excluded.closing_balance,
+            recorded_at = excluded.recorded_at,
+            declared_count = excluded.declared_count,
+            declared_total = excluded.declared_total",
+        params![
+            snapshot.account_id,
+            snapshot.ledger_id,
+            snapshot.statement_period,
+            snapshot.statement_date.to_string(),
+            snapshot.opening_balance,
+            snapshot.closing_balance,
+            Utc::now().to_rfc3339(),
+            snapshot.declared_count.map(|count| count as i64),
+            snapshot.declared_total,
+        ],
+    )?;
+
+    let event = Event::typed("statement_closed", "account", account_id, &snapshot, actor)?.with_ledger(ledger_id);
+    insert_event(conn, &event)?;
+
+    Ok(snapshot)
+}
+
+/// Snapshots for an account in a ledger, oldest statement first
+pub fn balance_history(conn: &Connection, account_id: &str, ledger_id: &str) -> Result<Vec<BalanceSnapshot>> {
+    let mut stmt = conn.prepare(
+        "SELECT account_id, ledger_id, statement_period, statement_date, opening_balance, closing_balance,
+                declared_count, declared_total
+         FROM balance_snapshots
+         WHERE account_id = ?1 AND ledger_id = ?2
+         ORDER BY statement_date",
+    )?;
+
+    let snapshots = stmt
+        .query_map([account_id, ledger_id], |row| {
+            let date: String = row.get(3)?;
+            Ok(BalanceSnapshot {
+                account_id: row.get(0)?,
+                ledger_id: row.get(1)?,
+                statement_period: row.get(2)?,
+                statement_date: NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap_or_default(),
+                opening_balance: row.get(4)?,
+                closing_balance: row.get(5)?,
+                declared_count: row.get::<_, Option<i64>>(6)?.map(|count| count as usize),
+                declared_total: row.get(7)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(snapshots)
+}
+
+/// Accounts that have at least one snapshot in a ledger
+pub fn snapshot_accounts(conn: &Connection, ledger_id: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT account_id FROM balance_snapshots WHERE ledger_id = ?1 ORDER BY account_id",
+    )?;
+    let accounts = stmt.query_map([ledger_id], |row| row.get(0))?.collect::<Result<Vec<_>, _>>()?;
+    Ok(accounts)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::setup_database;
+
+    fn statement(period: &str, date: &str, opening: f64, closing: f64) -> StatementMetadata {
+        StatementMetadata {
+            account_name: "BofA Checking".to_string(),
+            statement_period: period.to_string(),
+            opening_balance: opening,
+            closing_balance: closing,
+            statement_date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            declared_count: None,
+            declared_total: None,
+        }
+    }
+
+    #[test]
+    fn test_history_is_ordered_by_statement_date() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        record_statement_close(&conn, "acct-1", "default", &statement("February 2025", "2025-02-28", 2200.0, 1900.0), "ana").unwrap();
+        record_statement_close(&conn, "acct-1", "default", &statement("January 2025", "2025-01-31", 1000.0, 2200.0), "ana").unwrap();
+        record_statement_close(&conn, "acct-2", "default", &statement("January 2025", "2025-01-31", 50.0, 75.0), "ana").unwrap();
+
+        let history = balance_history(&conn, "acct-1", "default").unwrap();
+        let periods: Vec<&str> = history.iter().map(|s| s.statement_period.as_str()).collect();
+        assert_eq!(periods, vec!["January 2025", "February 2025"]);
+        assert_eq!(snapshot_accounts(&conn, "default").unwrap(), vec!["acct-1", "acct-2"]);
+    }
+
+    #[test]
+    fn test_recording_same_close_replaces_snapshot() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        record_statement_close(&conn, "acct-1", "default", &statement("January 2025", "2025-01-31", 1000.0, 2100.0), "ana").unwrap();
+        record_statement_close(&conn, "acct-1", "default", &statement("January 2025", "2025-01-31", 1000.0, 2200.0), "ana").unwrap();
+
+        let history = balance_history(&conn, "acct-1", "default").unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].closing_balance, 2200.0);
+    }
+
+    #[test]
+    fn test_same_close_in_two_ledgers_is_kept_apart() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let january = statement("January 2025", "2025-01-31", 1000.0, 2200.0);
+        record_statement_close(&conn, "Bank of America", "personal", &january, "ana").unwrap();
+        let business = StatementMetadata { closing_balance: 9000.0, ..january };
+        record_statement_close(&conn, "Bank of America", "business", &business, "ana").unwrap();
+
+        let personal = balance_history(&conn, "Bank of America", "personal").unwrap();
+        assert_eq!(personal.len(), 1);
+        assert_eq!(personal[0].ledger_id, "personal");
+        assert_eq!(personal[0].closing_balance, 2200.0);
+        assert_eq!(balance_history(&conn, "Bank of America", "business").unwrap()[0].closing_balance, 9000.0);
+    }
+}
@@ -0,0 +1,576 @@
+// 🔄 Sync - Converge two installations without a central database
+//
+// Problem solved:
+// - Laptop and home server both import and correct transactions
+// - No shared database between them, only files carried across
+//
+// An instance exports a changeset: every transaction version and event written
+// since a checkpoint, signed with a shared key (HMAC-SHA256). The other
+// instance imports it idempotently: versions are keyed by (tx_uuid, version)
+// and events by event_id, so applying the same changeset twice is a no-op.
+
+use crate::conflicts::{is_conflict, resolve_conflict, Conflict, ConflictPolicy};
+use crate::db::{
+    get_current_transaction, insert_event, insert_transaction_row, is_duplicate_insert, row_to_event,
+    row_to_transaction, Event, Transaction, EVENT_SELECT_COLUMNS, TRANSACTION_SELECT_COLUMNS,
+};
+use crate::event_schema::ChangesetImported;
+use crate::ledger::{list_ledgers, Ledger};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::BTreeSet;
+
+// ============================================================================
+// CHECKPOINT
+// ============================================================================
+
+/// Position in an instance's own write log: the last events/transactions row
+/// ids included in an export. Written as "<events>:<transactions>".
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub events: i64,
+    pub transactions: i64,
+}
+
+impl Checkpoint {
+    pub fn parse(s: &str) -> Result<Self> {
+        let (events, transactions) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid checkpoint '{}' (expected <events>:<transactions>)", s))?;
+
+        Ok(Checkpoint {
+            events: events.trim().parse().with_context(|| format!("Invalid checkpoint '{}'", s))?,
+            transactions: transactions
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid checkpoint '{}'", s))?,
+        })
+    }
+}
+
+impl std::fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.events, self.transactions)
+    }
+}
+
+// ============================================================================
+// CHANGESET
+// ============================================================================
+
+/// One stored transaction version, with the dedup hash it was stored under
+/// (corrected versions keep the ORIGINAL hash, so it can't be recomputed)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncedVersion {
+    pub idempotency_hash: String,
+    pub transaction: Transaction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Changeset {
+    /// Instance that produced the changeset
+    pub instance_id: String,
+    pub since: Checkpoint,
+
+    /// Pass as `--since` on the next export to the same peer
+    pub until: Checkpoint,
+    pub created_at: DateTime<Utc>,
+
+    pub ledgers: Vec<Ledger>,
+    pub versions: Vec<SyncedVersion>,
+    pub events: Vec<Event>,
+
+    /// HMAC-SHA256 (hex) over the changeset with this field empty
+    #[serde(default)]
+    pub signature: String,
+}
+
+impl Changeset {
+    /// Sign with the shared sync key
+    pub fn sign(&mut self, key: &str) -> Result<()> {
+        self.signature = self.compute_signature(key)?;
+        Ok(())
+    }
+
+    /// Check the signature (constant time)
+    pub fn verify(&self, key: &str) -> Result<()> {
+        let expected = hex_decode(&self.signature)
+            .ok_or_else(|| anyhow!("Changeset signature is not valid hex"))?;
+
+        self.mac(key)?
+            .verify_slice(&expected)
+            .map_err(|_| anyhow!("Changeset signature does not match (wrong key or modified file)"))
+    }
+
+    fn compute_signature(&self, key: &str) -> Result<String> {
+        let digest = self.mac(key)?.finalize().into_bytes();
+        Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    /// MAC over a canonical form: serde_json::Value maps are sorted, so field
+    /// and metadata order survive the file round-trip
+    fn mac(&self, key: &str) -> Result<Hmac<Sha256>> {
+        if key.is_empty() {
+            return Err(anyhow!("Sync key must not be empty"));
+        }
+
+        let mut canonical = serde_json::to_value(self)?;
+        canonical["signature"] = serde_json::Value::String(String::new());
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes())
+            .map_err(|e| anyhow!("Invalid sync key: {}", e))?;
+        mac.update(canonical.to_string().as_bytes());
+        Ok(mac)
+    }
+}
+
+fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// ============================================================================
+// INSTANCE IDENTITY
+// ============================================================================
+
+/// Stable id of this database, created on first use
+pub fn instance_id(conn: &Connection) -> Result<String> {
+    let existing: Option<String> = conn
+        .query_row("SELECT value FROM sync_state WHERE key = 'instance_id'", [], |row| row.get(0))
+        .optional()?;
+
+    match existing {
+        Some(id) => Ok(id),
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO sync_state (key, value) VALUES ('instance_id', ?1)",
+                [&id],
+            )?;
+            Ok(id)
+        }
+    }
+}
+
+// ============================================================================
+// EXPORT
+// ============================================================================
+
+/// Everything written after `since`, signed with `key`
+pub fn export_changeset(conn: &Connection, since: Checkpoint, key: &str) -> Result<Changeset> {
+    let until = Checkpoint {
+        events: conn.query_row("SELECT COALESCE(MAX(id), 0) FROM events", [], |row| row.get(0))?,
+        transactions: conn.query_row("SELECT COALESCE(MAX(id), 0) FROM transactions", [], |row| {
+            row.get(0)
+        })?,
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, idempotency_hash FROM transactions
+         WHERE id > ?1 AND id <= ?2 AND tx_uuid IS NOT NULL
+         ORDER BY id ASC",
+        TRANSACTION_SELECT_COLUMNS
+    ))?;
+    let versions = stmt
+        .query_map(params![since.transactions, until.transactions], |row| {
+            Ok(SyncedVersion {
+                transaction: row_to_transaction(row)?,
+                idempotency_hash: row.get(22)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM events WHERE id > ?1 AND id <= ?2 ORDER BY id ASC",
+        EVENT_SELECT_COLUMNS
+    ))?;
+    let events = stmt
+        .query_map(params![since.events, until.events], row_to_event)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut changeset = Changeset {
+        instance_id: instance_id(conn)?,
+        since,
+        until,
+        created_at: Utc::now(),
+        ledgers: list_ledgers(conn)?,
+        versions,
+        events,
+        signature: String::new(),
+    };
+    changeset.sign(key)?;
+
+    Ok(changeset)
+}
+
+// ============================================================================
+// IMPORT
+// ============================================================================
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportSummary {
+    pub versions_applied: usize,
+
+    /// Already present (same tx_uuid + version)
+    pub versions_skipped: usize,
+
+    /// Same statement line imported independently under another identity
+    pub versions_duplicate: usize,
+
+    /// Same version written differently on both sides (see conflicts.rs)
+    pub conflicts: usize,
+
+    pub events_applied: usize,
+    pub events_skipped: usize,
+}
+
+/// Verify and apply a changeset (all-or-nothing)
+pub fn import_changeset(
+    conn: &Connection,
+    changeset: &Changeset,
+    key: &str,
+    actor: &str,
+) -> Result<ImportSummary> {
+    changeset.verify(key)?;
+    if changeset.instance_id == instance_id(conn)? {
+        return Err(anyhow!("Changeset was exported by this instance"));
+    }
+
+    let db_tx = conn.unchecked_transaction()?;
+    let mut summary = ImportSummary::default();
+
+    for ledger in &changeset.ledgers {
+        conn.execute(
+            "INSERT OR IGNORE INTO ledgers (id, name, config, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                ledger.id,
+                ledger.name,
+                serde_json::to_string(&ledger.config)?,
+                ledger.created_at.to_rfc3339(),
+            ],
+        )?;
+    }
+
+    apply_versions(conn, &changeset.versions, actor, &mut summary)?;
+
+    for event in &changeset.events {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM events WHERE event_id = ?1)",
+            [&event.event_id],
+            |row| row.get(0),
+        )?;
+        if exists {
+            summary.events_skipped += 1;
+        } else {
+            insert_event(conn, event)?;
+            summary.events_applied += 1;
+        }
+    }
+
+    let payload = ChangesetImported {
+        since: changeset.since.to_string(),
+        until: changeset.until.to_string(),
+        summary: summary.clone(),
+    };
+    let event = Event::typed("changeset_imported", "sync", &changeset.instance_id, &payload, actor)?;
+    insert_event(conn, &event)?;
+
+    db_tx.commit()?;
+    Ok(summary)
+}
+
+/// Insert missing versions, make the highest version of every touched
+/// identity current, then resolve versions that exist here with other values
+fn apply_versions(
+    conn: &Connection,
+    versions: &[SyncedVersion],
+    actor: &str,
+    summary: &mut ImportSummary,
+) -> Result<()> {
+    let mut ordered: Vec<&SyncedVersion> = versions.iter().collect();
+    ordered.sort_by(|a, b| {
+        (a.transaction.id.as_str(), a.transaction.version)
+            .cmp(&(b.transaction.id.as_str(), b.transaction.version))
+    });
+
+    let mut touched = BTreeSet::new();
+    let mut duplicate_ids = BTreeSet::new();
+    let mut competing = Vec::new();
+
+    for synced in ordered {
+        let tx = &synced.transaction;
+        if tx.id.is_empty() {
+            continue;
+        }
+        if duplicate_ids.contains(&tx.id) {
+            summary.versions_duplicate += 1;
+            continue;
+        }
+
+        let existing = conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM transactions WHERE tx_uuid = ?1 AND version = ?2",
+                    TRANSACTION_SELECT_COLUMNS
+                ),
+                params![tx.id, tx.version],
+                row_to_transaction,
+            )
+            .optional()?;
+        if let Some(local) = existing {
+            if is_conflict(&local, tx) {
+                competing.push(tx);
+            } else {
+                summary.versions_skipped += 1;
+            }
+            continue;
+        }
+
+        // Same statement line already current here under another identity
+        let duplicate: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM transactions
+                           WHERE ledger_id = ?1 AND idempotency_hash = ?2
+                             AND valid_until IS NULL AND tx_uuid != ?3)",
+            params![tx.ledger_id, synced.idempotency_hash, tx.id],
+            |row| row.get(0),
+        )?;
+        if duplicate {
+            summary.versions_duplicate += 1;
+            duplicate_ids.insert(tx.id.clone());
+            continue;
+        }
+
+        // A newer version supersedes whatever is current here
+        let current_version: Option<i64> = conn
+            .query_row(
+                "SELECT version FROM transactions WHERE tx_uuid = ?1 AND valid_until IS NULL",
+                [&tx.id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let mut row = tx.clone();
+        match current_version {
+            Some(current) if tx.version > current => {
+                let expired_at = tx.valid_from.unwrap_or_else(Utc::now);
+                conn.execute(
+                    "UPDATE transactions SET valid_until = ?1 WHERE tx_uuid = ?2 AND valid_until IS NULL",
+                    params![expired_at.to_rfc3339(), tx.id],
+                )?;
+            }
+            // Filling a gap in local history: never becomes current
+            Some(_) if row.valid_until.is_none() => row.valid_until = Some(Utc::now()),
+            _ => {}
+        }
+
+        match insert_transaction_row(conn, &row, &synced.idempotency_hash) {
+            Ok(_) => {
+                summary.versions_applied += 1;
+                touched.insert(tx.id.clone());
+            }
+            Err(e) if is_duplicate_insert(conn, &e, &row.ledger_id, &synced.idempotency_hash)? => {
+                summary.versions_duplicate += 1;
+                duplicate_ids.insert(tx.id.clone());
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    for tx_uuid in touched {
+        conn.execute(
+            "UPDATE transactions SET valid_until = NULL
+             WHERE tx_uuid = ?1
+               AND version = (SELECT MAX(version) FROM transactions WHERE tx_uuid = ?1)
+               AND NOT EXISTS (SELECT 1 FROM transactions WHERE tx_uuid = ?1 AND valid_until IS NULL)",
+            [&tx_uuid],
+        )?;
+    }
+
+    // Judged against the CURRENT local version: if this changeset already
+    // carried a newer version of the identity, that version is the local side
+    for incoming in competing {
+        let Some(local) = get_current_transaction(conn, &incoming.id)? else {
+            continue;
+        };
+        if !is_conflict(&local, incoming) {
+            summary.versions_skipped += 1;
+            continue;
+        }
+
+        let policy = ConflictPolicy::for_ledger(conn, &local.ledger_id)?;
+        let conflict = Conflict { local, incoming: incoming.clone() };
+        resolve_conflict(conn, &conflict, policy, actor)?;
+        summary.conflicts += 1;
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::db::{
+        get_events_for_entity, get_transaction_history, insert_transaction_version,
+        insert_transactions_as, setup_database,
+    };
+
+    const KEY: &str = "shared-secret";
+
+    fn sample_transaction(line: &str, amount: f64) -> Transaction {
+        let mut tx = Transaction {
+            date: "05/01/2025".to_string(),
+            amount_original: format!("-${:.2}", amount),
+            amount_numeric: -amount,
+            category: "Food".to_string(),
+            merchant: "Coffee".to_string(),
+            account_number: "1234".to_string(),
+            line_number: line.to_string(),
+            ..test_support::sample_transaction()
+        };
+        tx.init_temporal_fields();
+        tx
+    }
+
+    fn new_instance() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let checkpoint = Checkpoint::parse("12:7").unwrap();
+        assert_eq!(checkpoint, Checkpoint { events: 12, transactions: 7 });
+        assert_eq!(checkpoint.to_string(), "12:7");
+        assert!(Checkpoint::parse("12").is_err());
+        assert!(Checkpoint::parse("a:b").is_err());
+    }
+
+    #[test]
+    fn test_signature_detects_tampering() {
+        let laptop = new_instance();
+        insert_transactions_as(&laptop, &[sample_transaction("1", 4.5)], "test").unwrap();
+
+        let changeset = export_changeset(&laptop, Checkpoint::default(), KEY).unwrap();
+        let json = serde_json::to_string(&changeset).unwrap();
+        let reloaded: Changeset = serde_json::from_str(&json).unwrap();
+        assert!(reloaded.verify(KEY).is_ok());
+        assert!(reloaded.verify("other-key").is_err());
+
+        let mut tampered = reloaded.clone();
+        tampered.versions[0].transaction.amount_numeric = -4500.0;
+        assert!(tampered.verify(KEY).is_err());
+    }
+
+    #[test]
+    fn test_import_is_idempotent_and_converges() {
+        let laptop = new_instance();
+        let server = new_instance();
+
+        let tx = sample_transaction("1", 4.5);
+        insert_transactions_as(&laptop, &[tx.clone(), sample_transaction("2", 9.0)], "test").unwrap();
+        let mut corrected = tx.next_version(Some("Wrong category".to_string()));
+        corrected.category = "Coffee".to_string();
+        insert_transaction_version(&laptop, &corrected, "laptop-user").unwrap();
+
+        let changeset = export_changeset(&laptop, Checkpoint::default(), KEY).unwrap();
+        let summary = import_changeset(&server, &changeset, KEY, "server-user").unwrap();
+        assert_eq!(summary.versions_applied, 3);
+        assert!(summary.events_applied > 0);
+
+        let history = get_transaction_history(&server, &tx.id).unwrap();
+        assert_eq!(history.len(), 2);
+        let current = get_current_transaction(&server, &tx.id).unwrap().unwrap();
+        assert_eq!(current.version, 2);
+        assert_eq!(current.category, "Coffee");
+
+        // Same changeset again: nothing new
+        let again = import_changeset(&server, &changeset, KEY, "server-user").unwrap();
+        assert_eq!(again.versions_applied, 0);
+        assert_eq!(again.versions_skipped, 3);
+        assert_eq!(again.events_applied, 0);
+
+        // Incremental export picks up only the new correction
+        let mut fixed = current.next_version(Some("Merchant typo".to_string()));
+        fixed.merchant = "Blue Bottle".to_string();
+        insert_transaction_version(&laptop, &fixed, "laptop-user").unwrap();
+
+        let delta = export_changeset(&laptop, changeset.until, KEY).unwrap();
+        assert_eq!(delta.versions.len(), 1);
+        import_changeset(&server, &delta, KEY, "server-user").unwrap();
+        let current = get_current_transaction(&server, &tx.id).unwrap().unwrap();
+        assert_eq!((current.version, current.merchant.as_str()), (3, "Blue Bottle"));
+
+        // A changeset can't be imported back into its origin
+        assert!(import_changeset(&laptop, &changeset, KEY, "laptop-user").is_err());
+    }
+
+    #[test]
+    fn test_independent_import_of_same_line_is_duplicate() {
+        let laptop = new_instance();
+        let server = new_instance();
+
+        // Both imported the same statement line, each under its own identity;
+        // the laptop also corrected it
+        let tx = sample_transaction("1", 4.5);
+        insert_transactions_as(&laptop, std::slice::from_ref(&tx), "test").unwrap();
+        insert_transaction_version(&laptop, &tx.next_version(None), "laptop-user").unwrap();
+        insert_transactions_as(&server, &[sample_transaction("1", 4.5)], "test").unwrap();
+
+        let changeset = export_changeset(&laptop, Checkpoint::default(), KEY).unwrap();
+        let summary = import_changeset(&server, &changeset, KEY, "server-user").unwrap();
+        assert_eq!(summary.versions_applied, 0);
+        assert_eq!(summary.versions_duplicate, 2);
+        assert!(get_transaction_history(&server, &tx.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_corrections_converge() {
+        let laptop = new_instance();
+        let server = new_instance();
+
+        let tx = sample_transaction("1", 4.5);
+        insert_transactions_as(&laptop, std::slice::from_ref(&tx), "test").unwrap();
+        let initial = export_changeset(&laptop, Checkpoint::default(), KEY).unwrap();
+        import_changeset(&server, &initial, KEY, "server-user").unwrap();
+
+        // Both sides write v2 before syncing again; the server writes last
+        let mut on_laptop = tx.next_version(None);
+        on_laptop.category = "Snacks".to_string();
+        insert_transaction_version(&laptop, &on_laptop, "laptop-user").unwrap();
+        let mut on_server = tx.next_version(None);
+        on_server.category = "Coffee".to_string();
+        insert_transaction_version(&server, &on_server, "server-user").unwrap();
+
+        let from_laptop = export_changeset(&laptop, initial.until, KEY).unwrap();
+        let from_server = export_changeset(&server, Checkpoint::default(), KEY).unwrap();
+
+        let summary = import_changeset(&server, &from_laptop, KEY, "server-user").unwrap();
+        assert_eq!(summary.conflicts, 1);
+        let summary = import_changeset(&laptop, &from_server, KEY, "laptop-user").unwrap();
+        assert_eq!(summary.conflicts, 1);
+
+        let on_server = get_current_transaction(&server, &tx.id).unwrap().unwrap();
+        let on_laptop = get_current_transaction(&laptop, &tx.id).unwrap().unwrap();
+        assert_eq!(on_server.category, "Coffee");
+        assert_eq!(on_laptop.category, "Coffee");
+
+        // Recorded, not silently dropped
+        let events = get_events_for_entity(&laptop, "transaction", &tx.id).unwrap();
+        assert!(events.iter().any(|e| e.event_type == "conflict_detected"));
+    }
+}
@@ -0,0 +1,808 @@
+// 🏛️ Trust System - One handle for the whole library
+//
+// Problem solved:
+// - Every caller opened a Connection, ran setup_database, loaded rules, built
+//   registries and engines, and passed ledger id and actor to each call
+// - The same steps were repeated (slightly differently) in the CLI, the
+//   server and tests
+//
+// `TrustSystem` owns the storage handle, the entity registries and the
+// engines, and works in one ledger as one actor. Its methods are the common
+// operations; `conn()` is still there for everything else.
+//
+// `TrustSystem::builder()` picks the parts: where data lives (a file, memory
+// or an open connection), and the rules, duplicate strategies and FX rates
+// to use instead of the stored / built-in ones. `TrustSystem::from_config`
+// takes storage and base currency from the config file, so a `db_path` of
+// ":memory:" runs the whole system (storage and registries) in memory.
+
+use crate::accounts::list_accounts;
+use crate::approvals::{submit_correction, submit_void, WriteOutcome};
+use crate::aliases::confirmed_alias_groups;
+use crate::analytics::{merchant_profile, MerchantProfile};
+use crate::bootstrap::{bootstrap_registries, register_confirmed_aliases, BootstrapReport};
+use crate::config::AppConfig;
+use crate::data_quality::{BatchSummary, DataQualityEngine};
+use crate::db::{get_active_transactions, get_current_transaction, setup_database, Transaction};
+use crate::deduplication::{DeduplicationEngine, DuplicateMatch, DuplicateMatcher};
+use crate::entities::{
+    load_accounts, load_banks, load_categories, load_merchants, save_registries, AccountRegistry, Bank, BankRegistry,
+    CategoryRegistry, Merchant, MerchantRegistry,
+};
+use crate::explanations::explain_report;
+use crate::fx::{convert, FixedRates, FxRateProvider};
+use crate::history::transactions_as_of;
+use crate::imports::{import_directory_with, import_statement_with, DirectoryImport, ImportContext, ImportSession};
+use crate::jobs::ledger_transactions;
+use crate::ledger::{require_ledger, DEFAULT_LEDGER_ID};
+use crate::period_close::{close_period, reopen_period, ClosedPeriod};
+use crate::positions::{net_worth, NetWorth};
+use crate::query::{TransactionFilter, TransactionPage, TransactionQuery};
+use crate::reconciliation::{ReconciliationEngine, ReconciliationReport, StatementMetadata};
+use crate::rules::RuleEngine;
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+/// Actor recorded when the caller doesn't name one
+pub const SYSTEM_ACTOR: &str = "system";
+
+/// Currency amounts are converted to when the builder doesn't say
+pub const DEFAULT_BASE_CURRENCY: &str = "USD";
+
+/// Fields a correction may change (unset fields keep their value)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Correction {
+    pub category: Option<String>,
+    pub merchant: Option<String>,
+    pub transaction_type: Option<String>,
+    pub reason: String,
+}
+
+impl Correction {
+    /// The next version of `current` with this correction applied
+    pub fn apply(&self, current: &Transaction) -> Transaction {
+        let mut next = current.next_version(Some(self.reason.clone()));
+        if let Some(category) = &self.category {
+            next.category = category.clone();
+        }
+        if let Some(merchant) = &self.merchant {
+            next.merchant = merchant.clone();
+        }
+        if let Some(transaction_type) = &self.transaction_type {
+            next.transaction_type = transaction_type.clone();
+        }
+        next
+    }
+}
+
+/// Registry entities that can be renamed by name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityKind {
+    Merchant,
+    Bank,
+    Category,
+}
+
+impl EntityKind {
+    /// "merchant" or "merchants", and so on
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "merchant" | "merchants" => Some(EntityKind::Merchant),
+            "bank" | "banks" => Some(EntityKind::Bank),
+            "category" | "categories" => Some(EntityKind::Category),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntityKind::Merchant => "merchant",
+            EntityKind::Bank => "bank",
+            EntityKind::Category => "category",
+        }
+    }
+}
+
+pub struct TrustSystem {
+    conn: Connection,
+    ledger_id: String,
+    actor: String,
+    base_currency: String,
+
+    pub rules: RuleEngine,
+    pub merchants: MerchantRegistry,
+    pub categories: CategoryRegistry,
+    pub banks: BankRegistry,
+    pub accounts: AccountRegistry,
+
+    pub deduplication: DeduplicationEngine,
+    pub reconciliation: ReconciliationEngine,
+    pub quality: DataQualityEngine,
+    pub fx_rates: Box<dyn FxRateProvider>,
+}
+
+impl TrustSystem {
+    pub fn builder() -> TrustSystemBuilder {
+        TrustSystemBuilder::default()
+    }
+
+    /// Open (and migrate) the database at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::builder().database(path).build()
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::builder().in_memory().build()
+    }
+
+    /// Open the storage `config` names (in memory for ":memory:"), with its
+    /// base currency
+    pub fn from_config(config: &AppConfig) -> Result<Self> {
+        Self::builder().config(config).build()
+    }
+
+    /// Migrate `conn` and load the stored rules; default ledger, system actor
+    pub fn from_connection(conn: Connection) -> Result<Self> {
+        Self::builder().connection(conn).build()
+    }
+
+    /// Work in another (existing) ledger
+    pub fn with_ledger(mut self, ledger_id: &str) -> Result<Self> {
+        self.ledger_id = require_ledger(&self.conn, ledger_id)?.id;
+        Ok(self)
+    }
+
+    /// Record writes as `actor`
+    pub fn with_actor(mut self, actor: &str) -> Self {
+        self.actor = actor.to_string();
+        self
+    }
+
+    pub fn conn(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Give up the facade, keeping the (migrated) connection
+    pub fn into_connection(self) -> Connection {
+        self.conn
+    }
+
+    pub fn ledger_id(&self) -> &str {
+        &self.ledger_id
+    }
+
+    pub fn actor(&self) -> &str {
+        &self.actor
+    }
+
+    pub fn base_currency(&self) -> &str {
+        &self.base_currency
+    }
+
+    /// Active transactions of the current ledger
+    pub fn transactions(&self) -> Result<Vec<Transaction>> {
+        ledger_transactions(&self.conn, &self.ledger_id)
+    }
+
+    /// Current version of a transaction in this ledger
+    pub fn transaction(&self, tx_uuid: &str) -> Result<Transaction> {
+        get_current_transaction(&self.conn, tx_uuid)?
+            .filter(|tx| tx.ledger_id == self.ledger_id)
+            .ok_or_else(|| anyhow!("Transaction {} not found in ledger '{}'", tx_uuid, self.ledger_id))
+    }
+
+    /// Import a statement file (CSV, JSON or OFX; the format is detected)
+    pub fn import_file(&self, path: impl AsRef<Path>) -> Result<ImportSession> {
+        self.import_path(path.as_ref(), false)
+    }
+
+    /// Re-import a statement file, filling blank fields of rows already
+    /// stored (as new versions) instead of skipping them
+    pub fn upsert_file(&self, path: impl AsRef<Path>) -> Result<ImportSession> {
+        self.import_path(path.as_ref(), true)
+    }
+
+    /// Import every statement file under a directory, one session per file
+    /// (see `import_directory_with`)
+    pub fn import_directory(&self, dir: impl AsRef<Path>, upsert: bool) -> Result<DirectoryImport> {
+        import_directory_with(
+            &self.conn,
+            dir.as_ref(),
+            &self.ledger_id,
+            &self.actor,
+            &ImportContext::new(&self.rules, &self.deduplication)
+                .with_upsert(upsert)
+                .with_prices(self.fx_rates.as_ref()),
+        )
+    }
+
+    fn import_path(&self, path: &Path, upsert: bool) -> Result<ImportSession> {
+        let content = std::fs::read(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let filename = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        import_statement_with(
+            &self.conn,
+            filename,
+            &content,
+            &self.ledger_id,
+            &self.actor,
+            &ImportContext::new(&self.rules, &self.deduplication)
+                .with_upsert(upsert)
+                .with_prices(self.fx_rates.as_ref()),
+        )
+    }
+
+    /// One page of this ledger's transactions (as of `query.as_of` if set)
+    pub fn query(&self, query: &TransactionQuery) -> Result<TransactionPage> {
+        let transactions = match query.as_of {
+            Some(as_of) => transactions_as_of(&self.conn, as_of)?,
+            None => get_active_transactions(&self.conn)?,
+        };
+        let in_ledger = TransactionFilter::new().in_ledger(&self.ledger_id).apply(&transactions);
+        query.run(&in_ledger)
+    }
+
+    /// Correct a transaction (or propose the correction when approval is required)
+    pub fn correct(&self, tx_uuid: &str, correction: &Correction) -> Result<WriteOutcome> {
+        let next = correction.apply(&self.transaction(tx_uuid)?);
+        submit_correction(&self.conn, &next, &self.actor)
+    }
+
+    pub fn void(&self, tx_uuid: &str, reason: &str) -> Result<WriteOutcome> {
+        self.transaction(tx_uuid)?;
+        submit_void(&self.conn, tx_uuid, reason, &self.actor)
+    }
+
+    /// Reconcile a statement against the account's transactions since `from`
+    /// (dated on the ledger's `date_basis`), explaining a balance mismatch
+    /// when a likely cause is found
+    pub fn reconcile(&self, statement: &StatementMetadata, from: NaiveDate) -> Result<ReconciliationReport> {
+        let basis = require_ledger(&self.conn, &self.ledger_id)?.config.date_basis.unwrap_or_default();
+        let ledger = self.transactions()?;
+        let transactions = ReconciliationEngine::statement_transactions(&ledger, statement, from, basis);
+        let mut report = self.reconciliation.reconcile(&transactions, statement);
+        explain_report(&mut report, &ledger, from, basis);
+        Ok(report)
+    }
+
+    /// Lock a reconciled statement period of an account against corrections
+    pub fn close_period(&self, account_id: &str, period: &str) -> Result<ClosedPeriod> {
+        close_period(&self.conn, &self.ledger_id, account_id, period, &self.actor)
+    }
+
+    /// Open a closed period to corrections again (`reason` is recorded)
+    pub fn reopen_period(&self, account_id: &str, period: &str, reason: &str) -> Result<ClosedPeriod> {
+        reopen_period(&self.conn, &self.ledger_id, account_id, period, reason, &self.actor)
+    }
+
+    /// Data quality of the ledger's active transactions (FX rates checked
+    /// against `fx_rates`)
+    pub fn quality_report(&self) -> Result<BatchSummary> {
+        let reports = self.quality.validate_batch_with_rates(&self.transactions()?, self.fx_rates.as_ref());
+        Ok(self.quality.batch_summary(&reports))
+    }
+
+    /// Likely duplicates among `transactions` (indexes refer to that slice)
+    pub fn find_duplicates(&self, transactions: &[Transaction]) -> Vec<DuplicateMatch> {
+        self.deduplication.find_duplicates(transactions)
+    }
+
+    /// A transaction's amount in the base currency, at its statement date's rate
+    pub fn amount_in_base_currency(&self, tx: &Transaction) -> Result<f64> {
+        let date = tx
+            .parsed_date()
+            .ok_or_else(|| anyhow!("Transaction {} has an unreadable date '{}'", tx.id, tx.date))?;
+        convert(self.fx_rates.as_ref(), tx.amount_numeric, &tx.currency, &self.base_currency, date)
+    }
+
+    /// Net worth of the ledger on `date`, positions priced with `fx_rates`
+    pub fn net_worth(&self, date: NaiveDate) -> Result<NetWorth> {
+        net_worth(&self.conn, &self.ledger_id, self.fx_rates.as_ref(), date)
+    }
+
+    /// Spend profile of a merchant in this ledger (None: unknown merchant id)
+    pub fn merchant_profile(&self, merchant_id: &str) -> Result<Option<MerchantProfile>> {
+        Ok(merchant_profile(&self.merchants, merchant_id, &self.transactions()?))
+    }
+
+    /// Fill the merchant, bank and account registries from the ledger's
+    /// stored accounts, confirmed alias groups and active transactions
+    pub fn bootstrap_entities(&mut self) -> Result<BootstrapReport> {
+        for account in list_accounts(&self.conn, &self.ledger_id)? {
+            if self.accounts.find_by_id(&account.id).is_none() {
+                self.accounts.register(account);
+            }
+        }
+        let mut report = BootstrapReport::default();
+        let confirmed = confirmed_alias_groups(&self.conn, Some(&self.ledger_id))?;
+        register_confirmed_aliases(&mut self.merchants, &confirmed, &mut report);
+
+        let transactions = self.transactions()?;
+        let scanned = bootstrap_registries(&transactions, &mut self.merchants, &mut self.banks, &mut self.accounts);
+        Ok(BootstrapReport {
+            merchants_added: report.merchants_added + scanned.merchants_added,
+            aliases_added: report.aliases_added + scanned.aliases_added,
+            ..scanned
+        })
+    }
+
+    /// Save every version of the four registries, so the next start (or
+    /// another process on the same database) gets the same entities and ids
+    pub fn save_entities(&self) -> Result<usize> {
+        save_registries(&self.conn, &self.merchants, &self.categories, &self.banks, &self.accounts)
+    }
+
+    /// Rename the merchant, bank or category with this id or name (ignoring
+    /// case) as a new version, and save the registries; returns its id
+    ///
+    /// Merchants and banks keep the old name as an alias, so rows spelled
+    /// the old way still resolve to them.
+    pub fn rename_entity(&mut self, kind: EntityKind, name: &str, new_name: &str) -> Result<String> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            return Err(anyhow!("The new {} name is empty", kind.as_str()));
+        }
+        let taken = |id: &str, other: Option<String>| match other {
+            Some(other) if other != id => Err(anyhow!("Another {} is already named '{}'", kind.as_str(), new_name)),
+            _ => Ok(()),
+        };
+        let rename = |current: &mut String, aliases: Option<&mut Vec<String>>| {
+            let old = std::mem::replace(current, new_name.to_string());
+            if let Some(aliases) = aliases {
+                aliases.retain(|alias| !alias.eq_ignore_ascii_case(new_name));
+                if !aliases.contains(&old) && old != new_name {
+                    aliases.push(old);
+                }
+            }
+        };
+
+        let id = match kind {
+            EntityKind::Merchant => {
+                let merchant = self.merchant_named(name)?;
+                taken(&merchant.id, self.merchant_named(new_name).ok().map(|m| m.id))?;
+                if merchant.canonical_name != new_name {
+                    self.merchants
+                        .update_merchant(&merchant.id, |m| rename(&mut m.canonical_name, Some(&mut m.aliases)))
+                        .map_err(|e| anyhow!(e))?;
+                }
+                merchant.id
+            }
+            EntityKind::Bank => {
+                let bank = self.bank_named(name)?;
+                taken(&bank.id, self.bank_named(new_name).ok().map(|b| b.id))?;
+                if bank.canonical_name != new_name {
+                    self.banks
+                        .update_bank(&bank.id, |b| rename(&mut b.canonical_name, Some(&mut b.aliases)))
+                        .map_err(|e| anyhow!(e))?;
+                }
+                bank.id
+            }
+            EntityKind::Category => {
+                let category = self
+                    .categories
+                    .find_by_id(name)
+                    .or_else(|| self.categories.find_by_name(name))
+                    .ok_or_else(|| anyhow!("No category named '{}'", name))?;
+                taken(&category.id, self.categories.get_id(new_name))?;
+                if category.name != new_name {
+                    self.categories
+                        .update_category(&category.id, |c| rename(&mut c.name, None))
+                        .map_err(|e| anyhow!(e))?;
+                }
+                category.id
+            }
+        };
+        self.save_entities()?;
+        Ok(id)
+    }
+
+    /// Add a spelling to the merchant with this id or name (ignoring case)
+    /// as a new version, and save the registries; returns the merchant
+    pub fn add_merchant_alias(&mut self, merchant: &str, alias: &str) -> Result<Merchant> {
+        let alias = alias.trim();
+        if alias.is_empty() {
+            return Err(anyhow!("The alias is empty"));
+        }
+        let merchant = self.merchant_named(merchant)?;
+        if let Ok(other) = self.merchant_named(alias) {
+            if other.id != merchant.id {
+                return Err(anyhow!("'{}' already names {}", alias, other.canonical_name));
+            }
+            return Ok(merchant);
+        }
+        self.merchants
+            .update_merchant(&merchant.id, |m| m.add_alias(alias.to_string()))
+            .map_err(|e| anyhow!(e))?;
+        self.save_entities()?;
+        self.merchants
+            .find_by_id(&merchant.id)
+            .ok_or_else(|| anyhow!("Merchant {} disappeared", merchant.id))
+    }
+
+    /// Current merchant whose id, name or alias is `name` (no fuzzy match:
+    /// edits must not land on a look-alike)
+    fn merchant_named(&self, name: &str) -> Result<Merchant> {
+        let name = name.trim();
+        self.merchants
+            .all_merchants()
+            .into_iter()
+            .find(|m| m.id == name || m.all_names().iter().any(|n| n.eq_ignore_ascii_case(name)))
+            .ok_or_else(|| anyhow!("No merchant named '{}'", name))
+    }
+
+    /// Current bank whose id, name or alias is `name`
+    fn bank_named(&self, name: &str) -> Result<Bank> {
+        let name = name.trim();
+        self.banks
+            .all_banks()
+            .into_iter()
+            .find(|b| b.id == name || b.all_names().iter().any(|n| n.eq_ignore_ascii_case(name)))
+            .ok_or_else(|| anyhow!("No bank named '{}'", name))
+    }
+}
+
+// ============================================================================
+// BUILDER
+// ============================================================================
+
+/// Where the data lives
+enum Storage {
+    InMemory,
+    File(PathBuf),
+    Connection(Connection),
+}
+
+/// Assembles a `TrustSystem`; every part not set gets the usual default
+///
+/// Defaults: in-memory storage, default ledger, system actor, the rules and
+/// entity registries stored in the database (built-in ones when none were
+/// saved), the built-in duplicate strategies, USD as base currency and no FX
+/// rates besides identity.
+pub struct TrustSystemBuilder {
+    storage: Storage,
+    ledger_id: Option<String>,
+    actor: Option<String>,
+    base_currency: Option<String>,
+    rules: Option<RuleEngine>,
+    merchants: Option<MerchantRegistry>,
+    categories: Option<CategoryRegistry>,
+    deduplication: DeduplicationEngine,
+    reconciliation: Option<ReconciliationEngine>,
+    quality: Option<DataQualityEngine>,
+    fx_rates: Option<Box<dyn FxRateProvider>>,
+}
+
+impl Default for TrustSystemBuilder {
+    fn default() -> Self {
+        TrustSystemBuilder {
+            storage: Storage::InMemory,
+            ledger_id: None,
+            actor: None,
+            base_currency: None,
+            rules: None,
+            merchants: None,
+            categories: None,
+            deduplication: DeduplicationEngine::new(),
+            reconciliation: None,
+            quality: None,
+            fx_rates: None,
+        }
+    }
+}
+
+impl TrustSystemBuilder {
+    /// Store data in the SQLite file at `path` (created if missing)
+    pub fn database(mut self, path: impl AsRef<Path>) -> Self {
+        self.storage = Storage::File(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Keep everything in memory (tests, demos)
+    pub fn in_memory(mut self) -> Self {
+        self.storage = Storage::InMemory;
+        self
+    }
+
+    /// Storage and base currency from `config` (a ":memory:" db_path keeps
+    /// everything in memory)
+    pub fn config(self, config: &AppConfig) -> Self {
+        let builder = if config.in_memory() { self.in_memory() } else { self.database(&config.db_path) };
+        builder.base_currency(&config.base_currency)
+    }
+
+    /// Use an already open connection
+    pub fn connection(mut self, conn: Connection) -> Self {
+        self.storage = Storage::Connection(conn);
+        self
+    }
+
+    /// Work in `ledger_id` (it must exist)
+    pub fn ledger(mut self, ledger_id: &str) -> Self {
+        self.ledger_id = Some(ledger_id.to_string());
+        self
+    }
+
+    pub fn actor(mut self, actor: &str) -> Self {
+        self.actor = Some(actor.to_string());
+        self
+    }
+
+    /// Currency `amount_in_base_currency` converts to
+    pub fn base_currency(mut self, currency: &str) -> Self {
+        self.base_currency = Some(currency.to_ascii_uppercase());
+        self
+    }
+
+    /// Classify with these rules instead of the ones stored in the database
+    pub fn rules(mut self, rules: RuleEngine) -> Self {
+        self.rules = Some(rules);
+        self
+    }
+
+    pub fn merchants(mut self, merchants: MerchantRegistry) -> Self {
+        self.merchants = Some(merchants);
+        self
+    }
+
+    pub fn categories(mut self, categories: CategoryRegistry) -> Self {
+        self.categories = Some(categories);
+        self
+    }
+
+    /// Replace the duplicate detection engine (thresholds and strategies)
+    pub fn deduplication(mut self, engine: DeduplicationEngine) -> Self {
+        self.deduplication = engine;
+        self
+    }
+
+    /// Add a duplicate strategy, tried after the built-in ones
+    pub fn match_strategy(mut self, matcher: impl DuplicateMatcher + 'static) -> Self {
+        self.deduplication = self.deduplication.with_matcher(matcher);
+        self
+    }
+
+    pub fn reconciliation(mut self, engine: ReconciliationEngine) -> Self {
+        self.reconciliation = Some(engine);
+        self
+    }
+
+    pub fn quality(mut self, engine: DataQualityEngine) -> Self {
+        self.quality = Some(engine);
+        self
+    }
+
+    pub fn fx_rates(mut self, provider: impl FxRateProvider + 'static) -> Self {
+        self.fx_rates = Some(Box::new(provider));
+        self
+    }
+
+    /// Open and migrate the storage, then assemble the system
+    pub fn build(self) -> Result<TrustSystem> {
+        let conn = match self.storage {
+            Storage::InMemory => Connection::open_in_memory()?,
+            Storage::File(path) => Connection::open(&path)
+                .map_err(|e| anyhow!("Failed to open database {}: {}", path.display(), e))?,
+            Storage::Connection(conn) => conn,
+        };
+        setup_database(&conn)?;
+
+        let rules = match self.rules {
+            Some(rules) => rules,
+            None => RuleEngine::from_database(&conn)?,
+        };
+        let ledger_id = match self.ledger_id {
+            Some(ledger_id) => require_ledger(&conn, &ledger_id)?.id,
+            None => DEFAULT_LEDGER_ID.to_string(),
+        };
+        let merchants = match self.merchants {
+            Some(merchants) => merchants,
+            None => load_merchants(&conn)?,
+        };
+        let categories = match self.categories {
+            Some(categories) => categories,
+            None => load_categories(&conn)?,
+        };
+        let banks = load_banks(&conn)?;
+        let accounts = load_accounts(&conn)?;
+
+        Ok(TrustSystem {
+            conn,
+            ledger_id,
+            actor: self.actor.unwrap_or_else(|| SYSTEM_ACTOR.to_string()),
+            base_currency: self.base_currency.unwrap_or_else(|| DEFAULT_BASE_CURRENCY.to_string()),
+            rules,
+            merchants,
+            categories,
+            banks,
+            accounts,
+            deduplication: self.deduplication,
+            reconciliation: self.reconciliation.unwrap_or_default(),
+            quality: self.quality.unwrap_or_default(),
+            fx_rates: self.fx_rates.unwrap_or_else(|| Box::new(FixedRates::new())),
+        })
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "Date,Description,Amount_Original,Amount_Numeric,Transaction_Type,Category,Merchant,Currency,Account_Name,Account_Number,Bank,Source_File,Line_Number,Classification_Notes
+01/05/2025,STARBUCKS,-5.00,-5.00,GASTO,Restaurants,Starbucks,USD,Apple Card,0001,AppleCard,a.csv,2,
+01/20/2025,PAYROLL,100.00,100.00,INGRESO,Income,Acme,USD,Apple Card,0001,AppleCard,a.csv,3,
+";
+
+    fn system_with_statement() -> TrustSystem {
+        let system = TrustSystem::open_in_memory().unwrap().with_actor("ana");
+        let path = std::env::temp_dir().join(format!("system-{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(&path, CSV).unwrap();
+        let session = system.import_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(session.inserted, 2);
+        system
+    }
+
+    #[test]
+    fn test_import_query_and_correct() {
+        let system = system_with_statement();
+        let page = system.query(&TransactionQuery::default()).unwrap();
+        assert_eq!(page.total, 2);
+
+        let starbucks = page.transactions.iter().find(|tx| tx.merchant == "Starbucks").unwrap();
+        let correction = Correction {
+            category: Some("Coffee".to_string()),
+            reason: "coffee, not a restaurant".to_string(),
+            ..Correction::default()
+        };
+        match system.correct(&starbucks.id, &correction).unwrap() {
+            WriteOutcome::Applied(tx) => assert_eq!((tx.category.as_str(), tx.version), ("Coffee", 2)),
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+        system.void(&starbucks.id, "duplicate").unwrap();
+        assert_eq!(system.transactions().unwrap().len(), 1);
+        assert!(system.correct("missing", &correction).is_err());
+        assert!(TrustSystem::open_in_memory().unwrap().with_ledger("nope").is_err());
+    }
+
+    #[test]
+    fn test_reconcile_and_quality_report() {
+        let system = system_with_statement();
+        let statement = StatementMetadata {
+            account_name: "Apple Card".to_string(),
+            statement_period: "2025-01".to_string(),
+            opening_balance: 0.0,
+            closing_balance: 95.0,
+            statement_date: NaiveDate::from_ymd_opt(2025, 1, 31).unwrap(),
+            declared_count: None,
+            declared_total: None,
+        };
+        let report = system.reconcile(&statement, NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()).unwrap();
+        assert!(report.is_balanced());
+        assert_eq!(report.transaction_count, 2);
+
+        assert_eq!(system.quality_report().unwrap().total_transactions, 2);
+    }
+
+    #[test]
+    fn test_builder_injects_rules_matchers_and_fx_rates() {
+        struct SameDay;
+
+        impl DuplicateMatcher for SameDay {
+            fn name(&self) -> &str {
+                "same_day"
+            }
+
+            fn check(&self, tx1: &Transaction, tx2: &Transaction) -> Option<(f64, String)> {
+                (tx1.date == tx2.date).then(|| (0.5, "same day".to_string()))
+            }
+        }
+
+        let rules = RuleEngine::from_rules(vec![crate::rules::ClassificationRule {
+            id: "coffee".to_string(),
+            pattern: "STARBUCKS".to_string(),
+            merchant: Some("Starbucks".to_string()),
+            category: Some("Coffee".to_string()),
+            transaction_type: None,
+            confidence: 0.9,
+            description: None,
+            priority: 0,
+        }]);
+        let system = TrustSystem::builder()
+            .in_memory()
+            .actor("ana")
+            .rules(rules)
+            .match_strategy(SameDay)
+            .base_currency("mxn")
+            .fx_rates(FixedRates::new().with_rate("USD", "MXN", 20.0))
+            .build()
+            .unwrap();
+        assert_eq!((system.actor(), system.base_currency()), ("ana", "MXN"));
+
+        let dir = std::env::temp_dir().join(format!("system-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bofa_jan.csv");
+        std::fs::write(&path, "Date,Description,Amount\n01/02/2025,STARBUCKS STORE 123,-5.25\n01/02/2025,SHELL OIL,-40.00\n").unwrap();
+        let session = system.import_file(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(session.inserted, 2);
+
+        let transactions = system.transactions().unwrap();
+        let coffee = transactions.iter().find(|tx| tx.merchant == "Starbucks").unwrap();
+        assert_eq!(coffee.category, "Coffee");
+        assert_eq!(system.amount_in_base_currency(coffee).unwrap(), -105.0);
+
+        let matches = system.find_duplicates(&transactions);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].strategy, crate::deduplication::MatchStrategy::Custom("same_day".to_string()));
+
+        assert!(TrustSystem::builder().ledger("nope").build().is_err());
+    }
+
+    #[test]
+    fn test_in_memory_config_leaves_no_file() {
+        let config = AppConfig {
+            db_path: crate::config::IN_MEMORY_DB_PATH.to_string(),
+            base_currency: "EUR".to_string(),
+            ..AppConfig::default()
+        };
+        let mut system = TrustSystem::from_config(&config).unwrap();
+        assert_eq!(system.base_currency(), "EUR");
+        assert_eq!(system.conn().path(), Some(""));
+        assert!(!Path::new(crate::config::IN_MEMORY_DB_PATH).exists());
+
+        let path = std::env::temp_dir().join(format!("system-{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(&path, CSV).unwrap();
+        assert_eq!(system.import_file(&path).unwrap().inserted, 2);
+        std::fs::remove_file(&path).unwrap();
+        assert!(system.bootstrap_entities().unwrap().merchants_added > 0);
+        assert!(system.merchants.find_by_string("Acme").is_some());
+    }
+
+    #[test]
+    fn test_saved_entities_survive_a_restart() {
+        let db_path = std::env::temp_dir().join(format!("system-{}.db", uuid::Uuid::new_v4()));
+        let mut system = TrustSystem::open(&db_path).unwrap();
+        let csv_path = db_path.with_extension("csv");
+        std::fs::write(&csv_path, CSV).unwrap();
+        system.import_file(&csv_path).unwrap();
+        std::fs::remove_file(&csv_path).unwrap();
+        system.bootstrap_entities().unwrap();
+        assert!(system.save_entities().unwrap() > 0);
+        let acme = system.merchants.get_id("Acme").unwrap();
+        let apple_card = system.banks.get_id("AppleCard").unwrap();
+        drop(system);
+
+        let reopened = TrustSystem::open(&db_path).unwrap();
+        assert_eq!(reopened.merchants.get_id("Acme"), Some(acme));
+        assert_eq!(reopened.banks.get_id("AppleCard"), Some(apple_card));
+        drop(reopened);
+        std::fs::remove_file(&db_path).unwrap();
+    }
+
+    #[test]
+    fn test_rename_and_alias_entities() {
+        let mut system = TrustSystem::open_in_memory().unwrap();
+        let id = system.rename_entity(EntityKind::Merchant, "starbucks", "Starbucks Coffee").unwrap();
+        let renamed = system.merchants.find_by_id(&id).unwrap();
+        assert_eq!((renamed.canonical_name.as_str(), renamed.version), ("Starbucks Coffee", 2));
+        assert!(renamed.aliases.contains(&"Starbucks".to_string()));
+        assert!(system.rename_entity(EntityKind::Merchant, "Starbucks Coffee", "Amazon").is_err());
+        assert!(system.rename_entity(EntityKind::Merchant, "Starbux", "Anything").is_err());
+
+        let category = system.rename_entity(EntityKind::Category, "Groceries", "Supermarket").unwrap();
+        assert_eq!(system.categories.get_id("Supermarket"), Some(category));
+
+        let merchant = system.add_merchant_alias("Starbucks Coffee", "SBUX MOBILE").unwrap();
+        assert!(merchant.aliases.contains(&"SBUX MOBILE".to_string()));
+        assert!(system.add_merchant_alias("Amazon", "sbux mobile").is_err());
+
+        // Saved: a restart on the same storage sees the edits
+        let stored = load_merchants(system.conn()).unwrap().find_by_id(&id).unwrap();
+        assert_eq!(stored.all_names().len(), merchant.all_names().len());
+    }
+}
@@ -0,0 +1,349 @@
+// 📦 System Archive - The whole installation as one portable file
+//
+// Problem solved:
+// - A backup meant copying the database file while nothing wrote to it, then
+//   remembering the config, the rules file and whatever else lived next to it
+// - A copy could be restored without anyone noticing it was incomplete or had
+//   been edited on the way
+//
+// `create_archive` writes one tar.zst holding a point-in-time snapshot of the
+// database (VACUUM INTO, so writers don't have to stop), the merchant, bank
+// and account registries as JSON, the config and the rules file it names, and
+// a `manifest.json` with the SHA-256 of every entry and the event-chain head:
+// a running SHA-256 over the event log in write order, with its length and
+// last event. `restore_archive` unpacks into an empty directory, checks every
+// hash and recomputes the head from the restored database, so a truncated or
+// tampered archive is refused instead of restored. The restored config points
+// at the restored files: run with TRUST_CONFIG=<dir>/config.json.
+
+use crate::bootstrap::bootstrap_entities;
+use crate::config::AppConfig;
+use crate::db::{setup_database, SCHEMA_VERSION};
+use crate::snapshot::DatabaseSnapshot;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+/// Bumped when the archive layout changes
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+pub const MANIFEST_ENTRY: &str = "manifest.json";
+pub const DATABASE_ENTRY: &str = "transactions.db";
+pub const CONFIG_ENTRY: &str = "config.json";
+
+/// Where the event log stood when the archive was taken
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventChainHead {
+    pub events: u64,
+    /// `events.id` of the newest event (0 for an empty log)
+    pub last_id: i64,
+    pub last_event_id: Option<String>,
+    /// SHA-256 chained over every event, oldest first
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub format_version: u32,
+    pub created_at: DateTime<Utc>,
+    pub schema_version: i64,
+    pub event_chain: EventChainHead,
+    /// Entry name → SHA-256 of its content (every entry but the manifest)
+    pub files: BTreeMap<String, String>,
+}
+
+/// What `restore_archive` wrote
+#[derive(Debug, Clone)]
+pub struct RestoredArchive {
+    pub manifest: ArchiveManifest,
+    pub database: PathBuf,
+    /// Config pointing at the restored database and rules
+    pub config: PathBuf,
+}
+
+fn sha256_hex(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// The head of the event log of `conn`: each event's stored columns are
+/// hashed together with the hash so far, so any change, removal or
+/// reordering of an event changes the head
+pub fn event_chain_head(conn: &Connection) -> Result<EventChainHead> {
+    let mut stmt = conn.prepare(
+        "SELECT id, event_id, timestamp, event_type, entity_type, entity_id, data, actor, ledger_id
+         FROM events ORDER BY id ASC",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut head = EventChainHead { events: 0, last_id: 0, last_event_id: None, sha256: sha256_hex(b"") };
+    while let Some(row) = rows.next()? {
+        let mut hasher = Sha256::new();
+        hasher.update(head.sha256.as_bytes());
+        for column in 1..9 {
+            hasher.update(b"\x1f");
+            hasher.update(row.get::<_, String>(column)?.as_bytes());
+        }
+        head.events += 1;
+        head.last_id = row.get(0)?;
+        head.last_event_id = Some(row.get(1)?);
+        head.sha256 = format!("{:x}", hasher.finalize());
+    }
+    Ok(head)
+}
+
+// ============================================================================
+// CREATE
+// ============================================================================
+
+/// Archive entries: name and content
+type Entries = Vec<(String, Vec<u8>)>;
+
+/// Entries of the archive of `conn` with `config`, before the manifest
+fn archive_entries(conn: &Connection, config: &AppConfig) -> Result<(Entries, EventChainHead)> {
+    let snapshot = DatabaseSnapshot::take(conn)?;
+    let event_chain = event_chain_head(&snapshot)?;
+    let mut entries = vec![(DATABASE_ENTRY.to_string(), fs::read(snapshot.path())?)];
+
+    let registries = bootstrap_entities(&snapshot)?;
+    let merchants = serde_json::to_vec_pretty(&registries.merchants.all_merchants())?;
+    entries.push(("registries/merchants.json".to_string(), merchants));
+    entries.push(("registries/banks.json".to_string(), serde_json::to_vec_pretty(&registries.banks.all_banks())?));
+    let accounts = serde_json::to_vec_pretty(&registries.accounts.all_accounts())?;
+    entries.push(("registries/accounts.json".to_string(), accounts));
+
+    entries.push((CONFIG_ENTRY.to_string(), serde_json::to_vec_pretty(config)?));
+    if let Some(rules_path) = &config.rules_path {
+        let path = Path::new(rules_path);
+        if path.is_file() {
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("rules.json");
+            entries.push((format!("rules/{}", name), fs::read(path)?));
+        }
+    }
+    Ok((entries, event_chain))
+}
+
+/// Write the archive of the database behind `conn` and `config` to `output`
+/// (tar.zst)
+pub fn create_archive(conn: &Connection, config: &AppConfig, output: &Path) -> Result<ArchiveManifest> {
+    let (entries, event_chain) = archive_entries(conn, config)?;
+    let manifest = ArchiveManifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        created_at: Utc::now(),
+        schema_version: SCHEMA_VERSION,
+        event_chain,
+        files: entries.iter().map(|(name, content)| (name.clone(), sha256_hex(content))).collect(),
+    };
+
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+    let all = std::iter::once((MANIFEST_ENTRY.to_string(), manifest_json)).chain(entries);
+    write_tar_zst(output, all, manifest.created_at)?;
+    Ok(manifest)
+}
+
+/// Write `entries` (manifest first) as a zstd-compressed tar
+fn write_tar_zst(
+    output: &Path,
+    entries: impl IntoIterator<Item = (String, Vec<u8>)>,
+    mtime: DateTime<Utc>,
+) -> Result<()> {
+    let file = File::create(output).with_context(|| format!("Failed to create {}", output.display()))?;
+    let mut tar = tar::Builder::new(zstd::Encoder::new(file, 0)?.auto_finish());
+    for (name, content) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o600);
+        header.set_mtime(mtime.timestamp().max(0) as u64);
+        tar.append_data(&mut header, &name, content.as_slice())?;
+    }
+    tar.into_inner()?;
+    Ok(())
+}
+
+// ============================================================================
+// RESTORE
+// ============================================================================
+
+/// Every entry of `archive`, by name (names that could leave the target
+/// directory are refused)
+fn read_entries(archive: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let file = File::open(archive).with_context(|| format!("Failed to open {}", archive.display()))?;
+    let mut tar = tar::Archive::new(zstd::Decoder::new(file)?);
+    let mut entries = BTreeMap::new();
+    for entry in tar.entries().context("Not a system archive (tar.zst)")? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if !path.components().all(|component| matches!(component, Component::Normal(_))) {
+            return Err(anyhow!("Archive entry {} points outside the archive", path.display()));
+        }
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+        entries.insert(path.to_string_lossy().replace('\\', "/"), content);
+    }
+    Ok(entries)
+}
+
+/// The manifest of `archive`, without restoring it
+pub fn read_manifest(archive: &Path) -> Result<ArchiveManifest> {
+    let entries = read_entries(archive)?;
+    let manifest = entries.get(MANIFEST_ENTRY).ok_or_else(|| anyhow!("Archive has no {}", MANIFEST_ENTRY))?;
+    Ok(serde_json::from_slice(manifest)?)
+}
+
+/// Unpack `archive` into `dir` (missing or empty), checking every entry's
+/// hash and the event-chain head before anything is written
+pub fn restore_archive(archive: &Path, dir: &Path) -> Result<RestoredArchive> {
+    if dir.exists() && fs::read_dir(dir)?.next().is_some() {
+        return Err(anyhow!("{} is not empty: restore into a new directory", dir.display()));
+    }
+    let mut entries = read_entries(archive)?;
+    let manifest: ArchiveManifest = serde_json::from_slice(
+        &entries.remove(MANIFEST_ENTRY).ok_or_else(|| anyhow!("Archive has no {}", MANIFEST_ENTRY))?,
+    )?;
+    if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(anyhow!(
+            "Archive format {} is newer than this version reads ({})",
+            manifest.format_version,
+            ARCHIVE_FORMAT_VERSION
+        ));
+    }
+    for (name, expected) in &manifest.files {
+        let content = entries.get(name).ok_or_else(|| anyhow!("Archive is missing {}", name))?;
+        if &sha256_hex(content) != expected {
+            return Err(anyhow!("{} does not match its checksum: the archive is damaged or was edited", name));
+        }
+    }
+    if let Some(extra) = entries.keys().find(|name| !manifest.files.contains_key(*name)) {
+        return Err(anyhow!("Archive entry {} is not in its manifest", extra));
+    }
+
+    // The database first, in a scratch file: only a matching event log is kept
+    fs::create_dir_all(dir)?;
+    let database = dir.join(DATABASE_ENTRY);
+    let scratch = dir.join(format!("{}.restoring", DATABASE_ENTRY));
+    fs::write(&scratch, &entries[DATABASE_ENTRY])?;
+    let head = Connection::open(&scratch).map_err(anyhow::Error::from).and_then(|conn| event_chain_head(&conn));
+    match head {
+        Ok(head) if head == manifest.event_chain => fs::rename(&scratch, &database)?,
+        outcome => {
+            let _ = fs::remove_file(&scratch);
+            return Err(match outcome {
+                Ok(head) => anyhow!(
+                    "Restored event log ends at event {} ({} events), the archive recorded {} ({} events)",
+                    head.last_id,
+                    head.events,
+                    manifest.event_chain.last_id,
+                    manifest.event_chain.events
+                ),
+                Err(e) => e.context("Archived database is unreadable"),
+            });
+        }
+    }
+    setup_database(&Connection::open(&database)?)?;
+
+    let mut config: AppConfig = match entries.get(CONFIG_ENTRY) {
+        Some(content) => serde_json::from_slice(content)?,
+        None => AppConfig::default(),
+    };
+    config.db_path = database.to_string_lossy().to_string();
+    for (name, content) in &entries {
+        if name == DATABASE_ENTRY || name == CONFIG_ENTRY {
+            continue;
+        }
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, content)?;
+        if name.starts_with("rules/") {
+            config.rules_path = Some(path.to_string_lossy().to_string());
+        }
+    }
+    let config_path = dir.join(CONFIG_ENTRY);
+    config.save_to(&config_path)?;
+
+    Ok(RestoredArchive { manifest, database, config: config_path })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::get_all_transactions;
+    use crate::imports::import_statement;
+
+    const BOFA: &str = "Date,Description,Amount\n\
+        01/02/2025,STARBUCKS STORE 123,-5.25\n\
+        01/03/2025,SHELL OIL 5521,-40.00\n";
+
+    fn archived() -> (Connection, PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("system-archive-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let rules = dir.join("merchants.json");
+        fs::write(&rules, "[]").unwrap();
+
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        import_statement(&conn, "bofa_jan.csv", BOFA.as_bytes(), "default", "ana").unwrap();
+        let config = AppConfig { rules_path: Some(rules.to_string_lossy().to_string()), ..AppConfig::default() };
+        let archive = dir.join("backup.tar.zst");
+        create_archive(&conn, &config, &archive).unwrap();
+        (conn, dir, archive)
+    }
+
+    #[test]
+    fn test_create_and_restore_round_trip() {
+        let (conn, dir, archive) = archived();
+        let manifest = read_manifest(&archive).unwrap();
+        assert_eq!(manifest.event_chain, event_chain_head(&conn).unwrap());
+        assert!(manifest.event_chain.events > 0);
+        assert!(manifest.files.contains_key("registries/merchants.json"));
+
+        let restored = restore_archive(&archive, &dir.join("restored")).unwrap();
+        let restored_db = Connection::open(&restored.database).unwrap();
+        assert_eq!(get_all_transactions(&restored_db).unwrap().len(), 2);
+        let config = AppConfig::load_from(&restored.config).unwrap();
+        assert_eq!(Path::new(&config.db_path), restored.database);
+        assert_eq!(fs::read_to_string(config.rules_path.unwrap()).unwrap(), "[]");
+
+        // Restoring never overwrites
+        assert!(restore_archive(&archive, &dir.join("restored")).unwrap_err().to_string().contains("not empty"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_edited_archives_are_refused() {
+        let (_conn, dir, archive) = archived();
+        let mut entries = read_entries(&archive).unwrap();
+        let mut manifest: ArchiveManifest = serde_json::from_slice(&entries[MANIFEST_ENTRY]).unwrap();
+
+        // A database with an event removed, re-hashed so the checksums agree
+        let edited = dir.join("edited.db");
+        fs::write(&edited, &entries[DATABASE_ENTRY]).unwrap();
+        let removed = "DELETE FROM events WHERE id = (SELECT MAX(id) FROM events)";
+        Connection::open(&edited).unwrap().execute(removed, []).unwrap();
+        let content = fs::read(&edited).unwrap();
+        manifest.files.insert(DATABASE_ENTRY.to_string(), sha256_hex(&content));
+        entries.insert(DATABASE_ENTRY.to_string(), content);
+        entries.insert(MANIFEST_ENTRY.to_string(), serde_json::to_vec(&manifest).unwrap());
+
+        let forged = dir.join("forged.tar.zst");
+        write_tar_zst(&forged, entries.clone(), Utc::now()).unwrap();
+        let error = restore_archive(&forged, &dir.join("forged")).unwrap_err().to_string();
+        assert!(error.contains("event log"), "{}", error);
+        assert!(!dir.join("forged").join(DATABASE_ENTRY).exists());
+
+        // A changed byte without a new checksum
+        entries.insert(CONFIG_ENTRY.to_string(), b"{}".to_vec());
+        write_tar_zst(&forged, entries, Utc::now()).unwrap();
+        assert!(restore_archive(&forged, &dir.join("forged2")).unwrap_err().to_string().contains("checksum"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,418 @@
+// 🔁 Transfers - Both legs of a transfer, written together
+//
+// Problem solved:
+// - A transfer between two of the user's accounts is two rows (money out of
+//   one, into the other), but they were only ever written one at a time, so a
+//   failure halfway left the ledger out of balance
+// - When only one side's statement was imported, the transfer-matching
+//   engine (DeduplicationEngine's transfer pairs) found a TRASPASO without its
+//   pair, and nothing could fill in the other side
+//
+// `create_transfer` writes both legs (TRASPASO, opposite amounts, same date)
+// in one SQLite transaction, linked by a shared `transfer_id`; they are manual
+// entries (manual.rs), so the actor and reason are on each row.
+// `unmatched_transfer_legs` lists imported legs the engine could not pair, and
+// `complete_transfer` writes the missing leg of one and links both the same
+// way. Legs that were both imported but differ by FX slippage (a Wise amount
+// converted at a slightly different rate than the bank's) are paired by the
+// reconciliation engine; `record_fx_slippage` links them and moves the
+// difference into an "FX Slippage" adjustment.
+
+use crate::accounts::OPENING_BALANCE_FLAG;
+use crate::db::{insert_event, insert_transaction_as, insert_transaction_version, Event, Transaction};
+use crate::deduplication::{DeduplicationEngine, MatchStrategy};
+use crate::event_schema::TransferCreated;
+use crate::manual::{manual_transaction, store_manual_transaction, ManualEntry, ManualKind};
+use crate::reconciliation::FxMatch;
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Metadata key linking the two legs of a transfer
+pub const TRANSFER_ID_KEY: &str = "transfer_id";
+
+/// Category of the adjustment that carries a transfer's FX slippage
+pub const FX_SLIPPAGE_CATEGORY: &str = "FX Slippage";
+
+/// One side of a transfer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferAccount {
+    pub bank: String,
+    pub account_name: String,
+}
+
+impl TransferAccount {
+    pub fn new(bank: &str, account_name: &str) -> Self {
+        TransferAccount { bank: bank.trim().to_string(), account_name: account_name.trim().to_string() }
+    }
+
+    /// "BofA:Checking"
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            Some((bank, account)) if !bank.trim().is_empty() && !account.trim().is_empty() => {
+                Ok(TransferAccount::new(bank, account))
+            }
+            _ => Err(anyhow!("Account must look like <bank>:<account>, got '{}'", s)),
+        }
+    }
+
+    /// The account `tx` was booked on
+    pub fn of(tx: &Transaction) -> Self {
+        TransferAccount::new(&tx.bank, &tx.account_name)
+    }
+
+    pub fn label(&self) -> String {
+        format!("{} {}", self.bank, self.account_name)
+    }
+}
+
+/// Both legs, linked by `transfer_id`
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub transfer_id: String,
+    /// Money leaving `from` (negative)
+    pub outgoing: Transaction,
+    /// Money arriving in `to` (positive)
+    pub incoming: Transaction,
+}
+
+/// The transfer `tx` is a leg of, if it was linked
+pub fn transfer_id(tx: &Transaction) -> Option<&str> {
+    tx.get_metadata(TRANSFER_ID_KEY).and_then(|v| v.as_str())
+}
+
+// ============================================================================
+// CREATE
+// ============================================================================
+
+/// Move `amount` (positive) from one account to another on `date`
+#[allow(clippy::too_many_arguments)]
+pub fn create_transfer(
+    conn: &Connection,
+    ledger_id: &str,
+    from: &TransferAccount,
+    to: &TransferAccount,
+    amount: f64,
+    date: NaiveDate,
+    actor: &str,
+    reason: &str,
+) -> Result<Transfer> {
+    if !amount.is_finite() || amount <= 0.0 {
+        return Err(anyhow!("A transfer needs a positive amount"));
+    }
+    if from == to {
+        return Err(anyhow!("Cannot transfer from {} to itself", from.label()));
+    }
+
+    let transfer_id = uuid::Uuid::new_v4().to_string();
+    let leg = |account: &TransferAccount, amount: f64, description: String| -> Result<Transaction> {
+        let entry = ManualEntry::transfer_leg(date, amount, &description)
+            .on_account(&account.bank, &account.account_name);
+        let mut tx = manual_transaction(conn, ledger_id, &entry, actor, reason)?;
+        tx.metadata.insert(TRANSFER_ID_KEY.to_string(), serde_json::json!(transfer_id));
+        Ok(tx)
+    };
+    let outgoing = leg(from, -amount, format!("Transfer to {}", to.label()))?;
+    let incoming = leg(to, amount, format!("Transfer from {}", from.label()))?;
+
+    let db_tx = conn.unchecked_transaction()?;
+    for tx in [&outgoing, &incoming] {
+        store_manual_transaction(conn, tx, ManualKind::Transfer, actor, reason)?;
+    }
+    let payload = TransferCreated {
+        amount,
+        date,
+        from: from.label(),
+        to: to.label(),
+        legs: vec![outgoing.id.clone(), incoming.id.clone()],
+        imported_leg: None,
+        fx_slippage: None,
+        fx_adjustment: None,
+    };
+    let event = Event::typed("transfer_created", "transfer", &transfer_id, &payload, actor)?.with_ledger(ledger_id);
+    insert_event(conn, &event)?;
+    db_tx.commit()?;
+
+    Ok(Transfer { transfer_id, outgoing, incoming })
+}
+
+// ============================================================================
+// ONE-SIDED TRANSFERS
+// ============================================================================
+
+/// Active TRASPASO rows that are not linked to a transfer and that the
+/// engine pairs with no other row (the other side was never imported)
+pub fn unmatched_transfer_legs(engine: &DeduplicationEngine, transactions: &[Transaction]) -> Vec<Transaction> {
+    let candidates: Vec<Transaction> = transactions
+        .iter()
+        .filter(|tx| tx.is_active() && tx.transaction_type == "TRASPASO")
+        .filter(|tx| transfer_id(tx).is_none() && !tx.has_metadata(OPENING_BALANCE_FLAG))
+        .cloned()
+        .collect();
+    let paired: HashSet<usize> = engine
+        .find_duplicates(&candidates)
+        .into_iter()
+        .filter(|m| m.strategy == MatchStrategy::TransferPair)
+        .flat_map(|m| [m.tx1_index, m.tx2_index])
+        .collect();
+    candidates
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| !paired.contains(index))
+        .map(|(_, tx)| tx)
+        .collect()
+}
+
+/// Write the missing other side of the imported leg `leg` on `counterpart`,
+/// and link both (the imported leg gets a new version carrying the link)
+pub fn complete_transfer(
+    conn: &Connection,
+    leg: &Transaction,
+    counterpart: &TransferAccount,
+    actor: &str,
+) -> Result<Transfer> {
+    if leg.transaction_type != "TRASPASO" || !leg.is_active() {
+        return Err(anyhow!("Transaction {} is not an active transfer", leg.id));
+    }
+    if let Some(existing) = transfer_id(leg) {
+        return Err(anyhow!("Transaction {} is already part of transfer {}", leg.id, existing));
+    }
+    let own = TransferAccount::of(leg);
+    if own == *counterpart {
+        return Err(anyhow!("Cannot transfer from {} to itself", own.label()));
+    }
+
+    let transfer_id = uuid::Uuid::new_v4().to_string();
+    let description = if leg.amount_numeric < 0.0 {
+        format!("Transfer from {}", own.label())
+    } else {
+        format!("Transfer to {}", own.label())
+    };
+    let mut metadata = HashMap::new();
+    metadata.insert(TRANSFER_ID_KEY.to_string(), serde_json::json!(transfer_id));
+    metadata.insert("transfer_counterpart".to_string(), serde_json::json!(leg.id));
+    let mut missing = Transaction {
+        date: leg.date.clone(),
+        description: description.clone(),
+        amount_original: format!("{:.2}", -leg.amount_numeric),
+        amount_numeric: -leg.amount_numeric,
+        transaction_type: "TRASPASO".to_string(),
+        category: "Transfer".to_string(),
+        merchant: description,
+        currency: leg.currency.clone(),
+        account_name: counterpart.account_name.clone(),
+        account_number: String::new(),
+        bank: counterpart.bank.clone(),
+        source_file: "transfer".to_string(),
+        line_number: "0".to_string(),
+        classification_notes: format!("Missing leg of transfer {}, written by {}", leg.id, actor),
+        id: String::new(),
+        version: 0,
+        system_time: None,
+        valid_from: None,
+        valid_until: None,
+        previous_version_id: None,
+        ledger_id: leg.ledger_id.clone(),
+        metadata,
+    };
+    missing.init_temporal_fields();
+
+    let mut linked = leg.next_version(Some(format!("Linked to transfer {}", transfer_id)));
+    linked.metadata.insert(TRANSFER_ID_KEY.to_string(), serde_json::json!(transfer_id));
+
+    let db_tx = conn.unchecked_transaction()?;
+    if !insert_transaction_as(conn, &missing, actor)? {
+        return Err(anyhow!("The other leg of {} is already in the ledger", leg.id));
+    }
+    insert_transaction_version(conn, &linked, actor)?;
+    let (outgoing, incoming) = if linked.amount_numeric < 0.0 { (linked, missing) } else { (missing, linked) };
+    let payload = TransferCreated {
+        amount: incoming.amount_numeric,
+        date: incoming.parsed_date().ok_or_else(|| anyhow!("Transaction {} has no valid date", leg.id))?,
+        from: TransferAccount::of(&outgoing).label(),
+        to: TransferAccount::of(&incoming).label(),
+        legs: vec![outgoing.id.clone(), incoming.id.clone()],
+        imported_leg: Some(leg.id.clone()),
+        fx_slippage: None,
+        fx_adjustment: None,
+    };
+    let event =
+        Event::typed("transfer_created", "transfer", &transfer_id, &payload, actor)?.with_ledger(&leg.ledger_id);
+    insert_event(conn, &event)?;
+    db_tx.commit()?;
+
+    Ok(Transfer { transfer_id, outgoing, incoming })
+}
+
+// ============================================================================
+// FX SLIPPAGE
+// ============================================================================
+
+/// Link the legs of `fx` as one transfer: the converted leg gets a version
+/// with exactly the other side's amount, and the slippage becomes an
+/// adjustment on the converted leg's account (category FX Slippage), so the
+/// account's total doesn't change and the transfer nets to zero
+pub fn record_fx_slippage(conn: &Connection, fx: &FxMatch, actor: &str) -> Result<Transfer> {
+    for leg in [&fx.converted, &fx.counterpart] {
+        if let Some(existing) = transfer_id(leg) {
+            return Err(anyhow!("Transaction {} is already part of transfer {}", leg.id, existing));
+        }
+    }
+    let transfer_id = uuid::Uuid::new_v4().to_string();
+    let date = fx
+        .converted
+        .parsed_date()
+        .ok_or_else(|| anyhow!("Transaction {} has no valid date", fx.converted.id))?;
+
+    let reason = format!("FX slippage {:.2} moved to an adjustment", fx.slippage);
+    let mut converted = fx.converted.next_version(Some(reason));
+    converted.amount_numeric = -fx.counterpart.amount_numeric;
+    converted.amount_original = format!("{:.2}", converted.amount_numeric);
+    converted.metadata.insert(TRANSFER_ID_KEY.to_string(), serde_json::json!(transfer_id));
+    let mut counterpart = fx.counterpart.next_version(Some(format!("Linked to transfer {}", transfer_id)));
+    counterpart.metadata.insert(TRANSFER_ID_KEY.to_string(), serde_json::json!(transfer_id));
+
+    let adjustment = if fx.slippage.abs() >= 0.005 {
+        let entry = ManualEntry::adjustment(date, fx.slippage, &format!("FX slippage on {}", fx.converted.description))
+            .on_account(&fx.converted.bank, &fx.converted.account_name)
+            .with_category(FX_SLIPPAGE_CATEGORY)
+            .with_currency(&fx.converted.currency);
+        let reason = format!("Rate difference against {} {}", fx.counterpart.bank, fx.counterpart.account_name);
+        let mut tx = manual_transaction(conn, &converted.ledger_id, &entry, actor, &reason)?;
+        tx.metadata.insert(TRANSFER_ID_KEY.to_string(), serde_json::json!(transfer_id));
+        Some((tx, reason))
+    } else {
+        None
+    };
+
+    let db_tx = conn.unchecked_transaction()?;
+    insert_transaction_version(conn, &converted, actor)?;
+    insert_transaction_version(conn, &counterpart, actor)?;
+    if let Some((tx, reason)) = &adjustment {
+        store_manual_transaction(conn, tx, ManualKind::Adjustment, actor, reason)?;
+    }
+    let (outgoing, incoming) =
+        if converted.amount_numeric < 0.0 { (converted, counterpart) } else { (counterpart, converted) };
+    let payload = TransferCreated {
+        amount: incoming.amount_numeric,
+        date,
+        from: TransferAccount::of(&outgoing).label(),
+        to: TransferAccount::of(&incoming).label(),
+        legs: vec![outgoing.id.clone(), incoming.id.clone()],
+        imported_leg: None,
+        fx_slippage: Some(fx.slippage),
+        fx_adjustment: adjustment.as_ref().map(|(tx, _)| tx.id.clone()),
+    };
+    let event =
+        Event::typed("transfer_created", "transfer", &transfer_id, &payload, actor)?.with_ledger(&outgoing.ledger_id);
+    insert_event(conn, &event)?;
+    db_tx.commit()?;
+
+    Ok(Transfer { transfer_id, outgoing, incoming })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{get_active_transactions, get_events_for_entity, setup_database};
+    use crate::manual::is_manual;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_create_transfer_writes_linked_legs() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        let checking = TransferAccount::new("BofA", "Checking");
+        let savings = TransferAccount::parse("Wise:Savings").unwrap();
+
+        let transfer =
+            create_transfer(&conn, "default", &checking, &savings, 500.0, date("2025-03-25"), "ana", "saving").unwrap();
+        assert_eq!((transfer.outgoing.amount_numeric, transfer.incoming.amount_numeric), (-500.0, 500.0));
+        assert_eq!(TransferAccount::of(&transfer.incoming), savings);
+        for leg in [&transfer.outgoing, &transfer.incoming] {
+            assert_eq!(leg.transaction_type, "TRASPASO");
+            assert_eq!(transfer_id(leg), Some(transfer.transfer_id.as_str()));
+            assert!(is_manual(leg));
+        }
+        assert_eq!(get_active_transactions(&conn).unwrap().len(), 2);
+        let events = get_events_for_entity(&conn, "transfer", &transfer.transfer_id).unwrap();
+        assert_eq!(events[0].event_type, "transfer_created");
+
+        // Nothing is written when a leg is invalid
+        assert!(create_transfer(&conn, "default", &checking, &checking, 5.0, date("2025-03-25"), "ana", "x").is_err());
+        assert!(create_transfer(&conn, "nope", &checking, &savings, 5.0, date("2025-03-25"), "ana", "x").is_err());
+        assert_eq!(get_active_transactions(&conn).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_complete_one_sided_transfer() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        // Only the checking statement was imported
+        let entry = ManualEntry::transfer_leg(date("2025-03-25"), -500.0, "Wise Us Inc, Des:transfer")
+            .on_account("BofA", "Checking");
+        let mut imported = manual_transaction(&conn, "default", &entry, "ana", "import").unwrap();
+        imported.metadata.clear();
+        imported.source_file = "bofa.csv".to_string();
+        assert!(insert_transaction_as(&conn, &imported, "ana").unwrap());
+
+        let engine = DeduplicationEngine::new();
+        let unmatched = unmatched_transfer_legs(&engine, &get_active_transactions(&conn).unwrap());
+        assert_eq!(unmatched.len(), 1);
+
+        let savings = TransferAccount::new("Wise", "Savings");
+        let transfer = complete_transfer(&conn, &unmatched[0], &savings, "ana").unwrap();
+        assert_eq!(transfer.outgoing.id, imported.id);
+        assert_eq!(transfer.outgoing.version, 2);
+        assert_eq!((transfer.incoming.bank.as_str(), transfer.incoming.amount_numeric), ("Wise", 500.0));
+
+        let active = get_active_transactions(&conn).unwrap();
+        assert_eq!(active.len(), 2);
+        assert!(active.iter().all(|tx| transfer_id(tx) == Some(transfer.transfer_id.as_str())));
+        assert!(unmatched_transfer_legs(&engine, &active).is_empty());
+        assert!(complete_transfer(&conn, &transfer.outgoing, &savings, "ana").is_err());
+    }
+
+    #[test]
+    fn test_record_fx_slippage_links_legs_and_adjusts() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+
+        let imported = |bank: &str, amount: f64, fx: bool| {
+            let entry = ManualEntry::transfer_leg(date("2025-01-10"), amount, "Transfer").on_account(bank, "Main");
+            let mut tx = manual_transaction(&conn, "default", &entry, "ana", "import").unwrap();
+            tx.metadata.clear();
+            if fx {
+                tx.metadata.insert("fx_rate".to_string(), serde_json::json!(18.15));
+                tx.metadata.insert("original_currency".to_string(), serde_json::json!("MXN"));
+            }
+            tx.source_file = format!("{}.csv", bank);
+            assert!(insert_transaction_as(&conn, &tx, "ana").unwrap());
+        };
+        imported("Wise", -55.10, true);
+        imported("BofA", 55.0, false);
+
+        let active = get_active_transactions(&conn).unwrap();
+        let fx = crate::reconciliation::ReconciliationEngine::new().match_fx_converted(&active).remove(0);
+        let transfer = record_fx_slippage(&conn, &fx, "ana").unwrap();
+        assert_eq!((transfer.outgoing.amount_numeric, transfer.incoming.amount_numeric), (-55.0, 55.0));
+
+        let active = get_active_transactions(&conn).unwrap();
+        let wise_total: f64 = active.iter().filter(|tx| tx.bank == "Wise").map(|tx| tx.amount_numeric).sum();
+        assert!((wise_total + 55.10).abs() < 1e-9);
+        let adjustment = active.iter().find(|tx| tx.category == FX_SLIPPAGE_CATEGORY).unwrap();
+        assert_eq!((adjustment.transaction_type.as_str(), adjustment.bank.as_str()), ("GASTO", "Wise"));
+        assert!(active.iter().all(|tx| transfer_id(tx) == Some(transfer.transfer_id.as_str())));
+        assert!(record_fx_slippage(&conn, &fx, "ana").is_err());
+    }
+}
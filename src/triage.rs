@@ -0,0 +1,254 @@
+// 🗂️ Triage - Ranked category suggestions for uncategorized transactions
+//
+// Problem solved:
+// - Clearing a backlog of "Unknown" transactions meant typing a category for
+//   each one, even when the same merchant had been categorized a dozen times
+// - CategoryInferrer (parser.rs) had no implementation
+//
+// `CategorySuggester` ranks categories for one transaction from three signals:
+// the rule engine (rules and MCC), past categories of the same merchant, and
+// words the description shares with categorized transactions. The TUI's triage
+// mode offers the top three; picking one writes a normal versioned correction.
+
+use crate::approvals::{submit_correction, WriteOutcome};
+use crate::db::Transaction;
+use crate::parser::CategoryInferrer;
+use crate::rules::RuleEngine;
+use anyhow::{anyhow, Result};
+use rusqlite::Connection;
+use std::collections::{BTreeSet, HashMap};
+
+/// Categories that mean "nobody has categorized this yet"
+const UNCATEGORIZED: [&str; 4] = ["", "unknown", "uncategorized", "other"];
+
+/// Signal weights: a rule match beats all history (merchant + words), and
+/// merchant history beats shared words
+const RULE_WEIGHT: f64 = 4.0;
+const MERCHANT_WEIGHT: f64 = 2.0;
+const WORD_WEIGHT: f64 = 1.0;
+
+pub fn needs_triage(tx: &Transaction) -> bool {
+    tx.is_active() && UNCATEGORIZED.contains(&tx.category.trim().to_lowercase().as_str())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategorySuggestion {
+    pub category: String,
+    /// Share of the total evidence (0.0 - 1.0)
+    pub score: f64,
+    /// Strongest signal behind it, e.g. "3× for this merchant"
+    pub reason: String,
+}
+
+pub struct CategorySuggester {
+    rules: RuleEngine,
+    /// merchant (lowercase) → category → count
+    by_merchant: HashMap<String, HashMap<String, usize>>,
+    /// description word → category → count
+    by_word: HashMap<String, HashMap<String, usize>>,
+    categories: BTreeSet<String>,
+}
+
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() >= 3 && !word.chars().all(|c| c.is_ascii_digit()))
+        .map(|word| word.to_lowercase())
+}
+
+impl CategorySuggester {
+    /// Learn from the categorized transactions in `history`
+    pub fn from_history(rules: RuleEngine, history: &[Transaction]) -> Self {
+        let mut suggester = CategorySuggester {
+            rules,
+            by_merchant: HashMap::new(),
+            by_word: HashMap::new(),
+            categories: BTreeSet::new(),
+        };
+
+        for tx in history.iter().filter(|tx| tx.is_active() && !needs_triage(tx)) {
+            suggester.categories.insert(tx.category.clone());
+            if !tx.merchant.is_empty() {
+                *suggester
+                    .by_merchant
+                    .entry(tx.merchant.to_lowercase())
+                    .or_default()
+                    .entry(tx.category.clone())
+                    .or_default() += 1;
+            }
+            for word in words(&tx.description).collect::<BTreeSet<_>>() {
+                *suggester.by_word.entry(word).or_default().entry(tx.category.clone()).or_default() += 1;
+            }
+        }
+        suggester
+    }
+
+    /// Every category seen in history, sorted (search fallback)
+    pub fn categories(&self) -> Vec<String> {
+        self.categories.iter().cloned().collect()
+    }
+
+    /// Categories containing `query` (case-insensitive)
+    pub fn search(&self, query: &str) -> Vec<String> {
+        let query = query.trim().to_lowercase();
+        self.categories
+            .iter()
+            .filter(|category| category.to_lowercase().contains(&query))
+            .cloned()
+            .collect()
+    }
+
+    /// Up to `limit` categories, best first
+    pub fn suggest(&self, tx: &Transaction, limit: usize) -> Vec<CategorySuggestion> {
+        let mut scores: HashMap<String, (f64, f64, String)> = HashMap::new();
+        let mut add = |category: &str, points: f64, reason: String| {
+            if UNCATEGORIZED.contains(&category.trim().to_lowercase().as_str()) {
+                return;
+            }
+            let entry = scores.entry(category.to_string()).or_insert((0.0, 0.0, String::new()));
+            entry.0 += points;
+            // Keep the reason of the strongest single signal
+            if points > entry.1 {
+                entry.1 = points;
+                entry.2 = reason;
+            }
+        };
+
+        let classified = self.rules.classify_transaction(tx);
+        if let Some(category) = &classified.category {
+            let source = classified.rule_id.as_deref().map_or("MCC".to_string(), |id| format!("rule {}", id));
+            add(category, RULE_WEIGHT, source);
+        }
+
+        if let Some(counts) = self.by_merchant.get(&tx.merchant.to_lowercase()) {
+            let total: usize = counts.values().sum();
+            for (category, count) in counts {
+                add(
+                    category,
+                    MERCHANT_WEIGHT * *count as f64 / total as f64,
+                    format!("{}× for {}", count, tx.merchant),
+                );
+            }
+        }
+
+        for word in words(&tx.description).collect::<BTreeSet<_>>() {
+            let Some(counts) = self.by_word.get(&word) else {
+                continue;
+            };
+            let total: usize = counts.values().sum();
+            for (category, count) in counts {
+                add(
+                    category,
+                    WORD_WEIGHT * *count as f64 / total as f64,
+                    format!("shares \"{}\"", word),
+                );
+            }
+        }
+
+        let evidence: f64 = scores.values().map(|(points, _, _)| points).sum();
+        let mut suggestions: Vec<CategorySuggestion> = scores
+            .into_iter()
+            .map(|(category, (points, _, reason))| CategorySuggestion {
+                category,
+                score: points / evidence,
+                reason,
+            })
+            .collect();
+        suggestions.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.category.cmp(&b.category)));
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
+
+impl CategoryInferrer for CategorySuggester {
+    fn infer_category(&self, merchant: &str, _amount: f64) -> Option<String> {
+        let mut best: Option<(usize, &String)> = None;
+        for (category, count) in self.by_merchant.get(&merchant.to_lowercase())? {
+            if best.is_none_or(|(most, _)| *count > most) {
+                best = Some((*count, category));
+            }
+        }
+        best.map(|(_, category)| category.clone())
+    }
+}
+
+/// Write the chosen category as a correction (or propose it for approval)
+pub fn categorize(conn: &Connection, tx: &Transaction, category: &str, actor: &str) -> Result<WriteOutcome> {
+    if category.trim().is_empty() {
+        return Err(anyhow!("Category must not be empty"));
+    }
+    let mut next = tx.next_version(Some(format!("triage: {}", category)));
+    next.category = category.trim().to_string();
+    submit_correction(conn, &next, actor)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use crate::rules::ClassificationRule;
+
+    fn tx(description: &str, merchant: &str, category: &str) -> Transaction {
+        let mut tx = Transaction {
+            date: "01/05/2025".to_string(),
+            description: description.to_string(),
+            amount_original: "-9.00".to_string(),
+            amount_numeric: -9.0,
+            category: category.to_string(),
+            merchant: merchant.to_string(),
+            account_name: "Apple Card".to_string(),
+            bank: "AppleCard".to_string(),
+            source_file: "applecard.csv".to_string(),
+            line_number: "2".to_string(),
+            id: uuid::Uuid::new_v4().to_string(),
+            ..test_support::sample_transaction()
+        };
+        tx.init_temporal_fields();
+        tx
+    }
+
+    #[test]
+    fn test_suggestions_rank_merchant_history_and_shared_words() {
+        let history = vec![
+            tx("BLUE BOTTLE COFFEE SF", "Blue Bottle", "Coffee"),
+            tx("BLUE BOTTLE COFFEE OAK", "Blue Bottle", "Coffee"),
+            tx("BLUE BOTTLE BEANS", "Blue Bottle", "Groceries"),
+            tx("SAFEWAY STORE", "Safeway", "Groceries"),
+            tx("SOMETHING", "Mystery", "Unknown"),
+        ];
+        let suggester = CategorySuggester::from_history(RuleEngine::new(), &history);
+
+        let suggestions = suggester.suggest(&tx("BLUE BOTTLE COFFEE NYC", "Blue Bottle", "Unknown"), 3);
+        assert_eq!(suggestions[0].category, "Coffee");
+        assert_eq!(suggestions[1].category, "Groceries");
+        assert!(suggestions[0].score > suggestions[1].score);
+        assert_eq!(suggester.categories(), vec!["Coffee", "Groceries"]);
+        assert_eq!(suggester.search("gro"), vec!["Groceries"]);
+        assert_eq!(suggester.infer_category("blue bottle", -9.0), Some("Coffee".to_string()));
+    }
+
+    #[test]
+    fn test_rule_match_outranks_history() {
+        let rule = ClassificationRule {
+            id: "uber".to_string(),
+            pattern: "UBER TRIP".to_string(),
+            merchant: Some("Uber".to_string()),
+            category: Some("Transport".to_string()),
+            transaction_type: None,
+            confidence: 0.9,
+            description: None,
+            priority: 0,
+        };
+        let history = vec![tx("UBER EATS", "Uber", "Restaurants")];
+        let suggester = CategorySuggester::from_history(RuleEngine::from_rules(vec![rule]), &history);
+
+        let pending = tx("UBER TRIP", "Uber", "");
+        assert!(needs_triage(&pending));
+        let suggestions = suggester.suggest(&pending, 3);
+        assert_eq!(suggestions[0].category, "Transport");
+        assert!(suggestions[0].reason.contains("rule uber"));
+    }
+}
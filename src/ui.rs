@@ -1,26 +1,122 @@
-use crate::db::Transaction;
+use trust_construction::db::Transaction;
+use trust_construction::notes::{thread_notes, Note};
+use trust_construction::provenance::{self, ProvenanceTrace};
+use trust_construction::bills::{BillOccurrence, BillStatus};
+use trust_construction::duplicates::{merge_cluster, record_decision, DuplicateCluster, DuplicateDecision};
+use trust_construction::jobs::{
+    import_csv_job, ledger_transactions, scan_duplicates_job, CsvImportSummary, Job, JobOutcome,
+};
+use trust_construction::{totals_by_bank, totals_by_type, verify_count};
+use trust_construction::source_stats::{compute_source_stats, source_stats, SourceStats};
+use trust_construction::config::AppConfig;
+use trust_construction::layout::{LedgerColumn, LedgerLayout};
+use trust_construction::locale::Locale;
+use trust_construction::bulk::{apply_bulk_action, BulkAction};
+use trust_construction::triage::{categorize, needs_triage, CategorySuggester, CategorySuggestion};
+use trust_construction::{submit_redo, submit_undo, RuleEngine, WriteOutcome};
+use trust_construction::analytics::{profile_merchant, MerchantProfile};
+use trust_construction::envelopes::EnvelopeMonth;
+use trust_construction::entities::{shared_registry, Merchant, MerchantType};
+use trust_construction::manual::{create_manual_transaction, ManualEntry};
 use anyhow::Result;
 use crossterm::{
     event::{self, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use rusqlite::Connection;
 use ratatui::{
-    backend::CrosstermBackend,
+    backend::{CrosstermBackend, TestBackend},
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+    widgets::{
+        Bar, BarChart, BarGroup, Block, Borders, Cell, Clear, Gauge, Paragraph, Row, Sparkline, Table,
+        TableState,
+    },
     Frame, Terminal,
 };
+use std::cell::OnceCell;
 use std::io;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use chrono::Datelike;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Page {
     BankStatements,
     TransactionLedger,
     Views,
+    Duplicates,
+    Sources,
+}
+
+/// Redraw interval while waiting for keys (~60fps, keeps job progress live)
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// What a background job hands back to the UI
+pub enum JobOutput {
+    Imported {
+        summary: CsvImportSummary,
+        transactions: Vec<Transaction>,
+        total_count: i64,
+    },
+    Duplicates(Vec<DuplicateCluster>),
+}
+
+/// Bulk action waiting for its text value (category, tag, void reason)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkPromptKind {
+    Category,
+    Tag,
+    VoidReason,
+}
+
+impl BulkPromptKind {
+    fn label(&self) -> &'static str {
+        match self {
+            BulkPromptKind::Category => "Set category",
+            BulkPromptKind::Tag => "Add tag",
+            BulkPromptKind::VoidReason => "Void reason",
+        }
+    }
+}
+
+/// Fields of the cash-entry form, in the order they are asked
+const MANUAL_FIELDS: [&str; 5] =
+    ["Date (YYYY-MM-DD, blank = today)", "Amount (negative = spent)", "Description", "Category", "Reason"];
+
+/// Cash entry being typed in, one field at a time
+#[derive(Debug, Clone, Default)]
+pub struct ManualForm {
+    /// Fields already entered, in MANUAL_FIELDS order
+    values: Vec<String>,
+    input: String,
+}
+
+impl ManualForm {
+    fn label(&self) -> &'static str {
+        MANUAL_FIELDS[self.values.len().min(MANUAL_FIELDS.len() - 1)]
+    }
+}
+
+/// Quick-categorize mode: walks uncategorized transactions one at a time
+pub struct Triage {
+    suggester: CategorySuggester,
+    /// Transaction uuids to categorize, in ledger order
+    queue: Vec<String>,
+    position: usize,
+    suggestions: Vec<CategorySuggestion>,
+    /// Search fallback: typed query and selected match
+    search: Option<(String, usize)>,
+    categorized: usize,
+}
+
+impl Triage {
+    fn search_matches(&self) -> Vec<String> {
+        self.search.as_ref().map_or_else(Vec::new, |(query, _)| self.suggester.search(query))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -46,15 +142,19 @@ impl Page {
         match self {
             Page::BankStatements => Page::TransactionLedger,
             Page::TransactionLedger => Page::Views,
-            Page::Views => Page::BankStatements,
+            Page::Views => Page::Duplicates,
+            Page::Duplicates => Page::Sources,
+            Page::Sources => Page::BankStatements,
         }
     }
 
     pub fn previous(&self) -> Self {
         match self {
-            Page::BankStatements => Page::Views,
+            Page::BankStatements => Page::Sources,
             Page::TransactionLedger => Page::BankStatements,
             Page::Views => Page::TransactionLedger,
+            Page::Duplicates => Page::Views,
+            Page::Sources => Page::Duplicates,
         }
     }
 
@@ -63,6 +163,8 @@ impl Page {
             Page::BankStatements => "Bank Statements",
             Page::TransactionLedger => "Transaction Ledger",
             Page::Views => "Views",
+            Page::Duplicates => "Duplicates",
+            Page::Sources => "Sources",
         }
     }
 }
@@ -76,6 +178,53 @@ pub struct App {
     pub bank_statements_state: TableState,
     pub show_detail: bool,
     pub filter_state: FilterState,
+    /// Database for writes (undo/redo); read-only session when None
+    pub conn: Option<Connection>,
+    /// Recorded on every write made from the UI
+    pub actor: String,
+    /// One-line feedback shown in the status bar
+    pub status_message: Option<String>,
+    /// Notes per transaction uuid, shown in the detail panel
+    pub notes: HashMap<String, Vec<Note>>,
+    /// Lineage of the transaction in the detail panel (needs a database)
+    pub trace: Option<ProvenanceTrace>,
+    /// Bill occurrences (matched, upcoming, missed) shown in the Views page
+    pub bills: Vec<BillOccurrence>,
+    /// This month's envelope balances, shown in the Views page
+    pub envelopes: Option<EnvelopeMonth>,
+    /// Duplicate clusters waiting for review (Duplicates page)
+    pub duplicate_clusters: Vec<DuplicateCluster>,
+    pub duplicates_state: TableState,
+    /// Candidate kept when the selected cluster is merged
+    pub keep_candidate: usize,
+    /// Ledger shown, and the CSV its `i` import reads
+    pub ledger_id: String,
+    pub import_path: Option<String>,
+    /// Import or duplicate scan running on a worker thread
+    pub job: Option<Job<JobOutput>>,
+    /// Ledger table columns (saved to the config file)
+    pub layout: LedgerLayout,
+    /// How amounts and dates are shown (from the config file)
+    pub locale: Locale,
+    /// Formatted cells of the ledger rows on screen
+    pub row_cache: RowCache,
+    /// Header stats and bank summary, cleared whenever the rows change
+    totals: Totals,
+    /// Selected row of the column chooser while it is open
+    pub column_chooser: Option<usize>,
+    /// Transactions marked for a bulk action (uuids)
+    pub marked: HashSet<String>,
+    /// Text being typed for a bulk action
+    pub bulk_prompt: Option<(BulkPromptKind, String)>,
+    /// Quick-categorize mode, when active
+    pub triage: Option<Triage>,
+    /// Per-file statistics (Sources page), refreshed when the page opens
+    pub sources: Vec<SourceStats>,
+    pub sources_state: TableState,
+    /// Spend profile of the selected transaction's merchant, while it is open
+    pub merchant_profile: Option<MerchantProfile>,
+    /// Cash entry being typed in
+    pub manual_form: Option<ManualForm>,
 }
 
 impl App {
@@ -101,11 +250,624 @@ impl App {
             filter_state: FilterState {
                 active_filter: FilterType::None,
             },
+            conn: None,
+            actor: String::new(),
+            status_message: None,
+            notes: HashMap::new(),
+            trace: None,
+            bills: Vec::new(),
+            envelopes: None,
+            duplicate_clusters: Vec::new(),
+            duplicates_state: TableState::default(),
+            keep_candidate: 0,
+            ledger_id: "default".to_string(),
+            import_path: None,
+            job: None,
+            layout: LedgerLayout::default(),
+            locale: Locale::default(),
+            row_cache: RowCache::default(),
+            totals: Totals::default(),
+            column_chooser: None,
+            marked: HashSet::new(),
+            bulk_prompt: None,
+            triage: None,
+            sources: Vec::new(),
+            sources_state: TableState::default(),
+            merchant_profile: None,
+            manual_form: None,
+        }
+    }
+
+    /// Attach a database connection so corrections can be written as `actor`
+    pub fn with_connection(mut self, conn: Connection, actor: &str) -> Self {
+        self.conn = Some(conn);
+        self.actor = actor.to_string();
+        self
+    }
+
+    /// Attach notes (any order) so the detail panel can show their threads
+    pub fn with_notes(mut self, notes: Vec<Note>) -> Self {
+        for note in notes {
+            self.notes.entry(note.tx_uuid.clone()).or_default().push(note);
+        }
+        self
+    }
+
+    /// Attach bill occurrences for the Views page
+    pub fn with_bills(mut self, bills: Vec<BillOccurrence>) -> Self {
+        self.bills = bills;
+        self
+    }
+
+    /// Attach this month's envelope balances for the Views page
+    pub fn with_envelopes(mut self, month: Option<EnvelopeMonth>) -> Self {
+        self.envelopes = month;
+        self
+    }
+
+    /// Ledger the session shows (background imports and scans target it)
+    pub fn with_ledger(mut self, ledger_id: &str, import_path: Option<String>) -> Self {
+        self.ledger_id = ledger_id.to_string();
+        self.import_path = import_path;
+        self
+    }
+
+    /// Ledger table layout (from the config file)
+    pub fn with_layout(mut self, layout: LedgerLayout) -> Self {
+        self.layout = layout.normalized();
+        self
+    }
+
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self.row_cache = RowCache::default();
+        self
+    }
+
+    pub fn open_column_chooser(&mut self) {
+        self.column_chooser = Some(0);
+    }
+
+    /// Profile the selected transaction's merchant over the loaded ledger;
+    /// names the registry doesn't know are profiled as they are spelled
+    pub fn open_merchant_profile(&mut self) {
+        let Some(name) = self.selected_transaction().map(|tx| tx.merchant.clone()) else {
+            return;
+        };
+        if name.trim().is_empty() {
+            self.status_message = Some("No merchant on this transaction".to_string());
+            return;
+        }
+        let merchant = shared_registry()
+            .find_by_string(&name)
+            .unwrap_or_else(|| Merchant::new(name, MerchantType::Other, None));
+        self.merchant_profile = Some(profile_merchant(&merchant, &self.transactions));
+    }
+
+    /// Open the cash-entry form (needs a writable session)
+    pub fn start_manual_form(&mut self) {
+        if self.conn.is_none() {
+            self.status_message = Some("Read-only session".to_string());
+            return;
+        }
+        self.manual_form = Some(ManualForm::default());
+    }
+
+    /// Accept the field being typed; the last one stores the entry
+    pub fn manual_form_next(&mut self) {
+        let Some(form) = self.manual_form.as_mut() else {
+            return;
+        };
+        let value = form.input.trim().to_string();
+        let invalid = match form.values.len() {
+            0 => !value.is_empty() && chrono::NaiveDate::parse_from_str(&value, "%Y-%m-%d").is_err(),
+            1 => value.parse::<f64>().map_or(true, |amount| amount == 0.0),
+            2 | 4 => value.is_empty(),
+            _ => false,
+        };
+        if invalid {
+            self.status_message = Some(format!("Invalid {}", form.label()));
+            return;
+        }
+        form.values.push(value);
+        form.input.clear();
+        if form.values.len() == MANUAL_FIELDS.len() {
+            self.submit_manual_form();
+        }
+    }
+
+    fn submit_manual_form(&mut self) {
+        let (Some(form), Some(conn)) = (self.manual_form.take(), self.conn.as_ref()) else {
+            return;
+        };
+        let [date, amount, description, category, reason] = &form.values[..] else {
+            return;
+        };
+        let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .unwrap_or_else(|_| chrono::Local::now().date_naive());
+        let mut entry = ManualEntry::cash(date, amount.parse().unwrap_or_default(), description);
+        if !category.is_empty() {
+            entry = entry.with_category(category);
+        }
+        match create_manual_transaction(conn, &self.ledger_id, &entry, &self.actor, reason) {
+            Ok(tx) => {
+                self.status_message = Some(format!("Added cash entry {} {:.2}", tx.merchant, tx.amount_numeric));
+                self.transactions.push(tx);
+                self.total_count += 1;
+                self.apply_filter(self.filter_state.active_filter.clone());
+            }
+            Err(e) => self.status_message = Some(e.to_string()),
+        }
+    }
+
+    /// Close the chooser and persist the layout
+    pub fn close_column_chooser(&mut self) {
+        self.column_chooser = None;
+        self.status_message = Some(match save_layout(&self.layout) {
+            Ok(()) => "Column layout saved".to_string(),
+            Err(e) => format!("Layout not saved: {}", e),
+        });
+    }
+
+    pub fn move_chooser(&mut self, forward: bool) {
+        let len = self.layout.columns.len();
+        if let Some(selected) = self.column_chooser {
+            self.column_chooser = Some(if forward { (selected + 1) % len } else { (selected + len - 1) % len });
+        }
+    }
+
+    pub fn toggle_chosen_column(&mut self) {
+        if let Some(selected) = self.column_chooser {
+            if !self.layout.toggle(selected) {
+                self.status_message = Some("At least one column stays visible".to_string());
+            }
+        }
+    }
+
+    pub fn resize_chosen_column(&mut self, delta: i32) {
+        if let Some(selected) = self.column_chooser {
+            self.layout.resize(selected, delta);
+        }
+    }
+
+    /// Mark/unmark the selected transaction and move to the next row
+    pub fn toggle_mark(&mut self) {
+        let Some(id) = self.selected_transaction().map(|tx| tx.id.clone()) else {
+            return;
+        };
+        if !self.marked.remove(&id) {
+            self.marked.insert(id);
+        }
+        self.next();
+    }
+
+    /// Ask for the value of a bulk action (needs marked rows)
+    pub fn start_bulk_prompt(&mut self, kind: BulkPromptKind) {
+        if self.marked.is_empty() {
+            self.status_message = Some("Mark rows with Space first".to_string());
+            return;
+        }
+        self.bulk_prompt = Some((kind, String::new()));
+    }
+
+    pub fn submit_bulk_prompt(&mut self) {
+        let Some((kind, input)) = self.bulk_prompt.take() else {
+            return;
+        };
+        let value = input.trim().to_string();
+        let action = match kind {
+            BulkPromptKind::Category => BulkAction::SetCategory(value),
+            BulkPromptKind::Tag => BulkAction::AddTag(value),
+            BulkPromptKind::VoidReason => BulkAction::Void(value),
+        };
+        self.apply_bulk(action);
+    }
+
+    /// Apply an action to every marked transaction (one batch)
+    pub fn apply_bulk(&mut self, action: BulkAction) {
+        if self.marked.is_empty() {
+            self.status_message = Some("Mark rows with Space first".to_string());
+            return;
+        }
+        let Some(conn) = self.conn.as_ref() else {
+            self.status_message = Some("Read-only session".to_string());
+            return;
+        };
+
+        let selected: Vec<Transaction> = self
+            .transactions
+            .iter()
+            .filter(|tx| self.marked.contains(&tx.id))
+            .cloned()
+            .collect();
+
+        match apply_bulk_action(conn, &selected, &action, &self.actor) {
+            Ok(result) => {
+                self.status_message = Some(format!(
+                    "Bulk {}: {} applied, {} pending approval, {} unchanged, {} failed",
+                    action.describe(),
+                    result.applied.len(),
+                    result.pending,
+                    result.skipped,
+                    result.failed.len()
+                ));
+                for tx in result.applied {
+                    self.replace_transaction(tx);
+                }
+                self.marked.clear();
+            }
+            Err(e) => self.status_message = Some(e.to_string()),
+        }
+    }
+
+    /// Enter triage over the uncategorized transactions
+    pub fn start_triage(&mut self) {
+        let Some(conn) = self.conn.as_ref() else {
+            self.status_message = Some("Read-only session".to_string());
+            return;
+        };
+        let queue: Vec<String> = self
+            .filtered_transactions
+            .iter()
+            .filter(|tx| needs_triage(tx))
+            .map(|tx| tx.id.clone())
+            .collect();
+        if queue.is_empty() {
+            self.status_message = Some("Nothing to triage: every transaction has a category".to_string());
+            return;
+        }
+
+        let rules = RuleEngine::from_database(conn).unwrap_or_default();
+        self.triage = Some(Triage {
+            suggester: CategorySuggester::from_history(rules, &self.transactions),
+            queue,
+            position: 0,
+            suggestions: Vec::new(),
+            search: None,
+            categorized: 0,
+        });
+        self.refresh_triage();
+    }
+
+    pub fn triage_transaction(&self) -> Option<&Transaction> {
+        let triage = self.triage.as_ref()?;
+        let id = triage.queue.get(triage.position)?;
+        self.transactions.iter().find(|tx| &tx.id == id)
+    }
+
+    fn refresh_triage(&mut self) {
+        let suggestions = match (self.triage.as_ref(), self.triage_transaction()) {
+            (Some(triage), Some(tx)) => triage.suggester.suggest(tx, 3),
+            _ => Vec::new(),
+        };
+        if let Some(triage) = self.triage.as_mut() {
+            triage.suggestions = suggestions;
+            triage.search = None;
+        }
+    }
+
+    /// Move to the next (or previous) transaction; leaving the end exits triage
+    pub fn triage_step(&mut self, forward: bool) {
+        let Some(triage) = self.triage.as_mut() else {
+            return;
+        };
+        if forward {
+            triage.position += 1;
+        } else {
+            triage.position = triage.position.saturating_sub(1);
+        }
+        if triage.position >= triage.queue.len() {
+            self.finish_triage();
+        } else {
+            self.refresh_triage();
+        }
+    }
+
+    pub fn finish_triage(&mut self) {
+        if let Some(triage) = self.triage.take() {
+            self.status_message = Some(format!(
+                "Triage: {} of {} categorized",
+                triage.categorized,
+                triage.queue.len()
+            ));
+        }
+    }
+
+    /// Pick suggestion 1-3
+    pub fn triage_pick(&mut self, index: usize) {
+        let category = self
+            .triage
+            .as_ref()
+            .and_then(|triage| triage.suggestions.get(index))
+            .map(|suggestion| suggestion.category.clone());
+        match category {
+            Some(category) => self.triage_apply(&category),
+            None => self.status_message = Some(format!("No suggestion {} (/ to search)", index + 1)),
+        }
+    }
+
+    /// Apply the selected search match, or the typed text as a new category
+    pub fn triage_apply_search(&mut self) {
+        let Some(triage) = self.triage.as_ref() else {
+            return;
+        };
+        let Some((query, selected)) = triage.search.as_ref() else {
+            return;
+        };
+        let category = triage.search_matches().get(*selected).cloned().unwrap_or_else(|| query.trim().to_string());
+        self.triage_apply(&category);
+    }
+
+    fn triage_apply(&mut self, category: &str) {
+        let (Some(conn), Some(tx)) = (self.conn.as_ref(), self.triage_transaction()) else {
+            return;
+        };
+        match categorize(conn, tx, category, &self.actor) {
+            Ok(WriteOutcome::Applied(next)) => {
+                self.status_message = Some(format!("{} → {}", truncate(&next.merchant, 30), category));
+                self.replace_transaction(next);
+            }
+            Ok(WriteOutcome::Pending(change)) => {
+                self.status_message = Some(format!("{} → {} (pending approval {})", tx.merchant, category, change.id));
+            }
+            Err(e) => {
+                self.status_message = Some(e.to_string());
+                return;
+            }
+        }
+        if let Some(triage) = self.triage.as_mut() {
+            triage.categorized += 1;
+        }
+        self.triage_step(true);
+    }
+
+    /// Database file for worker connections (None for read-only sessions)
+    fn database_path(&self) -> Option<PathBuf> {
+        self.conn
+            .as_ref()
+            .and_then(|conn| conn.path())
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+    }
+
+    /// Run `work` on a worker thread with its own connection
+    fn start_job<F>(&mut self, name: &str, work: F)
+    where
+        F: FnOnce(&trust_construction::JobContext, &Connection) -> Result<JobOutput> + Send + 'static,
+    {
+        if let Some(job) = &self.job {
+            self.status_message = Some(format!("{} is still running (Esc to cancel)", job.name));
+            return;
+        }
+        let Some(db_path) = self.database_path() else {
+            self.status_message = Some("Read-only session".to_string());
+            return;
+        };
+        self.job = Some(Job::spawn(name, move |ctx| {
+            let conn = open_worker_connection(&db_path)?;
+            work(ctx, &conn)
+        }));
+    }
+
+    /// Import the ledger's configured CSV in the background
+    pub fn start_import(&mut self) {
+        let Some(import_path) = self.import_path.clone() else {
+            self.status_message = Some(format!("No import file configured for ledger '{}'", self.ledger_id));
+            return;
+        };
+        let (ledger_id, actor) = (self.ledger_id.clone(), self.actor.clone());
+        self.start_job("Import", move |ctx, conn| {
+            let summary = import_csv_job(ctx, conn, Path::new(&import_path), &ledger_id, &actor)?;
+            ctx.progress(0, 0, "Reloading transactions");
+            Ok(JobOutput::Imported {
+                summary,
+                transactions: ledger_transactions(conn, &ledger_id)?,
+                total_count: verify_count(conn)?,
+            })
+        });
+    }
+
+    /// Re-run the duplicate scan in the background
+    pub fn start_duplicate_scan(&mut self) {
+        let ledger_id = self.ledger_id.clone();
+        self.start_job("Duplicate scan", move |ctx, conn| {
+            Ok(JobOutput::Duplicates(scan_duplicates_job(ctx, conn, &ledger_id, chrono::Utc::now())?))
+        });
+    }
+
+    /// Ask the running job to stop; false when nothing is running
+    pub fn cancel_job(&mut self) -> bool {
+        match &self.job {
+            Some(job) => {
+                job.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Take in job progress and apply its result once finished (every frame)
+    pub fn poll_job(&mut self) {
+        let Some(job) = self.job.as_mut() else {
+            return;
+        };
+        let Some(outcome) = job.poll() else {
+            return;
+        };
+        let name = job.name.clone();
+        let elapsed = job.elapsed().as_secs_f64();
+        self.job = None;
+
+        self.status_message = Some(match outcome {
+            JobOutcome::Completed(JobOutput::Imported { summary, transactions, total_count }) => {
+                self.transactions = transactions;
+                self.total_count = total_count;
+                self.apply_filter(self.filter_state.active_filter.clone());
+                format!(
+                    "Imported {} ({} duplicates skipped) in {:.1}s",
+                    summary.inserted, summary.skipped, elapsed
+                )
+            }
+            JobOutcome::Completed(JobOutput::Duplicates(clusters)) => {
+                let message = format!("{} duplicate clusters to review ({:.1}s)", clusters.len(), elapsed);
+                self.set_duplicates(clusters);
+                message
+            }
+            JobOutcome::Cancelled => format!("{} cancelled", name),
+            JobOutcome::Failed(e) => format!("{} failed: {}", name, e),
+        });
+    }
+
+    /// Attach duplicate clusters for the Duplicates page
+    pub fn with_duplicates(mut self, clusters: Vec<DuplicateCluster>) -> Self {
+        self.set_duplicates(clusters);
+        self
+    }
+
+    fn set_duplicates(&mut self, clusters: Vec<DuplicateCluster>) {
+        self.duplicates_state.select(if clusters.is_empty() { None } else { Some(0) });
+        self.keep_candidate = 0;
+        self.duplicate_clusters = clusters;
+    }
+
+    pub fn selected_cluster(&self) -> Option<&DuplicateCluster> {
+        self.duplicates_state.selected().and_then(|i| self.duplicate_clusters.get(i))
+    }
+
+    pub fn next_cluster(&mut self) {
+        let len = self.duplicate_clusters.len();
+        if len > 0 {
+            let i = self.duplicates_state.selected().map_or(0, |i| (i + 1) % len);
+            self.duplicates_state.select(Some(i));
+            self.keep_candidate = 0;
+        }
+    }
+
+    pub fn previous_cluster(&mut self) {
+        let len = self.duplicate_clusters.len();
+        if len > 0 {
+            let i = self.duplicates_state.selected().map_or(0, |i| (i + len - 1) % len);
+            self.duplicates_state.select(Some(i));
+            self.keep_candidate = 0;
+        }
+    }
+
+    /// Move the "keep" marker between the selected cluster's candidates
+    pub fn cycle_keep_candidate(&mut self, forward: bool) {
+        let len = self.selected_cluster().map_or(0, |cluster| cluster.transactions.len());
+        if len > 0 {
+            self.keep_candidate = if forward {
+                (self.keep_candidate + 1) % len
+            } else {
+                (self.keep_candidate + len - 1) % len
+            };
+        }
+    }
+
+    /// Merge (keeping the marked candidate), dismiss or defer the selected cluster
+    pub fn decide_selected_cluster(&mut self, decision: DuplicateDecision) {
+        let Some(conn) = self.conn.as_ref() else {
+            self.status_message = Some("Read-only session".to_string());
+            return;
+        };
+        let Some(index) = self.duplicates_state.selected() else {
+            return;
+        };
+        let Some(cluster) = self.duplicate_clusters.get(index).cloned() else {
+            return;
+        };
+
+        let result = match decision {
+            DuplicateDecision::Merged => {
+                let keep = cluster.transactions[self.keep_candidate.min(cluster.transactions.len() - 1)].id.clone();
+                merge_cluster(conn, &cluster, &keep, &self.actor)
+            }
+            _ => record_decision(conn, &cluster, decision, &self.actor).map(|_| Vec::new()),
+        };
+
+        match result {
+            Ok(voided) => {
+                self.status_message = Some(format!(
+                    "Cluster {} ({} transactions)",
+                    decision.as_str(),
+                    cluster.transactions.len()
+                ));
+                for tx in voided {
+                    self.replace_transaction(tx);
+                }
+                self.duplicate_clusters.remove(index);
+                self.keep_candidate = 0;
+                let len = self.duplicate_clusters.len();
+                self.duplicates_state.select(if len == 0 { None } else { Some(index.min(len - 1)) });
+            }
+            Err(e) => self.status_message = Some(e.to_string()),
+        }
+    }
+
+    /// Undo the last change to the selected transaction
+    pub fn undo_selected(&mut self) {
+        self.write_selected(submit_undo, "Undid");
+    }
+
+    /// Redo the last undone change to the selected transaction
+    pub fn redo_selected(&mut self) {
+        self.write_selected(submit_redo, "Redid");
+    }
+
+    fn write_selected(
+        &mut self,
+        write: fn(&Connection, &str, &str) -> Result<WriteOutcome>,
+        verb: &str,
+    ) {
+        let Some(conn) = self.conn.as_ref() else {
+            self.status_message = Some("Read-only session".to_string());
+            return;
+        };
+        let Some(tx_id) = self.selected_transaction().map(|tx| tx.id.clone()) else {
+            return;
+        };
+
+        match write(conn, &tx_id, &self.actor) {
+            Ok(WriteOutcome::Applied(updated)) => {
+                self.status_message = Some(format!("{} change → v{}", verb, updated.version));
+                self.replace_transaction(updated);
+            }
+            Ok(WriteOutcome::Pending(change)) => {
+                self.status_message = Some(format!("{} change (pending approval {})", verb, change.id));
+            }
+            Err(e) => self.status_message = Some(e.to_string()),
+        }
+    }
+
+    /// Swap in a new version of a transaction, keeping the selection
+    fn replace_transaction(&mut self, updated: Transaction) {
+        self.totals = Totals::default();
+        for tx in self
+            .transactions
+            .iter_mut()
+            .chain(self.filtered_transactions.iter_mut())
+            .filter(|tx| tx.id == updated.id)
+        {
+            *tx = updated.clone();
         }
     }
 
     pub fn toggle_detail(&mut self) {
         self.show_detail = !self.show_detail;
+        self.refresh_trace();
+    }
+
+    /// Load the lineage of the selected transaction while the detail panel is
+    /// open (kept until the selection moves)
+    pub fn refresh_trace(&mut self) {
+        let selected = self.selected_transaction().filter(|_| self.show_detail).map(|tx| tx.id.clone());
+        if self.trace.as_ref().map(|trace| &trace.transaction_id) == selected.as_ref() {
+            return;
+        }
+        self.trace = match (&self.conn, selected) {
+            (Some(conn), Some(tx_id)) => provenance::trace(conn, &tx_id).ok().flatten(),
+            _ => None,
+        };
     }
 
     pub fn selected_transaction(&self) -> Option<&Transaction> {
@@ -113,6 +875,7 @@ impl App {
     }
 
     pub fn apply_filter(&mut self, filter: FilterType) {
+        self.totals = Totals::default();
         self.filter_state.active_filter = filter.clone();
 
         self.filtered_transactions = match filter {
@@ -157,13 +920,68 @@ impl App {
 
     pub fn next_page(&mut self) {
         self.current_page = self.current_page.next();
+        if self.current_page == Page::Sources {
+            self.refresh_sources();
+        }
     }
 
     pub fn previous_page(&mut self) {
         self.current_page = self.current_page.previous();
+        if self.current_page == Page::Sources {
+            self.refresh_sources();
+        }
+    }
+
+    /// Recompute the Sources page (from the database when one is attached;
+    /// duplicates skipped at import are only known there)
+    pub fn refresh_sources(&mut self) {
+        let stats = match &self.conn {
+            Some(conn) => source_stats(conn, Some(&self.ledger_id)),
+            None => Ok(compute_source_stats(&self.transactions, &HashMap::new())),
+        };
+        match stats {
+            Ok(stats) => {
+                self.sources = stats;
+                let selected = self.sources_state.selected().unwrap_or(0);
+                self.sources_state
+                    .select((!self.sources.is_empty()).then(|| selected.min(self.sources.len() - 1)));
+            }
+            Err(e) => self.status_message = Some(format!("Source stats failed: {}", e)),
+        }
     }
 
-    pub fn bank_summary(&self) -> Vec<(String, usize, f64)> {
+    pub fn next_source(&mut self) {
+        let len = self.sources.len();
+        if len > 0 {
+            let i = self.sources_state.selected().map_or(0, |i| (i + 1) % len);
+            self.sources_state.select(Some(i));
+        }
+    }
+
+    pub fn previous_source(&mut self) {
+        let len = self.sources.len();
+        if len > 0 {
+            let i = self.sources_state.selected().map_or(0, |i| (i + len - 1) % len);
+            self.sources_state.select(Some(i));
+        }
+    }
+
+    /// Transactions and net amount per bank, most transactions first
+    /// (aggregated by the database when one is attached)
+    /// Rows and net amount per bank, busiest first (computed once until the
+    /// rows change)
+    pub fn bank_summary(&self) -> &[(String, usize, f64)] {
+        self.totals.banks.get_or_init(|| self.compute_bank_summary())
+    }
+
+    fn compute_bank_summary(&self) -> Vec<(String, usize, f64)> {
+        if let Some(Ok(totals)) = self.conn.as_ref().map(|conn| totals_by_bank(conn, Some(&self.ledger_id))) {
+            return totals
+                .into_iter()
+                .map(|group| (group.key, group.count as usize, group.total))
+                .collect();
+        }
+
         let mut summary: HashMap<String, (usize, f64)> = HashMap::new();
 
         for tx in &self.transactions {
@@ -181,6 +999,73 @@ impl App {
         result
     }
 
+    /// Bank highlighted on the Bank Statements page
+    pub fn selected_bank(&self) -> Option<String> {
+        let summary = self.bank_summary();
+        self.bank_statements_state
+            .selected()
+            .and_then(|i| summary.get(i))
+            .map(|(bank, _, _)| bank.clone())
+    }
+
+    pub fn next_bank(&mut self) {
+        let len = self.bank_summary().len();
+        if len == 0 {
+            return;
+        }
+        let i = self.bank_statements_state.selected().map_or(0, |i| (i + 1) % len);
+        self.bank_statements_state.select(Some(i));
+    }
+
+    pub fn previous_bank(&mut self) {
+        let len = self.bank_summary().len();
+        if len == 0 {
+            return;
+        }
+        let i = self.bank_statements_state.selected().map_or(0, |i| (i + len - 1) % len);
+        self.bank_statements_state.select(Some(i));
+    }
+
+    /// Expenses (GASTO) of one bank per category, largest first
+    pub fn bank_category_expenses(&self, bank: &str) -> Vec<(String, f64)> {
+        let mut totals: HashMap<String, f64> = HashMap::new();
+        for tx in self.bank_expenses(bank) {
+            *totals.entry(tx.category.clone()).or_insert(0.0) += tx.amount_numeric.abs();
+        }
+
+        let mut result: Vec<_> = totals.into_iter().collect();
+        result.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        result
+    }
+
+    /// Expenses (GASTO) of one bank per month, oldest first, empty months as 0
+    pub fn bank_monthly_expenses(&self, bank: &str) -> Vec<(String, f64)> {
+        let mut totals: BTreeMap<(i32, u32), f64> = BTreeMap::new();
+        for tx in self.bank_expenses(bank) {
+            if let Some(date) = tx.parsed_date() {
+                *totals.entry((date.year(), date.month())).or_insert(0.0) += tx.amount_numeric.abs();
+            }
+        }
+
+        let (Some(&first), Some(&last)) = (totals.keys().next(), totals.keys().next_back()) else {
+            return Vec::new();
+        };
+        let mut months = Vec::new();
+        let (mut year, mut month) = first;
+        while (year, month) <= last {
+            let total = totals.get(&(year, month)).copied().unwrap_or(0.0);
+            months.push((format!("{}-{:02}", year, month), total));
+            (year, month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+        }
+        months
+    }
+
+    fn bank_expenses<'a>(&'a self, bank: &'a str) -> impl Iterator<Item = &'a Transaction> {
+        self.transactions
+            .iter()
+            .filter(move |tx| tx.bank == bank && tx.transaction_type == "GASTO" && !tx.is_voided())
+    }
+
     pub fn next(&mut self) {
         let len = self.filtered_transactions.len();
         if len == 0 {
@@ -250,9 +1135,36 @@ impl App {
         self.state.select(Some(i));
     }
 
-    pub fn stats(&self) -> TransactionStats {
+    /// Counts and totals per type (aggregated by the database when one is
+    /// attached)
+    /// Header counts and totals (computed once until the rows change)
+    pub fn stats(&self) -> &TransactionStats {
+        self.totals.stats.get_or_init(|| self.compute_stats())
+    }
+
+    fn compute_stats(&self) -> TransactionStats {
         let mut stats = TransactionStats::default();
 
+        if let Some(Ok(totals)) = self.conn.as_ref().map(|conn| totals_by_type(conn, Some(&self.ledger_id))) {
+            for group in totals {
+                let count = group.count as usize;
+                match group.key.as_str() {
+                    "GASTO" => {
+                        stats.gastos_count = count;
+                        stats.gastos_total = group.total;
+                    }
+                    "INGRESO" => {
+                        stats.ingresos_count = count;
+                        stats.ingresos_total = group.total;
+                    }
+                    "PAGO_TARJETA" => stats.pago_tarjeta_count = count,
+                    "TRASPASO" => stats.traspaso_count = count,
+                    _ => {}
+                }
+            }
+            return stats;
+        }
+
         for tx in &self.transactions {
             match tx.transaction_type.as_str() {
                 "GASTO" => {
@@ -273,7 +1185,16 @@ impl App {
     }
 }
 
-#[derive(Default)]
+/// Whole-ledger aggregates the header and Bank Statements page read on every
+/// frame and keypress: each is a full scan (or SQL aggregate), so it is
+/// computed on first use and kept until the rows change
+#[derive(Debug, Default)]
+struct Totals {
+    stats: OnceCell<TransactionStats>,
+    banks: OnceCell<Vec<(String, usize, f64)>>,
+}
+
+#[derive(Debug, Default)]
 pub struct TransactionStats {
     pub gastos_count: usize,
     pub gastos_total: f64,
@@ -306,16 +1227,164 @@ pub fn run_ui(app: &mut App) -> Result<()> {
     Ok(())
 }
 
+/// Draw the ledger page `frames` times into an off-screen terminal, a page
+/// further down each time; how long each frame took
+pub fn measure_frames(app: &mut App, frames: usize, width: u16, height: u16) -> io::Result<Vec<Duration>> {
+    let mut terminal = Terminal::new(TestBackend::new(width, height))?;
+    let mut times = Vec::with_capacity(frames);
+    for _ in 0..frames {
+        let started = Instant::now();
+        terminal.draw(|f| ui(f, app))?;
+        times.push(started.elapsed());
+        app.page_down();
+    }
+    Ok(times)
+}
+
 fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
 ) -> io::Result<()> {
     loop {
+        app.poll_job();
+        app.refresh_trace();
         terminal.draw(|f| ui(f, app))?;
 
+        // Wake up every frame so a running job's progress keeps moving
+        if !event::poll(FRAME_INTERVAL)? {
+            continue;
+        }
+
         if let Event::Key(key) = event::read()? {
+            app.status_message = None;
+
+            // A bulk-action prompt takes all keys while it is open
+            if let Some((_, input)) = app.bulk_prompt.as_mut() {
+                match key.code {
+                    KeyCode::Enter => app.submit_bulk_prompt(),
+                    KeyCode::Esc => app.bulk_prompt = None,
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Char(c) => input.push(c),
+                    _ => {}
+                }
+                continue;
+            }
+
+            // The cash-entry form takes all keys while it is open
+            if let Some(form) = app.manual_form.as_mut() {
+                match key.code {
+                    KeyCode::Enter => app.manual_form_next(),
+                    KeyCode::Esc => app.manual_form = None,
+                    KeyCode::Backspace => {
+                        form.input.pop();
+                    }
+                    KeyCode::Char(c) => form.input.push(c),
+                    _ => {}
+                }
+                continue;
+            }
+
+            // Triage takes all keys while it is active
+            if let Some(triage) = app.triage.as_mut() {
+                if let Some((query, selected)) = triage.search.as_mut() {
+                    match key.code {
+                        KeyCode::Enter => app.triage_apply_search(),
+                        KeyCode::Esc => triage.search = None,
+                        KeyCode::Down => *selected += 1,
+                        KeyCode::Up => *selected = selected.saturating_sub(1),
+                        KeyCode::Backspace => {
+                            query.pop();
+                            *selected = 0;
+                        }
+                        KeyCode::Char(c) => {
+                            query.push(c);
+                            *selected = 0;
+                        }
+                        _ => {}
+                    }
+                    // Keep the selection on an existing match
+                    if let Some(triage) = app.triage.as_mut() {
+                        let matches = triage.search_matches().len();
+                        if let Some((_, selected)) = triage.search.as_mut() {
+                            *selected = (*selected).min(matches.saturating_sub(1));
+                        }
+                    }
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('1') => app.triage_pick(0),
+                    KeyCode::Char('2') => app.triage_pick(1),
+                    KeyCode::Char('3') => app.triage_pick(2),
+                    KeyCode::Char('/') => triage.search = Some((String::new(), 0)),
+                    KeyCode::Right | KeyCode::Char('s') | KeyCode::Char('l') => app.triage_step(true),
+                    KeyCode::Left | KeyCode::Char('h') => app.triage_step(false),
+                    KeyCode::Esc | KeyCode::Char('q') => app.finish_triage(),
+                    _ => {}
+                }
+                continue;
+            }
+
+            // The merchant profile stays up until it is closed
+            if app.merchant_profile.is_some() {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Char('M') | KeyCode::Char('q')) {
+                    app.merchant_profile = None;
+                }
+                continue;
+            }
+
+            // The column chooser takes all keys while it is open
+            if app.column_chooser.is_some() {
+                match key.code {
+                    KeyCode::Down | KeyCode::Char('j') => app.move_chooser(true),
+                    KeyCode::Up | KeyCode::Char('k') => app.move_chooser(false),
+                    KeyCode::Char(' ') | KeyCode::Enter => app.toggle_chosen_column(),
+                    KeyCode::Right | KeyCode::Char('+') | KeyCode::Char('l') => app.resize_chosen_column(2),
+                    KeyCode::Left | KeyCode::Char('-') | KeyCode::Char('h') => app.resize_chosen_column(-2),
+                    KeyCode::Esc | KeyCode::Char('v') | KeyCode::Char('q') => app.close_column_chooser(),
+                    _ => {}
+                }
+                continue;
+            }
+
             match key.code {
-                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Esc if app.cancel_job() => {
+                    app.status_message = Some("Cancelling…".to_string());
+                }
+                KeyCode::Esc if !app.marked.is_empty() => {
+                    app.marked.clear();
+                    app.status_message = Some("Marks cleared".to_string());
+                }
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    // Don't leave a worker writing after the terminal is restored
+                    if let Some(mut job) = app.job.take() {
+                        job.cancel();
+                        job.join();
+                    }
+                    return Ok(());
+                }
+                KeyCode::Char('v') if app.current_page == Page::TransactionLedger => {
+                    app.open_column_chooser()
+                }
+                KeyCode::Char(' ') if app.current_page == Page::TransactionLedger => app.toggle_mark(),
+                KeyCode::Char('M') if app.current_page == Page::TransactionLedger => app.open_merchant_profile(),
+                KeyCode::Char('N') if app.current_page == Page::TransactionLedger => app.start_manual_form(),
+                KeyCode::Char('C') if app.current_page == Page::TransactionLedger => {
+                    app.start_bulk_prompt(BulkPromptKind::Category)
+                }
+                KeyCode::Char('T') if app.current_page == Page::TransactionLedger => {
+                    app.start_bulk_prompt(BulkPromptKind::Tag)
+                }
+                KeyCode::Char('V') if app.current_page == Page::TransactionLedger => {
+                    app.start_bulk_prompt(BulkPromptKind::VoidReason)
+                }
+                KeyCode::Char('R') if app.current_page == Page::TransactionLedger => {
+                    app.apply_bulk(BulkAction::MarkReviewed)
+                }
+                KeyCode::Char('t') => app.start_triage(),
+                KeyCode::Char('i') => app.start_import(),
+                KeyCode::Char('s') => app.start_duplicate_scan(),
                 KeyCode::Enter => app.toggle_detail(),
                 KeyCode::Tab => {
                     if key.modifiers.contains(KeyModifiers::SHIFT) {
@@ -348,6 +1417,37 @@ fn run_app<B: ratatui::backend::Backend>(
                     app.apply_filter(FilterType::Traspasos);
                     app.current_page = Page::TransactionLedger;
                 }
+                KeyCode::Char('u') => app.undo_selected(),
+                KeyCode::Char('r') => app.redo_selected(),
+                KeyCode::Down | KeyCode::Char('j') if app.current_page == Page::Duplicates => {
+                    app.next_cluster()
+                }
+                KeyCode::Up | KeyCode::Char('k') if app.current_page == Page::Duplicates => {
+                    app.previous_cluster()
+                }
+                KeyCode::Right | KeyCode::Char('l') if app.current_page == Page::Duplicates => {
+                    app.cycle_keep_candidate(true)
+                }
+                KeyCode::Left | KeyCode::Char('h') if app.current_page == Page::Duplicates => {
+                    app.cycle_keep_candidate(false)
+                }
+                KeyCode::Char('m') if app.current_page == Page::Duplicates => {
+                    app.decide_selected_cluster(DuplicateDecision::Merged)
+                }
+                KeyCode::Char('x') if app.current_page == Page::Duplicates => {
+                    app.decide_selected_cluster(DuplicateDecision::Dismissed)
+                }
+                KeyCode::Char('d') if app.current_page == Page::Duplicates => {
+                    app.decide_selected_cluster(DuplicateDecision::Deferred)
+                }
+                KeyCode::Down | KeyCode::Char('j') if app.current_page == Page::BankStatements => {
+                    app.next_bank()
+                }
+                KeyCode::Down | KeyCode::Char('j') if app.current_page == Page::Sources => app.next_source(),
+                KeyCode::Up | KeyCode::Char('k') if app.current_page == Page::Sources => app.previous_source(),
+                KeyCode::Up | KeyCode::Char('k') if app.current_page == Page::BankStatements => {
+                    app.previous_bank()
+                }
                 KeyCode::Down | KeyCode::Char('j') => app.next(),
                 KeyCode::Up | KeyCode::Char('k') => app.previous(),
                 KeyCode::PageDown => app.page_down(),
@@ -378,7 +1478,9 @@ fn ui(f: &mut Frame, app: &mut App) {
     render_header(f, chunks[0], app);
 
     // Content area with optional split for detail panel
-    if app.show_detail && app.current_page == Page::TransactionLedger {
+    if app.triage.is_some() {
+        render_triage(f, chunks[1], app);
+    } else if app.show_detail && app.current_page == Page::TransactionLedger {
         let content_chunks = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([
@@ -395,6 +1497,8 @@ fn ui(f: &mut Frame, app: &mut App) {
             Page::BankStatements => render_bank_statements(f, chunks[1], app),
             Page::TransactionLedger => render_table(f, chunks[1], app),
             Page::Views => render_views(f, chunks[1], app),
+            Page::Duplicates => render_duplicates(f, chunks[1], app),
+            Page::Sources => render_sources(f, chunks[1], app),
         }
     }
 
@@ -410,6 +1514,8 @@ fn render_header(f: &mut Frame, area: Rect, app: &App) {
         (Page::BankStatements, "Bank Statements"),
         (Page::TransactionLedger, "Transaction Ledger"),
         (Page::Views, "Views"),
+        (Page::Duplicates, "Duplicates"),
+        (Page::Sources, "Sources"),
     ];
 
     let mut tab_spans = vec![];
@@ -447,17 +1553,72 @@ fn render_header(f: &mut Frame, area: Rect, app: &App) {
 
     let header_text = vec![Line::from(tab_spans)];
 
-    let header = Paragraph::new(header_text)
-        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Cyan)));
+    let header = Paragraph::new(header_text)
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Cyan)));
+
+    f.render_widget(header, area);
+}
+
+/// Rows formatted once and reused while they stay on screen
+const ROW_CACHE_CAPACITY: usize = 1024;
+
+/// Formatted cells of recently shown ledger rows (dates, amounts,
+/// truncation), keyed by transaction id and version; dropped when the
+/// layout changes or the cache is full
+#[derive(Debug, Default)]
+pub struct RowCache {
+    layout: Option<LedgerLayout>,
+    rows: HashMap<String, (i64, Vec<String>)>,
+}
+
+impl RowCache {
+    fn fill(&mut self, tx: &Transaction, layout: &LedgerLayout, locale: &Locale) {
+        if self.layout.as_ref() != Some(layout) {
+            self.rows.clear();
+            self.layout = Some(layout.clone());
+        }
+        if self.rows.get(&tx.id).is_some_and(|(version, _)| *version == tx.version) {
+            return;
+        }
+        if self.rows.len() >= ROW_CACHE_CAPACITY {
+            self.rows.clear();
+        }
+        self.rows.insert(tx.id.clone(), (tx.version, row_cells(tx, layout, locale)));
+    }
+
+    fn cells(&self, tx: &Transaction) -> &[String] {
+        self.rows.get(&tx.id).map_or(&[], |(_, cells)| cells.as_slice())
+    }
+}
 
-    f.render_widget(header, area);
+fn row_cells(tx: &Transaction, layout: &LedgerLayout, locale: &Locale) -> Vec<String> {
+    layout
+        .visible()
+        .map(|setting| {
+            let max_len = setting.width.saturating_sub(2) as usize;
+            match setting.column {
+                LedgerColumn::Date => locale.transaction_date(tx),
+                LedgerColumn::Bank => truncate(&tx.bank, max_len),
+                LedgerColumn::Merchant => truncate(&tx.merchant, max_len),
+                LedgerColumn::Amount => locale.amount(tx.amount_numeric),
+                LedgerColumn::Type => tx.transaction_type.clone(),
+                LedgerColumn::Category => truncate(&tx.category, max_len),
+                LedgerColumn::Account => truncate(&tx.account_name, max_len),
+                LedgerColumn::Currency => tx.currency.clone(),
+                LedgerColumn::SourceFile => truncate(&tx.source_file, max_len),
+                LedgerColumn::Confidence => tx
+                    .get_metadata("confidence_score")
+                    .and_then(|score| score.as_f64())
+                    .map_or("-".to_string(), |score| format!("{:.0}%", score * 100.0)),
+            }
+        })
+        .collect()
 }
 
 fn render_table(f: &mut Frame, area: Rect, app: &mut App) {
-    let header_cells = ["Date", "Bank", "Merchant", "Amount", "Type", "Category"]
-        .iter()
-        .map(|h| {
-            Cell::from(*h).style(
+    let header_cells = app.layout.visible()
+        .map(|setting| {
+            Cell::from(setting.column.title()).style(
                 Style::default()
                     .fg(Color::Yellow)
                     .add_modifier(Modifier::BOLD),
@@ -468,7 +1629,25 @@ fn render_table(f: &mut Frame, area: Rect, app: &mut App) {
         .style(Style::default().bg(Color::DarkGray))
         .height(1);
 
-    let rows = app.filtered_transactions.iter().map(|tx| {
+    // Only the rows that fit are built: keep the selection inside the window
+    let len = app.filtered_transactions.len();
+    let height = area.height.saturating_sub(3) as usize; // borders and header
+    let selected = app.state.selected().filter(|&i| i < len);
+    let mut offset = app.state.offset().min(len.saturating_sub(1));
+    if let Some(selected) = selected {
+        if selected < offset {
+            offset = selected;
+        } else if height > 0 && selected >= offset + height {
+            offset = selected + 1 - height;
+        }
+    }
+    *app.state.offset_mut() = offset;
+
+    let window = &app.filtered_transactions[offset..(offset + height).min(len)];
+    for tx in window {
+        app.row_cache.fill(tx, &app.layout, &app.locale);
+    }
+    let rows = window.iter().map(|tx| {
         let color = match tx.transaction_type.as_str() {
             "GASTO" => Color::Red,
             "INGRESO" => Color::Green,
@@ -477,47 +1656,311 @@ fn render_table(f: &mut Frame, area: Rect, app: &mut App) {
             _ => Color::White,
         };
 
-        let cells = vec![
-            Cell::from(tx.date.clone()),
-            Cell::from(tx.bank.clone()),
-            Cell::from(truncate(&tx.merchant, 30)),
-            Cell::from(format!("{:.2}", tx.amount_numeric)).style(Style::default().fg(color)),
-            Cell::from(tx.transaction_type.clone()).style(Style::default().fg(color)),
-            Cell::from(truncate(&tx.category, 20)),
-        ];
+        let marked = app.marked.contains(&tx.id);
+        let cells: Vec<Cell> = app.layout.visible()
+            .zip(app.row_cache.cells(tx))
+            .map(|(setting, text)| match setting.column {
+                LedgerColumn::Amount | LedgerColumn::Type => {
+                    Cell::from(text.as_str()).style(Style::default().fg(color))
+                }
+                _ => Cell::from(text.as_str()),
+            })
+            .collect();
 
-        Row::new(cells).height(1)
+        let style = if marked { Style::default().bg(Color::Blue) } else { Style::default() };
+        Row::new(cells).height(1).style(style)
     });
 
-    let table = Table::new(
-        rows,
-        [
-            Constraint::Length(12),
-            Constraint::Length(18),
-            Constraint::Length(32),
-            Constraint::Length(12),
-            Constraint::Length(15),
-            Constraint::Length(22),
-        ],
-    )
-    .header(header)
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::White))
-            .title(" Transactions "),
-    )
-    .highlight_style(
-        Style::default()
-            .bg(Color::DarkGray)
-            .add_modifier(Modifier::BOLD),
-    )
-    .highlight_symbol("→ ");
+    let widths: Vec<Constraint> = app.layout.visible().map(|setting| Constraint::Length(setting.width)).collect();
+
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::White))
+                .title(if app.marked.is_empty() {
+                    " Transactions ".to_string()
+                } else {
+                    format!(" Transactions ({} marked) ", app.marked.len())
+                }),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("→ ");
+
+    let mut window_state = TableState::default().with_selected(selected.map(|i| i - offset));
+    f.render_stateful_widget(table, area, &mut window_state);
+
+    if let Some(selected) = app.column_chooser {
+        render_column_chooser(f, area, &app.layout, selected);
+    }
+    if let Some(profile) = &app.merchant_profile {
+        render_merchant_profile(f, area, profile);
+    }
+}
+
+/// One uncategorized transaction with its top suggestions (or the search)
+fn render_triage(f: &mut Frame, area: Rect, app: &App) {
+    let (Some(triage), Some(tx)) = (app.triage.as_ref(), app.triage_transaction()) else {
+        return;
+    };
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(9), // Transaction
+            Constraint::Min(0),    // Suggestions or search
+        ])
+        .split(area);
+
+    let label = |text: &str| Span::styled(format!("  {:<13}", text), Style::default().fg(Color::Cyan));
+    let card = vec![
+        Line::from(vec![label("Date"), Span::raw(app.locale.transaction_date(tx))]),
+        Line::from(vec![label("Description"), Span::raw(tx.description.clone())]),
+        Line::from(vec![label("Merchant"), Span::styled(tx.merchant.clone(), Style::default().add_modifier(Modifier::BOLD))]),
+        Line::from(vec![label("Amount"), Span::raw(app.locale.money(tx.amount_numeric, &tx.currency))]),
+        Line::from(vec![label("Account"), Span::raw(format!("{} ({})", tx.account_name, tx.bank))]),
+        Line::from(vec![label("Category"), Span::styled(
+            if tx.category.is_empty() { "(none)".to_string() } else { tx.category.clone() },
+            Style::default().fg(Color::Red),
+        )]),
+    ];
+    f.render_widget(
+        Paragraph::new(card).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::White))
+                .title(format!(
+                    " Triage {}/{} · {} categorized ",
+                    triage.position + 1,
+                    triage.queue.len(),
+                    triage.categorized
+                )),
+        ),
+        chunks[0],
+    );
+
+    let mut lines = Vec::new();
+    let title = match &triage.search {
+        Some((query, selected)) => {
+            lines.push(Line::from(vec![
+                Span::styled("  / ", Style::default().fg(Color::Yellow)),
+                Span::raw(query.clone()),
+                Span::styled("█", Style::default().fg(Color::Cyan)),
+            ]));
+            lines.push(Line::from(""));
+            let matches = triage.search_matches();
+            if matches.is_empty() {
+                lines.push(Line::from(Span::styled(
+                    format!("  Enter: new category \"{}\"", query.trim()),
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            for (i, category) in matches.iter().enumerate() {
+                let style = if i == *selected {
+                    Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                lines.push(Line::from(Span::styled(format!("  {}", category), style)));
+            }
+            " Search categories (Enter apply · Esc back) "
+        }
+        None => {
+            if triage.suggestions.is_empty() {
+                lines.push(Line::from(Span::styled(
+                    "  No suggestions for this one (/ to search)",
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            for (i, suggestion) in triage.suggestions.iter().enumerate() {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  [{}] ", i + 1), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                    Span::styled(format!("{:<22}", suggestion.category), Style::default().add_modifier(Modifier::BOLD)),
+                    Span::styled(format!("{:>4.0}%  ", suggestion.score * 100.0), Style::default().fg(Color::Green)),
+                    Span::styled(suggestion.reason.clone(), Style::default().fg(Color::DarkGray)),
+                ]));
+            }
+            " Suggestions (1/2/3 pick · / search · → skip · ← back · Esc exit) "
+        }
+    };
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::White))
+                .title(title),
+        ),
+        chunks[1],
+    );
+}
+
+/// Popup listing every ledger column with its visibility and width
+fn render_column_chooser(f: &mut Frame, area: Rect, layout: &LedgerLayout, selected: usize) {
+    let height = (layout.columns.len() as u16 + 5).min(area.height);
+    let width = 44.min(area.width);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let mut lines: Vec<Line> = layout
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, setting)| {
+            let style = if i == selected {
+                Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            Line::from(Span::styled(
+                format!(
+                    " [{}] {:<14} {:>3}",
+                    if setting.visible { "x" } else { " " },
+                    setting.column.title(),
+                    setting.width
+                ),
+                style,
+            ))
+        })
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        " Space toggle · ←/→ width · Esc save",
+        Style::default().fg(Color::Yellow),
+    )));
+
+    f.render_widget(Clear, popup);
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan))
+                .title(" Columns "),
+        ),
+        popup,
+    );
+}
+
+/// Popup with a merchant's spend profile: totals, monthly trend, categories
+/// and accounts
+fn render_merchant_profile(f: &mut Frame, area: Rect, profile: &MerchantProfile) {
+    let width = 72.min(area.width);
+    let height = 24.min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .title(format!(" {} ", profile.merchant));
+    let inner = block.inner(popup);
+    f.render_widget(Clear, popup);
+    f.render_widget(block, popup);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(4), Constraint::Length(5), Constraint::Min(0)])
+        .split(inner);
+
+    let label = |text: &str| Span::styled(format!("{:<16}", text), Style::default().fg(Color::Cyan));
+    let seen = |date: Option<chrono::NaiveDate>| date.map_or_else(|| "-".to_string(), |d| d.to_string());
+    let totals = vec![
+        Line::from(vec![
+            label("Lifetime spend"),
+            Span::styled(format!("${:.2}", profile.lifetime_spend), Style::default().fg(Color::Red)),
+            Span::raw(format!("  over {} transactions", profile.transactions)),
+        ]),
+        Line::from(vec![label("Average ticket"), Span::raw(format!("${:.2}", profile.average_ticket))]),
+        Line::from(vec![label("First seen"), Span::raw(seen(profile.first_seen))]),
+        Line::from(vec![label("Last seen"), Span::raw(seen(profile.last_seen))]),
+    ];
+    f.render_widget(Paragraph::new(totals), chunks[0]);
+
+    let trend: Vec<u64> = profile.monthly.iter().map(|month| month.spend.max(0.0).round() as u64).collect();
+    let range = match (profile.monthly.first(), profile.monthly.last()) {
+        (Some(first), Some(last)) => format!(" Monthly spend {} → {} ", first.month, last.month),
+        _ => " Monthly spend ".to_string(),
+    };
+    f.render_widget(
+        Sparkline::default()
+            .block(Block::default().borders(Borders::TOP).title(range))
+            .data(&trend)
+            .style(Style::default().fg(Color::Cyan)),
+        chunks[1],
+    );
+
+    let mut usage = vec![Line::from(Span::styled("Categories", Style::default().add_modifier(Modifier::BOLD)))];
+    usage.extend(profile.categories.iter().take(4).map(|category| {
+        Line::from(format!("  {:<28} {:>4}×  ${:>10.2}", category.category, category.transactions, category.spend))
+    }));
+    usage.push(Line::from(Span::styled("Accounts", Style::default().add_modifier(Modifier::BOLD))));
+    usage.extend(profile.accounts.iter().take(4).map(|account| {
+        let name = format!("{} {} {}", account.bank, account.account_name, account.account_number);
+        Line::from(format!("  {:<28} {:>4}×  ${:>10.2}", truncate(&name, 28), account.transactions, account.spend))
+    }));
+    usage.push(Line::from(Span::styled(" Esc close", Style::default().fg(Color::Yellow))));
+    f.render_widget(Paragraph::new(usage), chunks[2]);
+}
 
-    f.render_stateful_widget(table, area, &mut app.state);
+/// Store the ledger layout in the config file, keeping the other settings
+fn save_layout(layout: &LedgerLayout) -> Result<()> {
+    let mut config = AppConfig::load()?;
+    config.ledger_layout = Some(layout.clone());
+    config.save_to(&AppConfig::path())
 }
 
 fn render_status_bar(f: &mut Frame, area: Rect, app: &App) {
+    if let Some(job) = &app.job {
+        render_job_progress(f, area, job);
+        return;
+    }
+
+    if let Some((kind, input)) = &app.bulk_prompt {
+        let prompt = Paragraph::new(Line::from(vec![
+            Span::styled(format!(" {} ({} marked): ", kind.label(), app.marked.len()), Style::default().fg(Color::Yellow)),
+            Span::raw(input.clone()),
+            Span::styled("█", Style::default().fg(Color::Cyan)),
+            Span::styled("  Enter apply · Esc cancel", Style::default().fg(Color::DarkGray)),
+        ]))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(prompt, area);
+        return;
+    }
+
+    if let Some(form) = &app.manual_form {
+        let prompt = Paragraph::new(Line::from(vec![
+            Span::styled(
+                format!(" Cash entry {}/{} · {}: ", form.values.len() + 1, MANUAL_FIELDS.len(), form.label()),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::raw(form.input.clone()),
+            Span::styled("█", Style::default().fg(Color::Cyan)),
+            Span::styled("  Enter next · Esc cancel", Style::default().fg(Color::DarkGray)),
+        ]))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(prompt, area);
+        return;
+    }
+
     let selected = app.state.selected().map(|i| i + 1).unwrap_or(0);
     let total = app.filtered_transactions.len();
 
@@ -549,6 +1992,23 @@ fn render_status_bar(f: &mut Frame, area: Rect, app: &App) {
         status_spans.push(Span::raw(" clear)"));
     }
 
+    if let Some(message) = &app.status_message {
+        status_spans.push(Span::raw(" | "));
+        status_spans.push(Span::styled(message.clone(), Style::default().fg(Color::Magenta)));
+    }
+
+    if app.current_page == Page::Duplicates {
+        status_spans.push(Span::raw(" | "));
+        status_spans.push(Span::styled("←/→", Style::default().fg(Color::Yellow)));
+        status_spans.push(Span::raw(" Keep | "));
+        status_spans.push(Span::styled("m", Style::default().fg(Color::Yellow)));
+        status_spans.push(Span::raw(" Merge | "));
+        status_spans.push(Span::styled("x", Style::default().fg(Color::Yellow)));
+        status_spans.push(Span::raw(" Dismiss | "));
+        status_spans.push(Span::styled("d", Style::default().fg(Color::Yellow)));
+        status_spans.push(Span::raw(" Defer"));
+    }
+
     status_spans.push(Span::raw(" | "));
     status_spans.push(Span::styled("Enter", Style::default().fg(Color::Yellow)));
     status_spans.push(Span::raw(" Details | "));
@@ -558,6 +2018,26 @@ fn render_status_bar(f: &mut Frame, area: Rect, app: &App) {
     status_spans.push(Span::raw(" Nav | "));
     status_spans.push(Span::styled("PgUp/PgDn", Style::default().fg(Color::Yellow)));
     status_spans.push(Span::raw(" Fast | "));
+    status_spans.push(Span::styled("u/r", Style::default().fg(Color::Yellow)));
+    status_spans.push(Span::raw(" Undo/Redo | "));
+    if app.current_page == Page::TransactionLedger {
+        status_spans.push(Span::styled("v", Style::default().fg(Color::Yellow)));
+        status_spans.push(Span::raw(" Columns | "));
+        status_spans.push(Span::styled("Space", Style::default().fg(Color::Yellow)));
+        status_spans.push(Span::raw(" Mark | "));
+        status_spans.push(Span::styled("M", Style::default().fg(Color::Yellow)));
+        status_spans.push(Span::raw(" Merchant | "));
+        status_spans.push(Span::styled("N", Style::default().fg(Color::Yellow)));
+        status_spans.push(Span::raw(" Cash entry | "));
+        if !app.marked.is_empty() {
+            status_spans.push(Span::styled("C/T/R/V", Style::default().fg(Color::Yellow)));
+            status_spans.push(Span::raw(" Category/Tag/Reviewed/Void | "));
+        }
+    }
+    status_spans.push(Span::styled("t", Style::default().fg(Color::Yellow)));
+    status_spans.push(Span::raw(" Triage | "));
+    status_spans.push(Span::styled("i/s", Style::default().fg(Color::Yellow)));
+    status_spans.push(Span::raw(" Import/Scan | "));
     status_spans.push(Span::styled("q", Style::default().fg(Color::Red)));
     status_spans.push(Span::raw(" Quit"));
 
@@ -572,16 +2052,60 @@ fn render_status_bar(f: &mut Frame, area: Rect, app: &App) {
     f.render_widget(status_bar, area);
 }
 
+/// Progress of the running job in place of the status bar
+fn render_job_progress(f: &mut Frame, area: Rect, job: &Job<JobOutput>) {
+    let progress = job.progress();
+    let elapsed = job.elapsed().as_secs();
+    let detail = match progress {
+        Some(p) if p.total > 0 => format!("{} {}/{}", p.message, p.done, p.total),
+        Some(p) => p.message.clone(),
+        None => "Starting".to_string(),
+    };
+    let state = if job.is_cancelling() { "cancelling…" } else { "Esc to cancel" };
+
+    let gauge = Gauge::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow))
+                .title(format!(" {} ", job.name)),
+        )
+        .gauge_style(Style::default().fg(Color::Cyan).bg(Color::DarkGray))
+        .ratio(progress.map_or(0.0, |p| p.ratio()))
+        .label(format!("{} · {}s · {}", detail, elapsed, state));
+    f.render_widget(gauge, area);
+}
+
+/// Worker connection to the same database file
+fn open_worker_connection(path: &Path) -> Result<Connection> {
+    let conn = Connection::open(path)?;
+    // The UI thread may be writing (undo, merges) at the same time
+    conn.busy_timeout(Duration::from_secs(5))?;
+    Ok(conn)
+}
+
 fn truncate(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
+    // Count chars, not bytes: column widths are user-set and names aren't ASCII-only
+    if s.chars().count() <= max_len {
         s.to_string()
+    } else if max_len <= 3 {
+        s.chars().take(max_len).collect()
     } else {
-        format!("{}...", &s[..max_len - 3])
+        format!("{}...", s.chars().take(max_len - 3).collect::<String>())
     }
 }
 
 fn render_bank_statements(f: &mut Frame, area: Rect, app: &mut App) {
-    let bank_summary = app.bank_summary();
+    let bank_summary = app.bank_summary().to_vec();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(6),     // Bank table
+            Constraint::Length(14), // Charts for the selected bank
+        ])
+        .split(area);
+    let area = chunks[0];
 
     let header_cells = ["Bank", "Transactions", "Total Amount", "Avg Amount"]
         .iter()
@@ -639,12 +2163,257 @@ fn render_bank_statements(f: &mut Frame, area: Rect, app: &mut App) {
     .highlight_symbol("→ ");
 
     f.render_stateful_widget(table, area, &mut app.bank_statements_state);
+
+    render_bank_charts(f, chunks[1], app);
+}
+
+/// Expenses by category (bar chart) and monthly trend (sparkline) of the selected bank
+fn render_bank_charts(f: &mut Frame, area: Rect, app: &App) {
+    let chart_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(60), // Expenses by category
+            Constraint::Percentage(40), // Monthly trend
+        ])
+        .split(area);
+
+    let Some(bank) = app.selected_bank() else {
+        let empty = Paragraph::new("  No transactions loaded")
+            .block(Block::default().borders(Borders::ALL).title(" Expenses by Category "));
+        f.render_widget(empty, area);
+        return;
+    };
+
+    // One bar per category, as many as fit in the block
+    let categories = app.bank_category_expenses(&bank);
+    let visible = chart_chunks[0].height.saturating_sub(2) as usize;
+    let bars: Vec<Bar> = categories
+        .iter()
+        .take(visible)
+        .map(|(category, total)| {
+            Bar::default()
+                .value(total.round() as u64)
+                .label(Line::from(truncate(category, 16)))
+                .text_value(format!("{:.0}", total))
+                .style(Style::default().fg(Color::Red))
+        })
+        .collect();
+
+    let bar_chart = BarChart::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::White))
+                .title(format!(" {} - Expenses by Category ", bank)),
+        )
+        .direction(Direction::Horizontal)
+        .bar_width(1)
+        .bar_gap(0)
+        .value_style(Style::default().fg(Color::White))
+        .label_style(Style::default().fg(Color::Yellow))
+        .data(BarGroup::default().bars(&bars));
+    f.render_widget(bar_chart, chart_chunks[0]);
+
+    // Sparkline shows the most recent months that fit
+    let months = app.bank_monthly_expenses(&bank);
+    let width = chart_chunks[1].width.saturating_sub(2) as usize;
+    let recent = &months[months.len().saturating_sub(width)..];
+    let data: Vec<u64> = recent.iter().map(|(_, total)| total.round() as u64).collect();
+    let title = match (recent.first(), recent.last()) {
+        (Some((first, _)), Some((last, _))) if first != last => format!(" Monthly Expenses {} → {} ", first, last),
+        (Some((month, _)), _) => format!(" Monthly Expenses {} ", month),
+        _ => " Monthly Expenses ".to_string(),
+    };
+
+    let sparkline = Sparkline::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::White))
+                .title(title),
+        )
+        .data(&data)
+        .style(Style::default().fg(Color::Cyan));
+    f.render_widget(sparkline, chart_chunks[1]);
+}
+
+/// Reads one compared field from a duplicate candidate
+type FieldValue = fn(&Transaction) -> String;
+
+/// Duplicate clusters (top) and a side-by-side comparison of the selected one
+/// Per-file statistics: how much each feed can be trusted right now
+fn render_sources(f: &mut Frame, area: Rect, app: &mut App) {
+    let header_cells = ["Bank", "Source file", "Txs", "Dates", "Quality", "Dupes", "Unresolved", "Types"]
+        .iter()
+        .map(|h| {
+            Cell::from(*h).style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )
+        });
+
+    let header = Row::new(header_cells)
+        .style(Style::default().bg(Color::DarkGray))
+        .height(1);
+
+    let rows = app.sources.iter().map(|source| {
+        let quality_color = if source.average_quality >= 0.9 {
+            Color::Green
+        } else if source.average_quality >= 0.7 {
+            Color::Yellow
+        } else {
+            Color::Red
+        };
+        let types: Vec<String> = source.by_type.iter().map(|(kind, count)| format!("{} {}", kind, count)).collect();
+
+        Row::new(vec![
+            Cell::from(source.bank.clone()),
+            Cell::from(source.source_file.clone()),
+            Cell::from(format!("{}", source.transaction_count)),
+            Cell::from(source.date_range()),
+            Cell::from(format!("{:.0}%", source.average_quality * 100.0)).style(Style::default().fg(quality_color)),
+            Cell::from(format!("{}", source.duplicates)),
+            Cell::from(format!("{}", source.unresolved_merchants)),
+            Cell::from(types.join(", ")),
+        ])
+        .height(1)
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(18),
+            Constraint::Length(28),
+            Constraint::Length(6),
+            Constraint::Length(25),
+            Constraint::Length(9),
+            Constraint::Length(7),
+            Constraint::Length(11),
+            Constraint::Min(20),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::White))
+            .title(format!(" Sources - {} files ", app.sources.len())),
+    )
+    .highlight_style(
+        Style::default()
+            .bg(Color::DarkGray)
+            .add_modifier(Modifier::BOLD),
+    )
+    .highlight_symbol("→ ");
+
+    f.render_stateful_widget(table, area, &mut app.sources_state);
+}
+
+fn render_duplicates(f: &mut Frame, area: Rect, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(40), // Cluster list
+            Constraint::Percentage(60), // Candidate comparison
+        ])
+        .split(area);
+
+    let header = Row::new(["Candidates", "Date", "Merchant", "Amount", "Confidence"].map(|h| {
+        Cell::from(h).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+    }))
+    .style(Style::default().bg(Color::DarkGray));
+
+    let rows = app.duplicate_clusters.iter().map(|cluster| {
+        let first = &cluster.transactions[0];
+        Row::new(vec![
+            Cell::from(format!("{}", cluster.transactions.len())),
+            Cell::from(first.date.clone()),
+            Cell::from(truncate(&first.merchant, 30)),
+            Cell::from(format!("{:.2}", first.amount_numeric)),
+            Cell::from(format!("{:.0}%", cluster.confidence * 100.0)),
+        ])
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(32),
+            Constraint::Length(12),
+            Constraint::Length(12),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::White))
+            .title(format!(" Duplicate Clusters ({} to review) ", app.duplicate_clusters.len())),
+    )
+    .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
+    .highlight_symbol("→ ");
+    f.render_stateful_widget(table, chunks[0], &mut app.duplicates_state);
+
+    let Some(cluster) = app.selected_cluster() else {
+        let empty = Paragraph::new("  No duplicate clusters to review")
+            .block(Block::default().borders(Borders::ALL).title(" Compare "));
+        f.render_widget(empty, chunks[1]);
+        return;
+    };
+
+    // One column per candidate; values that differ between candidates in yellow
+    let fields: [(&str, FieldValue); 9] = [
+        ("Date", |tx| tx.date.clone()),
+        ("Description", |tx| tx.description.clone()),
+        ("Amount", |tx| format!("{:.2}", tx.amount_numeric)),
+        ("Merchant", |tx| tx.merchant.clone()),
+        ("Category", |tx| tx.category.clone()),
+        ("Bank", |tx| tx.bank.clone()),
+        ("Account", |tx| tx.account_name.clone()),
+        ("Source", |tx| format!("{}:{}", tx.source_file, tx.line_number)),
+        ("UUID", |tx| tx.id.chars().take(8).collect()),
+    ];
+
+    let keep = app.keep_candidate.min(cluster.transactions.len() - 1);
+    let mut header_cells = vec![Cell::from("")];
+    for (i, _) in cluster.transactions.iter().enumerate() {
+        let (label, style) = if i == keep {
+            (format!("#{} KEEP", i + 1), Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))
+        } else {
+            (format!("#{}", i + 1), Style::default().fg(Color::Yellow))
+        };
+        header_cells.push(Cell::from(label).style(style));
+    }
+
+    let rows = fields.iter().map(|(name, value)| {
+        let values: Vec<String> = cluster.transactions.iter().map(value).collect();
+        let differs = values.iter().any(|v| v != &values[0]);
+        let style = if differs { Style::default().fg(Color::Yellow) } else { Style::default() };
+
+        let mut cells = vec![Cell::from(*name).style(Style::default().fg(Color::Cyan))];
+        cells.extend(values.into_iter().map(|v| Cell::from(v).style(style)));
+        Row::new(cells)
+    });
+
+    let column_width = (chunks[1].width.saturating_sub(16) / cluster.transactions.len() as u16).max(12);
+    let mut widths = vec![Constraint::Length(13)];
+    widths.extend(cluster.transactions.iter().map(|_| Constraint::Length(column_width)));
+
+    let comparison = Table::new(rows, widths).header(Row::new(header_cells)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::White))
+            .title(format!(" Compare - {} ", truncate(&cluster.reasons.join("; "), 80))),
+    );
+    f.render_widget(comparison, chunks[1]);
 }
 
 fn render_views(f: &mut Frame, area: Rect, app: &App) {
     let stats = app.stats();
 
-    let content = vec![
+    let mut content = vec![
         Line::from(""),
         Line::from(vec![
             Span::styled(
@@ -796,6 +2565,65 @@ fn render_views(f: &mut Frame, area: Rect, app: &App) {
         ]),
     ];
 
+    if !app.bills.is_empty() {
+        content.push(Line::from(""));
+        content.push(Line::from(Span::styled(
+            "  Bills",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )));
+        for occurrence in &app.bills {
+            let color = match occurrence.status {
+                BillStatus::Matched => Color::Green,
+                BillStatus::Upcoming => Color::Yellow,
+                BillStatus::Missed => Color::Red,
+            };
+            let amount = occurrence.actual_amount.unwrap_or(occurrence.bill.amount);
+            content.push(Line::from(vec![
+                Span::raw(format!("  {}  ", occurrence.due_date.format("%b %d"))),
+                Span::styled(format!("{:<9}", occurrence.status.as_str()), Style::default().fg(color)),
+                Span::raw(format!(
+                    "{:<24} {:>10.2} {}",
+                    truncate(&occurrence.bill.name, 24),
+                    amount,
+                    occurrence.bill.currency
+                )),
+            ]));
+        }
+    }
+
+    if let Some(month) = app.envelopes.as_ref().filter(|month| !month.envelopes.is_empty()) {
+        content.push(Line::from(""));
+        content.push(Line::from(Span::styled(
+            format!("  Envelopes {}  (buffer {:.2} → {:.2})", month.month, month.buffer_opening, month.buffer_closing),
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )));
+        for envelope in &month.envelopes {
+            let color = if envelope.closing < 0.0 {
+                Color::Red
+            } else if envelope.borrowed > 0.0 {
+                Color::Yellow
+            } else {
+                Color::Green
+            };
+            let borrowed = if envelope.borrowed > 0.0 {
+                format!("  borrowed {:.2}", envelope.borrowed)
+            } else {
+                String::new()
+            };
+            content.push(Line::from(vec![
+                Span::raw(format!(
+                    "  {:<24} {:>10.2} + {:>9.2} - {:>9.2} = ",
+                    truncate(&envelope.name, 24),
+                    envelope.opening,
+                    envelope.funded,
+                    envelope.spent
+                )),
+                Span::styled(format!("{:>10.2}", envelope.closing), Style::default().fg(color)),
+                Span::styled(borrowed, Style::default().fg(Color::Yellow)),
+            ]));
+        }
+    }
+
     let paragraph = Paragraph::new(content).block(
         Block::default()
             .borders(Borders::ALL)
@@ -822,11 +2650,11 @@ fn render_detail_panel(f: &mut Frame, area: Rect, app: &App) {
         }
     };
 
-    let content = vec![
+    let mut content = vec![
         Line::from(""),
         Line::from(vec![
             Span::styled("  Date: ", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
-            Span::raw(&tx.date),
+            Span::raw(app.locale.transaction_date(tx)),
         ]),
         Line::from(""),
         Line::from(vec![
@@ -837,11 +2665,9 @@ fn render_detail_panel(f: &mut Frame, area: Rect, app: &App) {
         Line::from(vec![
             Span::styled("  Amount: ", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
             Span::styled(
-                format!("{:.2}", tx.amount_numeric),
+                app.locale.money(tx.amount_numeric, &tx.currency),
                 Style::default().fg(if tx.amount_numeric < 0.0 { Color::Red } else { Color::Green }),
             ),
-            Span::raw(" "),
-            Span::raw(&tx.currency),
         ]),
         Line::from(""),
         Line::from(vec![
@@ -898,6 +2724,13 @@ fn render_detail_panel(f: &mut Frame, area: Rect, app: &App) {
             Span::styled("  Line Number: ", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
             Span::styled(&tx.line_number, Style::default().fg(Color::Green)),
         ]),
+    ];
+
+    if let Some(trace) = app.trace.as_ref().filter(|trace| trace.transaction_id == tx.id) {
+        content.extend(trace_lines(trace));
+    }
+
+    content.extend([
         Line::from(""),
         Line::from("  ─────────────────────────────────────"),
         Line::from(""),
@@ -917,6 +2750,37 @@ fn render_detail_panel(f: &mut Frame, area: Rect, app: &App) {
                 Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
             ),
         ]),
+    ]);
+
+    if let Some(notes) = app.notes.get(&tx.id).filter(|notes| !notes.is_empty()) {
+        content.push(Line::from(""));
+        content.push(Line::from("  ─────────────────────────────────────"));
+        content.push(Line::from(""));
+        content.push(Line::from(vec![Span::styled(
+            format!("  NOTES ({})", notes.len()),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        )]));
+        for (depth, note) in thread_notes(notes) {
+            let indent = "  ".repeat(depth + 1);
+            content.push(Line::from(""));
+            content.push(Line::from(vec![
+                Span::raw(indent.clone()),
+                Span::styled(&note.author, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+                Span::styled(
+                    format!("  {}", note.created_at.format("%Y-%m-%d %H:%M")),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+            content.push(Line::from(vec![
+                Span::raw(indent),
+                Span::raw(wrap_text(&note.text, 35usize.saturating_sub(depth * 2))),
+            ]));
+        }
+    }
+
+    content.extend([
         Line::from(""),
         Line::from(vec![
             Span::styled(
@@ -926,7 +2790,7 @@ fn render_detail_panel(f: &mut Frame, area: Rect, app: &App) {
                     .add_modifier(Modifier::ITALIC),
             ),
         ]),
-    ];
+    ]);
 
     let detail_panel = Paragraph::new(content).block(
         Block::default()
@@ -938,6 +2802,71 @@ fn render_detail_panel(f: &mut Frame, area: Rect, app: &App) {
     f.render_widget(detail_panel, area);
 }
 
+/// Provenance chain lines: raw line, file hash, import session, normalization
+/// steps and the report lines the transaction counts in
+fn trace_lines(trace: &ProvenanceTrace) -> Vec<Line<'static>> {
+    let label = |text: &str| Span::styled(format!("  {}: ", text), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+    let mut lines = Vec::new();
+
+    if let Some(text) = &trace.raw_line.text {
+        lines.push(Line::from(""));
+        lines.push(Line::from(label("Raw Line")));
+        lines.push(Line::from(vec![
+            Span::raw("  "),
+            Span::styled(wrap_text(text, 35), Style::default().fg(Color::DarkGray)),
+        ]));
+    }
+    if let Some(sha256) = &trace.source_file.sha256 {
+        let state = if trace.source_file.archived { "archived" } else { "not archived" };
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            label("File SHA-256"),
+            Span::styled(format!("{}… ({})", &sha256[..sha256.len().min(12)], state), Style::default().fg(Color::Green)),
+        ]));
+    }
+    if let Some(session) = &trace.import_session {
+        let mut text = session.id[..session.id.len().min(8)].to_string();
+        if let Some(actor) = &session.actor {
+            text.push_str(&format!(" by {}", actor));
+        }
+        if let Some(imported_at) = session.imported_at {
+            text.push_str(&format!(" {}", imported_at.format("%Y-%m-%d %H:%M")));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![label("Import"), Span::styled(text, Style::default().fg(Color::Green))]));
+    }
+    if !trace.fields.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(label("Set By")));
+        for (field, origin) in &trace.fields {
+            lines.push(Line::from(vec![
+                Span::styled(format!("    {:<9}", field), Style::default().fg(Color::Cyan)),
+                Span::raw(origin.source.label()),
+                Span::styled(format!("  {}", origin.at.format("%Y-%m-%d %H:%M")), Style::default().fg(Color::DarkGray)),
+            ]));
+        }
+    }
+    if !trace.normalization.steps.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(label("Normalization")));
+        for step in &trace.normalization.steps {
+            lines.push(Line::from(vec![Span::raw("    • "), Span::raw(wrap_text(step, 33))]));
+        }
+    }
+    if trace.versions.len() > 1 {
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![label("Versions"), Span::raw(trace.versions.len().to_string())]));
+    }
+    for aggregate in &trace.aggregates {
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            label(&aggregate.period.label),
+            Span::raw(format!("{} {:.2} of {:.2}", aggregate.line, aggregate.amount, aggregate.total)),
+        ]));
+    }
+    lines
+}
+
 fn wrap_text(text: &str, width: usize) -> String {
     if text.len() <= width {
         text.to_string()
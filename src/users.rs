@@ -0,0 +1,282 @@
+// 👥 Users & Roles - Who is allowed to change the books
+//
+// Problem solved:
+// - Events recorded an `actor` string nobody could verify
+// - A bookkeeper and an owner sharing the server need different permissions
+//
+// Roles are ordered: viewer < editor < admin. Each user authenticates with an
+// API token; only its SHA-256 hash is stored.
+
+use crate::db::{insert_event, Event};
+use crate::event_schema::{UserCreated, UserRoleChanged, UserTokenRotated};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// ============================================================================
+// ROLE
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Read transactions and reports
+    Viewer,
+
+    /// Viewer + corrections, voids, undo/redo, imports
+    Editor,
+
+    /// Editor + user management
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "viewer" => Ok(Role::Viewer),
+            "editor" => Ok(Role::Editor),
+            "admin" => Ok(Role::Admin),
+            other => Err(anyhow!("Unknown role '{}' (viewer, editor, admin)", other)),
+        }
+    }
+
+    /// Check if this role includes the permissions of `required`
+    pub fn allows(&self, required: Role) -> bool {
+        *self >= required
+    }
+}
+
+// ============================================================================
+// USER
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct User {
+    pub id: String,
+
+    /// Recorded as the `actor` of every event this user causes
+    pub username: String,
+
+    pub role: Role,
+
+    pub created_at: DateTime<Utc>,
+}
+
+impl User {
+    /// Check if this user may perform an action needing `required`
+    pub fn can(&self, required: Role) -> bool {
+        self.role.allows(required)
+    }
+
+    /// Stand-in user for installations with no users configured yet
+    pub fn anonymous() -> Self {
+        User {
+            id: String::new(),
+            username: "anonymous".to_string(),
+            role: Role::Admin,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+fn generate_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+// ============================================================================
+// PERSISTENCE
+// ============================================================================
+
+/// Create a user; returns the user and its API token (shown only once)
+pub fn create_user(
+    conn: &Connection,
+    username: &str,
+    role: Role,
+    actor: &str,
+) -> Result<(User, String)> {
+    let username = username.trim();
+    if username.is_empty() || username.contains(char::is_whitespace) {
+        return Err(anyhow!("Invalid username '{}'", username));
+    }
+    if get_user(conn, username)?.is_some() {
+        return Err(anyhow!("User '{}' already exists", username));
+    }
+
+    let user = User {
+        id: uuid::Uuid::new_v4().to_string(),
+        username: username.to_string(),
+        role,
+        created_at: Utc::now(),
+    };
+    let token = generate_token();
+
+    conn.execute(
+        "INSERT INTO users (id, username, role, token_hash, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            user.id,
+            user.username,
+            user.role.as_str(),
+            hash_token(&token),
+            user.created_at.to_rfc3339(),
+        ],
+    )?;
+
+    let payload = UserCreated { username: user.username.clone(), role: user.role };
+    let event = Event::typed("user_created", "user", &user.id, &payload, actor)?;
+    insert_event(conn, &event)?;
+
+    Ok((user, token))
+}
+
+/// Change a user's role
+pub fn set_user_role(conn: &Connection, username: &str, role: Role, actor: &str) -> Result<User> {
+    let mut user = get_user(conn, username)?
+        .ok_or_else(|| anyhow!("User '{}' not found", username))?;
+    let previous = user.role;
+
+    conn.execute(
+        "UPDATE users SET role = ?1 WHERE id = ?2",
+        params![role.as_str(), user.id],
+    )?;
+    user.role = role;
+
+    let payload = UserRoleChanged { username: user.username.clone(), from: previous, to: role };
+    let event = Event::typed("user_role_changed", "user", &user.id, &payload, actor)?;
+    insert_event(conn, &event)?;
+
+    Ok(user)
+}
+
+/// Issue a new API token, invalidating the old one
+pub fn rotate_token(conn: &Connection, username: &str, actor: &str) -> Result<String> {
+    let user = get_user(conn, username)?
+        .ok_or_else(|| anyhow!("User '{}' not found", username))?;
+    let token = generate_token();
+
+    conn.execute(
+        "UPDATE users SET token_hash = ?1 WHERE id = ?2",
+        params![hash_token(&token), user.id],
+    )?;
+
+    let payload = UserTokenRotated { username: user.username.clone() };
+    let event = Event::typed("user_token_rotated", "user", &user.id, &payload, actor)?;
+    insert_event(conn, &event)?;
+
+    Ok(token)
+}
+
+/// Resolve an API token to its user
+pub fn authenticate(conn: &Connection, token: &str) -> Result<Option<User>> {
+    Ok(conn
+        .query_row(
+            "SELECT id, username, role, created_at FROM users WHERE token_hash = ?1",
+            [hash_token(token.trim())],
+            row_to_user,
+        )
+        .optional()?)
+}
+
+pub fn get_user(conn: &Connection, username: &str) -> Result<Option<User>> {
+    Ok(conn
+        .query_row(
+            "SELECT id, username, role, created_at FROM users WHERE username = ?1",
+            [username],
+            row_to_user,
+        )
+        .optional()?)
+}
+
+pub fn list_users(conn: &Connection) -> Result<Vec<User>> {
+    let mut stmt = conn.prepare("SELECT id, username, role, created_at FROM users ORDER BY username")?;
+    let users = stmt
+        .query_map([], row_to_user)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(users)
+}
+
+/// Number of users (0 = authentication not configured yet)
+pub fn user_count(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?)
+}
+
+fn row_to_user(row: &rusqlite::Row) -> rusqlite::Result<User> {
+    let role: String = row.get(2)?;
+    let created_at: String = row.get(3)?;
+
+    Ok(User {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        role: Role::parse(&role).unwrap_or(Role::Viewer),
+        created_at: DateTime::parse_from_rfc3339(&created_at)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::setup_database;
+
+    #[test]
+    fn test_role_ordering() {
+        assert!(Role::Admin.allows(Role::Editor));
+        assert!(Role::Editor.allows(Role::Viewer));
+        assert!(!Role::Viewer.allows(Role::Editor));
+        assert!(!Role::Editor.allows(Role::Admin));
+        assert_eq!(Role::parse("Editor").unwrap(), Role::Editor);
+        assert!(Role::parse("owner").is_err());
+    }
+
+    #[test]
+    fn test_create_and_authenticate_user() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_database(&conn).unwrap();
+        assert_eq!(user_count(&conn).unwrap(), 0);
+
+        let (user, token) = create_user(&conn, "bookkeeper", Role::Editor, "owner").unwrap();
+        assert!(create_user(&conn, "bookkeeper", Role::Viewer, "owner").is_err());
+        assert!(create_user(&conn, "two words", Role::Viewer, "owner").is_err());
+
+        let authed = authenticate(&conn, &token).unwrap().unwrap();
+        assert_eq!(authed, user);
+        assert!(authenticate(&conn, "not-a-token").unwrap().is_none());
+
+        // Token is stored hashed
+        let stored: String = conn
+            .query_row("SELECT token_hash FROM users", [], |row| row.get(0))
+            .unwrap();
+        assert_ne!(stored, token);
+
+        let demoted = set_user_role(&conn, "bookkeeper", Role::Viewer, "owner").unwrap();
+        assert!(!demoted.can(Role::Editor));
+
+        let new_token = rotate_token(&conn, "bookkeeper", "owner").unwrap();
+        assert!(authenticate(&conn, &token).unwrap().is_none());
+        assert_eq!(authenticate(&conn, &new_token).unwrap().unwrap().role, Role::Viewer);
+    }
+}